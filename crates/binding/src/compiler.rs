@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use mako::compiler::{Args, Compiler, Context};
+use mako::config::Config;
+use mako::diagnostics::{Diagnostic, Severity};
+use mako::plugin::{Plugin, PluginGenerateEndParams};
+use mako::utils::thread_pool;
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::{JsFunction, JsObject, Status};
+use napi_derive::napi;
+use serde_json::{json, Value};
+
+use crate::js_hook::JsHooks;
+use crate::js_plugins;
+
+const EVENTS: [&str; 2] = ["diagnostic", "progress"];
+
+#[napi(object)]
+pub struct CompilerParams {
+    pub root: String,
+    #[napi(ts_type = r#"BuildParams["config"]"#)]
+    pub config: Value,
+    pub plugins: Option<Vec<JsHooks>>,
+    /// Return the sources of the assets in the build results, the sources
+    /// are kept in memory until the results are released, default true.
+    pub return_sources: Option<bool>,
+}
+
+#[napi(object)]
+pub struct BuildAsset {
+    pub name: String,
+    pub size: f64,
+    pub source: Option<Buffer>,
+}
+
+#[napi(object)]
+pub struct BuildDiagnostic {
+    pub code: String,
+    #[napi(ts_type = r#""warning" | "error" | "suppressed""#)]
+    pub severity: String,
+    pub message: String,
+    pub paths: Vec<String>,
+}
+
+impl From<&Diagnostic> for BuildDiagnostic {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        Self {
+            code: diagnostic.code.to_string(),
+            severity: match diagnostic.severity {
+                Severity::Warning => "warning".to_string(),
+                Severity::Error => "error".to_string(),
+                Severity::Suppressed => "suppressed".to_string(),
+            },
+            message: diagnostic.message.clone(),
+            paths: diagnostic
+                .paths
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct BuildResult {
+    /// false if the rebuild found no changes, the assets are of the last
+    /// build then
+    pub updated: bool,
+    pub assets: Vec<BuildAsset>,
+    pub diagnostics: Vec<BuildDiagnostic>,
+}
+
+type Listener = ThreadsafeFunction<Value, ErrorStrategy::Fatal>;
+
+/// The listeners of the events, which are called without waiting for them,
+/// so the build is never blocked by the js thread.
+#[derive(Clone, Default)]
+struct Events {
+    listeners: Arc<Mutex<HashMap<String, Vec<Listener>>>>,
+}
+
+impl Events {
+    fn on(&self, event: String, listener: Listener) {
+        let mut listeners = self.listeners.lock().unwrap();
+        listeners.entry(event).or_default().push(listener);
+    }
+
+    fn emit(&self, event: &str, payload: Value) {
+        let listeners = self.listeners.lock().unwrap();
+        for listener in listeners.get(event).into_iter().flatten() {
+            listener.call(payload.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    }
+
+    fn emit_diagnostics(&self, diagnostics: &[BuildDiagnostic]) {
+        for diagnostic in diagnostics {
+            self.emit(
+                "diagnostic",
+                json!({
+                    "code": diagnostic.code,
+                    "severity": diagnostic.severity,
+                    "message": diagnostic.message,
+                    "paths": diagnostic.paths,
+                }),
+            );
+        }
+    }
+}
+
+struct ProgressEvents {
+    events: Events,
+}
+
+impl Plugin for ProgressEvents {
+    fn name(&self) -> &str {
+        "progress_events"
+    }
+
+    fn build_start(&self, _context: &Arc<Context>) -> anyhow::Result<()> {
+        self.events.emit("progress", json!({ "stage": "build" }));
+        Ok(())
+    }
+
+    fn generate_begin(&self, context: &Arc<Context>) -> anyhow::Result<()> {
+        let modules = context.module_graph.read().unwrap().modules().len();
+        self.events.emit(
+            "progress",
+            json!({ "stage": "generate", "modules": modules }),
+        );
+        Ok(())
+    }
+
+    fn generate_end(
+        &self,
+        params: &PluginGenerateEndParams,
+        _context: &Arc<Context>,
+    ) -> anyhow::Result<()> {
+        self.events
+            .emit("progress", json!({ "stage": "done", "time": params.time }));
+        Ok(())
+    }
+}
+
+/// A compiler kept alive between the builds, e.g. by the build tools which
+/// rebuild the changes themselves, the module graph and the caches are
+/// reused by the rebuilds. It compiles in the watch mode without the dev
+/// server, so the module graph is not tree shaken.
+#[napi(js_name = "Compiler")]
+pub struct JsCompiler {
+    compiler: Arc<Compiler>,
+    events: Events,
+    return_sources: bool,
+    // whether it's built, which is locked during the builds
+    built: Arc<Mutex<bool>>,
+}
+
+#[napi]
+impl JsCompiler {
+    #[napi(constructor)]
+    pub fn new(env: Env, params: CompilerParams) -> napi::Result<Self> {
+        crate::init_logger_once();
+
+        if !params.config.is_object() {
+            return Err(invalid_config("config should be an object"));
+        }
+        let root = PathBuf::from(&params.root);
+        if !root.is_dir() {
+            return Err(napi::Error::new(
+                Status::InvalidArg,
+                format!("root {} is not a directory", params.root),
+            ));
+        }
+        let default_config = serde_json::to_string(&params.config).unwrap();
+        let config = Config::new(&root, Some(&default_config), None)
+            .map_err(|e| invalid_config(&format!("{:#}", e)))?;
+
+        let events = Events::default();
+        let mut plugins = js_plugins(env, params.plugins.as_deref().unwrap_or_default());
+        plugins.push(Arc::new(ProgressEvents {
+            events: events.clone(),
+        }));
+        let compiler = Compiler::new(config, root, Args { watch: true }, Some(plugins))
+            .map_err(|e| napi::Error::new(Status::GenericFailure, format!("{:#}", e)))?;
+        Ok(Self {
+            compiler: Arc::new(compiler),
+            events,
+            return_sources: params.return_sources.unwrap_or(true),
+            built: Default::default(),
+        })
+    }
+
+    /// Listen to the `diagnostic` and `progress` events.
+    #[napi(ts_args_type = r#"event: "diagnostic" | "progress", listener: (payload: any) => void"#)]
+    pub fn on(&self, env: Env, event: String, listener: JsFunction) -> napi::Result<()> {
+        if !EVENTS.contains(&event.as_str()) {
+            return Err(napi::Error::new(
+                Status::InvalidArg,
+                format!(
+                    "unknown event {}, it should be one of {}",
+                    event,
+                    EVENTS.join(", ")
+                ),
+            ));
+        }
+        let mut listener: Listener = listener
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<Value>| {
+                Ok(vec![ctx.env.to_js_value(&ctx.value)?])
+            })?;
+        // the listeners don't keep the process alive
+        listener.unref(&env)?;
+        self.events.on(event, listener);
+        Ok(())
+    }
+
+    #[napi(ts_return_type = "Promise<BuildResult>")]
+    pub fn build(&self, env: Env) -> napi::Result<JsObject> {
+        let (deferred, promise) = env.create_deferred()?;
+        let compiler = self.compiler.clone();
+        let events = self.events.clone();
+        let return_sources = self.return_sources;
+        let built = self.built.clone();
+        thread_pool::spawn(move || {
+            let mut built = built.lock().unwrap();
+            if *built {
+                deferred.reject(napi::Error::new(
+                    Status::GenericFailure,
+                    "the compiler is built, use rebuild() for the changed files",
+                ));
+                return;
+            }
+            *built = true;
+            let ret = compiler.compile();
+            match build_result(&compiler, &events, ret.map(|_| true), return_sources) {
+                Ok(result) => deferred.resolve(move |_| Ok(result)),
+                Err(e) => deferred.reject(e),
+            }
+        });
+        Ok(promise)
+    }
+
+    /// Rebuild the changed, added or removed files.
+    #[napi(ts_return_type = "Promise<BuildResult>")]
+    pub fn rebuild(&self, env: Env, changed_files: Vec<String>) -> napi::Result<JsObject> {
+        let (deferred, promise) = env.create_deferred()?;
+        let compiler = self.compiler.clone();
+        let events = self.events.clone();
+        let return_sources = self.return_sources;
+        let built = self.built.clone();
+        thread_pool::spawn(move || {
+            let built = built.lock().unwrap();
+            if !*built {
+                deferred.reject(napi::Error::new(
+                    Status::GenericFailure,
+                    "the compiler is not built, call build() first",
+                ));
+                return;
+            }
+            events.emit("progress", json!({ "stage": "build" }));
+            let paths = changed_files.into_iter().map(PathBuf::from).collect();
+            let ret = compiler.rebuild(paths);
+            match build_result(&compiler, &events, ret, return_sources) {
+                Ok(result) => deferred.resolve(move |_| Ok(result)),
+                Err(e) => deferred.reject(e),
+            }
+        });
+        Ok(promise)
+    }
+}
+
+fn invalid_config(message: &str) -> napi::Error {
+    napi::Error::new(Status::InvalidArg, format!("Invalid config: {}", message))
+}
+
+fn build_result(
+    compiler: &Compiler,
+    events: &Events,
+    ret: anyhow::Result<bool>,
+    return_sources: bool,
+) -> napi::Result<BuildResult> {
+    let context = &compiler.context;
+    let diagnostics = context
+        .diagnostics
+        .get_reported()
+        .iter()
+        .map(BuildDiagnostic::from)
+        .collect::<Vec<_>>();
+    events.emit_diagnostics(&diagnostics);
+    let updated = ret.map_err(|e| napi::Error::new(Status::GenericFailure, format!("{}", e)))?;
+
+    let mut assets = context.stats_info.get_assets();
+    assets.dedup_by(|a, b| a.hashname == b.hashname);
+    let output_path = &context.config.output.path;
+    let assets = assets
+        .into_iter()
+        .map(|asset| {
+            let source = return_sources
+                .then(|| {
+                    context.get_static_content(&asset.hashname).or_else(|| {
+                        context
+                            .output_fs
+                            .read(&output_path.join(&asset.hashname))
+                            .ok()
+                    })
+                })
+                .flatten()
+                .map(Buffer::from);
+            BuildAsset {
+                name: asset.hashname,
+                size: asset.size as f64,
+                source,
+            }
+        })
+        .collect();
+    Ok(BuildResult {
+        updated,
+        assets,
+        diagnostics,
+    })
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use napi::bindgen_prelude::*;
 use napi::NapiRaw;
 use napi_derive::napi;
@@ -16,6 +18,10 @@ pub struct JsHooks {
     pub load: Option<JsFunction>,
     #[napi(ts_type = "(filePath: string) => Promise<bool> | bool;")]
     pub load_include: Option<JsFunction>,
+    #[napi(
+        ts_type = "(filePath: string) => Promise<{ content: string, type: 'css'|'js', subModules: Record<string, { content: string, type: 'css'|'js' }> } | void> | void;"
+    )]
+    pub load_sub_modules: Option<JsFunction>,
     #[napi(ts_type = r#"(data: {
     isFirstCompile: boolean;
     time: number;
@@ -33,7 +39,13 @@ pub struct JsHooks {
       }[];
       modules: Record<
         string,
-        { id: string; dependents: string[]; dependencies: string[] }
+        {
+          id: string;
+          dependents: string[];
+          dependencies: string[];
+          directives: string[];
+          condition?: string;
+        }
       >;
       chunks: {
         type: string;
@@ -50,9 +62,26 @@ pub struct JsHooks {
           request: string;
         }[];
       }[];
-      entrypoints: Record<string, { name: string; chunks: string[] }>;
+      entrypoints: Record<
+        string,
+        {
+          name: string;
+          chunks: string[];
+          assets: { name: string; size: number }[];
+        }
+      >;
       rscClientComponents: { path; string; moduleId: string }[];
       rscCSSModules: { path; string; moduleId: string; modules: boolean }[];
+      packages: {
+        name: string;
+        version: string | null;
+        sourceSize: number;
+        transformedSize: number;
+        minifiedSize: number;
+        treeShakenSize: number;
+        keptModules: number;
+        droppedModules: number;
+      }[];
       startTime: number;
       endTime: number;
     };
@@ -60,6 +89,10 @@ pub struct JsHooks {
     pub generate_end: Option<JsFunction>,
     #[napi(ts_type = "() => Promise<void>;")]
     pub write_bundle: Option<JsFunction>,
+    #[napi(
+        ts_type = "(sourceMaps: { assetPath: string; sourceMapPath: string; debugId: string | null }[]) => Promise<void>;"
+    )]
+    pub after_emit: Option<JsFunction>,
     #[napi(
         ts_type = "(id: string, change: { event: 'create' | 'delete' | 'update' }) => Promise<void> | void;"
     )]
@@ -80,23 +113,33 @@ pub struct JsHooks {
     pub transform: Option<JsFunction>,
     #[napi(ts_type = "(filePath: string) => Promise<bool> | bool;")]
     pub transform_include: Option<JsFunction>,
+    #[napi(
+        ts_type = "(filePath: string, directives: string[]) => Promise<{ content: string, type: 'css' | 'js' } | void> | void;"
+    )]
+    pub replace_by_directive: Option<JsFunction>,
 }
 
 type ResolveIdFuncParams = (PluginContext, String, String, ResolveIdParams);
+type ReplaceByDirectiveFuncParams = (PluginContext, String, Vec<String>);
 
 pub struct TsFnHooks {
     pub build_start: Option<ThreadsafeFunction<PluginContext, ()>>,
     pub build_end: Option<ThreadsafeFunction<PluginContext, ()>>,
     pub write_bundle: Option<ThreadsafeFunction<PluginContext, ()>>,
+    pub after_emit: Option<ThreadsafeFunction<(PluginContext, Value), ()>>,
     pub generate_end: Option<ThreadsafeFunction<(PluginContext, Value), ()>>,
     pub load: Option<ThreadsafeFunction<(PluginContext, String), Option<LoadResult>>>,
     pub load_include: Option<ThreadsafeFunction<(PluginContext, String), Option<bool>>>,
+    pub load_sub_modules:
+        Option<ThreadsafeFunction<(PluginContext, String), Option<LoadSubModulesResult>>>,
     pub watch_changes: Option<ThreadsafeFunction<(PluginContext, String, WatchChangesParams), ()>>,
     pub resolve_id: Option<ThreadsafeFunction<ResolveIdFuncParams, Option<ResolveIdResult>>>,
     pub _on_generate_file: Option<ThreadsafeFunction<(PluginContext, WriteFile), ()>>,
     pub transform:
         Option<ThreadsafeFunction<(PluginContext, String, String), Option<TransformResult>>>,
     pub transform_include: Option<ThreadsafeFunction<(PluginContext, String), Option<bool>>>,
+    pub replace_by_directive:
+        Option<ThreadsafeFunction<ReplaceByDirectiveFuncParams, Option<LoadResult>>>,
 }
 
 impl TsFnHooks {
@@ -111,6 +154,9 @@ impl TsFnHooks {
             write_bundle: hooks.write_bundle.as_ref().map(|hook| unsafe {
                 ThreadsafeFunction::from_napi_value(env.raw(), hook.raw()).unwrap()
             }),
+            after_emit: hooks.after_emit.as_ref().map(|hook| unsafe {
+                ThreadsafeFunction::from_napi_value(env.raw(), hook.raw()).unwrap()
+            }),
             generate_end: hooks.generate_end.as_ref().map(|hook| unsafe {
                 ThreadsafeFunction::from_napi_value(env.raw(), hook.raw()).unwrap()
             }),
@@ -123,6 +169,9 @@ impl TsFnHooks {
             load_include: hooks.load_include.as_ref().map(|hook| unsafe {
                 ThreadsafeFunction::from_napi_value(env.raw(), hook.raw()).unwrap()
             }),
+            load_sub_modules: hooks.load_sub_modules.as_ref().map(|hook| unsafe {
+                ThreadsafeFunction::from_napi_value(env.raw(), hook.raw()).unwrap()
+            }),
             resolve_id: hooks.resolve_id.as_ref().map(|hook| unsafe {
                 ThreadsafeFunction::from_napi_value(env.raw(), hook.raw()).unwrap()
             }),
@@ -135,6 +184,9 @@ impl TsFnHooks {
             transform_include: hooks.transform_include.as_ref().map(|hook| unsafe {
                 ThreadsafeFunction::from_napi_value(env.raw(), hook.raw()).unwrap()
             }),
+            replace_by_directive: hooks.replace_by_directive.as_ref().map(|hook| unsafe {
+                ThreadsafeFunction::from_napi_value(env.raw(), hook.raw()).unwrap()
+            }),
         }
     }
 }
@@ -153,6 +205,15 @@ pub struct LoadResult {
     pub content_type: String,
 }
 
+#[napi(object, use_nullable = true)]
+pub struct LoadSubModulesResult {
+    pub content: String,
+    #[napi(js_name = "type")]
+    pub content_type: String,
+    // query -> content of the sub-module
+    pub sub_modules: HashMap<String, LoadResult>,
+}
+
 #[napi(object, use_nullable = true)]
 pub struct WatchChangesParams {
     pub event: String,
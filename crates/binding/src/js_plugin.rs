@@ -4,13 +4,16 @@ use std::sync::Arc;
 use anyhow::{anyhow, Result};
 use mako::ast::file::{Content, JsContent};
 use mako::compiler::Context;
-use mako::plugin::{Plugin, PluginGenerateEndParams, PluginLoadParam, PluginResolveIdParams};
+use mako::plugin::{
+    Plugin, PluginEmittedSourceMap, PluginGenerateEndParams, PluginLoadParam,
+    PluginReplaceByDirectiveParam, PluginResolveIdParams, PluginSubModules,
+};
 use mako::resolve::{ExternalResource, Resolution, ResolvedResource, ResolverResource};
 use napi_derive::napi;
 
 use crate::js_hook::{
-    LoadResult, ResolveIdParams, ResolveIdResult, TransformResult, TsFnHooks, WatchChangesParams,
-    WriteFile,
+    LoadResult, LoadSubModulesResult, ResolveIdParams, ResolveIdResult, TransformResult, TsFnHooks,
+    WatchChangesParams, WriteFile,
 };
 
 fn content_from_result(result: TransformResult) -> Result<Content> {
@@ -44,10 +47,11 @@ impl PluginContext {
         println!("ERROR: {}", msg)
     }
     #[napi]
-    pub fn emit_file(&self, origin_path: String, output_path: String) {
-        let mut assets_info = self.context.assets_info.lock().unwrap();
-        assets_info.insert(origin_path, output_path);
-        drop(assets_info);
+    pub fn emit_file(&self, origin_path: String, output_path: String) -> napi::Result<()> {
+        self.context
+            .emit_assets(origin_path, output_path, false)
+            .map_err(|e| napi::Error::new(napi::Status::GenericFailure, format!("{:#}", e)))?;
+        Ok(())
     }
 }
 
@@ -104,6 +108,77 @@ impl Plugin for JsPlugin {
         Ok(None)
     }
 
+    fn load_sub_modules(
+        &self,
+        param: &PluginLoadParam,
+        context: &Arc<Context>,
+    ) -> Result<Option<PluginSubModules>> {
+        if let Some(hook) = &self.hooks.load_sub_modules {
+            if self.hooks.load_include.is_some()
+                && self.hooks.load_include.as_ref().unwrap().call((
+                    PluginContext {
+                        context: context.clone(),
+                    },
+                    param.file.path.to_string_lossy().to_string(),
+                ))? == Some(false)
+            {
+                return Ok(None);
+            }
+            let x: Option<LoadSubModulesResult> = hook.call((
+                PluginContext {
+                    context: context.clone(),
+                },
+                param.file.path.to_string_lossy().to_string(),
+            ))?;
+            if let Some(x) = x {
+                let mut sub_modules = x
+                    .sub_modules
+                    .into_iter()
+                    .map(|(query, result)| {
+                        let content = content_from_result(TransformResult {
+                            content: result.content,
+                            content_type: result.content_type,
+                        })?;
+                        Ok((query, content))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                sub_modules.sort_by(|(a, _), (b, _)| a.cmp(b));
+                return Ok(Some(PluginSubModules {
+                    content: content_from_result(TransformResult {
+                        content: x.content,
+                        content_type: x.content_type,
+                    })?,
+                    sub_modules,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    fn replace_by_directive(
+        &self,
+        param: &PluginReplaceByDirectiveParam,
+        context: &Arc<Context>,
+    ) -> Result<Option<Content>> {
+        if let Some(hook) = &self.hooks.replace_by_directive {
+            let x: Option<LoadResult> = hook.call((
+                PluginContext {
+                    context: context.clone(),
+                },
+                param.file.path.to_string_lossy().to_string(),
+                param.directives.to_vec(),
+            ))?;
+            if let Some(x) = x {
+                return content_from_result(TransformResult {
+                    content: x.content,
+                    content_type: x.content_type,
+                })
+                .map(Some);
+            }
+        }
+        Ok(None)
+    }
+
     fn resolve_id(
         &self,
         source: &str,
@@ -136,6 +211,8 @@ impl Plugin for JsPlugin {
                         query: None,
                         fragment: None,
                         package_json: None,
+                        condition: None,
+                        root: None,
                     },
                 ))));
             }
@@ -186,6 +263,22 @@ impl Plugin for JsPlugin {
         Ok(())
     }
 
+    fn after_emit(
+        &self,
+        source_maps: &[PluginEmittedSourceMap],
+        context: &Arc<Context>,
+    ) -> Result<()> {
+        if let Some(hook) = &self.hooks.after_emit {
+            hook.call((
+                PluginContext {
+                    context: context.clone(),
+                },
+                serde_json::to_value(source_maps)?,
+            ))?
+        }
+        Ok(())
+    }
+
     fn before_write_fs(
         &self,
         path: &std::path::Path,
@@ -9,6 +9,7 @@ use js_plugin::JsPlugin;
 use mako::compiler::{Args, Compiler};
 use mako::config::Config;
 use mako::dev::DevServer;
+use mako::multi_compiler::MultiCompiler;
 use mako::plugin::Plugin;
 use mako::utils::logger::init_logger;
 use mako::utils::thread_pool;
@@ -16,43 +17,126 @@ use napi::bindgen_prelude::*;
 use napi::{JsObject, Status};
 use napi_derive::napi;
 
+mod compiler;
 mod js_hook;
 mod js_plugin;
 mod threadsafe_function;
 
 static LOG_INIT: Once = Once::new();
 
+fn init_logger_once() {
+    LOG_INIT.call_once(|| {
+        init_logger();
+    });
+}
+
+/// The plugins of the js hooks, sorted by enforce: pre / post.
+fn js_plugins(env: Env, hooks: &[JsHooks]) -> Vec<Arc<dyn Plugin>> {
+    let mut plugins: Vec<Arc<dyn Plugin>> = vec![];
+    for hooks in hooks.iter() {
+        let tsfn_hooks = TsFnHooks::new(env, hooks);
+        let plugin = JsPlugin {
+            name: hooks.name.clone(),
+            hooks: tsfn_hooks,
+            enforce: hooks.enforce.clone(),
+        };
+        plugins.push(Arc::new(plugin));
+    }
+
+    plugins.sort_by_key(|plugin| match plugin.enforce() {
+        Some("pre") => 0,
+        Some("post") => 2,
+        _ => 1,
+    });
+    plugins
+}
+
 #[napi(object)]
 pub struct BuildParams {
     pub root: String,
 
     #[napi(ts_type = r#"
 {
-    entry?: Record<string, string>;
+    entry?: Record<string, string | { import: string; dependOn?: string | string[]; target?: "web" | "webworker"; precacheManifest?: boolean }>;
+    entryPrelude?: Array<string | { inline: string }>;
     output?: {
         path: string;
         mode: "bundle" | "bundless" ;
         esVersion?: string;
         meta?: boolean;
+        chunkLoadingGlobal?: string;
+        chunkLoadingTimeout?: number;
+        chunkLoading?: "jsonp" | "import" | "fetch-eval";
+        uniqueName?: string;
         preserveModules?: boolean;
         preserveModulesRoot?: string;
         skipWrite?: boolean;
+        runtimeTarget?: false | "es5" | "es2015" | "es2020";
+        precompress?:
+            | false
+            | Array<"gzip" | "brotli">
+            | {
+                  algorithms: Array<"gzip" | "brotli">;
+                  gzipLevel?: number;
+                  brotliQuality?: number;
+                  threshold?: number;
+              };
+        debugIds?: boolean;
+        pushableChunks?: boolean;
+        relativeUrlBase?: "script" | "document";
+        hashFunction?: "md5" | "xxhash" | "sha256";
+        hashLength?: number;
+        assetFilename?: string;
+        unloadChunkCss?: boolean;
     };
     resolve?: {
        alias?: Array<[string, string]>;
        extensions?: string[];
+       tsconfig?: string;
+       conditionNames?: string[];
+       roots?: string[];
     };
     manifest?: false | {
         fileName: string;
         basePath: string;
+        precompressed?: boolean;
+        publicPath?: boolean;
+    };
+    buildManifest?: false | {
+        fileName?: string;
+        previous?: string;
+    };
+    cacheRules?: false | {
+        fileName?: string;
+        headers?: boolean;
+        overrides?: {
+            pattern: string;
+            rule: "immutable" | "revalidate";
+        }[];
     };
-    mode?: "development" | "production";
+    loadableManifest?: false | { fileName?: string };
+    maxInFlightChunkBytes?: number;
+    maxOpenFiles?: number;
+    mode?: "development" | "production" | "none";
+    env?: Record<string, Record<string, any>>;
+    envName?: string;
     define?: Record<string, string>;
+    envObject?: false | "empty" | "runtime";
+    graph?: false | { format: "dot" | "json"; collapseNodeModules?: boolean };
     devtool?: false | "source-map" | "inline-source-map";
+    devtoolsTiming?: boolean;
+    sourceMap?: {
+        excludeVendorSources?: boolean;
+        indexMap?: boolean;
+        vendor?: "none" | "cheap" | "full";
+        vendorGlobs?: string[];
+    };
+    sourceMapIgnoreList?: boolean | string[];
     externals?: Record<
         string,
         string | {
             root: string;
+            type?: "commonjs" | "module";
             script?: string;
             subpath?: {
                 exclude?: string[];
@@ -95,9 +179,13 @@ pub struct BuildParams {
     providers?: Record<string, string[]>;
     publicPath?: string;
     inlineLimit?: number;
+    inlineRuntime?: boolean;
+    inlineChunks?: string[];
     inlineExcludesExtensions?: string[];
+    imageOptimization?: false | { quality?: number };
     targets?: Record<string, number>;
     platform?: "node" | "browser";
+    nativeAddons?: "emit" | "external";
     hmr?: false | {};
     devServer?: false | { host?: string; port?: number };
     px2rem?: false | {
@@ -111,20 +199,49 @@ pub struct BuildParams {
     };
     stats?: false | {
         modules?: boolean;
+        packages?: boolean;
+        mangleExports?: boolean;
+        timestamps?: boolean;
     };
+    stub?: Record<string, { exports?: string[]; value?: "noop" | string | number | boolean | null | object }>;
     hash?: boolean;
+    asyncGlobalSideEffects?: false | { allowProperties?: string[]; allowModules?: string[] };
     autoCSSModules?: boolean;
     ignoreCSSParserErrors?: boolean;
+    remoteCSS?: "preserve" | "warn" | "download";
     dynamicImportToRequire?: boolean;
     umd?: false | string | { name: string, export?: string[] };
     cjs?: boolean;
     writeToDisk?: boolean;
+    transform?: { include?: string[]; exclude?: string[] };
     transformImport?: { libraryName: string; libraryDirectory?: string; style?: boolean | string }[];
+    transformImports?: Record<string, { transform: string; style?: string; skip?: string[] }>;
     clean?: boolean;
     nodePolyfill?: boolean;
     ignores?: string[];
-    moduleIdStrategy?: "hashed" | "named";
-    minify?: boolean;
+    ignore?: string[];
+    moduleIdStrategy?: "hashed" | "named" | "deterministic";
+    namedExportsCheck?: false | "error" | "warn";
+    defaultImportCheck?: false | "error" | "warn";
+    fullyDynamicImport?: false | "error" | "warn";
+    fullyDynamicRequire?: false | "error" | "warn";
+    moduleDirectives?: string[];
+    moduleRules?: {
+      test: string;
+      use: { jsx?: boolean; loader?: "raw"; defines?: Record<string, string> };
+    }[];
+    moduleTypes?: { include: string; type: "esm" | "commonjs" }[];
+    jsonExportsCheck?: false | "error" | "warn";
+    peerDependencyCheck?: false | "error" | "warn";
+    duplicatedModules?: false | { maxBytes: number };
+    parseFallback?: false | { steps: Array<"jsx" | "proposals" | "flow"> };
+    typeDeclaration?: boolean | { fileName?: string };
+    minify?:
+      | boolean
+      | {
+          keepNames?: boolean | ("ClassNames" | "FunctionNames")[];
+        };
+    licenseComments?: false | "extract" | "inline";
     _minifish?: false | {
         mapping: Record<string, string>;
         metaPath?: string;
@@ -138,15 +255,29 @@ pub struct BuildParams {
     };
     optimization?: false | {
         skipModules?: boolean;
+        sideEffects?: boolean;
         concatenateModules?: boolean;
+        concatenateCss?: boolean;
+        pureFunctions?: string[];
+        mangleExports?: boolean;
     };
     react?: {
         runtime?: "automatic" | "classic";
         pragma?: string;
         importSource?: string;
         pragmaFrag?: string;
+        removePropTypes?: boolean | {
+            include?: string[];
+            defaultProps?: boolean;
+        };
     };
     emitAssets?: boolean;
+    cssLowering?: {
+      isWhere?: boolean;
+      logicalProperties?: boolean;
+      direction?: "ltr" | "rtl";
+    };
+    cssModules?: { declarations?: boolean };
     cssModulesExportOnlyLocales?: boolean;
     inlineCSS?: false | {};
     rscServer?: false | {
@@ -163,8 +294,47 @@ pub struct BuildParams {
     watch?: {
         ignoredPaths?: string[];
         _nodeModulesRegexes?: string[];
+        debounce?: number;
+        settle?: number;
     };
     caseSensitiveCheck?: boolean;
+    dualBuild?: false | {
+        targets?: Record<string, number>;
+        outputDir?: string;
+        template?: string;
+    };
+    criticalCss?: false | {
+        entries: Record<string, { selectors?: string[]; modules?: string[] }>;
+        template?: string;
+    };
+    cspReport?: false | {
+        fileName?: string;
+        nonce?: string;
+    };
+    extractCss?: false | {
+        groups?: { name: string; test?: string[] }[];
+    };
+    importMap?: false | {
+        url?: string;
+        inject?: boolean;
+    };
+    polyfill?: false | {
+        mode: "usage" | "entry";
+        provider?: "core-js";
+    };
+    sideEffectOnlyImports?: boolean | {
+        include?: string[];
+    };
+    preBundle?: boolean | {
+        exclude?: string[];
+    };
+    diagnostics?: {
+        errorOn?: ("MAKO_DUPLICATE_PACKAGE" | "MAKO_CASE_MISMATCH" | "MAKO_CIRCULAR" | "MAKO_EVAL_BAILOUT" | "MAKO_DUPLICATED_MODULES" | "MAKO_PARSE_FALLBACK" | "MAKO_DYNAMIC_IMPORT" | "MAKO_DYNAMIC_REQUIRE" | "MAKO_CHUNK_NAME_COLLISION" | "MAKO_REMOTE_CSS" | "MAKO_ASYNC_GLOBAL_SIDE_EFFECT")[];
+        ignore?: {
+            code: "MAKO_DUPLICATE_PACKAGE" | "MAKO_CASE_MISMATCH" | "MAKO_CIRCULAR" | "MAKO_EVAL_BAILOUT" | "MAKO_DUPLICATED_MODULES" | "MAKO_PARSE_FALLBACK" | "MAKO_DYNAMIC_IMPORT" | "MAKO_DYNAMIC_REQUIRE" | "MAKO_CHUNK_NAME_COLLISION" | "MAKO_REMOTE_CSS" | "MAKO_ASYNC_GLOBAL_SIDE_EFFECT";
+            pathPattern?: string;
+        }[];
+    };
 }"#)]
     pub config: serde_json::Value,
     pub plugins: Vec<JsHooks>,
@@ -173,27 +343,9 @@ pub struct BuildParams {
 
 #[napi(ts_return_type = r#"Promise<void>"#)]
 pub fn build(env: Env, build_params: BuildParams) -> napi::Result<JsObject> {
-    LOG_INIT.call_once(|| {
-        init_logger();
-    });
+    init_logger_once();
 
-    let mut plugins: Vec<Arc<dyn Plugin>> = vec![];
-    for hooks in build_params.plugins.iter() {
-        let tsfn_hooks = TsFnHooks::new(env, hooks);
-        let plugin = JsPlugin {
-            name: hooks.name.clone(),
-            hooks: tsfn_hooks,
-            enforce: hooks.enforce.clone(),
-        };
-        plugins.push(Arc::new(plugin));
-    }
-
-    // sort with enforce: pre / post
-    plugins.sort_by_key(|plugin| match plugin.enforce() {
-        Some("pre") => 0,
-        Some("post") => 2,
-        _ => 1,
-    });
+    let plugins = js_plugins(env, &build_params.plugins);
 
     let root = std::path::PathBuf::from(&build_params.root);
     let default_config = serde_json::to_string(&build_params.config).unwrap();
@@ -221,7 +373,10 @@ pub fn build(env: Env, build_params: BuildParams) -> napi::Result<JsObject> {
                     deferred.reject(e);
                     return Ok(());
                 }
-                let d = DevServer::new(root.clone(), Arc::new(compiler));
+                // the standalone cli of @umijs/mako is restarted, others like umi
+                // handle config changes themselves
+                let d = DevServer::new(root.clone(), Arc::new(compiler))
+                    .restart_on_config_change(std::env::var("MAKO_CLI").is_ok());
                 deferred.resolve(move |env| env.get_undefined());
                 d.serve().await;
                 Ok(())
@@ -230,6 +385,23 @@ pub fn build(env: Env, build_params: BuildParams) -> napi::Result<JsObject> {
         )?;
         Ok(promise)
     } else {
+        if config.dual_build.is_some() {
+            let (deferred, promise) = env.create_deferred()?;
+            let legacy_config = Config::new(&root, Some(&default_config), None).map_err(|e| {
+                napi::Error::new(Status::GenericFailure, format!("Load config failed: {}", e))
+            })?;
+            thread_pool::spawn(move || {
+                let ret = MultiCompiler::dual_build(config, legacy_config, root, Some(plugins))
+                    .and_then(|compiler| compiler.compile())
+                    .map_err(|e| napi::Error::new(Status::GenericFailure, format!("{}", e)));
+                if let Err(e) = ret {
+                    deferred.reject(e);
+                    return;
+                }
+                deferred.resolve(move |env| env.get_undefined());
+            });
+            return Ok(promise);
+        }
         let (deferred, promise) = env.create_deferred()?;
         thread_pool::spawn(move || {
             let compiler =
@@ -5,6 +5,7 @@ pub(crate) mod css_ast;
 pub(crate) mod error;
 pub mod file;
 pub(crate) mod js_ast;
+pub mod parse_fallback;
 pub(crate) mod sourcemap;
 #[cfg(test)]
 pub mod tests;
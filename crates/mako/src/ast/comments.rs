@@ -1,9 +1,11 @@
+use std::cell::RefCell;
+
 use swc_core::common;
 use swc_core::common::comments::{Comment, Comments as CommentsTrait};
 use swc_core::common::{BytePos, Span};
 use swc_node_comments::SwcComments;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Comments(SwcComments);
 
 impl Comments {
@@ -11,6 +13,10 @@ impl Comments {
         &self.0
     }
 
+    pub(crate) fn get_emitted_comments(&self) -> EmittedComments<'_> {
+        EmittedComments(&self.0)
+    }
+
     pub fn add_leading_comment_at(&mut self, pos: BytePos, comment: Comment) {
         self.0.add_leading(pos, comment);
     }
@@ -34,7 +40,6 @@ impl Comments {
     /**
      * Check for `/*#__PURE__*/`
      */
-    #[allow(dead_code)]
     pub fn has_pure(&self, span: Span) -> bool {
         self.has_flag(span, "PURE")
     }
@@ -47,15 +52,22 @@ impl Comments {
         self.has_flag(span, "NO_SIDE_EFFECTS")
     }
 
-    #[allow(dead_code)]
+    /**
+     * Check for `/* @keepNames */` or `// @keepNames`
+     */
+    pub fn has_keep_names(&self, span: Span) -> bool {
+        self.find_comment(span, |c| c.text.trim() == "@keepNames")
+    }
+
     fn has_flag(&self, span: Span, text: &'static str) -> bool {
         self.find_comment(span, |c| {
             if c.kind == common::comments::CommentKind::Block {
-                //
-                if c.text.len() == (text.len() + 5)
-                    && (c.text.starts_with("#__") || c.text.starts_with("@__"))
-                    && c.text.ends_with("__")
-                    && text == &c.text[3..c.text.len() - 2]
+                // e.g. /* @__PURE__ */
+                let comment = c.text.trim();
+                if comment.len() == (text.len() + 5)
+                    && (comment.starts_with("#__") || comment.starts_with("@__"))
+                    && comment.ends_with("__")
+                    && text == &comment[3..comment.len() - 2]
                 {
                     return true;
                 }
@@ -65,7 +77,6 @@ impl Comments {
         })
     }
 
-    #[allow(dead_code)]
     fn find_comment<F>(&self, span: Span, mut op: F) -> bool
     where
         F: FnMut(&common::comments::Comment) -> bool,
@@ -84,3 +95,150 @@ impl Comments {
         found
     }
 }
+
+/// The comments of a parse, which are added to the shared ones as usual, and
+/// the ones matched by `filter` are kept for the caller, e.g. the suppression
+/// comments of the diagnostics.
+pub(crate) struct CollectingComments<'a> {
+    inner: &'a SwcComments,
+    filter: fn(&Comment) -> bool,
+    collected: RefCell<Vec<Comment>>,
+}
+
+impl<'a> CollectingComments<'a> {
+    pub fn new(inner: &'a SwcComments, filter: fn(&Comment) -> bool) -> Self {
+        Self {
+            inner,
+            filter,
+            collected: Default::default(),
+        }
+    }
+
+    pub fn into_collected(self) -> Vec<Comment> {
+        let mut collected = self.collected.into_inner();
+        collected.sort_by_key(|comment| comment.span.lo);
+        collected
+    }
+
+    fn collect<'c>(&self, comments: impl IntoIterator<Item = &'c Comment>) {
+        let mut collected = self.collected.borrow_mut();
+        collected.extend(comments.into_iter().filter(|c| (self.filter)(c)).cloned());
+    }
+}
+
+impl CommentsTrait for CollectingComments<'_> {
+    fn add_leading(&self, pos: BytePos, cmt: Comment) {
+        self.collect([&cmt]);
+        self.inner.add_leading(pos, cmt);
+    }
+
+    fn add_leading_comments(&self, pos: BytePos, comments: Vec<Comment>) {
+        self.collect(&comments);
+        self.inner.add_leading_comments(pos, comments);
+    }
+
+    fn has_leading(&self, pos: BytePos) -> bool {
+        self.inner.has_leading(pos)
+    }
+
+    fn move_leading(&self, from: BytePos, to: BytePos) {
+        self.inner.move_leading(from, to);
+    }
+
+    fn take_leading(&self, pos: BytePos) -> Option<Vec<Comment>> {
+        self.inner.take_leading(pos)
+    }
+
+    fn get_leading(&self, pos: BytePos) -> Option<Vec<Comment>> {
+        self.inner.get_leading(pos)
+    }
+
+    fn add_trailing(&self, pos: BytePos, cmt: Comment) {
+        self.collect([&cmt]);
+        self.inner.add_trailing(pos, cmt);
+    }
+
+    fn add_trailing_comments(&self, pos: BytePos, comments: Vec<Comment>) {
+        self.collect(&comments);
+        self.inner.add_trailing_comments(pos, comments);
+    }
+
+    fn has_trailing(&self, pos: BytePos) -> bool {
+        self.inner.has_trailing(pos)
+    }
+
+    fn move_trailing(&self, from: BytePos, to: BytePos) {
+        self.inner.move_trailing(from, to);
+    }
+
+    fn take_trailing(&self, pos: BytePos) -> Option<Vec<Comment>> {
+        self.inner.take_trailing(pos)
+    }
+
+    fn get_trailing(&self, pos: BytePos) -> Option<Vec<Comment>> {
+        self.inner.get_trailing(pos)
+    }
+
+    fn add_pure_comment(&self, pos: BytePos) {
+        self.inner.add_pure_comment(pos);
+    }
+}
+
+/// The comments passed to the emitter, which takes the comments it writes.
+/// They are kept here for the next emit of the same module, e.g. into the
+/// chunks of another entry, or for its size in the stats.
+pub(crate) struct EmittedComments<'a>(&'a SwcComments);
+
+impl CommentsTrait for EmittedComments<'_> {
+    fn add_leading(&self, pos: BytePos, cmt: Comment) {
+        self.0.add_leading(pos, cmt);
+    }
+
+    fn add_leading_comments(&self, pos: BytePos, comments: Vec<Comment>) {
+        self.0.add_leading_comments(pos, comments);
+    }
+
+    fn has_leading(&self, pos: BytePos) -> bool {
+        self.0.has_leading(pos)
+    }
+
+    fn move_leading(&self, from: BytePos, to: BytePos) {
+        self.0.move_leading(from, to);
+    }
+
+    fn take_leading(&self, pos: BytePos) -> Option<Vec<Comment>> {
+        self.0.get_leading(pos)
+    }
+
+    fn get_leading(&self, pos: BytePos) -> Option<Vec<Comment>> {
+        self.0.get_leading(pos)
+    }
+
+    fn add_trailing(&self, pos: BytePos, cmt: Comment) {
+        self.0.add_trailing(pos, cmt);
+    }
+
+    fn add_trailing_comments(&self, pos: BytePos, comments: Vec<Comment>) {
+        self.0.add_trailing_comments(pos, comments);
+    }
+
+    fn has_trailing(&self, pos: BytePos) -> bool {
+        self.0.has_trailing(pos)
+    }
+
+    fn move_trailing(&self, from: BytePos, to: BytePos) {
+        self.0.move_trailing(from, to);
+    }
+
+    fn take_trailing(&self, pos: BytePos) -> Option<Vec<Comment>> {
+        self.0.get_trailing(pos)
+    }
+
+    fn get_trailing(&self, pos: BytePos) -> Option<Vec<Comment>> {
+        self.0.get_trailing(pos)
+    }
+
+    fn add_pure_comment(&self, pos: BytePos) {
+        self.0.add_pure_comment(pos);
+    }
+}
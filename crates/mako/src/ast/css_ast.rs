@@ -120,7 +120,8 @@ impl CssAst {
         let mut gen = CodeGenerator::new(
             writer,
             CodegenConfig {
-                minify: context.config.minify && matches!(context.config.mode, Mode::Production),
+                minify: context.config.minify.is_some()
+                    && matches!(context.config.mode, Mode::Production),
             },
         );
         gen.emit(&self.ast).map_err(|err| {
@@ -129,7 +130,7 @@ impl CssAst {
             })
         })?;
 
-        let buf = build_source_map_to_buf(&source_map, &context.meta.css.cm);
+        let buf = build_source_map_to_buf(&source_map, &context.meta.css.cm, &context);
         let sourcemap = String::from_utf8(buf).unwrap();
         if matches!(context.config.devtool, Some(DevtoolConfig::SourceMap)) {
             let filename = &self.path;
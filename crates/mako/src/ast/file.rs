@@ -1,18 +1,20 @@
 use std::hash::{Hash, Hasher};
 use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock};
 
 use anyhow::{anyhow, Result};
+use mime_guess;
 use pathdiff::diff_paths;
-use percent_encoding::percent_decode_str;
 use regex::Regex;
 use thiserror::Error;
 use twox_hash::XxHash64;
-use url::Url;
-use {md5, mime_guess};
 
 use crate::compiler::Context;
+use crate::config::{ModuleKind, OutputConfig};
+pub use crate::utils::file_request::win_path;
+use crate::utils::file_request::FileRequest;
+use crate::utils::hash::ContentHasher;
 use crate::utils::{base64_decode, base64_encode};
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -66,6 +68,8 @@ pub struct File {
     pub search: String,
     pub params: Vec<(String, String)>,
     pub fragment: Option<String>,
+    /// The module type decided by the file itself rather than its syntax
+    pub module_kind: Option<ModuleKind>,
 }
 
 impl Default for File {
@@ -83,6 +87,7 @@ impl Default for File {
             search: "".to_string(),
             params: vec![],
             fragment: None,
+            module_kind: None,
         }
     }
 }
@@ -112,18 +117,23 @@ fn css_source_map_regex() -> &'static Regex {
 impl File {
     pub fn new(path: String, context: Arc<Context>) -> Self {
         let path = PathBuf::from(path);
-        // if path exists, it has no search and fragment
+        // the paths of the ids are encoded, see `encode_path`, and the raw paths
+        // of the real files have no search and fragment
         // support ./a#b.ts when a#b.ts is a real file
         // e.g. https://unpkg.com/browse/es5-ext@0.10.64/string/
-        let (pathname, search, params, fragment) = if path.exists() {
-            (
-                path.to_string_lossy().to_string(),
-                "".to_string(),
-                vec![],
-                None,
-            )
-        } else {
-            parse_path(&path.to_string_lossy()).unwrap()
+        let request = FileRequest::parse(&path.to_string_lossy());
+        let FileRequest {
+            path: pathname,
+            search,
+            params,
+            fragment,
+        } = match request {
+            Ok(request) if Path::new(&request.path).is_file() => request,
+            _ if path.exists() => FileRequest {
+                path: path.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            request => request.unwrap(),
         };
         let pathname = PathBuf::from(pathname);
         let is_virtual = path.starts_with(VIRTUAL) ||
@@ -196,6 +206,8 @@ impl File {
         }
     }
 
+    // the line endings are normalized, so that the checkouts with CRLF and LF
+    // have the same hash
     pub fn get_raw_hash(&self) -> u64 {
         let mut hasher: XxHash64 = Default::default();
         if let Some(content) = &self.content {
@@ -203,8 +215,12 @@ impl File {
                 Content::Js(JsContent { content, .. })
                 | Content::Css(content)
                 | Content::Assets(Asset { content, .. }) => {
-                    // hasher.write_u64(init);
-                    hasher.write(content.as_bytes());
+                    for (i, line) in content.split("\r\n").enumerate() {
+                        if i > 0 {
+                            hasher.write(b"\n");
+                        }
+                        hasher.write(line.as_bytes());
+                    }
                     hasher.finish()
                 }
             }
@@ -242,28 +258,25 @@ impl File {
         }
     }
 
-    pub fn get_content_hash(&self) -> Result<String> {
+    pub fn get_content_hash(&self, output: &OutputConfig) -> Result<String> {
         let file = std::fs::File::open(&self.pathname)?;
         let len = file.metadata()?.len();
         // Decide on a reasonable buffer size (1MB in this case, fastest will depend on hardware)
         let buf_len = len.min(1_000_000) as usize;
         let mut buf = BufReader::with_capacity(buf_len, file);
-        // webpack use md4
-        let mut context = md5::Context::new();
+        let mut hasher = ContentHasher::new(output.hash_function);
         loop {
             // Get a chunk of the file
             let part = buf.fill_buf()?;
             if part.is_empty() {
                 break;
             }
-            context.consume(part);
+            hasher.update(part);
             // Tell the buffer that the chunk is consumed
             let part_len = part.len();
             buf.consume(part_len);
         }
-        let digest = context.compute();
-        let hash = format!("{:x}", digest);
-        Ok(hash[0..8].to_string())
+        Ok(hasher.finish_hex(output))
     }
 
     pub fn is_content_jsx(&self) -> bool {
@@ -273,6 +286,13 @@ impl File {
         }
     }
 
+    /// The query as it's requested, e.g. `width=24&height=24`, for the
+    /// loaders which care about the order of the params, while the module
+    /// id has the normalized one, e.g. `height=24&width=24`.
+    pub fn raw_query(&self) -> &str {
+        &self.search
+    }
+
     pub fn has_param(&self, key: &str) -> bool {
         self.params.iter().any(|(k, _)| k == key)
     }
@@ -326,62 +346,10 @@ impl File {
     }
 }
 
-type PathName = String;
-type Search = String;
-type Params = Vec<(String, String)>;
-type Fragment = Option<String>;
-
-fn has_hash_without_dot(input: &str) -> bool {
-    if let Some(pos) = input.find('#') {
-        let after_hash = &input[pos + 1..];
-        !after_hash.contains('.')
-    } else {
-        false
-    }
-}
-
-pub fn win_path(path: &str) -> String {
-    #[cfg(target_os = "windows")]
-    let path = {
-        let prefix = "\\\\?\\";
-        let path = path.trim_start_matches(prefix);
-        path.replace('\\', "/")
-    };
-    #[cfg(not(target_os = "windows"))]
-    let path = path.to_string();
-    path
-}
-
-pub fn parse_path(path: &str) -> Result<(PathName, Search, Params, Fragment)> {
-    let path = win_path(path);
-    if path.contains('?') || has_hash_without_dot(path.as_str()) {
-        let (path, search) = if path.contains('?') {
-            path.split_once('?').unwrap_or((path.as_str(), ""))
-        } else {
-            path.split_once('#').unwrap_or((path.as_str(), ""))
-        };
-        let base = "http://a.com/";
-        let base_url = Url::parse(base)?;
-        let full_url = base_url.join(format!("?{}", search).as_str())?;
-        let fragment = full_url.fragment().map(|s| s.to_string());
-        let search = full_url.query().unwrap_or("").to_string();
-        let query_vec = full_url
-            .query_pairs()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect();
-        // dir or filename may contains space or other special characters
-        // so we need to decode it, e.g. "a%20b" -> "a b"
-        let path = percent_decode_str(path).decode_utf8()?;
-        Ok((path.to_string(), search.to_string(), query_vec, fragment))
-    } else {
-        let path = percent_decode_str(&path).decode_utf8()?;
-        Ok((path.to_string(), "".to_string(), vec![], None))
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::build::load::Load;
 
     #[test]
     fn test_abs_path() {
@@ -405,27 +373,27 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_path_support_windows() {
-        let path = "C:\\a\\b\\c?foo";
-        let (path, search, params, fragment) = parse_path(path).unwrap();
-        assert_eq!(path, "C:\\a\\b\\c");
-        assert_eq!(search, "foo");
-        assert_eq!(params, vec![("foo".to_string(), "".to_string())]);
-        assert_eq!(fragment, None);
-    }
-
-    #[test]
-    fn test_parse_path_with_fragment() {
-        assert_eq!(parse_path("foo.ts#bar").unwrap().0, "foo.ts");
-        assert_eq!(parse_path("foo#bar.ts").unwrap().0, "foo#bar.ts");
-    }
-
-    #[test]
-    fn test_has_hash_without_dot() {
-        assert!(has_hash_without_dot("foo.ts#world"));
-        assert!(!has_hash_without_dot("foo#bar.ts"));
-        assert!(has_hash_without_dot("#no_dot"));
-        assert!(!has_hash_without_dot("no_hash"));
-        assert!(!has_hash_without_dot("#.dot_after_hash"));
+    fn test_raw_hash_line_endings() {
+        let context = Arc::new(Context::default());
+        let load = |name: &str| {
+            let mut file = File::new(
+                format!(
+                    "{}/test/build/line-endings/{}",
+                    env!("CARGO_MANIFEST_DIR"),
+                    name
+                ),
+                context.clone(),
+            );
+            file.set_content(Load::load(&file, context.clone()).unwrap());
+            file
+        };
+        let lf = load("lf.js");
+        let crlf = load("crlf.js");
+        let bom = load("bom.js");
+        assert_eq!(crlf.get_raw_hash(), lf.get_raw_hash());
+        assert_eq!(bom.get_raw_hash(), lf.get_raw_hash());
+        // the original content is kept for the source maps
+        assert!(crlf.get_content_raw().contains("\r\n"));
+        assert!(!bom.get_content_raw().starts_with('\u{feff}'));
     }
 }
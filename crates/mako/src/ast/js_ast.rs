@@ -2,28 +2,42 @@ use std::fmt;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
+use colored::Colorize;
+use swc_core::common::comments::Comment;
 use swc_core::common::util::take::Take;
-use swc_core::common::{FileName, Mark, Spanned, GLOBALS};
-use swc_core::ecma::ast::{EsVersion, Module};
+use swc_core::common::{BytePos, FileName, Mark, Spanned, GLOBALS};
+use swc_core::ecma::ast::{
+    EsVersion, ImportDecl, ImportNamedSpecifier, ImportSpecifier, Module, ModuleDecl, ModuleItem,
+    NamedExport,
+};
 use swc_core::ecma::codegen::text_writer::JsWriter;
 use swc_core::ecma::codegen::{Config as JsCodegenConfig, Emitter};
-use swc_core::ecma::parser::error::SyntaxError;
+use swc_core::ecma::parser::error::{Error as ParserError, SyntaxError};
 use swc_core::ecma::parser::lexer::Lexer;
-use swc_core::ecma::parser::{EsSyntax, Parser, StringInput, Syntax, TsSyntax};
+use swc_core::ecma::parser::{EsSyntax, PResult, Parser, StringInput, Syntax, TsSyntax};
 use swc_core::ecma::transforms::base::helpers::inject_helpers;
+use swc_core::ecma::transforms::proposal::export_default_from;
 use swc_core::ecma::utils::contains_top_level_await;
 use swc_core::ecma::visit;
 use swc_core::ecma::visit::{VisitMutWith, VisitWith};
 
+use crate::ast::comments::CollectingComments;
 use crate::ast::file::{Content, File, JsContent};
+use crate::ast::parse_fallback::{self, ParseFallback};
 use crate::ast::sourcemap::build_source_map_to_buf;
 use crate::ast::{error, utils};
 use crate::compiler::Context;
-use crate::config::{DevtoolConfig, Mode, OutputMode};
+use crate::config::{DevtoolConfig, Mode, ModuleKind, OutputMode, ParseFallbackStep};
+use crate::diagnostics::Suppressions;
+use crate::generate::analyze::package_name;
 use crate::module::Dependency;
 use crate::utils::base64_encode;
 use crate::visitors::dep_analyzer::DepAnalyzer;
 
+// the times a file with syntax errors is parsed again to find the partial
+// imports
+const MAX_PARTIAL_PARSES: usize = 8;
+
 #[derive(Clone)]
 pub struct JsAst {
     pub ast: Module,
@@ -41,12 +55,159 @@ impl fmt::Debug for JsAst {
 
 impl JsAst {
     pub fn new(file: &File, context: Arc<Context>) -> Result<Self> {
-        let fm = context.meta.script.cm.new_source_file(
-            FileName::Real(file.relative_path.to_path_buf()).into(),
-            file.get_content_raw(),
-        );
-        let comments = context.meta.script.origin_comments.read().unwrap();
+        let content = file.get_content_raw();
+        let path = file.pathname.to_string_lossy().to_string();
+        // the steps chosen when the file is built last time
+        let cached = context.parse_fallbacks.get(&path).and_then(|fallback| {
+            Self::parse_checked(file, &content, &fallback.steps, &context).ok()
+        });
+        let (ast, suppression_comments) = match cached {
+            Some(parsed) => parsed,
+            None => match Self::parse_checked(file, &content, &[], &context) {
+                Ok(ast) => {
+                    context.parse_fallbacks.remove(&path);
+                    ast
+                }
+                Err(ast_errors) => match Self::parse_with_fallbacks(file, &content, &context) {
+                    Ok(ast) => ast,
+                    Err(attempted) => {
+                        return Err(Self::parse_error(file, ast_errors, attempted, &context))
+                    }
+                },
+            },
+        };
+        Self::set_suppressions(file, &suppression_comments, &context);
+
+        // top level mark and unresolved mark need to be persisted for transform usage
+        GLOBALS.set(&context.meta.script.globals, || {
+            let top_level_mark = Mark::new();
+            let unresolved_mark = Mark::new();
+            let contains_top_level_await = contains_top_level_await(&ast);
+            Ok(JsAst {
+                ast,
+                unresolved_mark,
+                top_level_mark,
+                path: file.relative_path.to_string_lossy().to_string(),
+                contains_top_level_await,
+            })
+        })
+    }
+
+    // the module with its suppression comments, or the errors except the ones
+    // in strict mode which are ignored
+    fn parse_checked(
+        file: &File,
+        content: &str,
+        steps: &[ParseFallbackStep],
+        context: &Arc<Context>,
+    ) -> std::result::Result<(Module, Vec<Comment>), Vec<ParserError>> {
+        let (mut ast, mut ast_errors, _, suppression_comments) =
+            Self::parse_content(file, content.to_string(), steps, context);
+        // the proposals are lowered so the module is standard as the others
+        if steps.contains(&ParseFallbackStep::Proposals) {
+            if let Ok(ast) = &mut ast {
+                ast.visit_mut_with(&mut export_default_from());
+            }
+        }
+        ast_errors.retain(|error| {
+            !matches!(
+                error.kind(),
+                SyntaxError::WithInStrict | SyntaxError::LegacyOctal
+            )
+        });
+        match ast {
+            Ok(ast) if ast_errors.is_empty() => Ok((ast, suppression_comments)),
+            Ok(_) => Err(ast_errors),
+            Err(err) => {
+                ast_errors.push(err);
+                Err(ast_errors)
+            }
+        }
+    }
+
+    // parse the file under node_modules with the `parseFallback` steps one
+    // after another, the chosen ones are cached, or the attempted ones
+    fn parse_with_fallbacks(
+        file: &File,
+        content: &str,
+        context: &Arc<Context>,
+    ) -> std::result::Result<(Module, Vec<Comment>), Vec<String>> {
+        let steps = parse_fallback::fallback_steps(file, context);
+        let mut attempted = vec![];
+        for i in 1..=steps.len() {
+            let steps = &steps[..i];
+            let description = steps
+                .iter()
+                .map(|step| step.to_string())
+                .collect::<Vec<_>>()
+                .join(" + ");
+            if Self::syntax(file, steps, content, context).is_none() {
+                attempted.push(format!("{} (skipped)", description));
+                continue;
+            }
+            match Self::parse_checked(file, content, steps, context) {
+                Ok(parsed) => {
+                    let (syntax, _) = Self::syntax(file, steps, content, context).unwrap();
+                    context.parse_fallbacks.insert(
+                        file.pathname.to_string_lossy().to_string(),
+                        ParseFallback {
+                            steps: steps.to_vec(),
+                            syntax,
+                        },
+                    );
+                    return Ok(parsed);
+                }
+                Err(_) => attempted.push(description),
+            }
+        }
+        Err(attempted)
+    }
+
+    fn parse_error(
+        file: &File,
+        ast_errors: Vec<ParserError>,
+        attempted: Vec<String>,
+        context: &Arc<Context>,
+    ) -> anyhow::Error {
+        let errors = ast_errors
+            .iter()
+            .map(|err| {
+                error::code_frame(
+                    error::ErrorSpan::Js(err.span()),
+                    err.kind().msg().to_string().as_str(),
+                    context.clone(),
+                )
+            })
+            .collect::<Vec<String>>();
+        let mut messages = errors.join("\n");
+        if !attempted.is_empty() {
+            messages.push_str(&format!(
+                "\nparseFallback attempted: {}",
+                attempted.join(", ")
+            ));
+        }
+        // e.g. jsx in .js files of packages which ship their source
+        if file.is_under_node_modules && !context.transpiles(file) {
+            let package = package_name(&file.pathname.to_string_lossy());
+            messages.push_str(&format!(
+                "\nhint: {} is not transpiled, add \"{}\" to transform.include if it ships untranspiled source",
+                file.relative_path.display(),
+                package.as_deref().unwrap_or("the package")
+            ));
+        }
+        anyhow!(error::ParseError::JsParseError { messages })
+    }
+
+    // the syntax of the file with the steps, and the content rewritten by
+    // them, none if a step doesn't apply to it
+    fn syntax(
+        file: &File,
+        steps: &[ParseFallbackStep],
+        content: &str,
+        context: &Arc<Context>,
+    ) -> Option<(Syntax, Option<String>)> {
         let extname = &file.extname;
+        let is_commonjs = matches!(file.module_kind, Some(ModuleKind::Commonjs));
         let syntax = if extname == "ts" || extname == "tsx" {
             Syntax::Typescript(TsSyntax {
                 tsx: extname == "tsx",
@@ -56,65 +217,159 @@ impl JsAst {
         } else {
             let jsx = file.is_content_jsx()
                 || extname == "jsx"
-                || (extname == "js" && !file.is_under_node_modules);
+                || (extname == "js" && context.transpiles(file));
             Syntax::Es(EsSyntax {
                 jsx,
                 decorators: true,
                 decorators_before_export: true,
+                import_attributes: true,
+                // the module wrapper is a function in node
+                allow_return_outside_function: is_commonjs,
                 ..Default::default()
             })
         };
+        parse_fallback::apply_steps(file, syntax, content, steps)
+    }
+
+    // parse the content as the file, with the recovered errors and the start
+    // position of the source file
+    fn parse_content(
+        file: &File,
+        content: String,
+        steps: &[ParseFallbackStep],
+        context: &Arc<Context>,
+    ) -> (PResult<Module>, Vec<ParserError>, BytePos, Vec<Comment>) {
+        // the steps which don't apply to the file, e.g. it's changed, are
+        // ignored
+        let (syntax, rewritten) = Self::syntax(file, steps, &content, context)
+            .unwrap_or_else(|| Self::syntax(file, &[], &content, context).unwrap());
+        let content = rewritten.unwrap_or(content);
+        let fm = context.meta.script.cm.new_source_file(
+            FileName::Real(file.relative_path.to_path_buf()).into(),
+            content,
+        );
+        let origin_comments = context.meta.script.origin_comments.read().unwrap();
+        let comments = CollectingComments::new(origin_comments.get_swc_comments(), |comment| {
+            Suppressions::is_candidate(&comment.text)
+        });
+        let is_commonjs = matches!(file.module_kind, Some(ModuleKind::Commonjs));
         let lexer = Lexer::new(
             syntax,
             EsVersion::Es2015,
             StringInput::from(&*fm),
-            Some(comments.get_swc_comments()),
+            Some(&comments),
         );
         let mut parser = Parser::new_from(lexer);
-        let ast = parser.parse_module();
+        // commonjs is parsed as a script, in sloppy mode and without import.meta
+        let ast = if is_commonjs {
+            parser.parse_script().map(|script| Module {
+                span: script.span,
+                body: script.body.into_iter().map(ModuleItem::Stmt).collect(),
+                shebang: script.shebang,
+            })
+        } else {
+            parser.parse_module()
+        };
+        let errors = parser.take_errors();
+        (ast, errors, fm.start_pos, comments.into_collected())
+    }
 
-        // handle ast errors
-        let mut ast_errors = parser.take_errors();
-        // ignore with syntax error in strict mode
-        ast_errors.retain(|error| {
-            !matches!(
-                error.kind(),
-                SyntaxError::WithInStrict | SyntaxError::LegacyOctal
-            )
-        });
-        if ast.is_err() {
-            ast_errors.push(ast.clone().unwrap_err());
-        }
-        if !ast_errors.is_empty() {
-            let errors = ast_errors
-                .iter()
-                .map(|err| {
-                    error::code_frame(
-                        error::ErrorSpan::Js(err.span()),
-                        err.kind().msg().to_string().as_str(),
-                        context.clone(),
-                    )
-                })
-                .collect::<Vec<String>>();
-            return Err(anyhow!(error::ParseError::JsParseError {
-                messages: errors.join("\n")
-            }));
+    fn set_suppressions(file: &File, comments: &[Comment], context: &Arc<Context>) {
+        let cm = &context.meta.script.cm;
+        let path = file.relative_path.to_string_lossy();
+        let (suppressions, warnings) = Suppressions::parse(
+            comments.iter().map(|comment| {
+                (
+                    comment.text.as_ref(),
+                    cm.lookup_char_pos(comment.span.lo).line,
+                    cm.lookup_char_pos(comment.span.hi).line,
+                )
+            }),
+            &path,
+        );
+        for warning in warnings {
+            eprintln!("{}: {}", "Warning".yellow(), warning);
         }
-        let ast = ast./*safe*/unwrap();
+        context
+            .diagnostics
+            .set_suppressions(file.pathname.clone(), suppressions);
+    }
 
-        // top level mark and unresolved mark need to be persisted for transform usage
-        GLOBALS.set(&context.meta.script.globals, || {
-            let top_level_mark = Mark::new();
-            let unresolved_mark = Mark::new();
-            let contains_top_level_await = contains_top_level_await(&ast);
-            Ok(JsAst {
-                ast,
-                unresolved_mark,
-                top_level_mark,
-                path: file.relative_path.to_string_lossy().to_string(),
-                contains_top_level_await,
+    /// The import and export declarations of a file with syntax errors which
+    /// are parsed before the first fatal error, as side effect imports.
+    pub fn parse_partial_imports(file: &File, context: Arc<Context>) -> Vec<ModuleItem> {
+        let content = file.get_content_raw();
+        let (ast, _, start_pos, _) = Self::parse_content(file, content.clone(), &[], &context);
+        let module = match ast {
+            Ok(module) => Some(module),
+            Err(err) => {
+                let error_pos = err.span().lo.0.saturating_sub(start_pos.0) as usize;
+                // the statement with the error usually starts at the beginning
+                // of a line, the content is cut there and parsed again
+                std::iter::once(0)
+                    .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+                    .filter(|i| {
+                        *i < error_pos
+                            && !content[*i..].starts_with(|c: char| {
+                                c.is_whitespace() || matches!(c, '}' | ')' | ']')
+                            })
+                    })
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .rev()
+                    .take(MAX_PARTIAL_PARSES)
+                    .find_map(|cut| {
+                        Self::parse_content(file, content[..cut].to_string(), &[], &context)
+                            .0
+                            .ok()
+                    })
+            }
+        };
+        let Some(module) = module else {
+            return vec![];
+        };
+        module
+            .body
+            .into_iter()
+            .filter_map(|item| {
+                let (span, src, with) = match item {
+                    ModuleItem::ModuleDecl(ModuleDecl::Import(import))
+                        if !import.type_only
+                            && (import.specifiers.is_empty()
+                                || import.specifiers.iter().any(|specifier| {
+                                    !matches!(
+                                        specifier,
+                                        ImportSpecifier::Named(ImportNamedSpecifier {
+                                            is_type_only: true,
+                                            ..
+                                        })
+                                    )
+                                })) =>
+                    {
+                        (import.span, import.src, import.with)
+                    }
+                    ModuleItem::ModuleDecl(ModuleDecl::ExportAll(export)) if !export.type_only => {
+                        (export.span, export.src, export.with)
+                    }
+                    ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+                        span,
+                        src: Some(src),
+                        type_only: false,
+                        with,
+                        ..
+                    })) => (span, src, with),
+                    _ => return None,
+                };
+                Some(ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+                    span,
+                    specifiers: vec![],
+                    src,
+                    type_only: false,
+                    with,
+                    phase: Default::default(),
+                })))
             })
-        })
+            .collect()
     }
 
     pub fn build(path: &str, content: &str, context: Arc<Context>) -> Result<Self> {
@@ -192,9 +447,9 @@ impl JsAst {
         let cm = context.meta.script.cm.clone();
         {
             let comments = context.meta.script.origin_comments.read().unwrap();
-            let swc_comments = comments.get_swc_comments();
+            let emitted_comments = comments.get_emitted_comments();
             let is_prod = matches!(context.config.mode, Mode::Production);
-            let minify = context.config.minify && is_prod;
+            let minify = context.config.minify.is_some() && is_prod;
             let ascii_only = if context.config.output.mode == OutputMode::Bundless {
                 false
             } else {
@@ -207,7 +462,11 @@ impl JsAst {
                     .with_ascii_only(ascii_only)
                     .with_omit_last_semi(true),
                 cm: cm.clone(),
-                comments: if minify { None } else { Some(swc_comments) },
+                comments: if minify {
+                    None
+                } else {
+                    Some(&emitted_comments)
+                },
                 wr: Box::new(JsWriter::new(
                     cm.clone(),
                     "\n",
@@ -224,7 +483,7 @@ impl JsAst {
 
         let sourcemap = match context.config.devtool {
             Some(DevtoolConfig::SourceMap | DevtoolConfig::InlineSourceMap) => {
-                let src_buf = build_source_map_to_buf(&source_map_buf, &cm);
+                let src_buf = build_source_map_to_buf(&source_map_buf, &cm, &context);
                 String::from_utf8(src_buf).unwrap()
             }
             None => "".to_string(),
@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+use regex::Regex;
+use swc_core::ecma::parser::{EsSyntax, Syntax, TsSyntax};
+
+use crate::ast::file::File;
+use crate::compiler::{Compiler, Context};
+use crate::config::ParseFallbackStep;
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::generate::analyze::package_name;
+use crate::module::relative_to_root;
+
+/// The `parseFallback` steps the files under node_modules are parsed with,
+/// which are cached by path so the files are parsed with them at once when
+/// they are built again.
+#[derive(Default)]
+pub struct ParseFallbacks {
+    chosen: RwLock<HashMap<String, ParseFallback>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ParseFallback {
+    pub steps: Vec<ParseFallbackStep>,
+    pub syntax: Syntax,
+}
+
+impl ParseFallbacks {
+    pub fn get(&self, path: &str) -> Option<ParseFallback> {
+        self.chosen.read().unwrap().get(path).cloned()
+    }
+
+    pub fn insert(&self, path: String, fallback: ParseFallback) {
+        self.chosen.write().unwrap().insert(path, fallback);
+    }
+
+    pub fn remove(&self, path: &str) {
+        self.chosen.write().unwrap().remove(path);
+    }
+
+    /// Whether the types of the file are stripped as typescript, and it's
+    /// tsx, since the Flow annotations are parsed as typescript.
+    pub fn strips_types(&self, path: &str) -> Option<bool> {
+        match self.get(path)?.syntax {
+            Syntax::Typescript(TsSyntax { tsx, .. }) => Some(tsx),
+            Syntax::Es(_) => None,
+        }
+    }
+}
+
+/// The syntax and the content with the steps applied, none if a step doesn't
+/// apply to the file, e.g. `flow` to a file without the `@flow` pragma.
+pub(crate) fn apply_steps(
+    file: &File,
+    syntax: Syntax,
+    content: &str,
+    steps: &[ParseFallbackStep],
+) -> Option<(Syntax, Option<String>)> {
+    let mut syntax = syntax;
+    let mut stripped = None;
+    for step in steps {
+        syntax = match (step, syntax) {
+            (ParseFallbackStep::Jsx, Syntax::Es(es)) => Syntax::Es(EsSyntax { jsx: true, ..es }),
+            (ParseFallbackStep::Jsx, Syntax::Typescript(ts)) => {
+                Syntax::Typescript(TsSyntax { tsx: true, ..ts })
+            }
+            (ParseFallbackStep::Proposals, Syntax::Es(es)) => Syntax::Es(EsSyntax {
+                export_default_from: true,
+                allow_return_outside_function: true,
+                ..es
+            }),
+            (ParseFallbackStep::Flow, Syntax::Es(es)) => {
+                if !is_flow(file, content) {
+                    return None;
+                }
+                stripped = Some(strip_flow_only_syntax(content));
+                Syntax::Typescript(TsSyntax {
+                    tsx: es.jsx,
+                    decorators: true,
+                    ..Default::default()
+                })
+            }
+            (_, syntax) => syntax,
+        };
+    }
+    Some((syntax, stripped))
+}
+
+// the `@flow` pragma in the leading comments, or a `.flow` sibling of the file
+// which declares its types
+fn is_flow(file: &File, content: &str) -> bool {
+    let mut flow_file = file.pathname.clone().into_os_string();
+    flow_file.push(".flow");
+    if Path::new(&flow_file).exists() {
+        return true;
+    }
+    has_flow_pragma(content)
+}
+
+// `// @flow` or `/** @flow */` in the comments before the code
+fn has_flow_pragma(content: &str) -> bool {
+    let mut rest = content.trim_start();
+    loop {
+        let (comment, after) = if let Some(line) = rest.strip_prefix("//") {
+            line.split_once('\n').unwrap_or((line, ""))
+        } else if let Some(block) = rest.strip_prefix("/*") {
+            match block.split_once("*/") {
+                Some(split) => split,
+                None => return false,
+            }
+        } else {
+            return false;
+        };
+        if comment.contains("@flow") {
+            return true;
+        }
+        rest = after.trim_start();
+    }
+}
+
+fn flow_maybe_type_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"([:<,|&]\s*)\?([\w({\[])").unwrap())
+}
+
+/// Rewrite the Flow annotations which typescript doesn't parse to the ones it
+/// does, in the same length so the positions of the source map are kept:
+/// the exact objects `{| |}`, the maybe types `?T` and `import typeof`.
+pub(crate) fn strip_flow_only_syntax(content: &str) -> String {
+    let content = content
+        .replace("{|", "{ ")
+        .replace("|}", " }")
+        .replace("import typeof ", "import type   ");
+    flow_maybe_type_regex()
+        .replace_all(&content, "$1 $2")
+        .to_string()
+}
+
+/// The steps of `parseFallback` for the file, none if it's not under
+/// node_modules.
+pub(crate) fn fallback_steps(file: &File, context: &Context) -> Vec<ParseFallbackStep> {
+    match &context.config.parse_fallback {
+        Some(config) if file.is_under_node_modules => config.steps.clone(),
+        _ => vec![],
+    }
+}
+
+impl Compiler {
+    /// Warn once for each package which has the files parsed with the
+    /// `parseFallback` steps, so it's pinned or patched.
+    pub(crate) fn report_parse_fallbacks(&self) {
+        let context = &self.context;
+        let module_graph = context.module_graph.read().unwrap();
+        // package -> (path, steps)
+        let mut packages: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        for module in module_graph.modules() {
+            let Some(info) = &module.info else {
+                continue;
+            };
+            let path = info.file.pathname.to_string_lossy().to_string();
+            let Some(fallback) = context.parse_fallbacks.get(&path) else {
+                continue;
+            };
+            let steps = fallback
+                .steps
+                .iter()
+                .map(|step| step.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let package = package_name(&path).unwrap_or_else(|| "node_modules".to_string());
+            packages
+                .entry(package)
+                .or_default()
+                .push((relative_to_root(&path, &context.root), steps));
+        }
+        for (package, mut files) in packages {
+            files.sort();
+            files.dedup();
+            let diagnostic = Diagnostic::new(
+                DiagnosticCode::ParseFallback,
+                format!(
+                    "{} file(s) of {} failed to parse with the default settings and are parsed with parseFallback, pin or patch the package:\n{}",
+                    files.len(),
+                    package,
+                    files
+                        .iter()
+                        .map(|(path, steps)| format!("  {} ({})", path, steps))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+            )
+            .paths(
+                files
+                    .iter()
+                    .map(|(path, _)| context.root.join(path))
+                    .collect(),
+            );
+            context.diagnostics.report(diagnostic, context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::compile_fixture;
+
+    fn build(entry: &str) -> anyhow::Result<Compiler> {
+        compile_fixture(
+            Path::new("test/build/parse-fallback"),
+            Some(&format!(
+                r#"{{"mode":"production","hash":false,"minify":false,"entry":{{"index":"{}"}}}}"#,
+                entry
+            )),
+        )
+    }
+
+    #[test]
+    fn test_parse_fallback() {
+        let compiler = build("src/index.js").unwrap();
+        let context = &compiler.context;
+        let js = String::from_utf8(
+            context
+                .output_fs
+                .read(&context.config.output.path.join("index.js"))
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(js.contains(r#"className: "jsx-pkg""#), "{}", js);
+        assert!(js.contains("proposal-pkg value"), "{}", js);
+        // the Flow annotations are stripped
+        assert!(js.contains("hello"), "{}", js);
+        assert!(!js.contains("Greeting"), "{}", js);
+
+        // the chosen steps are cached by file
+        let steps = |name: &str| {
+            let path = context.root.join("node_modules").join(name);
+            context
+                .parse_fallbacks
+                .get(&path.to_string_lossy())
+                .map(|fallback| fallback.steps)
+        };
+        assert_eq!(
+            steps("jsx-pkg/index.js"),
+            Some(vec![ParseFallbackStep::Jsx])
+        );
+        assert_eq!(
+            steps("flow-pkg/index.js"),
+            Some(vec![
+                ParseFallbackStep::Jsx,
+                ParseFallbackStep::Proposals,
+                ParseFallbackStep::Flow
+            ])
+        );
+        assert_eq!(steps("proposal-pkg/value.js"), None);
+
+        // a warning for each package
+        let reported = context.diagnostics.get_reported();
+        assert_eq!(reported.len(), 3, "{:?}", reported);
+        assert!(reported
+            .iter()
+            .all(|diagnostic| diagnostic.code == DiagnosticCode::ParseFallback));
+        assert!(
+            reported[0].message.contains("flow-pkg"),
+            "{}",
+            reported[0].message
+        );
+    }
+
+    #[test]
+    fn test_parse_fallback_failed() {
+        let err = build("src/broken.js").err().unwrap().to_string();
+        assert!(
+            err.contains(
+                "parseFallback attempted: jsx, jsx + proposals, jsx + proposals + flow (skipped)"
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_strip_flow_only_syntax() {
+        let content = r#"// @flow
+import typeof React from 'react';
+type Props = {| name: ?string, items: Array<?number> |};
+const a = b ? c : d;
+"#;
+        let stripped = strip_flow_only_syntax(content);
+        assert_eq!(stripped.len(), content.len());
+        assert_eq!(
+            stripped,
+            r#"// @flow
+import type   React from 'react';
+type Props = {  name:  string, items: Array< number>  };
+const a = b ? c : d;
+"#
+        );
+    }
+
+    #[test]
+    fn test_flow_pragma() {
+        assert!(has_flow_pragma("/**\n * @flow strict\n */\nexport {}"));
+        assert!(has_flow_pragma("/* license */\n// @flow\nexport {}"));
+        assert!(!has_flow_pragma("/* @noflow */\nexport {}"));
+        assert!(!has_flow_pragma("const a = 1; // @flow"));
+    }
+}
@@ -1,42 +1,466 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
+use glob_match::glob_match;
 use pathdiff::diff_paths;
+use serde::Serialize;
 use swc_core::base::sourcemap as swc_sourcemap;
 use swc_core::common::source_map::SourceMapGenConfig;
 use swc_core::common::sync::Lrc;
 use swc_core::common::{BytePos, FileName, LineCol, SourceMap};
+use twox_hash::XxHash64;
 
-pub struct SwcSourceMapGenConfig;
+use crate::ast::file::win_path;
+use crate::compiler::Context;
+use crate::config::{SourceMapFidelity, SourceMapIgnoreListConfig};
 
-impl SourceMapGenConfig for SwcSourceMapGenConfig {
+pub struct SwcSourceMapGenConfig<'a> {
+    context: &'a Context,
+}
+
+impl SourceMapGenConfig for SwcSourceMapGenConfig<'_> {
     fn file_name_to_source(&self, f: &FileName) -> String {
         f.to_string()
     }
 
-    /// 生成 sourceContents
-    fn inline_sources_content(&self, _f: &FileName) -> bool {
-        true
+    /// 生成 sourceContents，`sourceMap.vendor` 为 cheap 或 none 时 vendor 的源码除外
+    fn inline_sources_content(&self, f: &FileName) -> bool {
+        self.context.config.source_map.vendor == SourceMapFidelity::Full
+            || !is_vendor(&f.to_string(), self.context)
+    }
+}
+
+pub fn build_source_map_to_buf(
+    mappings: &[(BytePos, LineCol)],
+    cm: &Lrc<SourceMap>,
+    context: &Context,
+) -> Vec<u8> {
+    let sm = build_source_map(mappings, cm, context);
+
+    source_map_to_buf(&sm, context)
+}
+
+/// Serialize the source map with the `ignoreList` of the sources matched by
+/// `sourceMapIgnoreList`, whose frames are skipped by the debuggers.
+pub fn source_map_to_buf(sm: &swc_sourcemap::SourceMap, context: &Context) -> Vec<u8> {
+    let start = Instant::now();
+    let mut buf = vec![];
+
+    let optimized = optimize_source_map(sm, context.config.source_map.exclude_vendor_sources);
+    let sm = match &optimized {
+        Some((optimized, saved)) => {
+            context.stats_info.add_source_map_saved(*saved);
+            optimized
+        }
+        None => sm,
+    };
+    sm.to_writer(&mut buf).unwrap();
+
+    if let Some(SourceMapIgnoreListConfig::Globs(globs)) = &context.config.source_map_ignore_list {
+        let ignore_list = ignore_list(sm.sources(), globs, &context.root);
+        append_ignore_list(&mut buf, &ignore_list);
+    }
+
+    context.stats_info.add_source_map_duration(start.elapsed());
+    buf
+}
+
+#[derive(Serialize)]
+struct EncodedSourceMap<'a> {
+    version: u32,
+    sources: &'a [&'a str],
+    #[serde(rename = "sourcesContent", skip_serializing_if = "Option::is_none")]
+    sources_content: Option<&'a [Option<&'a str>]>,
+    names: &'a [&'a str],
+    mappings: &'a str,
+}
+
+/// Serialize the source map whose mappings are encoded already, e.g. from
+/// the mappings of the modules cached by `SourceMapCache`, in the same format
+/// as `source_map_to_buf`. Returns none if the sources must be deduped or
+/// their contents left out, which remaps the tokens.
+pub fn encoded_source_map_to_buf(
+    sources: &[&str],
+    sources_content: &[Option<&str>],
+    names: &[&str],
+    mappings: &str,
+    context: &Context,
+) -> Option<Vec<u8>> {
+    if needs_optimization(
+        sources,
+        sources_content,
+        context.config.source_map.exclude_vendor_sources,
+    ) {
+        return None;
+    }
+    let mut buf = vec![];
+    serde_json::to_writer(
+        &mut buf,
+        &EncodedSourceMap {
+            version: 3,
+            sources,
+            sources_content: sources_content
+                .iter()
+                .any(Option::is_some)
+                .then_some(sources_content),
+            names,
+            mappings,
+        },
+    )
+    .unwrap();
+
+    if let Some(SourceMapIgnoreListConfig::Globs(globs)) = &context.config.source_map_ignore_list {
+        let ignore_list = ignore_list(sources.iter().copied(), globs, &context.root);
+        append_ignore_list(&mut buf, &ignore_list);
+    }
+    Some(buf)
+}
+
+// the indices of the sources matched by the globs, the sources are absolute
+// paths or relative to the root
+fn ignore_list<'a>(
+    sources: impl Iterator<Item = &'a str>,
+    globs: &[String],
+    root: &Path,
+) -> Vec<u32> {
+    sources
+        .enumerate()
+        .filter(|(_, source)| matches_globs(source, globs, root))
+        .map(|(index, _)| index as u32)
+        .collect()
+}
+
+fn matches_globs(source: &str, globs: &[String], root: &Path) -> bool {
+    if globs.is_empty() {
+        return false;
+    }
+    let path = Path::new(source);
+    let relative = if path.is_absolute() {
+        diff_paths(path, root).unwrap_or(path.to_path_buf())
+    } else {
+        path.to_path_buf()
+    };
+    let relative = win_path(&relative.to_string_lossy());
+    globs
+        .iter()
+        .any(|glob| glob_match(glob.trim_start_matches("./"), &relative))
+}
+
+pub fn is_vendor_source(source: &str) -> bool {
+    source
+        .split(['/', '\\'])
+        .any(|segment| segment == "node_modules")
+}
+
+/// The sources in node_modules or matched by `sourceMap.vendorGlobs`, whose
+/// mappings are reduced by `sourceMap.vendor`.
+fn is_vendor(source: &str, context: &Context) -> bool {
+    is_vendor_source(source)
+        || matches_globs(
+            source,
+            &context.config.source_map.vendor_globs,
+            &context.root,
+        )
+}
+
+// the sources with the same path, or the contents of the sources in
+// node_modules to leave out
+fn needs_optimization(
+    sources: &[&str],
+    contents: &[Option<&str>],
+    exclude_vendor_sources: bool,
+) -> bool {
+    let has_duplicates = sources.iter().collect::<HashSet<_>>().len() < sources.len();
+    let has_vendor_contents = exclude_vendor_sources
+        && sources
+            .iter()
+            .zip(contents)
+            .any(|(source, content)| content.is_some() && is_vendor_source(source));
+    has_duplicates || has_vendor_contents
+}
+
+/// Dedupe the sources with the same path, which are merged from the modules,
+/// and leave out the `sourcesContent` of the sources in node_modules with
+/// `sourceMap.excludeVendorSources`. Returns the map and the bytes of the
+/// contents saved, or none if there's nothing to change.
+fn optimize_source_map(
+    sm: &swc_sourcemap::SourceMap,
+    exclude_vendor_sources: bool,
+) -> Option<(swc_sourcemap::SourceMap, u64)> {
+    let sources = sm.sources().collect::<Vec<_>>();
+    let contents = (0..sources.len() as u32)
+        .map(|index| sm.get_source_contents(index))
+        .collect::<Vec<_>>();
+    if !needs_optimization(&sources, &contents, exclude_vendor_sources) {
+        return None;
     }
+
+    let mut saved = 0;
+    let mut indexes = HashMap::new();
+    let mut deduped_sources: Vec<Arc<str>> = vec![];
+    let mut deduped_contents: Vec<Option<Arc<str>>> = vec![];
+    // old index -> new index
+    let remapped = sources
+        .iter()
+        .zip(&contents)
+        .map(|(source, content)| {
+            let content_len = content.map_or(0, |content| content.len() as u64);
+            if let Some(index) = indexes.get(source) {
+                saved += content_len;
+                return *index;
+            }
+            let index = deduped_sources.len() as u32;
+            indexes.insert(*source, index);
+            deduped_sources.push((*source).into());
+            if exclude_vendor_sources && is_vendor_source(source) {
+                saved += content_len;
+                deduped_contents.push(None);
+            } else {
+                deduped_contents.push(content.map(Into::into));
+            }
+            index
+        })
+        .collect::<Vec<_>>();
+    let tokens = sm
+        .tokens()
+        .map(|token| {
+            let mut raw = token.get_raw_token();
+            if let Some(index) = remapped.get(raw.src_id as usize) {
+                raw.src_id = *index;
+            }
+            raw
+        })
+        .collect();
+    let optimized = swc_sourcemap::SourceMap::new(
+        sm.get_file().map(Into::into),
+        tokens,
+        sm.names().map(Into::into).collect(),
+        deduped_sources,
+        Some(deduped_contents),
+    );
+    Some((optimized, saved))
 }
 
-pub fn build_source_map_to_buf(mappings: &[(BytePos, LineCol)], cm: &Lrc<SourceMap>) -> Vec<u8> {
-    let sm = build_source_map(mappings, cm);
+/// Split the map to an index map with `sourceMap.indexMap`, in which the
+/// mappings of each source in node_modules are a section of its own, written
+/// to a file named by the hash of its content. So the same module in
+/// multiple chunks, which has the same mappings relative to the section, is
+/// written once and referenced by the `url` of the sections. The mappings of
+/// the other sources are kept in the index map as the `map` of the sections.
+/// The names of the shared files are relative to the output, and the urls are
+/// prefixed with `url_prefix` to be relative to the index map.
+pub fn split_index_map(
+    sm: &swc_sourcemap::SourceMap,
+    url_prefix: &str,
+    context: &Context,
+) -> (Vec<u8>, Vec<(String, Vec<u8>)>) {
+    // the tokens of each section, the vendor source of them or none
+    let mut sections: Vec<(Option<&str>, Vec<swc_sourcemap::Token>)> = vec![];
+    for token in sm.tokens() {
+        let vendor = token.get_source().filter(|source| is_vendor_source(source));
+        match sections.last_mut() {
+            Some((last, tokens)) if *last == vendor => tokens.push(token),
+            _ => sections.push((vendor, vec![token])),
+        }
+    }
+
+    let mut shared = vec![];
+    let sections = sections
+        .into_iter()
+        .map(|(vendor, tokens)| {
+            let (line, col) = (tokens[0].get_dst_line(), tokens[0].get_dst_col());
+            let mut builder = swc_sourcemap::SourceMapBuilder::new(None);
+            for token in tokens {
+                let dst_line = token.get_dst_line() - line;
+                let dst_col = if dst_line == 0 {
+                    token.get_dst_col() - col
+                } else {
+                    token.get_dst_col()
+                };
+                let added = builder.add(
+                    dst_line,
+                    dst_col,
+                    token.get_src_line(),
+                    token.get_src_col(),
+                    token.get_source(),
+                    token.get_name(),
+                    false,
+                );
+                if !builder.has_source_contents(added.src_id) {
+                    builder.set_source_contents(
+                        added.src_id,
+                        token.get_source_view().map(|view| view.source()),
+                    );
+                }
+            }
+            let buf = source_map_to_buf(&builder.into_sourcemap(), context);
+            let offset = format!(r#"{{"offset":{{"line":{},"column":{}}},"#, line, col);
+            if vendor.is_none() {
+                return [offset.as_bytes(), b"\"map\":", &buf, b"}"].concat();
+            }
+            let name = format!("{}/{}.map", SHARED_SOURCE_MAPS_DIR, content_hash(&buf));
+            let url = serde_json::to_string(&format!("{}{}", url_prefix, name)).unwrap();
+            shared.push((name, buf));
+            format!(r#"{}"url":{}}}"#, offset, url).into_bytes()
+        })
+        .collect::<Vec<_>>();
+
+    // the sourcemap crate writes the absent fields of the sections as null
+    let mut index_map = br#"{"version":3,"#.to_vec();
+    if let Some(file) = sm.get_file() {
+        index_map.extend(format!(r#""file":{},"#, serde_json::to_string(file).unwrap()).bytes());
+    }
+    index_map.extend_from_slice(br#""sections":["#);
+    index_map.extend_from_slice(&sections.join(&b','));
+    index_map.extend_from_slice(b"]}");
+    (index_map, shared)
+}
 
-    let mut src_buf = vec![];
+/// The directory of the shared sections of the index maps in the output.
+pub const SHARED_SOURCE_MAPS_DIR: &str = "_sourcemaps";
 
-    sm.to_writer(&mut src_buf).unwrap();
+fn content_hash(content: &[u8]) -> String {
+    let mut hasher: XxHash64 = Default::default();
+    hasher.write(content);
+    format!("{:016x}", hasher.finish())
+}
 
-    src_buf
+// the sourcemap crate doesn't write the ignore list, so it's added to the end
+// of the serialized object, `x_google_ignoreList` is the name before it's
+// standardized, which is read by the older devtools
+fn append_ignore_list(buf: &mut Vec<u8>, ignore_list: &[u32]) {
+    if ignore_list.is_empty() || buf.last() != Some(&b'}') {
+        return;
+    }
+    let list = serde_json::to_string(ignore_list).unwrap();
+    buf.pop();
+    buf.extend_from_slice(
+        format!(r#","ignoreList":{list},"x_google_ignoreList":{list}}}"#).as_bytes(),
+    );
 }
 
 pub fn build_source_map(
     mappings: &[(BytePos, LineCol)],
     cm: &Lrc<SourceMap>,
+    context: &Context,
 ) -> swc_sourcemap::SourceMap {
-    let config = SwcSourceMapGenConfig;
+    crate::mako_profile_function!();
+    let start = Instant::now();
+    let config = SwcSourceMapGenConfig { context };
+
+    let sm = match context.config.source_map.vendor {
+        SourceMapFidelity::Full => cm.build_source_map_with_config(mappings, None, config),
+        fidelity => {
+            let (mappings, unmapped) = reduce_vendor_mappings(mappings, cm, fidelity, context);
+            let sm = cm.build_source_map_with_config(&mappings, None, config);
+            add_unmapped_tokens(sm, &unmapped)
+        }
+    };
+    context.stats_info.add_source_map_duration(start.elapsed());
+    sm
+}
 
-    cm.build_source_map_with_config(mappings, None, config)
+/// Reduce the mappings of the vendor sources with `sourceMap.vendor`,
+/// `cheap` keeps the first mapping of each generated line in a run of the
+/// mappings of a vendor source, and `none` keeps none of them. Returns the
+/// mappings kept and the generated positions where the runs of `none`
+/// start, which must end the mapping of the code before them, or the code
+/// of the vendor source is mapped to the previous source in the same line,
+/// e.g. in the minified chunks.
+fn reduce_vendor_mappings(
+    mappings: &[(BytePos, LineCol)],
+    cm: &Lrc<SourceMap>,
+    fidelity: SourceMapFidelity,
+    context: &Context,
+) -> (Vec<(BytePos, LineCol)>, Vec<LineCol>) {
+    let mut kept = Vec::with_capacity(mappings.len());
+    let mut unmapped = vec![];
+    // the range of the last source file and whether it's a vendor source
+    let mut file: Option<(BytePos, BytePos, bool)> = None;
+    // the start of the vendor source of the current run, and the generated
+    // line of the last mapping kept in it
+    let mut run: Option<(BytePos, Option<u32>)> = None;
+    for (pos, lc) in mappings {
+        if pos.is_reserved_for_comments() || pos.is_dummy() || *pos == BytePos(u32::MAX) {
+            kept.push((*pos, *lc));
+            continue;
+        }
+        let (start, vendor) = match file {
+            Some((start, end, vendor)) if start <= *pos && *pos < end => (start, vendor),
+            _ => match cm.try_lookup_source_file(*pos) {
+                Ok(f) => {
+                    let vendor = is_vendor(&f.name.to_string(), context);
+                    file = Some((f.start_pos, f.end_pos, vendor));
+                    (f.start_pos, vendor)
+                }
+                Err(_) => {
+                    kept.push((*pos, *lc));
+                    continue;
+                }
+            },
+        };
+        if !vendor {
+            run = None;
+            kept.push((*pos, *lc));
+            continue;
+        }
+        let last_line = match run {
+            Some((run_start, last_line)) if run_start == start => last_line,
+            _ => {
+                if fidelity == SourceMapFidelity::None {
+                    unmapped.push(*lc);
+                }
+                None
+            }
+        };
+        if fidelity == SourceMapFidelity::Cheap && last_line != Some(lc.line) {
+            kept.push((*pos, *lc));
+            run = Some((start, Some(lc.line)));
+        } else {
+            run = Some((start, last_line));
+        }
+    }
+    (kept, unmapped)
+}
+
+// the tokens without source at the generated positions, the tokens of the
+// same positions are kept
+fn add_unmapped_tokens(
+    sm: swc_sourcemap::SourceMap,
+    unmapped: &[LineCol],
+) -> swc_sourcemap::SourceMap {
+    if unmapped.is_empty() {
+        return sm;
+    }
+    let mut tokens = sm
+        .tokens()
+        .map(|token| token.get_raw_token())
+        .collect::<Vec<_>>();
+    tokens.extend(unmapped.iter().map(|lc| swc_sourcemap::RawToken {
+        dst_line: lc.line,
+        dst_col: lc.col,
+        src_line: 0,
+        src_col: 0,
+        src_id: !0,
+        name_id: !0,
+        is_range: false,
+    }));
+    tokens.sort_by_key(|token| (token.dst_line, token.dst_col));
+    tokens.dedup_by(|b, a| (a.dst_line, a.dst_col) == (b.dst_line, b.dst_col));
+    swc_sourcemap::SourceMap::new(
+        sm.get_file().map(Into::into),
+        tokens,
+        sm.names().map(Into::into).collect(),
+        sm.sources().map(Into::into).collect(),
+        Some(
+            sm.source_contents()
+                .map(|content| content.map(Into::into))
+                .collect(),
+        ),
+    )
 }
 
 // Add this type because the sourcemap::SourceMap type can't be cached,
@@ -50,6 +474,19 @@ pub struct RawSourceMap {
     pub sources_content: Vec<Option<String>>,
 }
 
+impl RawSourceMap {
+    pub fn estimated_bytes(&self) -> usize {
+        let strings = self
+            .names
+            .iter()
+            .chain(&self.sources)
+            .chain(self.sources_content.iter().flatten())
+            .map(String::len)
+            .sum::<usize>();
+        self.tokens.len() * std::mem::size_of::<swc_sourcemap::RawToken>() + strings
+    }
+}
+
 impl From<swc_sourcemap::SourceMap> for RawSourceMap {
     fn from(sm: swc_sourcemap::SourceMap) -> Self {
         Self {
@@ -155,10 +592,17 @@ pub fn merge_source_map(
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use std::str::FromStr;
+    use std::sync::Arc;
 
-    use crate::ast::sourcemap::{merge_source_map, swc_sourcemap};
+    use crate::ast::sourcemap::{
+        append_ignore_list, ignore_list, merge_source_map, optimize_source_map, swc_sourcemap,
+        SHARED_SOURCE_MAPS_DIR,
+    };
+    use crate::testing::compile_fixture;
+    use crate::utils::output_fs::MemoryFileSystem;
+    use crate::utils::test_helper::setup_compiler_with_output_fs;
 
     #[test]
     fn test_merge_empty_chain() {
@@ -249,4 +693,216 @@ mod test {
 
         assert!(merged.eq(r#"{"version":3,"sources":["index.ts"],"sourcesContent":["function sayHello(name: string) {\n  console.log(`Hello, ${name}`);\n}\n"],"names":[],"mappings":"AAAA,SAAS,SAAS,CAAY,EAC5B,QAAQ,GAAG,CAAC,UAAA,MAAA,CAAU,GACxB"}"#));
     }
+
+    #[test]
+    fn test_ignore_list() {
+        let root = PathBuf::from("/project");
+        let globs = vec!["**/node_modules/**".to_string(), "./vendor/**".to_string()];
+        let sources = [
+            "/project/src/index.ts",
+            "/project/node_modules/react/index.js",
+            "/node_modules/outside/index.js",
+            "vendor/jquery.js",
+            "src/node_modules.ts",
+            "../../node_modules/hoisted/index.js",
+        ];
+        assert_eq!(
+            ignore_list(sources.into_iter(), &globs, &root),
+            vec![1, 2, 3, 5]
+        );
+    }
+
+    #[test]
+    fn test_append_ignore_list() {
+        let mut buf = br#"{"version":3,"sources":["a.js","b.js"]}"#.to_vec();
+        append_ignore_list(&mut buf, &[1]);
+        assert_eq!(
+            String::from_utf8(buf.clone()).unwrap(),
+            r#"{"version":3,"sources":["a.js","b.js"],"ignoreList":[1],"x_google_ignoreList":[1]}"#
+        );
+        let sm = swc_sourcemap::SourceMap::from_slice(&buf).unwrap();
+        assert_eq!(sm.get_source(1), Some("b.js"));
+
+        let mut buf = br#"{"version":3,"sources":["a.js"]}"#.to_vec();
+        append_ignore_list(&mut buf, &[]);
+        assert_eq!(buf, br#"{"version":3,"sources":["a.js"]}"#);
+    }
+
+    #[test]
+    fn test_optimize_source_map() {
+        let sm = swc_sourcemap::SourceMap::from_slice(
+            br#"{
+                "version": 3,
+                "sources": ["src/a.js", "node_modules/pkg/index.js", "src/a.js"],
+                "sourcesContent": ["a", "pkg", "a"],
+                "names": [],
+                "mappings": "AAAA,CCAA,CCAA"
+            }"#,
+        )
+        .unwrap();
+
+        let (deduped, saved) = optimize_source_map(&sm, false).unwrap();
+        assert_eq!(
+            deduped.sources().collect::<Vec<_>>(),
+            vec!["src/a.js", "node_modules/pkg/index.js"]
+        );
+        assert_eq!(saved, 1);
+        let sources = deduped
+            .tokens()
+            .map(|token| token.get_source().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            vec!["src/a.js", "node_modules/pkg/index.js", "src/a.js"]
+        );
+
+        let (excluded, saved) = optimize_source_map(&sm, true).unwrap();
+        assert_eq!(excluded.get_source_contents(0), Some("a"));
+        assert_eq!(excluded.get_source_contents(1), None);
+        assert_eq!(saved, 4);
+
+        let (deduped, _) = optimize_source_map(&sm, false).unwrap();
+        assert!(optimize_source_map(&deduped, false).is_none());
+    }
+
+    #[test]
+    fn test_index_map() {
+        let output_fs = Arc::new(MemoryFileSystem::new());
+        let compiler = setup_compiler_with_output_fs("test/build/source-map", output_fs);
+        compiler.compile().unwrap();
+        let context = &compiler.context;
+        let read = |name: &str| -> serde_json::Value {
+            serde_json::from_slice(
+                &context
+                    .output_fs
+                    .read(&context.config.output.path.join(name))
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+        let shared_urls = |name: &str| {
+            read(name)["sections"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|section| section["url"].as_str().map(str::to_string))
+                .collect::<Vec<_>>()
+        };
+
+        // the vendor module in both chunks is written once
+        let urls = shared_urls("a.js.map");
+        assert_eq!(urls.len(), 1, "{:?}", urls);
+        assert!(urls[0].starts_with(SHARED_SOURCE_MAPS_DIR), "{:?}", urls);
+        assert_eq!(shared_urls("b.js.map"), urls);
+        let shared = read(&urls[0]);
+        assert_eq!(
+            shared["sources"],
+            serde_json::json!(["node_modules/vendor-pkg/index.js"])
+        );
+        // no sourcesContent without the contents
+        assert_eq!(shared["sourcesContent"], serde_json::Value::Null);
+
+        // the project sources are kept in the index map
+        let a = read("a.js.map");
+        let inline = a["sections"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|section| section.get("map"))
+            .collect::<Vec<_>>();
+        assert!(inline.iter().any(|map| map["sources"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("src/a.js"))));
+        // resolved the same as the map before it's split
+        let index =
+            swc_sourcemap::SourceMapIndex::from_slice(&serde_json::to_vec(&a).unwrap()).unwrap();
+        assert!(index.get_section_count() > 1);
+        assert!(context.stats_info.source_maps.lock().unwrap().saved > 0);
+    }
+
+    #[test]
+    fn test_vendor_fidelity() {
+        const VENDOR: &str = "node_modules/vendor-pkg/index.js";
+        let build = |mode: &str, vendor: &str, vendor_globs: &str| {
+            let edit = format!(
+                r#"{{"mode":"{}","minify":{},"hash":false,"sourceMap":{{"excludeVendorSources":false,"indexMap":false,"vendor":"{}","vendorGlobs":{}}},"sourceMapIgnoreList":["node_modules/**"]}}"#,
+                mode,
+                mode == "production",
+                vendor,
+                vendor_globs
+            );
+            let compiler =
+                compile_fixture(Path::new("test/build/source-map"), Some(&edit)).unwrap();
+            let context = &compiler.context;
+            let buf = context
+                .output_fs
+                .read(&context.config.output.path.join("a.js.map"))
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+            (swc_sourcemap::SourceMap::from_slice(&buf).unwrap(), json)
+        };
+        let vendor_tokens = |sm: &swc_sourcemap::SourceMap| {
+            sm.tokens()
+                .filter(|token| token.get_source() == Some(VENDOR))
+                .map(|token| token.get_dst_line())
+                .collect::<Vec<_>>()
+        };
+
+        for mode in ["development", "production"] {
+            let (full, full_json) = build(mode, "full", "[]");
+            let (cheap, cheap_json) = build(mode, "cheap", "[]");
+            let (none, none_json) = build(mode, "none", "[]");
+            let vendor_index = |json: &serde_json::Value| {
+                json["sources"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .position(|source| source == VENDOR)
+            };
+
+            let full_index = vendor_index(&full_json).unwrap();
+            assert!(full.get_source_contents(full_index as u32).is_some());
+            // one mapping per generated line of the vendor code
+            let cheap_index = vendor_index(&cheap_json).unwrap();
+            assert!(cheap.get_source_contents(cheap_index as u32).is_none());
+            let lines = vendor_tokens(&cheap);
+            let mut unique = lines.clone();
+            unique.dedup();
+            assert_eq!(lines, unique, "{}", mode);
+            assert!(lines.len() < vendor_tokens(&full).len(), "{}", mode);
+            // the ignore list applies to the cheap sources too
+            assert_eq!(
+                cheap_json["ignoreList"],
+                serde_json::json!([cheap_index]),
+                "{}",
+                mode
+            );
+
+            // the vendor code is not mapped to the code before it
+            assert!(vendor_index(&none_json).is_none(), "{}", mode);
+            assert!(none_json["sources"]
+                .as_array()
+                .unwrap()
+                .contains(&serde_json::json!("src/a.js")));
+            let unmapped = none.tokens().filter(|token| token.get_source().is_none());
+            assert!(unmapped.count() > 0, "{}", mode);
+            let first_vendor = full
+                .tokens()
+                .find(|token| token.get_source() == Some(VENDOR))
+                .unwrap();
+            let (line, col) = (first_vendor.get_dst_line(), first_vendor.get_dst_col());
+            assert_eq!(
+                none.lookup_token(line, col)
+                    .and_then(|token| token.get_source()),
+                None,
+                "{}",
+                mode
+            );
+        }
+
+        // the globs of the vendor sources
+        let (sm, _) = build("development", "none", r#"["src/a.js"]"#);
+        assert!(sm.sources().all(|source| source != "src/a.js"));
+    }
 }
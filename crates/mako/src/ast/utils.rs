@@ -1,7 +1,7 @@
-use swc_core::common::{Mark, DUMMY_SP};
+use swc_core::common::{Mark, SyntaxContext, DUMMY_SP};
 use swc_core::ecma::ast::{
-    CallExpr, Callee, Expr, ExprOrSpread, Ident, IdentName, Import, Lit, MemberExpr, MemberProp,
-    MetaPropExpr, MetaPropKind, Module, ModuleItem,
+    CallExpr, Callee, Expr, ExprOrSpread, ExprStmt, Ident, IdentName, Import, Lit, MemberExpr,
+    MemberProp, MetaPropExpr, MetaPropKind, Module, ModuleItem, Stmt, Str,
 };
 
 use crate::module::{ModuleAst, ModuleSystem};
@@ -23,6 +23,22 @@ pub fn is_remote_or_data_or_hash(url: &str) -> bool {
         // css url() should not resolve hash only url
         // e.g. fill: url(#gradient)
         || lower_url.starts_with('#')
+        || has_url_scheme(url)
+}
+
+// e.g. blob:, about:blank, chrome-extension://
+// single letter is not a scheme but a windows drive, e.g. C:/a.png
+fn has_url_scheme(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
 }
 
 pub fn remove_first_tilde(url: String) -> String {
@@ -45,6 +61,21 @@ pub fn is_esm(module: &Module) -> bool {
         .any(|item| matches!(item, ModuleItem::ModuleDecl(_)))
 }
 
+// the directives at the top of the module, e.g. "use client"
+pub fn get_directives(module: &Module) -> Vec<String> {
+    module
+        .body
+        .iter()
+        .map_while(|item| match item {
+            ModuleItem::Stmt(Stmt::Expr(ExprStmt {
+                expr: box Expr::Lit(Lit::Str(Str { value, .. })),
+                ..
+            })) => Some(value.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
 pub fn is_dynamic_import(call_expr: &CallExpr) -> bool {
     matches!(&call_expr.callee, Callee::Import(Import { .. }))
 }
@@ -53,7 +84,24 @@ pub fn is_commonjs_require(call_expr: &CallExpr, unresolved_mark: &Mark) -> bool
     if let Some(ident) = get_call_expr_ident(call_expr) {
         is_ident_undefined(ident, "require", unresolved_mark)
         // TODO: remove this, it's special logic
-        || is_ident(ident, "__mako_require__")
+        // the ones injected by the transforms, but not the ones bound in the
+        // code bundled by mako, e.g. a pre-bundled dependency
+        || (is_ident(ident, "__mako_require__")
+            && (ident.ctxt.outer() == *unresolved_mark || ident.ctxt == SyntaxContext::empty()))
+    } else {
+        false
+    }
+}
+
+// e.g. `require.resolve('./a')`
+pub fn is_commonjs_require_resolve(call_expr: &CallExpr, unresolved_mark: &Mark) -> bool {
+    if let Callee::Expr(box Expr::Member(MemberExpr {
+        obj: box Expr::Ident(ident),
+        prop: MemberProp::Ident(IdentName { sym, .. }),
+        ..
+    })) = &call_expr.callee
+    {
+        is_ident_undefined(ident, "require", unresolved_mark) && sym == "resolve"
     } else {
         false
     }
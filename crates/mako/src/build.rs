@@ -1,5 +1,6 @@
 pub(crate) mod analyze_deps;
 pub(crate) mod load;
+pub(crate) mod module_kind;
 pub(crate) mod parse;
 pub(crate) mod targets;
 pub(crate) mod transform;
@@ -10,16 +11,20 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use colored::Colorize;
+use swc_core::ecma::ast::ModuleItem;
 use thiserror::Error;
 
+use crate::ast::error;
 use crate::ast::file::{Content, File, JsContent};
-use crate::ast::utils::get_module_system;
+use crate::ast::js_ast::JsAst;
+use crate::ast::utils::{get_directives, get_module_system};
 use crate::compiler::{Compiler, Context};
-use crate::generate::chunk_pot::util::hash_hashmap;
+use crate::generate::chunk_pot::util::hash_vec;
 use crate::module::{Module, ModuleAst, ModuleId, ModuleInfo};
-use crate::plugin::NextBuildParam;
+use crate::plugin::{NextBuildParam, PluginReplaceByDirectiveParam};
 use crate::resolve::ResolverResource;
 use crate::utils::thread_pool;
+use crate::visitors::eval_detector::EvalDetector;
 
 #[derive(Debug, Error)]
 pub enum BuildError {
@@ -41,8 +46,8 @@ impl Compiler {
             let rs = rs.clone();
             let context = self.context.clone();
             thread_pool::spawn(move || {
-                let result = Self::build_module(&file, parent_resource, context.clone());
-                let result = Self::handle_build_result(result, &file, context);
+                let result = Self::build_module(&file, parent_resource.clone(), context.clone());
+                let result = Self::handle_build_result(result, &file, parent_resource, context);
                 rs.send(result).unwrap();
             });
         };
@@ -67,7 +72,8 @@ impl Compiler {
                     continue;
                 }
             }
-            let module = build_result.unwrap();
+            let mut module = build_result.unwrap();
+            module.id.intern(&self.context.interner);
             let module_id = module.id.clone();
 
             // update context.modules_with_missing_deps (watch only)
@@ -79,11 +85,15 @@ impl Compiler {
                         .unwrap()
                         .retain(|id| id != &module_id.id);
                 } else {
-                    self.context
-                        .modules_with_missing_deps
-                        .write()
-                        .unwrap()
-                        .push(module_id.id.clone());
+                    // the module is rebuilt with the same missing deps
+                    let mut modules_with_missing_deps =
+                        self.context.modules_with_missing_deps.write().unwrap();
+                    if !modules_with_missing_deps
+                        .iter()
+                        .any(|id| *id == module_id.id)
+                    {
+                        modules_with_missing_deps.push(module_id.id.to_string());
+                    }
                 }
             }
 
@@ -105,7 +115,7 @@ impl Compiler {
                 let path = dep.resolver_resource.get_resolved_path();
                 let dep_module_id = ModuleId::new(path.clone());
                 if !module_graph.has_module(&dep_module_id) {
-                    let module = match dep.resolver_resource {
+                    let mut module = match dep.resolver_resource {
                         ResolverResource::Virtual(_) | ResolverResource::Resolved(_) => {
                             let file = File::new(path.clone(), self.context.clone());
 
@@ -131,6 +141,7 @@ impl Compiler {
 
                     // 拿到依赖之后需要直接添加 module 到 module_graph 里，不能等依赖 build 完再添加
                     // 是因为由于是异步处理各个模块，后者会导致大量重复任务的 build_module 任务（3 倍左右）
+                    module.id.intern(&self.context.interner);
                     module_ids.insert(module.id.clone());
                     module_graph.add_module(module);
                 }
@@ -146,6 +157,9 @@ impl Compiler {
             return Err(anyhow::anyhow!(BuildError::BuildTasksError { errors }));
         }
 
+        // the graph changes queued by the plugins during the build
+        module_ids.extend(self.apply_graph_mutations()?);
+
         Ok(module_ids)
     }
 
@@ -158,7 +172,8 @@ impl Compiler {
             // safe
             .unwrap();
         let external_script = resolved_resource.get_script();
-        let is_async = external_script.is_some();
+        // the externals with script or the module type export a promise
+        let is_async = external_script.is_some() || external_name.starts_with("import(");
         let origin_path = resolved_resource.get_resolved_path();
         let path = format!("virtual:external_{}", origin_path);
         let mut file = File::new(path.clone(), context.clone());
@@ -196,27 +211,80 @@ __mako_require__.loadScript('{}', (e) => e.type === 'load' ? resolve() : reject(
         Module::new(module_id, false, Some(info))
     }
 
-    fn create_error_module(file: &File, err: String, context: Arc<Context>) -> Result<Module> {
+    fn create_error_module(
+        file: &File,
+        err: anyhow::Error,
+        parent_resource: Option<ResolverResource>,
+        context: Arc<Context>,
+    ) -> Result<Module> {
+        let is_syntax_error = matches!(
+            err.downcast_ref::<error::ParseError>(),
+            Some(error::ParseError::JsParseError { .. })
+        );
+        let err = err.to_string();
+        // the imports parsed before the syntax error are kept as the deps, so
+        // the graph is roughly the same while the error is being fixed
+        let imports = if is_syntax_error {
+            Self::parse_partial_imports(file, parent_resource.as_ref(), &context)
+        } else {
+            vec![]
+        };
         let mut file = file.clone();
-        let code = format!("throw new Error(`Module build failed:\n{:}`)", err);
+        // the code frame of the error may contain backticks
+        let code = format!(
+            "throw new Error({});",
+            serde_json::to_string(&format!("Module build failed:\n{}", err))?
+        );
         file.set_content(Content::Js(JsContent {
             content: code,
             ..Default::default()
         }));
-        let ast = parse::Parse::parse(&file, context.clone())?;
+        let mut ast = parse::Parse::parse(&file, context.clone())?;
+        let deps = if let ModuleAst::Script(script) = &mut ast
+            && !imports.is_empty()
+        {
+            script.ast.body.splice(0..0, imports);
+            analyze_deps::AnalyzeDeps::analyze_deps(&ast, &file, context.clone())?
+        } else {
+            Default::default()
+        };
         let path = file.path.to_string_lossy().to_string();
         let module_id = ModuleId::new(path.clone());
         let raw = file.get_content_raw();
         let info = ModuleInfo {
             file,
+            deps,
             module_system: get_module_system(&ast),
             ast,
+            resolved_resource: parent_resource,
             raw,
+            diagnostics: vec![err],
             ..Default::default()
         };
         Ok(Module::new(module_id, false, Some(info)))
     }
 
+    fn parse_partial_imports(
+        file: &File,
+        parent_resource: Option<&ResolverResource>,
+        context: &Arc<Context>,
+    ) -> Vec<ModuleItem> {
+        let mut file = file.clone();
+        let content = load::Load::load(&file, context.clone()).and_then(|mut content| {
+            context.plugin_driver.load_transform(
+                &mut content,
+                &file.path.to_string_lossy(),
+                context,
+            )
+        });
+        let Ok(content @ Content::Js(_)) = content else {
+            return vec![];
+        };
+        file.set_content(content);
+        file.module_kind = module_kind::detect_module_kind(&file, parent_resource, context);
+        JsAst::parse_partial_imports(&file, context.clone())
+    }
+
     fn create_ignored_module(path: &str, context: Arc<Context>) -> Module {
         let module_id = ModuleId::new(path.to_owned());
 
@@ -254,17 +322,14 @@ __mako_require__.loadScript('{}', (e) => e.type === 'load' ? resolve() : reject(
     pub fn handle_build_result(
         result: Result<Module>,
         file: &File,
+        parent_resource: Option<ResolverResource>,
         context: Arc<Context>,
     ) -> Result<Module> {
-        if result.is_err() && context.args.watch {
-            let module = Self::create_error_module(
-                file,
-                result.err().unwrap().to_string(),
-                context.clone(),
-            )?;
-            Ok(module)
-        } else {
-            result
+        match result {
+            Err(err) if context.args.watch => {
+                Self::create_error_module(file, err, parent_resource, context)
+            }
+            result => result,
         }
     }
 
@@ -282,15 +347,43 @@ __mako_require__.loadScript('{}', (e) => e.type === 'load' ? resolve() : reject(
             &context,
         )?;
         file.set_content(content);
+        file.module_kind =
+            module_kind::detect_module_kind(&file, parent_resource.as_ref(), &context);
 
         // 2. parse
         let mut ast = parse::Parse::parse(&file, context.clone())?;
+        let directives = Self::get_directives(&ast, &context);
+        if !directives.is_empty() {
+            let param = PluginReplaceByDirectiveParam {
+                file: &file,
+                directives: &directives,
+            };
+            if let Some(content) = context
+                .plugin_driver
+                .replace_by_directive(&param, &context)?
+            {
+                file.set_content(content);
+                ast = parse::Parse::parse(&file, context.clone())?;
+            }
+        }
+
+        // direct eval and new Function of the source, before the transforms
+        let bailouts = match &ast {
+            ModuleAst::Script(ast) => EvalDetector::detect(&ast.ast),
+            _ => vec![],
+        };
 
         // 3. transform
         transform::Transform::transform(&mut ast, &file, context.clone())?;
 
         // 4. analyze deps + resolve
         let deps = analyze_deps::AnalyzeDeps::analyze_deps(&ast, &file, context.clone())?;
+        let mut build_dependencies = vec![];
+        if let (ModuleAst::Script(_), Some(tsconfig)) = (&ast, &context.config.resolve.tsconfig)
+            && !(deps.resolved_deps.is_empty() && deps.missing_deps.is_empty())
+        {
+            build_dependencies.push(tsconfig.clone());
+        }
 
         // 5. create module
         let path = file.path.to_string_lossy().to_string();
@@ -308,25 +401,392 @@ __mako_require__.loadScript('{}', (e) => e.type === 'load' ? resolve() : reject(
         // raw_hash is only used in watch mode
         // so we don't need to calculate when watch is off
         let raw_hash = if context.args.watch {
+            // by the sources only, the spans of the deps vary with the order
+            // in which the files are parsed
+            let mut missing_deps = deps.missing_deps.keys().collect::<Vec<_>>();
+            missing_deps.sort();
             file.get_raw_hash()
-                .wrapping_add(hash_hashmap(&deps.missing_deps))
+                .wrapping_add(hash_vec(&missing_deps))
+                .wrapping_add(
+                    context
+                        .module_rules
+                        .matched(&file.pathname, &context.root)
+                        .hash(),
+                )
         } else {
             0
         };
+        let deps_hash = if context.args.watch {
+            deps.requests_hash()
+        } else {
+            0
+        };
+        let module_system = file
+            .module_kind
+            .map(Into::into)
+            .unwrap_or_else(|| get_module_system(&ast));
         let info = ModuleInfo {
             file,
             deps,
-            module_system: get_module_system(&ast),
+            module_system,
             ast,
             resolved_resource: parent_resource,
             source_map_chain,
             top_level_await,
             is_async,
             raw_hash,
+            deps_hash,
             raw,
+            directives,
+            build_dependencies,
+            bailouts,
             ..Default::default()
         };
         let module = Module::new(module_id, is_entry, Some(info));
         Ok(module)
     }
+
+    // directives of config.moduleDirectives, e.g. "use client"
+    fn get_directives(ast: &ModuleAst, context: &Arc<Context>) -> Vec<String> {
+        match ast {
+            ModuleAst::Script(ast) if !context.config.module_directives.is_empty() => {
+                get_directives(&ast.ast)
+                    .into_iter()
+                    .filter(|directive| context.config.module_directives.contains(directive))
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use anyhow::Result;
+
+    use crate::ast::file::{Content, JsContent};
+    use crate::compiler::{Args, Compiler, Context};
+    use crate::config::Config;
+    use crate::generate::chunk::ChunkType;
+    use crate::module::{ModuleId, ModuleSystem};
+    use crate::plugin::{Plugin, PluginReplaceByDirectiveParam};
+    use crate::testing::compile_fixture;
+    use crate::utils::output_fs::MemoryFileSystem;
+    use crate::utils::test_helper::{
+        get_module, setup_compiler_with_output_fs, setup_compiler_with_plugins,
+    };
+
+    struct ReplaceServerPlugin;
+
+    impl Plugin for ReplaceServerPlugin {
+        fn name(&self) -> &str {
+            "replace_server"
+        }
+
+        fn replace_by_directive(
+            &self,
+            param: &PluginReplaceByDirectiveParam,
+            _context: &Arc<Context>,
+        ) -> Result<Option<Content>> {
+            if !param.directives.iter().any(|d| d == "use server") {
+                return Ok(None);
+            }
+            Ok(Some(Content::Js(JsContent {
+                content: "export const save = () => 'server reference';".to_string(),
+                is_jsx: false,
+            })))
+        }
+    }
+
+    #[test]
+    fn test_module_directives() {
+        let compiler = setup_compiler_with_plugins(
+            "test/build/module-directives",
+            vec![Arc::new(ReplaceServerPlugin)],
+        );
+        compiler.compile().unwrap();
+        let root = &compiler.context.root;
+        let module_graph = compiler.context.module_graph.read().unwrap();
+
+        let client_modules = module_graph
+            .modules_with_directive("use client")
+            .iter()
+            .map(|module| module.id.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(client_modules, vec![ModuleId::from(root.join("client.js"))]);
+        let client = module_graph
+            .get_module(&ModuleId::from(root.join("client.js")))
+            .unwrap();
+        assert_eq!(client.info.as_ref().unwrap().directives, vec!["use client"]);
+
+        // deps of the replaced content are analyzed instead
+        assert_eq!(module_graph.modules_with_directive("use server").len(), 1);
+        assert!(module_graph
+            .get_module(&ModuleId::from(root.join("db.js")))
+            .is_none());
+        drop(module_graph);
+
+        let index = compiler
+            .context
+            .output_fs
+            .read(&compiler.context.config.output.path.join("index.js"))
+            .unwrap();
+        let index = String::from_utf8(index).unwrap();
+        assert!(index.contains("server reference"));
+    }
+
+    #[test]
+    fn test_partial_imports_of_syntax_error() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/partial-imports");
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler = Compiler::new_with_output_fs(
+            config,
+            root.clone(),
+            Args { watch: true },
+            None,
+            Some(Arc::new(MemoryFileSystem::new())),
+        )
+        .unwrap();
+        compiler.compile().unwrap();
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let broken = ModuleId::from(root.join("broken.ts"));
+        let mut deps = module_graph
+            .get_dependencies(&broken)
+            .iter()
+            .map(|(id, _)| id.id.trim_start_matches(root.to_str().unwrap()).to_string())
+            .collect::<Vec<_>>();
+        deps.sort();
+        // the type only import is not a dep
+        assert_eq!(deps, vec!["/a.ts", "/b.ts"]);
+        let info = module_graph
+            .get_module(&broken)
+            .unwrap()
+            .info
+            .as_ref()
+            .unwrap();
+        assert!(info.deps.missing_deps.contains_key("./missing"));
+        assert_eq!(info.diagnostics.len(), 1);
+        assert!(info.diagnostics[0].contains("broken.ts"));
+    }
+
+    #[test]
+    fn test_transform_include() {
+        let compiler = setup_compiler_with_output_fs(
+            "test/build/transform-include",
+            Arc::new(MemoryFileSystem::new()),
+        );
+        compiler.compile().unwrap();
+        let index = compiler
+            .context
+            .output_fs
+            .read(&compiler.context.config.output.path.join("index.js"))
+            .unwrap();
+        let index = String::from_utf8(index).unwrap();
+        // node_modules are not lowered unless included
+        assert!(index.contains("()=>'modern'"), "{}", index);
+        assert!(!index.contains("()=>'lowered'"), "{}", index);
+        assert!(!index.contains("()=>'app'"), "{}", index);
+
+        let err = compile_fixture(
+            Path::new("test/build/transform-include"),
+            // the jsx of the package is parsed by parseFallback otherwise
+            Some(r#"{ "transform": { "include": ["lowered"] }, "parseFallback": false }"#),
+        )
+        .err()
+        .unwrap();
+        let err = format!("{:?}", err);
+        assert!(
+            err.contains("add \"jsx-lib\" to transform.include"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_module_types() {
+        let compiler = setup_compiler_with_output_fs(
+            "test/build/module-types",
+            Arc::new(MemoryFileSystem::new()),
+        );
+        compiler.compile().unwrap();
+        let module_system = |path: &str| get_module(&compiler, path).info.unwrap().module_system;
+        assert_eq!(module_system("index.mjs"), ModuleSystem::ESModule);
+        assert_eq!(module_system("helper.cjs"), ModuleSystem::CommonJS);
+        // by the type field of lib/package.json, without imports and exports
+        assert_eq!(module_system("lib/polyfill.js"), ModuleSystem::ESModule);
+        // by moduleTypes, parsed as a script
+        assert_eq!(module_system("vendor/legacy.js"), ModuleSystem::CommonJS);
+
+        let index = compiler
+            .context
+            .output_fs
+            .read(&compiler.context.config.output.path.join("index.js"))
+            .unwrap();
+        let index = String::from_utf8(index).unwrap();
+        // module.exports is the default export
+        assert!(
+            index.contains(r#"_interop_require_wildcard._(__mako_require__("helper.cjs"), true)"#)
+        );
+        assert!(index.contains(r#"default: __mako_require__("vendor/legacy.js")"#));
+    }
+
+    #[test]
+    fn test_module_rules() {
+        let compiler = setup_compiler_with_output_fs(
+            "test/build/module-rules",
+            Arc::new(MemoryFileSystem::new()),
+        );
+        compiler.compile().unwrap();
+        let index = compiler
+            .context
+            .output_fs
+            .read(&compiler.context.config.output.path.join("index.js"))
+            .unwrap();
+        let index = String::from_utf8(index).unwrap();
+        // parsed with jsx by the rule
+        assert!(index.contains(r#"className: "legacy""#), "{}", index);
+        // loaded as a string
+        assert!(
+            index.contains(r#"module.exports = "void main() {\n  gl_FragColor"#),
+            "{}",
+            index
+        );
+        // defined in the vendor directory only
+        assert!(index.contains("const debug = false;"), "{}", index);
+        assert!(index.contains("typeof VENDOR_DEBUG"), "{}", index);
+    }
+
+    #[test]
+    fn test_side_effect_only_imports() {
+        let compiler = setup_compiler_with_output_fs(
+            "test/build/side-effect-only-imports",
+            Arc::new(MemoryFileSystem::new()),
+        );
+        compiler.compile().unwrap();
+        let index = compiler
+            .context
+            .output_fs
+            .read(&compiler.context.config.output.path.join("index.js"))
+            .unwrap();
+        let index = String::from_utf8(index).unwrap();
+        // executed in order, without the bindings
+        let init = index.find(r#"__mako_require__("src/init.js");"#);
+        let debug = index.find(r#"__mako_require__("src/debug.js");"#);
+        assert!(
+            init.is_some() && debug.is_some() && init < debug,
+            "{}",
+            index
+        );
+        assert!(!index.contains("var _init"), "{}", index);
+        assert!(!index.contains("var _debug"), "{}", index);
+        // referenced only in the removed dead branch
+        assert!(!index.contains("debug('start')"), "{}", index);
+        assert!(index.contains("_used.default"), "{}", index);
+        assert_eq!(
+            compiler.context.stats_info.get_side_effect_only_imports(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_remove_prop_types() {
+        let compile = |edit: &str| {
+            let compiler =
+                compile_fixture(Path::new("test/build/remove-prop-types"), Some(edit)).unwrap();
+            let index = compiler
+                .context
+                .output_fs
+                .read(&compiler.context.config.output.path.join("index.js"))
+                .unwrap();
+            String::from_utf8(index).unwrap()
+        };
+        let kept = compile(r#"{"mode":"production","react":{"removePropTypes":false}}"#);
+        let removed = compile(r#"{"mode":"production"}"#);
+        let all = compile(
+            r#"{"mode":"production","react":{"removePropTypes":{"include":["node_modules/ui-kit/"],"defaultProps":true}}}"#,
+        );
+        let development = compile(r#"{"mode":"development"}"#);
+        // kept in the development
+        assert!(
+            development.contains("Button.propTypes = {"),
+            "{}",
+            development
+        );
+        assert!(kept.contains("Button.propTypes = {"), "{}", kept);
+        assert!(kept.contains("isDev && (Button.displayName"), "{}", kept);
+
+        // the prop types of the src, the dev-only statement the simplifier
+        // keeps, and the import of prop-types
+        assert!(!removed.contains("Button.propTypes"), "{}", removed);
+        assert!(!removed.contains("static propTypes"), "{}", removed);
+        assert!(!removed.contains("Button.displayName"), "{}", removed);
+        assert!(!removed.contains("prop_types_index_js_esm"), "{}", removed);
+        assert!(removed.contains("Button.defaultProps = {"), "{}", removed);
+        assert!(removed.contains("static defaultProps = {"), "{}", removed);
+        // node_modules are left alone by default
+        assert!(removed.contains("Tag.propTypes = {"), "{}", removed);
+        assert!(removed.len() < kept.len());
+
+        // prop-types is not bundled when no module requires it
+        assert!(!all.contains("Tag.propTypes"), "{}", all);
+        assert!(!all.contains("node_modules/prop-types/index.js"), "{}", all);
+        assert!(!all.contains("defaultProps"), "{}", all);
+        assert!(all.len() < removed.len());
+    }
+
+    #[test]
+    fn test_query_dedupe() {
+        let compiler = setup_compiler_with_output_fs(
+            "test/build/query-dedupe",
+            Arc::new(MemoryFileSystem::new()),
+        );
+        compiler.compile().unwrap();
+        let root = &compiler.context.root;
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let index = ModuleId::from(root.join("src/index.js"));
+        let mut deps = module_graph
+            .get_dependencies(&index)
+            .iter()
+            .map(|(id, _)| id.relative_display(root))
+            .collect::<Vec<_>>();
+        // the requests of the same query in the other orders, encodings or
+        // empty values are one module
+        deps.dedup();
+        assert_eq!(
+            deps,
+            vec![
+                "./src/icon.svg?height=24&raw&width=24",
+                "./src/icon.svg?raw&width=32",
+                "./src/logo.png?h=2&w=1",
+                "./src/a.css?asmodule&theme=dark",
+                "./src/lazy.js?a=2&b=1",
+            ]
+        );
+        // the loaders see the query of the first request
+        let icon = module_graph
+            .get_module(&ModuleId::from(format!(
+                "{}?width=24&raw&height=24",
+                root.join("src/icon.svg").to_string_lossy()
+            )))
+            .unwrap();
+        let file = &icon.info.as_ref().unwrap().file;
+        assert_eq!(file.raw_query(), "raw&width=24&height=24");
+        assert!(file.has_param("raw"));
+        drop(module_graph);
+
+        let chunk_graph = compiler.context.chunk_graph.read().unwrap();
+        let async_chunks = chunk_graph
+            .get_all_chunks()
+            .into_iter()
+            .filter(|chunk| matches!(chunk.chunk_type, ChunkType::Async))
+            .map(|chunk| chunk.id.id.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(async_chunks, vec!["src/lazy.js?a=2&b=1"]);
+        assert_eq!(compiler.context.emitted_assets().len(), 1);
+    }
 }
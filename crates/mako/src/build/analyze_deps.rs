@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
@@ -7,13 +8,17 @@ use thiserror::Error;
 use crate::ast::error;
 use crate::ast::file::File;
 use crate::compiler::Context;
+use crate::config::matched_ignore_rule;
+use crate::generate::chunk_pot::util::hash_vec;
 use crate::module::{Dependency, ModuleAst};
-use crate::resolve::{resolve, ResolverResource};
+use crate::resolve::{get_probed_paths, get_shadowed_paths, resolve, ResolverResource};
 
 #[derive(Debug, Error)]
 pub enum AnalyzeDepsError {
     #[error("{message:}")]
     ModuleNotFound { message: String },
+    #[error("{message:}")]
+    ModuleIgnored { message: String },
 }
 
 #[derive(Debug, Clone, Default)]
@@ -22,6 +27,34 @@ pub struct AnalyzeDepsResult {
     // why use hash map?
     // since we need source as key to replace in generate step
     pub missing_deps: HashMap<String, Dependency>,
+    // paths probed by the missing deps (watch only), source as key
+    pub missing_dep_paths: HashMap<String, HashSet<PathBuf>>,
+    // paths probed in the directories of `resolve.roots` before the ones the
+    // deps are resolved in (watch only), source as key
+    pub shadowed_dep_paths: HashMap<String, HashSet<PathBuf>>,
+}
+
+impl AnalyzeDepsResult {
+    /// The hash of the dependency requests in order, i.e. the sources, the
+    /// resolve types and the resolved paths, but not the spans, which move
+    /// with the edits of the other code
+    pub fn requests_hash(&self) -> u64 {
+        let requests = self
+            .resolved_deps
+            .iter()
+            .map(|dep| {
+                (
+                    &dep.dependency.source,
+                    &dep.dependency.resolve_as,
+                    &dep.dependency.resolve_type,
+                    dep.dependency.order,
+                    dep.resolver_resource.get_resolved_path(),
+                    dep.resolver_resource.get_external(),
+                )
+            })
+            .collect::<Vec<_>>();
+        hash_vec(&requests)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -49,6 +82,8 @@ impl AnalyzeDeps {
 
         let mut resolved_deps = vec![];
         let mut missing_deps = HashMap::new();
+        let mut missing_dep_paths = HashMap::new();
+        let mut shadowed_dep_paths = HashMap::new();
 
         for dep in deps {
             let result = resolve(
@@ -59,12 +94,48 @@ impl AnalyzeDeps {
             );
             match result {
                 Ok(resolver_resource) => {
+                    if let ResolverResource::Resolved(resource) = &resolver_resource
+                        && let Some(rule) = matched_ignore_rule(
+                            &context.config.ignore,
+                            &context.root,
+                            resource.0.full_path().as_path(),
+                        )
+                    {
+                        return Err(anyhow!(AnalyzeDepsError::ModuleIgnored {
+                            message: Self::get_ignored_error(&dep, rule, context.clone()),
+                        }));
+                    }
+                    if context.args.watch
+                        && let ResolverResource::Resolved(resource) = &resolver_resource
+                    {
+                        let paths = get_shadowed_paths(
+                            &file.resolve_from(&context),
+                            &dep,
+                            &resource.0,
+                            &context.resolvers,
+                            &context.config,
+                        );
+                        if !paths.is_empty() {
+                            shadowed_dep_paths.insert(dep.source.clone(), paths);
+                        }
+                    }
                     resolved_deps.push(ResolvedDep {
                         resolver_resource,
                         dependency: dep,
                     });
                 }
                 Err(_err) => {
+                    if context.args.watch {
+                        missing_dep_paths.insert(
+                            dep.source.clone(),
+                            get_probed_paths(
+                                &file.resolve_from(&context),
+                                &dep,
+                                &context.resolvers,
+                                &context.config,
+                            ),
+                        );
+                    }
                     missing_deps.insert(dep.source.clone(), dep);
                 }
             }
@@ -88,6 +159,8 @@ impl AnalyzeDeps {
         Ok(AnalyzeDepsResult {
             resolved_deps,
             missing_deps,
+            missing_dep_paths,
+            shadowed_dep_paths,
         })
     }
 
@@ -107,6 +180,17 @@ impl AnalyzeDeps {
         Ok(())
     }
 
+    fn get_ignored_error(dep: &Dependency, rule: &str, context: Arc<Context>) -> String {
+        let message = format!(
+            "Module '{}' is excluded by the ignore rule {:?}",
+            dep.source, rule
+        );
+        match dep.span {
+            Some(span) => error::code_frame(error::ErrorSpan::Js(span), &message, context),
+            None => message,
+        }
+    }
+
     pub fn get_resolved_error(dep: &Dependency, context: Arc<Context>) -> String {
         let message = format!("Module not found: Can't resolve '{}'", dep.source);
         if dep.span.is_some() {
@@ -1,6 +1,7 @@
+use std::collections::HashMap;
 use std::io::Read;
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 use anyhow::{anyhow, Result};
 use mdxjs::{compile, Options as MdxOptions};
@@ -13,9 +14,13 @@ use tracing::debug;
 
 use crate::ast::file::{Content, File, JsContent};
 use crate::compiler::Context;
-use crate::config::Mode;
+use crate::config::{has_content_hash, EntryPreludeConfig, Mode, ModuleRuleLoader, Platform};
+use crate::generate::csp_report::{data_url_directive, sha256, InlineCode, InlineCodeKind};
+use crate::module::relative_to_root;
 use crate::plugin::PluginLoadParam;
+use crate::plugins::bundless_compiler::preserved_asset_name;
 use crate::utils::create_cached_regex;
+use crate::visitors::css_import_attributes::STYLESHEET_QUERY;
 
 #[derive(Debug, Error)]
 enum LoadError {
@@ -39,8 +44,11 @@ const XML_EXTENSIONS: [&str; 1] = ["xml"];
 const TOML_EXTENSIONS: [&str; 1] = ["toml"];
 const SVG_EXTENSIONS: [&str; 1] = ["svg"];
 const MD_EXTENSIONS: [&str; 2] = ["md", "mdx"];
+pub const NATIVE_ADDON_EXTENSION: &str = "node";
 const UNSUPPORTED_EXTENSIONS: [&str; 2] = ["sass", "stylus"];
 
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
 const SVGR_NAMED_EXPORT: &str = r#"ReactComponent"#;
 
 pub struct Load {}
@@ -59,6 +67,39 @@ impl Load {
             return Ok(content);
         }
 
+        // entry with ?hmr or ?prelude, which runs the hmr client and the
+        // entryPrelude before the entry
+        if file.is_entry
+            && (file.has_param("hmr") || file.has_param("prelude"))
+            && Self::is_script(file, &context)?
+        {
+            let mut content = String::new();
+            if file.has_param("hmr") {
+                content.push_str(include_str!("../runtime/runtime_hmr_entry.js"));
+                content.push('\n');
+            }
+            content.push_str(&entry_prelude(&context));
+            content.push_str(&format!(
+                "module.exports = require(\"{}\");\n",
+                file.pathname.to_string_lossy(),
+            ));
+            let is_jsx = file.extname.as_str() == "jsx" || file.extname.as_str() == "tsx";
+            return Ok(Content::Js(JsContent { content, is_jsx }));
+        }
+
+        // the files split into the sub-modules by plugins
+        if let Some(content) = context.sub_modules.load(file, &context)? {
+            return Ok(content);
+        }
+
+        // virtual modules registered by plugins
+        if let Some(content) = context
+            .virtual_modules
+            .load(&file.path.to_string_lossy(), &context)?
+        {
+            return Ok(content);
+        }
+
         // virtual:inline_css:runtime
         if file.path.to_str().unwrap() == "virtual:inline_css:runtime" {
             return Ok(Content::Js(JsContent {
@@ -66,8 +107,12 @@ impl Load {
 export function moduleToDom(css) {
     var styleElement = document.createElement("style");
     styleElement.type = "text/css";
+    if (typeof __mako_nonce__ !== "undefined") {
+        styleElement.setAttribute("nonce", __mako_nonce__);
+    }
     styleElement.appendChild(document.createTextNode(css))
     document.head.appendChild(styleElement);
+    return styleElement;
 }
                                 "#
                 .to_string(),
@@ -82,17 +127,19 @@ export function moduleToDom(css) {
             }));
         }
 
+        let rules = context.module_rules.matched(&file.pathname, &context.root);
+
         // unsupported
-        if UNSUPPORTED_EXTENSIONS.contains(&file.extname.as_str()) {
+        if UNSUPPORTED_EXTENSIONS.contains(&file.extname.as_str()) && rules.loader.is_none() {
             return Err(anyhow!(LoadError::UnsupportedExtName {
                 ext_name: file.extname.clone(),
                 path: file.path.to_string_lossy().to_string(),
             }));
         }
 
-        // ?raw
-        if file.has_param("raw") {
-            let content = FileSystem::read_file(&file.pathname)?;
+        // ?raw, or the files of the rules with the raw loader
+        if file.has_param("raw") || rules.loader == Some(ModuleRuleLoader::Raw) {
+            let content = FileSystem::read_file(&file.pathname, &context)?;
             let content = serde_json::to_string(&content)?;
             return Ok(Content::Js(JsContent {
                 content: format!("module.exports = {}", content),
@@ -100,31 +147,70 @@ export function moduleToDom(css) {
             }));
         }
 
-        // js
-        if JS_EXTENSIONS.contains(&file.extname.as_str()) {
-            // entry with ?hmr
-            let is_jsx = file.extname.as_str() == "jsx" || file.extname.as_str() == "tsx";
-            if file.is_entry && file.has_param("hmr") {
-                let content = format!(
-                    "{}\nmodule.exports = require(\"{}\");\n",
-                    include_str!("../runtime/runtime_hmr_entry.js"),
-                    file.pathname.to_string_lossy(),
+        // ?stylesheet, the css imported with `{ type: "css" }`
+        if file.has_param(STYLESHEET_QUERY) && CSS_EXTENSIONS.contains(&file.extname.as_str()) {
+            let asset_path = Self::handle_asset(file, true, false, context.clone())?;
+            // the style element of the browsers without constructable
+            // stylesheets has the text of the emitted file
+            if context.config.csp_report.is_some() {
+                let content = FileSystem::read_file(&file.pathname, &context)?;
+                context.stats_info.inline_code.report_module(
+                    &file.path.to_string_lossy(),
+                    InlineCode {
+                        origin: "cssImportAttributes".to_string(),
+                        kind: InlineCodeKind::Style,
+                        location: relative_to_root(
+                            &file.pathname.to_string_lossy().to_string(),
+                            &context.root,
+                        ),
+                        directive: "style-src".to_string(),
+                        hash: Some(sha256(&content)),
+                        nonce: Some("__mako_nonce__".to_string()),
+                    },
                 );
-                return Ok(Content::Js(JsContent { content, is_jsx }));
             }
-            let content = FileSystem::read_file(&file.pathname)?;
+            return Ok(Content::Js(JsContent {
+                content: format!(
+                    r#"
+var text = await fetch({}).then(function (res) {{ return res.text(); }});
+var sheet;
+if (typeof CSSStyleSheet === "function" && "replaceSync" in CSSStyleSheet.prototype) {{
+    sheet = new CSSStyleSheet();
+    sheet.replaceSync(text);
+}} else {{
+    var styleElement = document.createElement("style");
+    if (typeof __mako_nonce__ !== "undefined") {{
+        styleElement.setAttribute("nonce", __mako_nonce__);
+    }}
+    styleElement.appendChild(document.createTextNode(text));
+    document.head.appendChild(styleElement);
+}}
+export default sheet;
+"#,
+                    asset_path
+                ),
+                ..Default::default()
+            }));
+        }
+
+        // js
+        if JS_EXTENSIONS.contains(&file.extname.as_str()) {
+            let is_jsx = rules
+                .jsx
+                .unwrap_or(file.extname.as_str() == "jsx" || file.extname.as_str() == "tsx");
+            let content = FileSystem::read_file(&file.pathname, &context)?;
             return Ok(Content::Js(JsContent { content, is_jsx }));
         }
 
         // css
         if CSS_EXTENSIONS.contains(&file.extname.as_str()) {
-            let content = FileSystem::read_file(&file.pathname)?;
+            let content = FileSystem::read_file(&file.pathname, &context)?;
             return Ok(Content::Css(content));
         }
 
         // md & mdx
         if MD_EXTENSIONS.contains(&file.extname.as_str()) {
-            let content = FileSystem::read_file(&file.pathname)?;
+            let content = FileSystem::read_file(&file.pathname, &context)?;
             let options = MdxOptions {
                 development: matches!(context.config.mode, Mode::Development),
                 ..Default::default()
@@ -145,7 +231,7 @@ export function moduleToDom(css) {
         // svg
         // TODO: Not all svg files need to be converted to React Component, unnecessary performance consumption here
         if SVG_EXTENSIONS.contains(&file.extname.as_str()) {
-            let content = FileSystem::read_file(&file.pathname)?;
+            let content = FileSystem::read_file(&file.pathname, &context)?;
             let svgr_transformed = svgr_rs::transform(
                 content,
                 svgr_rs::Config {
@@ -170,7 +256,7 @@ export function moduleToDom(css) {
 
         // toml
         if TOML_EXTENSIONS.contains(&file.extname.as_str()) {
-            let content = FileSystem::read_file(&file.pathname)?;
+            let content = FileSystem::read_file(&file.pathname, &context)?;
             let content = from_toml_str::<TomlValue>(&content)?;
             let content = serde_json::to_string(&content)?;
             return Ok(Content::Js(JsContent {
@@ -181,7 +267,7 @@ export function moduleToDom(css) {
 
         // xml
         if XML_EXTENSIONS.contains(&file.extname.as_str()) {
-            let content = FileSystem::read_file(&file.pathname)?;
+            let content = FileSystem::read_file(&file.pathname, &context)?;
             let content = from_xml_str::<serde_json::Value>(&content)?;
             let content = serde_json::to_string(&content)?;
             return Ok(Content::Js(JsContent {
@@ -192,7 +278,7 @@ export function moduleToDom(css) {
 
         // yaml
         if YAML_EXTENSIONS.contains(&file.extname.as_str()) {
-            let content = FileSystem::read_file(&file.pathname)?;
+            let content = FileSystem::read_file(&file.pathname, &context)?;
             let content = from_yaml_str::<YamlValue>(&content)?;
             let content = serde_json::to_string(&content)?;
             return Ok(Content::Js(JsContent {
@@ -203,13 +289,26 @@ export function moduleToDom(css) {
 
         // json
         if JSON_EXTENSIONS.contains(&file.extname.as_str()) {
-            let content = FileSystem::read_file(&file.pathname)?;
+            let content = FileSystem::read_file(&file.pathname, &context)?;
             return Ok(Content::Js(JsContent {
                 content: format!("module.exports = {}", content),
                 ..Default::default()
             }));
         }
 
+        // native addons, loaded from the emitted file next to the chunks at
+        // runtime like node loads the required `.node` files
+        if file.extname == NATIVE_ADDON_EXTENSION && context.config.platform == Platform::Node {
+            let final_file_name = Self::emit_asset(file, context.clone())?;
+            return Ok(Content::Js(JsContent {
+                content: format!(
+                    "process.dlopen(module, require(\"path\").join(__dirname, {}));",
+                    serde_json::to_string(&final_file_name)?
+                ),
+                ..Default::default()
+            }));
+        }
+
         // assets
         let asset_path = Self::handle_asset(file, true, true, context.clone())?;
         Ok(Content::Js(JsContent {
@@ -230,7 +329,7 @@ export function moduleToDom(css) {
                 path: file.path.to_string_lossy().to_string(),
             })?;
         let emit_assets = || -> Result<String> {
-            let final_file_name = Self::emit_asset(file, context.clone());
+            let final_file_name = Self::emit_asset(file, context.clone())?;
             if inject_public_path {
                 Ok(format!("`${{require.publicPath}}{}`", final_file_name))
             } else {
@@ -247,15 +346,40 @@ export function moduleToDom(css) {
         let should_not_transform_base64 = inline_excludes_extensions
             .iter()
             .any(|regex| regex.is_match(&file.extname));
+        // ?inline and ?url override the limit, e.g. url(./icon.svg?inline) of css
+        let force_inline = file.has_param("inline");
         if !limit
-            || file_size > context.config.inline_limit.try_into().unwrap()
-            || should_not_transform_base64
+            || file.has_param("url")
+            || (!force_inline
+                && (file_size > context.config.inline_limit.try_into().unwrap()
+                    || should_not_transform_base64))
         {
             emit_assets()
         } else {
-            let base64_result = file.get_base64();
+            let base64_result = context.file_handles.run(|| file.get_base64());
             match base64_result {
                 Ok(base64) => {
+                    if context.config.csp_report.is_some() {
+                        let mime = base64
+                            .trim_start_matches("data:")
+                            .split(';')
+                            .next()
+                            .unwrap_or_default();
+                        context.stats_info.inline_code.report_module(
+                            &file.path.to_string_lossy(),
+                            InlineCode {
+                                origin: "inlineLimit".to_string(),
+                                kind: InlineCodeKind::DataUrl,
+                                location: relative_to_root(
+                                    &file.pathname.to_string_lossy().to_string(),
+                                    &context.root,
+                                ),
+                                directive: data_url_directive(mime).to_string(),
+                                hash: None,
+                                nonce: None,
+                            },
+                        );
+                    }
                     if inject_public_path {
                         Ok(format!("\"{}\"", base64))
                     } else {
@@ -267,26 +391,110 @@ export function moduleToDom(css) {
         }
     }
 
-    pub fn emit_asset(file: &File, context: Arc<Context>) -> String {
+    // the scripts, or the files whose primary modules split by the plugins are
+    // scripts, e.g. the html entries
+    fn is_script(file: &File, context: &Arc<Context>) -> Result<bool> {
+        if JS_EXTENSIONS.contains(&file.extname.as_str()) {
+            return Ok(true);
+        }
+        let primary = File::new(file.pathname.to_string_lossy().to_string(), context.clone());
+        Ok(matches!(
+            context.sub_modules.load(&primary, context)?,
+            Some(Content::Js(_))
+        ))
+    }
+
+    pub fn emit_asset(file: &File, context: Arc<Context>) -> Result<String> {
         let path = file.pathname.to_string_lossy().to_string();
-        let final_file_name = format!(
-            "{}.{}.{}",
-            file.get_file_stem(),
-            file.get_content_hash().unwrap(),
-            file.extname
-        );
-        context.emit_assets(path, final_file_name.clone());
-        final_file_name
+        // the assets mirror the source tree like the modules with preserveModules
+        if context.config.output.preserve_modules {
+            let final_file_name = preserved_asset_name(&file.pathname, &context);
+            return context.emit_assets(path, final_file_name, true);
+        }
+        let (final_file_name, hashed) = Self::asset_file_name(file, &context)?;
+        context.emit_assets(path, final_file_name, hashed)
+    }
+
+    /// The name of the asset by `output.assetFilename`, and whether it has the
+    /// content hash in it.
+    pub fn asset_file_name(file: &File, context: &Context) -> Result<(String, bool)> {
+        let template = &context.config.output.asset_filename;
+        let hashed = has_content_hash(template);
+        let mut name = template
+            .replace("[name]", &file.get_file_stem())
+            .replace("[ext]", &file.extname);
+        if hashed {
+            let hash = context
+                .file_handles
+                .run(|| file.get_content_hash(&context.config.output))?;
+            name = name
+                .replace("[hash]", &hash)
+                .replace("[contenthash]", &hash);
+        }
+        Ok((name, hashed))
     }
 }
 
 pub struct FileSystem {}
 
 impl FileSystem {
-    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<String> {
-        let mut file = std::fs::File::open(path.as_ref())?;
-        let mut buf = vec![];
-        file.read_to_end(&mut buf)?;
-        Ok(String::from_utf8_lossy(&buf).to_string())
+    pub fn read_file<P: AsRef<Path>>(path: P, context: &Context) -> Result<String> {
+        if let Some(file_contents) = &context.file_contents {
+            return file_contents.read(path.as_ref(), context);
+        }
+        Self::read_file_from_disk(path, context)
+    }
+
+    fn read_file_from_disk<P: AsRef<Path>>(path: P, context: &Context) -> Result<String> {
+        let buf = context.file_handles.run(|| {
+            let mut file = std::fs::File::open(path.as_ref())?;
+            let mut buf = vec![];
+            file.read_to_end(&mut buf)?;
+            Ok(buf)
+        })?;
+        // the BOM is not a part of the content
+        let buf = buf.strip_prefix(UTF8_BOM).unwrap_or(&buf);
+        Ok(String::from_utf8_lossy(buf).to_string())
     }
 }
+
+/// File contents read by one of the compilers of a multi build and reused by
+/// the others, it's never invalidated so it's not used in watch mode.
+#[derive(Default)]
+pub struct FileContentCache {
+    contents: RwLock<HashMap<PathBuf, String>>,
+}
+
+impl FileContentCache {
+    fn read(&self, path: &Path, context: &Context) -> Result<String> {
+        if let Some(content) = self.contents.read().unwrap().get(path) {
+            return Ok(content.clone());
+        }
+        let content = FileSystem::read_file_from_disk(path, context)?;
+        self.contents
+            .write()
+            .unwrap()
+            .insert(path.to_path_buf(), content.clone());
+        Ok(content)
+    }
+}
+
+// the sources starting with `.` are relative to the root
+fn entry_prelude(context: &Context) -> String {
+    context
+        .config
+        .entry_prelude
+        .iter()
+        .map(|item| match item {
+            EntryPreludeConfig::Import(source) => {
+                let source = if source.starts_with('.') {
+                    context.root.join(source).to_string_lossy().to_string()
+                } else {
+                    source.clone()
+                };
+                format!("require({:?});\n", source)
+            }
+            EntryPreludeConfig::Inline { inline } => format!("{}\n", inline),
+        })
+        .collect()
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::Path;
+
+use glob_match::glob_match;
+use serde_json::Value;
+
+use crate::ast::file::File;
+use crate::build::load::JS_EXTENSIONS;
+use crate::compiler::Context;
+use crate::config::ModuleKind;
+use crate::module::relative_to_root;
+use crate::resolve::{ResolvedResource, ResolverResource};
+
+/// The module type of the file by the rules of node, `None` if it's left to
+/// the syntax. Which is, in order, the `moduleTypes` rules of the config, the
+/// extension, and the `type` field of the nearest package.json.
+///
+/// `"type": "commonjs"` is the default of node, files of it are detected by
+/// syntax too, since the `module` field of such packages usually points to
+/// esm `.js` files.
+pub(crate) fn detect_module_kind(
+    file: &File,
+    resource: Option<&ResolverResource>,
+    context: &Context,
+) -> Option<ModuleKind> {
+    if file.is_virtual || !JS_EXTENSIONS.contains(&file.extname.as_str()) {
+        return None;
+    }
+    let path = file.pathname.to_string_lossy().to_string();
+    let relative_path = relative_to_root(&path, &context.root);
+    let relative_path = relative_path.trim_start_matches("./");
+    if let Some(rule) = context
+        .config
+        .module_types
+        .iter()
+        .find(|rule| glob_match(rule.include.trim_start_matches("./"), relative_path))
+    {
+        return Some(rule.module_kind);
+    }
+    match file.extname.as_str() {
+        "mjs" => return Some(ModuleKind::Esm),
+        "cjs" => return Some(ModuleKind::Commonjs),
+        _ => {}
+    }
+    let package_type = match resource {
+        Some(ResolverResource::Resolved(ResolvedResource(resolution))) => resolution
+            .package_json()
+            .and_then(|package_json| package_type(package_json.raw_json())),
+        // entries are not resolved with their package.json
+        _ => nearest_package_type(&file.pathname),
+    };
+    (package_type.as_deref() == Some("module")).then_some(ModuleKind::Esm)
+}
+
+fn package_type(package_json: &Value) -> Option<String> {
+    package_json
+        .get("type")
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string())
+}
+
+fn nearest_package_type(path: &Path) -> Option<String> {
+    path.ancestors().skip(1).find_map(|dir| {
+        let content = fs::read_to_string(dir.join("package.json")).ok()?;
+        // the nearest package.json decides, even without the type field
+        Some(
+            serde_json::from_str::<Value>(&content)
+                .ok()
+                .and_then(|package_json| package_type(&package_json)),
+        )
+    })?
+}
@@ -13,7 +13,8 @@ use crate::build::transform::Transform;
 use crate::compiler::Context;
 use crate::config;
 use crate::features::rsc::Rsc;
-use crate::module::ModuleAst;
+use crate::generate::csp_report::{sha256, InlineCode, InlineCodeKind};
+use crate::module::{relative_to_root, ModuleAst};
 use crate::plugin::PluginParseParam;
 use crate::visitors::css_imports::CSSImports;
 
@@ -35,6 +36,21 @@ pub enum ParseError {
 
 pub struct Parse {}
 
+// the css is reloaded by the css chunk, and the importers are updated only if
+// the class names are changed
+const CSS_MODULES_HOT_UPDATE: &str = r#"
+if (module.hot) {
+  var __mako_css_locals__ = JSON.stringify(module.exports.default);
+  if (module.hot.data && module.hot.data.locals !== __mako_css_locals__) {
+    module.hot.invalidate();
+  }
+  module.hot.accept();
+  module.hot.dispose(function (data) {
+    data.locals = __mako_css_locals__;
+  });
+}
+"#;
+
 impl Parse {
     pub fn parse(file: &File, context: Arc<Context>) -> Result<ModuleAst> {
         crate::mako_profile_function!(file.path.to_string_lossy());
@@ -67,11 +83,14 @@ impl Parse {
             if is_asmodule {
                 let mut ast = CssAst::new(file, context.clone(), css_modules)?;
                 let mut file = file.clone();
-                let content = CssAst::generate_css_modules_exports(
+                let mut content = CssAst::generate_css_modules_exports(
                     &file.pathname.to_string_lossy(),
                     &mut ast.ast,
                     context.config.css_modules_export_only_locales,
                 );
+                if Self::is_hot_update_enabled(&context) {
+                    content.push_str(CSS_MODULES_HOT_UPDATE);
+                }
                 file.set_content(Content::Js(JsContent {
                     content,
                     ..Default::default()
@@ -107,7 +126,7 @@ impl Parse {
                         .resolved_deps
                         .iter()
                         .map(|dep| {
-                            format!("import '{}';", dep.resolver_resource.get_resolved_path())
+                            format!("import '{}';", dep.resolver_resource.get_resolved_request())
                         })
                         .collect::<Vec<String>>()
                         .join("\n");
@@ -117,16 +136,45 @@ impl Parse {
                     ast.ast.visit_mut_with(&mut css_handler);
                     // ast to code
                     let code = ast.generate(context.clone())?.code;
+                    if context.config.csp_report.is_some() {
+                        context.stats_info.inline_code.report_module(
+                            &file.path.to_string_lossy(),
+                            InlineCode {
+                                origin: "inlineCSS".to_string(),
+                                kind: InlineCodeKind::Style,
+                                location: relative_to_root(
+                                    &file.pathname.to_string_lossy().to_string(),
+                                    &context.root,
+                                ),
+                                directive: "style-src".to_string(),
+                                hash: Some(sha256(&code)),
+                                nonce: Some("__mako_nonce__".to_string()),
+                            },
+                        );
+                    }
                     let mut file = file.clone();
+                    // the style element is replaced in place on hot updates
+                    let hot_update = if Self::is_hot_update_enabled(&context) {
+                        r#"
+if (module.hot) {
+  module.hot.accept();
+  module.hot.dispose(function () {
+    styleElement.remove();
+  });
+}"#
+                    } else {
+                        ""
+                    };
                     file.set_content(Content::Js(JsContent {
                         content: format!(
                             r#"
 import {{ moduleToDom }} from 'virtual:inline_css:runtime';
 {}
-moduleToDom({});
+var styleElement = moduleToDom({});{}
                         "#,
                             deps,
-                            serde_json::to_string(&code)?
+                            serde_json::to_string(&code)?,
+                            hot_update
                         ),
                         ..Default::default()
                     }));
@@ -145,4 +193,8 @@ moduleToDom({});
             path: file.path.to_string_lossy().to_string(),
         }))
     }
+
+    fn is_hot_update_enabled(context: &Arc<Context>) -> bool {
+        context.args.watch && context.config.hmr.is_some()
+    }
 }
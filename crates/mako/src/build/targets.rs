@@ -7,3 +7,41 @@ pub fn swc_preset_env_targets_from_map(map: HashMap<String, f32>) -> SwcPresetEn
     let targets: SwcPresetEnvTargets = serde_json::from_str(&serialized_str).unwrap();
     targets
 }
+
+/// Whether all the browsers of the targets support a feature, by the first
+/// versions which support it. The browsers missing in `support` don't support
+/// it, except node which has no css.
+pub fn targets_support(targets: &HashMap<String, f32>, support: &[(&str, f32)]) -> bool {
+    targets.iter().all(|(browser, version)| {
+        browser == "node"
+            || support
+                .iter()
+                .find(|(name, _)| name == browser)
+                .is_some_and(|(_, since)| version >= since)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::targets_support;
+
+    #[test]
+    fn test_targets_support() {
+        let support = [("chrome", 88.0), ("safari", 14.0)];
+        let targets = |list: &[(&str, f32)]| {
+            list.iter()
+                .map(|(name, version)| (name.to_string(), *version))
+                .collect::<HashMap<_, _>>()
+        };
+        assert!(targets_support(&targets(&[("chrome", 90.0)]), &support));
+        assert!(!targets_support(&targets(&[("chrome", 80.0)]), &support));
+        assert!(!targets_support(
+            &targets(&[("chrome", 90.0), ("safari", 13.1)]),
+            &support
+        ));
+        assert!(!targets_support(&targets(&[("ie", 11.0)]), &support));
+        assert!(targets_support(&targets(&[("node", 14.0)]), &support));
+    }
+}
@@ -1,9 +1,11 @@
 use std::sync::Arc;
 
 use anyhow::Result;
+use glob_match::glob_match;
+use regex::Regex;
 use swc_core::base::try_with_handler;
 use swc_core::common::errors::HANDLER;
-use swc_core::common::{Mark, GLOBALS};
+use swc_core::common::{Mark, Spanned, GLOBALS};
 use swc_core::css::ast::{AtRule, AtRulePrelude, ImportHref, Rule, Str, Stylesheet, UrlValue};
 use swc_core::css::compat::compiler::{self, Compiler};
 use swc_core::css::{compat as swc_css_compat, prefixer, visit as swc_css_visit};
@@ -21,29 +23,43 @@ use swc_core::ecma::visit::{Fold, VisitMut, VisitMutWith};
 
 use crate::ast::css_ast::CssAst;
 use crate::ast::file::File;
+use crate::build::load::NATIVE_ADDON_EXTENSION;
 use crate::build::targets;
-use crate::build::targets::swc_preset_env_targets_from_map;
+use crate::build::targets::{swc_preset_env_targets_from_map, targets_support};
 use crate::compiler::Context;
-use crate::config::Mode;
+use crate::config::{KeepNamesConfig, Mode, ModuleKind, OutputMode, PolyfillMode, RemoteCssConfig};
 use crate::features;
-use crate::module::ModuleAst;
+use crate::features::polyfill::Polyfill;
+use crate::generate::analyze::package_name;
+use crate::generate::runtime::runtime_target_folders;
+use crate::module::{relative_to_root, ModuleAst};
 use crate::plugin::PluginTransformJsParam;
 use crate::plugins::context_module::ContextModuleVisitor;
 use crate::visitors::amd_define_overrides::amd_define_overrides;
 use crate::visitors::clean_ctxt::clean_syntax_context;
+use crate::visitors::commonjs_globals::CommonJsGlobals;
 use crate::visitors::css_assets::CSSAssets;
 use crate::visitors::css_flexbugs::CSSFlexbugs;
+use crate::visitors::css_import_attributes::CSSImportAttributes;
+use crate::visitors::css_is_where::{CSSIsWhere, IS_WHERE_SUPPORT};
+use crate::visitors::css_logical_properties::{CSSLogicalProperties, LOGICAL_PROPERTIES_SUPPORT};
 use crate::visitors::css_px2rem::Px2Rem;
+use crate::visitors::css_remote_urls::CSSRemoteUrls;
 use crate::visitors::default_export_namer::DefaultExportNamer;
 use crate::visitors::dynamic_import_to_require::DynamicImportToRequire;
 use crate::visitors::env_replacer::{build_env_map, EnvReplacer};
 use crate::visitors::fix_symbol_conflict::FixSymbolConflict;
+use crate::visitors::import_meta_check::{preserve_import_meta, ImportMetaCheck};
 use crate::visitors::import_meta_env_replacer::ImportMetaEnvReplacer;
 use crate::visitors::import_template_to_string_literal::ImportTemplateToStringLiteral;
+use crate::visitors::keep_names::KeepNames;
 use crate::visitors::new_url_assets::NewUrlAssets;
+use crate::visitors::process_env_object::ProcessEnvObject;
 use crate::visitors::provide::Provide;
 use crate::visitors::public_path_assignment::PublicPathAssignment;
 use crate::visitors::react::react;
+use crate::visitors::remove_prop_types::RemovePropTypes;
+use crate::visitors::side_effect_only_imports::SideEffectOnlyImports;
 use crate::visitors::try_resolve::TryResolve;
 use crate::visitors::ts_strip::ts_strip;
 use crate::visitors::tsx_strip::tsx_strip;
@@ -69,8 +85,13 @@ impl Transform {
                                     context.meta.script.cm.clone();
                                 let origin_comments =
                                     context.meta.script.origin_comments.read().unwrap();
-                                let is_ts = file.extname == "ts";
-                                let is_tsx = file.extname == "tsx";
+                                // the Flow annotations parsed by parseFallback are
+                                // stripped as typescript
+                                let strips_types = context
+                                    .parse_fallbacks
+                                    .strips_types(&file.pathname.to_string_lossy());
+                                let is_ts = file.extname == "ts" || strips_types == Some(false);
+                                let is_tsx = file.extname == "tsx" || strips_types == Some(true);
                                 let is_jsx = file.is_content_jsx()
                                     || file.extname == "jsx"
                                     || file.extname == "js"
@@ -109,7 +130,27 @@ impl Transform {
                                     Box::new(WorkerModule::new(unresolved_mark)),
                                     Box::new(WebpackRuntimeReplacement::new(unresolved_mark)),
                                 ];
+                                if file.module_kind == Some(ModuleKind::Esm) {
+                                    visitors.push(Box::new(CommonJsGlobals { unresolved_mark }));
+                                }
 
+                                // /* @keepNames */ of the file, for the names not kept by
+                                // minify.keepNames already
+                                if let Some(minify) = &context.config.minify {
+                                    let config = KeepNamesConfig {
+                                        class_names: !minify.keep_names.class_names,
+                                        function_names: !minify.keep_names.function_names,
+                                    };
+                                    let has_keep_names = ast.ast.body.first().is_some_and(|item| {
+                                        origin_comments.has_keep_names(item.span())
+                                    });
+                                    if matches!(context.config.mode, Mode::Production)
+                                        && has_keep_names
+                                        && !config.is_empty()
+                                    {
+                                        visitors.push(Box::new(KeepNames { config }));
+                                    }
+                                }
                                 // named default export
                                 if context.args.watch && !file.is_under_node_modules && is_jsx {
                                     visitors.push(Box::new(DefaultExportNamer::new()));
@@ -136,14 +177,42 @@ impl Transform {
                                 }
                                 {
                                     let mut define = context.config.define.clone();
-                                    let mode = context.config.mode.to_string();
-                                    define
-                                        .entry("process.env.NODE_ENV".to_string())
-                                        .or_insert_with(|| format!("\"{}\"", mode).into());
+                                    // the defines of the rules which match the file
+                                    define.extend(
+                                        context
+                                            .module_rules
+                                            .matched(&file.pathname, &context.root)
+                                            .defines,
+                                    );
+                                    let mode = context.config.env_name.clone();
+                                    if let Some(node_env) = context.config.mode.node_env() {
+                                        define
+                                            .entry("process.env.NODE_ENV".to_string())
+                                            .or_insert_with(|| format!("\"{}\"", node_env).into());
+                                    }
                                     let env_map = build_env_map(define, &context)?;
+                                    let import_meta_env_replacer =
+                                        ImportMetaEnvReplacer::new(mode, &env_map);
+                                    let node_env = env_map.get("process.env.NODE_ENV").cloned();
                                     visitors
                                         .push(Box::new(EnvReplacer::new(env_map, unresolved_mark)));
-                                    visitors.push(Box::new(ImportMetaEnvReplacer::new(mode)));
+                                    visitors.push(Box::new(import_meta_env_replacer));
+                                    if !preserve_import_meta(&context.config) {
+                                        visitors.push(Box::new(ImportMetaCheck {
+                                            bundless: context.config.output.mode
+                                                == OutputMode::Bundless,
+                                        }));
+                                    }
+                                    // process.env is only defined for node
+                                    if is_browser {
+                                        visitors.push(Box::new(ProcessEnvObject::new(
+                                            context.clone(),
+                                            unresolved_mark,
+                                            context.config.env_object,
+                                            node_env,
+                                            !file.is_under_node_modules,
+                                        )));
+                                    }
                                 }
                                 visitors.push(Box::new(TryResolve {
                                     path: file.path.to_string_lossy().to_string(),
@@ -157,12 +226,30 @@ impl Transform {
                                     unresolved_mark,
                                     top_level_mark,
                                 )));
-                                visitors.push(Box::new(VirtualCSSModules {
-                                    auto_css_modules: context.config.auto_css_modules,
+                                visitors.push(Box::new(CSSImportAttributes {}));
+                                // ?asmodule imports the css with ?modules itself
+                                if !file.has_param("asmodule") {
+                                    visitors.push(Box::new(VirtualCSSModules {
+                                        auto_css_modules: context.config.auto_css_modules,
+                                        unresolved_mark,
+                                    }));
+                                }
+                                // TODO: move ContextModuleVisitor out of plugin
+                                visitors.push(Box::new(ContextModuleVisitor {
                                     unresolved_mark,
+                                    fully_dynamic_import: if file.is_under_node_modules {
+                                        None
+                                    } else {
+                                        context.config.fully_dynamic_import
+                                    },
+                                    fully_dynamic_require: if file.is_under_node_modules {
+                                        None
+                                    } else {
+                                        context.config.fully_dynamic_require
+                                    },
+                                    path: file.pathname.clone(),
+                                    context: context.clone(),
                                 }));
-                                // TODO: move ContextModuleVisitor out of plugin
-                                visitors.push(Box::new(ContextModuleVisitor { unresolved_mark }));
                                 visitors.push(Box::new(ImportTemplateToStringLiteral {}));
                                 // DynamicImportToRequire must be after ContextModuleVisitor
                                 // since ContextModuleVisitor will add extra dynamic imports
@@ -172,6 +259,15 @@ impl Transform {
                                     )));
                                 }
                                 if matches!(context.config.platform, crate::config::Platform::Node)
+                                {
+                                    visitors.push(Box::new(features::node::NativeAddonLoaders {
+                                        unresolved_mark,
+                                        current_path: file.pathname.clone(),
+                                    }));
+                                }
+                                // the native addons read the runtime `__dirname`
+                                if matches!(context.config.platform, crate::config::Platform::Node)
+                                    && file.extname != NATIVE_ADDON_EXTENSION
                                 {
                                     visitors.push(Box::new(
                                         features::node::MockFilenameAndDirname {
@@ -219,12 +315,23 @@ impl Transform {
                                 )?;
 
                                 // preset_env should go last
-                                let mut preset_folders: Vec<Box<dyn Fold>> = vec![
-                                    Box::new(swc_preset_env::preset_env(
+                                let mut preset_folders: Vec<Box<dyn Fold>> = vec![];
+                                let transpiles = context.transpiles(file);
+                                let polyfill = Polyfill::of(context.config.polyfill.as_ref(), file)
+                                    .filter(|_| transpiles);
+                                if transpiles {
+                                    if file.is_entry
+                                        && polyfill.is_some_and(|p| p.mode == PolyfillMode::Entry)
+                                    {
+                                        Polyfill::prepend_entry_import(&mut ast.ast);
+                                    }
+                                    let (mode, core_js) = Polyfill::preset_env_options(polyfill);
+                                    preset_folders.push(Box::new(swc_preset_env::preset_env(
                                         unresolved_mark,
                                         Some(comments),
                                         swc_preset_env::Config {
-                                            mode: Some(swc_preset_env::Mode::Entry),
+                                            mode: Some(mode),
+                                            core_js,
                                             targets: Some(swc_preset_env_targets_from_map(
                                                 context.config.targets.clone(),
                                             )),
@@ -232,7 +339,22 @@ impl Transform {
                                         },
                                         assumptions,
                                         &mut FeatureFlag::default(),
-                                    )),
+                                    )));
+                                } else {
+                                    crate::mako_profile_scope!("skip syntax lowering");
+                                }
+                                // the hmr client is generated by mako, like the runtime
+                                if file.is_entry
+                                    && file.has_param("hmr")
+                                    && let Some(target) = context.config.output.runtime_target
+                                {
+                                    preset_folders.extend(runtime_target_folders(
+                                        target,
+                                        unresolved_mark,
+                                        origin_comments.get_swc_comments().clone(),
+                                    ));
+                                }
+                                let post_folders: Vec<Box<dyn Fold>> = vec![
                                     Box::new(reserved_words::reserved_words()),
                                     Box::new(paren_remover(Default::default())),
                                     // simplify, but keep top level dead code
@@ -250,12 +372,45 @@ impl Transform {
                                     )),
                                     Box::new(amd_define_overrides(unresolved_mark)),
                                 ];
+                                preset_folders.extend(post_folders);
                                 ast.transform(
                                     &mut vec![],
                                     &mut preset_folders,
                                     true,
                                     context.clone(),
-                                )
+                                )?;
+                                // after the dead branches are removed by the simplifier
+                                if matches!(context.config.mode, Mode::Production)
+                                    && let Some(config) = &context.config.react.remove_prop_types
+                                    && (!file.is_under_node_modules
+                                        || config.include.iter().any(|include| {
+                                            Regex::new(include).is_ok_and(|regex| {
+                                                regex.is_match(&file.path.to_string_lossy())
+                                            })
+                                        }))
+                                {
+                                    ast.ast.visit_mut_with(&mut RemovePropTypes::new(
+                                        unresolved_mark,
+                                        config.default_props,
+                                    ));
+                                }
+                                if let Some(config) = &context.config.side_effect_only_imports {
+                                    let include = config
+                                        .include
+                                        .iter()
+                                        .filter_map(|include| Regex::new(include).ok())
+                                        .collect();
+                                    let mut visitor = SideEffectOnlyImports::new(include);
+                                    ast.ast.visit_mut_with(&mut visitor);
+                                    context.stats_info.set_side_effect_only_imports(
+                                        file.path.to_string_lossy().to_string(),
+                                        visitor.rewritten,
+                                    );
+                                }
+                                if polyfill.is_some() {
+                                    Polyfill::imports_to_requires(&mut ast.ast, unresolved_mark);
+                                }
+                                Ok(())
                             })
                         })
                     })?;
@@ -265,10 +420,32 @@ impl Transform {
             ModuleAst::Css(ast) => {
                 // replace @import url() to @import before CSSUrlReplacer
                 import_url_to_href(&mut ast.ast);
+                // before CSSAssets, which emits the downloaded fonts
+                if context.config.remote_css != RemoteCssConfig::Preserve {
+                    let mut visitor = CSSRemoteUrls::new(context.clone(), file.path.clone());
+                    swc_css_visit::VisitMutWith::visit_mut_with(&mut ast.ast, &mut visitor);
+                    if let Some(err) = visitor.error {
+                        return Err(err);
+                    }
+                }
                 let mut visitors: Vec<Box<dyn swc_css_visit::VisitMut>> = vec![];
                 visitors.push(Box::new(Compiler::new(compiler::Config {
                     process: swc_css_compat::feature::Features::NESTING,
                 })));
+                let css_lowering = &context.config.css_lowering;
+                let targets = &context.config.targets;
+                if css_lowering.is_where && !targets_support(targets, &IS_WHERE_SUPPORT) {
+                    visitors.push(Box::new(CSSIsWhere {
+                        context: context.clone(),
+                    }));
+                }
+                if css_lowering.logical_properties
+                    && !targets_support(targets, &LOGICAL_PROPERTIES_SUPPORT)
+                {
+                    visitors.push(Box::new(CSSLogicalProperties {
+                        direction: css_lowering.direction,
+                    }));
+                }
                 let path = file.path.to_string_lossy().to_string();
                 visitors.push(Box::new(CSSAssets {
                     path,
@@ -306,6 +483,27 @@ impl Transform {
 }
 
 impl Context {
+    /// Whether the file is lowered by the targets and parsed as jsx like the
+    /// source of the project, by `transform.include` and `transform.exclude`.
+    pub fn transpiles(&self, file: &File) -> bool {
+        let path = file.pathname.to_string_lossy().to_string();
+        let relative_path = relative_to_root(&path, &self.root);
+        let relative_path = relative_path.trim_start_matches("./");
+        let package = package_name(&path);
+        let matches = |pattern: &String| {
+            if is_package_name(pattern) {
+                package.as_deref() == Some(pattern.as_str())
+            } else {
+                glob_match(pattern.trim_start_matches("./"), relative_path)
+            }
+        };
+        let transform = &self.config.transform;
+        if transform.exclude.iter().any(matches) {
+            return false;
+        }
+        !file.is_under_node_modules || transform.include.iter().any(matches)
+    }
+
     pub fn assumptions_for(&self, file: &File) -> Assumptions {
         let is_ts = file.extname == "ts" || file.extname == "tsx";
 
@@ -318,6 +516,17 @@ impl Context {
     }
 }
 
+// `react` or `@scope/name` rather than a glob
+fn is_package_name(pattern: &str) -> bool {
+    if pattern.starts_with('.') || pattern.contains(['*', '?', '[', '{']) {
+        return false;
+    }
+    match pattern.strip_prefix('@') {
+        Some(scoped) => scoped.split('/').count() == 2,
+        None => !pattern.contains('/'),
+    }
+}
+
 // TODO: use visitor instead
 // Why do this?
 // 为了修复 @import url() 会把 css 当 asset 处理，返回 base64 的问题
@@ -2,24 +2,107 @@ use std::path::PathBuf;
 
 use clap;
 use clap::builder::TypedValueParser;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use crate::config::Mode;
 
 #[derive(Parser)]
+#[command(subcommand_negates_reqs = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     #[arg(short, long)]
     pub watch: bool,
-    pub root: PathBuf,
-    #[arg(long, default_value_t = Mode::Development,
-        value_parser = clap::builder::PossibleValuesParser::new(["production", "prod", "p", "development","dev"])
+    #[arg(required = true)]
+    pub root: Option<PathBuf>,
+    #[arg(long, global = true, default_value_t = Mode::Development,
+        value_parser = clap::builder::PossibleValuesParser::new(["production", "prod", "p", "development","dev", "none"])
                 .map(|s|{
                     match s.as_str() {
                         "production" | "prod" | "p" => Mode::Production,
                         "development" | "dev" => Mode::Development,
+                        "none" => Mode::None,
                         _ => unreachable!()
                     }
                 })
     )]
     pub mode: Mode,
+    /// the env whose overlay of `env` in the config is merged, and which is
+    /// `import.meta.env.MODE`, the mode by default, e.g. `--env staging`
+    #[arg(long, global = true, value_name = "NAME")]
+    pub env: Option<String>,
+    /// dump the module graph and chunk graph after build
+    #[arg(long, value_parser = ["dot", "json"])]
+    pub graph: Option<String>,
+    /// collapse modules of the same package under node_modules into one node
+    #[arg(long, requires = "graph")]
+    pub graph_collapse_node_modules: bool,
+    /// emit report.html with a treemap of the chunks, packages and modules
+    #[arg(long)]
+    pub analyze: bool,
+    /// add the sizes and tree shaking of each package to the stats and print them
+    #[arg(long)]
+    pub stats_packages: bool,
+    /// print the resolved config with the preset of mode applied and exit, or
+    /// the moduleRules which match the file with `--show-config=<FILE>`, the
+    /// file is relative to the root
+    #[arg(long, value_name = "FILE", num_args = 0..=1, require_equals = true)]
+    pub show_config: Option<Option<PathBuf>>,
+    /// print the entries and the estimated bytes of the module graph and the
+    /// caches after the build, and after each rebuild in watch mode
+    #[arg(long)]
+    pub memory_report: bool,
+    /// print the modules of the chunks whose names contain the filter and why
+    /// they are placed in the chunks, after the build
+    #[arg(long, value_name = "CHUNK_NAME_FILTER")]
+    pub verbose_chunks: Option<String>,
+    /// print the summary of the build in json instead of the messages, or
+    /// write it to the file with `--json=<FILE>`, the warnings and the errors
+    /// are still printed to stderr
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with_all = ["watch", "memory_report", "verbose_chunks"]
+    )]
+    pub json: Option<Option<PathBuf>>,
+    /// build only the entries of the names in watch mode, with the entries
+    /// they depend on, the others are built when their html or js is
+    /// requested by the dev server, e.g. `--only-entry=index,admin`
+    #[arg(long, value_name = "NAME", value_delimiter = ',', requires = "watch")]
+    pub only_entry: Vec<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// print the import chains from the entries to the module
+    Why {
+        /// path of the module, relative to the root
+        path: PathBuf,
+        #[arg(long, default_value = ".")]
+        root: PathBuf,
+        /// max number of the import chains to print
+        #[arg(long, default_value_t = 10)]
+        max_chains: usize,
+    },
+    /// print the dependencies of the file in json, resolved but not built,
+    /// without building the project
+    Deps {
+        /// path of the file, relative to the root
+        path: PathBuf,
+        #[arg(long, default_value = ".")]
+        root: PathBuf,
+    },
+    /// serve the output of the build from disk like a static host, with the
+    /// precompressed files and the fallback to index.html, without rebuilding
+    Preview {
+        #[arg(long, default_value = ".")]
+        root: PathBuf,
+        #[arg(long, default_value_t = 4173)]
+        port: u16,
+        /// serve over https with a self-signed certificate of localhost
+        #[arg(long)]
+        https: bool,
+    },
 }
@@ -1,6 +1,5 @@
-use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
 
@@ -17,33 +16,92 @@ use tracing::debug;
 
 use crate::ast::comments::Comments;
 use crate::ast::file::win_path;
-use crate::config::{Config, Mode, ModuleIdStrategy, OutputMode};
+use crate::ast::parse_fallback::ParseFallbacks;
+use crate::build::load::{FileContentCache, JS_EXTENSIONS};
+use crate::config::{
+    CacheKeys, Config, Mode, ModuleIdStrategy, ModuleRules, OutputMode, RemoteCssConfig,
+};
+use crate::dev::{ActiveEntries, BuildCancellation};
+use crate::diagnostics::Diagnostics;
 use crate::generate::chunk_graph::ChunkGraph;
+use crate::generate::chunk_hash_cache::ChunkHashCache;
+use crate::generate::chunk_pot::render_cache_usages;
 use crate::generate::optimize_chunk::OptimizeChunksInfo;
+use crate::generate::runtime::RuntimeFeatures;
+use crate::generate::source_map_cache::SourceMapCache;
+use crate::module::{relative_module_id, ModuleId, DETERMINISTIC_ID_LENGTH};
 use crate::module_graph::ModuleGraph;
+use crate::module_graph_view::GraphMutations;
 use crate::plugin::{Plugin, PluginDriver, PluginGenerateEndParams};
 use crate::plugins;
-use crate::resolve::{get_resolvers, Resolvers};
+use crate::plugins::html_entry::HtmlEntry;
+use crate::resolve::{get_resolvers, ResolveCache, Resolvers};
 use crate::share::helpers::SWC_HELPERS;
 use crate::stats::StatsInfo;
-use crate::utils::id_helper::{assign_numeric_ids, compare_modules_by_incoming_edges};
+use crate::sub_modules::SubModules;
+use crate::utils::chunk_budget::ChunkBytesBudget;
+use crate::utils::file_handles::FileHandleLimiter;
+use crate::utils::file_request::encode_path;
+use crate::utils::hash::{codegen_salt, content_hash};
+use crate::utils::id_helper::{
+    assign_deterministic_ids, assign_numeric_ids, compare_modules_by_incoming_edges,
+};
+use crate::utils::interner::Interner;
+use crate::utils::memory::{CacheUsage, MemoryReport};
+use crate::utils::output_fs::{DiskFileSystem, MemoryFileSystem, OutputFileSystem};
+use crate::utils::remote_assets::RemoteAssets;
 use crate::utils::{thread_pool, ParseRegex};
+use crate::virtual_modules::VirtualModules;
 
 pub struct Context {
     pub module_graph: RwLock<ModuleGraph>,
     pub chunk_graph: RwLock<ChunkGraph>,
     pub assets_info: Mutex<HashMap<String, String>>,
+    // output name -> the file first emitted as it, to tell the name conflicts
+    pub asset_origins: Mutex<HashMap<String, String>>,
     pub modules_with_missing_deps: RwLock<Vec<String>>,
     pub config: Config,
     pub numeric_ids_map: RwLock<HashMap<String, usize>>,
+    pub deterministic_ids_map: RwLock<HashMap<String, String>>,
     pub args: Args,
     pub root: PathBuf,
     pub meta: Meta,
     pub plugin_driver: PluginDriver,
     pub stats_info: StatsInfo,
-    pub resolvers: Resolvers,
+    pub resolvers: Arc<Resolvers>,
+    // resolved dependencies reused by the rebuilds, only in watch mode
+    pub resolve_cache: ResolveCache,
     pub static_cache: RwLock<MemoryChunkFileCache>,
     pub optimize_infos: Mutex<Option<Vec<OptimizeChunksInfo>>>,
+    pub output_fs: Arc<dyn OutputFileSystem>,
+    pub virtual_modules: VirtualModules,
+    pub sub_modules: SubModules,
+    pub active_entries: ActiveEntries,
+    pub build_cancellation: BuildCancellation,
+    pub remote_assets: RemoteAssets,
+    // html entry -> html without the bundled tags, see `HtmlEntryPlugin`
+    pub html_entries: RwLock<HashMap<PathBuf, HtmlEntry>>,
+    pub parse_fallbacks: ParseFallbacks,
+    pub module_rules: ModuleRules,
+    // graph changes queued by the plugins during the build
+    pub graph_mutations: GraphMutations,
+    pub chunk_hash_cache: ChunkHashCache,
+    // the maps of the js chunks reused by the rebuilds, only in watch mode
+    pub source_map_cache: SourceMapCache,
+    // folded into the chunk hashes, see `codegen_salt`
+    pub codegen_salt: u64,
+    // of the options consumed by the cached stages, see `CacheKeys`
+    pub cache_keys: CacheKeys,
+    pub diagnostics: Diagnostics,
+    // file -> hash of the content last precompressed, for watch mode
+    pub precompressed: Mutex<HashMap<String, u64>>,
+    pub file_handles: Arc<FileHandleLimiter>,
+    pub chunk_bytes_budget: ChunkBytesBudget,
+    pub runtime_features: RuntimeFeatures,
+    // contents of the source files, only shared by the compilers of a multi build
+    pub file_contents: Option<Arc<FileContentCache>>,
+    // of the module ids of the module graph
+    pub interner: Interner,
 }
 
 #[derive(Default)]
@@ -60,32 +118,72 @@ impl MemoryChunkFileCache {
         }
     }
 
-    pub fn write<T: AsRef<str>>(&mut self, path: T, content: Vec<u8>, hash: u64) -> Result<()> {
+    pub fn write<T: AsRef<str>>(
+        &mut self,
+        path: T,
+        content: Vec<u8>,
+        hash: u64,
+        output_fs: &dyn OutputFileSystem,
+    ) -> Result<()> {
         let str = path.as_ref();
 
         if let Some((_, in_mem_hash)) = self.content_map.get(str) {
             if *in_mem_hash != hash {
-                self.write_to_disk(str, &content)?;
+                self.write_to_output(str, &content, output_fs)?;
             }
         } else {
-            self.write_to_disk(str, &content)?;
+            self.write_to_output(str, &content, output_fs)?;
         }
         self.content_map
             .insert(path.as_ref().to_string(), (content, hash));
         Ok(())
     }
 
+    pub fn usage(&self) -> CacheUsage {
+        let bytes = self
+            .content_map
+            .values()
+            .map(|(content, _)| content.len())
+            .sum();
+        CacheUsage::new("chunk files in memory", self.content_map.len(), bytes)
+    }
+
     pub fn read<T: AsRef<str>>(&self, path: T) -> Option<Vec<u8>> {
         self.content_map
             .get(path.as_ref())
             .map(|(content, _)| content.clone())
     }
 
-    fn write_to_disk<T: AsRef<str>>(&self, path: T, content: &[u8]) -> Result<()> {
+    /// Drop the files which are not in the paths, e.g. the chunks removed in
+    /// watch mode, so they are not served anymore.
+    pub fn retain(
+        &mut self,
+        paths: &HashSet<String>,
+        output_fs: &dyn OutputFileSystem,
+    ) -> Result<Vec<String>> {
+        let stale = self
+            .content_map
+            .keys()
+            .filter(|path| !paths.contains(*path))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in &stale {
+            self.content_map.remove(path);
+            if let Some(root) = &self.root {
+                output_fs.remove(&root.join(path))?;
+            }
+        }
+        Ok(stale)
+    }
+
+    fn write_to_output<T: AsRef<str>>(
+        &self,
+        path: T,
+        content: &[u8],
+        output_fs: &dyn OutputFileSystem,
+    ) -> Result<()> {
         if let Some(root) = &self.root {
-            let path = root.join(path.as_ref());
-            fs::create_dir_all(path.parent().unwrap())?;
-            fs::write(path, content)?;
+            output_fs.write(&root.join(path.as_ref()), content)?;
         }
         Ok(())
     }
@@ -104,7 +202,12 @@ impl Context {
         hash: u64,
     ) -> Result<()> {
         let mut map = self.static_cache.write().unwrap();
-        map.write(path, content, hash)
+        map.write(path, content, hash, self.output_fs.as_ref())
+    }
+
+    pub fn retain_static_content(&self, paths: &HashSet<String>) -> Result<Vec<String>> {
+        let mut map = self.static_cache.write().unwrap();
+        map.retain(paths, self.output_fs.as_ref())
     }
 
     pub fn get_static_content<T: AsRef<str>>(&self, path: T) -> Option<Vec<u8>> {
@@ -120,7 +223,9 @@ impl Default for Context {
             numeric_ids_map.insert(item.to_string(), i);
         });
         let config: Config = Default::default();
-        let resolvers = get_resolvers(&config);
+        let resolvers = Arc::new(get_resolvers(&config));
+        let codegen_salt = codegen_salt(&config, env!("CARGO_PKG_VERSION"));
+        let cache_keys = CacheKeys::new(&config);
         Self {
             config,
             args: Args { watch: false },
@@ -128,14 +233,38 @@ impl Default for Context {
             module_graph: RwLock::new(ModuleGraph::new()),
             chunk_graph: RwLock::new(ChunkGraph::new()),
             assets_info: Mutex::new(HashMap::new()),
+            asset_origins: Default::default(),
             modules_with_missing_deps: RwLock::new(Vec::new()),
             meta: Meta::new(),
             plugin_driver: Default::default(),
             stats_info: StatsInfo::new(),
             resolvers,
+            resolve_cache: Default::default(),
             optimize_infos: Mutex::new(None),
             static_cache: Default::default(),
             numeric_ids_map: RwLock::new(numeric_ids_map),
+            deterministic_ids_map: Default::default(),
+            output_fs: Arc::new(DiskFileSystem::default()),
+            virtual_modules: Default::default(),
+            sub_modules: Default::default(),
+            active_entries: Default::default(),
+            build_cancellation: Default::default(),
+            remote_assets: Default::default(),
+            html_entries: Default::default(),
+            parse_fallbacks: Default::default(),
+            module_rules: Default::default(),
+            graph_mutations: Default::default(),
+            chunk_hash_cache: Default::default(),
+            source_map_cache: Default::default(),
+            codegen_salt,
+            cache_keys,
+            diagnostics: Default::default(),
+            precompressed: Default::default(),
+            file_handles: Default::default(),
+            chunk_bytes_budget: Default::default(),
+            runtime_features: Default::default(),
+            file_contents: None,
+            interner: Default::default(),
         }
     }
 }
@@ -211,9 +340,96 @@ impl CssMeta {
 }
 
 impl Context {
-    pub fn emit_assets(&self, origin_path: String, output_path: String) {
+    /// Emits the file at `origin_path` as `output_path` and returns the name
+    /// it's emitted as. A file with the same content as the one already
+    /// emitted as the name shares it, and a file with different content gets a
+    /// hash segment of its path if the name is `hashed`, or fails the build
+    /// otherwise, instead of overwriting the other one.
+    pub fn emit_assets(
+        &self,
+        origin_path: String,
+        output_path: String,
+        hashed: bool,
+    ) -> Result<String> {
         let mut assets_info = self.assets_info.lock().unwrap();
-        assets_info.insert(origin_path, output_path);
+        let mut asset_origins = self.asset_origins.lock().unwrap();
+        let mut output_path = output_path;
+        while let Some(existing) = asset_origins.get(&output_path).cloned() {
+            if existing == origin_path || self.same_asset_content(&existing, &origin_path) {
+                break;
+            }
+            // removed in watch mode, e.g. renamed to the file, the name goes
+            // to another file with the same content or is free
+            if !Path::new(&existing).exists() {
+                assets_info
+                    .retain(|origin, name| *name != output_path || Path::new(origin).exists());
+                match assets_info.iter().find(|(_, name)| **name == output_path) {
+                    Some((other, _)) => {
+                        asset_origins.insert(output_path.clone(), other.clone());
+                        continue;
+                    }
+                    None => {
+                        asset_origins.remove(&output_path);
+                        break;
+                    }
+                }
+            }
+            if !hashed {
+                return Err(anyhow!(
+                    "Asset name conflict: {} and {} are both emitted as {} with different content, add [hash] to the file name to tell them apart",
+                    existing,
+                    origin_path,
+                    output_path
+                ));
+            }
+            let disambiguated = disambiguated_asset_name(&output_path, &origin_path, self);
+            debug!(
+                "asset {} conflicts with {} at {}, emitted as {}",
+                origin_path, existing, output_path, disambiguated
+            );
+            output_path = disambiguated;
+        }
+        asset_origins
+            .entry(output_path.clone())
+            .or_insert_with(|| origin_path.clone());
+        assets_info.insert(origin_path, output_path.clone());
+        Ok(output_path)
+    }
+
+    fn same_asset_content(&self, a: &str, b: &str) -> bool {
+        let read = |path: &str| self.file_handles.run(|| Ok(std::fs::read(path)?));
+        matches!((read(a), read(b)), (Ok(a), Ok(b)) if a == b)
+    }
+
+    /// The emitted assets by output name, which is named by the content only,
+    /// so the same content referenced by several modules or at several paths
+    /// is emitted once.
+    pub fn emitted_assets(&self) -> BTreeMap<String, String> {
+        self.assets_info
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(origin_path, output_path)| (output_path.clone(), origin_path.clone()))
+            .collect()
+    }
+}
+
+// `icon.1a2b3c4d.svg` => `icon.1a2b3c4d.<hash of the path>.svg`
+fn disambiguated_asset_name(name: &str, origin_path: &str, context: &Context) -> String {
+    let relative = Path::new(origin_path)
+        .strip_prefix(&context.root)
+        .unwrap_or(Path::new(origin_path));
+    let segment = content_hash(
+        win_path(&relative.to_string_lossy()),
+        &context.config.output,
+    );
+    let base_start = name.rfind('/').map_or(0, |i| i + 1);
+    match name[base_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let dot = base_start + dot;
+            format!("{}.{}{}", &name[..dot], segment, &name[dot..])
+        }
+        _ => format!("{}.{}", name, segment),
     }
 }
 
@@ -227,6 +443,19 @@ impl Compiler {
         root: PathBuf,
         args: Args,
         extra_plugins: Option<Vec<Arc<dyn Plugin>>>,
+    ) -> Result<Self> {
+        Self::new_with_output_fs(config, root, args, extra_plugins, None)
+    }
+
+    // outputs are emitted to the given file system, e.g. a MemoryFileSystem for tests,
+    // if it's not specified, the dev server will use a MemoryFileSystem when writeToDisk
+    // is false, and others will write to disk
+    pub fn new_with_output_fs(
+        config: Config,
+        root: PathBuf,
+        args: Args,
+        extra_plugins: Option<Vec<Arc<dyn Plugin>>>,
+        output_fs: Option<Arc<dyn OutputFileSystem>>,
     ) -> Result<Self> {
         if !root.is_absolute() {
             return Err(anyhow!("root path must be absolute"));
@@ -259,16 +488,20 @@ impl Compiler {
             Arc::new(plugins::manifest::ManifestPlugin {}),
             Arc::new(plugins::copy::CopyPlugin {}),
             Arc::new(plugins::import::ImportPlugin {}),
+            Arc::new(plugins::build_info::BuildInfoPlugin::default()),
             // file types
             Arc::new(plugins::context_module::ContextModulePlugin {}),
             Arc::new(plugins::runtime::MakoRuntime {}),
             Arc::new(plugins::invalid_webpack_syntax::InvalidWebpackSyntaxPlugin {}),
             Arc::new(plugins::hmr_runtime::HMRRuntimePlugin {}),
             Arc::new(plugins::wasm_runtime::WasmRuntimePlugin {}),
+            Arc::new(plugins::image::ImagePlugin {}),
+            Arc::new(plugins::html_entry::HtmlEntryPlugin {}),
             Arc::new(plugins::async_runtime::AsyncRuntimePlugin {}),
             Arc::new(plugins::emotion::EmotionPlugin {}),
             Arc::new(plugins::tree_shaking::FarmTreeShake {}),
             Arc::new(plugins::detect_circular_dependence::LoopDetector {}),
+            Arc::new(plugins::empty_module::EmptyModulePlugin {}),
         ];
         plugins.extend(external_plugins);
         plugins.extend(builtin_plugins);
@@ -279,6 +512,40 @@ impl Compiler {
             plugins.push(Arc::new(plugins::imports_checker::ImportsChecker {}));
         }
 
+        if config.mode == Mode::Development {
+            plugins.push(Arc::new(plugins::eval_bailout::EvalBailoutPlugin {}));
+        }
+
+        if let Some(json_exports_check) = config.json_exports_check {
+            plugins.push(Arc::new(
+                plugins::json_exports_check::JsonExportsCheckPlugin {
+                    config: json_exports_check,
+                },
+            ));
+        }
+
+        if !config.stub.is_empty() {
+            plugins.push(Arc::new(plugins::stub::StubPlugin {}));
+        }
+
+        if let Some(peer_dependency_check) = config.peer_dependency_check {
+            plugins.push(Arc::new(
+                plugins::peer_dependency_check::PeerDependencyCheckPlugin {
+                    config: peer_dependency_check,
+                },
+            ));
+        }
+
+        if config.import_map.is_some() {
+            plugins.push(Arc::new(plugins::import_map::ImportMapPlugin {}));
+        }
+
+        if config.type_declaration.is_some() || config.css_modules.declarations {
+            plugins.push(Arc::new(
+                plugins::type_declaration::TypeDeclarationPlugin {},
+            ));
+        }
+
         if let Some(progress) = &config.progress {
             plugins.push(Arc::new(plugins::progress::ProgressPlugin::new(
                 plugins::progress::ProgressPluginOptions {
@@ -300,7 +567,8 @@ impl Compiler {
                 plugins::duplicate_package_checker::DuplicatePackageCheckerPlugin::new()
                     .show_help(duplicate_package_checker.show_help)
                     .emit_error(duplicate_package_checker.emit_error)
-                    .verbose(duplicate_package_checker.verbose),
+                    .verbose(duplicate_package_checker.verbose)
+                    .allow(duplicate_package_checker.allow.clone()),
             ));
         }
 
@@ -311,7 +579,9 @@ impl Compiler {
         if config.output.mode == OutputMode::Bundless {
             plugins.insert(
                 0,
-                Arc::new(plugins::bundless_compiler::BundlessCompilerPlugin {}),
+                Arc::new(plugins::bundless_compiler::BundlessCompilerPlugin {
+                    preserve_modules: config.output.preserve_modules,
+                }),
             );
         }
 
@@ -323,6 +593,23 @@ impl Compiler {
             plugins.push(Arc::new(plugins::ssu::SUPlus::new()));
         }
 
+        if args.watch
+            && config.mode == Mode::Development
+            && let Some(pre_bundle) = &config.pre_bundle
+        {
+            plugins.push(Arc::new(plugins::pre_bundle::PreBundlePlugin::new(
+                pre_bundle,
+            )));
+        }
+
+        if config.remote_css == RemoteCssConfig::Download {
+            plugins.push(Arc::new(plugins::remote_css::RemoteCSSPlugin {}));
+        }
+
+        if args.watch && config.dev_server.is_some() {
+            plugins.push(Arc::new(plugins::inspect::InspectPlugin {}));
+        }
+
         if args.watch && config.experimental.central_ensure {
             plugins.push(Arc::new(plugins::central_ensure::CentralChunkEnsure {}));
         }
@@ -360,6 +647,24 @@ impl Compiler {
             );
         }
 
+        // before buildManifest and cacheRules, which list the manifest too
+        if config.loadable_manifest.is_some() {
+            plugins.push(Arc::new(
+                plugins::loadable_manifest::LoadableManifestPlugin {},
+            ));
+        }
+
+        if config.build_manifest.is_some() {
+            plugins.push(Arc::new(plugins::build_manifest::BuildManifestPlugin::new(
+                &config, &root,
+            )?));
+        }
+
+        // after buildManifest, so that the manifest is classified too
+        if config.cache_rules.is_some() {
+            plugins.push(Arc::new(plugins::cache_rules::CacheRulesPlugin {}));
+        }
+
         let ignores = config
             .ignores
             .iter()
@@ -371,35 +676,133 @@ impl Compiler {
 
         plugin_driver.modify_config(&mut config, &root, &args)?;
 
-        let resolvers = get_resolvers(&config);
+        let resolvers = Arc::new(get_resolvers(&config));
         let mut numeric_ids_map = HashMap::new();
         SWC_HELPERS.iter().enumerate().for_each(|(i, item)| {
             numeric_ids_map.insert(item.to_string(), i);
         });
+        let output_fs = output_fs.unwrap_or_else(|| {
+            if args.watch && !config.write_to_disk {
+                Arc::new(MemoryFileSystem::new())
+            } else {
+                Arc::new(DiskFileSystem::default())
+            }
+        });
+        let file_handles = Arc::new(FileHandleLimiter::from_config(config.max_open_files));
+        let chunk_bytes_budget = ChunkBytesBudget::from_config(config.max_in_flight_chunk_bytes);
+        let codegen_salt = codegen_salt(&config, env!("CARGO_PKG_VERSION"));
+        let cache_keys = CacheKeys::new(&config);
+        let module_rules = ModuleRules::new(&config.module_rules)?;
+        let remote_assets = RemoteAssets::new(&root);
         Ok(Self {
             context: Arc::new(Context {
-                static_cache: if config.write_to_disk {
-                    RwLock::new(MemoryChunkFileCache::new(Some(config.output.path.clone())))
-                } else {
-                    Default::default()
-                },
+                static_cache: RwLock::new(MemoryChunkFileCache::new(Some(
+                    config.output.path.clone(),
+                ))),
                 config,
                 args,
                 root,
                 module_graph: RwLock::new(ModuleGraph::new()),
                 chunk_graph: RwLock::new(ChunkGraph::new()),
                 assets_info: Mutex::new(HashMap::new()),
+                asset_origins: Default::default(),
                 modules_with_missing_deps: RwLock::new(Vec::new()),
                 meta: Meta::new(),
                 plugin_driver,
                 numeric_ids_map: RwLock::new(numeric_ids_map),
+                deterministic_ids_map: Default::default(),
                 stats_info: StatsInfo::new(),
                 resolvers,
+                resolve_cache: Default::default(),
                 optimize_infos: Mutex::new(None),
+                output_fs,
+                virtual_modules: Default::default(),
+                sub_modules: Default::default(),
+                active_entries: Default::default(),
+                build_cancellation: Default::default(),
+                remote_assets,
+                html_entries: Default::default(),
+                parse_fallbacks: Default::default(),
+                module_rules,
+                graph_mutations: Default::default(),
+                chunk_hash_cache: Default::default(),
+                source_map_cache: Default::default(),
+                codegen_salt,
+                cache_keys,
+                diagnostics: Default::default(),
+                precompressed: Default::default(),
+                file_handles,
+                chunk_bytes_budget,
+                runtime_features: Default::default(),
+                file_contents: None,
+                interner: Default::default(),
             }),
         })
     }
 
+    /// Build only the entries of the names in watch mode, the others are
+    /// built when they're requested by the dev server, with the entries
+    /// they depend on.
+    pub fn only_entries(&self, names: &[String]) -> Result<()> {
+        let entry = &self.context.config.entry;
+        if let Some(name) = names.iter().find(|name| !entry.contains_key(*name)) {
+            return Err(anyhow!(
+                "entry {} is not found, the entries are: {}",
+                name,
+                entry.keys().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        self.context.active_entries.only(self.with_depend_on(names));
+        Ok(())
+    }
+
+    // the names with the entries they depend on
+    pub(crate) fn with_depend_on(&self, names: &[String]) -> Vec<String> {
+        let mut result = vec![];
+        let mut pending = names.to_vec();
+        while let Some(name) = pending.pop() {
+            if result.contains(&name) {
+                continue;
+            }
+            if let Some(entry) = self.context.config.entry.get(&name) {
+                pending.extend(entry.depend_on.iter().cloned());
+            }
+            result.push(name);
+        }
+        result
+    }
+
+    pub(crate) fn entry_files(&self, names: &[String]) -> Vec<crate::ast::file::File> {
+        names
+            .iter()
+            .filter_map(|name| self.context.config.entry.get(name))
+            .map(|entry| {
+                let is_js = entry
+                    .import
+                    .extension()
+                    .is_some_and(|ext| JS_EXTENSIONS.contains(&ext.to_string_lossy().as_ref()));
+                let is_webworker = entry.is_webworker();
+                // the same as the ids of the resolved files
+                let mut entry = encode_path(&entry.import.to_string_lossy());
+                let is_browser = matches!(
+                    self.context.config.platform,
+                    crate::config::Platform::Browser
+                );
+                let watch = self.context.args.watch;
+                let hmr = self.context.config.hmr.is_some();
+                // the workers have no document to update
+                if is_browser && watch && hmr && !is_webworker {
+                    entry = format!("{}?hmr", entry);
+                } else if is_js && !self.context.config.entry_prelude.is_empty() {
+                    // the prelude is run by a wrapper, so it's not mixed
+                    // into the entry itself
+                    entry = format!("{}?prelude", entry);
+                }
+                crate::ast::file::File::new_entry(entry, self.context.clone())
+            })
+            .collect()
+    }
+
     pub fn compile(&self) -> Result<()> {
         // 先清空 dist 目录
         if self.context.config.clean {
@@ -414,39 +817,70 @@ impl Compiler {
             self.context.config.mode
         )
         .green();
-        println!("{}", building_with_message);
+        if !self.context.config.quiet {
+            println!("{}", building_with_message);
+        }
+        let t_build = Instant::now();
         {
             crate::mako_profile_scope!("Build Stage");
-            let files = self
+            let names = self
                 .context
                 .config
                 .entry
-                .values()
-                .map(|entry| {
-                    let mut entry = entry.to_string_lossy().to_string();
-                    let is_browser = matches!(
-                        self.context.config.platform,
-                        crate::config::Platform::Browser
-                    );
-                    let watch = self.context.args.watch;
-                    let hmr = self.context.config.hmr.is_some();
-                    if is_browser && watch && hmr {
-                        entry = format!("{}?hmr", entry);
-                    }
-                    crate::ast::file::File::new_entry(entry, self.context.clone())
-                })
-                .collect();
+                .keys()
+                .filter(|name| self.context.active_entries.is_active(name))
+                .cloned()
+                .collect::<Vec<_>>();
+            let files = self.entry_files(&names);
             self.context.plugin_driver.build_start(&self.context)?;
 
             self.build(files)?;
 
+            debug!(
+                "{} files under node_modules skipped syntax lowering",
+                self.syntax_lowering_skipped()
+            );
             debug!("start after build");
 
             self.context
                 .plugin_driver
                 .after_build(&self.context, self)?;
         }
+        self.context.stats_info.timings.lock().unwrap().build =
+            t_build.elapsed().as_millis() as u64;
+
+        self.generate_bundle(true, t_compiler, start_time)
+    }
 
+    /// Rebuild the changed files with the module graph and the caches of the
+    /// previous build, and generate the bundle again, e.g. for the callers
+    /// which keep the compiler alive between the builds. Returns false if
+    /// nothing is changed. The diagnostics are of the rebuild only.
+    ///
+    /// It requires a compiler of the watch mode, since the module graph is
+    /// tree shaken in place by the other builds.
+    pub fn rebuild(&self, paths: Vec<PathBuf>) -> Result<bool> {
+        if !self.context.args.watch {
+            return Err(anyhow!("rebuild requires a compiler of the watch mode"));
+        }
+        let t_compiler = Instant::now();
+        let start_time = chrono::Local::now().timestamp_millis();
+        self.context.diagnostics.clear();
+        let result = self.update(paths)?;
+        if !result.is_updated() {
+            return Ok(false);
+        }
+        self.context.stats_info.clear_assets();
+        self.generate_bundle(false, t_compiler, start_time)?;
+        Ok(true)
+    }
+
+    fn generate_bundle(
+        &self,
+        is_first_compile: bool,
+        t_compiler: Instant,
+        start_time: i64,
+    ) -> Result<()> {
         self.context.plugin_driver.before_generate(&self.context)?;
 
         if let ModuleIdStrategy::Numeric = self.context.config.module_id_strategy {
@@ -457,7 +891,19 @@ impl Compiler {
                 |module, id| {
                     let mut numeric_ids_map = self.context.numeric_ids_map.write().unwrap();
                     // reserved ten indexes for swc helper and others runtime module
-                    numeric_ids_map.insert(module.id.id.clone(), id + 10);
+                    numeric_ids_map.insert(module.id.id.to_string(), id + 10);
+                },
+            )
+        }
+        if let ModuleIdStrategy::Deterministic = self.context.config.module_id_strategy {
+            let module_graph = self.context.module_graph.read().unwrap();
+            let mut deterministic_ids_map = self.context.deterministic_ids_map.write().unwrap();
+            assign_deterministic_ids(
+                module_graph.modules(),
+                |module| relative_module_id(&module.id.id, &self.context.root),
+                DETERMINISTIC_ID_LENGTH,
+                |module, id| {
+                    deterministic_ids_map.insert(module.id.id.to_string(), id);
                 },
             )
         }
@@ -469,23 +915,32 @@ impl Compiler {
             thread_pool::scope(|_| self.generate())
         };
         let t_compiler_duration = t_compiler.elapsed();
+        self.context.stats_info.timings.lock().unwrap().total =
+            t_compiler_duration.as_millis() as u64;
         match result {
             Ok(mut stats) => {
-                stats.start_time = start_time;
-                stats.end_time = chrono::Local::now().timestamp_millis();
-                println!(
-                    "{}",
-                    format!(
-                        "✓ Built in {}",
-                        format!("{}ms", t_compiler_duration.as_millis()).bold()
-                    )
-                    .green()
-                );
-                if !self.context.args.watch {
-                    println!("{}", "Complete!".bold());
+                self.context
+                    .diagnostics
+                    .summarize(self.context.config.quiet)?;
+                if self.context.config.stats_timestamps() {
+                    stats.start_time = start_time;
+                    stats.end_time = chrono::Local::now().timestamp_millis();
+                }
+                if !self.context.config.quiet {
+                    println!(
+                        "{}",
+                        format!(
+                            "✓ Built in {}",
+                            format!("{}ms", t_compiler_duration.as_millis()).bold()
+                        )
+                        .green()
+                    );
+                    if !self.context.args.watch {
+                        println!("{}", "Complete!".bold());
+                    }
                 }
                 let params = PluginGenerateEndParams {
-                    is_first_compile: true,
+                    is_first_compile,
                     time: t_compiler.elapsed().as_millis() as i64,
                     stats,
                 };
@@ -499,18 +954,500 @@ impl Compiler {
         }
     }
 
+    // the files excluded by transform.include and transform.exclude
+    fn syntax_lowering_skipped(&self) -> usize {
+        let module_graph = self.context.module_graph.read().unwrap();
+        module_graph
+            .modules()
+            .iter()
+            .filter_map(|module| module.info.as_ref())
+            .filter(|info| {
+                info.file.is_under_node_modules
+                    && info.ast.as_script().is_some()
+                    && !self.context.transpiles(&info.file)
+            })
+            .count()
+    }
+
     pub fn full_hash(&self) -> u64 {
         crate::mako_profile_function!();
         let cg = self.context.chunk_graph.read().unwrap();
         let mg = self.context.module_graph.read().unwrap();
-        cg.full_hash(&mg)
+        cg.full_hash(
+            &mg,
+            &self.context.chunk_hash_cache,
+            self.context.codegen_salt,
+        )
     }
-    fn clean_dist(&self) -> Result<()> {
+
+    /// The entries and the estimated bytes of the module graph and the caches
+    /// kept between the builds, which is printed with `--memory-report`.
+    pub fn memory_report(&self) -> MemoryReport {
+        let context = &self.context;
+        let mut caches = vec![];
+        {
+            let module_graph = context.module_graph.read().unwrap();
+            let modules = module_graph.modules();
+            let bytes = modules
+                .iter()
+                .map(|module| module.id.id.len() + module.info.as_ref().map_or(0, |i| i.raw.len()))
+                .sum();
+            caches.push(CacheUsage::new("module graph", modules.len(), bytes));
+        }
+        {
+            let chunk_graph = context.chunk_graph.read().unwrap();
+            let chunks = chunk_graph.get_all_chunks();
+            // the ids share the interned strings of the module graph
+            let bytes = chunks
+                .iter()
+                .map(|chunk| chunk.get_modules().len() * std::mem::size_of::<ModuleId>())
+                .sum();
+            caches.push(CacheUsage::new("chunk graph", chunks.len(), bytes));
+        }
+        {
+            let missing_deps = context.modules_with_missing_deps.read().unwrap();
+            let bytes = missing_deps.iter().map(String::len).sum();
+            caches.push(CacheUsage::new(
+                "modules with missing deps",
+                missing_deps.len(),
+                bytes,
+            ));
+        }
+        caches.push(context.resolve_cache.usage());
+        caches.push(context.chunk_hash_cache.usage());
+        caches.push(context.static_cache.read().unwrap().usage());
+        caches.extend(render_cache_usages());
+        caches.extend(context.source_map_cache.usages());
+        caches.push(context.interner.usage());
+        MemoryReport { caches }
+    }
+
+    pub(crate) fn clean_dist(&self) -> Result<()> {
         // compiler 前清除 dist，如果后续 dev 环境不在 output_path 里，需要再补上 dev 的逻辑
         let output_path = &self.context.config.output.path;
-        if fs::metadata(output_path).is_ok() {
-            fs::remove_dir_all(output_path)?;
+        self.context.output_fs.remove(output_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+    use crate::config::Mode;
+    use crate::testing::{compile_fixture, TempDir};
+
+    fn build_files(root: &Path) -> Vec<String> {
+        let compiler = compile_fixture(
+            root,
+            Some(
+                r#"{"mode":"production","minify":false,"hash":true,"moduleIdStrategy":"deterministic"}"#,
+            ),
+        )
+        .unwrap();
+        compiler
+            .context
+            .output_fs
+            .list(&root.join("dist"))
+            .unwrap()
+            .iter()
+            .map(|file| file.file_name().unwrap().to_string_lossy().to_string())
+            .filter(|file| file.ends_with(".js"))
+            .collect()
+    }
+
+    #[test]
+    fn test_deterministic_module_ids() {
+        let root = TempDir::new("deterministic-module-ids");
+        fs::write(
+            root.join("index.js"),
+            "import './a'; import('./b'); import('./c');",
+        )
+        .unwrap();
+        fs::write(root.join("a.js"), "export const a = 1;").unwrap();
+        fs::write(
+            root.join("b.js"),
+            "import { a } from './a'; console.log(a);",
+        )
+        .unwrap();
+        fs::write(root.join("c.js"), "console.log('c');").unwrap();
+        let files = build_files(&root);
+
+        // an unrelated module which is sorted before the others
+        fs::write(root.join("0.js"), "console.log(0);").unwrap();
+        fs::write(
+            root.join("index.js"),
+            "import './0'; import './a'; import('./b'); import('./c');",
+        )
+        .unwrap();
+        let new_files = build_files(&root);
+
+        let async_files = files
+            .iter()
+            .filter(|file| !file.starts_with("index."))
+            .collect::<Vec<_>>();
+        assert_eq!(async_files.len(), 2);
+        for file in async_files {
+            assert!(new_files.contains(file), "{} should be unchanged", file);
         }
-        Ok(())
+        assert!(!new_files.contains(
+            files
+                .iter()
+                .find(|file| file.starts_with("index."))
+                .unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_entry_prelude() {
+        let compiler = crate::utils::test_helper::setup_compiler("test/build/entry-prelude", false);
+        compiler.compile().unwrap();
+        let output_path = &compiler.context.config.output.path;
+
+        // the chunk is still named after the entry
+        let output = std::process::Command::new("node")
+            .arg("-e")
+            .arg(format!(
+                "require({:?}); console.log(JSON.stringify(globalThis.order));",
+                output_path.join("main.js"),
+            ))
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            r#"["prelude","entry build-1"]"#
+        );
+
+        // the prelude is in the wrapper instead of the entry in the source map
+        let map: serde_json::Value =
+            serde_json::from_slice(&fs::read(output_path.join("main.js.map")).unwrap()).unwrap();
+        let sources = map["sources"].as_array().unwrap();
+        let source_of = |name: &str| {
+            let index = sources
+                .iter()
+                .position(|source| source.as_str().unwrap().ends_with(name))
+                .unwrap_or_else(|| panic!("{} not found in {:?}", name, sources));
+            map["sourcesContent"][index].as_str().unwrap().to_string()
+        };
+        assert!(source_of("src/index.js?prelude").contains("build-1"));
+        assert!(!source_of("src/index.js").contains("build-1"));
+    }
+
+    fn build_assets(root: &Path) -> Vec<String> {
+        let compiler =
+            compile_fixture(root, Some(r#"{"mode":"production","minify":false}"#)).unwrap();
+        compiler
+            .context
+            .output_fs
+            .list(&root.join("dist"))
+            .unwrap()
+            .iter()
+            .map(|file| file.file_name().unwrap().to_string_lossy().to_string())
+            .filter(|file| file.ends_with(".png"))
+            .collect()
+    }
+
+    #[test]
+    fn test_asset_file_names() {
+        let root = TempDir::new("asset-file-names");
+        fs::create_dir_all(root.join("components")).unwrap();
+        fs::create_dir_all(root.join("copy")).unwrap();
+        // larger than inlineLimit
+        let logo = (0..20000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        fs::write(root.join("logo.png"), &logo).unwrap();
+        fs::write(root.join("index.js"), "import './components/Header';").unwrap();
+        fs::write(
+            root.join("components/Header.js"),
+            "import logo from '../logo.png'; console.log(logo);",
+        )
+        .unwrap();
+        let assets = build_assets(&root);
+        assert_eq!(assets.len(), 1);
+
+        // another importer, and the same content at another path
+        fs::remove_file(root.join("components/Header.js")).unwrap();
+        fs::write(
+            root.join("components/Nav.js"),
+            "import logo from '../logo.png'; export default logo;",
+        )
+        .unwrap();
+        fs::write(root.join("copy/logo.png"), &logo).unwrap();
+        fs::write(
+            root.join("index.js"),
+            "import nav from './components/Nav'; import copy from './copy/logo.png'; console.log(nav, copy);",
+        )
+        .unwrap();
+        assert_eq!(build_assets(&root), assets);
+    }
+
+    #[test]
+    fn test_max_open_files() {
+        let root = TempDir::new("max-open-files");
+        let count = 3000;
+        let imports = (0..count)
+            .map(|i| {
+                fs::write(
+                    root.join(format!("{}.js", i)),
+                    format!("console.log({});", i),
+                )
+                .unwrap();
+                format!("import './{}';", i)
+            })
+            .collect::<Vec<_>>();
+        fs::write(root.join("index.js"), imports.join("\n")).unwrap();
+
+        let compiler = compile_fixture(&root, Some(r#"{"maxOpenFiles":4}"#)).unwrap();
+        assert_eq!(compiler.context.file_handles.limit(), 4);
+        assert_eq!(
+            compiler
+                .context
+                .module_graph
+                .read()
+                .unwrap()
+                .modules()
+                .len(),
+            count + 1
+        );
+    }
+
+    fn build_outputs(
+        root: &Path,
+        output_name: &str,
+        mode: Mode,
+        max_in_flight_chunk_bytes: Option<usize>,
+    ) -> BTreeMap<String, Vec<u8>> {
+        // chunks are rendered in parallel in watch mode of development
+        let watch = mode == Mode::Development;
+        let output_dir = TempDir::new(output_name);
+        let output: &Path = &output_dir;
+        let mut config = Config::new(root, None, None).unwrap();
+        config.mode = mode;
+        config.output.path = output.to_path_buf();
+        config.max_in_flight_chunk_bytes = max_in_flight_chunk_bytes;
+        let compiler = Compiler::new(config, root.to_path_buf(), Args { watch }, None).unwrap();
+        compiler.compile().unwrap();
+        let stats = serde_json::to_string_pretty(&compiler.create_stats_info())
+            .unwrap()
+            .replace(&output.to_string_lossy().to_string(), "<output>");
+        let output_fs = DiskFileSystem {};
+        let mut outputs: BTreeMap<String, Vec<u8>> = output_fs
+            .list(output)
+            .unwrap()
+            .into_iter()
+            .map(|file| {
+                let content = fs::read(&file).unwrap();
+                let file = file
+                    .strip_prefix(output)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                (file, content)
+            })
+            .collect();
+        // passed to the generateEnd hooks, e.g. to be written as stats.json
+        outputs.insert("<stats>".to_string(), stats.into_bytes());
+        outputs
+    }
+
+    #[test]
+    fn test_max_in_flight_chunk_bytes() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/reproducible");
+        let outputs = build_outputs(&root, "in-flight-a", Mode::Production, None);
+        // every chunk is generated alone
+        let throttled_outputs = build_outputs(&root, "in-flight-b", Mode::Production, Some(1));
+        assert_eq!(
+            outputs.keys().collect::<Vec<_>>(),
+            throttled_outputs.keys().collect::<Vec<_>>()
+        );
+        for (file, content) in &outputs {
+            assert!(content == &throttled_outputs[file], "{} differs", file);
+        }
+    }
+
+    #[test]
+    fn test_reproducible_outputs() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/reproducible");
+        for mode in [Mode::Production, Mode::Development] {
+            let outputs = build_outputs(&root, "reproducible-a", mode.clone(), None);
+            let other_outputs = build_outputs(&root, "reproducible-b", mode.clone(), None);
+            assert!(outputs.keys().any(|file| file.ends_with(".js.map")));
+            assert!(outputs.contains_key("icons/e.svg"));
+            assert!(outputs.contains_key("asset-manifest.json"));
+            // not in watch mode
+            assert_eq!(
+                outputs.contains_key("build-manifest.json"),
+                mode == Mode::Production
+            );
+            assert_eq!(
+                outputs.keys().collect::<Vec<_>>(),
+                other_outputs.keys().collect::<Vec<_>>()
+            );
+            for (file, content) in &outputs {
+                assert!(
+                    content == &other_outputs[file],
+                    "{} of {:?} differs:\n{}\n{}",
+                    file,
+                    mode,
+                    String::from_utf8_lossy(content),
+                    String::from_utf8_lossy(&other_outputs[file])
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rebuild() {
+        let root = TempDir::new("rebuild");
+        fs::write(root.join("index.js"), "import './a'; import './b';").unwrap();
+        fs::write(root.join("a.js"), "console.log('version 1');").unwrap();
+        fs::write(root.join("b.js"), "console.log('b');").unwrap();
+        let config = Config::new(&root, None, Some(r#"{"hmr":false}"#)).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        compiler.compile().unwrap();
+        let read_index =
+            || String::from_utf8(compiler.context.get_static_content("index.js").unwrap()).unwrap();
+        assert!(read_index().contains("version 1"));
+
+        fs::write(root.join("a.js"), "console.log('version 2');").unwrap();
+        assert!(compiler.rebuild(vec![root.join("a.js")]).unwrap());
+        let index = read_index();
+        assert!(index.contains("version 2"), "{}", index);
+        assert!(index.contains("console.log('b')"), "{}", index);
+        assert!(compiler
+            .context
+            .stats_info
+            .get_assets()
+            .iter()
+            .any(|asset| asset.hashname == "index.js"));
+
+        // saved without changes
+        assert!(!compiler.rebuild(vec![root.join("a.js")]).unwrap());
+
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: false }, None).unwrap();
+        compiler.compile().unwrap();
+        assert!(compiler.rebuild(vec![root.join("a.js")]).is_err());
+    }
+
+    #[test]
+    fn test_emit_assets_name_conflicts() {
+        let root = TempDir::new("emit-assets-name-conflicts");
+        for (dir, content) in [
+            ("a", "<svg>a</svg>"),
+            ("b", "<svg>b</svg>"),
+            ("c", "<svg>a</svg>"),
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join("icon.svg"), content).unwrap();
+        }
+        let context = Context {
+            root: root.to_path_buf(),
+            ..Default::default()
+        };
+        let icon = |dir: &str| {
+            root.join(dir)
+                .join("icon.svg")
+                .to_string_lossy()
+                .to_string()
+        };
+
+        // the same content shares the name
+        assert_eq!(
+            context
+                .emit_assets(icon("a"), "icon.svg".to_string(), false)
+                .unwrap(),
+            "icon.svg"
+        );
+        assert_eq!(
+            context
+                .emit_assets(icon("c"), "icon.svg".to_string(), false)
+                .unwrap(),
+            "icon.svg"
+        );
+        assert_eq!(context.emitted_assets().len(), 1);
+
+        // different content fails without the hash in the name
+        let err = context
+            .emit_assets(icon("b"), "icon.svg".to_string(), false)
+            .unwrap_err();
+        assert!(err.to_string().contains("Asset name conflict"), "{}", err);
+
+        // and gets a hash segment of its path with the hash
+        context
+            .emit_assets(icon("a"), "icon.1a2b3c4d.svg".to_string(), true)
+            .unwrap();
+        let name = context
+            .emit_assets(icon("b"), "icon.1a2b3c4d.svg".to_string(), true)
+            .unwrap();
+        let segment = content_hash("b/icon.svg", &context.config.output);
+        assert_eq!(name, format!("icon.1a2b3c4d.{}.svg", segment));
+        assert_eq!(context.emitted_assets()[&name], icon("b"));
+        assert_eq!(context.emitted_assets()["icon.1a2b3c4d.svg"], icon("a"));
+
+        // the name of a removed file is taken over
+        fs::remove_file(icon("a")).unwrap();
+        fs::remove_file(icon("c")).unwrap();
+        assert_eq!(
+            context
+                .emit_assets(icon("b"), "icon.svg".to_string(), false)
+                .unwrap(),
+            "icon.svg"
+        );
+        assert_eq!(context.emitted_assets()["icon.svg"], icon("b"));
+    }
+
+    #[test]
+    fn test_asset_filename_conflicts() {
+        let root = TempDir::new("asset-filename-conflicts");
+        for (dir, content) in [("a", "a"), ("b", "b"), ("c", "a")] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join("logo.png"), content).unwrap();
+        }
+        fs::write(
+            root.join("mako.config.json"),
+            r#"{ "inlineLimit": 0, "output": { "assetFilename": "static/[name].[ext]" } }"#,
+        )
+        .unwrap();
+        let build = |index: &str| {
+            fs::write(root.join("index.js"), index).unwrap();
+            compile_fixture(&root, None).map(|compiler| {
+                let mut files = compiler
+                    .context
+                    .output_fs
+                    .list(&root.join("dist/static"))
+                    .unwrap()
+                    .iter()
+                    .map(|file| file.file_name().unwrap().to_string_lossy().to_string())
+                    .collect::<Vec<_>>();
+                files.sort();
+                files
+            })
+        };
+
+        let files =
+            build("import a from './a/logo.png'; import c from './c/logo.png'; console.log(a, c);")
+                .unwrap();
+        assert_eq!(files, vec!["logo.png"]);
+
+        let err =
+            build("import a from './a/logo.png'; import b from './b/logo.png'; console.log(a, b);")
+                .unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("are both emitted as static/logo.png"),
+            "{:#}",
+            err
+        );
     }
 }
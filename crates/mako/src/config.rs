@@ -1,30 +1,72 @@
 mod analyze;
+mod async_global_side_effects;
+mod build_manifest;
+mod cache_keys;
+mod cache_rules;
 mod code_splitting;
+mod config_file;
+mod critical_css;
+mod csp_report;
+mod css_lowering;
+mod css_modules;
+mod default_import_check;
 mod dev_server;
 mod devtool;
+mod diagnostics;
+mod dual_build;
 mod duplicate_package_checker;
+mod duplicated_modules;
+mod entry;
+mod env;
+mod env_object;
 mod experimental;
 mod external;
+mod extract_css;
+mod fully_dynamic_import;
 mod generic_usize;
+mod graph;
 mod hmr;
+mod ignore;
+mod image_optimization;
+mod import_map;
 mod inline_css;
+mod json_exports_check;
+mod license_comments;
+mod loadable_manifest;
 mod macros;
 mod manifest;
 mod minifish;
+mod minify;
 mod mode;
 mod module_id_strategy;
+mod module_rules;
+mod module_types;
+mod named_exports_check;
+mod native_addons;
 mod optimization;
 mod output;
+mod parse_fallback;
+mod peer_dependency_check;
+mod polyfill;
+mod pre_bundle;
+mod precompress;
 mod progress;
 mod provider;
+mod public_path;
 mod px2rem;
 mod react;
+mod remote_css;
 mod resolve;
 mod rsc_client;
 mod rsc_server;
+mod side_effect_only_imports;
+mod source_map;
 mod stats;
+mod stub;
+mod transform;
 mod transform_import;
 mod tree_shaking;
+mod type_declaration;
 mod umd;
 mod watch;
 
@@ -34,49 +76,107 @@ use std::path::{Path, PathBuf};
 
 pub use analyze::AnalyzeConfig;
 use anyhow::{anyhow, Result};
+pub use async_global_side_effects::{
+    deserialize_async_global_side_effects, AsyncGlobalSideEffectsConfig,
+};
+pub use build_manifest::{deserialize_build_manifest, BuildManifestConfig};
+pub(crate) use cache_keys::sorted_json;
+pub use cache_keys::{CacheKeys, OptionGroup};
+pub use cache_rules::{deserialize_cache_rules, CacheRule, CacheRuleOverride, CacheRulesConfig};
 pub use code_splitting::*;
 use colored::Colorize;
 use config;
+use config::builder::DefaultState;
+use config::ConfigBuilder;
+pub use config_file::{find_config_file, CONFIG_FILES};
+use config_file::{is_script_config, load_script_config, validate_config_keys};
+pub use critical_css::{deserialize_critical_css, CriticalCssConfig, CriticalCssEntryConfig};
+pub use csp_report::{deserialize_csp_report, CspReportConfig};
+pub use css_lowering::{CssDirection, CssLoweringConfig};
+pub use css_modules::CssModulesConfig;
+pub use default_import_check::{deserialize_default_import_check, DefaultImportCheckConfig};
 pub use dev_server::{deserialize_dev_server, DevServerConfig};
-pub use devtool::{deserialize_devtool, DevtoolConfig};
+pub use devtool::{deserialize_devtool, DevtoolConfig, SourceMapIgnoreListConfig};
+pub use diagnostics::{DiagnosticIgnoreRule, DiagnosticsConfig};
+pub use dual_build::{deserialize_dual_build, DualBuildConfig};
 pub use duplicate_package_checker::{
     deserialize_check_duplicate_package, DuplicatePackageCheckerConfig,
 };
+pub use duplicated_modules::{deserialize_duplicated_modules, DuplicatedModulesConfig};
+use entry::{validate_depend_on, validate_webworker};
+pub use entry::{EntryConfig, EntryPreludeConfig, EntryTarget};
+use env::env_configs;
+pub use env_object::{deserialize_env_object, EnvObjectConfig};
 use experimental::ExperimentalConfig;
 pub use external::{
     ExternalAdvanced, ExternalAdvancedSubpath, ExternalAdvancedSubpathConverter,
     ExternalAdvancedSubpathRule, ExternalAdvancedSubpathTarget, ExternalConfig,
 };
+pub use extract_css::{deserialize_extract_css, ExtractCssConfig, ExtractCssGroup};
+pub use fully_dynamic_import::{deserialize_fully_dynamic_import, FullyDynamicImportConfig};
 pub use generic_usize::GenericUsizeDefault;
+pub use graph::{deserialize_graph, GraphConfig, GraphFormat};
 pub use hmr::{deserialize_hmr, HmrConfig};
+use ignore::load_ignore_file;
+pub use ignore::{matched_ignore_rule, IGNORE_FILE};
+pub use image_optimization::{deserialize_image_optimization, ImageOptimizationConfig};
+pub use import_map::{deserialize_import_map, ImportMapConfig};
 pub use inline_css::{deserialize_inline_css, InlineCssConfig};
+pub use json_exports_check::{deserialize_json_exports_check, JsonExportsCheckConfig};
+pub use license_comments::{deserialize_license_comments, LicenseCommentsConfig};
+pub use loadable_manifest::{deserialize_loadable_manifest, LoadableManifestConfig};
 pub use manifest::{deserialize_manifest, ManifestConfig};
 use miette::{miette, ByteOffset, Diagnostic, NamedSource, SourceOffset, SourceSpan};
 pub use minifish::{deserialize_minifish, MinifishConfig};
+pub use minify::{deserialize_minify, KeepNamesConfig, MinifyConfig};
 pub use mode::Mode;
 pub use module_id_strategy::ModuleIdStrategy;
+pub use module_rules::{MatchedRules, ModuleRule, ModuleRuleLoader, ModuleRuleUse, ModuleRules};
+pub use module_types::{ModuleKind, ModuleTypeRule};
+pub use named_exports_check::{deserialize_named_exports_check, NamedExportsCheckConfig};
+pub use native_addons::NativeAddonsConfig;
 pub use optimization::{deserialize_optimization, OptimizationConfig};
-use output::get_default_chunk_loading_global;
-pub use output::{CrossOriginLoading, OutputConfig, OutputMode};
+use output::{get_default_chunk_loading_global, get_default_unique_name};
+pub use output::{
+    has_content_hash, ChunkLoading, CrossOriginLoading, HashFunction, OutputConfig, OutputMode,
+    RelativeUrlBase, RuntimeTarget,
+};
+pub use parse_fallback::{deserialize_parse_fallback, ParseFallbackConfig, ParseFallbackStep};
+use path_clean::PathClean;
+pub use peer_dependency_check::{deserialize_peer_dependency_check, PeerDependencyCheckConfig};
+pub use polyfill::{deserialize_polyfill, PolyfillConfig, PolyfillMode, PolyfillProvider};
+pub use pre_bundle::{deserialize_pre_bundle, PreBundleConfig};
+pub use precompress::{deserialize_precompress, PrecompressAlgorithm, PrecompressConfig};
 pub use progress::{deserialize_progress, ProgressConfig};
 pub use provider::Providers;
+use public_path::validate_public_path;
+pub use public_path::{static_public_path, PublicPathKind};
 pub use px2rem::{deserialize_px2rem, Px2RemConfig};
-pub use react::{ReactConfig, ReactRuntimeConfig};
+pub use react::{ReactConfig, ReactRuntimeConfig, RemovePropTypesConfig};
+pub use remote_css::RemoteCssConfig;
 pub use resolve::ResolveConfig;
 pub use rsc_client::{deserialize_rsc_client, LogServerComponent, RscClientConfig};
 pub use rsc_server::{deserialize_rsc_server, RscServerConfig};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+pub use side_effect_only_imports::{
+    deserialize_side_effect_only_imports, SideEffectOnlyImportsConfig,
+};
+pub use source_map::{SourceMapConfig, SourceMapFidelity};
 pub use stats::{deserialize_stats, StatsConfig};
+pub use stub::{StubConfig, STUB_NOOP};
 use thiserror::Error;
-pub use transform_import::{TransformImportConfig, TransformImportStyle};
+pub use transform::TransformConfig;
+pub use transform_import::{TransformImportConfig, TransformImportStyle, TransformImportsConfig};
 pub use tree_shaking::{deserialize_tree_shaking, TreeShakingStrategy};
+pub use type_declaration::{deserialize_type_declaration, TypeDeclarationConfig};
 pub use umd::{deserialize_umd, Umd};
 pub use watch::WatchConfig;
 
 use crate::build::load::JS_EXTENSIONS;
 use crate::config::experimental::RustPlugin;
 use crate::features::node::Node;
+use crate::features::polyfill::Polyfill;
 
 #[derive(Debug, Diagnostic)]
 #[diagnostic(code("mako.config.json parsed failed"))]
@@ -96,25 +196,21 @@ impl fmt::Display for ConfigParseError {
     }
 }
 
-fn validate_mako_config(abs_config_file: String) -> miette::Result<()> {
-    if Path::new(&abs_config_file).exists() {
-        let content = std::fs::read_to_string(abs_config_file.clone())
-            .map_err(|e| miette!("Failed to read file '{}': {}", &abs_config_file, e))?;
-        let result: Result<Value, serde_json::Error> = serde_json::from_str(&content);
-        if let Err(e) = result {
-            let line = e.line();
-            let column = e.column();
-            let start = SourceOffset::from_location(&content, line, column);
-            let span = SourceSpan::new(start, (1 as ByteOffset).into());
-            return Err(ConfigParseError {
-                src: NamedSource::new("mako.config.json", content),
-                span,
-                message: e.to_string(),
-            }
-            .into());
+fn parse_mako_config(abs_config_file: &Path) -> miette::Result<Value> {
+    let content = std::fs::read_to_string(abs_config_file)
+        .map_err(|e| miette!("Failed to read file '{}': {}", abs_config_file.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        let line = e.line();
+        let column = e.column();
+        let start = SourceOffset::from_location(&content, line, column);
+        let span = SourceSpan::new(start, (1 as ByteOffset).into());
+        ConfigParseError {
+            src: NamedSource::new("mako.config.json", content.clone()),
+            span,
+            message: e.to_string(),
         }
-    }
-    Ok(())
+        .into()
+    })
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
@@ -135,26 +231,58 @@ pub enum CopyConfig {
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
-    pub entry: BTreeMap<String, PathBuf>,
+    pub entry: BTreeMap<String, EntryConfig>,
+    #[serde(default)]
+    pub entry_prelude: Vec<EntryPreludeConfig>,
     pub output: OutputConfig,
     pub resolve: ResolveConfig,
     #[serde(deserialize_with = "deserialize_manifest", default)]
     pub manifest: Option<ManifestConfig>,
+    #[serde(deserialize_with = "deserialize_build_manifest", default)]
+    pub build_manifest: Option<BuildManifestConfig>,
+    #[serde(deserialize_with = "deserialize_cache_rules", default)]
+    pub cache_rules: Option<CacheRulesConfig>,
+    #[serde(deserialize_with = "deserialize_loadable_manifest", default)]
+    pub loadable_manifest: Option<LoadableManifestConfig>,
     pub mode: Mode,
-    pub minify: bool,
+    /// the env whose overlay of `env` is merged over the config, which is
+    /// `import.meta.env.MODE`, the mode by default
+    #[serde(default)]
+    pub env_name: String,
+    #[serde(deserialize_with = "deserialize_minify", default)]
+    pub minify: Option<MinifyConfig>,
     #[serde(deserialize_with = "deserialize_devtool")]
     pub devtool: Option<DevtoolConfig>,
+    /// time the module factories in the runtime, development only
+    #[serde(default)]
+    pub devtools_timing: bool,
+    /// resolved to the globs, or none if it's disabled
+    #[serde(default)]
+    pub source_map_ignore_list: Option<SourceMapIgnoreListConfig>,
+    #[serde(default)]
+    pub source_map: SourceMapConfig,
     pub externals: HashMap<String, ExternalConfig>,
+    /// the packages replaced with the generated modules of the exports
+    pub stub: HashMap<String, StubConfig>,
     pub providers: Providers,
     pub copy: Vec<CopyConfig>,
     pub public_path: String,
     pub inline_limit: usize,
     pub inline_excludes_extensions: Vec<String>,
+    #[serde(deserialize_with = "deserialize_image_optimization", default)]
+    pub image_optimization: Option<ImageOptimizationConfig>,
     pub targets: HashMap<String, f32>,
     pub platform: Platform,
+    /// how the `.node` native addons are bundled with the node platform
+    #[serde(default)]
+    pub native_addons: NativeAddonsConfig,
     pub module_id_strategy: ModuleIdStrategy,
     pub define: HashMap<String, Value>,
+    #[serde(deserialize_with = "deserialize_env_object", default)]
+    pub env_object: Option<EnvObjectConfig>,
     pub analyze: Option<AnalyzeConfig>,
+    #[serde(deserialize_with = "deserialize_graph", default)]
+    pub graph: Option<GraphConfig>,
     pub stats: Option<StatsConfig>,
     pub mdx: bool,
     #[serde(deserialize_with = "deserialize_hmr")]
@@ -165,6 +293,8 @@ pub struct Config {
     pub code_splitting: Option<CodeSplitting>,
     #[serde(deserialize_with = "deserialize_px2rem", default)]
     pub px2rem: Option<Px2RemConfig>,
+    #[serde(deserialize_with = "deserialize_license_comments", default)]
+    pub license_comments: Option<LicenseCommentsConfig>,
     #[serde(deserialize_with = "deserialize_progress", default)]
     pub progress: Option<ProgressConfig>,
     pub hash: bool,
@@ -174,16 +304,25 @@ pub struct Config {
     pub auto_css_modules: bool,
     #[serde(rename = "ignoreCSSParserErrors")]
     pub ignore_css_parser_errors: bool,
+    #[serde(rename = "remoteCSS")]
+    pub remote_css: RemoteCssConfig,
+    pub max_open_files: Option<usize>,
+    pub max_in_flight_chunk_bytes: Option<usize>,
     pub dynamic_import_to_require: bool,
     #[serde(deserialize_with = "deserialize_umd", default)]
     pub umd: Option<Umd>,
     pub cjs: bool,
     pub write_to_disk: bool,
+    pub transform: TransformConfig,
     pub transform_import: Vec<TransformImportConfig>,
+    // package name -> the templates of the sources of its members
+    pub transform_imports: HashMap<String, TransformImportsConfig>,
     pub chunk_parallel: bool,
     pub clean: bool,
     pub node_polyfill: bool,
     pub ignores: Vec<String>,
+    // globs of the files excluded from the graph, with the ones of .mako-ignore
+    pub ignore: Vec<String>,
     #[serde(
         rename = "_minifish",
         deserialize_with = "deserialize_minifish",
@@ -194,18 +333,27 @@ pub struct Config {
     pub optimize_package_imports: bool,
     pub emotion: bool,
     pub flex_bugs: bool,
+    pub css_lowering: CssLoweringConfig,
     #[serde(deserialize_with = "deserialize_optimization")]
     pub optimization: Option<OptimizationConfig>,
     pub react: ReactConfig,
     pub emit_assets: bool,
     #[serde(rename = "cssModulesExportOnlyLocales")]
     pub css_modules_export_only_locales: bool,
+    pub css_modules: CssModulesConfig,
+    #[serde(deserialize_with = "deserialize_type_declaration", default)]
+    pub type_declaration: Option<TypeDeclarationConfig>,
     #[serde(
         rename = "inlineCSS",
         deserialize_with = "deserialize_inline_css",
         default
     )]
     pub inline_css: Option<InlineCssConfig>,
+    #[serde(default)]
+    pub inline_runtime: bool,
+    /// regexes of the names of the entry chunks which are inlined in the html
+    #[serde(default)]
+    pub inline_chunks: Vec<String>,
     #[serde(
         rename = "rscServer",
         deserialize_with = "deserialize_rsc_server",
@@ -228,32 +376,199 @@ pub struct Config {
         default
     )]
     pub check_duplicate_package: Option<DuplicatePackageCheckerConfig>,
+    #[serde(deserialize_with = "deserialize_duplicated_modules", default)]
+    pub duplicated_modules: Option<DuplicatedModulesConfig>,
+    #[serde(deserialize_with = "deserialize_async_global_side_effects", default)]
+    pub async_global_side_effects: Option<AsyncGlobalSideEffectsConfig>,
+    #[serde(deserialize_with = "deserialize_parse_fallback", default)]
+    pub parse_fallback: Option<ParseFallbackConfig>,
+    #[serde(deserialize_with = "deserialize_named_exports_check", default)]
+    pub named_exports_check: Option<NamedExportsCheckConfig>,
+    #[serde(deserialize_with = "deserialize_default_import_check", default)]
+    pub default_import_check: Option<DefaultImportCheckConfig>,
+    #[serde(deserialize_with = "deserialize_fully_dynamic_import", default)]
+    pub fully_dynamic_import: Option<FullyDynamicImportConfig>,
+    #[serde(deserialize_with = "deserialize_fully_dynamic_import", default)]
+    pub fully_dynamic_require: Option<FullyDynamicImportConfig>,
+    pub module_directives: Vec<String>,
+    pub module_types: Vec<ModuleTypeRule>,
+    #[serde(default)]
+    pub module_rules: Vec<ModuleRule>,
+    #[serde(deserialize_with = "deserialize_json_exports_check", default)]
+    pub json_exports_check: Option<JsonExportsCheckConfig>,
+    #[serde(deserialize_with = "deserialize_peer_dependency_check", default)]
+    pub peer_dependency_check: Option<PeerDependencyCheckConfig>,
     // 是否开启 case sensitive 检查,只有mac平台才需要开启
     #[serde(rename = "caseSensitiveCheck")]
     pub case_sensitive_check: bool,
+    #[serde(deserialize_with = "deserialize_dual_build", default)]
+    pub dual_build: Option<DualBuildConfig>,
+    #[serde(deserialize_with = "deserialize_critical_css", default)]
+    pub critical_css: Option<CriticalCssConfig>,
+    #[serde(deserialize_with = "deserialize_csp_report", default)]
+    pub csp_report: Option<CspReportConfig>,
+    #[serde(deserialize_with = "deserialize_extract_css", default)]
+    pub extract_css: Option<ExtractCssConfig>,
+    #[serde(deserialize_with = "deserialize_import_map", default)]
+    pub import_map: Option<ImportMapConfig>,
+    #[serde(deserialize_with = "deserialize_polyfill", default)]
+    pub polyfill: Option<PolyfillConfig>,
+    #[serde(deserialize_with = "deserialize_side_effect_only_imports", default)]
+    pub side_effect_only_imports: Option<SideEffectOnlyImportsConfig>,
+    #[serde(deserialize_with = "deserialize_pre_bundle", default)]
+    pub pre_bundle: Option<PreBundleConfig>,
+    pub diagnostics: DiagnosticsConfig,
+    // the config file and its local imports, which restart the watch mode
+    #[serde(skip)]
+    pub config_files: Vec<PathBuf>,
+    // no build messages, e.g. for bundling the config file
+    #[serde(skip)]
+    pub quiet: bool,
+    // the build of a pre-bundled dependency, whose runtime is private to the
+    // artifact and doesn't take the globals of the app's runtime
+    #[serde(skip)]
+    pub pre_bundled: bool,
 }
 
-const CONFIG_FILE: &str = "mako.config.json";
 const DEFAULT_CONFIG: &str = include_str!("./config/mako.config.default.json");
 
+// the explicit sources, from the lowest priority to the highest
+fn add_explicit_sources(
+    c: ConfigBuilder<DefaultState>,
+    default_config: Option<&str>,
+    user_config: Option<&str>,
+    cli_config: Option<&str>,
+) -> ConfigBuilder<DefaultState> {
+    [
+        (default_config, config::FileFormat::Json5),
+        (user_config, config::FileFormat::Json),
+        (cli_config, config::FileFormat::Json5),
+    ]
+    .into_iter()
+    .fold(c, |c, (source, format)| match source {
+        Some(source) => c.add_source(config::File::from_str(source, format)),
+        None => c,
+    })
+}
+
+fn get_mode(
+    default_config: Option<&str>,
+    user_config: Option<&str>,
+    cli_config: Option<&str>,
+) -> Result<Mode> {
+    let default_source = config::File::from_str(DEFAULT_CONFIG, config::FileFormat::Json5);
+    add_explicit_sources(
+        config::Config::builder().add_source(default_source),
+        default_config,
+        user_config,
+        cli_config,
+    )
+    .build()?
+    .get::<Mode>("mode")
+    .map_err(|e| anyhow!("{}: {}", "config error".red(), e.to_string().red()))
+}
+
+// `--env` or `envName` of the explicit sources, or the mode
+fn get_env_name(
+    default_config: Option<&str>,
+    user_config: Option<&str>,
+    cli_config: Option<&str>,
+) -> Result<String> {
+    let default_source = config::File::from_str(DEFAULT_CONFIG, config::FileFormat::Json5);
+    let env_name = add_explicit_sources(
+        config::Config::builder().add_source(default_source),
+        default_config,
+        user_config,
+        cli_config,
+    )
+    .build()?
+    .get::<String>("envName")
+    .map_err(|e| anyhow!("{}: {}", "config error".red(), e.to_string().red()))?;
+    if env_name.is_empty() {
+        Ok(get_mode(default_config, user_config, cli_config)?.to_string())
+    } else {
+        Ok(env_name)
+    }
+}
+
 impl Config {
     pub fn new(
         root: &Path,
         default_config: Option<&str>,
         cli_config: Option<&str>,
     ) -> Result<Self> {
-        let abs_config_file = root.join(CONFIG_FILE);
-        let abs_config_file = abs_config_file.to_str().unwrap();
-        let mut overrides_json: Option<Value> = None;
-        let c = config::Config::builder();
-        // default config
-        let c = c.add_source(config::File::from_str(
-            DEFAULT_CONFIG,
-            config::FileFormat::Json5,
-        ));
+        // the config of the js api, which may have the overlays of `env` too
+        let (default_base, mut default_envs) =
+            match default_config.map(serde_json::from_str::<Value>) {
+                Some(Ok(value)) => {
+                    let (base, envs) = env_configs(&value)?;
+                    (Some(base.to_string()), envs)
+                }
+                _ => (default_config.map(str::to_string), BTreeMap::new()),
+            };
+        let default_config = default_base.as_deref();
+        // user config
+        let mut config_files = vec![];
+        let mut user_base = None;
+        let mut user_envs = BTreeMap::new();
+        if let Some(config_file) = find_config_file(root) {
+            let file_name = config_file
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            let value = if is_script_config(&config_file) {
+                // the mode of the other sources is passed to the config
+                let mode = get_mode(default_config, None, cli_config)?;
+                let (value, files) = load_script_config(root, &config_file, &mode)?;
+                config_files = files;
+                value
+            } else {
+                config_files.push(config_file.clone());
+                parse_mako_config(&config_file).map_err(|e| anyhow!("{}", e))?
+            };
+            // the overlay of the env is merged before the validation, so
+            // that the typos in the overlays are caught too
+            let (base, envs) = env_configs(&value)
+                .map_err(|e| anyhow!("Invalid config in {}: {}", file_name, e))?;
+            validate_config_keys(&base, &file_name)?;
+            for (name, env_config) in &envs {
+                validate_config_keys(env_config, &format!("{} (env.{})", file_name, name))?;
+            }
+            user_base = Some(base.to_string());
+            user_envs = envs;
+        }
+        let env_name = get_env_name(default_config, user_base.as_deref(), cli_config)?;
+        let default_config = match default_envs.remove(&env_name) {
+            Some(env_config) => Some(env_config.to_string()),
+            None => default_base,
+        };
+        let user_config = match user_envs.remove(&env_name) {
+            Some(env_config) => Some(env_config.to_string()),
+            None => user_base,
+        };
+        let ignore_file = root.join(IGNORE_FILE);
+        if ignore_file.is_file() {
+            config_files.push(ignore_file);
+        }
+        let mut config = Self::from_sources(
+            root,
+            default_config.as_deref(),
+            user_config.as_deref(),
+            cli_config,
+        )?;
+        config.config_files = config_files;
+        Ok(config)
+    }
 
-        // default config from args
-        let c = if let Some(default_config) = default_config {
+    pub(crate) fn from_sources(
+        root: &Path,
+        default_config: Option<&str>,
+        user_config: Option<&str>,
+        cli_config: Option<&str>,
+    ) -> Result<Self> {
+        let mut overrides_json: Option<Value> = None;
+        if let Some(default_config) = default_config {
             let result: Result<Value, serde_json::Error> = serde_json::from_str(default_config);
             if let Ok(config) = result {
                 if let Some(rust_plugins) = config
@@ -263,28 +578,19 @@ impl Config {
                     overrides_json = Some(json!({ "rust_plugins": rust_plugins }));
                 }
             };
-            c.add_source(config::File::from_str(
-                default_config,
+        }
+        // the preset of the mode lies between the default config and the explicit ones
+        let mode = get_mode(default_config, user_config, cli_config)?;
+        let c = config::Config::builder()
+            .add_source(config::File::from_str(
+                DEFAULT_CONFIG,
                 config::FileFormat::Json5,
             ))
-        } else {
-            c
-        };
-        // validate user config
-        validate_mako_config(abs_config_file.to_string()).map_err(|e| anyhow!("{}", e))?;
-        // user config
-        let c = c.add_source(config::File::with_name(abs_config_file).required(false));
-        // cli config
-        let c = if let Some(cli_config) = cli_config {
-            c.add_source(config::File::from_str(
-                cli_config,
+            .add_source(config::File::from_str(
+                mode.preset(),
                 config::FileFormat::Json5,
-            ))
-        } else {
-            c
-        };
-
-        let c = c.build()?;
+            ));
+        let c = add_explicit_sources(c, default_config, user_config, cli_config).build()?;
         let mut ret = c.try_deserialize::<Config>();
         // normalize & check
         if let Ok(config) = &mut ret {
@@ -296,34 +602,64 @@ impl Config {
                 config.experimental.rust_plugins = rust_plugins;
             }
 
+            if config.env_name.is_empty() {
+                config.env_name = config.mode.to_string();
+            }
+
             // normalize output
             if config.output.path.is_relative() {
                 config.output.path = root.join(config.output.path.to_string_lossy().to_string());
             }
 
+            let has_unique_name = !config.output.unique_name.is_empty();
+            if !has_unique_name {
+                config.output.unique_name = get_default_unique_name(config.umd.clone(), root);
+            }
             if config.output.chunk_loading_global.is_empty() {
-                config.output.chunk_loading_global =
-                    get_default_chunk_loading_global(config.umd.clone(), root);
+                config.output.chunk_loading_global = if has_unique_name {
+                    format!("makoChunk_{}", config.output.unique_name)
+                } else {
+                    get_default_chunk_loading_global(
+                        config.umd.clone(),
+                        root,
+                        &config.output.unique_name,
+                    )
+                };
             }
 
             let node_env_config_opt = config.define.get("NODE_ENV");
-            if let Some(node_env_config) = node_env_config_opt {
-                if node_env_config.as_str() != Some(config.mode.to_string().as_str()) {
-                    let warn_message = format!(
-                        "{}: The configuration of {} conflicts with current {} and will be overwritten as {} ",
-                        "warning".to_string().yellow(),
-                        "NODE_ENV".to_string().yellow(),
-                        "mode".to_string().yellow(),
-                        config.mode.to_string().red()
-                    );
-                    println!("{}", warn_message);
-                }
+            if let Some(node_env_config) = node_env_config_opt
+                && let Some(node_env) = config.mode.node_env()
+                && node_env_config.as_str() != Some(node_env.as_str())
+            {
+                let warn_message = format!(
+                    "{}: The configuration of {} conflicts with current {} and will be overwritten as {} ",
+                    "warning".to_string().yellow(),
+                    "NODE_ENV".to_string().yellow(),
+                    "mode".to_string().yellow(),
+                    config.mode.to_string().red()
+                );
+                eprintln!("{}", warn_message);
             }
 
             if config.cjs && config.umd.is_some() {
                 return Err(anyhow!("cjs and umd cannot be used at the same time",));
             }
 
+            // one esm file per module, which the bundlers of the consumers
+            // tree-shake by file
+            if config.output.preserve_modules {
+                if config.cjs || config.umd.is_some() {
+                    return Err(anyhow!("output.preserveModules can only be used with esm",));
+                }
+                config.output.mode = OutputMode::Bundless;
+                config._tree_shaking = None;
+            }
+
+            for transform_imports in config.transform_imports.values() {
+                transform_imports.render("Member")?;
+            }
+
             if config.hmr.is_some() && config.dev_server.is_none() {
                 return Err(anyhow!("hmr can only be used with devServer",));
             }
@@ -332,17 +668,39 @@ impl Config {
                 return Err(anyhow!("inlineCSS can only be used with umd",));
             }
 
-            let mode = format!("\"{}\"", config.mode);
-            config
-                .define
-                .insert("NODE_ENV".to_string(), serde_json::Value::String(mode));
+            for pattern in &config.inline_chunks {
+                regex::Regex::new(pattern).map_err(|e| {
+                    anyhow!("inlineChunks {:?} is not a valid regex: {}", pattern, e)
+                })?;
+            }
 
-            if ["runtime", "auto"].iter().all(|p| *p != config.public_path)
-                && !config.public_path.ends_with('/')
-            {
-                return Err(anyhow!("public_path must end with '/' or be 'runtime'"));
+            if let Some(precompress) = &config.output.precompress {
+                if precompress.gzip_level > 9 {
+                    return Err(anyhow!("output.precompress.gzipLevel must be 0-9"));
+                }
+                if precompress.brotli_quality > 11 {
+                    return Err(anyhow!("output.precompress.brotliQuality must be 0-11"));
+                }
+            }
+
+            let max_hash_length = config.output.hash_function.max_length();
+            if config.output.hash_length == 0 || config.output.hash_length > max_hash_length {
+                return Err(anyhow!(
+                    "output.hashLength must be 1-{} with the hash function {:?}",
+                    max_hash_length,
+                    config.output.hash_function
+                ));
             }
 
+            if let Some(node_env) = config.mode.node_env() {
+                config.define.insert(
+                    "NODE_ENV".to_string(),
+                    serde_json::Value::String(format!("\"{}\"", node_env)),
+                );
+            }
+
+            validate_public_path(&config.public_path)?;
+
             // 暂不支持 remote external
             // 如果 config.externals 中有值是以「script 」开头，则 panic 报错
             let basic_external_values = config
@@ -369,7 +727,7 @@ impl Config {
                     for ext in JS_EXTENSIONS {
                         let file_path = root.join(file_path).with_extension(ext);
                         if file_path.exists() {
-                            config.entry.insert("index".to_string(), file_path);
+                            config.entry.insert("index".to_string(), file_path.into());
                             break 'outer;
                         }
                     }
@@ -380,7 +738,8 @@ impl Config {
             }
 
             // normalize entry
-            config.entry.iter_mut().try_for_each(|(k, v)| {
+            config.entry.iter_mut().try_for_each(|(k, entry)| {
+                let v = &mut entry.import;
                 #[allow(clippy::needless_borrows_for_generic_args)]
                 if let Ok(entry_path) = root.join(&v).canonicalize()
                     && entry_path.is_file()
@@ -412,6 +771,38 @@ impl Config {
                 Ok(())
             })?;
 
+            config.ignore.extend(load_ignore_file(root));
+            for (name, entry) in &config.entry {
+                if let Some(rule) = matched_ignore_rule(&config.ignore, root, &entry.import) {
+                    return Err(anyhow!(
+                        "entry:{} is excluded by the ignore rule {:?}",
+                        name,
+                        rule
+                    ));
+                }
+            }
+
+            if let Some(side_effect_only_imports) = &config.side_effect_only_imports {
+                for include in &side_effect_only_imports.include {
+                    regex::Regex::new(include).map_err(|_| {
+                        anyhow!(
+                            "Config Error invalid regex of sideEffectOnlyImports.include: {}",
+                            include
+                        )
+                    })?;
+                }
+            }
+
+            validate_depend_on(&config.entry)?;
+            validate_webworker(&config.entry)?;
+            // the dependents are executed by the runtime of the entries they
+            // depend on, which the umd or cjs entries don't share
+            if (config.cjs || config.umd.is_some())
+                && config.entry.values().any(|e| !e.depend_on.is_empty())
+            {
+                return Err(anyhow!("entry dependOn can not be used with umd or cjs",));
+            }
+
             // support relative alias
             config.resolve.alias.iter_mut().for_each(|(_, v)| {
                 #[allow(clippy::needless_borrows_for_generic_args)]
@@ -419,17 +810,51 @@ impl Config {
                     *v = root.join(&v).to_string_lossy().to_string()
                 }
             });
+            if let Some(tsconfig) = &mut config.resolve.tsconfig
+                && tsconfig.is_relative()
+            {
+                *tsconfig = root.join(&*tsconfig);
+            }
+            config.resolve.roots.iter_mut().for_each(|dir| {
+                *dir = root.join(&*dir).clean();
+            });
 
             // dev 环境下不产生 hash, prod 环境下根据用户配置
             if config.mode == Mode::Development {
                 config.hash = false;
             }
 
+            config.source_map_ignore_list = match config.source_map_ignore_list.take() {
+                Some(SourceMapIgnoreListConfig::Enabled(true)) => Some(
+                    SourceMapIgnoreListConfig::Globs(SourceMapIgnoreListConfig::default_globs()),
+                ),
+                Some(SourceMapIgnoreListConfig::Globs(globs)) if !globs.is_empty() => {
+                    Some(SourceMapIgnoreListConfig::Globs(globs))
+                }
+                _ => None,
+            };
+
             // configure node platform
             Node::modify_config(config);
+
+            if let Some(polyfill) = &mut config.polyfill {
+                Polyfill::resolve_version(polyfill, root)?;
+            }
         }
         ret.map_err(|e| anyhow!("{}: {}", "config error".red(), e.to_string().red()))
     }
+
+    /// Whether the entry of the name is run in a worker, it's not in the html.
+    pub fn is_webworker_entry(&self, name: &str) -> bool {
+        self.entry
+            .get(name)
+            .is_some_and(|entry| entry.is_webworker())
+    }
+
+    /// Whether the stats have the times of the build, see `stats.timestamps`.
+    pub fn stats_timestamps(&self) -> bool {
+        self.stats.as_ref().is_some_and(|stats| stats.timestamps)
+    }
 }
 
 impl Default for Config {
@@ -452,7 +877,7 @@ pub enum ConfigError {
 
 #[cfg(test)]
 mod tests {
-    use crate::config::{Config, Mode, Platform};
+    use crate::config::{Config, Mode, ModuleIdStrategy, Platform, SourceMapIgnoreListConfig};
 
     #[test]
     fn test_config() {
@@ -503,6 +928,106 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_side_effect_only_imports() {
+        let current_dir = std::env::current_dir().unwrap();
+        let root = current_dir.join("test/config/normal");
+        let config = Config::new(&root, None, None).unwrap();
+        assert!(config.side_effect_only_imports.is_none());
+        let config = Config::new(
+            &root,
+            None,
+            Some(r#"{"sideEffectOnlyImports":{"include":["^\\./init$"]}}"#),
+        )
+        .unwrap();
+        assert_eq!(
+            config.side_effect_only_imports.unwrap().include,
+            vec!["^\\./init$".to_string()]
+        );
+        let err = Config::new(
+            &root,
+            None,
+            Some(r#"{"sideEffectOnlyImports":{"include":["("]}}"#),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("sideEffectOnlyImports.include"));
+    }
+
+    #[test]
+    fn test_mode_preset() {
+        let current_dir = std::env::current_dir().unwrap();
+        let root = current_dir.join("test/config/normal");
+        let config = Config::new(&root, None, Some(r#"{"mode":"production"}"#)).unwrap();
+        assert!(config.minify.is_some());
+        assert!(config.hmr.is_none());
+        assert!(config.hash);
+        assert!(matches!(
+            config.module_id_strategy,
+            ModuleIdStrategy::Deterministic
+        ));
+
+        let config = Config::new(&root, None, Some(r#"{"mode":"development"}"#)).unwrap();
+        assert!(config.minify.is_none());
+        assert!(!config.hash);
+        assert!(matches!(config.module_id_strategy, ModuleIdStrategy::Named));
+
+        // explicit config overrides the preset
+        let config = Config::new(
+            &root,
+            Some(r#"{"moduleIdStrategy":"named"}"#),
+            Some(r#"{"mode":"production","minify":false}"#),
+        )
+        .unwrap();
+        assert!(config.minify.is_none());
+        assert!(config.hash);
+        assert!(matches!(config.module_id_strategy, ModuleIdStrategy::Named));
+    }
+
+    #[test]
+    fn test_mode_none() {
+        let current_dir = std::env::current_dir().unwrap();
+        let config = Config::new(
+            &current_dir.join("test/config/normal"),
+            None,
+            Some(r#"{"mode":"none"}"#),
+        )
+        .unwrap();
+        assert_eq!(config.mode, Mode::None);
+        assert!(config.minify.is_none());
+        assert!(config._tree_shaking.is_none());
+        assert!(!config.define.contains_key("NODE_ENV"));
+    }
+
+    #[test]
+    fn test_source_map_ignore_list() {
+        let current_dir = std::env::current_dir().unwrap();
+        let root = current_dir.join("test/config/normal");
+        let ignore_list = |args: &str| {
+            Config::new(&root, None, Some(args))
+                .unwrap()
+                .source_map_ignore_list
+        };
+        let node_modules = Some(SourceMapIgnoreListConfig::Globs(vec![
+            "**/node_modules/**".to_string()
+        ]));
+        assert_eq!(ignore_list(r#"{"mode":"development"}"#), node_modules);
+        assert_eq!(ignore_list(r#"{"mode":"production"}"#), None);
+        assert_eq!(
+            ignore_list(r#"{"mode":"production","sourceMapIgnoreList":true}"#),
+            node_modules
+        );
+        assert_eq!(
+            ignore_list(r#"{"mode":"development","sourceMapIgnoreList":false}"#),
+            None
+        );
+        assert_eq!(
+            ignore_list(r#"{"mode":"production","sourceMapIgnoreList":["vendor/**"]}"#),
+            Some(SourceMapIgnoreListConfig::Globs(vec![
+                "vendor/**".to_string()
+            ]))
+        );
+    }
+
     #[test]
     #[should_panic(expected = "public_path must end with '/' or be 'runtime'")]
     fn test_config_invalid_public_path() {
@@ -515,6 +1040,93 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    #[should_panic(expected = "unknown helper snakeCase in transformImports template")]
+    fn test_config_invalid_transform_imports() {
+        let current_dir = std::env::current_dir().unwrap();
+        Config::new(
+            &current_dir.join("test/config/normal"),
+            None,
+            Some(
+                r#"{"transformImports":{"ui-lib":{"transform":"ui-lib/{{ snakeCase member }}"}}}"#,
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_entry_depend_on() {
+        let current_dir = std::env::current_dir().unwrap();
+        let config = Config::new(
+            &current_dir.join("test/config/normal"),
+            None,
+            Some(
+                r#"{"entry":{"a":"./index.ts","b":{"import":"./index.ts","dependOn":"a"},"c":{"import":"./index.ts","dependOn":["a","b"]}}}"#,
+            ),
+        )
+        .unwrap();
+        assert!(config.entry["a"].depend_on.is_empty());
+        assert_eq!(config.entry["b"].depend_on, vec!["a"]);
+        assert_eq!(config.entry["c"].depend_on, vec!["a", "b"]);
+        assert!(config.entry["c"].import.is_absolute());
+    }
+
+    #[test]
+    #[should_panic(expected = "entry dependOn has a circular dependency: a -> b -> a")]
+    fn test_entry_depend_on_circular() {
+        let current_dir = std::env::current_dir().unwrap();
+        Config::new(
+            &current_dir.join("test/config/normal"),
+            None,
+            Some(
+                r#"{"entry":{"a":{"import":"./index.ts","dependOn":"b"},"b":{"import":"./index.ts","dependOn":"a"}}}"#,
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "entry:a depends on entry:b which is not found")]
+    fn test_entry_depend_on_not_found() {
+        let current_dir = std::env::current_dir().unwrap();
+        Config::new(
+            &current_dir.join("test/config/normal"),
+            None,
+            Some(r#"{"entry":{"a":{"import":"./index.ts","dependOn":"b"}}}"#),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_entry_webworker() {
+        let current_dir = std::env::current_dir().unwrap();
+        let config = Config::new(
+            &current_dir.join("test/config/normal"),
+            None,
+            Some(
+                r#"{"entry":{"index":"./index.ts","sw":{"import":"./index.ts","target":"webworker","precacheManifest":true}}}"#,
+            ),
+        )
+        .unwrap();
+        assert!(!config.is_webworker_entry("index"));
+        assert!(config.is_webworker_entry("sw"));
+        assert!(config.entry["sw"].precache_manifest);
+    }
+
+    #[test]
+    #[should_panic(expected = "entry:b can not depend on entry:a")]
+    fn test_entry_webworker_depend_on() {
+        let current_dir = std::env::current_dir().unwrap();
+        Config::new(
+            &current_dir.join("test/config/normal"),
+            None,
+            Some(
+                r#"{"entry":{"a":{"import":"./index.ts","target":"webworker"},"b":{"import":"./index.ts","dependOn":"a"}}}"#,
+            ),
+        )
+        .unwrap();
+    }
+
     #[test]
     fn test_node_platform() {
         let current_dir = std::env::current_dir().unwrap();
@@ -530,4 +1142,81 @@ mod tests {
             "ignore Node.js standard library by default if platform is node",
         );
     }
+
+    #[test]
+    fn test_env_overlays() {
+        let root = std::env::current_dir().unwrap().join("test/config/env");
+        let development = Config::new(&root, None, Some(r#"{"mode":"development"}"#)).unwrap();
+        assert_eq!(development.env_name, "development");
+        assert_eq!(development.public_path, "/");
+        assert_eq!(development.copy.len(), 1);
+
+        let production = Config::new(&root, None, Some(r#"{"mode":"production"}"#)).unwrap();
+        assert_eq!(production.env_name, "production");
+        assert_eq!(production.public_path, "https://cdn.example.com/");
+        assert_eq!(production.copy.len(), 2);
+
+        let staging = Config::new(
+            &root,
+            None,
+            Some(r#"{"mode":"production","envName":"staging"}"#),
+        )
+        .unwrap();
+        assert_eq!(staging.env_name, "staging");
+        assert_eq!(staging.mode, Mode::Production);
+        assert_eq!(staging.public_path, "/");
+        assert_eq!(
+            staging.define.get("API"),
+            Some(&serde_json::Value::String("\"/staging-api\"".to_string()))
+        );
+
+        // the overlays of the config of the js api
+        let staging = Config::new(
+            &root,
+            Some(r#"{"hash":true,"env":{"staging":{"hash":false}}}"#),
+            Some(r#"{"mode":"production","envName":"staging"}"#),
+        )
+        .unwrap();
+        assert!(!staging.hash);
+
+        let err = Config::new(
+            &root.with_file_name("env-typo"),
+            None,
+            Some(r#"{"mode":"development"}"#),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.contains("Invalid config in mako.config.json (env.production)"),
+            "{}",
+            err
+        );
+        assert!(err.contains("did you mean \"publicPath\"?"), "{}", err);
+    }
+
+    #[test]
+    fn test_unique_name() {
+        let current_dir = std::env::current_dir().unwrap();
+        let normal = Config::new(&current_dir.join("test/config/normal"), None, None).unwrap();
+        let node_platform =
+            Config::new(&current_dir.join("test/config/node-platform"), None, None).unwrap();
+        assert_eq!(normal.output.unique_name.len(), 8);
+        assert_ne!(
+            normal.output.unique_name, node_platform.output.unique_name,
+            "builds without package names don't share the runtime globals",
+        );
+        assert_eq!(
+            normal.output.chunk_loading_global,
+            format!("makoChunk_{}", normal.output.unique_name)
+        );
+
+        let config = Config::new(
+            &current_dir.join("test/config/normal"),
+            None,
+            Some(r#"{ "output": { "uniqueName": "app" } }"#),
+        )
+        .unwrap();
+        assert_eq!(config.output.unique_name, "app");
+        assert_eq!(config.output.chunk_loading_global, "makoChunk_app");
+    }
 }
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AsyncGlobalSideEffectsConfig {
+    /// the global properties written on purpose, e.g. `__REGISTRY__` or
+    /// `__REGISTRY__.button`
+    #[serde(default)]
+    pub allow_properties: Vec<String>,
+    /// globs of the modules relative to the root, e.g. `src/polyfills/**`
+    #[serde(default)]
+    pub allow_modules: Vec<String>,
+}
+
+create_deserialize_fn!(
+    deserialize_async_global_side_effects,
+    AsyncGlobalSideEffectsConfig
+);
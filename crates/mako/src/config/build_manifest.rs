@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{create_deserialize_fn, plugins};
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildManifestConfig {
+    #[serde(default = "plugins::build_manifest::default_build_manifest_file_name")]
+    pub file_name: String,
+    /// the manifest of the previous build relative to the root, the added,
+    /// changed and removed assets since it are printed
+    #[serde(default)]
+    pub previous: Option<PathBuf>,
+}
+
+create_deserialize_fn!(deserialize_build_manifest, BuildManifestConfig);
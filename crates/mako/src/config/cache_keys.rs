@@ -0,0 +1,323 @@
+use std::fmt;
+use std::hash::Hasher;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use twox_hash::XxHash64;
+
+use super::Config;
+
+/// The stages of the pipeline whose results are cached, in the order they
+/// run, the options of a stage are consumed by it and the later stages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionGroup {
+    Resolve,
+    Transform,
+    Codegen,
+    Minify,
+}
+
+impl OptionGroup {
+    pub const ALL: [OptionGroup; 4] = [
+        OptionGroup::Resolve,
+        OptionGroup::Transform,
+        OptionGroup::Codegen,
+        OptionGroup::Minify,
+    ];
+
+    /// The config keys of the group, the nested ones are joined by `.`, the
+    /// keys of a parent which are in other groups are left out of it.
+    pub fn keys(&self) -> &'static [&'static str] {
+        match self {
+            OptionGroup::Resolve => &[
+                "caseSensitiveCheck",
+                "entry",
+                "externals",
+                "ignores",
+                "importMap",
+                "nativeAddons",
+                "platform",
+                "preBundle",
+                "resolve",
+                "stub",
+            ],
+            OptionGroup::Transform => &[
+                "autoCSSModules",
+                "cssLowering",
+                "cssModules",
+                "cssModulesExportOnlyLocales",
+                "define",
+                "dynamicImportToRequire",
+                "emitDecoratorMetadata",
+                "emotion",
+                "entryPrelude",
+                "envName",
+                "envObject",
+                "experimental",
+                "flexBugs",
+                "fullyDynamicImport",
+                "fullyDynamicRequire",
+                "hmr",
+                "ignoreCSSParserErrors",
+                "imageOptimization",
+                "inlineCSS",
+                "inlineExcludesExtensions",
+                "inlineLimit",
+                "mdx",
+                "mode",
+                "moduleDirectives",
+                "moduleRules",
+                "moduleTypes",
+                "nodePolyfill",
+                "optimizePackageImports",
+                "parseFallback",
+                "polyfill",
+                "providers",
+                "publicPath",
+                "px2rem",
+                "react",
+                "remoteCSS",
+                "rscClient",
+                "rscServer",
+                "targets",
+                "transform",
+                "transformImport",
+                "transformImports",
+                "useDefineForClassFields",
+            ],
+            OptionGroup::Codegen => &[
+                "_treeShaking",
+                "chunkParallel",
+                "cjs",
+                "codeSplitting",
+                "criticalCss",
+                "cspReport",
+                "devtool",
+                "devtoolsTiming",
+                "extractCss",
+                "hash",
+                "inlineChunks",
+                "inlineRuntime",
+                "licenseComments",
+                "loadableManifest",
+                "moduleIdStrategy",
+                "optimization",
+                "output",
+                "sourceMap",
+                "sourceMapIgnoreList",
+                "umd",
+            ],
+            OptionGroup::Minify => &["_minifish", "minify"],
+        }
+    }
+
+    /// The config keys which don't change what's cached, e.g. the dev
+    /// server, the checks reported after the build and where the files are
+    /// written.
+    pub fn unkeyed() -> &'static [&'static str] {
+        &[
+            "analyze",
+            "asyncGlobalSideEffects",
+            "buildManifest",
+            "cacheRules",
+            "clean",
+            "copy",
+            "defaultImportCheck",
+            "devServer",
+            "diagnostics",
+            "dualBuild",
+            "duplicatePackageChecker",
+            "duplicatedModules",
+            "emitAssets",
+            "graph",
+            "ignore",
+            "jsonExportsCheck",
+            "manifest",
+            "maxInFlightChunkBytes",
+            "maxOpenFiles",
+            "namedExportsCheck",
+            "output.path",
+            "output.precompress",
+            "output.skipWrite",
+            "peerDependencyCheck",
+            "progress",
+            "sideEffectOnlyImports",
+            "stats",
+            "typeDeclaration",
+            "watch",
+            "writeToDisk",
+        ]
+    }
+}
+
+impl fmt::Display for OptionGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionGroup::Resolve => write!(f, "resolve"),
+            OptionGroup::Transform => write!(f, "transform"),
+            OptionGroup::Codegen => write!(f, "codegen"),
+            OptionGroup::Minify => write!(f, "minify"),
+        }
+    }
+}
+
+/// The keys of the cache layers, each one hashes the options of its group
+/// and the key of the previous layer, so that a change of the resolve
+/// options invalidates the transformed modules which depend on the resolved
+/// paths, while a change of the minify options keeps them.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheKeys {
+    pub resolve: u64,
+    pub transform: u64,
+    pub codegen: u64,
+    pub minify: u64,
+}
+
+impl CacheKeys {
+    pub fn new(config: &Config) -> Self {
+        let value = serde_json::to_value(config).unwrap_or_default();
+        let mut previous = 0;
+        let [resolve, transform, codegen, minify] = OptionGroup::ALL.map(|group| {
+            previous = group_hash(&value, group, previous);
+            previous
+        });
+        Self {
+            resolve,
+            transform,
+            codegen,
+            minify,
+        }
+    }
+
+    pub fn get(&self, group: OptionGroup) -> u64 {
+        match group {
+            OptionGroup::Resolve => self.resolve,
+            OptionGroup::Transform => self.transform,
+            OptionGroup::Codegen => self.codegen,
+            OptionGroup::Minify => self.minify,
+        }
+    }
+
+    /// The first layer which is invalidated by the other keys, the later
+    /// layers are invalidated with it.
+    pub fn first_changed(&self, other: &CacheKeys) -> Option<OptionGroup> {
+        OptionGroup::ALL
+            .into_iter()
+            .find(|group| self.get(*group) != other.get(*group))
+    }
+}
+
+fn group_hash(config: &Value, group: OptionGroup, previous: u64) -> u64 {
+    let mut hasher: XxHash64 = Default::default();
+    hasher.write_u64(previous);
+    for key in group.keys() {
+        let mut value = config.get(key).cloned().unwrap_or_default();
+        // the nested keys of other groups
+        if let Value::Object(map) = &mut value {
+            for unkeyed in OptionGroup::unkeyed() {
+                if let Some(nested) = unkeyed
+                    .strip_prefix(key)
+                    .and_then(|rest| rest.strip_prefix('.'))
+                {
+                    map.remove(nested);
+                }
+            }
+        }
+        hasher.write(key.as_bytes());
+        hasher.write(sorted_json(value).to_string().as_bytes());
+    }
+    hasher.finish()
+}
+
+/// The value with the keys of the objects sorted, the maps of the config are
+/// serialized in the order of their hash maps.
+pub(crate) fn sorted_json(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.into_iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, sorted_json(v)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sorted_json).collect()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{CacheKeys, OptionGroup};
+    use crate::config::Config;
+
+    fn keys_with(edit: &str) -> CacheKeys {
+        let root = std::env::current_dir().unwrap().join("test/config/normal");
+        CacheKeys::new(&Config::new(&root, None, Some(edit)).unwrap())
+    }
+
+    #[test]
+    fn test_cache_keys() {
+        let base = keys_with("{}");
+        assert_eq!(base, keys_with("{}"));
+        let cases = [
+            (r#"{"devServer":{"port":4000}}"#, None),
+            (r#"{"output":{"path":"other-dist"}}"#, None),
+            (r#"{"stats":{"modules":true}}"#, None),
+            (
+                r#"{"resolve":{"alias":[["foo","./bar"]]}}"#,
+                Some(OptionGroup::Resolve),
+            ),
+            (r#"{"define":{"FOO":"1"}}"#, Some(OptionGroup::Transform)),
+            (r#"{"devtool":false}"#, Some(OptionGroup::Codegen)),
+            (
+                r#"{"output":{"chunkLoadingTimeout":1}}"#,
+                Some(OptionGroup::Codegen),
+            ),
+            (r#"{"mode":"production"}"#, Some(OptionGroup::Transform)),
+            (r#"{"minify":true}"#, Some(OptionGroup::Minify)),
+        ];
+        for (edit, changed) in cases {
+            let keys = keys_with(edit);
+            assert_eq!(base.first_changed(&keys), changed, "{}", edit);
+            // the layers before the changed one survive, the later ones don't
+            for group in OptionGroup::ALL {
+                let survives = changed.map_or(true, |changed| {
+                    OptionGroup::ALL.iter().position(|g| *g == group)
+                        < OptionGroup::ALL.iter().position(|g| *g == changed)
+                });
+                assert_eq!(
+                    base.get(group) == keys.get(group),
+                    survives,
+                    "{} {}",
+                    edit,
+                    group
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_option_groups_cover_config() {
+        let value = serde_json::to_value(Config::default()).unwrap();
+        let grouped = OptionGroup::ALL
+            .iter()
+            .flat_map(|group| group.keys().iter())
+            .chain(OptionGroup::unkeyed().iter())
+            .filter(|key| !key.contains('.'))
+            .collect::<Vec<_>>();
+        let unique = grouped.iter().collect::<BTreeSet<_>>();
+        assert_eq!(unique.len(), grouped.len(), "a key is in two groups");
+        let missing = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .filter(|key| !grouped.contains(&&key.as_str()))
+            .collect::<Vec<_>>();
+        assert!(missing.is_empty(), "not in any group: {:?}", missing);
+    }
+}
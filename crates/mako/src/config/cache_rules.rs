@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{create_deserialize_fn, plugins};
+
+/// How an emitted file is cached by the CDN and the browsers.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheRule {
+    /// the names with the content hashes, whose contents never change
+    Immutable,
+    /// revalidated on every request, e.g. the html, the entries without the
+    /// hashes and the manifests
+    Revalidate,
+}
+
+impl CacheRule {
+    pub fn cache_control(&self) -> &'static str {
+        match self {
+            CacheRule::Immutable => "max-age=31536000, immutable",
+            CacheRule::Revalidate => "no-cache",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheRulesConfig {
+    #[serde(default = "plugins::cache_rules::default_cache_rules_file_name")]
+    pub file_name: String,
+    /// the `_headers` file of netlify too
+    #[serde(default)]
+    pub headers: bool,
+    /// the first override whose pattern matches the file wins
+    #[serde(default)]
+    pub overrides: Vec<CacheRuleOverride>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheRuleOverride {
+    /// glob relative to `output.path`
+    pub pattern: String,
+    pub rule: CacheRule,
+}
+
+create_deserialize_fn!(deserialize_cache_rules, CacheRulesConfig);
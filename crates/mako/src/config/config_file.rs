@@ -0,0 +1,345 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use crate::compiler::{Args, Compiler, Context};
+use crate::config::{Config, Mode};
+use crate::plugin::{Plugin, PluginResolveIdParams};
+use crate::resolve::{ExternalResource, ResolverResource};
+use crate::utils::output_fs::{MemoryFileSystem, OutputFileSystem};
+
+/// The config files looked up in the root, the first existing one is used.
+pub const CONFIG_FILES: [&str; 7] = [
+    "mako.config.json",
+    "mako.config.ts",
+    "mako.config.mts",
+    "mako.config.cts",
+    "mako.config.js",
+    "mako.config.mjs",
+    "mako.config.cjs",
+];
+
+// keys handled by @umijs/mako before the config reaches the bundler
+const NODE_ONLY_KEYS: [&str; 4] = ["less", "sass", "plugins", "forkTSChecker"];
+
+// objects whose keys are user defined even though the default is not empty
+const FREE_FORM_KEYS: [&str; 1] = ["targets"];
+
+const RESULT_MARKER: &str = "__MAKO_CONFIG_RESULT__";
+
+// runs the bundled config, which is read from stdin, as if it's the config file
+const EVALUATE_SCRIPT: &str = r#"
+const Module = require('module');
+const path = require('path');
+const [file, mode] = process.argv.slice(1);
+const code = require('fs').readFileSync(0, 'utf-8');
+const m = new Module(file);
+m.filename = file;
+m.paths = Module._nodeModulePaths(path.dirname(file));
+const nodeRequire = m.require.bind(m);
+m.require = (id) =>
+  id === '@umijs/mako' ? { defineConfig: (config) => config } : nodeRequire(id);
+function findFunction(value, keyPath) {
+  if (typeof value === 'function') return keyPath || 'default';
+  if (value && typeof value === 'object') {
+    for (const key of Object.keys(value)) {
+      const found = findFunction(
+        value[key],
+        Array.isArray(value) ? `${keyPath}[${key}]` : keyPath ? `${keyPath}.${key}` : key,
+      );
+      if (found) return found;
+    }
+  }
+}
+(async () => {
+  m._compile(code, file);
+  let config = m.exports && m.exports.__esModule ? m.exports.default : m.exports;
+  if (typeof config === 'function') config = config({ mode });
+  config = (await config) || {};
+  const found = findFunction(config, '');
+  if (found) {
+    throw new Error(
+      `"${found}" is a function, JS plugins and other functions are only supported by the build API of @umijs/mako`,
+    );
+  }
+  process.stdout.write(`\n__MAKO_CONFIG_RESULT__${JSON.stringify(config)}`);
+})().catch((e) => {
+  console.error((e && e.stack) || e);
+  process.exit(1);
+});
+"#;
+
+pub fn find_config_file(root: &Path) -> Option<PathBuf> {
+    CONFIG_FILES
+        .iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file())
+}
+
+pub(crate) fn is_script_config(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext != "json")
+}
+
+/// Evaluate a script config with node, the default export may be a function
+/// of `{ mode }`. Returns the config and the local files it's built from.
+pub(crate) fn load_script_config(
+    root: &Path,
+    file: &Path,
+    mode: &Mode,
+) -> Result<(Value, Vec<PathBuf>)> {
+    crate::mako_profile_function!();
+
+    let (code, files) = bundle_config_file(root, file)?;
+    let mut child = Command::new("node")
+        .arg("-e")
+        .arg(EVALUATE_SCRIPT)
+        .arg(file)
+        .arg(mode.to_string())
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("node is required to load {}: {}", file.display(), e))?;
+    child.stdin.take().unwrap().write_all(code.as_bytes())?;
+    let output = child.wait_with_output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let result = stdout
+        .rsplit_once(&format!("\n{}", RESULT_MARKER))
+        .filter(|_| output.status.success());
+    let Some((logs, result)) = result else {
+        return Err(anyhow!(
+            "Failed to load {}:\n{}{}",
+            file.display(),
+            stdout,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    };
+    // logs of the config itself
+    if !logs.is_empty() {
+        eprintln!("{}", logs);
+    }
+    let config: Value = serde_json::from_str(result)?;
+    if !config.is_object() {
+        return Err(anyhow!(
+            "{} must export an object, but got {}",
+            file.display(),
+            result
+        ));
+    }
+    Ok((config, files))
+}
+
+// bundle the config file and its local imports into one commonjs file, with
+// packages required from node
+fn bundle_config_file(root: &Path, file: &Path) -> Result<(String, Vec<PathBuf>)> {
+    let output_path = root.join("node_modules/.mako/config");
+    let bundle_config = json!({
+        "mode": "none",
+        "entry": { "config": file },
+        "output": { "path": output_path },
+        "platform": "node",
+        "cjs": true,
+        "devtool": false,
+        "devServer": false,
+        "progress": false,
+        "clean": false,
+        "copy": [],
+        "hash": false,
+        "dynamicImportToRequire": true,
+    });
+    let mut config = Config::from_sources(root, None, None, Some(&bundle_config.to_string()))?;
+    config.quiet = true;
+    let output_fs = Arc::new(MemoryFileSystem::new());
+    let compiler = Compiler::new_with_output_fs(
+        config,
+        root.to_path_buf(),
+        Args { watch: false },
+        Some(vec![Arc::new(ExternalPackagesPlugin {})]),
+        Some(output_fs.clone()),
+    )?;
+    compiler
+        .compile()
+        .map_err(|e| anyhow!("Failed to bundle {}: {}", file.display(), e))?;
+    let code = output_fs.read(&output_path.join("config.js"))?;
+    let files = compiler
+        .context
+        .module_graph
+        .read()
+        .unwrap()
+        .modules()
+        .iter()
+        .filter(|module| {
+            module
+                .info
+                .as_ref()
+                .is_some_and(|info| info.external.is_none())
+        })
+        .map(|module| PathBuf::from(&module.id.id))
+        .filter(|path| path.is_file())
+        .collect();
+    Ok((String::from_utf8(code)?, files))
+}
+
+// packages are required at runtime instead of bundled
+struct ExternalPackagesPlugin {}
+
+impl Plugin for ExternalPackagesPlugin {
+    fn name(&self) -> &str {
+        "external_packages"
+    }
+
+    fn resolve_id(
+        &self,
+        source: &str,
+        _importer: &str,
+        _params: &PluginResolveIdParams,
+        _context: &Arc<Context>,
+    ) -> Result<Option<ResolverResource>> {
+        if source.starts_with('.') || Path::new(source).is_absolute() {
+            return Ok(None);
+        }
+        Ok(Some(ResolverResource::External(ExternalResource {
+            source: source.to_string(),
+            external: format!("require(\"{}\")", source),
+            script: None,
+        })))
+    }
+}
+
+/// Reject the keys which are unknown to the config, with the closest known
+/// keys as suggestions.
+pub(crate) fn validate_config_keys(config: &Value, file_name: &str) -> Result<()> {
+    let mut known = serde_json::to_value(Config::default())?;
+    for key in NODE_ONLY_KEYS {
+        known[key] = Value::Null;
+    }
+    let mut errors = vec![];
+    collect_unknown_keys(config, &known, "", &mut errors);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Invalid config in {}:\n{}",
+        file_name,
+        errors
+            .iter()
+            .map(|e| format!("  {}", e))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+fn collect_unknown_keys(config: &Value, known: &Value, prefix: &str, errors: &mut Vec<String>) {
+    let (Some(config), Some(known)) = (config.as_object(), known.as_object()) else {
+        return;
+    };
+    for (key, value) in config {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match known.get(key) {
+            Some(known_value) => {
+                let is_nested = known_value.as_object().is_some_and(|o| !o.is_empty())
+                    && !(prefix.is_empty() && FREE_FORM_KEYS.contains(&key.as_str()));
+                if is_nested {
+                    collect_unknown_keys(value, known_value, &path, errors);
+                }
+            }
+            None => {
+                let suggestion = suggest_key(key, known.keys());
+                errors.push(match suggestion {
+                    Some(suggestion) => {
+                        format!("\"{}\" — did you mean \"{}\"?", path, suggestion)
+                    }
+                    None => format!("\"{}\" is not a known config key", path),
+                });
+            }
+        }
+    }
+}
+
+fn suggest_key<'a>(key: &str, known: impl Iterator<Item = &'a String>) -> Option<&'a String> {
+    let key = key.to_lowercase();
+    let max_distance = (key.chars().count() / 3).max(1);
+    known
+        .map(|k| (strsim::damerau_levenshtein(&key, &k.to_lowercase()), k))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by(|a, b| a.cmp(b))
+        .map(|(_, k)| k)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::validate_config_keys;
+    use crate::config::Config;
+
+    #[test]
+    fn test_validate_config_keys() {
+        assert!(validate_config_keys(
+            &json!({
+                "output": { "path": "build" },
+                "targets": { "ios": 12 },
+                "define": { "FOO": "1" },
+                "less": { "modifyVars": {} },
+            }),
+            "mako.config.json"
+        )
+        .is_ok());
+        let err = validate_config_keys(
+            &json!({
+                "output": { "fileName": "[name].js" },
+                "publicPth": "/",
+                "foo": true,
+            }),
+            "mako.config.ts",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("Invalid config in mako.config.ts"), "{}", err);
+        assert!(
+            err.contains(r#""output.fileName" — did you mean "filename"?"#),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains(r#""publicPth" — did you mean "publicPath"?"#),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains(r#""foo" is not a known config key"#),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_script_config() {
+        let root = std::env::current_dir().unwrap().join("test/config/script");
+        let config = Config::new(&root, None, Some(r#"{ "mode": "production" }"#)).unwrap();
+        // branches on the mode passed to the exported function
+        assert_eq!(config.public_path, "/production/");
+        // imported from a local file
+        assert_eq!(
+            config.define.get("VERSION"),
+            Some(&serde_json::Value::String("\"1.0.0\"".to_string()))
+        );
+        assert!(config
+            .config_files
+            .iter()
+            .any(|file| file.ends_with("mako.config.ts")));
+        assert!(config
+            .config_files
+            .iter()
+            .any(|file| file.ends_with("version.ts")));
+    }
+}
@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+/// Inlines the above the fold css rules of the entries in the html, and
+/// loads the rest without blocking the render.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CriticalCssConfig {
+    /// entry name -> the rules to inline
+    pub entries: HashMap<String, CriticalCssEntryConfig>,
+    /// html template which the tags are injected to
+    pub template: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct CriticalCssEntryConfig {
+    /// globs of the selectors, e.g. `.header*`
+    #[serde(default)]
+    pub selectors: Vec<String>,
+    /// globs of the css files relative to the root, all their rules are inlined
+    #[serde(default)]
+    pub modules: Vec<String>,
+}
+
+create_deserialize_fn!(deserialize_critical_css, CriticalCssConfig);
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+/// Writes the report of the inline code which the build emits, e.g. the
+/// critical css in the html, with the hashes for the Content Security Policy.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CspReportConfig {
+    /// relative to output.path
+    #[serde(default = "default_file_name")]
+    pub file_name: String,
+    /// the placeholder of the `nonce` attribute of the inline elements
+    /// rendered in the html, e.g. `{{ nonce }}`, which the server replaces
+    /// for each response
+    #[serde(default)]
+    pub nonce: Option<String>,
+}
+
+fn default_file_name() -> String {
+    "csp-report.json".to_string()
+}
+
+create_deserialize_fn!(deserialize_csp_report, CspReportConfig);
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// The lowering of the css features which are not supported by the targets,
+/// each of them is opt-in.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CssLoweringConfig {
+    /// expand `:is()` and `:where()` into the selectors of their arguments
+    #[serde(default)]
+    pub is_where: bool,
+    /// convert the logical properties and values to the physical ones
+    #[serde(default)]
+    pub logical_properties: bool,
+    /// the direction which the logical properties are converted by
+    #[serde(default)]
+    pub direction: CssDirection,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CssDirection {
+    #[serde(rename = "ltr")]
+    #[default]
+    Ltr,
+    #[serde(rename = "rtl")]
+    Rtl,
+}
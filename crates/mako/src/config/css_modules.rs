@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct CssModulesConfig {
+    /// write a `.d.ts` with the class names next to each css module of the
+    /// project
+    #[serde(default)]
+    pub declarations: bool,
+}
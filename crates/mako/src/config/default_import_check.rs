@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefaultImportCheckConfig {
+    /// fail the build when a module default-imports an esm module without a
+    /// default export
+    #[serde(rename = "error")]
+    Error,
+    #[serde(rename = "warn")]
+    Warn,
+}
+
+create_deserialize_fn!(deserialize_default_import_check, DefaultImportCheckConfig);
@@ -13,3 +13,20 @@ pub enum DevtoolConfig {
 }
 
 create_deserialize_fn!(deserialize_devtool, DevtoolConfig);
+
+/// The sources whose frames are skipped by the debuggers, which are added to
+/// the `ignoreList` of the source maps.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SourceMapIgnoreListConfig {
+    /// `true` for the packages in node_modules, `false` to disable it
+    Enabled(bool),
+    /// globs of the source paths relative to the root
+    Globs(Vec<String>),
+}
+
+impl SourceMapIgnoreListConfig {
+    pub fn default_globs() -> Vec<String> {
+        vec!["**/node_modules/**".to_string()]
+    }
+}
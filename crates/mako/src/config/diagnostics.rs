@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::DiagnosticCode;
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsConfig {
+    /// the codes reported as errors, which fail the build
+    #[serde(default)]
+    pub error_on: Vec<DiagnosticCode>,
+    #[serde(default)]
+    pub ignore: Vec<DiagnosticIgnoreRule>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticIgnoreRule {
+    pub code: DiagnosticCode,
+    /// glob relative to the root, the diagnostics of any path are ignored
+    /// without it
+    pub path_pattern: Option<String>,
+}
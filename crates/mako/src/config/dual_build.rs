@@ -0,0 +1,30 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+/// Builds `nomodule` legacy bundles next to the modern ones.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DualBuildConfig {
+    /// targets of the legacy build
+    #[serde(default = "default_legacy_targets")]
+    pub targets: HashMap<String, f32>,
+    /// dir under output.path for the legacy outputs
+    #[serde(default = "default_legacy_output_dir")]
+    pub output_dir: String,
+    /// html template which the script tags are injected to
+    pub template: Option<PathBuf>,
+}
+
+fn default_legacy_targets() -> HashMap<String, f32> {
+    HashMap::from([("ie".to_string(), 11.0)])
+}
+
+fn default_legacy_output_dir() -> String {
+    "legacy".to_string()
+}
+
+create_deserialize_fn!(deserialize_dual_build, DualBuildConfig);
@@ -10,6 +10,9 @@ pub struct DuplicatePackageCheckerConfig {
     pub emit_error: bool,
     #[serde(rename = "showHelp", default)]
     pub show_help: bool,
+    /// names of the packages which are allowed to have multiple versions
+    #[serde(default)]
+    pub allow: Vec<String>,
 }
 
 create_deserialize_fn!(
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatedModulesConfig {
+    /// warn when the extra copies of the modules in the chunks are more than
+    /// the bytes
+    pub max_bytes: u64,
+}
+
+create_deserialize_fn!(deserialize_duplicated_modules, DuplicatedModulesConfig);
@@ -0,0 +1,173 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// An entry, which is the path of the entry module, or
+/// `{ "import": "./src/index.tsx", "dependOn": "bootstrap" }` for an entry
+/// which shares the modules and the runtime of the entries it depends on,
+/// they are loaded and executed before it.
+///
+/// `{ "import": "./src/sw.ts", "target": "webworker" }` is an entry run in a
+/// worker, e.g. a service worker, see [EntryTarget::Webworker].
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(from = "EntryValue", rename_all = "camelCase")]
+pub struct EntryConfig {
+    pub import: PathBuf,
+    pub depend_on: Vec<String>,
+    pub target: EntryTarget,
+    /// `self.__MAKO_MANIFEST__` of the other emitted files in the webworker
+    /// entry, for the precaching of a service worker
+    pub precache_manifest: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryTarget {
+    #[default]
+    Web,
+    /// The chunks are loaded by `importScripts`, the css imports are the urls
+    /// of the css files which are not injected, and the entry is not in the
+    /// html and emitted without the hash in the file name.
+    Webworker,
+}
+
+impl From<PathBuf> for EntryConfig {
+    fn from(import: PathBuf) -> Self {
+        Self {
+            import,
+            depend_on: vec![],
+            target: EntryTarget::Web,
+            precache_manifest: false,
+        }
+    }
+}
+
+impl EntryConfig {
+    pub fn is_webworker(&self) -> bool {
+        self.target == EntryTarget::Webworker
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EntryValue {
+    Import(PathBuf),
+    #[serde(rename_all = "camelCase")]
+    Descriptor {
+        import: PathBuf,
+        depend_on: Option<DependOn>,
+        #[serde(default)]
+        target: EntryTarget,
+        #[serde(default)]
+        precache_manifest: bool,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DependOn {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<EntryValue> for EntryConfig {
+    fn from(value: EntryValue) -> Self {
+        match value {
+            EntryValue::Import(import) => import.into(),
+            EntryValue::Descriptor {
+                import,
+                depend_on,
+                target,
+                precache_manifest,
+            } => Self {
+                import,
+                depend_on: match depend_on {
+                    None => vec![],
+                    Some(DependOn::One(name)) => vec![name],
+                    Some(DependOn::Many(names)) => names,
+                },
+                target,
+                precache_manifest,
+            },
+        }
+    }
+}
+
+/// An item run before each entry, e.g. `"core-js/stable"`,
+/// `"./src/telemetry-init.ts"` which is relative to the root, or
+/// `{ "inline": "window.__BUILD__ = '1';" }` for a snippet.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum EntryPreludeConfig {
+    Import(String),
+    Inline { inline: String },
+}
+
+/// The dependOn of the entries should be the other entries without cycles.
+pub(crate) fn validate_depend_on(entry: &BTreeMap<String, EntryConfig>) -> Result<()> {
+    fn visit<'a>(
+        name: &'a str,
+        entry: &'a BTreeMap<String, EntryConfig>,
+        visiting: &mut Vec<&'a str>,
+        visited: &mut HashSet<&'a str>,
+    ) -> Result<()> {
+        if visited.contains(name) {
+            return Ok(());
+        }
+        if visiting.contains(&name) {
+            visiting.push(name);
+            return Err(anyhow!(
+                "entry dependOn has a circular dependency: {}",
+                visiting.join(" -> ")
+            ));
+        }
+        visiting.push(name);
+        for depend_on in &entry[name].depend_on {
+            if depend_on == name {
+                return Err(anyhow!("entry:{} can not depend on itself", name));
+            }
+            if !entry.contains_key(depend_on) {
+                return Err(anyhow!(
+                    "entry:{} depends on entry:{} which is not found",
+                    name,
+                    depend_on
+                ));
+            }
+            visit(depend_on, entry, visiting, visited)?;
+        }
+        visiting.pop();
+        visited.insert(name);
+        Ok(())
+    }
+
+    let mut visited = HashSet::new();
+    for name in entry.keys() {
+        visit(name, entry, &mut vec![], &mut visited)?;
+    }
+    Ok(())
+}
+
+/// The webworker entries have their own runtime, so they don't share it with
+/// the other entries by dependOn.
+pub(crate) fn validate_webworker(entry: &BTreeMap<String, EntryConfig>) -> Result<()> {
+    for (name, config) in entry {
+        if config.precache_manifest && !config.is_webworker() {
+            return Err(anyhow!(
+                "entry:{} precacheManifest can only be used with the webworker target",
+                name
+            ));
+        }
+        for depend_on in &config.depend_on {
+            if config.is_webworker() || entry[depend_on].is_webworker() {
+                return Err(anyhow!(
+                    "entry:{} can not depend on entry:{}, the webworker entries can not be used with dependOn",
+                    name,
+                    depend_on
+                ));
+            }
+        }
+    }
+    Ok(())
+}
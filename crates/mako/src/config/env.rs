@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+// e.g. `{ "env": { "production": { "devtool": false } } }`
+const ENV_KEY: &str = "env";
+// e.g. `"+copy": ["public"]` appends to `copy` instead of replacing it
+const APPEND_PREFIX: char = '+';
+
+/// The config without `env`, and the config with each overlay of `env` merged
+/// over it by the names of the envs, so that the overlays are validated like
+/// the config.
+pub(crate) fn env_configs(config: &Value) -> Result<(Value, BTreeMap<String, Value>)> {
+    let mut base = config.clone();
+    let overlays = match base.as_object_mut().and_then(|map| map.remove(ENV_KEY)) {
+        None => return Ok((base, BTreeMap::new())),
+        Some(Value::Object(overlays)) => overlays,
+        Some(_) => return Err(anyhow!("{} must be an object of the envs", ENV_KEY)),
+    };
+    let envs = overlays
+        .into_iter()
+        .map(|(name, overlay)| {
+            if !overlay.is_object() {
+                return Err(anyhow!("{}.{} must be an object", ENV_KEY, name));
+            }
+            let mut merged = base.clone();
+            merge(&mut merged, &overlay, &format!("{}.{}", ENV_KEY, name))?;
+            Ok((name, merged))
+        })
+        .collect::<Result<_>>()?;
+    Ok((base, envs))
+}
+
+// objects are merged deeply, the other values including the arrays are
+// replaced, unless the key is prefixed with `+` which appends to the array
+fn merge(base: &mut Value, overlay: &Value, path: &str) -> Result<()> {
+    let Value::Object(overlay) = overlay else {
+        *base = overlay.clone();
+        return Ok(());
+    };
+    if !base.is_object() {
+        *base = Value::Object(Map::new());
+    }
+    let base = base.as_object_mut().unwrap();
+    for (key, value) in overlay {
+        if let Some(key) = key.strip_prefix(APPEND_PREFIX) {
+            let Value::Array(values) = value else {
+                return Err(anyhow!(
+                    "{}.{}{} must be an array to append",
+                    path,
+                    APPEND_PREFIX,
+                    key
+                ));
+            };
+            match base.get_mut(key) {
+                Some(Value::Array(base_values)) => base_values.extend(values.iter().cloned()),
+                _ => {
+                    base.insert(key.to_string(), value.clone());
+                }
+            }
+        } else {
+            let base_value = base.entry(key.clone()).or_insert(Value::Null);
+            merge(base_value, value, &format!("{}.{}", path, key))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::env_configs;
+
+    #[test]
+    fn test_env_configs() {
+        let config = json!({
+            "devtool": "source-map",
+            "copy": ["public"],
+            "resolve": { "alias": [["a", "./a"]], "extensions": ["js"] },
+            "env": {
+                "production": {
+                    "devtool": false,
+                    "+copy": ["static"],
+                    "resolve": { "alias": [["b", "./b"]] },
+                    "define": { "+API": ["x"] }
+                },
+                "staging": { "output": { "path": "dist-staging" } }
+            }
+        });
+        let (base, envs) = env_configs(&config).unwrap();
+        assert!(base.get("env").is_none());
+        assert_eq!(base["devtool"], "source-map");
+        assert_eq!(
+            envs.keys().collect::<Vec<_>>(),
+            vec!["production", "staging"]
+        );
+        let production = &envs["production"];
+        assert_eq!(production["devtool"], false);
+        assert_eq!(production["copy"], json!(["public", "static"]));
+        assert_eq!(
+            production["resolve"],
+            json!({ "alias": [["b", "./b"]], "extensions": ["js"] })
+        );
+        assert_eq!(production["define"], json!({ "API": ["x"] }));
+        assert_eq!(envs["staging"]["output"], json!({ "path": "dist-staging" }));
+        assert_eq!(envs["staging"]["copy"], json!(["public"]));
+    }
+
+    #[test]
+    fn test_env_configs_errors() {
+        let err = env_configs(&json!({ "env": { "production": { "+devtool": false } } }))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "env.production.+devtool must be an array to append");
+        let err = env_configs(&json!({ "env": { "production": false } }))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "env.production must be an object");
+    }
+}
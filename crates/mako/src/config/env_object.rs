@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+/// The replacement of `process.env` used as an object in the browser, e.g.
+/// `{ ...process.env }`, which is not replaced by define.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvObjectConfig {
+    /// `{ NODE_ENV }`
+    #[serde(rename = "empty")]
+    Empty,
+    /// the `process.env` at runtime if there's one, otherwise `{ NODE_ENV }`
+    #[serde(rename = "runtime")]
+    Runtime,
+}
+
+create_deserialize_fn!(deserialize_env_object, EnvObjectConfig);
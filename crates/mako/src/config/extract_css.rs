@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+/// Moves the css of the chunks to the css only chunks loaded by the entries,
+/// so that the edits of the js don't change the css files and the reverse.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractCssConfig {
+    /// a css file is moved to the chunk of the first group it matches
+    #[serde(default = "default_groups")]
+    pub groups: Vec<ExtractCssGroup>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ExtractCssGroup {
+    pub name: String,
+    /// globs of the css files relative to the root, all the css files if
+    /// it's empty
+    #[serde(default)]
+    pub test: Vec<String>,
+}
+
+fn default_groups() -> Vec<ExtractCssGroup> {
+    vec![ExtractCssGroup {
+        name: "styles".to_string(),
+        test: vec![],
+    }]
+}
+
+create_deserialize_fn!(deserialize_extract_css, ExtractCssConfig);
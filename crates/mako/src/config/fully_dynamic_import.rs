@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullyDynamicImportConfig {
+    /// fail the build when the argument of `import()` or `require()` has no
+    /// static prefix
+    #[serde(rename = "error")]
+    Error,
+    #[serde(rename = "warn")]
+    Warn,
+}
+
+create_deserialize_fn!(deserialize_fully_dynamic_import, FullyDynamicImportConfig);
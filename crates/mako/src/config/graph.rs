@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    #[serde(rename = "dot")]
+    Dot,
+    #[serde(rename = "json")]
+    Json,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphConfig {
+    pub format: GraphFormat,
+    /// merge modules of the same package under node_modules into one node
+    #[serde(default)]
+    pub collapse_node_modules: bool,
+}
+
+create_deserialize_fn!(deserialize_graph, GraphConfig);
@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use glob_match::glob_match;
+
+use crate::module::relative_to_root;
+
+/// The file in the root with more `ignore` globs, one per line, the empty
+/// lines and the lines starting with `#` are skipped.
+pub const IGNORE_FILE: &str = ".mako-ignore";
+
+pub(crate) fn load_ignore_file(root: &Path) -> Vec<String> {
+    std::fs::read_to_string(root.join(IGNORE_FILE))
+        .map(|content| {
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The `ignore` glob which matches the path, the globs are relative to the
+/// root, e.g. `**/__fixtures__/**`. A dir matches if the files in it match.
+pub fn matched_ignore_rule<'a>(ignore: &'a [String], root: &Path, path: &Path) -> Option<&'a str> {
+    if ignore.is_empty() {
+        return None;
+    }
+    let relative_path = relative_to_root(&path.to_string_lossy().to_string(), &root.to_path_buf());
+    let relative_path = relative_path.trim_start_matches("./");
+    let dir_path = format!("{}/", relative_path);
+    ignore
+        .iter()
+        .map(|glob| glob.trim_start_matches("./"))
+        .find(|glob| glob_match(glob, relative_path) || glob_match(glob, &dir_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::matched_ignore_rule;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_matched_ignore_rule() {
+        let ignore = vec![
+            "**/__fixtures__/**".to_string(),
+            "./src/**/*.stories.tsx".to_string(),
+        ];
+        let root = Path::new("/root");
+        let matched = |path: &str| matched_ignore_rule(&ignore, root, Path::new(path));
+        assert_eq!(
+            matched("/root/src/__fixtures__/a/b.js"),
+            Some("**/__fixtures__/**")
+        );
+        assert_eq!(matched("/root/__fixtures__"), Some("**/__fixtures__/**"));
+        assert_eq!(
+            matched("/root/src/button.stories.tsx"),
+            Some("src/**/*.stories.tsx")
+        );
+        assert_eq!(matched("/root/src/button.tsx"), None);
+        assert_eq!(matched("/root/fixtures/a.js"), None);
+    }
+
+    #[test]
+    fn test_ignore() {
+        let compiler = setup_compiler("test/build/ignore", false);
+        compiler.compile().unwrap();
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let modules = module_graph
+            .modules()
+            .iter()
+            .map(|module| module.id.id.clone())
+            .collect::<Vec<_>>();
+        assert!(modules.iter().any(|id| id.ends_with("src/a.js")));
+        // excluded from require.context and the context modules
+        assert!(
+            !modules
+                .iter()
+                .any(|id| id.ends_with("big.js") || id.ends_with("a.stories.js")),
+            "{:?}",
+            modules
+        );
+    }
+
+    #[test]
+    fn test_ignore_import_error() {
+        let compiler = setup_compiler("test/build/ignore-import", false);
+        let err = compiler.compile().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Module './__fixtures__/data' is excluded by the ignore rule \"**/__fixtures__/**\""),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ImageOptimizationConfig {
+    /// quality used when re-encoding jpeg, 1-100
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+}
+
+fn default_quality() -> u8 {
+    80
+}
+
+impl Default for ImageOptimizationConfig {
+    fn default() -> Self {
+        ImageOptimizationConfig {
+            quality: default_quality(),
+        }
+    }
+}
+
+create_deserialize_fn!(deserialize_image_optimization, ImageOptimizationConfig);
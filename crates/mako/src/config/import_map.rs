@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+/// Generates `importmap.json` for the externals with the `module` type, so
+/// the browser resolves them to the urls.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ImportMapConfig {
+    /// url template with `{name}`, `{version}` and `{path}`
+    #[serde(default = "default_url")]
+    pub url: String,
+    /// inject `<script type="importmap">` into the html
+    #[serde(default)]
+    pub inject: bool,
+}
+
+fn default_url() -> String {
+    "https://esm.sh/{name}@{version}{path}".to_string()
+}
+
+create_deserialize_fn!(deserialize_import_map, ImportMapConfig);
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonExportsCheckConfig {
+    /// fail the build when a named import is not a key of the json module
+    #[serde(rename = "error")]
+    Error,
+    #[serde(rename = "warn")]
+    Warn,
+}
+
+create_deserialize_fn!(deserialize_json_exports_check, JsonExportsCheckConfig);
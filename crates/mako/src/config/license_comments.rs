@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum LicenseCommentsConfig {
+    /// write to `<chunk>.LICENSE.txt` and `THIRD-PARTY-NOTICES.txt`
+    #[serde(rename = "extract")]
+    Extract,
+    /// keep in the chunk even if it's minified
+    #[serde(rename = "inline")]
+    Inline,
+}
+
+create_deserialize_fn!(deserialize_license_comments, LicenseCommentsConfig);
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{create_deserialize_fn, plugins};
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadableManifestConfig {
+    #[serde(default = "plugins::loadable_manifest::default_loadable_manifest_file_name")]
+    pub file_name: String,
+}
+
+create_deserialize_fn!(deserialize_loadable_manifest, LoadableManifestConfig);
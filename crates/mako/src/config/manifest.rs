@@ -11,6 +11,12 @@ pub struct ManifestConfig {
     pub file_name: String,
     #[serde(rename(deserialize = "basePath"), default)]
     pub base_path: String,
+    // list the .gz and .br files of output.precompress
+    #[serde(default)]
+    pub precompressed: bool,
+    // prefix the files with the publicPath, like the urls in the html
+    #[serde(rename(deserialize = "publicPath"), default)]
+    pub public_path: bool,
 }
 
 create_deserialize_fn!(deserialize_manifest, ManifestConfig);
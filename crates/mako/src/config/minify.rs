@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MinifyConfig {
+    #[serde(deserialize_with = "deserialize_keep_names", default)]
+    pub keep_names: KeepNamesConfig,
+}
+
+/// Names of classes and functions to keep from mangling, e.g. for
+/// `error.constructor.name` and `fn.name`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeepNamesConfig {
+    pub class_names: bool,
+    pub function_names: bool,
+}
+
+impl KeepNamesConfig {
+    pub fn is_empty(&self) -> bool {
+        !self.class_names && !self.function_names
+    }
+}
+
+pub fn deserialize_minify<'de, D>(deserializer: D) -> Result<Option<MinifyConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Bool(false) => Ok(None),
+        serde_json::Value::Bool(true) => Ok(Some(Default::default())),
+        serde_json::Value::Object(obj) => Ok(Some(
+            serde_json::from_value::<MinifyConfig>(serde_json::Value::Object(obj))
+                .map_err(serde::de::Error::custom)?,
+        )),
+        _ => Err(serde::de::Error::custom(format!(
+            "invalid `minify` value: {}",
+            value
+        ))),
+    }
+}
+
+fn deserialize_keep_names<'de, D>(deserializer: D) -> Result<KeepNamesConfig, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Bool(keep) => Ok(KeepNamesConfig {
+            class_names: keep,
+            function_names: keep,
+        }),
+        serde_json::Value::Array(names) => {
+            let mut config = KeepNamesConfig::default();
+            for name in names {
+                match name.as_str() {
+                    Some("ClassNames") => config.class_names = true,
+                    Some("FunctionNames") => config.function_names = true,
+                    _ => {
+                        return Err(serde::de::Error::custom(format!(
+                            "invalid `minify.keepNames` item: {}, expect \"ClassNames\" or \"FunctionNames\"",
+                            name
+                        )))
+                    }
+                }
+            }
+            Ok(config)
+        }
+        _ => Err(serde::de::Error::custom(format!(
+            "invalid `minify.keepNames` value: {}",
+            value
+        ))),
+    }
+}
@@ -1,12 +1,35 @@
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, ValueEnum, Clone)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Hash, ValueEnum, Clone)]
 pub enum Mode {
     #[serde(rename = "development")]
     Development,
     #[serde(rename = "production")]
     Production,
+    /// no preset and no `NODE_ENV` define
+    #[serde(rename = "none")]
+    None,
+}
+
+impl Mode {
+    /// Defaults of the mode, applied on top of the default config and
+    /// overridden by any explicit config.
+    pub fn preset(&self) -> &'static str {
+        match self {
+            Mode::Development => include_str!("mako.config.development.json"),
+            Mode::Production => include_str!("mako.config.production.json"),
+            Mode::None => r#"{ "minify": false, "_treeShaking": false, "hmr": false }"#,
+        }
+    }
+
+    /// The value of `process.env.NODE_ENV`, none for the none mode.
+    pub fn node_env(&self) -> Option<String> {
+        match self {
+            Mode::None => None,
+            _ => Some(self.to_string()),
+        }
+    }
 }
 
 impl std::fmt::Display for Mode {
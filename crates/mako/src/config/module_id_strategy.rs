@@ -8,4 +8,6 @@ pub enum ModuleIdStrategy {
     Named,
     #[serde(rename = "numeric")]
     Numeric,
+    #[serde(rename = "deterministic")]
+    Deterministic,
 }
@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use glob_match::glob_match;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use twox_hash::XxHash64;
+
+use crate::ast::file::win_path;
+use crate::module::relative_to_root;
+
+/// Adjusts how the files matched by `test` are loaded, parsed and
+/// transformed, e.g. `{ "test": "src/legacy/**/*.js", "use": { "jsx": true } }`.
+/// The rules are applied in order, and the later ones override the former.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ModuleRule {
+    /// a glob relative to the root, or a regex of the absolute path in
+    /// slashes, e.g. `/\.frag$/`
+    pub test: String,
+    #[serde(rename = "use")]
+    pub use_: ModuleRuleUse,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ModuleRuleUse {
+    /// parse the js files with jsx
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jsx: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loader: Option<ModuleRuleLoader>,
+    /// replaced in the matched files only, after `define`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defines: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleRuleLoader {
+    /// the content as a string, like the `?raw` query
+    Raw,
+}
+
+/// The `moduleRules` of the config with the matchers compiled once, which are
+/// matched against each file when it's loaded and transformed.
+#[derive(Default)]
+pub struct ModuleRules {
+    rules: Vec<(RuleMatcher, ModuleRule)>,
+}
+
+enum RuleMatcher {
+    // relative to the root, without the leading `./`
+    Glob(String),
+    Regex(Regex),
+}
+
+/// The rules which match a file, merged in order.
+#[derive(Debug, Default, PartialEq)]
+pub struct MatchedRules {
+    /// the indexes of the rules in `moduleRules`
+    pub indexes: Vec<usize>,
+    pub jsx: Option<bool>,
+    pub loader: Option<ModuleRuleLoader>,
+    pub defines: BTreeMap<String, Value>,
+}
+
+impl ModuleRules {
+    pub fn new(rules: &[ModuleRule]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let matcher = match rule
+                    .test
+                    .strip_prefix('/')
+                    .and_then(|test| test.strip_suffix('/'))
+                {
+                    Some(regex) if !regex.is_empty() => {
+                        RuleMatcher::Regex(Regex::new(regex).map_err(|_| {
+                            anyhow!("Config Error invalid regex of moduleRules: {}", rule.test)
+                        })?)
+                    }
+                    _ => RuleMatcher::Glob(rule.test.trim_start_matches("./").to_string()),
+                };
+                Ok((matcher, rule.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn matched(&self, path: &Path, root: &Path) -> MatchedRules {
+        let mut matched = MatchedRules::default();
+        if self.rules.is_empty() {
+            return matched;
+        }
+        let path = win_path(&path.to_string_lossy());
+        let relative_path = relative_to_root(&path, &root.to_path_buf());
+        let relative_path = relative_path.trim_start_matches("./");
+        for (index, (matcher, rule)) in self.rules.iter().enumerate() {
+            let is_match = match matcher {
+                RuleMatcher::Glob(glob) => glob_match(glob, relative_path),
+                RuleMatcher::Regex(regex) => regex.is_match(&path),
+            };
+            if !is_match {
+                continue;
+            }
+            matched.indexes.push(index);
+            if rule.use_.jsx.is_some() {
+                matched.jsx = rule.use_.jsx;
+            }
+            if rule.use_.loader.is_some() {
+                matched.loader = rule.use_.loader;
+            }
+            matched.defines.extend(rule.use_.defines.clone());
+        }
+        matched
+    }
+
+    /// The rules which match the file and the merged result, for
+    /// `--show-config=<file>`.
+    pub fn format_matched(&self, path: &Path, root: &Path) -> String {
+        let matched = self.matched(path, root);
+        let relative_path =
+            relative_to_root(&path.to_string_lossy().to_string(), &root.to_path_buf());
+        if matched.indexes.is_empty() {
+            return format!("no moduleRules matched {}", relative_path);
+        }
+        let mut lines = vec![format!("moduleRules matched {}:", relative_path)];
+        for index in &matched.indexes {
+            let (_, rule) = &self.rules[*index];
+            lines.push(format!(
+                "  #{} {}",
+                index,
+                serde_json::to_string(rule).unwrap()
+            ));
+        }
+        let merged = ModuleRuleUse {
+            jsx: matched.jsx,
+            loader: matched.loader,
+            defines: matched.defines,
+        };
+        lines.push(format!(
+            "result: {}",
+            serde_json::to_string(&merged).unwrap()
+        ));
+        lines.join("\n")
+    }
+}
+
+impl MatchedRules {
+    /// Folded into the hash of the module, so it's rebuilt when the rules
+    /// which match it change, 0 if there's none.
+    pub fn hash(&self) -> u64 {
+        if self.indexes.is_empty() {
+            return 0;
+        }
+        let mut hasher: XxHash64 = Default::default();
+        for index in &self.indexes {
+            hasher.write_usize(*index);
+        }
+        hasher.write(format!("{:?}{:?}", self.jsx, self.loader).as_bytes());
+        hasher.write(serde_json::to_string(&self.defines).unwrap().as_bytes());
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn rules(rules: serde_json::Value) -> ModuleRules {
+        ModuleRules::new(&serde_json::from_value::<Vec<ModuleRule>>(rules).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_matched() {
+        let rules = rules(json!([
+            { "test": "./src/legacy/**/*.js", "use": { "jsx": true } },
+            { "test": "/\\.frag$/", "use": { "loader": "raw" } },
+            { "test": "vendor/**", "use": { "defines": { "DEBUG": "false", "A": "1" } } },
+            { "test": "vendor/b/**", "use": { "defines": { "A": "2" } } },
+        ]));
+        let root = Path::new("/project");
+        let matched = |path: &str| rules.matched(&root.join(path), root);
+
+        let legacy = matched("src/legacy/old/a.js");
+        assert_eq!(legacy.indexes, vec![0]);
+        assert_eq!(legacy.jsx, Some(true));
+        assert_eq!(matched("src/legacy/a.ts").indexes, Vec::<usize>::new());
+
+        assert_eq!(
+            matched("src/shaders/a.frag").loader,
+            Some(ModuleRuleLoader::Raw)
+        );
+
+        // the later rules override the former
+        let vendor = matched("vendor/b/index.js");
+        assert_eq!(vendor.indexes, vec![2, 3]);
+        assert_eq!(vendor.defines["A"], json!("2"));
+        assert_eq!(vendor.defines["DEBUG"], json!("false"));
+        assert_ne!(vendor.hash(), matched("vendor/a/index.js").hash());
+        assert_eq!(matched("src/index.js").hash(), 0);
+    }
+
+    #[test]
+    fn test_format_matched() {
+        let rules = rules(json!([
+            { "test": "src/legacy/**", "use": { "jsx": true } },
+            { "test": "/\\.js$/", "use": { "jsx": false, "defines": { "LEGACY": "true" } } },
+        ]));
+        let root = Path::new("/project");
+        assert_eq!(
+            rules.format_matched(&root.join("src/legacy/a.js"), root),
+            r#"moduleRules matched ./src/legacy/a.js:
+  #0 {"test":"src/legacy/**","use":{"jsx":true}}
+  #1 {"test":"/\\.js$/","use":{"jsx":false,"defines":{"LEGACY":"true"}}}
+result: {"jsx":false,"defines":{"LEGACY":"true"}}"#
+        );
+        assert_eq!(
+            rules.format_matched(&root.join("src/a.ts"), root),
+            "no moduleRules matched ./src/a.ts"
+        );
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let rules = serde_json::from_value::<Vec<ModuleRule>>(json!([
+            { "test": "/(/", "use": { "jsx": true } }
+        ]))
+        .unwrap();
+        assert!(ModuleRules::new(&rules)
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("invalid regex of moduleRules: /(/"));
+    }
+}
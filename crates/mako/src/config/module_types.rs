@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleKind {
+    Esm,
+    Commonjs,
+}
+
+/// Force the module type of the files matched by the glob, which is relative
+/// to the root, e.g. `{ "include": "node_modules/foo/**/*.js", "type": "commonjs" }`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ModuleTypeRule {
+    pub include: String,
+    #[serde(rename = "type")]
+    pub module_kind: ModuleKind,
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NamedExportsCheckConfig {
+    /// fail the build when a named import is not exported by the target module
+    #[serde(rename = "error")]
+    Error,
+    /// only print warnings, for packages which inject exports at runtime
+    #[serde(rename = "warn")]
+    Warn,
+}
+
+create_deserialize_fn!(deserialize_named_exports_check, NamedExportsCheckConfig);
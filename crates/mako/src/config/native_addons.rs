@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// How the `.node` native addons are bundled with the node platform.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NativeAddonsConfig {
+    /// emit the `.node` files as the assets, loaded from the output directory
+    #[default]
+    #[serde(rename = "emit")]
+    Emit,
+    /// keep the packages with the native addons as the externals required at
+    /// runtime
+    #[serde(rename = "external")]
+    External,
+}
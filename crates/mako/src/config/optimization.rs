@@ -6,7 +6,11 @@ use crate::create_deserialize_fn;
 #[serde(rename_all = "camelCase")]
 pub struct OptimizationConfig {
     pub skip_modules: Option<bool>,
+    pub side_effects: Option<bool>,
     pub concatenate_modules: Option<bool>,
+    pub concatenate_css: Option<bool>,
+    pub pure_functions: Option<Vec<String>>,
+    pub mangle_exports: Option<bool>,
 }
 
 create_deserialize_fn!(deserialize_optimization, OptimizationConfig);
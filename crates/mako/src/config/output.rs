@@ -5,7 +5,7 @@ use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use swc_core::ecma::ast::EsVersion;
 
-use super::Umd;
+use super::{deserialize_precompress, PrecompressConfig, Umd};
 use crate::create_deserialize_fn;
 use crate::utils::get_pkg_name;
 
@@ -18,12 +18,37 @@ pub struct OutputConfig {
     pub es_version: EsVersion,
     pub meta: bool,
     pub chunk_loading_global: String,
+    // namespace of the runtime globals, e.g. for hot updates
+    pub unique_name: String,
+    // in milliseconds
+    pub chunk_loading_timeout: u64,
     pub preserve_modules: bool,
     pub preserve_modules_root: PathBuf,
     pub skip_write: bool,
     #[serde(deserialize_with = "deserialize_cross_origin_loading")]
     pub cross_origin_loading: Option<CrossOriginLoading>,
+    // how the runtime loads the async js chunks in the browser
+    pub chunk_loading: ChunkLoading,
     pub global_module_registry: bool,
+    #[serde(deserialize_with = "deserialize_runtime_target", default)]
+    pub runtime_target: Option<RuntimeTarget>,
+    // .gz and .br siblings of the emitted files
+    #[serde(deserialize_with = "deserialize_precompress", default)]
+    pub precompress: Option<PrecompressConfig>,
+    // `//# debugId=` comments in the chunks and `debug_id` in their source maps
+    pub debug_ids: bool,
+    // the `.push.json` wrappers of the async js chunks for ssr to inline
+    pub pushable_chunks: bool,
+    // what the relative and the `auto` publicPath are resolved against
+    pub relative_url_base: RelativeUrlBase,
+    // of the hashes in the file names
+    pub hash_function: HashFunction,
+    pub hash_length: usize,
+    // the names of the emitted assets, with `[name]`, `[hash]` and `[ext]`
+    pub asset_filename: String,
+    // the css of the async chunks is reference counted and removable by
+    // `unloadChunkCss` and `releaseChunk` of the runtime
+    pub unload_chunk_css: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, ValueEnum, Clone)]
@@ -51,13 +76,110 @@ impl fmt::Display for CrossOriginLoading {
     }
 }
 
-pub fn get_default_chunk_loading_global(umd: Option<Umd>, root: &Path) -> String {
-    let unique_name = umd.map_or_else(
-        || get_pkg_name(root).unwrap_or("global".to_string()),
+/// How the runtime loads the async js chunks and the hot update chunks in the
+/// browser, the workers always load them by `importScripts`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkLoading {
+    /// `<script>` elements
+    #[serde(rename = "jsonp")]
+    Jsonp,
+    /// `import()`, e.g. where the chunks can only be loaded as modules
+    #[serde(rename = "import")]
+    Import,
+    /// `fetch()` and evaluated by `Function`, or `import()` of a blob url if
+    /// eval is disallowed, e.g. in the extension content scripts or the
+    /// sandboxed iframes whose CSP blocks the `<script>` elements
+    #[serde(rename = "fetch-eval")]
+    FetchEval,
+}
+
+/// What the runtime resolves the relative or the `auto` publicPath against,
+/// i.e. the urls of the chunks and the assets in the browser.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeUrlBase {
+    /// the url of the runtime script, `document.currentScript.src` when it
+    /// starts, or `document.baseURI` if it's unavailable, e.g. in a module
+    /// script
+    #[serde(rename = "script")]
+    Script,
+    /// `document.baseURI`, i.e. the url of the page
+    #[serde(rename = "document")]
+    Document,
+}
+
+impl fmt::Display for ChunkLoading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkLoading::Jsonp => write!(f, "jsonp"),
+            ChunkLoading::Import => write!(f, "import"),
+            ChunkLoading::FetchEval => write!(f, "fetch-eval"),
+        }
+    }
+}
+
+/// The syntax floor of the code generated by mako itself, e.g. the runtime, no
+/// matter what the targets of the app code are.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeTarget {
+    #[serde(rename = "es5")]
+    Es5,
+    #[serde(rename = "es2015")]
+    Es2015,
+    #[serde(rename = "es2020")]
+    Es2020,
+}
+
+/// The hash function of the hashes in the file names, i.e. of the chunks,
+/// the assets and the queries of the async chunk names.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashFunction {
+    #[serde(rename = "md5")]
+    Md5,
+    #[serde(rename = "xxhash")]
+    Xxhash,
+    #[serde(rename = "sha256")]
+    Sha256,
+}
+
+impl HashFunction {
+    /// The length of the hex digest.
+    pub fn max_length(&self) -> usize {
+        match self {
+            HashFunction::Md5 => 32,
+            HashFunction::Xxhash => 16,
+            HashFunction::Sha256 => 64,
+        }
+    }
+}
+
+/// Whether the names of a file name template have the content hashes, i.e.
+/// `[hash]` or `[contenthash]`.
+pub fn has_content_hash(template: &str) -> bool {
+    template.contains("[hash]") || template.contains("[contenthash]")
+}
+
+pub fn get_default_chunk_loading_global(
+    umd: Option<Umd>,
+    root: &Path,
+    unique_name: &str,
+) -> String {
+    let name = umd.map_or_else(
+        || get_pkg_name(root).unwrap_or(unique_name.to_string()),
         |umd| umd.name.clone(),
     );
 
-    format!("makoChunk_{}", unique_name)
+    format!("makoChunk_{}", name)
+}
+
+/// A hash of the umd name or the package name, or of the root if there's no
+/// name, so that builds on the same page don't share the runtime globals.
+pub fn get_default_unique_name(umd: Option<Umd>, root: &Path) -> String {
+    let name = umd.map_or_else(
+        || get_pkg_name(root).unwrap_or(root.to_string_lossy().to_string()),
+        |umd| umd.name.clone(),
+    );
+    format!("{:x}", md5::compute(name))[..8].to_string()
 }
 
 create_deserialize_fn!(deserialize_cross_origin_loading, CrossOriginLoading);
+create_deserialize_fn!(deserialize_runtime_target, RuntimeTarget);
@@ -0,0 +1,39 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+/// The looser settings to parse the files under node_modules again with,
+/// when they fail to parse with the default ones.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ParseFallbackStep {
+    /// jsx in the .js files
+    Jsx,
+    /// `export v from "mod"` which is lowered to `export { default as v }`,
+    /// and `return` outside of the functions
+    Proposals,
+    /// the Flow annotations, only for the files with the `@flow` pragma or
+    /// a `.flow` sibling
+    Flow,
+}
+
+impl fmt::Display for ParseFallbackStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ParseFallbackStep::Jsx => "jsx",
+            ParseFallbackStep::Proposals => "proposals",
+            ParseFallbackStep::Flow => "flow",
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseFallbackConfig {
+    /// tried in order, each with the settings of the steps before it
+    pub steps: Vec<ParseFallbackStep>,
+}
+
+create_deserialize_fn!(deserialize_parse_fallback, ParseFallbackConfig);
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeerDependencyCheckConfig {
+    /// fail the build when a peer dependency range is not satisfied
+    #[serde(rename = "error")]
+    Error,
+    #[serde(rename = "warn")]
+    Warn,
+}
+
+create_deserialize_fn!(deserialize_peer_dependency_check, PeerDependencyCheckConfig);
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use crate::create_deserialize_fn;
+
+/// Injects the imports of the polyfills which the targets lack, the syntax is
+/// lowered by the targets already.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PolyfillConfig {
+    pub mode: PolyfillMode,
+    #[serde(default)]
+    pub provider: PolyfillProvider,
+    /// version of the installed provider, resolved from the root
+    #[serde(skip)]
+    pub version: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PolyfillMode {
+    /// the polyfills of the built-ins referenced by each module
+    Usage,
+    /// all the polyfills of the targets before each entry
+    Entry,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PolyfillProvider {
+    #[default]
+    #[serde(rename = "core-js")]
+    CoreJs,
+}
+
+create_deserialize_fn!(deserialize_polyfill, PolyfillConfig);
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PreBundleConfig {
+    /// the packages or the specifiers which are not pre-bundled, e.g. `antd`
+    /// for `antd` and `antd/es/button`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+pub fn deserialize_pre_bundle<'de, D>(deserializer: D) -> Result<Option<PreBundleConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Bool(false) => Ok(None),
+        serde_json::Value::Bool(true) => Ok(Some(Default::default())),
+        serde_json::Value::Object(obj) => Ok(Some(
+            serde_json::from_value::<PreBundleConfig>(serde_json::Value::Object(obj))
+                .map_err(serde::de::Error::custom)?,
+        )),
+        _ => Err(serde::de::Error::custom(format!(
+            "invalid `preBundle` value: {}",
+            value
+        ))),
+    }
+}
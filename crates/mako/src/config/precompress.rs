@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecompressAlgorithm {
+    #[serde(rename = "gzip")]
+    Gzip,
+    #[serde(rename = "brotli")]
+    Brotli,
+}
+
+impl PrecompressAlgorithm {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PrecompressAlgorithm::Gzip => "gz",
+            PrecompressAlgorithm::Brotli => "br",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PrecompressConfig {
+    pub algorithms: Vec<PrecompressAlgorithm>,
+    // 0-9
+    #[serde(default = "default_gzip_level")]
+    pub gzip_level: u32,
+    // 0-11
+    #[serde(default = "default_brotli_quality")]
+    pub brotli_quality: u32,
+    // files smaller than it in bytes are not compressed
+    #[serde(default = "default_threshold")]
+    pub threshold: usize,
+}
+
+impl From<Vec<PrecompressAlgorithm>> for PrecompressConfig {
+    fn from(algorithms: Vec<PrecompressAlgorithm>) -> Self {
+        Self {
+            algorithms,
+            gzip_level: default_gzip_level(),
+            brotli_quality: default_brotli_quality(),
+            threshold: default_threshold(),
+        }
+    }
+}
+
+fn default_gzip_level() -> u32 {
+    9
+}
+
+fn default_brotli_quality() -> u32 {
+    11
+}
+
+fn default_threshold() -> usize {
+    1024
+}
+
+/// `false`, a list of the algorithms, or the full config.
+pub fn deserialize_precompress<'de, D>(
+    deserializer: D,
+) -> Result<Option<PrecompressConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Bool(false) | serde_json::Value::Null => Ok(None),
+        serde_json::Value::Array(_) => {
+            let algorithms = serde_json::from_value::<Vec<PrecompressAlgorithm>>(value)
+                .map_err(serde::de::Error::custom)?;
+            Ok(Some(algorithms.into()))
+        }
+        serde_json::Value::Object(_) => Ok(Some(
+            serde_json::from_value::<PrecompressConfig>(value).map_err(serde::de::Error::custom)?,
+        )),
+        _ => Err(serde::de::Error::custom(format!(
+            "invalid `precompress` value: {}",
+            value
+        ))),
+    }
+}
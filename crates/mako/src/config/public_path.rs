@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Result};
+
+/// How the `publicPath`, which is the base of the urls of the output files,
+/// is resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublicPathKind {
+    /// `runtime`, read from `globalThis.publicPath` when the runtime starts
+    Runtime,
+    /// `auto`, the directory of the current script
+    Auto,
+    /// e.g. `./` or `../static/`, relative to the html, the chunks are
+    /// loaded relative to the current script since it's in the same directory
+    Relative,
+    /// e.g. `/` or `/app/`
+    Absolute,
+    /// e.g. `https://cdn.com/app/` or `//cdn.com/app/`
+    Url,
+}
+
+impl PublicPathKind {
+    pub fn of(public_path: &str) -> Self {
+        match public_path {
+            "runtime" => PublicPathKind::Runtime,
+            "auto" => PublicPathKind::Auto,
+            _ if public_path.starts_with("//") || public_path.contains("://") => {
+                PublicPathKind::Url
+            }
+            _ if public_path.starts_with('/') => PublicPathKind::Absolute,
+            _ => PublicPathKind::Relative,
+        }
+    }
+
+    /// Whether it's known at build time, otherwise it's resolved by the
+    /// runtime in the browser.
+    pub fn is_static(&self) -> bool {
+        !matches!(self, PublicPathKind::Runtime | PublicPathKind::Auto)
+    }
+}
+
+/// The prefix of the urls written at build time, i.e. in the html, the
+/// manifest and the inlined css, which is empty for `runtime` and `auto`
+/// since the files are next to the html then.
+pub fn static_public_path(public_path: &str) -> &str {
+    if PublicPathKind::of(public_path).is_static() {
+        public_path
+    } else {
+        ""
+    }
+}
+
+pub(crate) fn validate_public_path(public_path: &str) -> Result<()> {
+    let kind = PublicPathKind::of(public_path);
+    if !kind.is_static() {
+        return Ok(());
+    }
+    if !public_path.ends_with('/') {
+        return Err(anyhow!("public_path must end with '/' or be 'runtime'"));
+    }
+    if public_path.contains(['?', '#']) {
+        return Err(anyhow!(
+            "public_path {} should not contain a query or a hash",
+            public_path
+        ));
+    }
+    if kind == PublicPathKind::Url
+        && !["http://", "https://", "//"]
+            .iter()
+            .any(|scheme| public_path.starts_with(scheme))
+    {
+        return Err(anyhow!(
+            "public_path {} should start with http://, https:// or //",
+            public_path
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_path_kind() {
+        assert_eq!(PublicPathKind::of("runtime"), PublicPathKind::Runtime);
+        assert_eq!(PublicPathKind::of("auto"), PublicPathKind::Auto);
+        assert_eq!(PublicPathKind::of("./"), PublicPathKind::Relative);
+        assert_eq!(PublicPathKind::of("../static/"), PublicPathKind::Relative);
+        assert_eq!(PublicPathKind::of("static/"), PublicPathKind::Relative);
+        assert_eq!(PublicPathKind::of("/"), PublicPathKind::Absolute);
+        assert_eq!(PublicPathKind::of("/app/"), PublicPathKind::Absolute);
+        assert_eq!(
+            PublicPathKind::of("https://cdn.com/app/"),
+            PublicPathKind::Url
+        );
+        assert_eq!(PublicPathKind::of("//cdn.com/app/"), PublicPathKind::Url);
+    }
+
+    #[test]
+    fn test_static_public_path() {
+        assert_eq!(static_public_path("runtime"), "");
+        assert_eq!(static_public_path("auto"), "");
+        assert_eq!(static_public_path("./"), "./");
+        assert_eq!(static_public_path("/app/"), "/app/");
+        assert_eq!(
+            static_public_path("https://cdn.com/app/"),
+            "https://cdn.com/app/"
+        );
+    }
+
+    #[test]
+    fn test_validate_public_path() {
+        assert!(validate_public_path("runtime").is_ok());
+        assert!(validate_public_path("auto").is_ok());
+        assert!(validate_public_path("./").is_ok());
+        assert!(validate_public_path("/app/").is_ok());
+        assert!(validate_public_path("https://cdn.com/app/").is_ok());
+        assert!(validate_public_path("//cdn.com/app/").is_ok());
+        assert!(validate_public_path("/app").is_err());
+        assert!(validate_public_path("").is_err());
+        assert!(validate_public_path("/app/?v=1/").is_err());
+        assert!(validate_public_path("ftp://cdn.com/app/").is_err());
+    }
+}
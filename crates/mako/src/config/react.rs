@@ -8,6 +8,12 @@ pub struct ReactConfig {
     pub runtime: ReactRuntimeConfig,
     #[serde(rename = "pragmaFrag")]
     pub pragma_frag: String,
+    #[serde(
+        rename = "removePropTypes",
+        deserialize_with = "deserialize_remove_prop_types",
+        default
+    )]
+    pub remove_prop_types: Option<RemovePropTypesConfig>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -17,3 +23,36 @@ pub enum ReactRuntimeConfig {
     #[serde(rename = "classic")]
     Classic,
 }
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovePropTypesConfig {
+    /// regexes of the paths of the modules under node_modules to strip too,
+    /// which are left alone by default
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// strip `defaultProps` too, which change the props at runtime
+    #[serde(default)]
+    pub default_props: bool,
+}
+
+pub fn deserialize_remove_prop_types<'de, D>(
+    deserializer: D,
+) -> Result<Option<RemovePropTypesConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Bool(false) => Ok(None),
+        serde_json::Value::Bool(true) => Ok(Some(Default::default())),
+        serde_json::Value::Object(obj) => Ok(Some(
+            serde_json::from_value::<RemovePropTypesConfig>(serde_json::Value::Object(obj))
+                .map_err(serde::de::Error::custom)?,
+        )),
+        _ => Err(serde::de::Error::custom(format!(
+            "invalid `react.removePropTypes` value: {}",
+            value
+        ))),
+    }
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// How the remote css of `@import` and the remote fonts of `@font-face` are
+/// handled, e.g. `@import url("https://fonts.googleapis.com/css2?family=Inter")`.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteCssConfig {
+    /// kept as is, loaded by the browsers
+    #[default]
+    Preserve,
+    /// kept as is and reported as `MAKO_REMOTE_CSS`
+    Warn,
+    /// fetched at build time and bundled, the fonts are emitted as assets
+    Download,
+}
@@ -1,7 +1,24 @@
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct ResolveConfig {
     pub alias: Vec<(String, String)>,
     pub extensions: Vec<String>,
+    /// tsconfig.json whose `paths` are used to resolve the scripts
+    #[serde(default)]
+    pub tsconfig: Option<PathBuf>,
+    /// Custom conditions of the scripts, e.g. `web`, which select
+    /// `Button.web.tsx` over `Button.tsx` and the matched conditions of
+    /// `exports` and `imports` in package.json
+    #[serde(default)]
+    pub condition_names: Vec<String>,
+    /// Directories tried in order for the relative, absolute and aliased
+    /// requests of the files under them, e.g. `["./brands/acme", "./src"]`
+    /// resolves `./Button` from `src/Header.tsx` to `brands/acme/Button.tsx`
+    /// if it exists, otherwise to `src/Button.tsx`
+    #[serde(default)]
+    pub roots: Vec<PathBuf>,
 }
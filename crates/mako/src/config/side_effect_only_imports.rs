@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SideEffectOnlyImportsConfig {
+    /// regexes of the sources of the imports to rewrite, all the imports if
+    /// it's empty
+    #[serde(default)]
+    pub include: Vec<String>,
+}
+
+pub fn deserialize_side_effect_only_imports<'de, D>(
+    deserializer: D,
+) -> Result<Option<SideEffectOnlyImportsConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Bool(false) => Ok(None),
+        serde_json::Value::Bool(true) => Ok(Some(Default::default())),
+        serde_json::Value::Object(obj) => Ok(Some(
+            serde_json::from_value::<SideEffectOnlyImportsConfig>(serde_json::Value::Object(obj))
+                .map_err(serde::de::Error::custom)?,
+        )),
+        _ => Err(serde::de::Error::custom(format!(
+            "invalid `sideEffectOnlyImports` value: {}",
+            value
+        ))),
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// Reduces the size of the emitted source maps.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceMapConfig {
+    /// leave out the `sourcesContent` of the sources in node_modules
+    #[serde(default)]
+    pub exclude_vendor_sources: bool,
+    /// write the maps as index maps, whose sections of node_modules are
+    /// shared files
+    #[serde(default)]
+    pub index_map: bool,
+    /// the mappings of the vendor sources
+    #[serde(default)]
+    pub vendor: SourceMapFidelity,
+    /// globs of the sources relative to the root which are vendor sources
+    /// too, besides the ones in node_modules
+    #[serde(default)]
+    pub vendor_globs: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SourceMapFidelity {
+    /// no mappings
+    None,
+    /// the mappings of the lines only, without the `sourcesContent`
+    Cheap,
+    #[default]
+    Full,
+}
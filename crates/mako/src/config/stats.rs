@@ -4,7 +4,17 @@ use crate::create_deserialize_fn;
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct StatsConfig {
+    #[serde(default)]
     pub modules: bool,
+    /// sizes and tree shaking of the bundled packages
+    #[serde(default)]
+    pub packages: bool,
+    /// names and bytes saved by optimization.mangleExports
+    #[serde(rename = "mangleExports", default)]
+    pub mangle_exports: bool,
+    /// builtAt, startTime and endTime, which differ in every build
+    #[serde(default)]
+    pub timestamps: bool,
 }
 
 create_deserialize_fn!(deserialize_stats, StatsConfig);
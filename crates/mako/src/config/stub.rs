@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const STUB_NOOP: &str = "noop";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StubConfig {
+    /// the named exports of the stub, the default export is an object of them
+    #[serde(default)]
+    pub exports: Vec<String>,
+    /// `"noop"` for the functions which do nothing, or the literal value of
+    /// each export
+    #[serde(default = "default_value")]
+    pub value: Value,
+}
+
+fn default_value() -> Value {
+    Value::String(STUB_NOOP.to_string())
+}
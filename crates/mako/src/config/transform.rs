@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// Which files go through syntax lowering by the targets, by package names,
+/// e.g. `"@scope/name"`, or globs relative to the root. Files under
+/// node_modules are excluded by default, exclude wins over include.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct TransformConfig {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
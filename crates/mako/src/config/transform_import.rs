@@ -1,5 +1,10 @@
+use anyhow::{anyhow, Result};
+use convert_case::{Case, Casing};
+use regex::Captures;
 use serde::{Deserialize, Serialize};
 
+use crate::utils::create_cached_regex;
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(untagged)]
 pub enum TransformImportStyle {
@@ -14,3 +19,56 @@ pub struct TransformImportConfig {
     pub library_directory: Option<String>,
     pub style: Option<TransformImportStyle>,
 }
+
+/// Rewrites the named imports of a package to the imports of the files of the
+/// members, e.g. `import { DatePicker } from 'antd'` with
+/// `{ transform: "antd/es/{{ kebabCase member }}" }` to
+/// `import DatePicker from 'antd/es/date-picker'`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TransformImportsConfig {
+    pub transform: String,
+    /// imported for the side effects after each member, e.g. the styles
+    pub style: Option<String>,
+    /// the members which are kept in the original import
+    #[serde(default)]
+    pub skip: Vec<String>,
+}
+
+impl TransformImportsConfig {
+    /// The source of the member and the source of its style.
+    pub fn render(&self, member: &str) -> Result<(String, Option<String>)> {
+        let source = render_member_template(&self.transform, member)?;
+        let style = self
+            .style
+            .as_ref()
+            .map(|style| render_member_template(style, member))
+            .transpose()?;
+        Ok((source, style))
+    }
+}
+
+// `{{ member }}`, `{{ kebabCase member }}` or `{{ camelCase member }}`
+fn render_member_template(template: &str, member: &str) -> Result<String> {
+    let regex = create_cached_regex(r"\{\{\s*(?:(\w+)\s+)?member\s*\}\}");
+    let mut error = None;
+    let rendered = regex.replace_all(template, |captures: &Captures| {
+        match captures.get(1).map(|helper| helper.as_str()) {
+            None => member.to_string(),
+            Some("kebabCase") => member.to_case(Case::Kebab),
+            Some("camelCase") => member.to_case(Case::Camel),
+            Some(helper) => {
+                error = Some(anyhow!(
+                    "unknown helper {} in transformImports template {:?}, it should be kebabCase or camelCase",
+                    helper,
+                    template
+                ));
+                String::new()
+            }
+        }
+    });
+    match error {
+        Some(error) => Err(error),
+        None => Ok(rendered.to_string()),
+    }
+}
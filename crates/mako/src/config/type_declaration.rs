@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+/// The declaration file written into the root for the env keys and the asset
+/// imports.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeDeclarationConfig {
+    #[serde(default = "default_file_name")]
+    pub file_name: String,
+}
+
+fn default_file_name() -> String {
+    "mako-env.d.ts".to_string()
+}
+
+/// `true` for `mako-env.d.ts`, `false`, or the full config.
+pub fn deserialize_type_declaration<'de, D>(
+    deserializer: D,
+) -> Result<Option<TypeDeclarationConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: serde_json::Value = serde_json::Value::deserialize(deserializer)?;
+    match value {
+        serde_json::Value::Bool(false) | serde_json::Value::Null => Ok(None),
+        serde_json::Value::Bool(true) => Ok(Some(TypeDeclarationConfig {
+            file_name: default_file_name(),
+        })),
+        serde_json::Value::Object(_) => Ok(Some(
+            serde_json::from_value::<TypeDeclarationConfig>(value)
+                .map_err(serde::de::Error::custom)?,
+        )),
+        _ => Err(serde::de::Error::custom(format!(
+            "invalid `typeDeclaration` value: {}",
+            value
+        ))),
+    }
+}
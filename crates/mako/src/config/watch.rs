@@ -6,4 +6,8 @@ pub struct WatchConfig {
     pub ignore_paths: Option<Vec<String>>,
     #[serde(rename = "_nodeModulesRegexes")]
     pub node_modules_regexes: Option<Vec<String>>,
+    // ms to wait for more events of the same path before rebuilding
+    pub debounce: Option<u64>,
+    // ms without changes to wait for after a pause before rebuilding
+    pub settle: Option<u64>,
 }
@@ -0,0 +1,371 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use swc_core::common::{SourceMap, Span, GLOBALS};
+use swc_core::css::ast::{ImportHref, ImportPrelude, Url, UrlValue};
+use swc_core::css::visit::{Visit, VisitWith};
+use swc_core::ecma::transforms::base::resolver;
+use swc_core::ecma::visit::VisitMutWith;
+
+use crate::ast::css_ast::CssAst;
+use crate::ast::file::{Content, File};
+use crate::ast::js_ast::JsAst;
+use crate::ast::utils::{is_remote_or_data_or_hash, remove_first_tilde};
+use crate::build::load::Load;
+use crate::build::module_kind::detect_module_kind;
+use crate::compiler::{Compiler, Context};
+use crate::module::{relative_to_root, Dependency, ResolveType};
+use crate::resolve::{resolve, ResolverResource};
+
+/// The version of the schema of [FileDeps], which is bumped when a field is
+/// removed or its meaning is changed, adding fields keeps the version.
+pub const FILE_DEPS_VERSION: u32 = 1;
+
+/// The dependencies of a file printed by `mako deps`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDeps {
+    pub version: u32,
+    /// relative to the root
+    pub path: String,
+    /// in the order of the source
+    pub dependencies: Vec<FileDependency>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDependency {
+    /// the text of the source, e.g. `./a` of `import "./a"`
+    pub specifier: String,
+    pub kind: DependencyKind,
+    /// the absolute path of the resolved file with the query, null if it's
+    /// external, ignored or not resolved
+    pub resolved: Option<String>,
+    /// the expression of the external, e.g. `React` of `react`
+    pub external: Option<String>,
+    /// why it's not resolved
+    pub error: Option<String>,
+    pub span: DependencySpan,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DependencyKind {
+    /// import, export from, require and @import
+    Static,
+    /// import()
+    Dynamic,
+    /// new Worker(new URL(.., import.meta.url))
+    Worker,
+    /// url() of the css
+    CssUrl,
+}
+
+/// `start` and `end` are the byte offsets in the file, `line` is 1-based and
+/// `column` is 0-based, as the locations in the stats.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencySpan {
+    pub start: u32,
+    pub end: u32,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Compiler {
+    /// The dependencies of the file, relative to the root, for the editors.
+    /// The file is loaded, parsed and analyzed as in the build, but not
+    /// transformed, and the dependencies are resolved with the aliases and
+    /// the tsconfig paths but not built, so it doesn't need compile, and the
+    /// caches of the resolvers are reused by the calls.
+    pub fn deps(&self, path: &Path) -> Result<FileDeps> {
+        let context = &self.context;
+        let path = context.root.join(path);
+        if !path.is_file() {
+            return Err(anyhow!(
+                "File \"{}\" not found",
+                relative_to_root(&path.to_string_lossy().to_string(), &context.root)
+            ));
+        }
+
+        let mut file = File::new(path.to_string_lossy().to_string(), context.clone());
+        let mut content = Load::load(&file, context.clone())?;
+        let content = context.plugin_driver.load_transform(
+            &mut content,
+            &file.path.to_string_lossy(),
+            context,
+        )?;
+        file.set_content(content);
+        file.module_kind = detect_module_kind(&file, None, context);
+
+        let deps = match &file.content {
+            Some(Content::Js(_)) => analyze_js(&file, context)?,
+            Some(Content::Css(_)) => analyze_css(&file, context)?,
+            _ => vec![],
+        };
+        let resolve_from = file.resolve_from(context);
+        let dependencies = deps
+            .into_iter()
+            .map(|(dep, mut dependency)| {
+                match resolve(&resolve_from, &dep, &context.resolvers, context) {
+                    Ok(
+                        resource @ (ResolverResource::Resolved(_) | ResolverResource::Virtual(_)),
+                    ) => {
+                        dependency.resolved = Some(resource.get_resolved_path());
+                    }
+                    Ok(resource @ ResolverResource::External(_)) => {
+                        dependency.external = resource.get_external();
+                    }
+                    Ok(ResolverResource::Ignored(_)) => {}
+                    Err(err) => dependency.error = Some(err.to_string()),
+                }
+                dependency
+            })
+            .collect();
+
+        Ok(FileDeps {
+            version: FILE_DEPS_VERSION,
+            path: relative_to_root(&file.path.to_string_lossy().to_string(), &context.root),
+            dependencies,
+        })
+    }
+}
+
+// the marks of the resolver are needed to tell the requires from the locals
+fn analyze_js(file: &File, context: &Arc<Context>) -> Result<Vec<(Dependency, FileDependency)>> {
+    let mut ast = JsAst::new(file, context.clone())?;
+    let is_ts = file.extname == "ts" || file.extname == "tsx";
+    GLOBALS.set(&context.meta.script.globals, || {
+        ast.ast.visit_mut_with(&mut resolver(
+            ast.unresolved_mark,
+            ast.top_level_mark,
+            is_ts,
+        ));
+    });
+    let cm = &context.meta.script.cm;
+    Ok(ast
+        .analyze_deps(context.clone())
+        .into_iter()
+        .filter_map(|dep| {
+            let kind = match dep.resolve_type {
+                ResolveType::DynamicImport(_) => DependencyKind::Dynamic,
+                ResolveType::Worker(_) => DependencyKind::Worker,
+                _ => DependencyKind::Static,
+            };
+            let span = dependency_span(dep.span?, cm);
+            let dependency = FileDependency::new(dep.source.clone(), kind, span);
+            Some((dep, dependency))
+        })
+        .collect())
+}
+
+fn analyze_css(file: &File, context: &Arc<Context>) -> Result<Vec<(Dependency, FileDependency)>> {
+    let ast = CssAst::new(file, context.clone(), file.has_param("modules"))?;
+    let mut visitor = CSSDeps { deps: vec![] };
+    ast.ast.visit_with(&mut visitor);
+    let cm = &context.meta.css.cm;
+    Ok(visitor
+        .deps
+        .into_iter()
+        .map(|(specifier, kind, span)| {
+            let source = remove_first_tilde(specifier.clone());
+            // the query and the fragment of the assets are kept in the css,
+            // e.g. url(font.eot?#iefix)
+            let source = match kind {
+                DependencyKind::CssUrl => source.split(['?', '#']).next().unwrap().to_string(),
+                _ => source,
+            };
+            let dep = Dependency {
+                source,
+                resolve_as: None,
+                resolve_type: ResolveType::Css,
+                order: 0,
+                span: None,
+            };
+            (
+                dep,
+                FileDependency::new(specifier, kind, dependency_span(span, cm)),
+            )
+        })
+        .collect())
+}
+
+impl FileDependency {
+    fn new(specifier: String, kind: DependencyKind, span: DependencySpan) -> Self {
+        Self {
+            specifier,
+            kind,
+            resolved: None,
+            external: None,
+            error: None,
+            span,
+        }
+    }
+}
+
+fn dependency_span(span: Span, cm: &SourceMap) -> DependencySpan {
+    let start = cm.lookup_byte_offset(span.lo);
+    let loc = cm.lookup_char_pos(span.lo);
+    DependencySpan {
+        start: start.pos.0,
+        end: start.pos.0 + (span.hi.0 - span.lo.0),
+        line: loc.line,
+        column: loc.col_display,
+    }
+}
+
+// @import and url() of the css, in the order of the source, with the spans
+// which the dependencies of the build don't have
+struct CSSDeps {
+    deps: Vec<(String, DependencyKind, Span)>,
+}
+
+impl CSSDeps {
+    fn add(&mut self, specifier: String, kind: DependencyKind, span: Span) {
+        if specifier.is_empty() || is_remote_or_data_or_hash(&specifier) {
+            return;
+        }
+        self.deps.push((specifier, kind, span));
+    }
+}
+
+impl Visit for CSSDeps {
+    // the url() of @import is not an asset
+    fn visit_import_prelude(&mut self, n: &ImportPrelude) {
+        match &*n.href {
+            ImportHref::Url(url) => {
+                if let Some(value) = &url.value {
+                    self.add(url_value(value), DependencyKind::Static, url.span);
+                }
+            }
+            ImportHref::Str(str) => {
+                self.add(str.value.to_string(), DependencyKind::Static, str.span);
+            }
+        }
+    }
+
+    fn visit_url(&mut self, n: &Url) {
+        if let Some(value) = &n.value {
+            self.add(url_value(value), DependencyKind::CssUrl, n.span);
+        }
+    }
+}
+
+fn url_value(value: &UrlValue) -> String {
+    match value {
+        UrlValue::Str(str) => str.value.to_string(),
+        UrlValue::Raw(raw) => raw.value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_deps() {
+        let compiler = setup_compiler("test/build/deps", false);
+        let root = compiler.context.root.clone();
+        let deps = compiler.deps(Path::new("index.ts")).unwrap();
+        assert_eq!(deps.version, FILE_DEPS_VERSION);
+        assert_eq!(deps.path, "./index.ts");
+        let summary = deps
+            .dependencies
+            .iter()
+            .map(|dep| {
+                (
+                    dep.specifier.as_str(),
+                    dep.kind,
+                    dep.resolved
+                        .as_ref()
+                        .map(|path| relative_to_root(path, &root)),
+                    dep.error.is_some(),
+                    dep.span.line,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "@/shared",
+                    DependencyKind::Static,
+                    Some("./src/shared.ts".to_string()),
+                    false,
+                    1
+                ),
+                (
+                    "./style.css",
+                    DependencyKind::Static,
+                    Some("./style.css".to_string()),
+                    false,
+                    2
+                ),
+                ("./missing", DependencyKind::Static, None, true, 4),
+                (
+                    "./lazy",
+                    DependencyKind::Dynamic,
+                    Some("./lazy.ts".to_string()),
+                    false,
+                    5
+                ),
+                (
+                    "./worker",
+                    DependencyKind::Worker,
+                    Some("./worker.ts".to_string()),
+                    false,
+                    6
+                ),
+                (
+                    "./shared.cjs",
+                    DependencyKind::Static,
+                    Some("./shared.cjs".to_string()),
+                    false,
+                    7
+                ),
+            ]
+        );
+        // the span of the source of the import
+        let index = std::fs::read_to_string(root.join("index.ts")).unwrap();
+        let span = deps.dependencies[0].span;
+        assert_eq!(
+            &index[span.start as usize..span.end as usize],
+            "\"@/shared\""
+        );
+        assert_eq!((span.line, span.column), (1, 23));
+        // the types are not dependencies
+        assert!(!deps
+            .dependencies
+            .iter()
+            .any(|dep| dep.specifier == "./types"));
+
+        let deps = compiler.deps(Path::new("style.css")).unwrap();
+        let summary = deps
+            .dependencies
+            .iter()
+            .map(|dep| (dep.specifier.as_str(), dep.kind, dep.resolved.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("./reset.css", DependencyKind::Static, true),
+                ("./logo.png?v=1", DependencyKind::CssUrl, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deps_missing_file() {
+        let compiler = setup_compiler("test/build/deps", false);
+        assert!(compiler
+            .deps(Path::new("missing.ts"))
+            .unwrap_err()
+            .to_string()
+            .contains("File \"./missing.ts\" not found"));
+    }
+}
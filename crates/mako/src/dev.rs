@@ -1,53 +1,117 @@
+mod active_entries;
+mod cancellation;
+mod hmr_clients;
+mod routes;
 pub(crate) mod update;
 mod watch;
 
 use std::net::{SocketAddr, TcpListener};
 use std::path::PathBuf;
-use std::sync::{mpsc, Arc};
+use std::process::Command;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{mpsc, Arc, Mutex};
 use std::time::{Duration, Instant};
 
+pub use active_entries::ActiveEntries;
 use anyhow::{self, Result};
+pub use cancellation::{BuildCancellation, BuildCancelled, CarriedUpdate};
 use colored::Colorize;
 use futures::{SinkExt, StreamExt};
 use get_if_addrs::get_if_addrs;
+use hmr_clients::{HmrClient, HotUpdate};
 use hyper::header::{ACCESS_CONTROL_ALLOW_ORIGIN, CACHE_CONTROL, CONTENT_TYPE};
 use hyper::service::{make_service_fn, service_fn};
-use hyper::{Body, Request, Server};
-use notify_debouncer_full::new_debouncer;
+use hyper::{Body, Method, Request, Server};
+use notify_debouncer_full::{new_debouncer, DebouncedEvent};
+use routes::RebuildTrigger;
+pub use routes::{DevRequest, DevResponse, DevServerHandle, DevServerHandler, DevServerRoute};
 use tokio::sync::broadcast;
 use tracing::debug;
 use tungstenite::Message;
+pub use watch::WatchPause;
 use {hyper, hyper_staticfile, hyper_tungstenite, open};
 
 use crate::compiler::{Compiler, Context};
+use crate::module::relative_to_root;
 use crate::plugin::PluginGenerateEndParams;
 use crate::utils::{process_req_url, tokio_runtime};
 
 pub struct DevServer {
     root: PathBuf,
     compiler: Arc<Compiler>,
+    restart_on_config_change: bool,
+    memory_report: bool,
+    pause: Arc<WatchPause>,
+    rebuild: Arc<RebuildTrigger>,
 }
 
 impl DevServer {
     pub fn new(root: PathBuf, compiler: Arc<Compiler>) -> Self {
-        Self { root, compiler }
+        Self {
+            pause: Arc::new(WatchPause::new(&root)),
+            rebuild: Default::default(),
+            root,
+            compiler,
+            restart_on_config_change: false,
+            memory_report: false,
+        }
+    }
+
+    /// Pause and resume the rebuilds, the same as `POST /__/watch/pause` and
+    /// `POST /__/watch/resume` of the dev server.
+    pub fn watch_pause(&self) -> Arc<WatchPause> {
+        self.pause.clone()
+    }
+
+    /// Restart the process with the same arguments when the config file or
+    /// its imports change, for the standalone cli.
+    pub fn restart_on_config_change(mut self, restart: bool) -> Self {
+        self.restart_on_config_change = restart;
+        self
+    }
+
+    /// Print the usages of the module graph and the caches after each
+    /// rebuild, so the memory growth of a long watch session is visible.
+    pub fn memory_report(mut self, memory_report: bool) -> Self {
+        self.memory_report = memory_report;
+        self
     }
 
     pub async fn serve(&self) {
-        let (txws, _) = broadcast::channel::<WsMessage>(256);
+        let (txws, _) = broadcast::channel::<Arc<HotUpdate>>(256);
 
         // watch
         let root = self.root.clone();
         let compiler = self.compiler.clone();
         let txws_watch = txws.clone();
+        let restart = self.restart_on_config_change;
+        let memory_report = self.memory_report;
+        let pause = self.pause.clone();
+        let rebuild = self.rebuild.clone();
 
         if self.compiler.context.config.dev_server.is_some() {
             std::thread::spawn(move || {
-                if let Err(e) = Self::watch_for_changes(root, compiler, txws_watch) {
+                if let Err(e) = Self::watch_for_changes(
+                    root,
+                    compiler,
+                    txws_watch,
+                    pause,
+                    rebuild,
+                    restart,
+                    memory_report,
+                ) {
                     eprintln!("Error watching files: {:?}", e);
                 }
             });
-        } else if let Err(e) = Self::watch_for_changes(root, compiler, txws_watch) {
+        } else if let Err(e) = Self::watch_for_changes(
+            root,
+            compiler,
+            txws_watch,
+            pause,
+            rebuild,
+            restart,
+            memory_report,
+        ) {
             eprintln!("Error watching files: {:?}", e);
         }
 
@@ -65,20 +129,34 @@ impl DevServer {
             let addr: SocketAddr = ([127, 0, 0, 1], port).into();
             let context = self.compiler.context.clone();
             let txws = txws.clone();
+            let pause = self.pause.clone();
+            let routes = Arc::new(context.plugin_driver.dev_server_routes());
+            let handle = DevServerHandle::with_rebuild(self.compiler.clone(), self.rebuild.clone());
             let make_svc = make_service_fn(move |_conn| {
                 let context = context.clone();
                 let txws = txws.clone();
+                let pause = pause.clone();
+                let routes = routes.clone();
+                let handle = handle.clone();
                 async move {
                     Ok::<_, hyper::Error>(service_fn(move |req| {
                         let context = context.clone();
                         let txws = txws.clone();
+                        let pause = pause.clone();
+                        let routes = routes.clone();
+                        let handle = handle.clone();
                         let staticfile = {
                             let mut sf =
                                 hyper_staticfile::Static::new(context.config.output.path.clone());
                             sf.cache_headers(Some(0));
                             sf
                         };
-                        async move { Self::handle_requests(req, context, staticfile, txws).await }
+                        async move {
+                            Self::handle_requests(
+                                req, context, staticfile, txws, pause, routes, handle,
+                            )
+                            .await
+                        }
                     }))
                 }
             });
@@ -123,7 +201,10 @@ impl DevServer {
         req: Request<Body>,
         context: Arc<Context>,
         staticfile: hyper_staticfile::Static,
-        txws: broadcast::Sender<WsMessage>,
+        txws: broadcast::Sender<Arc<HotUpdate>>,
+        pause: Arc<WatchPause>,
+        routes: Arc<Vec<DevServerRoute>>,
+        handle: DevServerHandle,
     ) -> Result<hyper::Response<Body>> {
         debug!("> {} {}", req.method().to_string(), req.uri().path());
 
@@ -147,6 +228,13 @@ impl DevServer {
                 .body(hyper::Body::empty())
                 .unwrap()
         };
+        // the routes of the plugins
+        if let Some(route) = routes
+            .iter()
+            .find(|route| route.matches(req.method(), &path))
+        {
+            return Self::handle_route(route.clone(), req, path, handle).await;
+        }
         match path.as_str() {
             "/__/hmr-ws" => {
                 if hyper_tungstenite::is_upgrade_request(&req) {
@@ -162,7 +250,42 @@ impl DevServer {
                     Ok(not_found_response())
                 }
             }
+            // e.g. called by the git hooks around the checkouts
+            "/__/watch/pause" | "/__/watch/resume" if req.method() == Method::POST => {
+                if path.ends_with("pause") {
+                    pause.pause();
+                } else {
+                    pause.resume();
+                }
+                Ok(hyper::Response::builder()
+                    .status(hyper::StatusCode::NO_CONTENT)
+                    .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                    .body(hyper::Body::empty())
+                    .unwrap())
+            }
             _ => {
+                // the entries out of `--only-entry` are built on the first request
+                if let Some(name) = Self::requested_entry(path_without_slash_start, &context)
+                    && context
+                        .active_entries
+                        .request(&name, context.config.entry[&name].import.clone())
+                {
+                    println!("Building entry {} on request...", name.cyan());
+                    let waiting = context.clone();
+                    let emitted = tokio::task::spawn_blocking(move || {
+                        waiting
+                            .active_entries
+                            .wait_emitted(&name, Duration::from_secs(60))
+                    })
+                    .await?;
+                    if !emitted {
+                        return Ok(hyper::Response::builder()
+                            .status(hyper::StatusCode::SERVICE_UNAVAILABLE)
+                            .body(hyper::Body::from("Entry is still building"))
+                            .unwrap());
+                    }
+                }
+
                 // for bundle outputs
 
                 let ext = path.rsplit('.').next();
@@ -178,8 +301,9 @@ impl DevServer {
                 // it will response 302 and we will get the old file
                 // TODO: fix the 302 problem?
                 if !context.config.write_to_disk {
-                    if let Some(res) = context.get_static_content(path_without_slash_start) {
-                        debug!("serve with context.get_static_content: {}", path);
+                    let output_file = context.config.output.path.join(path_without_slash_start);
+                    if let Ok(res) = context.output_fs.read(&output_file) {
+                        debug!("serve with context.output_fs: {}", path);
 
                         return Ok(hyper::Response::builder()
                             .status(hyper::StatusCode::OK)
@@ -219,6 +343,45 @@ impl DevServer {
         }
     }
 
+    async fn handle_route(
+        route: DevServerRoute,
+        req: Request<Body>,
+        path: String,
+        handle: DevServerHandle,
+    ) -> Result<hyper::Response<Body>> {
+        let (parts, body) = req.into_parts();
+        let request = DevRequest {
+            method: parts.method,
+            path,
+            query: parts.uri.query().map(|query| query.to_string()),
+            headers: parts.headers,
+            body: hyper::body::to_bytes(body).await?.to_vec(),
+        };
+        let response =
+            tokio::task::spawn_blocking(move || (route.handler)(&request, &handle)).await?;
+        response
+            .unwrap_or_else(|e| {
+                eprintln!("Error handling {}: {:?}", route.path, e);
+                DevResponse::text(e.to_string()).status(500)
+            })
+            .into_hyper()
+    }
+
+    // the name of the entry whose html or js is requested
+    fn requested_entry(path: &str, context: &Context) -> Option<String> {
+        let name = if path.is_empty() {
+            "index"
+        } else {
+            path.strip_suffix(".html")
+                .or_else(|| path.strip_suffix(".js"))?
+        };
+        context
+            .config
+            .entry
+            .contains_key(name)
+            .then(|| name.to_string())
+    }
+
     fn get_ips() -> Vec<String> {
         let mut ips = vec![];
         match get_if_addrs() {
@@ -235,7 +398,7 @@ impl DevServer {
         ips
     }
 
-    fn find_available_port(host: String, port: u16) -> u16 {
+    pub(crate) fn find_available_port(host: String, port: u16) -> u16 {
         let mut port = port;
         if TcpListener::bind((host.clone(), port)).is_ok() {
             port
@@ -245,29 +408,31 @@ impl DevServer {
         }
     }
 
-    // TODO: refact socket message data structure
+    // the updates are filtered by the chunks loaded by each client, which
+    // are reported by the client on the same socket
     async fn handle_websocket(
         websocket: hyper_tungstenite::HyperWebsocket,
-        mut receiver: broadcast::Receiver<WsMessage>,
+        mut receiver: broadcast::Receiver<Arc<HotUpdate>>,
     ) -> Result<()> {
         let websocket = websocket.await?;
         let (mut sender, mut ws_recv) = websocket.split();
+        let client = Arc::new(Mutex::new(HmrClient::default()));
+        let task_client = client.clone();
         let task = tokio_runtime::spawn(async move {
             loop {
-                if let Ok(msg) = receiver.recv().await {
-                    if sender
-                        .send(Message::text(format!(r#"{{"hash":"{}"}}"#, msg.hash)))
-                        .await
-                        .is_err()
-                    {
+                if let Ok(update) = receiver.recv().await {
+                    let message = task_client.lock().unwrap().message(&update);
+                    if sender.send(Message::text(message)).await.is_err() {
                         break;
                     }
                 }
             }
         });
         while let Some(message) = ws_recv.next().await {
-            if let Ok(Message::Close(_)) = message {
-                break;
+            match message {
+                Ok(Message::Close(_)) => break,
+                Ok(Message::Text(text)) => client.lock().unwrap().receive(&text),
+                _ => {}
             }
         }
         debug!("websocket connection disconnected");
@@ -278,48 +443,200 @@ impl DevServer {
     fn watch_for_changes(
         root: PathBuf,
         compiler: Arc<Compiler>,
-        txws: broadcast::Sender<WsMessage>,
+        txws: broadcast::Sender<Arc<HotUpdate>>,
+        pause: Arc<WatchPause>,
+        rebuild: Arc<RebuildTrigger>,
+        restart_on_config_change: bool,
+        memory_report: bool,
     ) -> Result<()> {
         let (tx, rx) = mpsc::channel();
+        // the changes cancel the rebuild in flight before they're received by
+        // the loop, which starts the next rebuild with them at once
+        let (change_tx, change_rx) = mpsc::channel::<notify_debouncer_full::DebounceEventResult>();
+        let forward_tx = tx.clone();
+        let forward_compiler = compiler.clone();
+        std::thread::spawn(move || {
+            for result in change_rx {
+                if let Ok(events) = &result
+                    && watch::Watcher::supersedes(events, &forward_compiler)
+                {
+                    forward_compiler.context.build_cancellation.cancel();
+                }
+                if forward_tx.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+        // invalidated virtual modules are handled like modified files
+        let virtual_tx = change_tx.clone();
+        compiler.context.virtual_modules.set_invalidator(move |id| {
+            let event = notify::Event::new(notify::EventKind::Modify(
+                notify::event::ModifyKind::Data(notify::event::DataChange::Content),
+            ))
+            .add_path(PathBuf::from(id));
+            let _ = virtual_tx.send(Ok(vec![DebouncedEvent::new(event, Instant::now())]));
+        });
+        // the rebuilds requested by the routes of the plugins
+        let rebuild_tx = change_tx.clone();
+        rebuild.set(move |paths| {
+            let event = paths.into_iter().fold(
+                notify::Event::new(notify::EventKind::Modify(notify::event::ModifyKind::Data(
+                    notify::event::DataChange::Content,
+                ))),
+                |event, path| event.add_path(path),
+            );
+            let _ = rebuild_tx.send(Ok(vec![DebouncedEvent::new(event, Instant::now())]));
+        });
+        // the entries requested with `--only-entry` are built by the next update
+        let entry_tx = tx.clone();
+        compiler.context.active_entries.set_waker(move |path| {
+            let event = notify::Event::new(notify::EventKind::Any).add_path(path);
+            let _ = entry_tx.send(Ok(vec![DebouncedEvent::new(event, Instant::now())]));
+        });
         // let mut watcher = RecommendedWatcher::new(tx, notify::Config::default())?;
-        let mut debouncer = new_debouncer(Duration::from_millis(10), None, tx).unwrap();
+        let debounce = compiler.context.config.watch.debounce.unwrap_or(30);
+        let mut debouncer =
+            new_debouncer(Duration::from_millis(debounce), None, change_tx).unwrap();
         let mut watcher = watch::Watcher::new(&root, debouncer.watcher(), &compiler);
         watcher.watch()?;
 
+        let settle = Duration::from_millis(compiler.context.config.watch.settle.unwrap_or(200));
+
         let initial_hash = compiler.full_hash();
         let mut snapshot_hash = Box::new(initial_hash);
         let mut hmr_hash = Box::new(initial_hash);
-
-        for result in rx {
-            if result.is_err() {
-                eprintln!("Error watching files: {:?}", result.err().unwrap());
+        // the hmr hashes of the cancelled emit, which is done by the next
+        // rebuild even if it has no changes
+        let mut deferred_emit: Option<(u64, u64)> = None;
+
+        // the changes buffered while paused or until the files settle
+        let mut pending: Vec<PathBuf> = vec![];
+        let mut settling = false;
+        loop {
+            let received = if pending.is_empty() {
+                rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                rx.recv_timeout(settle)
+            };
+            let mut events = match received {
+                Ok(Ok(events)) => events,
+                Ok(Err(e)) => {
+                    eprintln!("Error watching files: {:?}", e);
+                    vec![]
+                }
+                Err(RecvTimeoutError::Timeout) => vec![],
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            // coalesce the events received during the last rebuild into one
+            // rebuild, e.g. when switching branches
+            for result in rx.try_iter() {
+                match result {
+                    Ok(more_events) => events.extend(more_events),
+                    Err(e) => eprintln!("Error watching files: {:?}", e),
+                }
+            }
+            // no changes in the settle time
+            let is_quiet = events.is_empty();
+            let paths = watch::Watcher::normalize_events(events);
+            let config_file = paths
+                .iter()
+                .find(|path| compiler.context.config.config_files.contains(path));
+            if restart_on_config_change && let Some(config_file) = config_file {
+                println!(
+                    "{} is changed, restarting...",
+                    relative_to_root(&config_file.to_string_lossy().to_string(), &root)
+                );
+                return Self::restart();
+            }
+            // the files in the watched dirs which are excluded by ignore
+            pending.extend(paths.into_iter().filter(|path| !watcher.is_ignored(path)));
+            if pending.is_empty() {
                 continue;
             }
-            let paths = watch::Watcher::normalize_events(result.unwrap());
-            if !paths.is_empty() {
-                let compiler = compiler.clone();
-                let txws = txws.clone();
-                if let Err(e) =
-                    Self::rebuild(paths, compiler, txws, &mut snapshot_hash, &mut hmr_hash)
-                {
-                    eprintln!("Error rebuilding: {:?}", e);
+            if pause.is_paused() {
+                if !settling {
+                    println!("Paused, the changes are rebuilt after resuming");
                 }
+                settling = true;
+                continue;
+            }
+            if settling && !is_quiet {
+                continue;
+            }
+            settling = false;
+            let mut paths = std::mem::take(&mut pending);
+            paths.sort();
+            paths.dedup();
+            // the changes received from now on cancel the rebuild, the ones
+            // before are built by it
+            compiler.context.build_cancellation.reset();
+            match Self::rebuild(
+                paths.clone(),
+                compiler.clone(),
+                txws.clone(),
+                &mut snapshot_hash,
+                &mut hmr_hash,
+                &mut deferred_emit,
+            ) {
+                Ok(Rebuild::Done) => {}
+                Ok(Rebuild::Cancelled) => {
+                    println!("Cancelled, rebuilding with the newer changes");
+                    continue;
+                }
+                Ok(Rebuild::Unsettled) => {
+                    debug!("files are changed during the rebuild, retry after they settle");
+                    pending = paths;
+                    settling = true;
+                    continue;
+                }
+                Err(e) => eprintln!("Error rebuilding: {:?}", e),
+            }
+            compiler.context.active_entries.set_emitted();
+            if memory_report {
+                println!("{}", compiler.memory_report());
+            }
+            if let Err(e) = watcher.watch_missing_deps() {
+                eprintln!("Error watching missing deps: {:?}", e);
+            }
+            if let Err(e) = watcher.watch_build_dependencies() {
+                eprintln!("Error watching build dependencies: {:?}", e);
+            }
+            if let Err(e) = watcher.watch_lock_files() {
+                eprintln!("Error watching lock files: {:?}", e);
             }
         }
         Ok(())
     }
 
+    // replace the process so that the port of the dev server is released
+    fn restart() -> Result<()> {
+        let mut command = Command::new(std::env::current_exe()?);
+        command.args(std::env::args_os().skip(1));
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            Err(command.exec().into())
+        }
+        #[cfg(not(unix))]
+        {
+            let status = command.status()?;
+            std::process::exit(status.code().unwrap_or(1));
+        }
+    }
+
     fn rebuild(
         paths: Vec<PathBuf>,
         compiler: Arc<Compiler>,
-        txws: broadcast::Sender<WsMessage>,
+        txws: broadcast::Sender<Arc<HotUpdate>>,
         last_snapshot_hash: &mut Box<u64>,
         hmr_hash: &mut Box<u64>,
-    ) -> Result<()> {
+        deferred_emit: &mut Option<(u64, u64)>,
+    ) -> Result<Rebuild> {
         debug!("watch paths detected: {:?}", paths);
         debug!("checking update status...");
         println!("Checking...");
-        let update_result = compiler.update(paths);
+        let existed = paths.iter().map(|path| path.exists()).collect::<Vec<_>>();
+        let update_result = compiler.update(paths.clone());
         let has_missing_deps = {
             compiler
                 .context
@@ -334,17 +651,32 @@ impl DevServer {
 
         if let Err(e) = update_result {
             debug!("checking update status... failed");
+            if BuildCancellation::is_cancellation(&e) {
+                return Ok(Rebuild::Cancelled);
+            }
+            // e.g. the files are removed by git while being read
+            if watch::Watcher::is_unsettled(&paths, &existed) {
+                return Ok(Rebuild::Unsettled);
+            }
             eprintln!("{}", e);
             // do not return error, since it's already printed
-            return Ok(());
+            return Ok(Rebuild::Done);
         }
 
         let res = update_result.unwrap();
+        // the last check before the files are emitted
+        if compiler.context.build_cancellation.is_cancelled() {
+            let _ = compiler.context.build_cancellation.carry(CarriedUpdate {
+                result: res,
+                ..Default::default()
+            });
+            return Ok(Rebuild::Cancelled);
+        }
         let is_updated = res.is_updated();
         debug!("update status is ok, is_updated: {}", is_updated);
-        if !is_updated {
+        if !is_updated && deferred_emit.is_none() {
             println!("No changes");
-            return Ok(());
+            return Ok(Rebuild::Done);
         }
 
         let t_compiler = Instant::now();
@@ -365,27 +697,49 @@ impl DevServer {
             last_snapshot_hash,
             next_snapshot_hash == **last_snapshot_hash
         );
-        if next_snapshot_hash == **last_snapshot_hash {
-            debug!("hash equals, will not do full rebuild");
-            return Ok(());
+        let (emitted_hmr_hash, update) = if next_snapshot_hash == **last_snapshot_hash {
+            // no hot update, only the emit of the cancelled rebuild
+            match deferred_emit.take() {
+                Some(hashes) => (hashes, None),
+                None => {
+                    debug!("hash equals, will not do full rebuild");
+                    return Ok(Rebuild::Done);
+                }
+            }
         } else {
             **last_snapshot_hash = next_snapshot_hash;
             **hmr_hash = next_hmr_hash;
-        }
+            *deferred_emit = None;
+            (
+                (next_hmr_hash, current_hmr_hash),
+                Some(HotUpdate::new(&compiler, current_hmr_hash, next_hmr_hash)),
+            )
+        };
 
         debug!("full rebuild...");
 
         compiler.context.stats_info.clear_assets();
 
-        let mut stats = compiler
-            .emit_dev_chunks(next_hmr_hash, current_hmr_hash)
-            .map_err(|e| {
+        let mut stats = match compiler.emit_dev_chunks(emitted_hmr_hash.0, emitted_hmr_hash.1) {
+            // the hot update chunks are written, so the clients are updated,
+            // and the chunks are emitted by the next rebuild
+            Err(e) if BuildCancellation::is_cancellation(&e) => {
+                *deferred_emit = Some(emitted_hmr_hash);
+                if let Some(update) = update {
+                    Self::send_update(&txws, update);
+                }
+                return Ok(Rebuild::Cancelled);
+            }
+            result => result.map_err(|e| {
                 debug!("  > build failed: {:?}", e);
                 e
-            })?;
+            })?,
+        };
 
-        stats.start_time = start_time;
-        stats.end_time = chrono::Local::now().timestamp_millis();
+        if compiler.context.config.stats_timestamps() {
+            stats.start_time = start_time;
+            stats.end_time = chrono::Local::now().timestamp_millis();
+        }
 
         debug!("full rebuild...done");
         if !has_missing_deps {
@@ -416,18 +770,29 @@ impl DevServer {
                 })?;
         }
 
+        if let Some(update) = update {
+            Self::send_update(&txws, update);
+        }
+
+        Ok(Rebuild::Done)
+    }
+
+    fn send_update(txws: &broadcast::Sender<Arc<HotUpdate>>, update: HotUpdate) {
         let receiver_count = txws.receiver_count();
         debug!("receiver count: {}", receiver_count);
         if receiver_count > 0 {
-            txws.send(WsMessage { hash: **hmr_hash }).unwrap();
+            txws.send(Arc::new(update)).unwrap();
             debug!("send message to clients");
         }
-
-        Ok(())
     }
 }
 
-#[derive(Clone, Debug)]
-struct WsMessage {
-    hash: u64,
+enum Rebuild {
+    Done,
+    // superseded by the newer changes, which are rebuilt at once with the
+    // work carried from it
+    Cancelled,
+    // the files are changed during the rebuild, which is retried after they
+    // settle
+    Unsettled,
 }
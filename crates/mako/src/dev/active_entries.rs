@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+type Waker = Box<dyn Fn(PathBuf) + Send + Sync>;
+
+/// The entries which are built in watch mode. With `--only-entry`, the other
+/// entries are not in the module graph and their chunks are not emitted,
+/// until they are requested by the dev server, e.g. by opening their html,
+/// and built by the next update, which extends the module graph instead of
+/// building from scratch.
+#[derive(Default)]
+pub struct ActiveEntries {
+    state: Mutex<State>,
+    emitted: Condvar,
+    // wakes the watcher to build the requested entries, with the path of
+    // the entry
+    waker: Mutex<Option<Waker>>,
+}
+
+#[derive(Default)]
+struct State {
+    // none if all the entries are active
+    active: Option<BTreeSet<String>>,
+    requested: BTreeSet<String>,
+    // the active entries whose chunks are emitted
+    emitted: BTreeSet<String>,
+}
+
+impl ActiveEntries {
+    /// Build only the entries of the names.
+    pub fn only(&self, names: impl IntoIterator<Item = String>) {
+        let mut state = self.state.lock().unwrap();
+        let names = names.into_iter().collect::<BTreeSet<_>>();
+        state.emitted = names.clone();
+        state.active = Some(names);
+    }
+
+    pub fn is_active(&self, name: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        state
+            .active
+            .as_ref()
+            .map_or(true, |active| active.contains(name))
+    }
+
+    /// The names of the active entries, none if all the entries are active.
+    pub fn names(&self) -> Option<Vec<String>> {
+        let state = self.state.lock().unwrap();
+        state
+            .active
+            .as_ref()
+            .map(|active| active.iter().cloned().collect())
+    }
+
+    /// Request to build the entry by the next update and wake the watcher,
+    /// false if it's active already.
+    pub fn request(&self, name: &str, path: PathBuf) -> bool {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state
+                .active
+                .as_ref()
+                .map_or(true, |active| active.contains(name))
+            {
+                return false;
+            }
+            state.requested.insert(name.to_string());
+        }
+        if let Some(waker) = self.waker.lock().unwrap().as_ref() {
+            waker(path);
+        }
+        true
+    }
+
+    pub(crate) fn set_waker(&self, waker: impl Fn(PathBuf) + Send + Sync + 'static) {
+        *self.waker.lock().unwrap() = Some(Box::new(waker));
+    }
+
+    pub(crate) fn take_requested(&self) -> Vec<String> {
+        let mut state = self.state.lock().unwrap();
+        std::mem::take(&mut state.requested).into_iter().collect()
+    }
+
+    pub(crate) fn activate(&self, names: &[String]) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(active) = &mut state.active {
+            active.extend(names.iter().cloned());
+        }
+    }
+
+    /// Called after the chunks are emitted, or failed to, the entries
+    /// requested during the rebuild are still waiting for the next one.
+    pub(crate) fn set_emitted(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(active) = state.active.clone() {
+            state.emitted.extend(active);
+        }
+        self.emitted.notify_all();
+    }
+
+    /// Wait until the chunks of the requested entry are emitted, false if
+    /// it's timed out.
+    pub fn wait_emitted(&self, name: &str, timeout: Duration) -> bool {
+        let state = self.state.lock().unwrap();
+        let (_state, result) = self
+            .emitted
+            .wait_timeout_while(state, timeout, |state| !state.emitted.contains(name))
+            .unwrap();
+        !result.timed_out()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::ActiveEntries;
+
+    #[test]
+    fn test_active_entries() {
+        let entries = Arc::new(ActiveEntries::default());
+        assert!(entries.is_active("a"));
+        assert!(!entries.request("a", PathBuf::from("/a.js")));
+
+        let woken = Arc::new(Mutex::new(vec![]));
+        let woken_clone = woken.clone();
+        entries.set_waker(move |path| woken_clone.lock().unwrap().push(path));
+        entries.only(["a".to_string()]);
+        assert!(!entries.is_active("b"));
+        assert!(entries.request("b", PathBuf::from("/b.js")));
+        assert_eq!(*woken.lock().unwrap(), vec![PathBuf::from("/b.js")]);
+        assert!(!entries.wait_emitted("b", Duration::from_millis(10)));
+
+        let waiter = {
+            let entries = entries.clone();
+            std::thread::spawn(move || entries.wait_emitted("b", Duration::from_secs(10)))
+        };
+        let requested = entries.take_requested();
+        assert_eq!(requested, vec!["b".to_string()]);
+        entries.activate(&requested);
+        assert!(entries.is_active("b"));
+        entries.set_emitted();
+        assert!(waiter.join().unwrap());
+        assert_eq!(
+            entries.names(),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use thiserror::Error;
+
+use crate::dev::update::UpdateResult;
+
+#[derive(Debug, Error)]
+#[error("the rebuild is cancelled by the newer changes")]
+pub struct BuildCancelled;
+
+/// Cancels the rebuild in flight in watch mode when the newer changes
+/// supersede it, instead of finishing the work which is thrown away. The
+/// update checks it between the module tasks and between the phases, and
+/// carries what it has done, i.e. the removed modules and the paths still to
+/// build, to the next update, which starts with the newer changes at once.
+/// The module graph is only changed by the phases which are completed, so
+/// the next update continues from a consistent state. The emit of the chunks
+/// checks it before the chunks are generated and rendered, and the cancelled
+/// emit is done by the next rebuild, even if it has no changes.
+#[derive(Default)]
+pub struct BuildCancellation {
+    cancelled: AtomicBool,
+    carried: Mutex<Option<CarriedUpdate>>,
+}
+
+/// The work of a cancelled update, which is merged into the next one.
+#[derive(Default)]
+pub struct CarriedUpdate {
+    pub result: UpdateResult,
+    // rebuilt without checking the content, e.g. the dependents of the
+    // removed modules
+    pub modified: Vec<PathBuf>,
+    pub added: Vec<PathBuf>,
+}
+
+impl BuildCancellation {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Called before a rebuild starts, the changes which are received before
+    /// it are built by it.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            return Err(BuildCancelled.into());
+        }
+        Ok(())
+    }
+
+    /// Keep the work of the cancelled update for the next one, returns the
+    /// error of the cancellation.
+    pub fn carry(&self, carried: CarriedUpdate) -> anyhow::Error {
+        *self.carried.lock().unwrap() = Some(carried);
+        BuildCancelled.into()
+    }
+
+    pub fn take_carried(&self) -> CarriedUpdate {
+        self.carried.lock().unwrap().take().unwrap_or_default()
+    }
+
+    pub fn is_cancellation(err: &anyhow::Error) -> bool {
+        err.downcast_ref::<BuildCancelled>().is_some()
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+use serde_json::json;
+use tracing::debug;
+
+use crate::compiler::Compiler;
+use crate::generate::chunk::ChunkType;
+use crate::generate::HotUpdateManifest;
+use crate::module::generate_module_id;
+
+/// A hot update broadcast to the clients of the dev server, the manifest of
+/// it is filtered for each client by the chunks the client has loaded.
+#[derive(Debug, Default)]
+pub(crate) struct HotUpdate {
+    pub hash: u64,
+    // None if hmr is disabled, the clients fetch nothing then
+    pub manifest: Option<HotUpdateManifest>,
+    // file name of the chunk -> the ids of it in the runtimes, the first
+    // one is the id of the chunk maps and the others are the ids of the
+    // entry chunks in `cssInstalledChunks`
+    pub chunk_ids: HashMap<String, Vec<String>>,
+}
+
+impl HotUpdate {
+    /// The update from `last_hmr_hash` to `hash`, it should be called after
+    /// the hot update chunks are generated.
+    pub fn new(compiler: &Compiler, last_hmr_hash: u64, hash: u64) -> Self {
+        let context = &compiler.context;
+        let manifest = context
+            .output_fs
+            .read(
+                &context
+                    .config
+                    .output
+                    .path
+                    .join(format!("{}.hot-update.json", last_hmr_hash)),
+            )
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok());
+        let chunk_graph = context.chunk_graph.read().unwrap();
+        let chunk_ids = chunk_graph
+            .get_all_chunks()
+            .into_iter()
+            .map(|chunk| {
+                let mut ids = vec![chunk.id.id.to_string()];
+                if let ChunkType::Entry(module_id, _, false) | ChunkType::Worker(module_id) =
+                    &chunk.chunk_type
+                {
+                    let id = generate_module_id(&module_id.id, context);
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+                }
+                (chunk.filename(), ids)
+            })
+            .collect();
+        Self {
+            hash,
+            manifest,
+            chunk_ids,
+        }
+    }
+}
+
+/// The messages of the hmr clients.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ClientMessage {
+    /// the chunks loaded by the client, all of them on connect and the new
+    /// ones as they're loaded
+    Chunks { chunks: Vec<String> },
+}
+
+/// The state of a client connected to the hmr websocket.
+#[derive(Debug, Default)]
+pub(crate) struct HmrClient {
+    // None until the client reports, e.g. the runtimes of the older
+    // versions, which get the whole updates
+    loaded_chunks: Option<HashSet<String>>,
+}
+
+impl HmrClient {
+    pub fn receive(&mut self, text: &str) {
+        match serde_json::from_str::<ClientMessage>(text) {
+            Ok(ClientMessage::Chunks { chunks }) => {
+                self.loaded_chunks
+                    .get_or_insert_with(HashSet::new)
+                    .extend(chunks);
+            }
+            Err(e) => debug!("invalid hmr client message {}: {:?}", text, e),
+        }
+    }
+
+    /// The message of the update for the client. The modified chunks the
+    /// client hasn't loaded are moved to the outdated chunks, so that their
+    /// hot update chunks aren't applied to the modules it never executed,
+    /// and they're loaded fresh when they're required.
+    pub fn message(&self, update: &HotUpdate) -> String {
+        let hash = update.hash.to_string();
+        let Some(manifest) = &update.manifest else {
+            return json!({ "hash": hash }).to_string();
+        };
+        let mut manifest = manifest.clone();
+        if let Some(loaded_chunks) = &self.loaded_chunks {
+            let (modified, outdated): (Vec<_>, Vec<_>) =
+                manifest.modified_chunks.into_iter().partition(|name| {
+                    update
+                        .chunk_ids
+                        .get(name)
+                        .map_or(true, |ids| ids.iter().any(|id| loaded_chunks.contains(id)))
+                });
+            manifest.modified_chunks = modified;
+            manifest.outdated_chunks = outdated
+                .iter()
+                .map(|name| update.chunk_ids[name][0].clone())
+                .collect();
+        }
+        json!({ "hash": hash, "update": manifest }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::compiler::Args;
+    use crate::config::Config;
+    use crate::testing::TempDir;
+
+    fn update() -> HotUpdate {
+        HotUpdate {
+            hash: 2,
+            manifest: Some(HotUpdateManifest {
+                modified_chunks: vec!["index.js".to_string(), "lazy_js-async.js".to_string()],
+                removed_chunks: vec![],
+                removed_modules: vec!["b.js".to_string()],
+                outdated_chunks: vec![],
+            }),
+            chunk_ids: HashMap::from([
+                (
+                    "index.js".to_string(),
+                    vec!["/root/index.js".to_string(), "index.js".to_string()],
+                ),
+                ("lazy_js-async.js".to_string(), vec!["lazy.js".to_string()]),
+            ]),
+        }
+    }
+
+    fn manifest(message: &str) -> serde_json::Value {
+        serde_json::from_str::<serde_json::Value>(message).unwrap()["update"].clone()
+    }
+
+    #[test]
+    fn test_message_per_client() {
+        let update = update();
+        let mut a = HmrClient::default();
+        a.receive(r#"{"type":"chunks","chunks":["index.js"]}"#);
+        let mut b = HmrClient::default();
+        b.receive(r#"{"type":"chunks","chunks":["index.js"]}"#);
+        b.receive(r#"{"type":"chunks","chunks":["lazy.js"]}"#);
+
+        // a hasn't loaded the lazy chunk, it's loaded fresh on demand
+        let message: serde_json::Value = serde_json::from_str(&a.message(&update)).unwrap();
+        assert_eq!(message["hash"], "2");
+        assert_eq!(message["update"]["c"], json!(["index.js"]));
+        assert_eq!(message["update"]["o"], json!(["lazy.js"]));
+        assert_eq!(message["update"]["m"], json!(["b.js"]));
+        // b has loaded both
+        let message = manifest(&b.message(&update));
+        assert_eq!(message["c"], json!(["index.js", "lazy_js-async.js"]));
+        assert!(message.get("o").is_none());
+    }
+
+    #[test]
+    fn test_message_unreported_client() {
+        let update = update();
+        let mut client = HmrClient::default();
+        client.receive("ping");
+        let message = manifest(&client.message(&update));
+        assert_eq!(message["c"], json!(["index.js", "lazy_js-async.js"]));
+        assert_eq!(
+            client.message(&HotUpdate {
+                hash: 3,
+                ..Default::default()
+            }),
+            r#"{"hash":"3"}"#
+        );
+    }
+
+    #[test]
+    fn test_hot_update() {
+        let root = TempDir::new("hmr-clients");
+        fs::write(root.join("index.js"), "import './a'; import('./lazy');").unwrap();
+        fs::write(root.join("a.js"), "console.log('a');").unwrap();
+        fs::write(root.join("lazy.js"), "import './a'; console.log('lazy');").unwrap();
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        compiler.compile().unwrap();
+        compiler.emit_dev_chunks(1, 0).unwrap();
+
+        fs::write(root.join("a.js"), "console.log('a2');").unwrap();
+        fs::write(root.join("lazy.js"), "import './a'; console.log('lazy2');").unwrap();
+        let result = compiler
+            .update(vec![root.join("a.js"), root.join("lazy.js")])
+            .unwrap();
+        let (_, next_hmr_hash, _) = compiler.generate_hot_update_chunks(result, 0, 1).unwrap();
+        let update = HotUpdate::new(&compiler, 1, next_hmr_hash);
+        let mut modified_chunks = update.manifest.as_ref().unwrap().modified_chunks.clone();
+        modified_chunks.sort();
+        assert_eq!(modified_chunks, vec!["index.js", "lazy_js-async.js"]);
+        let lazy_id = update.chunk_ids["lazy_js-async.js"][0].clone();
+
+        // two clients of the same page, only one has loaded the lazy chunk
+        let index_only = update.chunk_ids["index.js"].last().unwrap().clone();
+        let mut a = HmrClient::default();
+        a.receive(&json!({ "type": "chunks", "chunks": [index_only] }).to_string());
+        let mut b = HmrClient::default();
+        b.receive(&json!({ "type": "chunks", "chunks": [index_only, lazy_id] }).to_string());
+        let message = manifest(&a.message(&update));
+        assert_eq!(message["c"], json!(["index.js"]));
+        assert_eq!(message["o"], json!([lazy_id]));
+        let message = manifest(&b.message(&update));
+        assert_eq!(message["c"].as_array().unwrap().len(), 2);
+        assert!(message.get("o").is_none());
+    }
+}
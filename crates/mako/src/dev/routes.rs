@@ -0,0 +1,220 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use glob_match::glob_match;
+use hyper::header::{ACCESS_CONTROL_ALLOW_ORIGIN, CACHE_CONTROL, CONTENT_TYPE};
+use hyper::{Body, HeaderMap, Method};
+use serde::Serialize;
+use url::form_urlencoded;
+
+use crate::compiler::{Compiler, Context};
+
+pub type DevServerHandler =
+    Arc<dyn Fn(&DevRequest, &DevServerHandle) -> Result<DevResponse> + Send + Sync>;
+
+/// A custom endpoint of the dev server, returned by
+/// `Plugin::dev_server_routes`. The routes are matched before the hmr and
+/// the static handlers, in the order of the plugins, and the handler runs in
+/// a blocking thread, so it can read the graphs of the compiler.
+#[derive(Clone)]
+pub struct DevServerRoute {
+    /// matches any method when it's None
+    pub method: Option<Method>,
+    /// the path after `publicPath`, e.g. `/__inspect`, or a glob, e.g.
+    /// `/__inspect/**`
+    pub path: String,
+    pub handler: DevServerHandler,
+}
+
+impl DevServerRoute {
+    pub fn new(
+        method: Option<Method>,
+        path: &str,
+        handler: impl Fn(&DevRequest, &DevServerHandle) -> Result<DevResponse> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            method,
+            path: path.to_string(),
+            handler: Arc::new(handler),
+        }
+    }
+
+    pub fn get(
+        path: &str,
+        handler: impl Fn(&DevRequest, &DevServerHandle) -> Result<DevResponse> + Send + Sync + 'static,
+    ) -> Self {
+        Self::new(Some(Method::GET), path, handler)
+    }
+
+    pub fn post(
+        path: &str,
+        handler: impl Fn(&DevRequest, &DevServerHandle) -> Result<DevResponse> + Send + Sync + 'static,
+    ) -> Self {
+        Self::new(Some(Method::POST), path, handler)
+    }
+
+    pub(crate) fn matches(&self, method: &Method, path: &str) -> bool {
+        self.method.as_ref().map_or(true, |m| m == method)
+            && (self.path == path || glob_match(&self.path, path))
+    }
+}
+
+pub struct DevRequest {
+    pub method: Method,
+    /// the path after `publicPath`
+    pub path: String,
+    pub query: Option<String>,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl DevRequest {
+    /// The decoded value of the first parameter of the query with the key.
+    pub fn query_param(&self, key: &str) -> Option<String> {
+        let query = self.query.as_ref()?;
+        form_urlencoded::parse(query.as_bytes())
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.to_string())
+    }
+}
+
+pub struct DevResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl DevResponse {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: vec![(CONTENT_TYPE.to_string(), content_type.to_string())],
+            body: body.into(),
+        }
+    }
+
+    pub fn text(body: impl Into<String>) -> Self {
+        Self::new(200, "text/plain; charset=utf-8", body.into())
+    }
+
+    pub fn html(body: impl Into<String>) -> Self {
+        Self::new(200, "text/html; charset=utf-8", body.into())
+    }
+
+    pub fn json<T: Serialize>(value: &T) -> Result<Self> {
+        Ok(Self::new(
+            200,
+            "application/json; charset=utf-8",
+            serde_json::to_vec(value)?,
+        ))
+    }
+
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub(crate) fn into_hyper(self) -> Result<hyper::Response<Body>> {
+        let mut builder = hyper::Response::builder()
+            .status(self.status)
+            .header(CACHE_CONTROL, "no-cache")
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        Ok(builder.body(Body::from(self.body))?)
+    }
+}
+
+type Trigger = Box<dyn Fn(Vec<PathBuf>) + Send + Sync>;
+
+/// Starts a rebuild from the handlers, set by the watcher of the dev server.
+#[derive(Default)]
+pub(crate) struct RebuildTrigger {
+    trigger: Mutex<Option<Trigger>>,
+}
+
+impl RebuildTrigger {
+    pub(crate) fn set(&self, trigger: impl Fn(Vec<PathBuf>) + Send + Sync + 'static) {
+        *self.trigger.lock().unwrap() = Some(Box::new(trigger));
+    }
+}
+
+/// What the handlers of the routes get to query the compiler or to rebuild.
+#[derive(Clone)]
+pub struct DevServerHandle {
+    compiler: Arc<Compiler>,
+    rebuild: Arc<RebuildTrigger>,
+}
+
+impl DevServerHandle {
+    /// A handle without the watcher, whose `rebuild` does nothing, e.g. for
+    /// the tests of the handlers.
+    pub fn new(compiler: Arc<Compiler>) -> Self {
+        Self::with_rebuild(compiler, Default::default())
+    }
+
+    pub(crate) fn with_rebuild(compiler: Arc<Compiler>, rebuild: Arc<RebuildTrigger>) -> Self {
+        Self { compiler, rebuild }
+    }
+
+    pub fn compiler(&self) -> &Arc<Compiler> {
+        &self.compiler
+    }
+
+    pub fn context(&self) -> &Arc<Context> {
+        &self.compiler.context
+    }
+
+    /// Rebuild the paths as if they were modified, the same as the changes
+    /// of the watcher, returns false when there's no watcher.
+    pub fn rebuild(&self, paths: Vec<PathBuf>) -> bool {
+        match self.rebuild.trigger.lock().unwrap().as_ref() {
+            Some(trigger) => {
+                trigger(paths);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_matches() {
+        let route = DevServerRoute::get("/__inspect", |_, _| Ok(DevResponse::text("")));
+        assert!(route.matches(&Method::GET, "/__inspect"));
+        assert!(!route.matches(&Method::POST, "/__inspect"));
+        assert!(!route.matches(&Method::GET, "/__inspect/module"));
+
+        let route = DevServerRoute::new(None, "/__inspect/**", |_, _| Ok(DevResponse::text("")));
+        assert!(route.matches(&Method::POST, "/__inspect/module"));
+        assert!(route.matches(&Method::GET, "/__inspect/a/b"));
+        assert!(!route.matches(&Method::GET, "/index.js"));
+    }
+
+    #[test]
+    fn test_query_param() {
+        let request = DevRequest {
+            method: Method::GET,
+            path: "/__inspect/module".to_string(),
+            query: Some("id=%2Fsrc%2Findex.ts%3Fa%3D1&id=b".to_string()),
+            headers: HeaderMap::new(),
+            body: vec![],
+        };
+        assert_eq!(
+            request.query_param("id"),
+            Some("/src/index.ts?a=1".to_string())
+        );
+        assert_eq!(request.query_param("missing"), None);
+    }
+}
@@ -4,14 +4,16 @@ use std::fmt::Debug;
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Ok, Result};
+use colored::Colorize;
 use rayon::prelude::*;
 use tracing::debug;
 
 use crate::ast::file::File;
 use crate::build::BuildError;
 use crate::compiler::Compiler;
+use crate::dev::CarriedUpdate;
 use crate::generate::transform::transform_modules;
-use crate::module::{Dependency, Module, ModuleId, ResolveType};
+use crate::module::{relative_to_root, Dependency, Module, ModuleId, ResolveType};
 use crate::module_graph::ModuleGraph;
 use crate::plugin::NextBuildParam;
 use crate::resolve::{self, clear_resolver_cache};
@@ -33,6 +35,8 @@ pub struct UpdateResult {
     pub modified: HashSet<ModuleId>,
     // 依赖变更，典型的如 async import 变成 import
     pub dep_changed: HashSet<ModuleId>,
+    // 修改的模块中依赖请求未变化的模块Id，不需要重新分组 chunk
+    pub deps_unchanged: HashSet<ModuleId>,
 }
 
 impl UpdateResult {
@@ -79,13 +83,24 @@ dep_changed:{:?}
     }
 }
 
+// the modified, the dep changed and the deps unchanged module ids, and the added paths
+type BuildByModifyResult = (
+    HashSet<ModuleId>,
+    HashSet<ModuleId>,
+    HashSet<ModuleId>,
+    Vec<PathBuf>,
+);
+
 impl Compiler {
     pub fn update(&self, paths: Vec<PathBuf>) -> Result<UpdateResult> {
         let module_graph = self.context.module_graph.read().unwrap();
         let paths = paths
             .into_iter()
             .map(|path| {
-                let update_type = if path.exists() {
+                let is_virtual = self.context.virtual_modules.has(&path.to_string_lossy());
+                let update_type = if is_virtual {
+                    UpdateType::Modify
+                } else if path.exists() {
                     let path = path.to_string_lossy().to_string();
                     if module_graph.has_module(&path.clone().into())
                         || module_graph.has_module(&format!("{}?modules", path).into())
@@ -103,37 +118,58 @@ impl Compiler {
             .collect::<Vec<_>>();
         drop(module_graph);
         debug!("update: {:?}", &paths);
-        let mut update_result: UpdateResult = Default::default();
+        // the work of the update cancelled by these changes
+        let carried = self.context.build_cancellation.take_carried();
+        let mut update_result = carried.result;
 
         let mut modified = vec![];
         let mut removed = vec![];
         let mut added = vec![];
 
         debug!("checking added...");
-        let mut has_added = false;
-        for (path, update_type) in &paths {
-            if matches!(update_type, UpdateType::Add) {
-                debug!("  > {} is added", path.to_string_lossy());
-                has_added = true;
-                break;
-            }
-        }
-        debug!("checking added...done, has_added:{}", has_added);
+        let added_paths = paths
+            .iter()
+            .filter(|(_, update_type)| matches!(update_type, UpdateType::Add))
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        debug!("checking added...done, added_paths:{:?}", added_paths);
+
+        // the lockfile is changed when the packages are installed, updated or
+        // removed, whose files are not watched, so the bare specifiers are
+        // resolved again
+        let lock_file_changed = paths.iter().any(|(path, _)| resolve::is_lock_file(path));
 
         // try to resolve modules with missing deps
         // if found, add to modified queue
-        if has_added {
+        if !added_paths.is_empty() || lock_file_changed {
             debug!("checking modules_with_missing_deps... since has added modules");
             // clear resolver cache before resolving to avoid wrong result, i.e. add missing dep after watch started
             clear_resolver_cache(&self.context.resolvers);
+            self.context.resolve_cache.clear();
             let mut modules_with_missing_deps =
                 self.context.modules_with_missing_deps.write().unwrap();
             let mut module_graph = self.context.module_graph.write().unwrap();
             for module_id in modules_with_missing_deps.clone().iter() {
                 let id = ModuleId::new(module_id.clone());
                 let module = module_graph.get_module_mut(&id).unwrap();
-                let missing_deps = module.info.clone().unwrap().deps.missing_deps;
-                for (_source, dep) in missing_deps {
+                let info = module.info.as_mut().unwrap();
+                for (source, dep) in info.deps.missing_deps.clone() {
+                    // only the deps whose probed paths (negative dependencies) are
+                    // added need to be resolved again
+                    let is_affected = match info.deps.missing_dep_paths.get(&source) {
+                        _ if lock_file_changed && resolve::is_bare_specifier(&dep.source) => true,
+                        Some(probed_paths) if !probed_paths.is_empty() => {
+                            added_paths.iter().any(|added| {
+                                probed_paths.iter().any(|probed| {
+                                    probed.starts_with(added) || added.starts_with(probed)
+                                })
+                            })
+                        }
+                        _ => true,
+                    };
+                    if !is_affected {
+                        continue;
+                    }
                     let resolved =
                         resolve::resolve(module_id, &dep, &self.context.resolvers, &self.context);
                     if resolved.is_ok() {
@@ -142,27 +178,83 @@ impl Compiler {
                             dep.source, module_id
                         );
                         modified.push(PathBuf::from(module_id.clone()));
-                        let info = module.info.as_mut().unwrap();
-                        info.deps.missing_deps.remove(&dep.source);
+                        info.deps.missing_deps.remove(&source);
+                        info.deps.missing_dep_paths.remove(&source);
                         if info.deps.missing_deps.is_empty() {
                             debug!("  > remove {} from modules_with_missing_deps", module_id);
-                            modules_with_missing_deps.retain(|x| x == module_id);
+                            modules_with_missing_deps.retain(|x| x != module_id);
                         }
+                    } else {
+                        // the probed paths may change, e.g. node_modules/foo is created
+                        // but node_modules/foo/package.json is not yet
+                        info.deps.missing_dep_paths.insert(
+                            source,
+                            resolve::get_probed_paths(
+                                module_id,
+                                &dep,
+                                &self.context.resolvers,
+                                &self.context.config,
+                            ),
+                        );
                     }
                 }
             }
             debug!("checking modules_with_missing_deps...done");
+            drop(module_graph);
+            drop(modules_with_missing_deps);
+
+            // the deps resolved in the directories of `resolve.roots` after the
+            // ones the added files are in are overridden by them
+            let module_graph = self.context.module_graph.read().unwrap();
+            for module in module_graph.modules() {
+                let Some(info) = module.info.as_ref() else {
+                    continue;
+                };
+                let is_shadowed = info
+                    .deps
+                    .shadowed_dep_paths
+                    .values()
+                    .flatten()
+                    .any(|probed| {
+                        added_paths
+                            .iter()
+                            .any(|added| probed.starts_with(added) || added.starts_with(probed))
+                    });
+                let path = module.id.to_path();
+                if is_shadowed && !modified.contains(&path) {
+                    debug!(
+                        "  > deps of {} are overridden in resolve.roots",
+                        module.id.id
+                    );
+                    modified.push(path);
+                }
+            }
+        }
+        if lock_file_changed {
+            for path in self.modules_with_changed_packages() {
+                if !modified.contains(&path) {
+                    modified.push(path);
+                }
+            }
         }
 
         // watch 到变化的文件，如果不在之前的 module graph 中，需过滤掉
         debug!("filtering paths...");
+        // modified paths which are skipped if the content is not changed, since
+        // editors may write files or touch mtimes without changing the content
+        let mut content_checked_paths = HashSet::new();
+        let mut build_dependency_changed = false;
         let paths: Vec<(PathBuf, UpdateType)> = {
             let module_graph = self.context.module_graph.read().unwrap();
             let mut new_paths = vec![];
+            let mut dependent_paths = HashSet::new();
             paths.into_iter().for_each(|(p, update_type)| {
                 if module_graph.has_module(&p.clone().into()) {
                     debug!("  > {} is filtered", p.to_string_lossy());
                     new_paths.push((p.clone(), update_type.clone()));
+                    if matches!(update_type, UpdateType::Modify) {
+                        content_checked_paths.insert(p.clone());
+                    }
                 }
                 let path = p.to_string_lossy().to_string();
                 let watch_parent_searches = vec!["?modules", "?watch=parent"];
@@ -176,13 +268,64 @@ impl Compiler {
                             debug!("  > {} is filtered", dependent.0.id);
                             new_paths
                                 .push((PathBuf::from(dependent.0.id.clone()), update_type.clone()));
+                            dependent_paths.insert(PathBuf::from(dependent.0.id.clone()));
                         }
                     }
                 }
+                // the sub-modules of the files split by the plugins, e.g. the
+                // style of a single file component
+                if self.context.sub_modules.has(&path) {
+                    self.context.sub_modules.invalidate(&path);
+                    let prefix = format!("{}?", path);
+                    for module in module_graph.modules() {
+                        let sub_module = module.id.to_path();
+                        if module.id.id.starts_with(&prefix)
+                            && !new_paths.iter().any(|(p, _)| *p == sub_module)
+                        {
+                            debug!("  > {} is filtered as a sub-module", module.id.id);
+                            new_paths.push((sub_module, update_type.clone()));
+                        }
+                    }
+                }
+                // modules whose output depends on the file, and the config file
+                // invalidates every module
+                let is_config_file = self.context.config.config_files.contains(&p);
+                if is_config_file {
+                    println!(
+                        "{}: {} is changed, restart to apply the new config",
+                        "warning".to_string().yellow(),
+                        relative_to_root(&p.to_string_lossy().to_string(), &self.context.root)
+                    );
+                }
+                module_graph
+                    .modules()
+                    .into_iter()
+                    .filter(|module| {
+                        module.info.as_ref().is_some_and(|info| {
+                            info.external.is_none()
+                                && (is_config_file || info.build_dependencies.contains(&p))
+                        })
+                    })
+                    .for_each(|module| {
+                        debug!(
+                            "  > {} is filtered by build dependency {:?}",
+                            module.id.id, p
+                        );
+                        build_dependency_changed = true;
+                        new_paths.push((module.id.to_path(), UpdateType::Modify));
+                        dependent_paths.insert(module.id.to_path());
+                    });
             });
+            // dependents are rebuilt for the changes of their children
+            content_checked_paths.retain(|p| !dependent_paths.contains(p));
             new_paths
         };
         debug!("filtering paths...done");
+        if build_dependency_changed {
+            // e.g. the tsconfig.json is cached by the resolvers
+            clear_resolver_cache(&self.context.resolvers);
+            self.context.resolve_cache.clear();
+        }
 
         // 先分组
         for (path, update_type) in paths {
@@ -216,6 +359,10 @@ impl Compiler {
 
         // 先做删除
         debug!("remove: {:?}", &removed);
+        if !removed.is_empty() {
+            // the dependents are resolved again without the removed files
+            self.context.resolve_cache.clear();
+        }
         let (removed_module_ids, affected_module_ids) = self.build_by_remove(removed);
         debug!("after build_by_remove");
         debug!("  > removed_module_ids: {:?}", &removed_module_ids);
@@ -225,24 +372,68 @@ impl Compiler {
         );
         update_result.removed.extend(removed_module_ids);
         modified.extend(affected_module_ids.into_iter().map(|i| i.to_path()));
+        {
+            // unless they are removed by these changes
+            let module_graph = self.context.module_graph.read().unwrap();
+            modified.extend(
+                carried
+                    .modified
+                    .into_iter()
+                    .filter(|path| module_graph.has_module(&path.clone().into())),
+            );
+        }
+        added.extend(carried.added.into_iter().filter(|path| path.exists()));
 
         // 分析修改的模块，结果中会包含新增的模块
+        // a dependent of the removed modules may be modified too, it's built once
+        let mut modified_set = HashSet::new();
+        modified.retain(|path| modified_set.insert(path.clone()));
         debug!("modify: {:?}", &modified);
-        let (modified_module_ids, dep_changed_module_ids, add_paths) =
-            self.build_by_modify(modified).map_err(|err| anyhow!(err))?;
+        // the module graph is not changed by the phase if it's cancelled
+        let (modified_module_ids, dep_changed_module_ids, deps_unchanged_module_ids, add_paths) =
+            match self.build_by_modify(modified.clone(), &content_checked_paths) {
+                Err(_) if self.context.build_cancellation.is_cancelled() => {
+                    return Err(self.context.build_cancellation.carry(CarriedUpdate {
+                        result: update_result,
+                        modified,
+                        added,
+                    }));
+                }
+                result => result.map_err(|err| anyhow!(err))?,
+            };
         debug!("after build_by_modify");
         debug!("  > modified_module_ids: {:?}", &modified_module_ids);
+        debug!(
+            "  > deps unchanged: {} of {} modified modules",
+            deps_unchanged_module_ids.len(),
+            modified_module_ids.len()
+        );
         debug!(
             "  > add_paths: {:?} (these will be added to added)",
             &add_paths
         );
 
         added.extend(add_paths);
+        // a file may be both created and imported by a modified module
+        let mut added_set = HashSet::new();
+        added.retain(|path| added_set.insert(path.clone()));
 
         update_result.modified.extend(modified_module_ids);
 
         update_result.dep_changed.extend(dep_changed_module_ids);
 
+        update_result
+            .deps_unchanged
+            .extend(deps_unchanged_module_ids);
+
+        if self.context.build_cancellation.is_cancelled() {
+            return Err(self.context.build_cancellation.carry(CarriedUpdate {
+                result: update_result,
+                modified: vec![],
+                added,
+            }));
+        }
+
         // 最后做添加
         debug!("add: {:?}", &added);
         let added_module_ids = self.build_by_add(&added)?;
@@ -254,8 +445,70 @@ impl Compiler {
         );
         update_result.added.extend(added_module_ids);
 
+        // the entries requested by the dev server with `--only-entry`
+        let requested = self.context.active_entries.take_requested();
+        if !requested.is_empty() {
+            let names = self
+                .with_depend_on(&requested)
+                .into_iter()
+                .filter(|name| !self.context.active_entries.is_active(name))
+                .collect::<Vec<_>>();
+            debug!("build requested entries: {:?}", &names);
+            self.context.active_entries.activate(&names);
+            let module_ids = self.build(self.entry_files(&names))?;
+            // the new entries need the chunks to be grouped again
+            let module_graph = self.context.module_graph.read().unwrap();
+            update_result.dep_changed.extend(
+                module_ids
+                    .iter()
+                    .filter(|id| module_graph.get_module(id).is_some_and(|m| m.is_entry))
+                    .cloned(),
+            );
+            drop(module_graph);
+            update_result.added.extend(module_ids);
+        }
+
+        // the modules which are not imported anymore, so the hot updates drop
+        // them in the runtime
+        let unreachable_module_ids = self.remove_unreachable_modules();
+        debug!("  > unreachable_module_ids: {:?}", &unreachable_module_ids);
+        for module_id in &unreachable_module_ids {
+            update_result.added.remove(module_id);
+            update_result.modified.remove(module_id);
+            update_result.deps_unchanged.remove(module_id);
+        }
+        update_result.removed.extend(unreachable_module_ids);
+        self.evict_removed_modules();
+        // the carried modules may be added or removed again by these changes
+        {
+            let module_graph = self.context.module_graph.read().unwrap();
+            update_result
+                .removed
+                .retain(|id| !module_graph.has_module(id));
+            for ids in [
+                &mut update_result.added,
+                &mut update_result.modified,
+                &mut update_result.dep_changed,
+                &mut update_result.deps_unchanged,
+            ] {
+                ids.retain(|id| module_graph.has_module(id));
+            }
+            let dep_changed = &update_result.dep_changed;
+            update_result
+                .deps_unchanged
+                .retain(|id| !dep_changed.contains(id));
+        }
+
         debug!("update_result: {:?}", &update_result);
 
+        self.context.chunk_hash_cache.invalidate(
+            update_result
+                .added
+                .iter()
+                .chain(&update_result.removed)
+                .chain(&update_result.modified),
+        );
+
         self.context.plugin_driver.after_update(self)?;
 
         Result::Ok(update_result)
@@ -276,15 +529,19 @@ impl Compiler {
     fn build_by_modify(
         &self,
         modified: Vec<PathBuf>,
-    ) -> Result<(HashSet<ModuleId>, HashSet<ModuleId>, Vec<PathBuf>)> {
+        content_checked_paths: &HashSet<PathBuf>,
+    ) -> Result<BuildByModifyResult> {
         let result = modified
             .par_iter()
             .map(|entry| {
                 debug!("build by modify: {:?} start", entry);
                 // first build
+                // the entry wrapped by ?hmr or ?prelude in watch mode is an entry too
                 let is_entry = {
+                    let path = entry.to_string_lossy();
+                    let path = path.split('?').next().unwrap();
                     let mut entries = self.context.config.entry.values();
-                    entries.any(|e| e.eq(entry))
+                    entries.any(|e| e.import.to_string_lossy() == path)
                 };
 
                 let path = entry.to_string_lossy().to_string();
@@ -293,9 +550,16 @@ impl Compiler {
                 } else {
                     crate::ast::file::File::new(path, self.context.clone())
                 };
+                // the tasks which are not started are skipped once cancelled
+                self.context.build_cancellation.check()?;
                 let module = Self::build_module(&file, None, self.context.clone())
                     .map_err(|err| BuildError::BuildTasksError { errors: vec![err] })?;
 
+                if content_checked_paths.contains(entry) && self.is_content_unchanged(&module) {
+                    debug!("build by modify: {:?} is skipped, content unchanged", entry);
+                    return Result::Ok(None);
+                }
+
                 debug!(
                     "  > missing deps: {:?}",
                     module.info.as_ref().unwrap().deps.missing_deps
@@ -309,11 +573,32 @@ impl Compiler {
                         .unwrap()
                         .retain(|id| id != &module.id.id);
                 } else {
-                    self.context
-                        .modules_with_missing_deps
-                        .write()
-                        .unwrap()
-                        .push(module.id.id.clone());
+                    // the module is rebuilt with the same missing deps
+                    let mut modules_with_missing_deps =
+                        self.context.modules_with_missing_deps.write().unwrap();
+                    if !modules_with_missing_deps
+                        .iter()
+                        .any(|id| *id == module.id.id)
+                    {
+                        modules_with_missing_deps.push(module.id.id.to_string());
+                    }
+                }
+
+                let resolved_deps = &module.info.as_ref().unwrap().deps.resolved_deps;
+                if self.is_deps_unchanged(&module) {
+                    // only the data of the edges, e.g. the spans, need to be updated
+                    crate::mako_profile_scope!("deps unchanged", &module.id.id);
+                    debug!("build by modify: {:?} end, deps unchanged", entry);
+                    let target_dependencies = resolved_deps
+                        .iter()
+                        .map(|dep| {
+                            (
+                                ModuleId::new(dep.resolver_resource.get_resolved_path()),
+                                dep.dependency.clone(),
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    return Result::Ok(Some((module, None, target_dependencies)));
                 }
 
                 // diff
@@ -327,7 +612,6 @@ impl Compiler {
 
                 let mut dependence_modules: HashMap<ModuleId, Module> = HashMap::new();
                 let mut target_dependencies: Vec<(ModuleId, Dependency)> = vec![];
-                let resolved_deps = &module.info.as_ref().unwrap().deps.resolved_deps;
                 resolved_deps.iter().for_each(|dep| {
                     let resolved_path = dep.resolver_resource.get_resolved_path();
                     let is_external = dep.resolver_resource.get_external().is_some();
@@ -350,22 +634,40 @@ impl Compiler {
                 let modules_diff = diff(&current_dependencies, &target_dependencies);
 
                 debug!("build by modify: {:?} end", entry);
-                Result::Ok((
+                Result::Ok(Some((
                     module,
-                    modules_diff,
-                    dependence_modules,
+                    Some((modules_diff, dependence_modules)),
                     target_dependencies,
-                ))
+                )))
             })
             .collect::<Result<Vec<_>>>();
-        let modified_results = result?;
+        let modified_results = result?.into_iter().flatten();
 
         let mut added = vec![];
         let mut modified_module_ids = HashSet::new();
         let mut dep_changed_module_ids = HashSet::new();
+        let mut deps_unchanged_module_ids = HashSet::new();
 
         let mut module_graph = self.context.module_graph.write().unwrap();
-        for (modified_module, diff, mut dependence_modules, dependencies) in modified_results {
+        for (mut modified_module, diff, dependencies) in modified_results {
+            modified_module.id.intern(&self.context.interner);
+            let Some((diff, mut dependence_modules)) = diff else {
+                let to_module_ids = module_graph
+                    .get_dependencies(&modified_module.id)
+                    .into_iter()
+                    .map(|(module_id, _)| module_id.clone())
+                    .collect::<HashSet<_>>();
+                for to_module_id in &to_module_ids {
+                    module_graph.clear_dependency(&modified_module.id, to_module_id);
+                }
+                for (to_module_id, dep) in &dependencies {
+                    module_graph.add_dependency(&modified_module.id, to_module_id, dep.clone());
+                }
+                modified_module_ids.insert(modified_module.id.clone());
+                deps_unchanged_module_ids.insert(modified_module.id.clone());
+                module_graph.replace_module(modified_module);
+                continue;
+            };
             if diff.dependence_changed(&modified_module.id, &module_graph, &dependencies) {
                 dep_changed_module_ids.insert(modified_module.id.clone());
             }
@@ -390,7 +692,8 @@ impl Compiler {
                 if add_module.is_none() {
                     continue;
                 }
-                let add_module = add_module.unwrap();
+                let mut add_module = add_module.unwrap();
+                add_module.id.intern(&self.context.interner);
 
                 // 只针对非 external 的模块设置 add Task
                 if add_module.info.is_none() {
@@ -424,7 +727,87 @@ impl Compiler {
             module_graph.replace_module(modified_module);
         }
 
-        Result::Ok((modified_module_ids, dep_changed_module_ids, added))
+        Result::Ok((
+            modified_module_ids,
+            dep_changed_module_ids,
+            deps_unchanged_module_ids,
+            added,
+        ))
+    }
+
+    // the modules of the project whose bare specifiers are resolved to other
+    // files or not resolved anymore after the packages are changed, e.g. a
+    // package is updated to a version whose entry is another file
+    fn modules_with_changed_packages(&self) -> Vec<PathBuf> {
+        let module_graph = self.context.module_graph.read().unwrap();
+        module_graph
+            .modules()
+            .into_iter()
+            .filter(|module| {
+                module
+                    .info
+                    .as_ref()
+                    .is_some_and(|info| info.external.is_none() && !info.file.is_under_node_modules)
+            })
+            .filter(|module| {
+                module_graph
+                    .get_dependencies(&module.id)
+                    .into_iter()
+                    .filter(|(_, dep)| resolve::is_bare_specifier(&dep.source))
+                    .any(|(target, dep)| {
+                        resolve::resolve(&module.id.id, dep, &self.context.resolvers, &self.context)
+                            .map_or(true, |resource| {
+                                ModuleId::new(resource.get_resolved_path()) != *target
+                            })
+                    })
+            })
+            .map(|module| {
+                debug!("  > {} is modified by the changed packages", module.id.id);
+                module.id.to_path()
+            })
+            .collect()
+    }
+
+    fn is_deps_unchanged(&self, module: &Module) -> bool {
+        // deps_hash is only calculated in watch mode
+        if !self.context.args.watch {
+            return false;
+        }
+        let module_graph = self.context.module_graph.read().unwrap();
+        match (
+            module_graph
+                .get_module(&module.id)
+                .and_then(|m| m.info.as_ref()),
+            module.info.as_ref(),
+        ) {
+            // the deps may be removed from the graph, e.g. deleted and created again
+            (Some(old), Some(new)) => {
+                old.deps_hash == new.deps_hash
+                    && new.deps.resolved_deps.iter().all(|dep| {
+                        module_graph
+                            .has_module(&ModuleId::new(dep.resolver_resource.get_resolved_path()))
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    fn is_content_unchanged(&self, module: &Module) -> bool {
+        // raw_hash is only calculated in watch mode
+        if !self.context.args.watch {
+            return false;
+        }
+        let module_graph = self.context.module_graph.read().unwrap();
+        match (
+            module_graph
+                .get_module(&module.id)
+                .and_then(|m| m.info.as_ref()),
+            module.info.as_ref(),
+        ) {
+            // raw_hash covers the loaded content and the missing deps
+            (Some(old), Some(new)) => old.raw_hash == new.raw_hash,
+            _ => false,
+        }
     }
 
     fn build_by_add(&self, added: &[PathBuf]) -> Result<HashSet<ModuleId>> {
@@ -440,6 +823,28 @@ impl Compiler {
         self.build(files)
     }
 
+    fn remove_unreachable_modules(&self) -> HashSet<ModuleId> {
+        let mut module_graph = self.context.module_graph.write().unwrap();
+        let module_ids = module_graph.unreachable_module_ids();
+        for module_id in &module_ids {
+            module_graph.remove_module_and_deps(module_id);
+        }
+        module_ids.into_iter().collect()
+    }
+
+    // the states of the modules which are not in the module graph anymore,
+    // and the caches which only grow
+    fn evict_removed_modules(&self) {
+        let module_graph = self.context.module_graph.read().unwrap();
+        self.context
+            .modules_with_missing_deps
+            .write()
+            .unwrap()
+            .retain(|id| module_graph.has_module(&ModuleId::new(id.clone())));
+        self.context.resolve_cache.trim(&self.context.resolvers);
+        self.context.interner.prune();
+    }
+
     fn build_by_remove(&self, removed: Vec<PathBuf>) -> (HashSet<ModuleId>, HashSet<ModuleId>) {
         let mut module_graph = self.context.module_graph.write().unwrap();
         let mut removed_module_ids = HashSet::new();
@@ -525,3 +930,588 @@ fn diff(origin: &[(ModuleId, Dependency)], new_deps: &[(ModuleId, Dependency)])
         modified,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::compiler::Args;
+    use crate::config::Config;
+    use crate::testing::TempDir;
+    use crate::utils::memory::MemoryReport;
+
+    #[test]
+    fn test_skip_unchanged_content() {
+        let root = TempDir::new("skip-unchanged-content");
+        fs::write(root.join("index.js"), "import './a';").unwrap();
+        fs::write(root.join("a.js"), "console.log('a');").unwrap();
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        compiler.compile().unwrap();
+
+        // e.g. the file is saved twice by the editor
+        fs::write(root.join("a.js"), "console.log('a');").unwrap();
+        let result = compiler.update(vec![root.join("a.js")]).unwrap();
+        assert!(!result.is_updated());
+
+        fs::write(root.join("a.js"), "console.log('b');").unwrap();
+        let result = compiler.update(vec![root.join("a.js")]).unwrap();
+        assert!(result.modified.contains(&root.join("a.js").into()));
+    }
+
+    #[test]
+    fn test_lock_file_changed() {
+        let root = TempDir::new("lock-file-changed");
+        fs::create_dir_all(root.join("node_modules/foo")).unwrap();
+        fs::write(root.join("index.js"), "import 'foo'; import './a';").unwrap();
+        fs::write(root.join("a.js"), "import('bar').catch(() => {});").unwrap();
+        fs::write(
+            root.join("node_modules/foo/package.json"),
+            r#"{ "name": "foo", "main": "v1.js" }"#,
+        )
+        .unwrap();
+        fs::write(root.join("node_modules/foo/v1.js"), "").unwrap();
+        fs::write(root.join("node_modules/foo/v2.js"), "").unwrap();
+        fs::write(root.join("package-lock.json"), "{}").unwrap();
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        let _ = compiler.compile();
+        let index: ModuleId = root.join("index.js").into();
+        let a: ModuleId = root.join("a.js").into();
+        assert!(compiler
+            .context
+            .modules_with_missing_deps
+            .read()
+            .unwrap()
+            .contains(&a.id.to_string()));
+
+        // e.g. `npm install bar` and `npm update foo`, whose files under
+        // node_modules are not watched
+        fs::create_dir_all(root.join("node_modules/bar")).unwrap();
+        fs::write(root.join("node_modules/bar/index.js"), "").unwrap();
+        fs::write(
+            root.join("node_modules/foo/package.json"),
+            r#"{ "name": "foo", "main": "v2.js" }"#,
+        )
+        .unwrap();
+        fs::write(root.join("package-lock.json"), r#"{ "version": 2 }"#).unwrap();
+        let result = compiler
+            .update(vec![root.join("package-lock.json")])
+            .unwrap();
+        assert_eq!(result.modified, HashSet::from([index.clone(), a.clone()]));
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let info = module_graph.get_module(&a).unwrap().info.as_ref().unwrap();
+        assert!(!info.deps.missing_deps.contains_key("bar"));
+        assert!(module_graph.has_module(&root.join("node_modules/foo/v2.js").into()));
+        assert!(module_graph.has_module(&root.join("node_modules/bar/index.js").into()));
+        drop(module_graph);
+
+        // the resolutions are unchanged
+        fs::write(root.join("package-lock.json"), r#"{ "version": 3 }"#).unwrap();
+        let result = compiler
+            .update(vec![root.join("package-lock.json")])
+            .unwrap();
+        assert!(!result.is_updated());
+    }
+
+    #[test]
+    fn test_resolve_roots_override() {
+        let root = TempDir::new("resolve-roots-override");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("brands/acme")).unwrap();
+        fs::write(root.join("src/index.js"), "import './Button';").unwrap();
+        fs::write(root.join("src/Button.js"), "console.log('button');").unwrap();
+        let config = Config::new(
+            &root,
+            None,
+            Some(r#"{"resolve": {"roots": ["./brands/acme", "./src"]}}"#),
+        )
+        .unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        compiler.compile().unwrap();
+        let index: ModuleId = root.join("src/index.js").into();
+        let has_module = |path: &str| {
+            compiler
+                .context
+                .module_graph
+                .read()
+                .unwrap()
+                .has_module(&root.join(path).into())
+        };
+        assert!(has_module("src/Button.js"));
+
+        // the override is created after the dep is resolved in src
+        let acme_button = root.join("brands/acme/Button.tsx");
+        fs::write(&acme_button, "console.log('acme button');").unwrap();
+        let result = compiler.update(vec![acme_button.clone()]).unwrap();
+        assert!(result.modified.contains(&index));
+        assert!(has_module("brands/acme/Button.tsx"));
+        assert!(!has_module("src/Button.js"));
+
+        // and removed
+        fs::remove_file(&acme_button).unwrap();
+        compiler.update(vec![acme_button]).unwrap();
+        assert!(has_module("src/Button.js"));
+        assert!(!has_module("brands/acme/Button.tsx"));
+    }
+
+    #[test]
+    fn test_deps_unchanged() {
+        let root = TempDir::new("deps-unchanged");
+        fs::write(root.join("index.js"), "import './a'; import './b';").unwrap();
+        fs::write(root.join("a.js"), "import './c'; console.log('a');").unwrap();
+        fs::write(root.join("b.js"), "console.log('b');").unwrap();
+        fs::write(root.join("c.js"), "console.log('c');").unwrap();
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        compiler.compile().unwrap();
+        let a: ModuleId = root.join("a.js").into();
+        let b: ModuleId = root.join("b.js").into();
+
+        // the function bodies change only
+        fs::write(
+            root.join("a.js"),
+            "console.log('a1');\nimport './c';\nconsole.log('a2');",
+        )
+        .unwrap();
+        fs::write(root.join("b.js"), "console.log('b1');").unwrap();
+        let result = compiler
+            .update(vec![root.join("a.js"), root.join("b.js")])
+            .unwrap();
+        assert_eq!(result.modified, HashSet::from([a.clone(), b.clone()]));
+        assert_eq!(result.deps_unchanged, HashSet::from([a.clone(), b.clone()]));
+        assert!(result.dep_changed.is_empty());
+        // the spans of the edges are updated
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let info = module_graph.get_module(&a).unwrap().info.as_ref().unwrap();
+        let dep = &info.deps.resolved_deps[0].dependency;
+        let edges = module_graph.get_dependencies(&a);
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].1.span, dep.span);
+        drop(module_graph);
+
+        fs::write(root.join("b.js"), "import './c';").unwrap();
+        let result = compiler.update(vec![root.join("b.js")]).unwrap();
+        assert!(result.deps_unchanged.is_empty());
+        assert!(result.dep_changed.contains(&b));
+    }
+
+    #[test]
+    fn test_removed_modules() {
+        let root = TempDir::new("removed-modules");
+        fs::write(
+            root.join("index.js"),
+            "import './a'; import './b'; import('./lazy');",
+        )
+        .unwrap();
+        fs::write(root.join("a.js"), "console.log('a');").unwrap();
+        fs::write(root.join("b.js"), "import './c'; console.log('b');").unwrap();
+        fs::write(root.join("c.js"), "import './b'; console.log('c');").unwrap();
+        fs::write(root.join("lazy.js"), "console.log('lazy');").unwrap();
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        compiler.compile().unwrap();
+        let output = compiler.context.config.output.path.clone();
+        compiler.emit_dev_chunks(1, 0).unwrap();
+        assert!(output.join("lazy_js-async.js").exists());
+
+        // b and c import each other, but nothing imports them anymore
+        fs::write(root.join("index.js"), "import './a';").unwrap();
+        let result = compiler.update(vec![root.join("index.js")]).unwrap();
+        let ids = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| ModuleId::from(root.join(name)))
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(result.removed, ids(&["b.js", "c.js", "lazy.js"]));
+        {
+            let module_graph = compiler.context.module_graph.read().unwrap();
+            assert!(!module_graph.has_module(&root.join("b.js").into()));
+            assert!(module_graph.has_module(&root.join("a.js").into()));
+        }
+
+        let (_, next_hmr_hash, _) = compiler.generate_hot_update_chunks(result, 0, 1).unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&fs::read(output.join("1.hot-update.json")).unwrap()).unwrap();
+        assert_eq!(
+            manifest["m"],
+            serde_json::json!(["b.js", "c.js", "lazy.js"])
+        );
+        assert_eq!(manifest["r"], serde_json::json!(["lazy_js-async.js"]));
+
+        // the removed chunk is not served anymore
+        compiler.emit_dev_chunks(next_hmr_hash, 1).unwrap();
+        assert!(!output.join("lazy_js-async.js").exists());
+        assert!(output.join("index.js").exists());
+    }
+
+    #[test]
+    fn test_build_dependencies() {
+        let root = TempDir::new("build-dependencies");
+        fs::create_dir_all(root.join("src/a")).unwrap();
+        fs::create_dir_all(root.join("src/b")).unwrap();
+        let write_tsconfig = |dir: &str| {
+            fs::write(
+                root.join("tsconfig.json"),
+                format!(
+                    r#"{{ "compilerOptions": {{ "baseUrl": ".", "paths": {{ "@/*": ["src/{}/*"] }} }} }}"#,
+                    dir
+                ),
+            )
+            .unwrap();
+        };
+        write_tsconfig("a");
+        fs::write(
+            root.join("mako.config.json"),
+            r#"{ "resolve": { "tsconfig": "tsconfig.json" } }"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("index.ts"),
+            "import { foo } from '@/foo'; console.log(foo);",
+        )
+        .unwrap();
+        fs::write(root.join("src/a/foo.ts"), "export const foo = 'a';").unwrap();
+        fs::write(root.join("src/b/foo.ts"), "export const foo = 'b';").unwrap();
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        compiler.compile().unwrap();
+        let index: ModuleId = root.join("index.ts").into();
+        assert_eq!(
+            compiler
+                .context
+                .module_graph
+                .read()
+                .unwrap()
+                .get_module(&index)
+                .unwrap()
+                .info
+                .as_ref()
+                .unwrap()
+                .build_dependencies,
+            vec![root.join("tsconfig.json")]
+        );
+
+        write_tsconfig("b");
+        let result = compiler.update(vec![root.join("tsconfig.json")]).unwrap();
+        assert!(result.modified.contains(&index));
+        assert!(result.added.contains(&root.join("src/b/foo.ts").into()));
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let deps = module_graph
+            .get_dependencies(&index)
+            .into_iter()
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(deps, vec![root.join("src/b/foo.ts").into()]);
+        drop(module_graph);
+    }
+
+    #[test]
+    fn test_memory_bounded_with_churn() {
+        let root = TempDir::new("memory-bounded-with-churn");
+        let write_index = |i: usize| {
+            fs::write(
+                root.join("index.js"),
+                format!("import './a'; import('./churn_{}');", i),
+            )
+            .unwrap();
+            fs::write(
+                root.join(format!("churn_{}.js", i)),
+                format!("import './a'; console.log({});", i),
+            )
+            .unwrap();
+        };
+        fs::write(root.join("a.js"), "console.log('a');").unwrap();
+        write_index(0);
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        compiler.compile().unwrap();
+        let mut snapshot_hash = compiler.full_hash();
+        let mut hmr_hash = snapshot_hash;
+
+        // the caches of this compiler, the render caches are shared by the
+        // compilers of the tests which run at the same time
+        let own_usages = |report: MemoryReport| {
+            report
+                .caches
+                .into_iter()
+                .filter(|cache| {
+                    !cache.name.starts_with("render") && cache.name != "emitted modules"
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut warm = None;
+        for i in 1..=500 {
+            write_index(i);
+            let previous = root.join(format!("churn_{}.js", i - 1));
+            fs::remove_file(&previous).unwrap();
+            let result = compiler
+                .update(vec![
+                    root.join("index.js"),
+                    root.join(format!("churn_{}.js", i)),
+                    previous,
+                ])
+                .unwrap();
+            assert!(result.is_updated());
+            let (next_snapshot_hash, next_hmr_hash, current_hmr_hash) = compiler
+                .generate_hot_update_chunks(result, snapshot_hash, hmr_hash)
+                .unwrap();
+            snapshot_hash = next_snapshot_hash;
+            hmr_hash = next_hmr_hash;
+            compiler
+                .emit_dev_chunks(next_hmr_hash, current_hmr_hash)
+                .unwrap();
+            if i == 50 {
+                warm = Some(own_usages(compiler.memory_report()));
+            }
+        }
+
+        let report = compiler.memory_report();
+        let warm = warm.unwrap();
+        for (cache, warm) in own_usages(report.clone()).iter().zip(&warm) {
+            assert!(
+                cache.entries <= warm.entries && cache.bytes <= warm.bytes + 64,
+                "{:?} grows from {:?}",
+                cache,
+                warm
+            );
+        }
+        // index.js?hmr, index.js, a.js and the last churn
+        assert_eq!(report.get("module graph").unwrap().entries, 4);
+        let rendered = report
+            .caches
+            .iter()
+            .filter(|cache| cache.name.starts_with("render") || cache.name == "emitted modules")
+            .map(|cache| cache.entries)
+            .sum::<usize>();
+        assert!(rendered < 100, "{}", report);
+    }
+
+    #[test]
+    fn test_only_entries() {
+        let root = TempDir::new("only-entries");
+        fs::write(
+            root.join("mako.config.json"),
+            r#"{"entry":{"index":{"import":"./index.js","dependOn":"vendor"},"admin":"./admin.js","vendor":"./vendor.js"}}"#,
+        )
+        .unwrap();
+        fs::write(root.join("index.js"), "import './a'; console.log('index');").unwrap();
+        fs::write(root.join("a.js"), "console.log('a');").unwrap();
+        fs::write(root.join("admin.js"), "console.log('admin');").unwrap();
+        fs::write(root.join("vendor.js"), "console.log('vendor');").unwrap();
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        assert!(compiler.only_entries(&["foo".to_string()]).is_err());
+        compiler.only_entries(&["admin".to_string()]).unwrap();
+        compiler.compile().unwrap();
+        let output = compiler.context.config.output.path.clone();
+        compiler.emit_dev_chunks(1, 0).unwrap();
+        assert!(output.join("admin.js").exists());
+        assert!(!output.join("index.js").exists());
+        assert!(!compiler
+            .context
+            .module_graph
+            .read()
+            .unwrap()
+            .has_module(&root.join("a.js").into()));
+
+        // the requested entry is built with the entry it depends on
+        let active_entries = &compiler.context.active_entries;
+        assert!(active_entries.request("index", root.join("index.js")));
+        let result = compiler.update(vec![root.join("index.js")]).unwrap();
+        assert!(result.is_updated());
+        assert!(result.added.contains(&root.join("a.js").into()));
+        assert_eq!(
+            active_entries.names(),
+            Some(vec![
+                "admin".to_string(),
+                "index".to_string(),
+                "vendor".to_string()
+            ])
+        );
+        let (_, next_hmr_hash, _) = compiler.generate_hot_update_chunks(result, 0, 1).unwrap();
+        compiler.emit_dev_chunks(next_hmr_hash, 1).unwrap();
+        assert!(output.join("index.js").exists());
+        assert!(output.join("vendor.js").exists());
+        assert!(output.join("admin.js").exists());
+    }
+
+    // cancels the update when the file is loaded
+    struct CancelOnLoad {
+        path: PathBuf,
+        armed: std::sync::atomic::AtomicBool,
+    }
+
+    impl crate::plugin::Plugin for CancelOnLoad {
+        fn name(&self) -> &str {
+            "cancel_on_load"
+        }
+
+        fn load(
+            &self,
+            param: &crate::plugin::PluginLoadParam,
+            context: &std::sync::Arc<crate::compiler::Context>,
+        ) -> Result<Option<crate::ast::file::Content>> {
+            if param.file.path == self.path
+                && self.armed.swap(false, std::sync::atomic::Ordering::SeqCst)
+            {
+                context.build_cancellation.cancel();
+            }
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn test_cancelled_update() {
+        let root = TempDir::new("cancelled-update");
+        let names = ["a", "b", "c", "d", "e", "f"];
+        let imports = names.map(|name| format!("import './{}';", name)).join("");
+        fs::write(root.join("index.js"), imports + "import './user';").unwrap();
+        for name in names {
+            fs::write(
+                root.join(format!("{}.js", name)),
+                format!("console.log('{}1');", name),
+            )
+            .unwrap();
+        }
+        fs::write(
+            root.join("user.js"),
+            "import './gone'; console.log('user');",
+        )
+        .unwrap();
+        fs::write(root.join("gone.js"), "console.log('gone');").unwrap();
+        let plugin = std::sync::Arc::new(CancelOnLoad {
+            path: root.join("b.js"),
+            armed: false.into(),
+        });
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler = Compiler::new(
+            config,
+            root.to_path_buf(),
+            Args { watch: true },
+            Some(vec![plugin.clone()]),
+        )
+        .unwrap();
+        compiler.compile().unwrap();
+        let output = compiler.context.config.output.path.clone();
+        compiler.emit_dev_chunks(1, 0).unwrap();
+        let id = |name: &str| ModuleId::from(root.join(name));
+
+        // cancelled by a newer change while the modified modules are built
+        for name in names {
+            fs::write(
+                root.join(format!("{}.js", name)),
+                format!("import './{}_new'; console.log('{}2');", name, name),
+            )
+            .unwrap();
+            fs::write(root.join(format!("{}_new.js", name)), "").unwrap();
+        }
+        fs::remove_file(root.join("gone.js")).unwrap();
+        plugin
+            .armed
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        let mut paths = names
+            .iter()
+            .map(|name| root.join(format!("{}.js", name)))
+            .collect::<Vec<_>>();
+        paths.push(root.join("gone.js"));
+        let err = compiler.update(paths).unwrap_err();
+        assert!(crate::dev::BuildCancellation::is_cancellation(&err));
+        {
+            // the phases are either done or not at all
+            let module_graph = compiler.context.module_graph.read().unwrap();
+            assert!(!module_graph.has_module(&id("gone.js")));
+            let rebuilt = names
+                .iter()
+                .filter(|name| module_graph.has_module(&id(&format!("{}_new.js", name))))
+                .count();
+            assert!(rebuilt == 0 || rebuilt == names.len(), "{}", rebuilt);
+        }
+
+        // the next update starts with the newer change and the carried work
+        compiler.context.build_cancellation.reset();
+        fs::write(root.join("c.js"), "console.log('c3');").unwrap();
+        let result = compiler.update(vec![root.join("c.js")]).unwrap();
+        let mut modified = names
+            .iter()
+            .map(|name| format!("{}.js", name))
+            .collect::<Vec<_>>();
+        modified.push("user.js".to_string());
+        assert_eq!(
+            result.modified,
+            modified.iter().map(|name| id(name)).collect::<HashSet<_>>()
+        );
+        // and the module imported by the carried c.js, if it's built
+        assert!(result.removed.contains(&id("gone.js")));
+        assert!(result
+            .removed
+            .is_subset(&HashSet::from([id("gone.js"), id("c_new.js")])));
+        assert_eq!(
+            result.added,
+            names
+                .iter()
+                .filter(|name| **name != "c")
+                .map(|name| id(&format!("{}_new.js", name)))
+                .collect::<HashSet<_>>()
+        );
+        assert!(!compiler
+            .context
+            .module_graph
+            .read()
+            .unwrap()
+            .has_module(&id("c_new.js")));
+
+        let (_, next_hmr_hash, _) = compiler.generate_hot_update_chunks(result, 0, 1).unwrap();
+        compiler.emit_dev_chunks(next_hmr_hash, 1).unwrap();
+        let index = fs::read_to_string(output.join("index.js")).unwrap();
+        for name in names.iter().filter(|name| **name != "c") {
+            assert!(
+                index.contains(&format!("console.log('{}2')", name)),
+                "{}",
+                index
+            );
+        }
+        assert!(index.contains("console.log('c3')"), "{}", index);
+        assert!(!index.contains("console.log('gone')"), "{}", index);
+    }
+
+    #[test]
+    fn test_cancelled_emit() {
+        let root = TempDir::new("cancelled-emit");
+        fs::write(root.join("index.js"), "import './a';").unwrap();
+        fs::write(root.join("a.js"), "console.log('a1');").unwrap();
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        compiler.compile().unwrap();
+        compiler.emit_dev_chunks(1, 0).unwrap();
+        let index = compiler.context.config.output.path.join("index.js");
+
+        fs::write(root.join("a.js"), "console.log('a2');").unwrap();
+        let result = compiler.update(vec![root.join("a.js")]).unwrap();
+        let (_, next_hmr_hash, _) = compiler.generate_hot_update_chunks(result, 0, 1).unwrap();
+        compiler.context.build_cancellation.cancel();
+        let err = compiler.emit_dev_chunks(next_hmr_hash, 1).unwrap_err();
+        assert!(crate::dev::BuildCancellation::is_cancellation(&err));
+        // the chunks of the last emit are kept
+        assert!(fs::read_to_string(&index)
+            .unwrap()
+            .contains("console.log('a1')"));
+
+        compiler.context.build_cancellation.reset();
+        compiler.emit_dev_chunks(next_hmr_hash, 1).unwrap();
+        assert!(fs::read_to_string(&index)
+            .unwrap()
+            .contains("console.log('a2')"));
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -11,7 +12,65 @@ use regex::Regex;
 use tracing::debug;
 
 use crate::compiler::Compiler;
-use crate::resolve::ResolverResource;
+use crate::config::matched_ignore_rule;
+use crate::resolve::{ResolverResource, LOCK_FILES};
+
+// exist while git is writing the working tree, e.g. checkout and merge
+const VCS_LOCK_FILES: [&str; 2] = ["index.lock", "MERGE_HEAD"];
+
+/// Pauses the rebuilds, explicitly by the dev server api or while a git
+/// operation is in progress. The changes are buffered meanwhile and rebuilt
+/// once after the files settle, instead of rebuilding the half written files
+/// of e.g. a checkout again and again.
+#[derive(Default)]
+pub struct WatchPause {
+    paused: AtomicBool,
+    vcs_lock_files: Vec<PathBuf>,
+}
+
+impl WatchPause {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            vcs_lock_files: git_dir(root)
+                .map(|dir| VCS_LOCK_FILES.iter().map(|file| dir.join(file)).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst) || self.is_vcs_locked()
+    }
+
+    fn is_vcs_locked(&self) -> bool {
+        self.vcs_lock_files.iter().any(|file| file.exists())
+    }
+}
+
+// the .git dir of the repo of the root, which is a file of `gitdir: <path>`
+// for worktrees and submodules
+fn git_dir(root: &Path) -> Option<PathBuf> {
+    root.ancestors().find_map(|dir| {
+        let git = dir.join(".git");
+        if git.is_dir() {
+            Some(git)
+        } else if git.is_file() {
+            let content = std::fs::read_to_string(&git).ok()?;
+            let git_dir = content.trim().strip_prefix("gitdir:")?.trim();
+            Some(dir.join(git_dir))
+        } else {
+            None
+        }
+    })
+}
 
 pub struct Watcher<'a> {
     pub watcher: &'a mut dyn NotifyWatcher,
@@ -19,6 +78,8 @@ pub struct Watcher<'a> {
     pub compiler: &'a Compiler,
     pub watched_files: HashSet<PathBuf>,
     pub watched_dirs: HashSet<PathBuf>,
+    // non-recursive watched dirs for paths probed by missing deps
+    pub watched_missing_dirs: HashSet<PathBuf>,
     node_modules_regexes: Vec<Regex>,
 }
 
@@ -34,6 +95,7 @@ impl<'a> Watcher<'a> {
             compiler,
             watched_dirs: HashSet::new(),
             watched_files: HashSet::new(),
+            watched_missing_dirs: HashSet::new(),
             node_modules_regexes: compiler
                 .context
                 .config
@@ -89,6 +151,11 @@ impl<'a> Watcher<'a> {
             self.watch_dir_recursive(dir.into(), &self.get_ignore_list(false))?;
             Ok(())
         })?;
+        drop(module_graph);
+
+        self.watch_missing_deps()?;
+        self.watch_build_dependencies()?;
+        self.watch_lock_files()?;
 
         let t_watch_duration = t_watch.elapsed();
         debug!(
@@ -103,8 +170,118 @@ impl<'a> Watcher<'a> {
         Ok(())
     }
 
+    // paths probed by missing deps may be outside of the watched dirs, e.g.
+    // node_modules/foo or files in root dir, watch their nearest existing dirs
+    // so that creating them triggers resolving again, see
+    // `probed_dir_to_watch` for the ones under node_modules
+    pub fn watch_missing_deps(&mut self) -> anyhow::Result<()> {
+        let module_graph = self.compiler.context.module_graph.read().unwrap();
+        let modules_with_missing_deps = self
+            .compiler
+            .context
+            .modules_with_missing_deps
+            .read()
+            .unwrap();
+        let mut dirs = HashSet::new();
+        for module_id in modules_with_missing_deps.iter() {
+            let info = module_graph
+                .get_module(&module_id.as_str().into())
+                .and_then(|module| module.info.as_ref());
+            if let Some(info) = info {
+                info.deps
+                    .missing_dep_paths
+                    .values()
+                    .flatten()
+                    .filter_map(|path| probed_dir_to_watch(path, self.root))
+                    .for_each(|dir| {
+                        dirs.insert(dir);
+                    });
+            }
+        }
+        // and the paths of the overrides in the directories of resolve.roots
+        if !self.compiler.context.config.resolve.roots.is_empty() {
+            module_graph
+                .modules()
+                .iter()
+                .filter_map(|module| module.info.as_ref())
+                .flat_map(|info| info.deps.shadowed_dep_paths.values().flatten())
+                .filter_map(|path| probed_dir_to_watch(path, self.root))
+                .for_each(|dir| {
+                    dirs.insert(dir);
+                });
+        }
+        drop(modules_with_missing_deps);
+        drop(module_graph);
+
+        for dir in dirs {
+            let is_watched = self.watched_missing_dirs.contains(&dir)
+                || self
+                    .watched_dirs
+                    .iter()
+                    .any(|watched| dir.starts_with(watched));
+            if !is_watched {
+                self.watcher
+                    .watch(dir.as_path(), notify::RecursiveMode::NonRecursive)?;
+                self.watched_missing_dirs.insert(dir);
+            }
+        }
+        Ok(())
+    }
+
+    // build dependencies like tsconfig.json may be outside of the watched dirs,
+    // e.g. the tsconfig.json of the parent dir
+    pub fn watch_build_dependencies(&mut self) -> anyhow::Result<()> {
+        let module_graph = self.compiler.context.module_graph.read().unwrap();
+        let files = module_graph
+            .modules()
+            .into_iter()
+            .filter_map(|module| module.info.as_ref())
+            .flat_map(|info| info.build_dependencies.iter().cloned())
+            .collect::<HashSet<_>>();
+        drop(module_graph);
+
+        for file in files {
+            let is_watched = self.watched_files.contains(&file)
+                || self
+                    .watched_dirs
+                    .iter()
+                    .any(|watched| file.starts_with(watched));
+            if !is_watched && file.is_file() {
+                self.watcher
+                    .watch(file.as_path(), notify::RecursiveMode::NonRecursive)?;
+                self.watched_files.insert(file);
+            }
+        }
+        Ok(())
+    }
+
+    // the lockfiles of the root and its parent dirs, e.g. of a monorepo, whose
+    // changes flush the resolutions of the packages since node_modules is not
+    // watched
+    pub fn watch_lock_files(&mut self) -> anyhow::Result<()> {
+        let files = self
+            .root
+            .ancestors()
+            .flat_map(|dir| LOCK_FILES.iter().map(move |name| dir.join(name)))
+            .filter(|file| file.is_file())
+            .collect::<Vec<_>>();
+        for file in files {
+            let is_watched = self.watched_files.contains(&file)
+                || self
+                    .watched_dirs
+                    .iter()
+                    .any(|watched| file.starts_with(watched));
+            if !is_watched {
+                self.watcher
+                    .watch(file.as_path(), notify::RecursiveMode::NonRecursive)?;
+                self.watched_files.insert(file);
+            }
+        }
+        Ok(())
+    }
+
     fn get_ignore_list(&self, with_output_dir: bool) -> Vec<PathBuf> {
-        let mut ignore_list = vec![".git", "node_modules", ".DS_Store", ".node"];
+        let mut ignore_list = vec![".git", "node_modules", ".DS_Store", ".node", "coverage"];
         if with_output_dir {
             ignore_list.push(self.compiler.context.config.output.path.to_str().unwrap());
         }
@@ -152,6 +329,7 @@ impl<'a> Watcher<'a> {
     fn watch_file_or_dir(&mut self, path: PathBuf, ignore_list: &[PathBuf]) -> anyhow::Result<()> {
         if Self::should_ignore_watch(&path, ignore_list)
             || path.to_string_lossy().contains("node_modules")
+            || self.is_ignored(&path)
         {
             return Ok(());
         }
@@ -170,6 +348,21 @@ impl<'a> Watcher<'a> {
         Ok(())
     }
 
+    // the paths excluded by the ignore config
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let context = &self.compiler.context;
+        matched_ignore_rule(&context.config.ignore, &context.root, path).is_some()
+    }
+
+    /// Whether the events are the changes which supersede the rebuild in
+    /// flight, i.e. of the paths which are not excluded by the ignore config.
+    pub fn supersedes(events: &[DebouncedEvent], compiler: &Compiler) -> bool {
+        let context = &compiler.context;
+        Self::normalize_events(events.to_vec())
+            .iter()
+            .any(|path| matched_ignore_rule(&context.config.ignore, &context.root, path).is_none())
+    }
+
     fn should_ignore_watch(path: &Path, ignore_list: &[PathBuf]) -> bool {
         let path = path.to_string_lossy();
         ignore_list
@@ -187,13 +380,30 @@ impl<'a> Watcher<'a> {
         let ignore_list = [".DS_Store", ".swx", ".swp"];
         // Ignore directory changes, but it should be noted that if the directory is deleted, it cannot be detected at this time
         // TODO: so, should it be put outside, based on whether the module_graph exists this module to judge?
-        if path.is_dir() {
+        // created dirs are kept since they may be probed by missing deps, e.g. node_modules/foo,
+        // and so are the renamed ones, e.g. a package moved into node_modules by the installer
+        if path.is_dir()
+            && !matches!(
+                kind,
+                EventKind::Create(_) | EventKind::Modify(notify::event::ModifyKind::Name(_))
+            )
+        {
             return true;
         }
         let path = path.to_string_lossy();
         ignore_list.iter().any(|ignored| path.ends_with(ignored))
     }
 
+    /// Whether the files are added or removed since `existed` is checked,
+    /// e.g. a rebuild fails for the files removed by git during the rebuild,
+    /// in which case they're rebuilt again after they settle.
+    pub fn is_unsettled(paths: &[PathBuf], existed: &[bool]) -> bool {
+        paths
+            .iter()
+            .zip(existed)
+            .any(|(path, existed)| path.exists() != *existed)
+    }
+
     pub fn normalize_events(events: Vec<DebouncedEvent>) -> Vec<PathBuf> {
         let mut paths = vec![];
         let mut create_paths = HashMap::new();
@@ -216,3 +426,99 @@ impl<'a> Watcher<'a> {
         paths
     }
 }
+
+// the dir to watch for a path probed by a missing dep, the nearest existing
+// dir of it. For the paths under node_modules, which are probed in the
+// node_modules of every parent dir for a bare specifier, it's the package dir
+// or the node_modules dir, or the dir where node_modules would be created
+// only if it's in the root, instead of e.g. `/` for `/node_modules/foo`
+fn probed_dir_to_watch(path: &Path, root: &Path) -> Option<PathBuf> {
+    let node_modules = path
+        .ancestors()
+        .find(|dir| dir.file_name().is_some_and(|name| name == "node_modules"));
+    let dir = path.ancestors().find(|dir| dir.is_dir())?;
+    match node_modules {
+        Some(node_modules) if !dir.starts_with(node_modules) => {
+            let project_dir = node_modules.parent()?;
+            (project_dir == dir && project_dir.starts_with(root)).then(|| dir.to_path_buf())
+        }
+        _ => Some(dir.to_path_buf()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_watch_pause() {
+        let root = TempDir::new("watch-pause");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("packages/app")).unwrap();
+
+        let pause = WatchPause::new(&root.join("packages/app"));
+        assert!(!pause.is_paused());
+        pause.pause();
+        assert!(pause.is_paused());
+        pause.resume();
+        assert!(!pause.is_paused());
+
+        // e.g. git checkout
+        fs::write(root.join(".git/index.lock"), "").unwrap();
+        assert!(pause.is_paused());
+        fs::remove_file(root.join(".git/index.lock")).unwrap();
+        assert!(!pause.is_paused());
+    }
+
+    #[test]
+    fn test_git_dir_of_worktree() {
+        let root = TempDir::new("git-dir-of-worktree");
+        fs::write(root.join(".git"), "gitdir: ../repo/.git/worktrees/app\n").unwrap();
+        assert_eq!(
+            git_dir(&root),
+            Some(root.join("../repo/.git/worktrees/app"))
+        );
+    }
+
+    #[test]
+    fn test_probed_dir_to_watch() {
+        let root = TempDir::new("probed-dir-to-watch");
+        let app = root.join("packages/app");
+        fs::create_dir_all(app.join("src")).unwrap();
+        fs::create_dir_all(root.join("node_modules/@scope")).unwrap();
+        let dir = |path: &Path| probed_dir_to_watch(path, &app);
+
+        assert_eq!(dir(&app.join("src/missing.js")), Some(app.join("src")));
+        // node_modules will be created in the root by the installer
+        assert_eq!(
+            dir(&app.join("node_modules/foo/package.json")),
+            Some(app.clone())
+        );
+        // not in the existing node_modules of the parent dirs
+        assert_eq!(
+            dir(&root.join("node_modules/foo/package.json")),
+            Some(root.join("node_modules"))
+        );
+        assert_eq!(
+            dir(&root.join("node_modules/@scope/foo/index.js")),
+            Some(root.join("node_modules/@scope"))
+        );
+        // the parent dirs without node_modules are not watched
+        assert_eq!(dir(&root.join("packages/node_modules/foo")), None);
+        assert_eq!(dir(Path::new("/node_modules/foo")), None);
+    }
+
+    #[test]
+    fn test_is_unsettled() {
+        let root = TempDir::new("is-unsettled");
+        let file = root.join("index.js");
+        fs::write(&file, "").unwrap();
+        assert!(!Watcher::is_unsettled(&[file.clone()], &[true]));
+        fs::remove_file(&file).unwrap();
+        assert!(Watcher::is_unsettled(&[file.clone()], &[true]));
+        assert!(!Watcher::is_unsettled(&[file], &[false]));
+    }
+}
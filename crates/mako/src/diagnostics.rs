@@ -0,0 +1,545 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::compiler::Context;
+use crate::config::matched_ignore_rule;
+
+/// The stable codes of the diagnostics, which are used in the
+/// `diagnostics` config and printed with the messages.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiagnosticCode {
+    #[serde(rename = "MAKO_DUPLICATE_PACKAGE")]
+    DuplicatePackage,
+    #[serde(rename = "MAKO_CASE_MISMATCH")]
+    CaseMismatch,
+    #[serde(rename = "MAKO_CIRCULAR")]
+    Circular,
+    #[serde(rename = "MAKO_EVAL_BAILOUT")]
+    EvalBailout,
+    #[serde(rename = "MAKO_DUPLICATED_MODULES")]
+    DuplicatedModules,
+    #[serde(rename = "MAKO_PARSE_FALLBACK")]
+    ParseFallback,
+    #[serde(rename = "MAKO_DYNAMIC_IMPORT")]
+    DynamicImport,
+    #[serde(rename = "MAKO_DYNAMIC_REQUIRE")]
+    DynamicRequire,
+    #[serde(rename = "MAKO_CHUNK_NAME_COLLISION")]
+    ChunkNameCollision,
+    #[serde(rename = "MAKO_REMOTE_CSS")]
+    RemoteCss,
+    #[serde(rename = "MAKO_ASYNC_GLOBAL_SIDE_EFFECT")]
+    AsyncGlobalSideEffect,
+}
+
+impl DiagnosticCode {
+    pub const ALL: [DiagnosticCode; 11] = [
+        DiagnosticCode::DuplicatePackage,
+        DiagnosticCode::CaseMismatch,
+        DiagnosticCode::Circular,
+        DiagnosticCode::EvalBailout,
+        DiagnosticCode::DuplicatedModules,
+        DiagnosticCode::ParseFallback,
+        DiagnosticCode::DynamicImport,
+        DiagnosticCode::DynamicRequire,
+        DiagnosticCode::ChunkNameCollision,
+        DiagnosticCode::RemoteCss,
+        DiagnosticCode::AsyncGlobalSideEffect,
+    ];
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == code)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::DuplicatePackage => "MAKO_DUPLICATE_PACKAGE",
+            DiagnosticCode::CaseMismatch => "MAKO_CASE_MISMATCH",
+            DiagnosticCode::Circular => "MAKO_CIRCULAR",
+            DiagnosticCode::EvalBailout => "MAKO_EVAL_BAILOUT",
+            DiagnosticCode::DuplicatedModules => "MAKO_DUPLICATED_MODULES",
+            DiagnosticCode::ParseFallback => "MAKO_PARSE_FALLBACK",
+            DiagnosticCode::DynamicImport => "MAKO_DYNAMIC_IMPORT",
+            DiagnosticCode::DynamicRequire => "MAKO_DYNAMIC_REQUIRE",
+            DiagnosticCode::ChunkNameCollision => "MAKO_CHUNK_NAME_COLLISION",
+            DiagnosticCode::RemoteCss => "MAKO_REMOTE_CSS",
+            DiagnosticCode::AsyncGlobalSideEffect => "MAKO_ASYNC_GLOBAL_SIDE_EFFECT",
+        }
+    }
+
+    fn default_severity(&self) -> Severity {
+        match self {
+            DiagnosticCode::CaseMismatch => Severity::Error,
+            DiagnosticCode::DuplicatePackage
+            | DiagnosticCode::Circular
+            | DiagnosticCode::EvalBailout
+            | DiagnosticCode::DuplicatedModules
+            | DiagnosticCode::ParseFallback
+            | DiagnosticCode::DynamicImport
+            | DiagnosticCode::DynamicRequire
+            | DiagnosticCode::ChunkNameCollision
+            | DiagnosticCode::RemoteCss
+            | DiagnosticCode::AsyncGlobalSideEffect => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+    /// suppressed by the comments in the files, which is not printed
+    Suppressed,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Diagnostic {
+    pub code: DiagnosticCode,
+    pub severity: Severity,
+    pub message: String,
+    /// the files or dirs the diagnostic is about, which are matched by
+    /// `diagnostics.ignore[].pathPattern`
+    pub paths: Vec<PathBuf>,
+    /// the files and the 1-based lines the diagnostic is at, which are
+    /// matched by `mako-ignore-next-line`
+    pub locations: Vec<(PathBuf, usize)>,
+}
+
+impl Diagnostic {
+    pub fn new(code: DiagnosticCode, message: String) -> Self {
+        Self {
+            code,
+            severity: code.default_severity(),
+            message,
+            paths: vec![],
+            locations: vec![],
+        }
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    pub fn locations(mut self, locations: Vec<(PathBuf, usize)>) -> Self {
+        self.locations = locations;
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Warning => format!("warning[{}]", self.code).yellow(),
+            Severity::Error => format!("error[{}]", self.code).red(),
+            Severity::Suppressed => format!("suppressed[{}]", self.code).dimmed(),
+        };
+        write!(f, "{}: {}", label, self.message)
+    }
+}
+
+// e.g. `// mako-ignore-next-line MAKO_CIRCULAR`
+const IGNORE_NEXT_LINE: &str = "mako-ignore-next-line";
+// e.g. `/* mako-disable MAKO_DYNAMIC_REQUIRE */`
+const DISABLE: &str = "mako-disable";
+
+/// The diagnostics suppressed by the comments of a file, the ones of the
+/// codes of `/* mako-disable <code> */` in the file, and the ones of the codes
+/// of `// mako-ignore-next-line <code>` at the line after it. The codes are
+/// separated by spaces or commas.
+#[derive(Default, Debug, PartialEq)]
+pub struct Suppressions {
+    file: HashSet<DiagnosticCode>,
+    // line -> codes
+    lines: HashMap<usize, HashSet<DiagnosticCode>>,
+}
+
+impl Suppressions {
+    /// Whether the comment is possibly a suppression comment.
+    pub fn is_candidate(text: &str) -> bool {
+        text.contains(IGNORE_NEXT_LINE) || text.contains(DISABLE)
+    }
+
+    /// The suppressions of the comments, the texts with their start and end
+    /// lines, and the warnings of the unknown codes in them, so that a typo
+    /// doesn't silently suppress nothing.
+    pub fn parse<'a>(
+        comments: impl IntoIterator<Item = (&'a str, usize, usize)>,
+        path: &str,
+    ) -> (Self, Vec<String>) {
+        let mut suppressions = Self::default();
+        let mut warnings = vec![];
+        for (text, start_line, end_line) in comments {
+            let text = text.trim().trim_start_matches('*').trim();
+            let (directive, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+            let codes = match directive {
+                IGNORE_NEXT_LINE => suppressions.lines.entry(end_line + 1).or_default(),
+                DISABLE => &mut suppressions.file,
+                _ => continue,
+            };
+            let names = rest
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<_>>();
+            if names.is_empty() {
+                warnings.push(format!(
+                    "{} at {}:{} has no diagnostic codes, e.g. `{} MAKO_CIRCULAR`",
+                    directive, path, start_line, directive
+                ));
+            }
+            for name in names {
+                match DiagnosticCode::from_code(name) {
+                    Some(code) => {
+                        codes.insert(code);
+                    }
+                    None => warnings.push(format!(
+                        "Unknown diagnostic code {} in {} at {}:{}{}",
+                        name,
+                        directive,
+                        path,
+                        start_line,
+                        suggest_code(name)
+                            .map(|code| format!(", did you mean {}?", code))
+                            .unwrap_or_default()
+                    )),
+                }
+            }
+        }
+        suppressions.lines.retain(|_, codes| !codes.is_empty());
+        (suppressions, warnings)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file.is_empty() && self.lines.is_empty()
+    }
+
+    fn suppresses(&self, code: DiagnosticCode, line: Option<usize>) -> bool {
+        self.file.contains(&code)
+            || line.is_some_and(|line| {
+                self.lines
+                    .get(&line)
+                    .is_some_and(|codes| codes.contains(&code))
+            })
+    }
+}
+
+fn suggest_code(name: &str) -> Option<&'static str> {
+    let name = name.to_uppercase();
+    let max_distance = (name.chars().count() / 3).max(1);
+    DiagnosticCode::ALL
+        .iter()
+        .map(|code| {
+            (
+                strsim::damerau_levenshtein(&name, code.as_str()),
+                code.as_str(),
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, code)| code)
+}
+
+/// The diagnostics reported during a build, the `diagnostics` config and the
+/// suppression comments are applied when they are reported.
+#[derive(Default)]
+pub struct Diagnostics {
+    inner: Mutex<DiagnosticsInner>,
+    // the suppressions of the parsed files
+    suppressions: RwLock<HashMap<PathBuf, Suppressions>>,
+}
+
+#[derive(Default)]
+struct DiagnosticsInner {
+    reported: Vec<Diagnostic>,
+    ignored: usize,
+}
+
+impl Diagnostics {
+    /// Set the suppressions of a file when it's parsed.
+    pub fn set_suppressions(&self, path: PathBuf, suppressions: Suppressions) {
+        let mut all = self.suppressions.write().unwrap();
+        if suppressions.is_empty() {
+            all.remove(&path);
+        } else {
+            all.insert(path, suppressions);
+        }
+    }
+
+    // by the comments of any of its files or its lines
+    fn is_suppressed(&self, diagnostic: &Diagnostic) -> bool {
+        let all = self.suppressions.read().unwrap();
+        let suppressed_at = |path: &Path, line: Option<usize>| {
+            all.get(path)
+                .is_some_and(|suppressions| suppressions.suppresses(diagnostic.code, line))
+        };
+        diagnostic
+            .paths
+            .iter()
+            .any(|path| suppressed_at(path, None))
+            || diagnostic
+                .locations
+                .iter()
+                .any(|(path, line)| suppressed_at(path, Some(*line)))
+    }
+
+    /// Report a diagnostic, the warnings are printed at once and the errors
+    /// fail the build after it's generated. Returns the severity after
+    /// `diagnostics.errorOn` and the suppression comments, or `None` if it's
+    /// ignored.
+    pub fn report(&self, diagnostic: Diagnostic, context: &Context) -> Option<Severity> {
+        let config = &context.config.diagnostics;
+        let mut inner = self.inner.lock().unwrap();
+        let ignored = config.ignore.iter().any(|rule| {
+            rule.code == diagnostic.code
+                && rule.path_pattern.as_ref().map_or(true, |pattern| {
+                    diagnostic.paths.iter().any(|path| {
+                        matched_ignore_rule(std::slice::from_ref(pattern), &context.root, path)
+                            .is_some()
+                    })
+                })
+        });
+        if ignored {
+            inner.ignored += 1;
+            return None;
+        }
+        let diagnostic = if self.is_suppressed(&diagnostic) {
+            diagnostic.severity(Severity::Suppressed)
+        } else if config.error_on.contains(&diagnostic.code) {
+            diagnostic.severity(Severity::Error)
+        } else {
+            diagnostic
+        };
+        if diagnostic.severity == Severity::Warning {
+            eprintln!("{}", diagnostic);
+        }
+        let severity = diagnostic.severity;
+        inner.reported.push(diagnostic);
+        Some(severity)
+    }
+
+    pub fn get_reported(&self) -> Vec<Diagnostic> {
+        self.inner.lock().unwrap().reported.clone()
+    }
+
+    pub fn ignored(&self) -> usize {
+        self.inner.lock().unwrap().ignored
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.reported.clear();
+        inner.ignored = 0;
+    }
+
+    /// Print the count of the ignored and the suppressed diagnostics, so the
+    /// suppressions are not forgotten, and fail with the errors.
+    pub(crate) fn summarize(&self, quiet: bool) -> Result<()> {
+        let inner = self.inner.lock().unwrap();
+        if inner.ignored > 0 && !quiet {
+            println!(
+                "{} diagnostic(s) ignored by diagnostics.ignore",
+                inner.ignored
+            );
+        }
+        let suppressed = inner
+            .reported
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Suppressed)
+            .count();
+        if suppressed > 0 && !quiet {
+            println!(
+                "{} diagnostic(s) suppressed by the comments in the files",
+                suppressed
+            );
+        }
+        let errors = inner
+            .reported
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("{}", errors.join("\n")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{Diagnostic, DiagnosticCode, Severity, Suppressions};
+    use crate::compiler::Context;
+    use crate::config::DiagnosticIgnoreRule;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_report() {
+        let mut context = Context {
+            root: PathBuf::from("/root"),
+            ..Default::default()
+        };
+        context.config.diagnostics.error_on = vec![DiagnosticCode::DuplicatePackage];
+        context.config.diagnostics.ignore = vec![
+            DiagnosticIgnoreRule {
+                code: DiagnosticCode::Circular,
+                path_pattern: Some("node_modules/old-lib/**".to_string()),
+            },
+            DiagnosticIgnoreRule {
+                code: DiagnosticCode::CaseMismatch,
+                path_pattern: None,
+            },
+        ];
+        let report = |code: DiagnosticCode, path: &str| {
+            let diagnostic =
+                Diagnostic::new(code, "message".to_string()).paths(vec![PathBuf::from(path)]);
+            context.diagnostics.report(diagnostic, &context)
+        };
+
+        assert_eq!(
+            report(DiagnosticCode::Circular, "/root/src/a.js"),
+            Some(Severity::Warning)
+        );
+        assert_eq!(
+            report(DiagnosticCode::Circular, "/root/node_modules/old-lib/a.js"),
+            None
+        );
+        assert_eq!(report(DiagnosticCode::CaseMismatch, "/root/src/A.js"), None);
+        assert_eq!(
+            report(DiagnosticCode::DuplicatePackage, "/root/node_modules/a"),
+            Some(Severity::Error)
+        );
+        assert_eq!(context.diagnostics.ignored(), 2);
+        assert_eq!(context.diagnostics.get_reported().len(), 2);
+        let err = context.diagnostics.summarize(true).unwrap_err();
+        assert!(
+            err.to_string().contains("MAKO_DUPLICATE_PACKAGE"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_suppressions() {
+        let (suppressions, warnings) = Suppressions::parse(
+            [
+                (" mako-ignore-next-line MAKO_CIRCULAR", 1, 1),
+                (
+                    "* mako-disable MAKO_EVAL_BAILOUT,MAKO_DYNAMIC_REQUIRE ",
+                    3,
+                    5,
+                ),
+                (
+                    " mako-ignore-next-line MAKO_CIRCULR mako_eval_bailout",
+                    7,
+                    7,
+                ),
+                (" mako-ignore-next-line", 9, 9),
+                (" eslint-disable-next-line no-eval", 11, 11),
+            ],
+            "src/a.js",
+        );
+        assert!(suppressions.suppresses(DiagnosticCode::Circular, Some(2)));
+        assert!(!suppressions.suppresses(DiagnosticCode::Circular, Some(3)));
+        assert!(!suppressions.suppresses(DiagnosticCode::Circular, None));
+        assert!(suppressions.suppresses(DiagnosticCode::EvalBailout, None));
+        assert!(suppressions.suppresses(DiagnosticCode::DynamicRequire, Some(20)));
+        assert!(!suppressions.suppresses(DiagnosticCode::DynamicImport, Some(20)));
+        assert_eq!(
+            warnings,
+            vec![
+                "Unknown diagnostic code MAKO_CIRCULR in mako-ignore-next-line at src/a.js:7, did you mean MAKO_CIRCULAR?",
+                "Unknown diagnostic code mako_eval_bailout in mako-ignore-next-line at src/a.js:7, did you mean MAKO_EVAL_BAILOUT?",
+                "mako-ignore-next-line at src/a.js:9 has no diagnostic codes, e.g. `mako-ignore-next-line MAKO_CIRCULAR`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_suppressed() {
+        let mut context = Context {
+            root: PathBuf::from("/root"),
+            ..Default::default()
+        };
+        context.config.diagnostics.error_on = vec![DiagnosticCode::Circular];
+        let (suppressions, _) =
+            Suppressions::parse([(" mako-ignore-next-line MAKO_CIRCULAR", 1, 1)], "b.js");
+        context
+            .diagnostics
+            .set_suppressions(PathBuf::from("/root/b.js"), suppressions);
+        let report = |line: usize| {
+            let diagnostic = Diagnostic::new(DiagnosticCode::Circular, "message".to_string())
+                .paths(vec![
+                    PathBuf::from("/root/a.js"),
+                    PathBuf::from("/root/b.js"),
+                ])
+                .locations(vec![
+                    (PathBuf::from("/root/a.js"), 1),
+                    (PathBuf::from("/root/b.js"), line),
+                ]);
+            context.diagnostics.report(diagnostic, &context)
+        };
+
+        assert_eq!(report(2), Some(Severity::Suppressed));
+        assert_eq!(report(3), Some(Severity::Error));
+        context.diagnostics.clear();
+        assert_eq!(report(2), Some(Severity::Suppressed));
+        assert!(context.diagnostics.summarize(true).is_ok());
+    }
+
+    #[test]
+    fn test_diagnostics_suppressions() {
+        let compiler = setup_compiler("test/build/diagnostics-suppressions", false);
+        compiler.compile().unwrap();
+        let mut suppressed = compiler
+            .context
+            .diagnostics
+            .get_reported()
+            .into_iter()
+            .map(|diagnostic| {
+                assert_eq!(diagnostic.severity, Severity::Suppressed);
+                diagnostic.code
+            })
+            .collect::<Vec<_>>();
+        suppressed.sort_by_key(|code| code.as_str());
+        assert_eq!(
+            suppressed,
+            vec![DiagnosticCode::Circular, DiagnosticCode::DynamicRequire]
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let compiler = setup_compiler("test/build/diagnostics", false);
+        let err = compiler.compile().unwrap_err().to_string();
+        assert!(err.contains("error[MAKO_CIRCULAR]"), "{}", err);
+        assert!(
+            err.contains(r#""./a.js" -> "./b.js" -> "./a.js""#)
+                || err.contains(r#""./b.js" -> "./a.js" -> "./b.js""#),
+            "{}",
+            err
+        );
+        assert!(!err.contains("old-lib"), "{}", err);
+        assert_eq!(compiler.context.diagnostics.ignored(), 1);
+    }
+}
@@ -1,16 +1,45 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use pathdiff::diff_paths;
 use serde_json::Value;
 use swc_core::common::{Mark, DUMMY_SP};
-use swc_core::ecma::ast::{Expr, Lit, Str};
+use swc_core::ecma::ast::{CallExpr, Callee, Expr, ExprOrSpread, Lit, Prop, PropOrSpread, Str};
+use swc_core::ecma::utils::{quote_ident, quote_str, ExprFactory};
 use swc_core::ecma::visit::{VisitMut, VisitMutWith};
 
+use crate::ast::utils::{get_first_str_arg, is_commonjs_require, is_ident_undefined};
+use crate::ast::DUMMY_CTXT;
+use crate::build::load::NATIVE_ADDON_EXTENSION;
 use crate::compiler::Context;
 use crate::config::{Config, ExternalConfig, Platform};
 
+// the packages which find and load the native addons at runtime
+const NATIVE_ADDON_LOADERS: [&str; 6] = [
+    "bindings",
+    "node-gyp-build",
+    "node-gyp-build-optional-packages",
+    "@mapbox/node-pre-gyp",
+    "node-pre-gyp",
+    "prebuild-install",
+];
+
+// the directories searched by `bindings`, relative to the package root
+const BINDINGS_DIRS: [&str; 8] = [
+    "build",
+    "build/Debug",
+    "build/Release",
+    "out/Debug",
+    "Debug",
+    "out/Release",
+    "Release",
+    "build/default",
+];
+
+// the directories searched by `node-gyp-build` before the prebuilds
+const NODE_GYP_BUILD_DIRS: [&str; 2] = ["build/Release", "build/Debug"];
+
 pub struct Node {}
 
 impl Node {
@@ -69,6 +98,32 @@ impl Node {
         }
     }
 
+    /// Whether the package has the native addons, by the `gypfile` or
+    /// `binary` of its package.json, or its dependency on one of the loaders.
+    /// The package.json is read again since the dependencies are not kept in
+    /// the resolved ones.
+    pub fn is_native_addon_package(package_json: &Path) -> bool {
+        let Some(package_json) = std::fs::read_to_string(package_json)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        else {
+            return false;
+        };
+        if package_json.get("gypfile") == Some(&Value::Bool(true))
+            || package_json.get("binary").is_some()
+        {
+            return true;
+        }
+        ["dependencies", "optionalDependencies"]
+            .iter()
+            .any(|field| {
+                package_json
+                    .get(field)
+                    .and_then(Value::as_object)
+                    .is_some_and(|deps| NATIVE_ADDON_LOADERS.iter().any(|l| deps.contains_key(*l)))
+            })
+    }
+
     fn get_polyfill_modules() -> Vec<String> {
         vec![
             "assert",
@@ -176,3 +231,291 @@ impl VisitMut for MockFilenameAndDirname {
         expr.visit_mut_children_with(self);
     }
 }
+
+/// Rewrites the calls of the loaders of the native addons to the requires of
+/// the `.node` files they'd find at runtime, e.g.
+/// `require("bindings")("addon")` to `require("./build/Release/addon.node")`
+/// and `require("node-gyp-build")(__dirname)` to the addon of the build or
+/// the prebuild of the current platform, so that the addons are emitted with
+/// the chunks. It must run before `MockFilenameAndDirname`.
+pub struct NativeAddonLoaders {
+    pub unresolved_mark: Mark,
+    pub current_path: PathBuf,
+}
+
+impl NativeAddonLoaders {
+    fn find_addon(&self, loader: &str, call_expr: &CallExpr) -> Option<PathBuf> {
+        let dir = self.current_path.parent()?;
+        match loader {
+            "bindings" => {
+                let name = match call_expr.args.first() {
+                    Some(ExprOrSpread {
+                        expr: box Expr::Lit(Lit::Str(str)),
+                        ..
+                    }) => str.value.to_string(),
+                    // e.g. `bindings({ bindings: "addon" })`
+                    Some(ExprOrSpread {
+                        expr: box Expr::Object(object),
+                        ..
+                    }) => object.props.iter().find_map(|prop| match prop {
+                        PropOrSpread::Prop(box Prop::KeyValue(kv))
+                            if kv.key.as_ident().is_some_and(|key| key.sym == "bindings") =>
+                        {
+                            kv.value.as_lit().and_then(|lit| match lit {
+                                Lit::Str(str) => Some(str.value.to_string()),
+                                _ => None,
+                            })
+                        }
+                        _ => None,
+                    })?,
+                    None => "bindings".to_string(),
+                    _ => return None,
+                };
+                let name = if name.ends_with(".node") {
+                    name
+                } else {
+                    format!("{}.{}", name, NATIVE_ADDON_EXTENSION)
+                };
+                let root = dir
+                    .ancestors()
+                    .find(|dir| dir.join("package.json").is_file())?;
+                BINDINGS_DIRS
+                    .iter()
+                    .map(|sub| root.join(sub).join(&name))
+                    .find(|path| path.is_file())
+            }
+            _ => {
+                // only the addons next to the module, e.g. `load(__dirname)`
+                match call_expr.args.first() {
+                    Some(ExprOrSpread {
+                        expr: box Expr::Ident(ident),
+                        ..
+                    }) if is_ident_undefined(ident, "__dirname", &self.unresolved_mark) => {}
+                    _ => return None,
+                }
+                NODE_GYP_BUILD_DIRS
+                    .iter()
+                    .find_map(|sub| addons_in(&dir.join(sub)).into_iter().next())
+                    .or_else(|| {
+                        let prebuilds = dir.join("prebuilds").join(prebuild_platform());
+                        let addons = addons_in(&prebuilds);
+                        // the abi stable ones work with any version of node
+                        addons
+                            .iter()
+                            .find(|path| path.to_string_lossy().contains(".napi."))
+                            .or(addons.first())
+                            .cloned()
+                    })
+            }
+        }
+    }
+}
+
+impl VisitMut for NativeAddonLoaders {
+    fn visit_mut_expr(&mut self, expr: &mut Expr) {
+        if let Expr::Call(call_expr) = expr
+            && let Callee::Expr(box Expr::Call(loader)) = &call_expr.callee
+            && is_commonjs_require(loader, &self.unresolved_mark)
+            && let Some(source) = get_first_str_arg(loader)
+            && (source == "bindings" || source.starts_with("node-gyp-build"))
+        {
+            if let Some(addon) = self.find_addon(&source, call_expr) {
+                let dir = self.current_path.parent().unwrap();
+                let relative = diff_paths(&addon, dir).unwrap_or(addon);
+                let relative = relative.to_string_lossy().replace('\\', "/");
+                let relative = if relative.starts_with("../") {
+                    relative
+                } else {
+                    format!("./{}", relative)
+                };
+                *expr = quote_ident!(DUMMY_CTXT.apply_mark(self.unresolved_mark), "require")
+                    .as_call(DUMMY_SP, vec![quote_str!(relative).as_arg()]);
+            }
+            return;
+        }
+        expr.visit_mut_children_with(self);
+    }
+}
+
+// the `.node` files of the directory, sorted by their names
+fn addons_in(dir: &Path) -> Vec<PathBuf> {
+    let mut addons = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.is_file()
+                        && path
+                            .extension()
+                            .is_some_and(|ext| ext == NATIVE_ADDON_EXTENSION)
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    addons.sort();
+    addons
+}
+
+// the platform of the prebuilds, e.g. `linux-x64`, it's the one which builds,
+// the same as the one which runs the bundle
+fn prebuild_platform() -> String {
+    let platform = match std::env::consts::OS {
+        "macos" => "darwin",
+        "windows" => "win32",
+        os => os,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "x86" => "ia32",
+        "aarch64" => "arm64",
+        "powerpc64" => "ppc64",
+        arch => arch,
+    };
+    format!("{}-{}", platform, arch)
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_core::common::GLOBALS;
+    use swc_core::ecma::visit::VisitMutWith;
+
+    use super::{prebuild_platform, NativeAddonLoaders};
+    use crate::ast::tests::TestUtils;
+    use crate::compiler::{Args, Compiler};
+    use crate::config::Config;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_native_addons() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/native-addons");
+        let compile = |edit: &str| {
+            let config = Config::new(&root, None, Some(edit)).unwrap();
+            let compiler =
+                Compiler::new(config, root.clone(), Args { watch: false }, None).unwrap();
+            compiler.compile().unwrap();
+            compiler
+        };
+        let read_dir = |compiler: &Compiler| {
+            let mut files = std::fs::read_dir(&compiler.context.config.output.path)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+                .filter(|name| name.ends_with(".node"))
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        };
+
+        let compiler = compile("{}");
+        assert_eq!(
+            read_dir(&compiler),
+            vec![
+                "binding.b49daac0.node",
+                "direct.7caa701b.node",
+                "gyp_addon.f8466a35.node"
+            ]
+        );
+        // run.cjs reports the files loaded by process.dlopen
+        let output = std::process::Command::new("node")
+            .arg(root.join("run.cjs"))
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            r#"[{"name":"direct.7caa701b.node","exists":true},{"name":"gyp_addon.f8466a35.node","exists":true},{"name":"binding.b49daac0.node","exists":true}]"#
+        );
+        std::fs::remove_dir_all(&compiler.context.config.output.path).unwrap();
+
+        // the packages with the addons are required at runtime instead
+        let compiler = compile(r#"{"nativeAddons":"external"}"#);
+        assert_eq!(read_dir(&compiler), vec!["direct.7caa701b.node"]);
+        let index =
+            std::fs::read_to_string(compiler.context.config.output.path.join("index.js")).unwrap();
+        assert!(index.contains(r#"module.exports = require("gyp-addon");"#));
+        assert!(index.contains(r#"module.exports = require("bindings-addon");"#));
+        std::fs::remove_dir_all(&compiler.context.config.output.path).unwrap();
+    }
+
+    #[test]
+    fn test_native_addon_loaders() {
+        let root = TempDir::new("native-addon-loaders");
+        let prebuilds = root.join("prebuilds").join(prebuild_platform());
+        std::fs::create_dir_all(&prebuilds).unwrap();
+        std::fs::write(root.join("package.json"), "{}").unwrap();
+        std::fs::write(prebuilds.join("node.abi115.node"), "").unwrap();
+        std::fs::write(prebuilds.join("node.napi.node"), "").unwrap();
+        let current_path = root.join("index.js");
+
+        // the abi stable prebuild is preferred
+        assert_eq!(
+            run(
+                r#"module.exports = require("node-gyp-build")(__dirname);"#,
+                &current_path
+            ),
+            format!(
+                r#"module.exports = require("./prebuilds/{}/node.napi.node");"#,
+                prebuild_platform()
+            )
+        );
+        // the addons of the builds go before the prebuilds
+        std::fs::create_dir_all(root.join("build/Debug")).unwrap();
+        std::fs::write(root.join("build/Debug/addon.node"), "").unwrap();
+        assert_eq!(
+            run(
+                r#"module.exports = require("node-gyp-build")(__dirname);"#,
+                &current_path
+            ),
+            r#"module.exports = require("./build/Debug/addon.node");"#
+        );
+        assert_eq!(
+            run(
+                r#"module.exports = require("bindings")({ bindings: "addon" });"#,
+                &current_path
+            ),
+            r#"module.exports = require("./build/Debug/addon.node");"#
+        );
+        // the package root is found from the nested modules
+        assert_eq!(
+            run(
+                r#"module.exports = require("bindings")("addon.node");"#,
+                &root.join("lib/index.js")
+            ),
+            r#"module.exports = require("../build/Debug/addon.node");"#
+        );
+        // kept if the addon is not found
+        assert_eq!(
+            run(
+                r#"module.exports = require("bindings")("missing");"#,
+                &current_path
+            ),
+            r#"module.exports = require("bindings")("missing");"#
+        );
+        assert_eq!(
+            run(
+                r#"module.exports = require("node-gyp-build")(dir);"#,
+                &current_path
+            ),
+            r#"module.exports = require("node-gyp-build")(dir);"#
+        );
+    }
+
+    fn run(js_code: &str, current_path: &std::path::Path) -> String {
+        let mut test_utils = TestUtils::gen_js_ast(js_code);
+        let ast = test_utils.ast.js_mut();
+        GLOBALS.set(&test_utils.context.meta.script.globals, || {
+            let mut visitor = NativeAddonLoaders {
+                unresolved_mark: ast.unresolved_mark,
+                current_path: current_path.to_path_buf(),
+            };
+            ast.ast.visit_mut_with(&mut visitor);
+        });
+        test_utils.js_ast_to_code()
+    }
+}
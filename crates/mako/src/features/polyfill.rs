@@ -0,0 +1,181 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use semver::Version;
+use serde_json::Value;
+use swc_core::common::{Mark, DUMMY_SP};
+use swc_core::ecma::ast::{ImportDecl, Module, ModuleDecl, ModuleItem};
+use swc_core::ecma::preset_env::{Mode, Version as CoreJsVersion};
+use swc_core::ecma::utils::{quote_ident, quote_str, ExprFactory};
+
+use crate::ast::file::File;
+use crate::ast::DUMMY_CTXT;
+use crate::config::{PolyfillConfig, PolyfillMode};
+use crate::generate::analyze::package_name;
+
+// all the stable features, which are filtered by the targets
+const CORE_JS_ENTRY: &str = "core-js/stable";
+
+pub struct Polyfill {}
+
+impl Polyfill {
+    /// Resolve the version of core-js installed in the root or its ancestors,
+    /// e.g. the root of a monorepo.
+    pub fn resolve_version(config: &mut PolyfillConfig, root: &Path) -> Result<()> {
+        let version = root
+            .ancestors()
+            .find_map(|dir| {
+                let path = dir.join("node_modules/core-js/package.json");
+                let json: Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+                json.get("version")?.as_str().map(String::from)
+            })
+            .ok_or_else(|| anyhow!("polyfill requires core-js, install core-js@3 first"))?;
+        if Version::parse(&version).map_or(true, |v| v.major != 3) {
+            return Err(anyhow!(
+                "polyfill requires core-js@3, but core-js@{} is installed",
+                version
+            ));
+        }
+        config.version = version;
+        Ok(())
+    }
+
+    /// The polyfill config of the file, core-js itself is never polyfilled.
+    pub fn of<'a>(config: Option<&'a PolyfillConfig>, file: &File) -> Option<&'a PolyfillConfig> {
+        config.filter(|_| {
+            package_name(&file.pathname.to_string_lossy()).as_deref() != Some("core-js")
+        })
+    }
+
+    /// The mode and the core-js version of preset_env, the imports of core-js
+    /// entries are replaced with the modules which the targets lack in both
+    /// modes.
+    pub fn preset_env_options(config: Option<&PolyfillConfig>) -> (Mode, Option<CoreJsVersion>) {
+        match config {
+            Some(config) => (
+                match config.mode {
+                    PolyfillMode::Usage => Mode::Usage,
+                    PolyfillMode::Entry => Mode::Entry,
+                },
+                config.version.parse().ok(),
+            ),
+            None => (Mode::Entry, None),
+        }
+    }
+
+    /// Prepend the import of all the features to an entry, which preset_env
+    /// replaces with the modules of the targets.
+    pub fn prepend_entry_import(module: &mut Module) {
+        module.body.insert(
+            0,
+            ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+                span: DUMMY_SP,
+                specifiers: vec![],
+                src: Box::new(CORE_JS_ENTRY.into()),
+                type_only: false,
+                with: None,
+                phase: Default::default(),
+            })),
+        );
+    }
+
+    /// preset_env injects the polyfills as imports, which turn a commonjs
+    /// module into an es module, so they are required instead.
+    pub fn imports_to_requires(module: &mut Module, unresolved_mark: Mark) {
+        if module
+            .body
+            .iter()
+            .any(|item| matches!(item, ModuleItem::ModuleDecl(decl) if !is_polyfill_import(decl)))
+        {
+            return;
+        }
+        for item in module.body.iter_mut() {
+            let ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = item else {
+                continue;
+            };
+            let ctxt = DUMMY_CTXT.apply_mark(unresolved_mark);
+            let require = quote_ident!(ctxt, "require").as_call(
+                DUMMY_SP,
+                vec![quote_str!(import.src.value.clone()).as_arg()],
+            );
+            *item = ModuleItem::Stmt(require.into_stmt());
+        }
+    }
+}
+
+fn is_polyfill_import(decl: &ModuleDecl) -> bool {
+    match decl {
+        ModuleDecl::Import(import) => {
+            import.specifiers.is_empty()
+                && (import.src.value.starts_with("core-js/")
+                    || import.src.value == "regenerator-runtime/runtime.js")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::testing::compile_fixture;
+
+    // the core-js modules in the graph, with the file which imports them
+    fn compile_polyfills(config: &str) -> Vec<(String, String)> {
+        let compiler = compile_fixture(Path::new("test/build/polyfill"), Some(config)).unwrap();
+        let root = &compiler.context.root;
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let mut polyfills = vec![];
+        for module in module_graph.modules() {
+            for (id, _) in module_graph.get_dependencies(&module.id) {
+                if let Some((_, name)) = id.id.split_once("node_modules/core-js/modules/") {
+                    let importer = module.id.id.trim_start_matches(root.to_str().unwrap());
+                    polyfills.push((importer.to_string(), name.to_string()));
+                }
+            }
+        }
+        polyfills.sort();
+        polyfills
+    }
+
+    #[test]
+    fn test_polyfill_usage() {
+        let polyfills = compile_polyfills(r#"{ "targets": { "ios": 12 } }"#);
+        let has = |importer: &str, name: &str| {
+            polyfills.contains(&(importer.to_string(), name.to_string()))
+        };
+        // required by the commonjs module
+        assert!(has("/flat.js", "es.array.unscopables.flat.js"));
+        assert!(has("/index.js", "es.promise.all-settled.js"));
+        assert!(has("/index.js", "es.global-this.js"));
+        assert!(!has("/index.js", "es.array.unscopables.flat.js"));
+
+        let polyfills = compile_polyfills(r#"{ "targets": { "chrome": 120 } }"#);
+        assert_eq!(polyfills, vec![]);
+    }
+
+    #[test]
+    fn test_polyfill_entry() {
+        let legacy =
+            compile_polyfills(r#"{ "polyfill": { "mode": "entry" }, "targets": { "ios": 12 } }"#);
+        let modern = compile_polyfills(
+            r#"{ "polyfill": { "mode": "entry" }, "targets": { "chrome": 120 } }"#,
+        );
+        let names = |polyfills: &Vec<(String, String)>| {
+            assert!(polyfills
+                .iter()
+                .all(|(importer, _)| importer == "/index.js"));
+            polyfills
+                .iter()
+                .map(|(_, name)| name.clone())
+                .collect::<Vec<_>>()
+        };
+        let legacy = names(&legacy);
+        let modern = names(&modern);
+        assert!(legacy.contains(&"es.promise.all-settled.js".to_string()));
+        assert!(legacy.contains(&"es.global-this.js".to_string()));
+        assert!(!modern.contains(&"es.promise.all-settled.js".to_string()));
+        assert!(modern.len() < legacy.len());
+    }
+}
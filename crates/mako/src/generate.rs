@@ -1,19 +1,37 @@
 pub(crate) mod analyze;
+pub(crate) mod artifacts;
+pub(crate) mod async_global_side_effects;
 pub(crate) mod chunk;
 pub(crate) mod chunk_graph;
+pub(crate) mod chunk_hash_cache;
+pub(crate) mod chunk_names;
+pub(crate) mod chunk_placement;
 pub(crate) mod chunk_pot;
+pub(crate) mod concatenate_css;
+pub(crate) mod critical_css;
+pub(crate) mod csp_report;
+pub(crate) mod extract_css;
 pub(crate) mod generate_chunks;
+pub(crate) mod graph_export;
 pub(crate) mod group_chunk;
 pub(crate) mod hmr;
+pub(crate) mod html;
+pub(crate) mod inline_chunks;
+pub(crate) mod license_comments;
+pub(crate) mod mangle_exports;
 pub(crate) mod minify;
 pub(crate) mod optimize_chunk;
+pub(crate) mod precompress;
+pub(crate) mod pushable_chunks;
 pub(crate) mod runtime;
+pub(crate) mod source_map_cache;
 pub(crate) mod swc_helpers;
 pub(crate) mod transform;
-use std::collections::{HashMap, HashSet};
+pub(crate) mod webworker;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::ops::DerefMut;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -21,23 +39,31 @@ use analyze::Analyze;
 use anyhow::{anyhow, Result};
 use indexmap::IndexSet;
 use rayon::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use swc_core::base::sourcemap as swc_sourcemap;
 use tracing::debug;
 
+use crate::ast::sourcemap::split_index_map;
 use crate::compiler::{Compiler, Context};
-use crate::config::{DevtoolConfig, OutputMode, TreeShakingStrategy};
+use crate::config::{has_content_hash, DevtoolConfig, OutputMode, TreeShakingStrategy};
 use crate::dev::update::UpdateResult;
+use crate::generate::artifacts::{has_artifacts, GenerateArtifacts};
+use crate::generate::chunk_pot::sweep_render_caches;
 use crate::generate::generate_chunks::{ChunkFile, ChunkFileType};
+use crate::generate::graph_export::GraphExport;
+use crate::generate::pushable_chunks::pushable_chunk_files;
 use crate::module::{Dependency, ModuleId};
+use crate::plugin::PluginEmittedSourceMap;
 use crate::plugins::bundless_compiler::BundlessCompiler;
 use crate::stats::StatsJsonMap;
 use crate::utils::base64_encode;
+use crate::utils::output_fs::{MemoryFileSystem, OutputFileSystem};
 use crate::visitors::async_module::mark_async;
 
 #[derive(Serialize)]
 struct ChunksUrlMap {
-    js: HashMap<String, String>,
-    css: HashMap<String, String>,
+    js: BTreeMap<String, String>,
+    css: BTreeMap<String, String>,
 }
 
 impl Compiler {
@@ -68,7 +94,10 @@ impl Compiler {
     pub fn generate(&self) -> Result<StatsJsonMap> {
         debug!("generate");
         let t_generate = Instant::now();
+        self.context.stats_info.inline_code.clear_generated();
 
+        // before the modules are concatenated and removed from the graph
+        self.report_parse_fallbacks();
         if self
             .context
             .config
@@ -78,6 +107,18 @@ impl Compiler {
         {
             self.context.stats_info.parse_modules(self.context.clone());
         }
+        if self
+            .context
+            .config
+            .stats
+            .as_ref()
+            .is_some_and(|s| s.packages)
+        {
+            let module_graph = self.context.module_graph.read().unwrap();
+            self.context
+                .stats_info
+                .record_package_modules(&module_graph, &self.context);
+        }
 
         debug!("tree_shaking");
         let t_tree_shaking = Instant::now();
@@ -134,6 +175,24 @@ impl Compiler {
                 &self.context,
             )?;
         }
+        self.extract_css()?;
+        self.disambiguate_chunk_names();
+        // the chunks are final, and the modules are not transformed for them yet
+        self.check_async_global_side_effects();
+
+        // the export names are read by the importers in the other chunks, so
+        // they are renamed after the chunks are optimized and before the es
+        // modules are transformed to commonjs
+        if !self.context.args.watch
+            && self
+                .context
+                .config
+                .optimization
+                .as_ref()
+                .is_some_and(|o| o.mangle_exports.unwrap_or(false))
+        {
+            self.mangle_exports()?;
+        }
 
         // 为啥单独提前 transform modules？
         // 因为放 chunks 的循环里，一个 module 可能存在于多个 chunk 里，可能会被编译多遍
@@ -142,35 +201,54 @@ impl Compiler {
         self.transform_all(async_dep_map)?;
         let t_transform_modules = t_transform_modules.elapsed();
 
-        // ensure output dir exists
+        self.check_duplicated_modules();
+
         let config = &self.context.config;
-        if !config.output.path.exists() {
-            fs::create_dir_all(&config.output.path)?;
-        }
 
         let full_hash = self.full_hash();
-        let (t_generate_chunks, t_ast_to_code_and_write) = self.write_chunk_files(full_hash)?;
+        // the outputs are staged and written after the after_generate hook
+        let staged = MemoryFileSystem::new();
+        let output_fs: &dyn OutputFileSystem = if has_artifacts(&self.context) {
+            &staged
+        } else {
+            self.context.output_fs.as_ref()
+        };
+        let (chunk_files, t_generate_chunks, t_ast_to_code_and_write) =
+            self.write_chunk_files(full_hash, output_fs)?;
 
         // write assets
         if config.emit_assets {
             let t_write_assets = Instant::now();
             debug!("write assets");
             {
-                let assets_info = &(*self.context.assets_info.lock().unwrap());
-                for (k, v) in assets_info {
-                    let asset_path = &self.context.root.join(k);
-                    let asset_output_path = &config.output.path.join(v);
-                    if asset_path.exists() {
-                        fs::copy(asset_path, asset_output_path)?;
-                    } else {
-                        return Err(anyhow!("asset not found: {}", asset_path.display()));
-                    }
-                }
+                self.write_assets(output_fs)?;
             }
             let t_write_assets = t_write_assets.elapsed();
             debug!("  - write assets: {}ms", t_write_assets.as_millis());
         }
 
+        if config.critical_css.is_some() {
+            critical_css::record_critical_css(&chunk_files, &self.context);
+        }
+        let (artifacts, htmls) = if has_artifacts(&self.context) {
+            let mut artifacts = GenerateArtifacts::new(&staged, &self.context)?;
+            self.context
+                .plugin_driver
+                .after_generate(&mut artifacts, &self.context)?;
+            artifacts.emit(&self.context)?;
+            let htmls = artifacts.html.clone().into_iter().collect();
+            (Some(artifacts), htmls)
+        } else if html::has_html(&self.context) {
+            (None, html::write_html(&self.context, None)?)
+        } else {
+            (None, vec![])
+        };
+        // with the html which the plugins have changed
+        csp_report::write_csp_report(&htmls, &self.context)?;
+
+        self.precompress(&chunk_files, artifacts.as_ref())?;
+        self.after_emit(&chunk_files, artifacts.as_ref())?;
+
         // generate stats
         let stats = self.create_stats_info();
 
@@ -180,12 +258,34 @@ impl Compiler {
             .build_success(&stats, &self.context)?;
 
         // print stats
-        if !self.context.args.watch {
+        if !self.context.args.watch && !self.context.config.quiet {
             self.print_stats();
         }
 
         if self.context.config.analyze.is_some() {
-            Analyze::write_analyze(&stats, &self.context.config.output.path)?;
+            Analyze::write_analyze(&stats, &self.context)?;
+        }
+
+        if let Some(graph) = &self.context.config.graph {
+            GraphExport::write_graph(graph, &self.context)?;
+        }
+
+        {
+            let mut timings = self.context.stats_info.timings.lock().unwrap();
+            timings.tree_shaking = t_tree_shaking.as_millis() as u64;
+            timings.group_chunks = t_group_chunks.as_millis() as u64;
+            timings.optimize_chunks = t_optimize_chunks.as_millis() as u64;
+            timings.transform_modules = t_transform_modules.as_millis() as u64;
+            timings.generate_chunks = t_generate_chunks.as_millis() as u64;
+            timings.emit = t_ast_to_code_and_write.as_millis() as u64;
+            timings.source_maps = self
+                .context
+                .stats_info
+                .source_maps
+                .lock()
+                .unwrap()
+                .duration
+                .as_millis() as u64;
         }
 
         debug!("generate done in {}ms", t_generate.elapsed().as_millis());
@@ -205,11 +305,16 @@ impl Compiler {
         Ok(stats)
     }
 
-    fn write_chunk_files(&self, full_hash: u64) -> Result<(Duration, Duration)> {
+    fn write_chunk_files(
+        &self,
+        full_hash: u64,
+        output_fs: &dyn OutputFileSystem,
+    ) -> Result<(Vec<ChunkFile>, Duration, Duration)> {
         // generate chunks
         let t_generate_chunks = Instant::now();
         debug!("generate chunks");
-        let chunk_files = self.generate_chunk_files(full_hash)?;
+        let mut chunk_files = self.generate_chunk_files(full_hash)?;
+        inline_chunks::take_inlined_chunks(&mut chunk_files, &self.context);
         self.context
             .plugin_driver
             .after_generate_chunk_files(&chunk_files, &self.context)?;
@@ -219,19 +324,85 @@ impl Compiler {
         let t_ast_to_code_and_write = if self.context.args.watch {
             self.generate_chunk_mem_file(&chunk_files)?
         } else {
-            self.generate_chunk_disk_file(&chunk_files)?
+            self.generate_chunk_disk_file(&chunk_files, output_fs)?
         };
 
-        Ok((t_generate_chunks, t_ast_to_code_and_write))
+        Ok((chunk_files, t_generate_chunks, t_ast_to_code_and_write))
+    }
+
+    fn precompress(
+        &self,
+        chunk_files: &[ChunkFile],
+        artifacts: Option<&GenerateArtifacts>,
+    ) -> Result<()> {
+        if self.context.config.output.precompress.is_none() {
+            return Ok(());
+        }
+        let t_precompress = Instant::now();
+        let mut files = chunk_files
+            .iter()
+            .map(|file| file.disk_name())
+            .collect::<Vec<_>>();
+        if self.context.config.emit_assets {
+            files.extend(self.context.emitted_assets().into_keys());
+        }
+        if let Some(artifacts) = artifacts {
+            files = files.iter().map(|file| artifacts.file_name(file)).collect();
+        }
+        precompress::precompress_files(&files, &self.context)?;
+        debug!("  - precompress: {}ms", t_precompress.elapsed().as_millis());
+        Ok(())
+    }
+
+    fn after_emit(
+        &self,
+        chunk_files: &[ChunkFile],
+        artifacts: Option<&GenerateArtifacts>,
+    ) -> Result<()> {
+        if !matches!(self.context.config.devtool, Some(DevtoolConfig::SourceMap)) {
+            return Ok(());
+        }
+        let output_path = &self.context.config.output.path;
+        let file_name = |name: String| match artifacts {
+            Some(artifacts) => artifacts.file_name(&name),
+            None => name,
+        };
+        let source_maps = chunk_files
+            .iter()
+            .filter(|file| file.source_map.is_some())
+            .map(|file| PluginEmittedSourceMap {
+                asset_path: output_path.join(file_name(file.disk_name())),
+                source_map_path: output_path.join(file_name(file.source_map_disk_name())),
+                debug_id: self
+                    .context
+                    .config
+                    .output
+                    .debug_ids
+                    .then(|| file.debug_id()),
+            })
+            .collect::<Vec<_>>();
+        if source_maps.is_empty() {
+            return Ok(());
+        }
+        self.context
+            .plugin_driver
+            .after_emit(&source_maps, &self.context)
     }
 
-    fn generate_chunk_disk_file(&self, chunk_files: &Vec<ChunkFile>) -> Result<Duration> {
+    fn generate_chunk_disk_file(
+        &self,
+        chunk_files: &Vec<ChunkFile>,
+        output_fs: &dyn OutputFileSystem,
+    ) -> Result<Duration> {
         let t_ast_to_code_and_write = Instant::now();
         debug!("ast to code and write");
-        chunk_files.par_iter().try_for_each(|file| -> Result<()> {
-            self.emit_chunk_file(file);
-            Ok(())
-        })?;
+        chunk_files
+            .par_iter()
+            .try_for_each(|file| emit_chunk_file(&self.context, file, output_fs))?;
+        for file in pushable_chunk_files(chunk_files, &self.context) {
+            let to = self.context.config.output.path.join(file.name);
+            output_fs.write(&to, &file.content)?;
+        }
         let t_ast_to_code_and_write = t_ast_to_code_and_write.elapsed();
 
         Ok(t_ast_to_code_and_write)
@@ -246,13 +417,31 @@ impl Compiler {
             write_dev_chunk_file(&self.context, file)?;
             Ok(())
         })?;
+        // the chunks removed since the last build
+        let mut paths = chunk_files
+            .iter()
+            .flat_map(|file| {
+                std::iter::once(file.disk_name()).chain(
+                    file.source_map
+                        .as_ref()
+                        .map(|_| file.source_map_disk_name()),
+                )
+            })
+            .collect::<HashSet<_>>();
+        for file in pushable_chunk_files(chunk_files, &self.context) {
+            self.context
+                .write_static_content(&file.name, file.content, file.raw_hash)?;
+            paths.insert(file.name);
+        }
+        let stale = self.context.retain_static_content(&paths)?;
+        debug!("  - removed stale chunk files: {:?}", stale);
         let t_ast_to_code_and_write = t_ast_to_code_and_write.elapsed();
 
         Ok(t_ast_to_code_and_write)
     }
 
-    pub fn emit_chunk_file(&self, chunk_file: &ChunkFile) {
-        emit_chunk_file(&self.context, chunk_file);
+    pub fn emit_chunk_file(&self, chunk_file: &ChunkFile) -> Result<()> {
+        emit_chunk_file(&self.context, chunk_file, self.context.output_fs.as_ref())
     }
 
     pub fn emit_dev_chunks(
@@ -274,21 +463,32 @@ impl Compiler {
         {
             self.context.stats_info.parse_modules(self.context.clone());
         }
+        if self
+            .context
+            .config
+            .stats
+            .as_ref()
+            .is_some_and(|s| s.packages)
+        {
+            let module_graph = self.context.module_graph.read().unwrap();
+            self.context
+                .stats_info
+                .record_package_modules(&module_graph, &self.context);
+        }
 
-        // ensure output dir exists
         let config = &self.context.config;
-        if !config.output.path.exists() {
-            fs::create_dir_all(&config.output.path)?;
-        }
 
         // generate chunks
         let t_generate_chunks = Instant::now();
+        // cancelled before the chunks are written, so the dist is the one of
+        // the last emit
+        self.context.build_cancellation.check()?;
         let chunk_files = self.generate_chunk_files(current_hmr_hash)?;
 
         if config.hmr.is_some() {
             let mut chunk_id_url_map = ChunksUrlMap {
-                js: HashMap::new(),
-                css: HashMap::new(),
+                js: BTreeMap::new(),
+                css: BTreeMap::new(),
             };
 
             chunk_files.iter().for_each(|c| match c.file_type {
@@ -316,6 +516,7 @@ impl Compiler {
 
         let t_generate_chunks = t_generate_chunks.elapsed();
 
+        self.context.build_cancellation.check()?;
         // ast to code and sourcemap, then write
         debug!("ast to code and write");
         let t_ast_to_code_and_write = self.generate_chunk_mem_file(&chunk_files)?;
@@ -323,20 +524,15 @@ impl Compiler {
         // write assets
         let t_write_assets = Instant::now();
         debug!("write assets");
-        {
-            let assets_info = &(*self.context.assets_info.lock().unwrap());
-            for (k, v) in assets_info {
-                let asset_path = &self.context.root.join(k);
-                let asset_output_path = &config.output.path.join(v);
-                if asset_path.exists() {
-                    fs::copy(asset_path, asset_output_path)?;
-                } else {
-                    panic!("asset not found: {}", asset_path.display());
-                }
-            }
-        }
+        self.write_assets(self.context.output_fs.as_ref())?;
         let t_write_assets = t_write_assets.elapsed();
 
+        self.precompress(&chunk_files, None)?;
+        self.after_emit(&chunk_files, None)?;
+        // every chunk is rendered, so the entries not used are stale
+        sweep_render_caches();
+        self.context.source_map_cache.sweep();
+
         let stats = self.create_stats_info();
 
         let t_generate = t_generate.elapsed();
@@ -351,6 +547,14 @@ impl Compiler {
             t_ast_to_code_and_write.as_millis()
         );
         debug!("  - write assets: {}ms", t_write_assets.as_millis());
+        {
+            let source_maps = self.context.stats_info.source_maps.lock().unwrap();
+            debug!(
+                "  - source maps: {}ms, {} reused",
+                source_maps.duration.as_millis(),
+                source_maps.reused
+            );
+        }
 
         Ok(stats)
     }
@@ -378,6 +582,7 @@ impl Compiler {
 
         let t_optimize_chunks = Instant::now();
         self.optimize_hot_update_chunk(&group_result);
+        self.disambiguate_chunk_names();
         let t_optimize_chunks = t_optimize_chunks.elapsed();
 
         let t_transform_modules = Instant::now();
@@ -405,12 +610,6 @@ impl Compiler {
         }
 
         if self.context.config.hmr.is_some() {
-            // ensure output dir exists
-            let config = &self.context.config;
-            if !config.output.path.exists() {
-                fs::create_dir_all(&config.output.path).unwrap();
-            }
-
             let (current_chunks, modified_chunks) = {
                 let cg = self.context.chunk_graph.read().unwrap();
 
@@ -459,11 +658,19 @@ impl Compiler {
             }
             let t_generate_hmr_chunk = t_generate_hmr_chunk.elapsed();
 
+            let mut removed_modules = updated_modules
+                .removed
+                .iter()
+                .map(|module_id| module_id.generate(&self.context))
+                .collect::<Vec<_>>();
+            removed_modules.sort();
             self.write_to_dist(
                 format!("{}.hot-update.json", last_hmr_hash),
                 serde_json::to_string(&HotUpdateManifest {
                     removed_chunks,
                     modified_chunks,
+                    removed_modules,
+                    outdated_chunks: vec![],
                 })
                 .unwrap(),
             );
@@ -495,7 +702,30 @@ impl Compiler {
         content: C,
     ) {
         let to = self.context.config.output.path.join(filename);
-        std::fs::write(to, content).unwrap();
+        self.context.output_fs.write(&to, content.as_ref()).unwrap();
+    }
+
+    fn write_assets(&self, output_fs: &dyn OutputFileSystem) -> Result<()> {
+        let output = &self.context.config.output;
+        // the assets of preserveModules mirror the source tree
+        let content_hashed = !output.preserve_modules && has_content_hash(&output.asset_filename);
+        for (output_path, origin_path) in &self.context.emitted_assets() {
+            if content_hashed {
+                self.context
+                    .stats_info
+                    .add_content_hashed(output_path.clone());
+            }
+            let asset_path = &self.context.root.join(origin_path);
+            let asset_output_path = &self.context.config.output.path.join(output_path);
+            if asset_path.exists() {
+                let file_handles = &self.context.file_handles;
+                let content = file_handles.run(|| Ok(fs::read(asset_path)?))?;
+                file_handles.run(|| output_fs.write(asset_output_path, &content))?;
+            } else {
+                return Err(anyhow!("asset not found: {}", asset_path.display()));
+            }
+        }
+        Ok(())
     }
 }
 
@@ -518,8 +748,7 @@ fn write_dev_chunk_file(context: &Arc<Context>, chunk: &ChunkFile) -> Result<()>
             }
         };
 
-        let mut code = Vec::new();
-
+        let mut code = Vec::with_capacity(chunk.content.len() + source_map_url_line.len());
         code.extend_from_slice(&chunk.content);
         code.extend_from_slice(source_map_url_line.as_bytes());
 
@@ -543,20 +772,44 @@ fn write_dev_chunk_file(context: &Arc<Context>, chunk: &ChunkFile) -> Result<()>
     Ok(())
 }
 
-fn emit_chunk_file(context: &Arc<Context>, chunk_file: &ChunkFile) {
+fn emit_chunk_file(
+    context: &Arc<Context>,
+    chunk_file: &ChunkFile,
+    output_fs: &dyn OutputFileSystem,
+) -> Result<()> {
     crate::mako_profile_function!(&chunk_file.file_name);
 
     let dist_name = chunk_file.disk_name();
 
     let to: PathBuf = context.config.output.path.join(dist_name.as_str());
     let stats_info = &context.stats_info;
+    if chunk_file.is_content_hashed() {
+        stats_info.add_content_hashed(dist_name.clone());
+        stats_info.add_content_hashed(chunk_file.source_map_disk_name());
+    }
 
     match context.config.devtool {
+        // the lines after the content are written after it instead of
+        // copying the content, which may be big
         Some(DevtoolConfig::SourceMap) => {
-            let mut code = Vec::new();
-            code.extend_from_slice(&chunk_file.content);
+            let mut suffix = Vec::new();
 
             if let Some(source_map) = &chunk_file.source_map {
+                let debug_id = context
+                    .config
+                    .output
+                    .debug_ids
+                    .then(|| chunk_file.debug_id());
+                let source_map = if context.config.source_map.index_map {
+                    write_index_map(context, chunk_file, source_map, output_fs)
+                } else {
+                    source_map.clone()
+                };
+                let source_map = match &debug_id {
+                    Some(debug_id) => with_debug_id(&source_map, debug_id),
+                    None => source_map,
+                };
+                let source_map = &source_map;
                 let size = source_map.len() as u64;
 
                 let source_map_file_path = context
@@ -576,8 +829,15 @@ fn emit_chunk_file(context: &Arc<Context>, chunk_file: &ChunkFile) {
                     .output
                     .path
                     .join(chunk_file.source_map_disk_name());
-                write_to_file(to.to_str().unwrap(), source_map).unwrap();
-
+                output_fs.write(&to, source_map)?;
+
+                if let Some(debug_id) = &debug_id {
+                    let debug_id_line = match chunk_file.file_type {
+                        ChunkFileType::JS => format!("\n//# debugId={}", debug_id),
+                        ChunkFileType::Css => format!("\n/*# debugId={}*/", debug_id),
+                    };
+                    suffix.extend_from_slice(debug_id_line.as_bytes());
+                }
                 let source_map_url_line = match chunk_file.file_type {
                     ChunkFileType::JS => {
                         format!(
@@ -592,10 +852,10 @@ fn emit_chunk_file(context: &Arc<Context>, chunk_file: &ChunkFile) {
                         )
                     }
                 };
-                code.extend_from_slice(source_map_url_line.as_bytes());
+                suffix.extend_from_slice(source_map_url_line.as_bytes());
             }
 
-            let size = code.len() as u64;
+            let size = (chunk_file.content.len() + suffix.len()) as u64;
             stats_info.add_assets(
                 size,
                 chunk_file.file_name.clone(),
@@ -603,14 +863,13 @@ fn emit_chunk_file(context: &Arc<Context>, chunk_file: &ChunkFile) {
                 to.to_string_lossy().to_string(),
                 dist_name.clone(),
             );
-            write_to_file(to.to_str().unwrap(), &code).unwrap();
+            output_fs.write_parts(&to, &[&chunk_file.content, &suffix])?;
         }
         Some(DevtoolConfig::InlineSourceMap) => {
-            let mut code = Vec::new();
-            code.extend_from_slice(&chunk_file.content);
+            let mut suffix = Vec::new();
 
             if let Some(source_map) = &chunk_file.source_map {
-                code.extend_from_slice(
+                suffix.extend_from_slice(
                     format!(
                         "\n//# sourceMappingURL=data:application/json;charset=utf-8;base64,{}",
                         base64_encode(source_map)
@@ -619,7 +878,7 @@ fn emit_chunk_file(context: &Arc<Context>, chunk_file: &ChunkFile) {
                 );
             }
 
-            let size = code.len() as u64;
+            let size = (chunk_file.content.len() + suffix.len()) as u64;
             stats_info.add_assets(
                 size,
                 chunk_file.file_name.clone(),
@@ -627,7 +886,7 @@ fn emit_chunk_file(context: &Arc<Context>, chunk_file: &ChunkFile) {
                 to.to_string_lossy().to_string(),
                 dist_name.clone(),
             );
-            write_to_file(to.to_str().unwrap(), &code).unwrap();
+            output_fs.write_parts(&to, &[&chunk_file.content, &suffix])?;
         }
         None => {
             stats_info.add_assets(
@@ -635,20 +894,75 @@ fn emit_chunk_file(context: &Arc<Context>, chunk_file: &ChunkFile) {
                 chunk_file.file_name.clone(),
                 chunk_file.chunk_id.clone(),
                 to.to_string_lossy().to_string(),
-                dist_name,
+                dist_name.clone(),
             );
-            write_to_file(to.to_str().unwrap(), &chunk_file.content).unwrap();
+            output_fs.write(&to, &chunk_file.content)?;
         }
     }
+
+    if chunk_file.content.starts_with(b"#!") {
+        output_fs.set_executable(&to)?;
+    }
+
+    if context.config.analyze.is_some() {
+        let treemap_chunk = Analyze::treemap_chunk(chunk_file, dist_name, context)?;
+        stats_info.add_treemap_chunk(treemap_chunk);
+    }
+    if context
+        .config
+        .stats
+        .as_ref()
+        .is_some_and(|stats| stats.packages)
+    {
+        stats_info.add_module_sizes(&chunk_file.module_sizes);
+    }
+
+    Ok(())
+}
+
+// the source map with the `debug_id` field, or unchanged if it's not an object
+// split the map to an index map with sourceMap.indexMap, the shared sections
+// are written once for all the chunks
+fn write_index_map(
+    context: &Arc<Context>,
+    chunk_file: &ChunkFile,
+    source_map: &[u8],
+    output_fs: &dyn OutputFileSystem,
+) -> Vec<u8> {
+    let Ok(sm) = swc_sourcemap::SourceMap::from_slice(source_map) else {
+        return source_map.to_vec();
+    };
+    // the urls of the sections are relative to the index map
+    let source_map_disk_name = chunk_file.source_map_disk_name();
+    let depth = Path::new(&source_map_disk_name).components().count() - 1;
+    let (index_map, shared) = split_index_map(&sm, &"../".repeat(depth), context);
+    for (name, content) in shared {
+        let size = content.len() as u64;
+        if context.stats_info.add_shared_source_map(&name, size) {
+            let to = context.config.output.path.join(&name);
+            output_fs.write(&to, &content).unwrap();
+            // named by the content hashes
+            context.stats_info.add_content_hashed(name.clone());
+            context.stats_info.add_assets(
+                size,
+                name.clone(),
+                chunk_file.chunk_id.clone(),
+                to.to_string_lossy().to_string(),
+                name,
+            );
+        }
+    }
+    index_map
 }
 
-fn write_to_file(path: &str, content: &Vec<u8>) -> std::io::Result<()> {
-    if let Some(parent) = std::path::Path::new(path).parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent)?;
+fn with_debug_id(source_map: &[u8], debug_id: &str) -> Vec<u8> {
+    match serde_json::from_slice::<serde_json::Value>(source_map) {
+        Ok(serde_json::Value::Object(mut map)) => {
+            map.insert("debug_id".to_string(), debug_id.into());
+            serde_json::to_vec(&map).unwrap()
         }
+        _ => source_map.to_vec(),
     }
-    fs::write(path, content)
 }
 
 fn to_hot_update_chunk_name(chunk_name: &String, hash: u64) -> String {
@@ -662,14 +976,23 @@ fn to_hot_update_chunk_name(chunk_name: &String, hash: u64) -> String {
     }
 }
 
-#[derive(Serialize)]
-struct HotUpdateManifest {
-    #[serde(rename(serialize = "c"))]
-    modified_chunks: Vec<String>,
-
-    #[serde(rename(serialize = "r"))]
-    removed_chunks: Vec<String>,
-    // TODO
-    // #[serde(rename(serialize = "c"))]
-    // removed_modules: Vec<String>,
+/// The `{hash}.hot-update.json` of a hot update, which the dev server
+/// filters for each client by the chunks it has loaded.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct HotUpdateManifest {
+    #[serde(rename = "c")]
+    pub modified_chunks: Vec<String>,
+
+    #[serde(rename = "r")]
+    pub removed_chunks: Vec<String>,
+
+    // the ids of the modules removed from the graph, whose factories and
+    // instances are dropped by the runtime
+    #[serde(rename = "m")]
+    pub removed_modules: Vec<String>,
+
+    // the ids of the modified chunks which the client hasn't loaded, they
+    // are loaded fresh instead of being updated, only in the filtered ones
+    #[serde(rename = "o", default, skip_serializing_if = "Vec::is_empty")]
+    pub outdated_chunks: Vec<String>,
 }
@@ -1,17 +1,89 @@
-use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::sync::Arc;
 
 use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use indexmap::IndexMap;
+use sailfish::TemplateOnce;
+use tracing::warn;
 
-use crate::stats::StatsJsonMap;
+use crate::compiler::Context;
+use crate::generate::generate_chunks::ChunkFile;
+use crate::module::relative_to_root;
+use crate::stats::{StatsJsonMap, TreemapChunk, TreemapModule, TreemapPackage};
+use crate::utils::get_pkg_name;
+
+#[derive(TemplateOnce)]
+#[template(path = "analyze_report.stpl")]
+struct AnalyzeReportTemplate {
+    treemap_json: String,
+}
 
 pub struct Analyze {}
 
 impl Analyze {
-    pub fn write_analyze(stats: &StatsJsonMap, path: &Path) -> Result<()> {
-        let stats_json = serde_json::to_string_pretty(&stats).unwrap();
-        let html_str = format!(
-            r#"<!DOCTYPE html>
+    /// The chunk file in the report, with its modules grouped by package.
+    pub fn treemap_chunk(
+        chunk_file: &ChunkFile,
+        file: String,
+        context: &Arc<Context>,
+    ) -> Result<TreemapChunk> {
+        crate::mako_profile_function!(&file);
+
+        let mut packages: IndexMap<String, TreemapPackage> = IndexMap::new();
+        for (path, size) in &chunk_file.module_sizes {
+            let name = package_name(path)
+                .or_else(|| get_pkg_name(&context.root))
+                .unwrap_or_else(|| ".".to_string());
+            let package = packages
+                .entry(name.clone())
+                .or_insert_with(|| TreemapPackage {
+                    name,
+                    size: 0,
+                    modules: vec![],
+                });
+            package.size += size;
+            package.modules.push(TreemapModule {
+                path: relative_to_root(path, &context.root),
+                size: *size,
+            });
+        }
+        let mut packages = packages.into_values().collect::<Vec<_>>();
+        packages.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+        Ok(TreemapChunk {
+            chunk_id: chunk_file.chunk_id.clone(),
+            file,
+            size: chunk_file.content.len() as u64,
+            gzip_size: gzip_size(&chunk_file.content)?,
+            packages,
+        })
+    }
+
+    pub fn write_analyze(stats: &StatsJsonMap, context: &Arc<Context>) -> Result<()> {
+        // to keep the json from closing the script
+        let treemap_json = serde_json::to_string(&stats.treemap)?.replace("</", "<\\/");
+        let html = AnalyzeReportTemplate { treemap_json }.render_once()?;
+        let report_path = context.config.output.path.join("report.html");
+        context.output_fs.write(&report_path, html.as_bytes())?;
+        write_legacy_analyze(stats, context)?;
+        if !context.config.quiet {
+            println!(
+                "Analyze report generated at: {}",
+                report_path.to_string_lossy()
+            );
+        }
+        Ok(())
+    }
+}
+
+// the report of the client package, which is deprecated by report.html and
+// will be removed in the next major version
+fn write_legacy_analyze(stats: &StatsJsonMap, context: &Arc<Context>) -> Result<()> {
+    let stats_json = serde_json::to_string_pretty(&stats)?;
+    let html_str = format!(
+        r#"<!DOCTYPE html>
 <html>
   <head>
     <meta charset="UTF-8"/>
@@ -26,16 +98,114 @@ impl Analyze {
     <script>{}</script>
   </body>
 </html>"#,
-            include_str!("../../../../client/dist/index.css"),
-            stats_json,
-            include_str!("../../../../client/dist/index.js").replace("</script>", "<\\/script>")
+        include_str!("../../../../client/dist/index.css"),
+        stats_json,
+        include_str!("../../../../client/dist/index.js").replace("</script>", "<\\/script>")
+    );
+    let report_path = context.config.output.path.join("analyze-report.html");
+    context.output_fs.write(&report_path, html_str.as_bytes())?;
+    warn!(
+        "analyze-report.html is deprecated and will be removed in the next major version, use {} instead",
+        context.config.output.path.join("report.html").to_string_lossy()
+    );
+    Ok(())
+}
+
+// the package of a module under node_modules, e.g. `@scope/name`
+pub(crate) fn package_name(path: &str) -> Option<String> {
+    let path = path.replace('\\', "/");
+    let (_, rest) = path.rsplit_once("/node_modules/")?;
+    let mut segments = rest.split('/');
+    let first = segments.next()?;
+    if first.starts_with('@') {
+        Some(format!("{}/{}", first, segments.next()?))
+    } else {
+        Some(first.to_string())
+    }
+}
+
+pub(crate) fn gzip_size(content: &[u8]) -> Result<u64> {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(content)?;
+    Ok(encoder.finish()?.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::package_name;
+    use crate::testing::compile_fixture;
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(
+            package_name("/app/node_modules/react/index.js"),
+            Some("react".to_string())
         );
-        let report_path = path.join("analyze-report.html");
-        fs::write(&report_path, html_str).unwrap();
-        println!(
-            "Analyze report generated at: {}",
-            report_path.to_string_lossy()
+        assert_eq!(
+            package_name("/app/node_modules/a/node_modules/@scope/b/lib/c.js"),
+            Some("@scope/b".to_string())
         );
-        Ok(())
+        assert_eq!(package_name("/app/src/index.js"), None);
+    }
+
+    #[test]
+    fn test_treemap() {
+        let compiler = compile_fixture(
+            Path::new("test/build/analyze"),
+            Some(r#"{ "mode": "production", "hash": false }"#),
+        )
+        .unwrap();
+        let root = &compiler.context.root;
+        let stats = compiler.create_stats_info();
+        let context = &compiler.context;
+        let chunk = stats
+            .treemap
+            .iter()
+            .find(|chunk| chunk.file == "index.js")
+            .unwrap();
+        assert!(chunk.gzip_size > 0 && chunk.gzip_size < chunk.size);
+        let names = chunk
+            .packages
+            .iter()
+            .map(|package| package.name.as_str())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"foo"));
+        let app = chunk
+            .packages
+            .iter()
+            .find(|package| package.name != "foo")
+            .unwrap();
+        let module = app
+            .modules
+            .iter()
+            .find(|module| module.path == "./index.js")
+            .unwrap();
+        // the factory of the module, not the entries of the runtime keyed by
+        // the same id
+        assert!(module.size > 100);
+        let source_size = fs::metadata(root.join("index.js")).unwrap().len();
+        assert!(module.size < source_size);
+        let modules_size = chunk
+            .packages
+            .iter()
+            .map(|package| package.size)
+            .sum::<u64>();
+        assert!(modules_size < chunk.size);
+
+        let report = String::from_utf8(
+            context
+                .output_fs
+                .read(&context.config.output.path.join("report.html"))
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(report.contains(r#""path":"./node_modules/foo/index.js""#));
+        // the deprecated report is still emitted
+        assert!(context
+            .output_fs
+            .exists(&context.config.output.path.join("analyze-report.html")));
     }
 }
@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+use crate::compiler::Context;
+use crate::config::OutputMode;
+use crate::generate::html::{self, entrypoint_files};
+use crate::plugins::manifest::generate_manifest;
+use crate::utils::output_fs::{MemoryFileSystem, OutputFileSystem};
+
+/// The outputs of a build before they are written to `output.path`, which
+/// plugins change in `Plugin::after_generate`, e.g. to inject tags to the html
+/// of a framework.
+#[derive(Debug, Default)]
+pub struct GenerateArtifacts {
+    /// file name relative to `output.path` -> content, of the chunks, their
+    /// source maps and the assets
+    pub assets: BTreeMap<String, Vec<u8>>,
+    /// entry name -> files in load order
+    pub entrypoints: BTreeMap<String, Vec<String>>,
+    /// file name -> html, e.g. `index.html` with `criticalCss`
+    pub html: BTreeMap<String, String>,
+    /// with `manifest`
+    pub manifest: Option<GenerateManifest>,
+    // original file name -> renamed file name
+    renames: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub struct GenerateManifest {
+    /// relative to `output.path`
+    pub file_name: String,
+    pub content: serde_json::Value,
+}
+
+/// Whether the outputs of the generate are collected as artifacts before they
+/// are written, the chunks of the watch mode are served from the memory cache
+/// and the bundless outputs are written by module instead.
+pub(crate) fn has_artifacts(context: &Context) -> bool {
+    !context.args.watch && context.config.output.mode != OutputMode::Bundless
+}
+
+impl GenerateArtifacts {
+    /// Collect the files written to the staged file system, with the html and
+    /// the manifest which are rendered from the stats of them.
+    pub(crate) fn new(staged: &MemoryFileSystem, context: &Arc<Context>) -> Result<Self> {
+        let output_path = &context.config.output.path;
+        let mut assets = BTreeMap::new();
+        for path in staged.list(output_path)? {
+            let name = path
+                .strip_prefix(output_path)?
+                .to_string_lossy()
+                .replace('\\', "/");
+            assets.insert(name, staged.read(&path)?);
+        }
+        let html = if html::has_html(context) {
+            html::render_html(context, None)?.into_iter().collect()
+        } else {
+            BTreeMap::new()
+        };
+        let manifest = generate_manifest(context)?
+            .map(|(file_name, content)| GenerateManifest { file_name, content });
+        Ok(Self {
+            assets,
+            entrypoints: entrypoint_files(context).into_iter().collect(),
+            html,
+            manifest,
+            renames: HashMap::new(),
+        })
+    }
+
+    /// Rename an asset, the references to it in the entrypoints, the html and
+    /// the values of the manifest are renamed too. The references in the
+    /// contents of the chunks are not rewritten.
+    pub fn rename_asset(&mut self, from: &str, to: &str) -> Result<()> {
+        let content = self
+            .assets
+            .remove(from)
+            .ok_or_else(|| anyhow!("asset not found: {}", from))?;
+        self.assets.insert(to.to_string(), content);
+        for files in self.entrypoints.values_mut() {
+            for file in files.iter_mut().filter(|file| *file == from) {
+                *file = to.to_string();
+            }
+        }
+        // the src and href attributes, with or without the public path
+        let reference = Regex::new(&format!(r#"(["/]){}""#, regex::escape(from))).unwrap();
+        for html in self.html.values_mut() {
+            *html = reference
+                .replace_all(html, format!(r#"${{1}}{}""#, to))
+                .to_string();
+        }
+        if let Some(manifest) = &mut self.manifest {
+            rename_value(&mut manifest.content, from, to);
+        }
+        let original = self
+            .renames
+            .iter()
+            .find(|(_, renamed)| *renamed == from)
+            .map(|(original, _)| original.clone())
+            .unwrap_or_else(|| from.to_string());
+        self.renames.insert(original, to.to_string());
+        Ok(())
+    }
+
+    /// The file name of an asset after the renames.
+    pub(crate) fn file_name(&self, name: &str) -> String {
+        self.renames
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Write the artifacts to the output file system, and record the renamed
+    /// assets in the stats.
+    pub(crate) fn emit(&self, context: &Arc<Context>) -> Result<()> {
+        let output_path = &context.config.output.path;
+        for (name, content) in &self.assets {
+            let to = output_path.join(name);
+            context
+                .file_handles
+                .run(|| context.output_fs.write(&to, content))?;
+            if content.starts_with(b"#!") {
+                context.output_fs.set_executable(&to)?;
+            }
+        }
+        for (name, html) in &self.html {
+            context
+                .output_fs
+                .write(&output_path.join(name), html.as_bytes())?;
+        }
+        if let Some(manifest) = &self.manifest {
+            let content = serde_json::to_string_pretty(&manifest.content)?;
+            context
+                .output_fs
+                .write(&output_path.join(&manifest.file_name), content.as_bytes())?;
+        }
+        for (from, to) in &self.renames {
+            context.stats_info.rename_asset(
+                from,
+                to.clone(),
+                output_path.join(to).to_string_lossy().to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+fn rename_value(value: &mut serde_json::Value, from: &str, to: &str) {
+    match value {
+        serde_json::Value::String(s) if s == from => *s = to.to_string(),
+        serde_json::Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| rename_value(value, from, to)),
+        serde_json::Value::Object(map) => map
+            .values_mut()
+            .for_each(|value| rename_value(value, from, to)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use anyhow::Result;
+
+    use super::GenerateArtifacts;
+    use crate::compiler::Context;
+    use crate::generate::html::inject;
+    use crate::plugin::Plugin;
+    use crate::utils::test_helper::setup_compiler_with_plugins;
+
+    struct FrameworkPlugin {}
+
+    impl Plugin for FrameworkPlugin {
+        fn name(&self) -> &str {
+            "framework"
+        }
+
+        fn after_generate(
+            &self,
+            artifacts: &mut GenerateArtifacts,
+            _context: &Arc<Context>,
+        ) -> Result<()> {
+            for html in artifacts.html.values_mut() {
+                *html = inject(
+                    html,
+                    "</head>",
+                    &["<script>window.__SSR__ = true;</script>".to_string()],
+                );
+            }
+            if let Some(manifest) = &mut artifacts.manifest {
+                manifest.file_name = "framework-manifest.json".to_string();
+            }
+            artifacts.rename_asset("index.js", "main.js")?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_after_generate() {
+        let compiler = setup_compiler_with_plugins(
+            "test/build/after-generate",
+            vec![Arc::new(FrameworkPlugin {})],
+        );
+        compiler.compile().unwrap();
+        let context = &compiler.context;
+        let output_path = &context.config.output.path;
+        let read = |name: &str| {
+            String::from_utf8(context.output_fs.read(&output_path.join(name)).unwrap()).unwrap()
+        };
+
+        let html = read("index.html");
+        assert!(
+            html.contains("<script>window.__SSR__ = true;</script>\n</head>"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains(r#"<script src="/main.js"></script>"#),
+            "{}",
+            html
+        );
+        assert!(!html.contains("index.js"), "{}", html);
+
+        assert!(!context
+            .output_fs
+            .exists(&output_path.join("asset-manifest.json")));
+        let manifest: serde_json::Value =
+            serde_json::from_str(&read("framework-manifest.json")).unwrap();
+        assert_eq!(manifest["index.js"], "main.js");
+        assert_eq!(
+            manifest["entrypoints"]["index"],
+            serde_json::json!(["index.css", "main.js"])
+        );
+
+        assert!(read("main.js").contains("after generate"));
+        assert!(!context.output_fs.exists(&output_path.join("index.js")));
+        assert!(context
+            .stats_info
+            .get_assets()
+            .iter()
+            .any(|asset| asset.hashname == "main.js"));
+    }
+}
@@ -0,0 +1,643 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use swc_core::common::{Mark, Span, GLOBALS};
+use swc_core::ecma::ast::{
+    ArrowExpr, AssignExpr, AssignOp, AssignTarget, BinExpr, BinaryOp, CallExpr, Callee, ClassProp,
+    ComputedPropName, CondExpr, Constructor, Expr, Function, GetterProp, IdentName, IfStmt, Lit,
+    MemberExpr, MemberProp, Module, ObjectLit, PrivateProp, Prop, PropName, PropOrSpread,
+    SetterProp, SimpleAssignTarget, UnaryExpr, UnaryOp, UpdateExpr,
+};
+use swc_core::ecma::visit::{Visit, VisitWith};
+
+use crate::ast::utils::is_ident_undefined;
+use crate::compiler::Compiler;
+use crate::config::matched_ignore_rule;
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::generate::chunk::ChunkType;
+use crate::module::{ModuleAst, ModuleId};
+
+// the names of the global object
+const GLOBAL_OBJECTS: [&str; 4] = ["window", "globalThis", "self", "global"];
+// the global registries which are written by their methods
+const GLOBAL_REGISTRIES: [&str; 1] = ["customElements"];
+
+/// A write to a property of the global object when a module is executed,
+/// e.g. `window.__REGISTRY__.button = Button`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobalWrite {
+    /// the path of the property from the global object, e.g.
+    /// `__REGISTRY__.button`, `*` for the computed keys
+    pub property: String,
+    pub span: Span,
+}
+
+impl GlobalWrite {
+    // by the property or the properties it's in
+    fn is_allowed(&self, allow_properties: &[String]) -> bool {
+        allow_properties.iter().any(|allowed| {
+            self.property == *allowed
+                || self
+                    .property
+                    .strip_prefix(allowed.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+}
+
+/// The writes to the global properties in the top level statements of a
+/// module, and in the functions called at once. The writes guarded by a test
+/// of the same property are the feature detections, e.g. the polyfills of
+/// `if (!window.fetch) window.fetch = ...` and the registries initialized by
+/// `window.__REGISTRY__ = window.__REGISTRY__ || {}`, which are skipped.
+pub fn global_writes(module: &Module, unresolved_mark: Mark) -> Vec<GlobalWrite> {
+    let mut visitor = GlobalWrites {
+        unresolved_mark,
+        guards: vec![],
+        writes: vec![],
+    };
+    module.visit_with(&mut visitor);
+    visitor.writes
+}
+
+struct GlobalWrites {
+    unresolved_mark: Mark,
+    // the properties tested by the enclosing conditions
+    guards: Vec<BTreeSet<String>>,
+    writes: Vec<GlobalWrite>,
+}
+
+impl GlobalWrites {
+    // the path of the property from the global object, e.g. `a.b` of
+    // `window.a.b` or `window["a"].b`, and an empty path of the global object
+    fn path(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Ident(ident) => {
+                if GLOBAL_OBJECTS
+                    .iter()
+                    .any(|name| is_ident_undefined(ident, name, &self.unresolved_mark))
+                {
+                    Some("".to_string())
+                } else {
+                    GLOBAL_REGISTRIES
+                        .iter()
+                        .find(|name| is_ident_undefined(ident, name, &self.unresolved_mark))
+                        .map(|name| name.to_string())
+                }
+            }
+            Expr::Member(member) => self.member_path(member),
+            Expr::Paren(paren) => self.path(&paren.expr),
+            _ => None,
+        }
+    }
+
+    fn member_path(&self, member: &MemberExpr) -> Option<String> {
+        let name = match &member.prop {
+            MemberProp::Ident(IdentName { sym, .. }) => sym.to_string(),
+            MemberProp::Computed(ComputedPropName {
+                expr: box Expr::Lit(Lit::Str(str)),
+                ..
+            }) => str.value.to_string(),
+            MemberProp::Computed(_) => "*".to_string(),
+            MemberProp::PrivateName(_) => return None,
+        };
+        self.path(&member.obj).map(|path| join(&path, &name))
+    }
+
+    // the properties tested by a condition, e.g. `a` and `a.b` of
+    // `window.a && window.a.b`, `a` of `"a" in window`
+    fn tested(&self, expr: &Expr) -> BTreeSet<String> {
+        let mut tested = Tested {
+            visitor: self,
+            properties: BTreeSet::new(),
+        };
+        expr.visit_with(&mut tested);
+        tested.properties
+    }
+
+    fn is_guarded(&self, property: &str) -> bool {
+        self.guards.iter().any(|guard| guard.contains(property))
+    }
+
+    fn write(&mut self, property: String, span: Span) {
+        if !property.is_empty() && !self.is_guarded(&property) {
+            self.writes.push(GlobalWrite { property, span });
+        }
+    }
+
+    fn with_guard(&mut self, guard: BTreeSet<String>, f: impl FnOnce(&mut Self)) {
+        self.guards.push(guard);
+        f(self);
+        self.guards.pop();
+    }
+
+    // the keys of `Object.assign(window, { a, b })`, or the target itself
+    fn write_object(&mut self, path: &str, object: Option<&Expr>, span: Span) {
+        let keys = match object {
+            Some(Expr::Object(ObjectLit { props, .. })) => props
+                .iter()
+                .map(|prop| match prop {
+                    PropOrSpread::Prop(box Prop::Shorthand(ident)) => ident.sym.to_string(),
+                    PropOrSpread::Prop(box Prop::KeyValue(kv)) => prop_name(&kv.key),
+                    PropOrSpread::Prop(box Prop::Method(method)) => prop_name(&method.key),
+                    PropOrSpread::Prop(box Prop::Getter(getter)) => prop_name(&getter.key),
+                    PropOrSpread::Prop(box Prop::Setter(setter)) => prop_name(&setter.key),
+                    _ => "*".to_string(),
+                })
+                .collect::<Vec<_>>(),
+            _ => vec![],
+        };
+        if keys.is_empty() {
+            let property = if path.is_empty() { "*" } else { path };
+            self.write(property.to_string(), span);
+        } else {
+            for key in keys {
+                self.write(join(path, &key), span);
+            }
+        }
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn prop_name(key: &PropName) -> String {
+    match key {
+        PropName::Ident(ident) => ident.sym.to_string(),
+        PropName::Str(str) => str.value.to_string(),
+        _ => "*".to_string(),
+    }
+}
+
+fn str_arg(call: &CallExpr, index: usize) -> Option<String> {
+    match call.args.get(index) {
+        Some(arg) => match &*arg.expr {
+            Expr::Lit(Lit::Str(str)) => Some(str.value.to_string()),
+            _ => Some("*".to_string()),
+        },
+        None => None,
+    }
+}
+
+// the callee of a method call, e.g. `Object` and `assign` of `Object.assign()`
+fn method(call: &CallExpr) -> Option<(&Expr, &str)> {
+    match &call.callee {
+        Callee::Expr(box Expr::Member(MemberExpr {
+            obj,
+            prop: MemberProp::Ident(IdentName { sym, .. }),
+            ..
+        })) => Some((obj, sym.as_ref())),
+        _ => None,
+    }
+}
+
+// `(function () {})()`, `(() => {})()` and `(function () {}).call(this)`
+fn iife_body(call: &CallExpr) -> Option<IifeBody<'_>> {
+    let callee = match &call.callee {
+        Callee::Expr(callee) => callee,
+        _ => return None,
+    };
+    let callee = match method(call) {
+        Some((obj, "call" | "apply")) => obj,
+        _ => callee,
+    };
+    match callee.unwrap_parens() {
+        Expr::Fn(fn_expr) => Some(IifeBody::Function(&fn_expr.function)),
+        Expr::Arrow(arrow) => Some(IifeBody::Arrow(arrow)),
+        _ => None,
+    }
+}
+
+enum IifeBody<'a> {
+    Function(&'a Function),
+    Arrow(&'a ArrowExpr),
+}
+
+impl Visit for GlobalWrites {
+    // not executed when the module is executed
+    fn visit_function(&mut self, _: &Function) {}
+    fn visit_arrow_expr(&mut self, _: &ArrowExpr) {}
+    fn visit_constructor(&mut self, _: &Constructor) {}
+    fn visit_getter_prop(&mut self, _: &GetterProp) {}
+    fn visit_setter_prop(&mut self, _: &SetterProp) {}
+    fn visit_class_prop(&mut self, _: &ClassProp) {}
+    fn visit_private_prop(&mut self, _: &PrivateProp) {}
+
+    fn visit_assign_expr(&mut self, n: &AssignExpr) {
+        if let AssignTarget::Simple(SimpleAssignTarget::Member(member)) = &n.left
+            && let Some(property) = self.member_path(member)
+        {
+            // initialized only if it's not, e.g. `window.a = window.a || {}`
+            let is_init = match (n.op, n.right.unwrap_parens()) {
+                (AssignOp::OrAssign | AssignOp::NullishAssign | AssignOp::AndAssign, _) => true,
+                (
+                    AssignOp::Assign,
+                    Expr::Bin(BinExpr {
+                        op: BinaryOp::LogicalOr | BinaryOp::NullishCoalescing,
+                        left,
+                        ..
+                    }),
+                ) => self.tested(left).contains(&property),
+                (AssignOp::Assign, Expr::Cond(CondExpr { test, .. })) => {
+                    self.tested(test).contains(&property)
+                }
+                _ => false,
+            };
+            if !is_init {
+                self.write(property, n.span);
+            }
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_update_expr(&mut self, n: &UpdateExpr) {
+        if let Some(property) = self.path(&n.arg) {
+            self.write(property, n.span);
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_unary_expr(&mut self, n: &UnaryExpr) {
+        if n.op == UnaryOp::Delete
+            && let Some(property) = self.path(&n.arg)
+        {
+            self.write(property, n.span);
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, n: &CallExpr) {
+        if let Some(body) = iife_body(n) {
+            match body {
+                IifeBody::Function(function) => function.body.visit_with(self),
+                IifeBody::Arrow(arrow) => arrow.body.visit_with(self),
+            }
+        }
+        if let Some((obj, method)) = method(n) {
+            let is_object = matches!(obj, Expr::Ident(ident) if is_ident_undefined(ident, "Object", &self.unresolved_mark));
+            let target = n.args.first().and_then(|arg| self.path(&arg.expr));
+            match (is_object, method, target) {
+                (true, "assign" | "defineProperties", Some(target)) => {
+                    let object = n.args.get(1).map(|arg| &*arg.expr);
+                    self.write_object(&target, object, n.span);
+                }
+                (true, "defineProperty", Some(target)) => {
+                    let key = str_arg(n, 1).unwrap_or_else(|| "*".to_string());
+                    self.write(join(&target, &key), n.span);
+                }
+                // e.g. `customElements.define("x-button", Button)`
+                (false, "define", _) => {
+                    if let Some(registry) = self.path(obj)
+                        && GLOBAL_REGISTRIES.contains(&registry.as_str())
+                    {
+                        let name = str_arg(n, 0).unwrap_or_else(|| "*".to_string());
+                        self.write(join(&registry, &name), n.span);
+                    }
+                }
+                _ => {}
+            }
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_if_stmt(&mut self, n: &IfStmt) {
+        n.test.visit_with(self);
+        let guard = self.tested(&n.test);
+        self.with_guard(guard, |this| {
+            n.cons.visit_with(this);
+            n.alt.visit_with(this);
+        });
+    }
+
+    fn visit_cond_expr(&mut self, n: &CondExpr) {
+        n.test.visit_with(self);
+        let guard = self.tested(&n.test);
+        self.with_guard(guard, |this| {
+            n.cons.visit_with(this);
+            n.alt.visit_with(this);
+        });
+    }
+
+    fn visit_bin_expr(&mut self, n: &BinExpr) {
+        if matches!(
+            n.op,
+            BinaryOp::LogicalAnd | BinaryOp::LogicalOr | BinaryOp::NullishCoalescing
+        ) {
+            n.left.visit_with(self);
+            let guard = self.tested(&n.left);
+            self.with_guard(guard, |this| n.right.visit_with(this));
+        } else {
+            n.visit_children_with(self);
+        }
+    }
+}
+
+struct Tested<'a> {
+    visitor: &'a GlobalWrites,
+    properties: BTreeSet<String>,
+}
+
+impl Tested<'_> {
+    fn insert(&mut self, property: String) {
+        if !property.is_empty() {
+            self.properties.insert(property);
+        }
+    }
+}
+
+impl Visit for Tested<'_> {
+    fn visit_expr(&mut self, n: &Expr) {
+        if let Some(property) = self.visitor.path(n) {
+            self.insert(property);
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_bin_expr(&mut self, n: &BinExpr) {
+        // `"a" in window`
+        if n.op == BinaryOp::In
+            && let Expr::Lit(Lit::Str(key)) = &*n.left
+            && let Some(path) = self.visitor.path(&n.right)
+        {
+            self.insert(join(&path, &key.value));
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, n: &CallExpr) {
+        // `customElements.get("x-button")`, `window.hasOwnProperty("a")`
+        if let Some((obj, "get" | "has" | "hasOwnProperty")) = method(n)
+            && let Some(path) = self.visitor.path(obj)
+            && let Some(key) = str_arg(n, 0)
+        {
+            self.insert(join(&path, &key));
+        }
+        n.visit_children_with(self);
+    }
+}
+
+impl Compiler {
+    /// Warns of the writes to the global properties by the modules which are
+    /// only in the async chunks, with `asyncGlobalSideEffects`, which happen
+    /// only after the chunks are loaded, so whether they happen depends on
+    /// the route visited first.
+    pub(crate) fn check_async_global_side_effects(&self) {
+        let Some(config) = &self.context.config.async_global_side_effects else {
+            return;
+        };
+        let mut diagnostics = vec![];
+        {
+            let chunk_graph = self.context.chunk_graph.read().unwrap();
+            let module_graph = self.context.module_graph.read().unwrap();
+            // the chunks loaded on the start, the entries with their shared chunks
+            let eager_chunks = chunk_graph
+                .entrypoints()
+                .into_iter()
+                .flat_map(|entrypoint| entrypoint.chunks)
+                .collect::<HashSet<_>>();
+            let mut eager_modules = HashSet::new();
+            // module id -> the file names of the async chunks
+            let mut async_modules: BTreeMap<&ModuleId, Vec<String>> = BTreeMap::new();
+            for chunk in chunk_graph.get_chunks() {
+                match chunk.chunk_type {
+                    // the workers have their own globals
+                    ChunkType::Worker(_) => {}
+                    ChunkType::Runtime | ChunkType::Entry(_, _, false) => {
+                        eager_modules.extend(chunk.get_modules());
+                    }
+                    _ if eager_chunks.contains(&chunk.id) => {
+                        eager_modules.extend(chunk.get_modules());
+                    }
+                    _ => {
+                        for module_id in chunk.get_modules() {
+                            async_modules
+                                .entry(module_id)
+                                .or_default()
+                                .push(chunk.filename());
+                        }
+                    }
+                }
+            }
+            let cm = &self.context.meta.script.cm;
+            for (module_id, mut chunks) in async_modules {
+                if eager_modules.contains(module_id) {
+                    continue;
+                }
+                let Some(info) = module_graph
+                    .get_module(module_id)
+                    .and_then(|module| module.info.as_ref())
+                else {
+                    continue;
+                };
+                let ModuleAst::Script(ast) = &info.ast else {
+                    continue;
+                };
+                let path = info.file.pathname.clone();
+                if matched_ignore_rule(&config.allow_modules, &self.context.root, &path).is_some() {
+                    continue;
+                }
+                chunks.sort();
+                let chunks = chunks
+                    .iter()
+                    .map(|chunk| format!("`{}`", chunk))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let writes = GLOBALS.set(&self.context.meta.script.globals, || {
+                    global_writes(&ast.ast, ast.unresolved_mark)
+                });
+                for write in writes {
+                    if write.is_allowed(&config.allow_properties) {
+                        continue;
+                    }
+                    let line = cm.lookup_char_pos(write.span.lo).line;
+                    let diagnostic = Diagnostic::new(
+                        DiagnosticCode::AsyncGlobalSideEffect,
+                        format!(
+                            "{}:{} writes the global `{}` when it's executed, which is only after the async chunk {} is loaded, so it depends on the route visited first, add it to `asyncGlobalSideEffects.allowProperties` if it's intended",
+                            module_id.relative_display(&self.context.root),
+                            line,
+                            write.property,
+                            chunks
+                        ),
+                    )
+                    .paths(vec![path.clone()])
+                    .locations(vec![(path.clone(), line)]);
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+        for diagnostic in diagnostics {
+            self.context.diagnostics.report(diagnostic, &self.context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_core::common::GLOBALS;
+
+    use super::global_writes;
+    use crate::ast::tests::TestUtils;
+    use crate::diagnostics::{DiagnosticCode, Severity};
+    use crate::testing::build_fixture;
+
+    fn writes(code: &str) -> Vec<String> {
+        let test_utils = TestUtils::gen_js_ast(code);
+        let ast = test_utils.ast.js();
+        GLOBALS.set(&test_utils.context.meta.script.globals, || {
+            global_writes(&ast.ast, ast.unresolved_mark)
+                .into_iter()
+                .map(|write| write.property)
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_global_writes() {
+        assert_eq!(writes("window.a = 1;"), vec!["a"]);
+        assert_eq!(writes("globalThis.a.b = 1;"), vec!["a.b"]);
+        assert_eq!(writes("self['a'].b += 1;"), vec!["a.b"]);
+        assert_eq!(writes("window[key] = 1;"), vec!["*"]);
+        assert_eq!(
+            writes("window.count++; delete window.b;"),
+            vec!["count", "b"]
+        );
+        assert_eq!(
+            writes("Object.assign(window, { a, b: 1 }); Object.assign(window.c, d);"),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(writes("Object.defineProperty(window, 'a', {});"), vec!["a"]);
+        assert_eq!(
+            writes("customElements.define('x-a', A); window.customElements.define('x-b', B);"),
+            vec!["customElements.x-a", "customElements.x-b"]
+        );
+        // the registrations after the registry is initialized
+        assert_eq!(
+            writes("window.r = window.r || {}; window.r.button = Button;"),
+            vec!["r.button"]
+        );
+        // in the blocks and the functions called at once
+        assert_eq!(
+            writes("if (isBrowser) { window.a = 1; } (function () { window.b = 1; })(); (() => { window.c = 1; })(); (function () { window.d = 1; }).call(this);"),
+            vec!["a", "b", "c", "d"]
+        );
+    }
+
+    #[test]
+    fn test_global_writes_skipped() {
+        // not executed with the module
+        assert!(writes("function f() { window.a = 1; }").is_empty());
+        assert!(writes("const f = () => { window.a = 1; };").is_empty());
+        assert!(writes("class A { constructor() { window.a = 1; } b = window.b = 1; }").is_empty());
+        assert!(writes("export default { get a() { return window.a = 1; } };").is_empty());
+        // the reads and the feature detections
+        assert!(writes("const isBrowser = typeof window !== 'undefined' && window.a;").is_empty());
+        assert!(writes("if (!window.fetch) window.fetch = fetch;").is_empty());
+        assert!(writes("if (!('fetch' in window)) { window.fetch = fetch; }").is_empty());
+        assert!(writes("window.fetch || (window.fetch = fetch);").is_empty());
+        assert!(writes("window.a = window.a || {}; window.b = window.b ?? {};").is_empty());
+        assert!(writes("window.a ||= {}; window.b ??= {};").is_empty());
+        assert!(writes("window.a = typeof window.a === 'undefined' ? {} : window.a;").is_empty());
+        assert!(
+            writes("if (!customElements.get('x-a')) customElements.define('x-a', A);").is_empty()
+        );
+        // the locals of the same names
+        assert!(writes("const window = {}; window.a = 1;").is_empty());
+        assert!(writes("function f(self) { self.a = 1; } f({});").is_empty());
+        assert!(writes("other.a = 1; module.exports = {}; exports.a = 1;").is_empty());
+        // the registry is tested, but not the registration
+        assert_eq!(
+            writes("if (window.r) { window.r.button = Button; }"),
+            vec!["r.button"]
+        );
+        assert_eq!(
+            writes("typeof window !== 'undefined' && (window.a = 1);"),
+            vec!["a"]
+        );
+    }
+
+    fn build(config: &str) -> Vec<String> {
+        let artifacts = build_fixture(
+            Some(config),
+            &[
+                (
+                    "src/index.js",
+                    "import './shared';\nimport('./lazy');\nimport('./polyfill');\nwindow.eager = 1;",
+                ),
+                (
+                    "src/lazy.js",
+                    "import './shared';\nimport './registry';\nwindow.__REGISTRY__ = window.__REGISTRY__ || {};\nwindow.__REGISTRY__.button = 'button';",
+                ),
+                ("src/registry.js", "window.analytics = {};"),
+                ("src/shared.js", "window.shared = 1;"),
+                (
+                    "src/polyfill.js",
+                    "if (!window.fetch) window.fetch = function () {};",
+                ),
+            ],
+        )
+        .unwrap();
+        artifacts
+            .diagnostics
+            .iter()
+            .filter(|d| d.code == DiagnosticCode::AsyncGlobalSideEffect)
+            .map(|d| {
+                let (path, line) = &d.locations[0];
+                format!("{}:{}", path.display(), line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_async_global_side_effects() {
+        assert_eq!(
+            build(r#"{"asyncGlobalSideEffects":{}}"#),
+            vec!["src/lazy.js:4", "src/registry.js:1"]
+        );
+        assert_eq!(
+            build(
+                r#"{"asyncGlobalSideEffects":{"allowProperties":["__REGISTRY__"],"allowModules":["src/registry.js"]}}"#
+            ),
+            Vec::<String>::new()
+        );
+        assert!(build("{}").is_empty());
+    }
+
+    #[test]
+    fn test_async_global_side_effects_suppressed() {
+        let artifacts = build_fixture(
+            Some(r#"{"asyncGlobalSideEffects":{}}"#),
+            &[
+                ("src/index.js", "import('./lazy');"),
+                (
+                    "src/lazy.js",
+                    "// mako-ignore-next-line MAKO_ASYNC_GLOBAL_SIDE_EFFECT\nwindow.a = 1;",
+                ),
+            ],
+        )
+        .unwrap();
+        assert_eq!(artifacts.diagnostics.len(), 1);
+        assert_eq!(artifacts.diagnostics[0].severity, Severity::Suppressed);
+    }
+
+    #[test]
+    fn test_async_global_side_effects_message() {
+        let artifacts = build_fixture(
+            Some(r#"{"asyncGlobalSideEffects":{},"hash":false}"#),
+            &[
+                ("src/index.js", "import('./lazy');"),
+                ("src/lazy.js", "customElements.define('x-a', class {});"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(artifacts.diagnostics.len(), 1);
+        assert_eq!(
+            artifacts.diagnostics[0].message,
+            "./src/lazy.js:1 writes the global `customElements.x-a` when it's executed, which is only after the async chunk `src_lazy_js-async.js` is loaded, so it depends on the route visited first, add it to `asyncGlobalSideEffects.allowProperties` if it's intended"
+        );
+    }
+}
@@ -5,10 +5,11 @@ use std::path::{Component, Path};
 use hashlink::LinkedHashSet;
 use twox_hash::XxHash64;
 
-use crate::ast::file::parse_path;
+use crate::config::HashFunction;
 use crate::module::ModuleId;
 use crate::module_graph::ModuleGraph;
-use crate::utils::url_safe_base64_encode;
+use crate::utils::file_request::{decode_path, normalize_query};
+use crate::utils::hash::query_hash;
 
 // TODO: Refact ChunkId
 /*
@@ -47,6 +48,15 @@ pub struct Chunk {
     pub modules: LinkedHashSet<ModuleId>,
     pub content: Option<String>,
     pub source_map: Option<String>,
+    // of the query hashes in the name
+    pub hash_function: HashFunction,
+    pub hash_length: usize,
+    // the entry chunks whose runtime executes this entry chunk, see the
+    // dependOn of the entries
+    pub depend_on: Vec<ChunkId>,
+    // appended to the name when it collides with the name of another chunk,
+    // see disambiguate_chunk_names
+    pub collision_index: Option<usize>,
 }
 
 impl Debug for Chunk {
@@ -70,17 +80,40 @@ impl Chunk {
             chunk_type,
             content: None,
             source_map: None,
+            hash_function: HashFunction::Md5,
+            hash_length: 8,
+            depend_on: vec![],
+            collision_index: None,
         }
     }
 
     pub fn name(&self) -> String {
+        match self.collision_index {
+            Some(index) => format!("{}-{}", self.base_name(), index),
+            None => self.base_name(),
+        }
+    }
+
+    /// Where the name of the chunk comes from, for the messages.
+    pub fn source(&self) -> String {
+        match &self.chunk_type {
+            ChunkType::Runtime => "the runtime chunk".to_string(),
+            ChunkType::Entry(_, name, false) => format!("the entry `{}`", name),
+            ChunkType::Entry(_, name, true) => format!("the code splitting group `{}`", name),
+            ChunkType::Sync => format!("the code splitting group `{}`", self.id.id),
+            ChunkType::Async => format!("the async chunk `{}`", self.id.id),
+            ChunkType::Worker(_) => format!("the worker chunk `{}`", self.id.id),
+        }
+    }
+
+    fn base_name(&self) -> String {
         match &self.chunk_type {
             ChunkType::Runtime => "runtime".into(),
             // foo/bar.tsx -> bar.js
             ChunkType::Entry(_, name, _) => name.clone(),
             // foo/bar.tsx -> foo_bar_tsx-async.js
             ChunkType::Async | ChunkType::Sync | ChunkType::Worker(_) => {
-                let (path, search, ..) = parse_path(&self.id.id).unwrap();
+                let path = decode_path(self.id.path());
                 let path = Path::new(&path);
 
                 let mut name = path
@@ -91,17 +124,24 @@ impl Chunk {
                         Component::Prefix(_) => "ps_".to_string(),
                         Component::RootDir => "".to_string(),
                         Component::CurDir => "".to_string(),
-                        Component::Normal(seg) => {
-                            seg.to_string_lossy().replace(['.', '?', '@'], "_")
-                        }
+                        Component::Normal(seg) => seg
+                            .to_string_lossy()
+                            .replace(|c: char| is_unsafe_in_name(c), "_"),
                     })
                     .collect::<Vec<String>>()
                     .join("_");
 
-                if !search.is_empty() {
-                    let search_hash =
-                        url_safe_base64_encode(md5::compute(search).0)[..4].to_string();
-                    name = format!("{}_q_{}", name, search_hash);
+                let query = self.id.query();
+                if !query.is_empty() {
+                    name = format!(
+                        "{}_q_{}",
+                        name,
+                        query_hash(
+                            &normalize_query(&query),
+                            self.hash_function,
+                            self.hash_length
+                        )
+                    );
                 }
 
                 format!(
@@ -117,6 +157,12 @@ impl Chunk {
         }
     }
 
+    /// Whether it's an entry chunk which is executed by the runtime of the
+    /// entries it depends on, it's rendered without the runtime.
+    pub fn is_dependent_entry(&self) -> bool {
+        matches!(self.chunk_type, ChunkType::Entry(_, _, false)) && !self.depend_on.is_empty()
+    }
+
     pub fn filename(&self) -> String {
         format!("{}.js", self.name())
     }
@@ -137,14 +183,16 @@ impl Chunk {
         self.modules.contains(module_id)
     }
 
-    pub fn hash(&self, mg: &ModuleGraph) -> u64 {
-        let mut sorted_module_ids = self.modules.iter().cloned().collect::<Vec<ModuleId>>();
-        sorted_module_ids.sort_by_key(|m| m.id.clone());
+    /// The hash of the modules, with the codegen salt of the context.
+    pub fn hash(&self, mg: &ModuleGraph, salt: u64) -> u64 {
+        let mut sorted_module_ids = self.modules.iter().collect::<Vec<&ModuleId>>();
+        sorted_module_ids.sort_by(|a, b| a.id.cmp(&b.id));
 
         let mut hash: XxHash64 = Default::default();
+        hash.write_u64(salt);
 
         for id in sorted_module_ids {
-            let m = mg.get_module(&id).unwrap();
+            let m = mg.get_module(id).unwrap();
 
             if let Some(info) = &m.info {
                 hash.write_u64(info.raw_hash);
@@ -155,12 +203,54 @@ impl Chunk {
 
         hash.finish()
     }
+
+    // identifies the module set, it's order independent so that no sorting
+    // is needed
+    pub fn fingerprint(&self) -> u64 {
+        self.modules
+            .iter()
+            .fold(self.modules.len() as u64, |fingerprint, id| {
+                let mut hash: XxHash64 = Default::default();
+                hash.write(id.id.as_bytes());
+                fingerprint.wrapping_add(hash.finish())
+            })
+    }
+}
+
+// the characters of the decoded file names which break the urls or the file
+// names of the chunks, the unicode is kept, e.g. `a?b #c.js` -> `a_b__c_js`
+fn is_unsafe_in_name(c: char) -> bool {
+    c.is_whitespace()
+        || c.is_control()
+        || matches!(
+            c,
+            '.' | '?'
+                | '@'
+                | '#'
+                | '%'
+                | '&'
+                | '+'
+                | '='
+                | ':'
+                | '*'
+                | '"'
+                | '<'
+                | '>'
+                | '|'
+                | '\\'
+        )
 }
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
+    use regex::Regex;
+
     use crate::generate::chunk::{Chunk, ChunkType};
     use crate::module::ModuleId;
+    use crate::testing::build_fixture;
+    use crate::utils::test_helper::setup_compiler;
 
     #[test]
     fn test_filename() {
@@ -177,4 +267,102 @@ mod tests {
         let chunk = Chunk::new(ModuleId::new("foo/bar.tsx".into()), ChunkType::Runtime);
         assert_eq!(chunk.filename(), "runtime.js");
     }
+
+    #[test]
+    fn test_filename_with_query() {
+        let name = |id: &str| Chunk::new(ModuleId::new(id.into()), ChunkType::Async).filename();
+        assert_eq!(name("a.js?b=1&a=2"), name("a.js?a=2&b=1"));
+        assert_ne!(name("a.js?locale=zh-CN"), name("a.js?locale=zh-TW"));
+        assert!(
+            Regex::new(r"^a_js_q_[0-9a-f]{8}-async\.js$")
+                .unwrap()
+                .is_match(&name("a.js?locale=zh-CN")),
+            "{}",
+            name("a.js?locale=zh-CN")
+        );
+    }
+
+    #[test]
+    fn test_filename_of_encoded_paths() {
+        let name = |path: &str, query: Option<&str>| {
+            let id = ModuleId::from_resolved(path, query, Path::new("/root"));
+            Chunk::new(id, ChunkType::Async).filename()
+        };
+        for path in [
+            "/root/src/a?b.js",
+            "/root/src/a%3Fb.js",
+            "/root/src/a#b.js",
+            "/root/src/with space.js",
+            "/root/src/中文/文件.js",
+            "/root/src/100%.js",
+        ] {
+            for query in [None, Some("raw"), Some("b=2&a=1"), Some("q=a%20b")] {
+                let filename = name(path, query);
+                assert!(
+                    !filename.contains(['%', '?', '#', ' ']),
+                    "{} {:?} -> {}",
+                    path,
+                    query,
+                    filename
+                );
+                // deterministic
+                assert_eq!(name(path, query), filename);
+                if query.is_some() {
+                    assert!(filename.contains("_q_"), "{}", filename);
+                }
+            }
+        }
+        assert_eq!(name("/root/src/a?b.js", None), "root_src_a_b_js-async.js");
+        assert_eq!(name("/root/src/a#b.js", None), "root_src_a_b_js-async.js");
+        assert_eq!(
+            name("/root/src/中文/文件.js", None),
+            "root_src_中文_文件_js-async.js"
+        );
+        assert_eq!(
+            name("/root/src/a.js", Some("b=2&a=1")),
+            name("/root/src/a.js", Some("a=1&b=2"))
+        );
+    }
+
+    #[test]
+    fn test_chunks_of_encoded_file_names() {
+        let artifacts = build_fixture(
+            Some(r#"{"mode":"production","minify":false,"hash":false,"moduleIdStrategy":"named"}"#),
+            &[
+                (
+                    "src/index.js",
+                    "import('./a%3Fb.js');\nimport('./with space.js');\nimport('./a.js?raw');",
+                ),
+                ("src/a%3Fb.js", "console.log('escaped');"),
+                ("src/a.js", "console.log('plain');"),
+                ("src/with space.js", "console.log('space');"),
+            ],
+        )
+        .unwrap();
+        assert!(
+            artifacts.modules.contains_key("src/a%253Fb.js"),
+            "{:?}",
+            artifacts.modules.keys()
+        );
+        let escaped = artifacts.asset("src_a_3Fb_js-async.js").unwrap();
+        assert!(escaped.contains("escaped"), "{}", escaped);
+        let space = artifacts.asset("src_with_space_js-async.js").unwrap();
+        assert!(space.contains("space"), "{}", space);
+        for name in artifacts.assets.keys() {
+            assert!(!name.contains(['%', '?', '#', ' ']), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_query_chunks() {
+        let compiler = setup_compiler("test/build/query-chunks", false);
+        compiler.compile().unwrap();
+        let output_path = &compiler.context.config.output.path;
+        let locales = std::fs::read_dir(output_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("locale_js_q_") && name.ends_with(".js"))
+            .collect::<Vec<_>>();
+        assert_eq!(locales.len(), 2, "{:?}", locales);
+    }
 }
@@ -8,12 +8,27 @@ use petgraph::Direction;
 use twox_hash::XxHash64;
 
 use crate::generate::chunk::{Chunk, ChunkId, ChunkType};
+use crate::generate::chunk_hash_cache::ChunkHashCache;
+use crate::generate::chunk_placement::ChunkPlacement;
 use crate::module::ModuleId;
 use crate::module_graph::ModuleGraph;
 
+/// The chunks to load for an entry, in load order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entrypoint {
+    pub name: String,
+    pub chunks: Vec<ChunkId>,
+}
+
 pub struct ChunkGraph {
     pub(crate) graph: StableDiGraph<Chunk, ()>,
     id_index_map: HashMap<ChunkId, NodeIndex<DefaultIx>>,
+    // (importer, imported) of the dynamic imports whose module is loaded with
+    // every chunk of the importer, they don't need a chunk
+    loaded_dynamic_imports: HashSet<(ModuleId, ModuleId)>,
+    // why the modules are placed in the chunks, for `--verbose-chunks` and
+    // the stats
+    placements: HashMap<(ChunkId, ModuleId), ChunkPlacement>,
 }
 
 impl ChunkGraph {
@@ -21,12 +36,16 @@ impl ChunkGraph {
         Self {
             graph: StableDiGraph::new(),
             id_index_map: HashMap::new(),
+            loaded_dynamic_imports: HashSet::new(),
+            placements: HashMap::new(),
         }
     }
 
     pub fn clear(&mut self) {
         self.graph.clear();
         self.id_index_map.clear();
+        self.loaded_dynamic_imports.clear();
+        self.placements.clear();
     }
 
     pub fn add_chunk(&mut self, chunk: Chunk) {
@@ -100,17 +119,72 @@ impl ChunkGraph {
             .remove_edge(self.graph.find_edge(*from, *to).unwrap());
     }
 
+    pub fn add_loaded_dynamic_import(&mut self, importer: &ModuleId, imported: &ModuleId) {
+        self.loaded_dynamic_imports
+            .insert((importer.clone(), imported.clone()));
+    }
+
+    /// Whether the dynamic import is resolved with the module which is already
+    /// loaded, e.g. a module which imports itself.
+    pub fn is_loaded_dynamic_import(&self, importer: &ModuleId, imported: &ModuleId) -> bool {
+        self.loaded_dynamic_imports
+            .contains(&(importer.clone(), imported.clone()))
+    }
+
+    /// Keep the loaded dynamic imports only if the module is loaded with every
+    /// chunk of the importer, in the chunk or its entry ancestors.
+    pub fn retain_loaded_dynamic_imports(&mut self) {
+        let imports = std::mem::take(&mut self.loaded_dynamic_imports);
+        self.loaded_dynamic_imports = imports
+            .into_iter()
+            .filter(|(importer, imported)| {
+                self.get_all_chunks()
+                    .into_iter()
+                    .filter(|chunk| chunk.has_module(importer))
+                    .all(|chunk| {
+                        chunk.has_module(imported)
+                            || self
+                                .entry_ancestors_chunk(&chunk.id)
+                                .iter()
+                                .any(|id| self.chunk(id).unwrap().has_module(imported))
+                    })
+            })
+            .collect();
+    }
+
+    /// Record why the module is placed in the chunk, the first placement is
+    /// kept if it's placed again, e.g. by another root of a named chunk.
+    pub fn set_placement(
+        &mut self,
+        chunk_id: &ChunkId,
+        module_id: &ModuleId,
+        placement: ChunkPlacement,
+    ) {
+        self.placements
+            .entry((chunk_id.clone(), module_id.clone()))
+            .or_insert(placement);
+    }
+
+    pub fn remove_placement(&mut self, chunk_id: &ChunkId, module_id: &ModuleId) {
+        self.placements
+            .remove(&(chunk_id.clone(), module_id.clone()));
+    }
+
+    pub fn placement(&self, chunk_id: &ChunkId, module_id: &ModuleId) -> Option<&ChunkPlacement> {
+        self.placements.get(&(chunk_id.clone(), module_id.clone()))
+    }
+
     pub fn chunk_names(&self) -> HashSet<String> {
         self.graph.node_weights().map(|c| c.filename()).collect()
     }
 
-    pub fn full_hash(&self, module_graph: &ModuleGraph) -> u64 {
+    pub fn full_hash(&self, module_graph: &ModuleGraph, cache: &ChunkHashCache, salt: u64) -> u64 {
         let mut chunks = self.get_all_chunks();
-        chunks.sort_by_key(|c| c.id.id.clone());
+        chunks.sort_by(|a, b| a.id.id.cmp(&b.id.id));
 
         let mut hasher: XxHash64 = Default::default();
         for c in chunks {
-            hasher.write_u64(c.hash(module_graph))
+            hasher.write_u64(cache.hash(c, module_graph, salt))
         }
         hasher.finish()
     }
@@ -154,6 +228,79 @@ impl ChunkGraph {
             .collect::<Vec<ChunkId>>()
     }
 
+    /// The entry chunks which the entry chunk depends on, directly or not, in
+    /// execution order.
+    pub fn depend_on_chunks(&self, chunk_id: &ChunkId) -> Vec<ChunkId> {
+        fn visit(chunk_graph: &ChunkGraph, chunk_id: &ChunkId, ret: &mut Vec<ChunkId>) {
+            let Some(chunk) = chunk_graph.chunk(chunk_id) else {
+                return;
+            };
+            for depend_on in &chunk.depend_on {
+                if !ret.contains(depend_on) {
+                    visit(chunk_graph, depend_on, ret);
+                    ret.push(depend_on.clone());
+                }
+            }
+        }
+
+        let mut ret = vec![];
+        visit(self, chunk_id, &mut ret);
+        ret
+    }
+
+    /// The entry chunks which depend on the entry chunk, directly or not,
+    /// they are executed by its runtime.
+    pub fn depend_on_dependents_chunk(&self, chunk_id: &ChunkId) -> Vec<ChunkId> {
+        self.graph
+            .node_weights()
+            .filter(|chunk| {
+                chunk.is_dependent_entry() && self.depend_on_chunks(&chunk.id).contains(chunk_id)
+            })
+            .map(|chunk| chunk.id.clone())
+            .collect()
+    }
+
+    /// Entries with their chunks in load order, the shared chunks split from
+    /// the entry (e.g. vendors) go first and the entry chunk which contains
+    /// the runtime goes last. The chunks of the entries it depends on go
+    /// before them.
+    pub fn entrypoints(&self) -> Vec<Entrypoint> {
+        let entry_chunks = |chunk_id: &ChunkId| {
+            let mut chunks = self.entry_dependencies_chunk(chunk_id);
+            // same as sync_dependencies_chunk, the neighbors ordering is reversed
+            chunks.reverse();
+            chunks.push(chunk_id.clone());
+            chunks
+        };
+        let mut entrypoints = self
+            .get_chunks()
+            .into_iter()
+            .filter_map(|chunk| match &chunk.chunk_type {
+                ChunkType::Entry(_, name, false) => {
+                    let mut chunks: Vec<ChunkId> = vec![];
+                    for chunk_id in self
+                        .depend_on_chunks(&chunk.id)
+                        .iter()
+                        .chain(std::iter::once(&chunk.id))
+                    {
+                        for chunk_id in entry_chunks(chunk_id) {
+                            if !chunks.contains(&chunk_id) {
+                                chunks.push(chunk_id);
+                            }
+                        }
+                    }
+                    Some(Entrypoint {
+                        name: name.clone(),
+                        chunks,
+                    })
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        entrypoints.sort_by(|a, b| a.name.cmp(&b.name));
+        entrypoints
+    }
+
     pub fn entry_ancestors_chunk(&self, chunk_id: &ChunkId) -> Vec<ChunkId> {
         let mut stack = vec![*self.id_index_map.get(chunk_id).unwrap()];
         let mut ret = vec![];
@@ -201,6 +348,7 @@ impl ChunkGraph {
     pub fn remove_chunk(&mut self, chunk_id: &ChunkId) {
         let idx = self.id_index_map.remove(chunk_id).unwrap();
         self.graph.remove_node(idx);
+        self.placements.retain(|(id, _), _| id != chunk_id);
     }
 }
 
@@ -0,0 +1,166 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::generate::chunk::Chunk;
+use crate::module::ModuleId;
+use crate::module_graph::ModuleGraph;
+use crate::utils::memory::CacheUsage;
+
+/// Chunk hashes of previous builds, keyed by the fingerprint of the chunk's
+/// module set, so that only the chunks which contain changed modules are
+/// re-hashed in watch mode.
+#[derive(Default)]
+pub struct ChunkHashCache {
+    inner: Mutex<ChunkHashCacheInner>,
+}
+
+#[derive(Default)]
+struct ChunkHashCacheInner {
+    // chunk fingerprint -> chunk hash
+    hashes: HashMap<u64, u64>,
+    // module id -> fingerprints of the cached chunks which contain it
+    module_chunks: HashMap<ModuleId, HashSet<u64>>,
+}
+
+impl ChunkHashCache {
+    pub fn hash(&self, chunk: &Chunk, module_graph: &ModuleGraph, salt: u64) -> u64 {
+        let fingerprint = chunk.fingerprint();
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(hash) = inner.hashes.get(&fingerprint) {
+            return *hash;
+        }
+        let hash = chunk.hash(module_graph, salt);
+        inner.hashes.insert(fingerprint, hash);
+        for module_id in chunk.modules.iter() {
+            inner
+                .module_chunks
+                .entry(module_id.clone())
+                .or_default()
+                .insert(fingerprint);
+        }
+        hash
+    }
+
+    pub fn usage(&self) -> CacheUsage {
+        let inner = self.inner.lock().unwrap();
+        let bytes = inner.hashes.len() * 16
+            + inner
+                .module_chunks
+                .iter()
+                .map(|(module_id, fingerprints)| module_id.id.len() + fingerprints.len() * 8)
+                .sum::<usize>();
+        CacheUsage::new("chunk hashes", inner.hashes.len(), bytes)
+    }
+
+    /// Drop the cached hashes of chunks which contain any of the modules.
+    pub fn invalidate<'a, I>(&self, module_ids: I)
+    where
+        I: IntoIterator<Item = &'a ModuleId>,
+    {
+        let mut inner = self.inner.lock().unwrap();
+        for module_id in module_ids {
+            if let Some(fingerprints) = inner.module_chunks.remove(module_id) {
+                for fingerprint in fingerprints {
+                    inner.hashes.remove(&fingerprint);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::generate::chunk::ChunkType;
+    use crate::generate::chunk_graph::ChunkGraph;
+    use crate::module::{Module, ModuleInfo};
+
+    fn module_id(i: usize) -> ModuleId {
+        ModuleId::new(format!("/src/components/module_{}/index.tsx", i))
+    }
+
+    // modules are split into chunks evenly
+    fn create_graphs(modules: usize, chunks: usize) -> (ModuleGraph, ChunkGraph) {
+        let mut module_graph = ModuleGraph::new();
+        for i in 0..modules {
+            let info = ModuleInfo {
+                raw_hash: i as u64,
+                ..Default::default()
+            };
+            module_graph.add_module(Module::new(module_id(i), i == 0, Some(info)));
+        }
+        let mut chunk_graph = ChunkGraph::new();
+        for c in 0..chunks {
+            let mut chunk = Chunk::new(module_id(c), ChunkType::Async);
+            for i in (c..modules).step_by(chunks) {
+                chunk.add_module(module_id(i));
+            }
+            chunk_graph.add_chunk(chunk);
+        }
+        (module_graph, chunk_graph)
+    }
+
+    #[test]
+    fn test_chunk_hash_cache() {
+        let (mut module_graph, chunk_graph) = create_graphs(100, 10);
+        let cache = ChunkHashCache::default();
+        let hash = chunk_graph.full_hash(&module_graph, &cache, 0);
+        assert_eq!(hash, chunk_graph.full_hash(&module_graph, &cache, 0));
+        assert_eq!(
+            hash,
+            chunk_graph.full_hash(&module_graph, &ChunkHashCache::default(), 0)
+        );
+
+        module_graph
+            .get_module_mut(&module_id(42))
+            .unwrap()
+            .info
+            .as_mut()
+            .unwrap()
+            .raw_hash = 0;
+        // stale until the changed module is invalidated
+        assert_eq!(hash, chunk_graph.full_hash(&module_graph, &cache, 0));
+        cache.invalidate([&module_id(42)]);
+        let new_hash = chunk_graph.full_hash(&module_graph, &cache, 0);
+        assert_ne!(hash, new_hash);
+        assert_eq!(
+            new_hash,
+            chunk_graph.full_hash(&module_graph, &ChunkHashCache::default(), 0)
+        );
+        // only the chunk which contains the module is re-hashed
+        assert_eq!(cache.inner.lock().unwrap().hashes.len(), 10);
+    }
+
+    // RAYON_NUM_THREADS=4 cargo test --release bench_chunk_hash_cache -- --ignored --nocapture
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_chunk_hash_cache() {
+        let (module_graph, chunk_graph) = create_graphs(8000, 300);
+        let cache = ChunkHashCache::default();
+        let rounds: u32 = 20;
+
+        let start = Instant::now();
+        for _ in 0..rounds {
+            chunk_graph.full_hash(&module_graph, &ChunkHashCache::default(), 0);
+        }
+        let uncached = start.elapsed();
+
+        chunk_graph.full_hash(&module_graph, &cache, 0);
+        let start = Instant::now();
+        for i in 0..rounds {
+            // one changed module per rebuild
+            cache.invalidate([&module_id(i as usize)]);
+            chunk_graph.full_hash(&module_graph, &cache, 0);
+        }
+        let cached = start.elapsed();
+
+        println!(
+            "full_hash of 8000 modules in 300 chunks, uncached: {:?}, cached: {:?}",
+            uncached / rounds,
+            cached / rounds
+        );
+        assert!(cached < uncached);
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use crate::compiler::Compiler;
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::generate::chunk::ChunkType;
+use crate::generate::optimize_chunk::OptimizeChunksInfo;
+
+impl Compiler {
+    /// Renames the code splitting groups whose names are the ids of the other
+    /// chunks, e.g. the async chunk of the same `webpackChunkName`, or the
+    /// names of the groups before them, which would replace the chunks of the
+    /// same ids otherwise. The renamed groups are saved for the hot updates.
+    pub(crate) fn disambiguate_group_names(
+        &self,
+        optimize_chunks_infos: &mut [OptimizeChunksInfo],
+    ) {
+        let chunk_graph = self.context.chunk_graph.read().unwrap();
+        // name -> source
+        let mut names: HashMap<String, String> = HashMap::new();
+        let mut messages = vec![];
+        for info in optimize_chunks_infos.iter_mut() {
+            let name = info.group_options.name.clone();
+            let collided = chunk_graph
+                .chunk(&name.as_str().into())
+                .map(|chunk| chunk.source())
+                .or_else(|| names.get(&name).cloned());
+            let name = match collided {
+                Some(collided) => {
+                    let renamed = (2..)
+                        .map(|index| format!("{}-{}", name, index))
+                        .find(|renamed| {
+                            !names.contains_key(renamed)
+                                && !chunk_graph.has_chunk(&renamed.as_str().into())
+                        })
+                        .unwrap();
+                    messages.push(format!(
+                        "The code splitting group `{}` has the same name as {}, it's renamed to `{}`",
+                        name, collided, renamed
+                    ));
+                    info.group_options.name = renamed.clone();
+                    renamed
+                }
+                None => name,
+            };
+            names.insert(name.clone(), format!("the code splitting group `{}`", name));
+        }
+        drop(chunk_graph);
+        self.report_chunk_name_collisions(messages);
+    }
+
+    /// Suffixes the names of the chunks whose file names collide with the
+    /// ones of the other chunks, the case is ignored for the case-insensitive
+    /// file systems. The entries keep their names first, then the code
+    /// splitting groups, the async chunks and the workers, and the chunks of
+    /// the same kind by their ids, so that the names don't depend on the
+    /// order the chunks are created or emitted. The async chunks of the same
+    /// `webpackChunkName` are already merged into one chunk.
+    pub(crate) fn disambiguate_chunk_names(&self) {
+        let mut chunk_graph = self.context.chunk_graph.write().unwrap();
+        let mut chunks = chunk_graph.mut_chunks();
+        chunks
+            .iter_mut()
+            .for_each(|chunk| chunk.collision_index = None);
+        chunks.sort_by_cached_key(|chunk| (kind_order(&chunk.chunk_type), chunk.id.id.clone()));
+        // lowercase file name -> file name and source
+        let mut file_names: HashMap<String, (String, String)> = HashMap::new();
+        let mut messages = vec![];
+        for chunk in chunks {
+            let file_name = chunk.filename();
+            if let Some((collided_file_name, collided)) = file_names.get(&file_name.to_lowercase())
+            {
+                let collided = format!(
+                    "The file name `{}` of {} collides with `{}` of {}",
+                    file_name,
+                    chunk.source(),
+                    collided_file_name,
+                    collided
+                );
+                let mut index = 2;
+                chunk.collision_index = Some(index);
+                while file_names.contains_key(&chunk.filename().to_lowercase()) {
+                    index += 1;
+                    chunk.collision_index = Some(index);
+                }
+                messages.push(format!(
+                    "{}, it's renamed to `{}`",
+                    collided,
+                    chunk.filename()
+                ));
+            }
+            file_names.insert(
+                chunk.filename().to_lowercase(),
+                (chunk.filename(), chunk.source()),
+            );
+        }
+        drop(chunk_graph);
+        self.report_chunk_name_collisions(messages);
+    }
+
+    fn report_chunk_name_collisions(&self, messages: Vec<String>) {
+        for message in messages {
+            self.context.diagnostics.report(
+                Diagnostic::new(DiagnosticCode::ChunkNameCollision, message),
+                &self.context,
+            );
+        }
+    }
+}
+
+fn kind_order(chunk_type: &ChunkType) -> u8 {
+    match chunk_type {
+        ChunkType::Runtime | ChunkType::Entry(_, _, false) => 0,
+        ChunkType::Entry(_, _, true) | ChunkType::Sync => 1,
+        ChunkType::Async => 2,
+        ChunkType::Worker(_) => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::diagnostics::DiagnosticCode;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_chunk_names() {
+        let compiler = setup_compiler("test/build/chunk-names", false);
+        compiler.compile().unwrap();
+
+        let chunk_graph = compiler.context.chunk_graph.read().unwrap();
+        let names = chunk_graph
+            .get_all_chunks()
+            .iter()
+            .map(|chunk| (chunk.filename(), chunk.source()))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            names,
+            BTreeMap::from(
+                [
+                    ("Vendors.js", "the entry `Vendors`"),
+                    ("lazy-async.js", "the entry `lazy-async`"),
+                    ("main.js", "the entry `main`"),
+                    // entry vs async
+                    ("lazy-async-2.js", "the async chunk `lazy`"),
+                    // no collision with the entry of the same name
+                    ("main-async.js", "the async chunk `main`"),
+                    // manual vs magic
+                    ("vendor-async.js", "the async chunk `vendor`"),
+                    ("vendor-2-async.js", "the code splitting group `vendor-2`"),
+                    // case only
+                    ("vendors-2.js", "the code splitting group `vendors`"),
+                ]
+                .map(|(name, source)| (name.to_string(), source.to_string()))
+            )
+        );
+        // the imports of the same name are merged
+        let lazy = chunk_graph
+            .get_chunk_by_name(&"lazy-async-2.js".to_string())
+            .unwrap();
+        assert_eq!(lazy.modules.len(), 2);
+        drop(chunk_graph);
+
+        let messages = compiler
+            .context
+            .diagnostics
+            .get_reported()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.code == DiagnosticCode::ChunkNameCollision)
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "The code splitting group `vendor` has the same name as the async chunk `vendor`, it's renamed to `vendor-2`",
+                "The file name `vendors.js` of the code splitting group `vendors` collides with `Vendors.js` of the entry `Vendors`, it's renamed to `vendors-2.js`",
+                "The file name `lazy-async.js` of the async chunk `lazy` collides with `lazy-async.js` of the entry `lazy-async`, it's renamed to `lazy-async-2.js`",
+            ]
+        );
+
+        let output = &compiler.context.config.output.path;
+        let main = std::fs::read_to_string(output.join("main.js")).unwrap();
+        assert!(main.contains(r#""lazy": "lazy-async-2.js""#), "{}", main);
+        assert!(
+            main.contains(r#""vendor-2": "vendor-2-async.js""#),
+            "{}",
+            main
+        );
+        let lazy = std::fs::read_to_string(output.join("lazy-async-2.js")).unwrap();
+        assert!(lazy.contains("lazy more"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::compiler::Compiler;
+use crate::module::relative_to_root;
+
+/// Why a module is placed in a chunk, which is recorded when the chunks are
+/// grouped and optimized. Unlike `why`, which explains how a module is
+/// imported, it explains the chunk the module ends up in.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ChunkPlacement {
+    /// reachable from the entry by the static imports
+    Entry { entry: String },
+    /// reachable from the root of the async chunk, which is dynamically
+    /// imported, by the static imports
+    Async { root: String },
+    /// reachable from the worker by the static imports
+    Worker { root: String },
+    /// shared by the chunks, it's moved to the chunk of the code splitting
+    /// group
+    Shared { group: String, parents: Vec<String> },
+    /// matched by the test of the code splitting group
+    Rule {
+        group: String,
+        test: String,
+        parents: Vec<String>,
+    },
+    /// a css module moved to the css only chunk of the group of extractCss
+    Css { group: String, parents: Vec<String> },
+}
+
+impl ChunkPlacement {
+    pub fn describe(&self, root: &PathBuf) -> String {
+        match self {
+            ChunkPlacement::Entry { entry } => {
+                format!("reachable from entry {}", relative_to_root(entry, root))
+            }
+            ChunkPlacement::Async { root: chunk_root } => format!(
+                "reachable from dynamic import of {}",
+                relative_to_root(chunk_root, root)
+            ),
+            ChunkPlacement::Worker { root: chunk_root } => {
+                format!(
+                    "reachable from worker {}",
+                    relative_to_root(chunk_root, root)
+                )
+            }
+            ChunkPlacement::Shared { group, parents } => format!(
+                "shared by {} chunks ({}), extracted by group {}",
+                parents.len(),
+                parents.join(", "),
+                group
+            ),
+            ChunkPlacement::Rule {
+                group,
+                test,
+                parents,
+            } => format!(
+                "matched test {} of group {}, from {} chunks ({})",
+                test,
+                group,
+                parents.len(),
+                parents.join(", ")
+            ),
+            ChunkPlacement::Css { group, parents } => format!(
+                "css extracted by group {} from {} chunks ({})",
+                group,
+                parents.len(),
+                parents.join(", ")
+            ),
+        }
+    }
+}
+
+impl Compiler {
+    /// The modules of the chunks whose names contain the filter with their
+    /// placements, it should be called after compile.
+    pub fn format_chunk_placements(&self, filter: &str) -> String {
+        let root = &self.context.root;
+        let chunk_graph = self.context.chunk_graph.read().unwrap();
+        let mut chunks = chunk_graph
+            .get_chunks()
+            .into_iter()
+            .filter(|chunk| chunk.name().contains(filter))
+            .collect::<Vec<_>>();
+        chunks.sort_by_key(|chunk| chunk.name());
+        if chunks.is_empty() {
+            return format!("No chunk matches \"{}\"", filter);
+        }
+        chunks
+            .iter()
+            .map(|chunk| {
+                let mut lines = vec![format!(
+                    "{} ({} modules)",
+                    chunk.filename(),
+                    chunk.modules.len()
+                )];
+                for module_id in chunk.get_modules() {
+                    let placement = chunk_graph
+                        .placement(&chunk.id, module_id)
+                        .map(|placement| placement.describe(root))
+                        .unwrap_or_else(|| "unknown".to_string());
+                    lines.push(format!(
+                        "  {} — {}",
+                        module_id.relative_display(root),
+                        placement
+                    ));
+                }
+                lines.join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::ChunkPlacement;
+    use crate::compiler::Compiler;
+    use crate::module::ModuleId;
+    use crate::testing::compile_fixture;
+
+    fn compile(overrides: &str) -> Compiler {
+        compile_fixture(Path::new("test/build/entrypoints"), Some(overrides)).unwrap()
+    }
+
+    #[test]
+    fn test_chunk_placements() {
+        let compiler = compile(r#"{"mode":"production","hash":false,"moduleIdStrategy":"named"}"#);
+        let root = &compiler.context.root;
+        let module_id = |name: &str| ModuleId::from(root.join(name));
+        {
+            let chunk_graph = compiler.context.chunk_graph.read().unwrap();
+            let chunk_id = |name: &str| {
+                chunk_graph
+                    .get_chunk_by_name(&name.to_string())
+                    .unwrap()
+                    .id
+                    .clone()
+            };
+            assert_eq!(
+                chunk_graph.placement(&chunk_id("a.js"), &module_id("src/a.ts")),
+                Some(&ChunkPlacement::Entry {
+                    entry: module_id("src/a.ts").id.to_string()
+                })
+            );
+            assert_eq!(
+                chunk_graph.placement(&chunk_id("common.js"), &module_id("src/utils.ts")),
+                Some(&ChunkPlacement::Shared {
+                    group: "common".to_string(),
+                    parents: vec!["a".to_string(), "b".to_string()],
+                })
+            );
+            // it's moved out of the entry chunks
+            assert!(chunk_graph
+                .placement(&chunk_id("a.js"), &module_id("src/utils.ts"))
+                .is_none());
+        }
+        let text = compiler.format_chunk_placements("common");
+        assert!(
+            text.contains("./src/utils.ts — shared by 2 chunks (a, b), extracted by group common"),
+            "{}",
+            text
+        );
+        assert!(!text.contains("a.js"), "{}", text);
+
+        let compiler = compile(
+            r#"{"mode":"production","hash":false,"moduleIdStrategy":"named","codeSplitting":{"strategy":"advanced","options":{"groups":[{"name":"utils","allowChunks":"entry","minSize":1,"test":"utils"}]}}}"#,
+        );
+        let text = compiler.format_chunk_placements("utils");
+        assert!(
+            text.contains(
+                "./src/utils.ts — matched test utils of group utils, from 2 chunks (a, b)"
+            ),
+            "{}",
+            text
+        );
+    }
+}
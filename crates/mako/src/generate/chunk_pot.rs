@@ -1,4 +1,5 @@
 mod ast_impl;
+mod fragments;
 mod str_impl;
 pub mod util;
 
@@ -12,13 +13,14 @@ use swc_core::css::ast::Stylesheet;
 
 use crate::compiler::Context;
 use crate::config::Mode;
-use crate::generate::chunk::Chunk;
-pub use crate::generate::chunk_pot::util::CHUNK_FILE_NAME_HASH_LENGTH;
+use crate::generate::chunk::{Chunk, ChunkType};
 use crate::generate::chunk_pot::util::{hash_hashmap, hash_vec};
 use crate::generate::generate_chunks::ChunkFile;
+use crate::generate::inline_chunks::inline_runtime;
 use crate::module::{Module, ModuleAst, ModuleId};
 use crate::module_graph::ModuleGraph;
 use crate::ternary;
+use crate::utils::memory::CacheUsage;
 
 pub struct ChunkPot<'a> {
     pub chunk_id: String,
@@ -27,6 +29,9 @@ pub struct ChunkPot<'a> {
     pub js_hash: u64,
     pub stylesheet: Option<CssModules<'a>>,
     pub chunk_name: String,
+    // the entry module executed once the chunk is installed, if it's the
+    // chunk of an entry which depends on other entries
+    pub entry_module_id: Option<String>,
 }
 
 impl<'cp> ChunkPot<'cp> {
@@ -39,11 +44,17 @@ impl<'cp> ChunkPot<'cp> {
 
         ChunkPot {
             js_name: chunk.filename(),
-            chunk_id: chunk.id.id.clone(),
+            chunk_id: chunk.id.id.to_string(),
             module_map: js_modules.module_map,
             js_hash: js_modules.raw_hash,
             chunk_name: chunk.name(),
             stylesheet,
+            entry_module_id: match &chunk.chunk_type {
+                ChunkType::Entry(module_id, _, false) if chunk.is_dependent_entry() => {
+                    Some(module_id.generate(context))
+                }
+                _ => None,
+            },
         }
     }
 
@@ -61,26 +72,34 @@ impl<'cp> ChunkPot<'cp> {
             return Ok(files);
         }
 
-        let js_chunk_file = ternary!(
-            self.use_chunk_parallel(context),
-            ternary!(
-                context.args.watch,
-                str_impl::render_normal_js_chunk,
-                str_impl::render_normal_js_chunk_no_cache
-            ),
-            ternary!(
-                context.args.watch,
-                ast_impl::render_normal_js_chunk,
-                ast_impl::render_normal_js_chunk_no_cache
-            )
-        )(self, context)?;
+        // the css only chunks of extractCss are loaded by the html, not by
+        // the runtime
+        let css_only = self.module_map.is_empty()
+            && self.stylesheet.is_some()
+            && matches!(chunk.chunk_type, ChunkType::Entry(_, _, true));
+
+        if !css_only {
+            let js_chunk_file = ternary!(
+                self.use_chunk_parallel(context),
+                ternary!(
+                    context.args.watch,
+                    str_impl::render_normal_js_chunk,
+                    str_impl::render_normal_js_chunk_no_cache
+                ),
+                ternary!(
+                    context.args.watch,
+                    ast_impl::render_normal_js_chunk,
+                    ast_impl::render_normal_js_chunk_no_cache
+                )
+            )(self, context)?;
+
+            if js_chunk_file.content.is_empty() {
+                panic!("Normal chunk {} output is empty.", chunk.id.id);
+            }
 
-        if js_chunk_file.content.is_empty() {
-            panic!("Normal chunk {} output is empty.", chunk.id.id);
+            files.push(js_chunk_file);
         }
 
-        files.push(js_chunk_file);
-
         if self.stylesheet.is_some() {
             let css_chunk_file = ternary!(
                 context.args.watch,
@@ -105,6 +124,27 @@ impl<'cp> ChunkPot<'cp> {
 
         let mut files = vec![];
 
+        if inline_runtime(context) && !matches!(chunk.chunk_type, ChunkType::Worker(_)) {
+            // the modules are emitted as a normal chunk, and the runtime which
+            // installs them is split out to be inlined in the html
+            let mut css_map = css_map.clone();
+            if self.stylesheet.is_some() {
+                let css_chunk_file = ast_impl::render_css_chunk_no_cache(self, chunk, context)?;
+                css_map.insert(css_chunk_file.chunk_id.clone(), css_chunk_file.disk_name());
+                files.push(css_chunk_file);
+            }
+
+            let mut modules_chunk_file = ast_impl::render_normal_js_chunk_no_cache(self, context)?;
+            modules_chunk_file.file_name_template = context.config.output.filename.clone();
+            files.push(modules_chunk_file);
+
+            files.push(ast_impl::render_runtime_js_chunk(
+                self, js_map, &css_map, chunk, context, hmr_hash,
+            )?);
+
+            return Ok(files);
+        }
+
         let js_chunk_file = if self.stylesheet.is_some() {
             let css_chunk_file = ast_impl::render_css_chunk(self, chunk, context)?;
 
@@ -166,14 +206,14 @@ impl<'cp> ChunkPot<'cp> {
             let ast = &module_info.ast;
 
             if let ModuleAst::Script(_) = ast {
-                module_raw_hash_map.insert(module.id.id.clone(), module_info.raw_hash);
+                module_raw_hash_map.insert(module.id.id.to_string(), module_info.raw_hash);
                 module_map.insert(module.id.generate(context), (module, module_info.raw_hash));
             }
 
             if let ModuleAst::Css(ast) = ast {
                 // not add empty css to chunk
                 if !ast.ast.rules.is_empty() {
-                    merged_css_modules.push((module.id.id.clone(), &ast.ast));
+                    merged_css_modules.push((module.id.id.to_string(), &ast.ast));
                     css_raw_hashes.push(module_info.raw_hash);
                 }
             }
@@ -230,3 +270,16 @@ pub fn get_css_chunk_filename(js_chunk_filename: &str) -> String {
         js_chunk_filename.strip_suffix(".js").unwrap_or("")
     )
 }
+
+/// Drop the rendered chunks and modules which are not used by the last
+/// build in watch mode, i.e. the previous versions of the changed ones.
+pub(crate) fn sweep_render_caches() {
+    ast_impl::sweep_render_caches();
+    str_impl::sweep_render_caches();
+}
+
+pub(crate) fn render_cache_usages() -> Vec<CacheUsage> {
+    let mut usages = ast_impl::render_cache_usages();
+    usages.extend(str_impl::render_cache_usages());
+    usages
+}
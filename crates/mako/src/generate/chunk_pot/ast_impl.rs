@@ -17,17 +17,22 @@ use swc_core::ecma::ast::{
 use swc_core::ecma::utils::{quote_ident, quote_str, ExprFactory};
 
 use crate::ast::js_ast::JsAst;
-use crate::ast::sourcemap::{build_source_map, merge_source_map};
+use crate::ast::sourcemap::{build_source_map, merge_source_map, source_map_to_buf};
 use crate::compiler::Context;
 use crate::config::Mode;
 use crate::generate::chunk::{Chunk, ChunkType};
 use crate::generate::chunk_pot::util::{
-    file_content_hash, pot_to_chunk_module, pot_to_module_object, runtime_code,
+    entry_runtime_code, entry_shebang, pot_to_chunk_module, pot_to_module_object,
 };
 use crate::generate::chunk_pot::{get_css_chunk_filename, util, ChunkPot};
+use crate::generate::concatenate_css::concatenate_css;
+use crate::generate::critical_css::extract_critical_rules;
 use crate::generate::generate_chunks::{ChunkFile, ChunkFileType};
+use crate::generate::inline_chunks::RUNTIME_CHUNK_NAME;
 use crate::generate::minify::{minify_css, minify_js};
 use crate::generate::transform::transform_css_generate;
+use crate::utils::hash::chunk_content_hash;
+use crate::utils::memory::{sized_cache_usage, sweep_stale_entries, CacheUsage};
 use crate::{mako_profile_scope, ternary};
 
 #[cached(
@@ -35,7 +40,7 @@ use crate::{mako_profile_scope, ternary};
     type = "SizedCache<String , ChunkFile>",
     create = "{ SizedCache::with_size(500) }",
     key = "String",
-    convert = r#"{format!("{}.{:x}",chunk_pot.chunk_id,chunk_pot.stylesheet.as_ref().unwrap().raw_hash)}"#
+    convert = r#"{format!("{}.{:x}.{:x}",chunk_pot.chunk_id,chunk_pot.stylesheet.as_ref().unwrap().raw_hash,context.cache_keys.minify)}"#
 )]
 pub(crate) fn render_css_chunk(
     chunk_pot: &ChunkPot,
@@ -67,16 +72,59 @@ pub(crate) fn render_css_chunk(
         transform_css_generate(&mut stylesheet, context);
     }
 
-    if context.config.minify && matches!(context.config.mode, Mode::Production) {
+    let critical_config = match &chunk.chunk_type {
+        ChunkType::Entry(_, name, _) => context
+            .config
+            .critical_css
+            .as_ref()
+            .and_then(|config| config.entries.get(name)),
+        _ => None,
+    };
+    let mut critical = match critical_config {
+        Some(config) => {
+            crate::mako_profile_scope!("extract_critical_rules");
+            Some(Stylesheet {
+                span: DUMMY_SP,
+                rules: extract_critical_rules(&mut stylesheet.rules, config, context)?,
+            })
+        }
+        None => None,
+    };
+
+    if context
+        .config
+        .optimization
+        .as_ref()
+        .is_some_and(|o| o.concatenate_css.unwrap_or(false))
+    {
+        crate::mako_profile_scope!("concatenate_css");
+        let mut saved = concatenate_css(&mut stylesheet)?;
+        if let Some(critical) = &mut critical {
+            saved += concatenate_css(critical)?;
+        }
+        context
+            .stats_info
+            .add_css_saved_bytes(get_css_chunk_filename(&chunk_pot.js_name), saved as u64);
+    }
+
+    let minify = context.config.minify.is_some() && matches!(context.config.mode, Mode::Production);
+    if minify {
         minify_css(&mut stylesheet, context)?;
+        if let Some(critical) = &mut critical {
+            minify_css(critical, context)?;
+        }
     }
+    let critical_css = match critical {
+        Some(critical) => {
+            let mut code = String::new();
+            let writer = BasicCssWriter::new(&mut code, None, BasicCssWriterConfig::default());
+            CodeGenerator::new(writer, CodegenConfig { minify }).emit(&critical)?;
+            Some(code)
+        }
+        None => None,
+    };
 
-    let mut gen = CodeGenerator::new(
-        css_writer,
-        CodegenConfig {
-            minify: context.config.minify && matches!(context.config.mode, Mode::Production),
-        },
-    );
+    let mut gen = CodeGenerator::new(css_writer, CodegenConfig { minify });
     gen.emit(&stylesheet)?;
 
     let cm = &context.meta.css.cm;
@@ -86,7 +134,7 @@ pub(crate) fn render_css_chunk(
             mako_profile_scope!("build_source_map");
 
             let module_graph = context.module_graph.read().unwrap();
-            let chunk_source_map = build_source_map(&source_map, cm);
+            let chunk_source_map = build_source_map(&source_map, cm, context);
 
             let mut chain_map = HashMap::<String, Vec<swc_sourcemap::SourceMap>>::new();
 
@@ -96,7 +144,9 @@ pub(crate) fn render_css_chunk(
                         && matches!(info.ast, crate::module::ModuleAst::Css(_))
                     {
                         let relative_source = diff_paths(&module_id.id, &context.root)
-                            .map_or(module_id.id.clone(), |p| p.to_string_lossy().to_string());
+                            .map_or(module_id.id.to_string(), |p| {
+                                p.to_string_lossy().to_string()
+                            });
 
                         chain_map.insert(
                             relative_source,
@@ -111,16 +161,16 @@ pub(crate) fn render_css_chunk(
 
             let merged_source_map = merge_source_map(chunk_source_map, chain_map, &context.root);
 
-            let mut buf = vec![];
-
-            merged_source_map.to_writer(&mut buf).unwrap();
-
-            Some(buf)
+            Some(source_map_to_buf(&merged_source_map, context))
         }
     };
 
     let css_hash = if context.config.hash {
-        Some(file_content_hash(&css_code))
+        Some(chunk_content_hash(
+            &css_code,
+            context.codegen_salt,
+            &context.config.output,
+        ))
     } else {
         None
     };
@@ -135,6 +185,8 @@ pub(crate) fn render_css_chunk(
         file_type: ChunkFileType::Css,
         chunk_name: chunk_pot.chunk_name.clone(),
         file_name_template: None,
+        module_sizes: vec![],
+        critical_css,
     })
 }
 
@@ -143,7 +195,7 @@ pub(crate) fn render_css_chunk(
     type = "SizedCache<String , ChunkFile>",
     create = "{ SizedCache::with_size(500) }",
     key = "String",
-    convert = r#"{format!("{}.{:x}", chunk_pot.chunk_id, chunk_pot.js_hash)}"#
+    convert = r#"{format!("{}.{:x}.{:x}", chunk_pot.chunk_id, chunk_pot.js_hash, context.cache_keys.minify)}"#
 )]
 pub(crate) fn render_normal_js_chunk(
     chunk_pot: &ChunkPot,
@@ -165,14 +217,23 @@ pub(crate) fn render_normal_js_chunk(
         path: "".to_string(),
     });
 
-    if context.config.minify && matches!(context.config.mode, Mode::Production) {
+    if context.config.minify.is_some() && matches!(context.config.mode, Mode::Production) {
         minify_js(&mut ast, context)?;
     }
 
+    let module_sizes = if util::collect_module_sizes(context) {
+        util::module_sizes(&ast.ast, chunk_pot, context)?
+    } else {
+        vec![]
+    };
     let (buf, source_map) = util::render_module_js(&ast.ast, context)?;
 
     let hash = if context.config.hash {
-        Some(file_content_hash(&buf))
+        Some(chunk_content_hash(
+            &buf,
+            context.codegen_salt,
+            &context.config.output,
+        ))
     } else {
         None
     };
@@ -187,6 +248,8 @@ pub(crate) fn render_normal_js_chunk(
         chunk_id: chunk_pot.chunk_id.clone(),
         file_type: ChunkFileType::JS,
         file_name_template: None,
+        module_sizes,
+        critical_css: None,
     })
 }
 
@@ -204,6 +267,7 @@ pub(crate) fn render_entry_js_chunk(
         content,
         source_map,
         hash,
+        module_sizes,
     } = ternary!(
         context.args.watch,
         render_entry_chunk_js_without_full_hash,
@@ -228,13 +292,59 @@ pub(crate) fn render_entry_js_chunk(
         file_type: ChunkFileType::JS,
         chunk_name: pot.chunk_name.clone(),
         file_name_template: context.config.output.filename.clone(),
+        module_sizes,
+        critical_css: None,
+    })
+}
+
+/// The runtime of an entry chunk which is split out of it by inlineRuntime,
+/// it installs the modules of the entry from the chunk and executes it.
+pub(crate) fn render_runtime_js_chunk(
+    pot: &ChunkPot,
+    js_map: &HashMap<String, String>,
+    css_map: &HashMap<String, String>,
+    chunk: &Chunk,
+    context: &Arc<Context>,
+    hmr_hash: u64,
+) -> Result<ChunkFile> {
+    crate::mako_profile_function!(&pot.chunk_id);
+
+    let modules = ObjectLit {
+        span: DUMMY_SP,
+        props: vec![],
+    };
+    let mut ast = entry_ast(pot, modules, js_map, css_map, chunk, context)?;
+
+    if context.config.minify.is_some() && matches!(context.config.mode, Mode::Production) {
+        minify_js(&mut ast, context)?;
+    }
+
+    let (buf, _) = util::render_module_js(&ast.ast, context)?;
+    let content = String::from_utf8(buf)?
+        .replace("_%full_hash%_", &hmr_hash.to_string())
+        .into_bytes();
+
+    Ok(ChunkFile {
+        raw_hash: hmr_hash,
+        content,
+        hash: None,
+        source_map: None,
+        file_name: format!("{}~{}", RUNTIME_CHUNK_NAME, pot.js_name),
+        chunk_id: pot.chunk_id.clone(),
+        file_type: ChunkFileType::JS,
+        chunk_name: RUNTIME_CHUNK_NAME.to_string(),
+        file_name_template: None,
+        module_sizes: vec![],
+        critical_css: None,
     })
 }
 
 #[cached(
     result = true,
+    type = "SizedCache<String , RenderedChunk>",
+    create = "{ SizedCache::with_size(500) }",
     key = "String",
-    convert = r#"{format!("{}",pot.js_hash)}"#
+    convert = r#"{format!("{}.{:x}",pot.js_hash,context.cache_keys.minify)}"#
 )]
 fn render_entry_chunk_js_without_full_hash(
     pot: &ChunkPot,
@@ -245,6 +355,52 @@ fn render_entry_chunk_js_without_full_hash(
 ) -> Result<RenderedChunk> {
     crate::mako_profile_function!(&pot.chunk_id);
 
+    let modules = {
+        crate::mako_profile_scope!("to_module_object");
+
+        pot_to_module_object(pot, context)?
+    };
+    let mut ast = entry_ast(pot, modules, js_map, css_map, chunk, context)?;
+
+    if context.config.minify.is_some() && matches!(context.config.mode, Mode::Production) {
+        minify_js(&mut ast, context)?;
+    }
+
+    let module_sizes = if util::collect_module_sizes(context) {
+        util::module_sizes(&ast.ast, pot, context)?
+    } else {
+        vec![]
+    };
+    let (buf, source_map_buf) = util::render_module_js(&ast.ast, context)?;
+
+    let hash = if context.config.hash || context.config.output.filename.is_some() {
+        crate::mako_profile_scope!("entryHash");
+        Some(chunk_content_hash(
+            &buf,
+            context.codegen_salt,
+            &context.config.output,
+        ))
+    } else {
+        None
+    };
+
+    Ok(RenderedChunk {
+        content: buf,
+        source_map: source_map_buf,
+        hash,
+        module_sizes,
+    })
+}
+
+// the entry chunk with the modules and the runtime in an iife
+fn entry_ast(
+    pot: &ChunkPot,
+    modules: ObjectLit,
+    js_map: &HashMap<String, String>,
+    css_map: &HashMap<String, String>,
+    chunk: &Chunk,
+    context: &Arc<Context>,
+) -> Result<JsAst> {
     let mut stmts = vec![];
 
     let (js_map_stmt, css_map_stmt) = chunk_map_decls(js_map, css_map);
@@ -294,7 +450,7 @@ fn render_entry_chunk_js_without_full_hash(
     let mut ast = {
         crate::mako_profile_scope!("parse_runtime_entry");
 
-        let runtime_content = runtime_code(context)?;
+        let runtime_content = entry_runtime_code(chunk, context)?;
 
         JsAst::build(
             "_mako_internal/runtime_entry.js",
@@ -304,13 +460,9 @@ fn render_entry_chunk_js_without_full_hash(
         .unwrap()
     };
 
-    let modules_lit: Stmt = {
-        crate::mako_profile_scope!("to_module_object");
-
-        pot_to_module_object(pot, context)?
-            .into_var_decl(VarDeclKind::Var, quote_ident!("m").into())
-            .into()
-    };
+    let modules_lit: Stmt = modules
+        .into_var_decl(VarDeclKind::Var, quote_ident!("m").into())
+        .into();
 
     {
         crate::mako_profile_scope!("entryInsert");
@@ -322,26 +474,10 @@ fn render_entry_chunk_js_without_full_hash(
             .splice(0..0, stmts.into_iter().map(|s| s.into()));
 
         ast.ast = wrap_in_iife(ast.ast);
+        ast.ast.shebang = entry_shebang(pot, chunk, context);
     }
 
-    if context.config.minify && matches!(context.config.mode, Mode::Production) {
-        minify_js(&mut ast, context)?;
-    }
-
-    let (buf, source_map_buf) = util::render_module_js(&ast.ast, context)?;
-
-    let hash = if context.config.hash || context.config.output.filename.is_some() {
-        crate::mako_profile_scope!("entryHash");
-        Some(file_content_hash(&buf))
-    } else {
-        None
-    };
-
-    Ok(RenderedChunk {
-        content: buf,
-        source_map: source_map_buf,
-        hash,
-    })
+    Ok(ast)
 }
 
 #[derive(Clone)]
@@ -349,6 +485,7 @@ struct RenderedChunk {
     content: Vec<u8>,
     source_map: Option<Vec<u8>>,
     hash: Option<String>,
+    module_sizes: Vec<(String, u64)>,
 }
 
 fn chunk_map_decls(
@@ -425,3 +562,31 @@ fn wrap_in_iife(module: SwcModule) -> SwcModule {
         span: DUMMY_SP,
     }
 }
+
+pub(super) fn sweep_render_caches() {
+    sweep_stale_entries(&mut RENDER_CSS_CHUNK.lock().unwrap());
+    sweep_stale_entries(&mut RENDER_NORMAL_JS_CHUNK.lock().unwrap());
+    sweep_stale_entries(&mut RENDER_ENTRY_CHUNK_JS_WITHOUT_FULL_HASH.lock().unwrap());
+}
+
+pub(super) fn render_cache_usages() -> Vec<CacheUsage> {
+    let chunk_file_bytes =
+        |file: &ChunkFile| file.content.len() + file.source_map.as_ref().map_or(0, Vec::len);
+    vec![
+        sized_cache_usage(
+            "rendered css chunks",
+            &RENDER_CSS_CHUNK.lock().unwrap(),
+            chunk_file_bytes,
+        ),
+        sized_cache_usage(
+            "rendered js chunks",
+            &RENDER_NORMAL_JS_CHUNK.lock().unwrap(),
+            chunk_file_bytes,
+        ),
+        sized_cache_usage(
+            "rendered entry chunks",
+            &RENDER_ENTRY_CHUNK_JS_WITHOUT_FULL_HASH.lock().unwrap(),
+            |chunk| chunk.content.len() + chunk.source_map.as_ref().map_or(0, Vec::len),
+        ),
+    ]
+}
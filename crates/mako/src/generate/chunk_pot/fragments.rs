@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+/// The content of a chunk assembled from the fragments, e.g. the emitted
+/// modules which are shared with the render caches, so the content is only
+/// copied once when the fragments are joined instead of being re-allocated
+/// by every `format!` and `push_str` of a big chunk.
+#[derive(Clone, Default, Debug)]
+pub(crate) struct Fragments {
+    fragments: Vec<Arc<String>>,
+    len: usize,
+}
+
+impl Fragments {
+    pub fn push<T: Into<String>>(&mut self, fragment: T) {
+        self.push_shared(Arc::new(fragment.into()));
+    }
+
+    pub fn push_shared(&mut self, fragment: Arc<String>) {
+        self.len += fragment.len();
+        self.fragments.push(fragment);
+    }
+
+    pub fn extend(&mut self, other: Fragments) {
+        self.len += other.len;
+        self.fragments.extend(other.fragments);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.len);
+        for fragment in &self.fragments {
+            bytes.extend_from_slice(fragment.as_bytes());
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fragments;
+
+    #[test]
+    fn test_fragments() {
+        let mut fragments = Fragments::default();
+        fragments.push("var m = ");
+        let mut modules = Fragments::default();
+        modules.push("{ ");
+        modules.push("\"a\": 1");
+        modules.push(" }");
+        fragments.extend(modules);
+        fragments.push(";");
+        let bytes = fragments.into_bytes();
+        assert_eq!(bytes, b"var m = { \"a\": 1 };");
+        // the capacity is the length counted by the fragments
+        assert_eq!(bytes.capacity(), bytes.len());
+    }
+}
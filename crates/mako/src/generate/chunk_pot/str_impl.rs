@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
@@ -9,15 +9,20 @@ use swc_core::base::sourcemap;
 use swc_core::ecma::codegen::text_writer::JsWriter;
 use swc_core::ecma::codegen::{Config as JsCodegenConfig, Emitter};
 
-use crate::ast::sourcemap::{build_source_map, RawSourceMap};
+use crate::ast::sourcemap::{build_source_map, source_map_to_buf, RawSourceMap};
 use crate::compiler::Context;
 use crate::generate::chunk::{Chunk, ChunkType};
 use crate::generate::chunk_pot::ast_impl::{render_css_chunk, render_css_chunk_no_cache};
-use crate::generate::chunk_pot::util::runtime_code;
+use crate::generate::chunk_pot::fragments::Fragments;
+use crate::generate::chunk_pot::util::{
+    collect_module_sizes, entry_runtime_code, entry_shebang, module_banner,
+};
 use crate::generate::chunk_pot::ChunkPot;
 use crate::generate::generate_chunks::{ChunkFile, ChunkFileType};
+use crate::generate::source_map_cache::ChunkModule;
 use crate::module::{generate_module_id, Module, ModuleAst};
 use crate::ternary;
+use crate::utils::memory::{sized_cache_usage, sweep_stale_entries, CacheUsage};
 
 pub(super) fn render_entry_js_chunk(
     pot: &ChunkPot,
@@ -34,7 +39,7 @@ pub(super) fn render_entry_js_chunk(
 
     lines.push(format!(
         "var chunksIdToUrlMap= {};",
-        serde_json::to_string(js_map).unwrap()
+        sorted_map_json(js_map)
     ));
 
     if pot.stylesheet.is_some() {
@@ -49,14 +54,14 @@ pub(super) fn render_entry_js_chunk(
         css_map.insert(css_chunk_file.chunk_id.clone(), css_chunk_file.disk_name());
         lines.push(format!(
             "var cssChunksIdToUrlMap= {};",
-            serde_json::to_string(&css_map).unwrap()
+            sorted_map_json(&css_map)
         ));
 
         files.push(css_chunk_file);
     } else {
         lines.push(format!(
             "var cssChunksIdToUrlMap= {};",
-            serde_json::to_string(css_map).unwrap()
+            sorted_map_json(css_map)
         ));
     }
 
@@ -76,28 +81,29 @@ pub(super) fn render_entry_js_chunk(
     lines.push(init_install_css_chunk);
     lines.push(format!("var e = \"{}\";", chunk_root_module_id));
 
-    let runtime_content = runtime_code(context)?.replace("_%full_hash%_", &hmr_hash.to_string());
+    let runtime_content =
+        entry_runtime_code(chunk, context)?.replace("_%full_hash%_", &hmr_hash.to_string());
 
-    let entry_prefix_code = "!(function(){\n";
-
-    let (chunk_content, chunk_raw_sourcemap) =
-        pot_to_chunk_module_object_string(pot, context, entry_prefix_code.lines().count() as u32)?;
+    let entry_prefix_code = match entry_shebang(pot, chunk, context) {
+        Some(shebang) => format!("#!{}\n!(function(){{\n", shebang),
+        None => "!(function(){\n".to_string(),
+    };
 
-    let mut content: Vec<u8> = format!("var m = {};", chunk_content).into();
+    let (chunk_content, source_map_buf) =
+        render_entry_chunk_modules(pot, context, entry_prefix_code.lines().count() as u32)?;
 
-    {
-        content.splice(0..0, entry_prefix_code.bytes());
-        content.extend(lines.join("\n").into_bytes());
-        content.extend(runtime_content.into_bytes());
-        content.extend("\n})();".as_bytes());
-    }
-
-    let mut source_map_buf: Vec<u8> = vec![];
-    sourcemap::SourceMap::from(chunk_raw_sourcemap).to_writer(&mut source_map_buf)?;
+    let mut content = Fragments::default();
+    content.push(entry_prefix_code);
+    content.push("var m = ");
+    content.extend(chunk_content);
+    content.push(";");
+    content.push(lines.join("\n"));
+    content.push(runtime_content);
+    content.push("\n})();");
 
     Ok(ChunkFile {
         raw_hash: hmr_hash,
-        content,
+        content: content.into_bytes(),
         hash: None,
         source_map: Some(source_map_buf),
         file_name: pot.js_name.clone(),
@@ -105,15 +111,99 @@ pub(super) fn render_entry_js_chunk(
         file_type: ChunkFileType::JS,
         file_name_template: None,
         chunk_name: pot.chunk_name.clone(),
+        module_sizes: module_sizes(pot, context)?,
+        critical_css: None,
+    })
+}
+
+// the chunk maps are serialized in the order of chunk ids, so the output is
+// the same in every build
+fn sorted_map_json(map: &HashMap<String, String>) -> String {
+    serde_json::to_string(&map.iter().collect::<BTreeMap<_, _>>()).unwrap()
+}
+
+// the modules of the entry chunk and their serialized source map, the map is
+// reused until any module of the chunk changes, since the runtime after them
+// changes in every build with the full hash
+fn render_entry_chunk_modules(
+    pot: &ChunkPot,
+    context: &Arc<Context>,
+    chunk_prefix_offset: u32,
+) -> Result<(Fragments, Vec<u8>)> {
+    let modules = emit_chunk_modules(pot, context)?;
+    let source_map = chunk_source_map(pot, &modules, chunk_prefix_offset, context);
+
+    Ok((modules_content(&modules), source_map))
+}
+
+// in watch mode, the map is reused if the modules of the chunk are not
+// changed, see `SourceMapCache`
+fn chunk_source_map(
+    pot: &ChunkPot,
+    modules: &[ChunkModuleWithKey],
+    chunk_prefix_offset: u32,
+    context: &Arc<Context>,
+) -> Vec<u8> {
+    if !context.args.watch {
+        return merged_source_map(modules, chunk_prefix_offset, context);
+    }
+
+    let key = format!("{}.{:x}.{}", pot.chunk_id, pot.js_hash, chunk_prefix_offset);
+    context.source_map_cache.chunk_map(&key, context, || {
+        assembled_source_map(modules, chunk_prefix_offset, context)
     })
 }
 
+// assembled from the cached mappings of the modules, so only the changed
+// modules are encoded again, or merged if the sources must be optimized
+fn assembled_source_map(
+    modules: &[ChunkModuleWithKey],
+    chunk_prefix_offset: u32,
+    context: &Arc<Context>,
+) -> Vec<u8> {
+    let mut dst_line = chunk_prefix_offset + MODULE_PREFIX_LINES;
+    let chunk_modules = modules
+        .iter()
+        .filter_map(|(key, (content, source_map))| {
+            let module = source_map.as_ref().map(|map| ChunkModule {
+                key: key.clone(),
+                map,
+                dst_line,
+            });
+            dst_line += content.lines().count() as u32;
+            module
+        })
+        .collect::<Vec<_>>();
+    context
+        .source_map_cache
+        .assemble(&chunk_modules, context)
+        .unwrap_or_else(|| merged_source_map(modules, chunk_prefix_offset, context))
+}
+
+fn merged_source_map(
+    modules: &[ChunkModuleWithKey],
+    chunk_prefix_offset: u32,
+    context: &Context,
+) -> Vec<u8> {
+    let (_, chunk_raw_sourcemap) = merge_code_and_sourcemap(
+        modules.iter().map(|(_, module)| module.clone()).collect(),
+        chunk_prefix_offset,
+    );
+    serialize_source_map(chunk_raw_sourcemap, context)
+}
+
+fn serialize_source_map(raw_source_map: RawSourceMap, context: &Context) -> Vec<u8> {
+    crate::mako_profile_function!();
+
+    source_map_to_buf(&sourcemap::SourceMap::from(raw_source_map), context)
+}
+
 #[cached(
     result = true,
     type = "SizedCache<String , ChunkFile>",
     create = "{ SizedCache::with_size(500) }",
     key = "String",
-    convert = r#"{format!("{}.{:x}", chunk_pot.chunk_id, chunk_pot.js_hash)}"#
+    convert = r#"{format!("{}.{:x}.{:x}", chunk_pot.chunk_id, chunk_pot.js_hash, context.cache_keys.minify)}"#
 )]
 pub(super) fn render_normal_js_chunk(
     chunk_pot: &ChunkPot,
@@ -131,24 +221,33 @@ pub(super) fn render_normal_js_chunk(
             safe_prop, safe_prop, pot.chunk_id,
         );
 
-        let (chunk_content, chunk_raw_sourcemap) = pot_to_chunk_module_object_string(
+        let modules = emit_chunk_modules(pot, context)?;
+        let source_map = chunk_source_map(
             pot,
-            context,
+            &modules,
             chunk_prefix_code.lines().count() as u32,
-        )?;
-
-        let mut source_map_buf: Vec<u8> = vec![];
-        sourcemap::SourceMap::from(chunk_raw_sourcemap).to_writer(&mut source_map_buf)?;
+            context,
+        );
 
-        (
-            format!("{}\n{}]);", chunk_prefix_code, chunk_content),
-            source_map_buf,
-        )
+        // the entry module is executed by the runtime of the entries it
+        // depends on, see the dependOn of the entries
+        let chunk_suffix_code = match &pot.entry_module_id {
+            Some(module_id) => format!(", {}", serde_json::to_string(&[module_id]).unwrap()),
+            None => "".to_string(),
+        };
+
+        let mut content = Fragments::default();
+        content.push(chunk_prefix_code);
+        content.push("\n");
+        content.extend(modules_content(&modules));
+        content.push(chunk_suffix_code);
+        content.push("]);");
+        (content, source_map)
     };
 
     Ok(ChunkFile {
         raw_hash: chunk_pot.js_hash,
-        content: content_buf.into(),
+        content: content_buf.into_bytes(),
         hash: None,
         source_map: Some(source_map_buf),
         file_name: chunk_pot.js_name.clone(),
@@ -156,17 +255,24 @@ pub(super) fn render_normal_js_chunk(
         file_type: ChunkFileType::JS,
         file_name_template: None,
         chunk_name: chunk_pot.chunk_name.clone(),
+        module_sizes: module_sizes(chunk_pot, context)?,
+        critical_css: None,
     })
 }
 
-type EmittedWithMapping = (String, Option<RawSourceMap>);
+// shared with the cache, so a module is not copied when it's rendered in a
+// chunk again
+type EmittedWithMapping = (Arc<String>, Option<Arc<RawSourceMap>>);
+
+// the banner comment and the function header before the module code
+const MODULE_PREFIX_LINES: u32 = 2;
 
 #[cached(
     result = true,
     key = "String",
     type = "SizedCache<String , EmittedWithMapping>",
     create = "{ SizedCache::with_size(20000) }",
-    convert = r#"{format!("{}-{}", _raw_hash, module_id)}"#
+    convert = r#"{format!("{}-{}-{:x}", _raw_hash, module_id, context.cache_keys.codegen)}"#
 )]
 fn emit_module_with_mapping(
     module_id: &str,
@@ -178,7 +284,7 @@ fn emit_module_with_mapping(
         ModuleAst::Script(ast) => {
             let cm = context.meta.script.cm.clone();
             let comments = context.meta.script.origin_comments.read().unwrap();
-            let swc_comments = comments.get_swc_comments();
+            let emitted_comments = comments.get_emitted_comments();
 
             let mut buf = vec![];
             let mut source_mappings = Vec::new();
@@ -189,7 +295,7 @@ fn emit_module_with_mapping(
                     .with_ascii_only(false)
                     .with_omit_last_semi(true),
                 cm: cm.clone(),
-                comments: Some(swc_comments),
+                comments: Some(&emitted_comments),
                 wr: Box::new(JsWriter::new(
                     cm.clone(),
                     "\n",
@@ -199,26 +305,32 @@ fn emit_module_with_mapping(
             };
             emitter.emit_module(&ast.ast)?;
 
-            let source_map = build_source_map(&source_mappings, &cm);
+            let source_map = build_source_map(&source_mappings, &cm, context);
 
             let content = { String::from_utf8_lossy(&buf) };
             Ok((
-                format!(
-                    r#""{}": function (module, exports, __mako_require__){{
+                Arc::new(format!(
+                    r#"/* {} */
+"{}": function (module, exports, __mako_require__){{
 {}
 }},
 "#,
-                    module_id, content
-                ),
-                Some(source_map.into()),
+                    module_banner(module, context),
+                    module_id,
+                    content
+                )),
+                Some(Arc::new(source_map.into())),
             ))
         }
         ModuleAst::Css(_) => Ok((
-            format!(
-                r#""{}" : function (module, exports, __mako_require__){{
-  }},"#,
+            Arc::new(format!(
+                r#"/* {} */
+"{}" : function (module, exports, __mako_require__){{
+  }},
+"#,
+                module_banner(module, context),
                 module_id,
-            ),
+            )),
             None,
         )),
 
@@ -226,11 +338,28 @@ fn emit_module_with_mapping(
     }
 }
 
-fn pot_to_chunk_module_object_string(
-    pot: &ChunkPot,
-    context: &Arc<Context>,
-    chunk_prefix_offset: u32,
-) -> Result<(String, RawSourceMap)> {
+// the emitted modules are cached, so they are not emitted again
+fn module_sizes(pot: &ChunkPot, context: &Arc<Context>) -> Result<Vec<(String, u64)>> {
+    if !collect_module_sizes(context) {
+        return Ok(vec![]);
+    }
+    let mut sizes = pot
+        .module_map
+        .iter()
+        .map(|(module_id, (module, raw_hash))| {
+            let (content, _) = emit_module_with_mapping(module_id, module, *raw_hash, context)?;
+            Ok((module.id.id.to_string(), content.len() as u64))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    sizes.sort();
+    Ok(sizes)
+}
+
+// an emitted module with the key of its mappings in `SourceMapCache`
+type ChunkModuleWithKey = (String, EmittedWithMapping);
+
+// the emitted modules of the pot in the order of the module ids
+fn emit_chunk_modules(pot: &ChunkPot, context: &Arc<Context>) -> Result<Vec<ChunkModuleWithKey>> {
     let sorted_kv = {
         let mut sorted_kv = pot.module_map.iter().collect::<Vec<_>>();
 
@@ -239,61 +368,85 @@ fn pot_to_chunk_module_object_string(
         sorted_kv
     };
 
-    let emitted_modules_with_mapping = sorted_kv
+    sorted_kv
         .par_iter()
-        .map(|(module_id, module_and_hash)| {
-            emit_module_with_mapping(module_id, module_and_hash.0, module_and_hash.1, context)
+        .map(|(module_id, (module, raw_hash))| {
+            let emitted = emit_module_with_mapping(module_id, module, *raw_hash, context)?;
+            Ok((format!("{}-{}", raw_hash, module_id), emitted))
         })
-        .collect::<Result<Vec<(String, Option<RawSourceMap>)>>>()?;
-
-    let (chunk_content, chunk_raw_sourcemap) =
-        merge_code_and_sourcemap(emitted_modules_with_mapping, chunk_prefix_offset);
+        .collect()
+}
 
-    Ok((format!(r#"{{ {} }}"#, chunk_content), chunk_raw_sourcemap))
+fn modules_content(modules: &[ChunkModuleWithKey]) -> Fragments {
+    let mut chunk_content = Fragments::default();
+    chunk_content.push("{ ");
+    for (_, (module_content, _)) in modules {
+        chunk_content.push_shared(module_content.clone());
+    }
+    chunk_content.push(" }");
+    chunk_content
 }
 
 fn merge_code_and_sourcemap(
     modules_with_sourcemap: Vec<EmittedWithMapping>,
     chunk_prefix_offset: u32,
-) -> (String, RawSourceMap) {
+) -> (Fragments, RawSourceMap) {
+    crate::mako_profile_function!();
+
     let mut dst_line_offset = 0u32;
     let mut src_id_offset = 0u32;
     let mut name_id_offset = 0u32;
     let (chunk_content, chunk_raw_sourcemap) = modules_with_sourcemap.into_iter().fold(
-        (String::new(), RawSourceMap::default()),
+        (Fragments::default(), RawSourceMap::default()),
         |(mut chunk_content, mut chunk_raw_sourcemap), (module_content, source_mapping)| {
-            chunk_content.push_str(&module_content);
+            // modules without mappings, e.g. css modules, take lines too
+            let module_lines = module_content.lines().count() as u32;
+            chunk_content.push_shared(module_content);
 
-            if let Some(mut mappings) = source_mapping {
+            if let Some(mappings) = source_mapping {
                 chunk_raw_sourcemap
                     .tokens
-                    .extend(mappings.tokens.drain(0..).map(|t| sourcemap::RawToken {
-                        // 1. in emit_module_with_sourcemap, we have added 1 line code before module output,
-                        //    need to add 1
+                    .extend(mappings.tokens.iter().map(|t| sourcemap::RawToken {
+                        // 1. in emit_module_with_sourcemap, we have added the banner and the function
+                        //    header before module output, need to add MODULE_PREFIX_LINES
                         // 2. we also have added some prefix code lines in entry chunks or normal
                         //    chunks before chunk output, which it's lines count been stored in PrefixCode,
                         //    need to add its line count
                         // 3. we need to add all code lines count of modules before current
-                        dst_line: t.dst_line + 1 + chunk_prefix_offset + dst_line_offset,
-                        src_id: t.src_id + src_id_offset,
-                        name_id: t.name_id + name_id_offset,
-                        ..t
+                        dst_line: t.dst_line
+                            + MODULE_PREFIX_LINES
+                            + chunk_prefix_offset
+                            + dst_line_offset,
+                        // the tokens without source end the mapping of the code before
+                        src_id: if t.src_id == !0 {
+                            !0
+                        } else {
+                            t.src_id + src_id_offset
+                        },
+                        name_id: if t.name_id == !0 {
+                            !0
+                        } else {
+                            t.name_id + name_id_offset
+                        },
+                        ..*t
                     }));
 
-                chunk_raw_sourcemap.names.extend(mappings.names.drain(0..));
+                chunk_raw_sourcemap
+                    .names
+                    .extend(mappings.names.iter().cloned());
 
                 chunk_raw_sourcemap
                     .sources
-                    .extend(mappings.sources.drain(0..));
+                    .extend(mappings.sources.iter().cloned());
 
                 chunk_raw_sourcemap
                     .sources_content
-                    .extend(mappings.sources_content.drain(0..));
+                    .extend(mappings.sources_content.iter().cloned());
 
                 name_id_offset = chunk_raw_sourcemap.names.len() as u32;
                 src_id_offset = chunk_raw_sourcemap.sources.len() as u32;
-                dst_line_offset += module_content.lines().count() as u32;
             }
+            dst_line_offset += module_lines;
 
             (chunk_content, chunk_raw_sourcemap)
         },
@@ -301,9 +454,35 @@ fn merge_code_and_sourcemap(
     (chunk_content, chunk_raw_sourcemap)
 }
 
+pub(super) fn sweep_render_caches() {
+    sweep_stale_entries(&mut RENDER_NORMAL_JS_CHUNK.lock().unwrap());
+    sweep_stale_entries(&mut EMIT_MODULE_WITH_MAPPING.lock().unwrap());
+}
+
+pub(super) fn render_cache_usages() -> Vec<CacheUsage> {
+    vec![
+        sized_cache_usage(
+            "rendered js chunks (parallel)",
+            &RENDER_NORMAL_JS_CHUNK.lock().unwrap(),
+            |file| file.content.len() + file.source_map.as_ref().map_or(0, Vec::len),
+        ),
+        sized_cache_usage(
+            "emitted modules",
+            &EMIT_MODULE_WITH_MAPPING.lock().unwrap(),
+            |(content, source_map)| {
+                content.len()
+                    + source_map
+                        .as_ref()
+                        .map_or(0, |source_map| source_map.estimated_bytes())
+            },
+        ),
+    ]
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
+    use std::time::Instant;
 
     use anyhow::Result;
     use swc_core::base::sourcemap;
@@ -315,11 +494,15 @@ mod tests {
     use swc_core::ecma::transforms::base::{hygiene, resolver};
     use swc_core::ecma::visit::VisitMutWith;
 
-    use super::{merge_code_and_sourcemap, EmittedWithMapping};
+    use super::{
+        assembled_source_map, merge_code_and_sourcemap, merged_source_map, ChunkModuleWithKey,
+        EmittedWithMapping, MODULE_PREFIX_LINES,
+    };
     use crate::ast::js_ast::JsAst;
-    use crate::ast::sourcemap::build_source_map;
+    use crate::ast::sourcemap::{build_source_map, RawSourceMap};
     use crate::compiler::{Args, Context};
     use crate::config::{Config, Mode};
+    use crate::generate::chunk_pot::fragments::Fragments;
     use crate::generate::minify::minify_js;
 
     #[test]
@@ -327,7 +510,7 @@ mod tests {
         let context = Arc::new(Context {
             config: Config {
                 mode: Mode::Development,
-                minify: true,
+                minify: Some(Default::default()),
                 ..Default::default()
             },
             args: Args { watch: true },
@@ -359,9 +542,9 @@ mod tests {
 
             let emitted_add_code = emitted_add.0.clone();
             let emitted_add_sourcemap: sourcemap::SourceMap =
-                emitted_add.1.as_ref().unwrap().clone().into();
+                emitted_add.1.as_deref().unwrap().clone().into();
             let emitted_sub_sourcemap: sourcemap::SourceMap =
-                emitted_sub.1.as_ref().unwrap().clone().into();
+                emitted_sub.1.as_deref().unwrap().clone().into();
 
             let chunk_prefix_offset = 1u32;
 
@@ -370,8 +553,8 @@ mod tests {
 
             let merged_sourcemap: sourcemap::SourceMap = merged_code_and_sourcemap.1.into();
 
-            // in fn emit_module_with_sourcemap, we add 1 line prefix code before module output
-            let emit_module_with_sourcemap_gap = 1u32;
+            // in fn emit_module_with_sourcemap, we add prefix lines before module output
+            let emit_module_with_sourcemap_gap = MODULE_PREFIX_LINES;
 
             assert_eq!(
                 emitted_add_sourcemap
@@ -404,6 +587,219 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_merge_modules_without_sourcemap() {
+        let context = Arc::new(Context {
+            config: Config {
+                mode: Mode::Development,
+                ..Default::default()
+            },
+            args: Args { watch: true },
+            ..Default::default()
+        });
+
+        GLOBALS.set(&context.meta.script.globals, || {
+            let emitted_add =
+                build_file("add.js", "function add(a, b) { return a + b; }", &context).unwrap();
+            let add_sourcemap: sourcemap::SourceMap =
+                emitted_add.1.as_deref().unwrap().clone().into();
+            let emitted_css = (
+                Arc::new(
+                    "/* ./a.css */\n\"./a.css\" : function (module, exports, __mako_require__){\n  },\n"
+                        .to_string(),
+                ),
+                None,
+            );
+
+            let (_, merged_sourcemap) = merge_code_and_sourcemap(vec![emitted_css, emitted_add], 0);
+            let merged_sourcemap: sourcemap::SourceMap = merged_sourcemap.into();
+
+            // the lines of the css module are counted
+            assert_eq!(
+                add_sourcemap
+                    .tokens()
+                    .map(|t| t.get_dst_line() + MODULE_PREFIX_LINES + 3)
+                    .collect::<Vec<u32>>(),
+                merged_sourcemap
+                    .tokens()
+                    .map(|t| t.get_dst_line())
+                    .collect::<Vec<u32>>()
+            );
+        });
+    }
+
+    #[test]
+    fn test_assembled_source_map() {
+        let context = Arc::new(Context {
+            config: Config {
+                mode: Mode::Development,
+                ..Default::default()
+            },
+            args: Args { watch: true },
+            ..Default::default()
+        });
+
+        GLOBALS.set(&context.meta.script.globals, || {
+            let module = |file: &str, code: &str| -> ChunkModuleWithKey {
+                (
+                    format!("0-./{}", file),
+                    build_file(file, code, &context).unwrap(),
+                )
+            };
+            let add = module(
+                "add.js",
+                "function add(a, b) {\n  return a + b;\n}\nconsole.log(add(1, 2));",
+            );
+            let sub = module(
+                "sub.js",
+                "function sub(a, b) {\n  return a - b;\n}\nconsole.log(sub(2, 1));",
+            );
+            let css = (
+                "0-./a.css".to_string(),
+                (
+                    Arc::new(
+                        "/* ./a.css */\n\"./a.css\" : function (module, exports, __mako_require__){\n  },\n"
+                            .to_string(),
+                    ),
+                    None,
+                ),
+            );
+
+            // the mappings of the modules are cached by the first chunk, and
+            // reused by the second one at other lines
+            let chunks = [
+                vec![add.clone(), css.clone(), sub.clone()],
+                vec![sub, add, css],
+            ];
+            for modules in chunks {
+                assert_eq!(
+                    String::from_utf8(assembled_source_map(&modules, 1, &context)).unwrap(),
+                    String::from_utf8(merged_source_map(&modules, 1, &context)).unwrap()
+                );
+            }
+        });
+    }
+
+    // cargo test --release bench_chunk_source_map -- --ignored --nocapture
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_chunk_source_map() {
+        let context = Arc::new(Context {
+            args: Args { watch: true },
+            ..Default::default()
+        });
+        // a module of 200 lines, the hash is changed when it's changed
+        let module = |i: usize, hash: u32| -> ChunkModuleWithKey {
+            let code = "var a = 1;\n".repeat(200);
+            let tokens = (0..200)
+                .flat_map(|line| {
+                    [0, 4, 8].map(|col| sourcemap::RawToken {
+                        dst_line: line,
+                        dst_col: col,
+                        src_line: line,
+                        src_col: col,
+                        src_id: 0,
+                        name_id: !0,
+                        is_range: false,
+                    })
+                })
+                .collect();
+            let source_map = RawSourceMap {
+                tokens,
+                sources: vec![format!("src/{}.js", i)],
+                sources_content: vec![Some(code.clone())],
+                ..Default::default()
+            };
+            (
+                format!("{}-./src/{}.js", hash, i),
+                (
+                    Arc::new(format!(
+                        "/* {} */\n\"{}\": function (module, exports, __mako_require__){{\n{}\n}},\n",
+                        i, i, code
+                    )),
+                    Some(Arc::new(source_map)),
+                ),
+            )
+        };
+        // 2000 modules, one of which is changed in each build
+        let mut modules = (0..2000).map(|i| module(i, 0)).collect::<Vec<_>>();
+        assembled_source_map(&modules, 1, &context);
+        let rounds: u32 = 10;
+
+        // how the map was built before, from the tokens of all the modules
+        let start = Instant::now();
+        for round in 1..=rounds {
+            modules[0] = module(0, round);
+            merged_source_map(&modules, 1, &context);
+        }
+        let merged = start.elapsed();
+
+        let start = Instant::now();
+        for round in 1..=rounds {
+            modules[0] = module(0, rounds + round);
+            assembled_source_map(&modules, 1, &context);
+        }
+        let assembled = start.elapsed();
+
+        println!(
+            "source map of a chunk of 2000 modules, merged: {:?}, assembled: {:?}",
+            merged / rounds,
+            assembled / rounds
+        );
+        assert!(assembled < merged);
+    }
+
+    // RAYON_NUM_THREADS=4 cargo test --release bench_join_large_chunk -- --ignored --nocapture
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_join_large_chunk() {
+        // 2000 modules of 10 KB, a 20 MB chunk
+        let modules = (0..2000)
+            .map(|i| {
+                let code = format!("/* {} */\n{}", i, "var a = 1;\n".repeat(1000));
+                (Arc::new(code), None)
+            })
+            .collect::<Vec<EmittedWithMapping>>();
+        let prefix = "!(function(){\n".to_string();
+        let rounds: u32 = 10;
+
+        // how the content was assembled before, with a string of each step
+        let start = Instant::now();
+        for _ in 0..rounds {
+            let mut modules_content = String::new();
+            for (code, _) in &modules {
+                modules_content.push_str(code);
+            }
+            let chunk_content = format!("{{ {} }}", modules_content);
+            let mut content: Vec<u8> = format!("var m = {};", chunk_content).into();
+            content.splice(0..0, prefix.bytes());
+            content.extend("\n})();".as_bytes());
+            assert!(content.len() > 20_000_000);
+        }
+        let strings = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..rounds {
+            let (chunk_content, _) = merge_code_and_sourcemap(modules.clone(), 0);
+            let mut content = Fragments::default();
+            content.push(prefix.clone());
+            content.push("var m = { ");
+            content.extend(chunk_content);
+            content.push(" };");
+            content.push("\n})();");
+            let content = content.into_bytes();
+            assert!(content.len() > 20_000_000);
+        }
+        let fragments = start.elapsed();
+
+        println!(
+            "join a 20 MB chunk, strings: {:?}, fragments: {:?}",
+            strings / rounds,
+            fragments / rounds
+        );
+        assert!(fragments < strings);
+    }
+
     fn build_file(file: &str, code: &str, context: &Arc<Context>) -> Result<EmittedWithMapping> {
         let mut ast = JsAst::build(file, code, context.clone()).unwrap();
 
@@ -422,10 +818,10 @@ mod tests {
         let mut source_map_buf = Vec::new();
         let cm = &context.meta.script.cm;
         let comments = context.meta.script.origin_comments.read().unwrap();
-        let swc_comments = comments.get_swc_comments();
+        let emitted_comments = comments.get_emitted_comments();
         {
             let with_minify =
-                context.config.minify && matches!(context.config.mode, Mode::Production);
+                context.config.minify.is_some() && matches!(context.config.mode, Mode::Production);
             let mut emitter = Emitter {
                 cfg: JsCodegenConfig::default()
                     .with_minify(with_minify)
@@ -433,7 +829,7 @@ mod tests {
                     .with_ascii_only(with_minify)
                     .with_omit_last_semi(true),
                 cm: cm.clone(),
-                comments: (!with_minify).then_some(swc_comments as &dyn Comments),
+                comments: (!with_minify).then_some(&emitted_comments as &dyn Comments),
                 wr: Box::new(JsWriter::new(
                     cm.clone(),
                     "\n",
@@ -445,7 +841,7 @@ mod tests {
         }
 
         let code = String::from_utf8(buf)?;
-        let source_map = build_source_map(&source_map_buf, cm);
-        Ok((code, Some(source_map.into())))
+        let source_map = build_source_map(&source_map_buf, cm, context);
+        Ok((Arc::new(code), Some(Arc::new(source_map.into()))))
     }
 }
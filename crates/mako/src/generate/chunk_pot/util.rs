@@ -1,9 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
-use md5;
 use sailfish::TemplateOnce;
 use swc_core::base::try_with_handler;
 use swc_core::common::comments::{Comment, CommentKind, Comments};
@@ -11,20 +10,24 @@ use swc_core::common::errors::HANDLER;
 use swc_core::common::{Span, DUMMY_SP, GLOBALS};
 use swc_core::ecma::ast::{
     ArrayLit, AssignOp, BinaryOp, BlockStmt, CondExpr, Expr, ExprOrSpread, FnExpr, Function,
-    KeyValueProp, Module as SwcModule, ObjectLit, Prop, PropOrSpread, UnaryExpr, UnaryOp,
+    KeyValueProp, Module as SwcModule, ObjectLit, Prop, PropName, PropOrSpread, UnaryExpr, UnaryOp,
 };
-use swc_core::ecma::atoms::js_word;
+use swc_core::ecma::atoms::{js_word, Atom};
 use swc_core::ecma::codegen::text_writer::JsWriter;
-use swc_core::ecma::codegen::{Config as JsCodegenConfig, Emitter};
+use swc_core::ecma::codegen::{Config as JsCodegenConfig, Emitter, Node};
 use swc_core::ecma::utils::{quote_ident, quote_str, ExprFactory};
+use swc_core::ecma::visit::{Visit, VisitWith};
 use twox_hash::XxHash64;
 
 use crate::ast::sourcemap::build_source_map_to_buf;
 use crate::compiler::Context;
 use crate::config::Mode;
+use crate::generate::chunk::{Chunk, ChunkType};
+use crate::generate::chunk_graph::ChunkGraph;
 use crate::generate::chunk_pot::ChunkPot;
-use crate::generate::runtime::AppRuntimeTemplate;
-use crate::module::{relative_to_root, Module, ModuleAst};
+use crate::generate::inline_chunks::inline_runtime;
+use crate::generate::runtime::{downlevel_runtime_code, AppRuntimeTemplate};
+use crate::module::{Module, ModuleAst};
 use crate::utils::get_pkg_name;
 
 pub(crate) fn render_module_js(
@@ -36,21 +39,17 @@ pub(crate) fn render_module_js(
     let mut buf = vec![];
     let mut source_map_buf = Vec::new();
     let cm = context.meta.script.cm.clone();
-    let with_minify = context.config.minify && matches!(context.config.mode, Mode::Production);
+    let with_minify = with_minify(context);
     let comments = context.meta.script.origin_comments.read().unwrap();
-    let swc_comments = comments.get_swc_comments();
+    let emitted_comments = comments.get_emitted_comments();
 
     let mut emitter = Emitter {
-        cfg: JsCodegenConfig::default()
-            .with_minify(with_minify)
-            .with_target(context.config.output.es_version)
-            .with_ascii_only(with_minify)
-            .with_omit_last_semi(true),
+        cfg: js_codegen_config(context),
         cm: cm.clone(),
         comments: if with_minify {
             None
         } else {
-            Some(swc_comments)
+            Some(&emitted_comments)
         },
         wr: Box::new(JsWriter::new(cm, "\n", &mut buf, Some(&mut source_map_buf))),
     };
@@ -61,13 +60,121 @@ pub(crate) fn render_module_js(
         crate::mako_profile_scope!("build_source_map");
         match context.config.devtool {
             None => None,
-            _ => Some(build_source_map_to_buf(&source_map_buf, cm)),
+            _ => Some(build_source_map_to_buf(&source_map_buf, cm, context)),
         }
     };
 
     Ok((buf, source_map))
 }
 
+fn with_minify(context: &Arc<Context>) -> bool {
+    context.config.minify.is_some() && matches!(context.config.mode, Mode::Production)
+}
+
+fn js_codegen_config(context: &Arc<Context>) -> JsCodegenConfig {
+    let with_minify = with_minify(context);
+    JsCodegenConfig::default()
+        .with_minify(with_minify)
+        .with_target(context.config.output.es_version)
+        .with_ascii_only(with_minify)
+        .with_omit_last_semi(true)
+}
+
+/// Whether the chunks record the sizes of their modules, for the analyze
+/// report and the package stats.
+pub(crate) fn collect_module_sizes(context: &Arc<Context>) -> bool {
+    context.config.analyze.is_some()
+        || context
+            .config
+            .stats
+            .as_ref()
+            .is_some_and(|stats| stats.packages)
+}
+
+/// The bytes of each module factory in the rendered chunk, by module path. The
+/// factories are found by their keys in the module object and emitted alone
+/// with the config of the chunk, so the sizes are the minified ones in
+/// production.
+pub(crate) fn module_sizes(
+    ast: &SwcModule,
+    pot: &ChunkPot,
+    context: &Arc<Context>,
+) -> Result<Vec<(String, u64)>> {
+    crate::mako_profile_function!();
+
+    let mut collector = ModuleSizeCollector {
+        pot,
+        context,
+        sizes: HashMap::new(),
+        error: None,
+    };
+    ast.visit_with(&mut collector);
+    if let Some(e) = collector.error {
+        return Err(e);
+    }
+    let mut sizes = collector.sizes.into_iter().collect::<Vec<_>>();
+    sizes.sort();
+    Ok(sizes)
+}
+
+struct ModuleSizeCollector<'a, 'p> {
+    pot: &'p ChunkPot<'a>,
+    context: &'p Arc<Context>,
+    sizes: HashMap<String, u64>,
+    error: Option<anyhow::Error>,
+}
+
+impl ModuleSizeCollector<'_, '_> {
+    fn emitted_size(&self, prop: &KeyValueProp) -> Result<u64> {
+        let context = self.context;
+        let cm = context.meta.script.cm.clone();
+        let comments = context.meta.script.origin_comments.read().unwrap();
+        let emitted_comments = comments.get_emitted_comments();
+        let mut buf = vec![];
+        let mut emitter = Emitter {
+            cfg: js_codegen_config(context),
+            cm: cm.clone(),
+            comments: if with_minify(context) {
+                None
+            } else {
+                Some(&emitted_comments)
+            },
+            wr: Box::new(JsWriter::new(cm, "\n", &mut buf, None)),
+        };
+        prop.emit_with(&mut emitter)?;
+        // and the comma after it
+        Ok(buf.len() as u64 + 1)
+    }
+}
+
+impl Visit for ModuleSizeCollector<'_, '_> {
+    fn visit_key_value_prop(&mut self, n: &KeyValueProp) {
+        // keys may be turned into identifiers or numbers by the minifier
+        let key = match &n.key {
+            PropName::Str(str) => str.value.to_string(),
+            PropName::Ident(ident) => ident.sym.to_string(),
+            PropName::Num(num) => num.value.to_string(),
+            _ => String::new(),
+        };
+        // the chunk maps of the runtime are keyed by chunk ids, which may be
+        // the same as the module ids, but only the factories are functions
+        let is_factory = matches!(*n.value, Expr::Fn(_) | Expr::Arrow(_));
+        match self.pot.module_map.get(&key) {
+            // the module code is not visited, in case its keys collide with
+            // the module ids
+            Some((module, _)) if is_factory && !self.sizes.contains_key(module.id.id.as_str()) => {
+                match self.emitted_size(n) {
+                    Ok(size) => {
+                        self.sizes.insert(module.id.id.to_string(), size);
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+            _ => n.visit_children_with(self),
+        }
+    }
+}
+
 pub(crate) fn empty_module_fn_expr() -> FnExpr {
     let func = Function {
         span: DUMMY_SP,
@@ -95,6 +202,26 @@ pub(crate) fn empty_module_fn_expr() -> FnExpr {
 }
 
 pub(crate) fn runtime_code(context: &Arc<Context>) -> Result<String> {
+    render_runtime_code(context, false)
+}
+
+/// The runtime of the webworker entries, which loads the chunks by
+/// `importScripts` and has no stylesheets to load.
+pub(crate) fn webworker_runtime_code(context: &Arc<Context>) -> Result<String> {
+    render_runtime_code(context, true)
+}
+
+/// The runtime of the entry chunk, of the webworker entry or not.
+pub(crate) fn entry_runtime_code(chunk: &Chunk, context: &Arc<Context>) -> Result<String> {
+    match &chunk.chunk_type {
+        ChunkType::Entry(_, name, false) if context.config.is_webworker_entry(name) => {
+            webworker_runtime_code(context)
+        }
+        _ => runtime_code(context),
+    }
+}
+
+fn render_runtime_code(context: &Arc<Context>, is_webworker: bool) -> Result<String> {
     let umd = context.config.umd.as_ref().map(|umd| umd.name.clone());
     let umd_export = context.config.umd.as_ref().map_or(vec![], |umd| {
         umd.export
@@ -103,23 +230,58 @@ pub(crate) fn runtime_code(context: &Arc<Context>) -> Result<String> {
             .collect()
     });
     let chunk_graph = context.chunk_graph.read().unwrap();
-    let has_dynamic_chunks = chunk_graph.get_all_chunks().len() > 1;
+    // the modules of the entries are installed from the chunks when the
+    // runtime is inlined
+    let has_dynamic_chunks = chunk_graph.get_all_chunks().len() > 1 || inline_runtime(context);
     let has_hmr = context.args.watch;
+    let features = &context.runtime_features;
+    // the chunks of `import()`, the scripts of the externals, or the split
+    // chunks which may be loaded by the host through the chunk api
+    let has_chunk_loader = chunk_graph.get_all_chunks().len() > 1
+        || ["ensure", "ensure2", "loadScript"]
+            .iter()
+            .any(|member| features.uses_member(member, context));
+    // the css of the entries is loaded by the html
+    let has_css_loader = has_chunk_loader
+        && (has_hmr || {
+            let module_graph = context.module_graph.read().unwrap();
+            chunk_graph
+                .get_all_chunks()
+                .iter()
+                .filter(|chunk| !matches!(chunk.chunk_type, ChunkType::Entry(..)))
+                .flat_map(|chunk| chunk.get_modules())
+                .any(|module_id| {
+                    module_graph
+                        .get_module(module_id)
+                        .and_then(|module| module.info.as_ref())
+                        .is_some_and(|info| matches!(info.ast, ModuleAst::Css(_)))
+                })
+        });
     let app_runtime = AppRuntimeTemplate {
         has_dynamic_chunks,
         has_hmr,
+        has_chunk_loader,
+        has_css_loader,
+        has_dynamic_import: ["dr", "weak"]
+            .iter()
+            .any(|member| features.uses_member(member, context)),
         umd,
         umd_export,
-        is_browser: matches!(context.config.platform, crate::config::Platform::Browser),
+        is_browser: is_webworker
+            || matches!(context.config.platform, crate::config::Platform::Browser),
+        is_webworker,
         cjs: context.config.cjs,
         chunk_loading_global: serde_json::to_string(&context.config.output.chunk_loading_global)
             .unwrap(),
+        unique_name: serde_json::to_string(&context.config.output.unique_name).unwrap(),
+        chunk_loading_timeout: context.config.output.chunk_loading_timeout,
         cross_origin_loading: context
             .config
             .output
             .cross_origin_loading
             .clone()
             .map(|s| s.to_string()),
+        chunk_loading: context.config.output.chunk_loading.to_string(),
         pkg_name: get_pkg_name(&context.root),
         concatenate_enabled: context
             .config
@@ -127,13 +289,44 @@ pub(crate) fn runtime_code(context: &Arc<Context>) -> Result<String> {
             .as_ref()
             .map_or(false, |o| o.concatenate_modules.unwrap_or(false)),
         global_module_registry: context.config.output.global_module_registry,
+        is_dev: context.config.mode == Mode::Development,
+        devtools_timing: context.config.devtools_timing && context.config.mode == Mode::Development,
+        pre_bundled: context.config.pre_bundled,
+        unload_chunk_css: context.config.output.unload_chunk_css,
+        chunk_dependencies: if context.config.output.unload_chunk_css {
+            serde_json::to_string(&chunk_dependencies(&chunk_graph, context))?
+        } else {
+            "{}".to_string()
+        },
     };
     let app_runtime = app_runtime.render_once()?;
     let app_runtime = app_runtime.replace(
         "// __inject_runtime_code__",
         &context.plugin_driver.runtime_plugins_code(context)?,
     );
-    Ok(app_runtime)
+    downlevel_runtime_code(app_runtime, context)
+}
+
+// the chunks ensured with the async chunks, which `releaseChunk` releases
+// with them
+fn chunk_dependencies(
+    chunk_graph: &ChunkGraph,
+    context: &Arc<Context>,
+) -> BTreeMap<String, Vec<String>> {
+    chunk_graph
+        .get_all_chunks()
+        .into_iter()
+        .filter(|chunk| matches!(chunk.chunk_type, ChunkType::Async))
+        .filter_map(|chunk| {
+            let deps = chunk_graph.sync_dependencies_chunk(&chunk.id);
+            (!deps.is_empty()).then(|| {
+                (
+                    chunk.id.generate(context),
+                    deps.iter().map(|id| id.generate(context)).collect(),
+                )
+            })
+        })
+        .collect()
 }
 
 pub(crate) fn hash_hashmap<K, V>(map: &HashMap<K, V>) -> u64
@@ -171,6 +364,27 @@ pub(super) fn to_array_lit(elems: Vec<ExprOrSpread>) -> ArrayLit {
     }
 }
 
+/// The original path of the module, which is emitted as a comment before its
+/// factory.
+pub(crate) fn module_banner(module: &Module, context: &Arc<Context>) -> String {
+    let id = module.id.relative_display(&context.root);
+    // to avoid comment broken by glob=**/* for context module
+    id.replace("*/", "*\\/")
+}
+
+/// The shebang of the entry module, e.g. `/usr/bin/env node` of a cli, which is
+/// emitted as the first line of the entry chunk.
+pub(crate) fn entry_shebang(pot: &ChunkPot, chunk: &Chunk, context: &Arc<Context>) -> Option<Atom> {
+    let ChunkType::Entry(module_id, _, false) = &chunk.chunk_type else {
+        return None;
+    };
+    let (module, _) = pot.module_map.get(&module_id.generate(context))?;
+    match &module.info.as_ref()?.ast {
+        ModuleAst::Script(script) => script.ast.shebang.clone(),
+        _ => None,
+    }
+}
+
 pub(crate) fn pot_to_module_object(pot: &ChunkPot, context: &Arc<Context>) -> Result<ObjectLit> {
     crate::mako_profile_function!();
 
@@ -190,15 +404,12 @@ pub(crate) fn pot_to_module_object(pot: &ChunkPot, context: &Arc<Context>) -> Re
                     let fn_expr = to_module_fn_expr(module.0)?;
 
                     let span = Span::dummy_with_cmt();
-                    let id = relative_to_root(&module.0.id.id, &context.root);
-                    // to avoid comment broken by glob=**/* for context module
-                    let id = id.replace("*/", "*\\/");
                     comments.add_leading(
                         span.hi,
                         Comment {
                             kind: CommentKind::Block,
                             span: DUMMY_SP,
-                            text: id.into(),
+                            text: module_banner(module.0, context).into(),
                         },
                     );
                     let pv: PropOrSpread = Prop::KeyValue(KeyValueProp {
@@ -260,11 +471,18 @@ pub(crate) fn pot_to_chunk_module(
     let chunk_register_stmt = chunk_global_obj
         .as_call(
             DUMMY_SP,
-            // [[ "module id"], { module object }]
-            vec![to_array_lit(vec![
-                to_array_lit(vec![quote_str!(pot.chunk_id.clone()).as_arg()]).as_arg(),
-                module_object.as_arg(),
-            ])
+            // [[ "module id"], { module object }, [ "entry module id" ]?]
+            vec![to_array_lit(
+                [
+                    to_array_lit(vec![quote_str!(pot.chunk_id.clone()).as_arg()]).as_arg(),
+                    module_object.as_arg(),
+                ]
+                .into_iter()
+                .chain(pot.entry_module_id.iter().map(|module_id| {
+                    to_array_lit(vec![quote_str!(module_id.clone()).as_arg()]).as_arg()
+                }))
+                .collect(),
+            )
             .as_arg()],
         )
         .into_stmt();
@@ -334,12 +552,3 @@ fn to_module_fn_expr(module: &Module) -> Result<FnExpr> {
         ModuleAst::None => Err(anyhow!("ModuleAst::None({}) cannot concert", module.id.id)),
     }
 }
-
-pub const CHUNK_FILE_NAME_HASH_LENGTH: usize = 8;
-
-pub fn file_content_hash<T: AsRef<[u8]>>(content: T) -> String {
-    let digest = md5::compute(content);
-    let mut hash = format!("{:x}", digest);
-    hash.truncate(CHUNK_FILE_NAME_HASH_LENGTH);
-    hash
-}
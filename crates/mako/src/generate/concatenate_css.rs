@@ -0,0 +1,241 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use swc_core::common::Spanned;
+use swc_core::css::ast::{AtRule, ComponentValue, DeclarationName, Rule, Stylesheet};
+use swc_core::css::codegen::writer::basic::{BasicCssWriter, BasicCssWriterConfig};
+use swc_core::css::codegen::{CodeGenerator, CodegenConfig, Emit};
+
+// None means the properties are unknown, and the item may affect anything
+type Properties = Option<HashSet<String>>;
+
+/// Remove later copies of identical rules and merge adjacent identical media
+/// queries in a css chunk, returns the bytes saved.
+///
+/// A later copy is only removed when none of the rules between the two copies
+/// sets a property of the same group, so that the cascade order is kept.
+pub fn concatenate_css(stylesheet: &mut Stylesheet) -> Result<usize> {
+    let before = to_code(stylesheet)?.len();
+    merge_media(&mut stylesheet.rules)?;
+    dedupe(&mut stylesheet.rules, to_code, rule_properties)?;
+    // removing duplicates may make media queries adjacent
+    merge_media(&mut stylesheet.rules)?;
+    let after = to_code(stylesheet)?.len();
+    Ok(before.saturating_sub(after))
+}
+
+pub(crate) fn to_code<T: Spanned>(node: &T) -> Result<String>
+where
+    for<'a> CodeGenerator<BasicCssWriter<'a, &'a mut String>>: Emit<T>,
+{
+    let mut code = String::new();
+    {
+        let writer = BasicCssWriter::new(&mut code, None, BasicCssWriterConfig::default());
+        let mut gen = CodeGenerator::new(writer, CodegenConfig { minify: true });
+        gen.emit(node)?;
+    }
+    Ok(code)
+}
+
+fn dedupe<T>(
+    items: &mut Vec<T>,
+    key: impl Fn(&T) -> Result<String>,
+    properties: impl Fn(&T) -> Properties,
+) -> Result<()> {
+    let mut kept: Vec<(T, Properties)> = vec![];
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for item in items.drain(..) {
+        let key = key(&item)?;
+        let item_properties = properties(&item);
+        if let Some(&index) = seen.get(&key) {
+            let between = &kept[index + 1..];
+            let removable = match &item_properties {
+                Some(item_properties) => between
+                    .iter()
+                    .all(|(_, p)| p.as_ref().is_some_and(|p| p.is_disjoint(item_properties))),
+                None => between.is_empty(),
+            };
+            if removable {
+                continue;
+            }
+        }
+        seen.insert(key, kept.len());
+        kept.push((item, item_properties));
+    }
+    items.extend(kept.into_iter().map(|(item, _)| item));
+    Ok(())
+}
+
+fn merge_media(rules: &mut Vec<Rule>) -> Result<()> {
+    let mut merged: Vec<Rule> = vec![];
+    for rule in rules.drain(..) {
+        if let (Some(Rule::AtRule(last)), Rule::AtRule(current)) = (merged.last_mut(), &rule) {
+            if is_media(last)
+                && is_media(current)
+                && to_code(&last.prelude)? == to_code(&current.prelude)?
+            {
+                if let (Some(last_block), Some(block)) = (&mut last.block, &current.block) {
+                    last_block.value.extend(block.value.iter().cloned());
+                    continue;
+                }
+            }
+        }
+        merged.push(rule);
+    }
+    for rule in merged.iter_mut() {
+        if let Rule::AtRule(at_rule) = rule
+            && is_media(at_rule)
+            && let Some(block) = &mut at_rule.block
+        {
+            dedupe(&mut block.value, to_code, |value| {
+                component_values_properties(std::slice::from_ref(value))
+            })?;
+        }
+    }
+    *rules = merged;
+    Ok(())
+}
+
+fn is_media(at_rule: &AtRule) -> bool {
+    at_rule.name == *"media"
+}
+
+fn rule_properties(rule: &Rule) -> Properties {
+    match rule {
+        Rule::QualifiedRule(rule) => component_values_properties(&rule.block.value),
+        Rule::AtRule(rule) => at_rule_properties(rule),
+        Rule::ListOfComponentValues(_) => None,
+    }
+}
+
+fn at_rule_properties(at_rule: &AtRule) -> Properties {
+    // other at-rules like @font-face and @keyframes are not merged by properties
+    if at_rule.name != *"media" && at_rule.name != *"supports" {
+        return None;
+    }
+    match &at_rule.block {
+        Some(block) => component_values_properties(&block.value),
+        None => Some(HashSet::new()),
+    }
+}
+
+fn component_values_properties(values: &[ComponentValue]) -> Properties {
+    let mut properties = HashSet::new();
+    for value in values {
+        match value {
+            ComponentValue::Declaration(declaration) => {
+                let name = match &declaration.name {
+                    DeclarationName::Ident(ident) => ident.value.to_string(),
+                    DeclarationName::DashedIdent(ident) => format!("--{}", ident.value),
+                };
+                properties.insert(property_group(&name)?);
+            }
+            ComponentValue::QualifiedRule(rule) => {
+                properties.extend(component_values_properties(&rule.block.value)?)
+            }
+            ComponentValue::AtRule(rule) => properties.extend(at_rule_properties(rule)?),
+            _ => return None,
+        }
+    }
+    Some(properties)
+}
+
+// shorthands and longhands must be in the same group, so the grouping is
+// deliberately coarse, e.g. margin and margin-top are both `margin`
+fn property_group(name: &str) -> Option<String> {
+    if name.starts_with("--") {
+        return Some(name.to_string());
+    }
+    let name = name.to_ascii_lowercase();
+    // -webkit-box-shadow -> box-shadow
+    let name = match name.strip_prefix('-') {
+        Some(rest) => rest.split_once('-').map_or(rest, |(_, name)| name),
+        None => name.as_str(),
+    };
+    let group = match name {
+        "all" => return None,
+        "top" | "right" | "bottom" | "left" => "inset",
+        "width" | "height" | "inline-size" | "block-size" => "size",
+        "gap" | "row-gap" | "column-gap" | "grid-gap" | "grid-row-gap" | "grid-column-gap" => "gap",
+        "columns" => "column",
+        "line-height" => "font",
+        "white-space" => "text",
+        name if name.starts_with("place-")
+            || name.starts_with("align-")
+            || name.starts_with("justify-") =>
+        {
+            "align"
+        }
+        name => name.split('-').next().unwrap(),
+    };
+    Some(group.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ast::css_ast::CssAst;
+    use crate::compiler::Context;
+
+    fn run(content: &str) -> (String, usize) {
+        let context: Arc<Context> = Arc::new(Default::default());
+        let mut ast = CssAst::build("test.css", content, context, false).unwrap();
+        let saved = concatenate_css(&mut ast.ast).unwrap();
+        (to_code(&ast.ast).unwrap(), saved)
+    }
+
+    #[test]
+    fn test_remove_later_duplicates() {
+        let (code, saved) = run(".a{color:red}.b{margin:0}.a{color:red}.c{padding:0}");
+        assert_eq!(code, ".a{color:red}.b{margin:0}.c{padding:0}");
+        assert_eq!(saved, ".a{color:red}".len());
+    }
+
+    #[test]
+    fn test_keep_duplicates_which_affect_cascade() {
+        let code = ".a{color:red}.b{color:blue}.a{color:red}";
+        assert_eq!(run(code).0, code);
+        // shorthand and longhand
+        let code = ".a{margin:0}.b{margin-top:1px}.a{margin:0}";
+        assert_eq!(run(code).0, code);
+        // unknown at-rules
+        let code = ".a{color:red}@font-face{font-family:x}.a{color:red}";
+        assert_eq!(run(code).0, code);
+    }
+
+    #[test]
+    fn test_merge_adjacent_media() {
+        let (code, _) = run(
+            "@media (min-width:100px){.a{color:red}}@media (min-width:100px){.b{color:blue}.a{color:red}}",
+        );
+        assert_eq!(
+            code,
+            "@media(min-width:100px){.a{color:red}.b{color:blue}.a{color:red}}"
+        );
+
+        let (code, _) = run(
+            "@media print{.a{color:red}}.b{margin:0}@media print{.a{color:red}}@media screen{.c{color:red}}",
+        );
+        assert_eq!(
+            code,
+            "@media print{.a{color:red}}.b{margin:0}@media screen{.c{color:red}}"
+        );
+    }
+
+    #[test]
+    fn test_property_group() {
+        assert_eq!(property_group("margin-top"), Some("margin".to_string()));
+        assert_eq!(
+            property_group("-webkit-box-shadow"),
+            Some("box".to_string())
+        );
+        assert_eq!(property_group("left"), Some("inset".to_string()));
+        assert_eq!(
+            property_group("--main-color"),
+            Some("--main-color".to_string())
+        );
+        assert_eq!(property_group("all"), None);
+    }
+}
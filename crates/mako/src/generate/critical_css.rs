@@ -0,0 +1,308 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use glob_match::glob_match;
+use swc_core::common::{FileName, Span};
+use swc_core::css::ast::{AtRule, ComponentValue, QualifiedRule, QualifiedRulePrelude, Rule};
+
+use crate::compiler::Context;
+use crate::config::CriticalCssEntryConfig;
+use crate::generate::concatenate_css::to_code;
+use crate::generate::csp_report::nonce_attr;
+use crate::generate::generate_chunks::ChunkFile;
+use crate::stats::human_readable_size;
+
+/// Move the rules of an entry css chunk which match the selectors or the
+/// modules of criticalCss out of the rules, the order of both parts is kept.
+pub(crate) fn extract_critical_rules(
+    rules: &mut Vec<Rule>,
+    config: &CriticalCssEntryConfig,
+    context: &Arc<Context>,
+) -> Result<Vec<Rule>> {
+    let extractor = Extractor { config, context };
+    let mut critical = vec![];
+    let mut rest = vec![];
+    for rule in std::mem::take(rules) {
+        match rule {
+            Rule::QualifiedRule(qualified) => {
+                if extractor.is_critical(&qualified)? {
+                    critical.push(Rule::QualifiedRule(qualified));
+                } else {
+                    rest.push(Rule::QualifiedRule(qualified));
+                }
+            }
+            Rule::AtRule(at_rule) => {
+                let (critical_rule, rest_rule) = extractor.split_at_rule(*at_rule)?;
+                critical.extend(critical_rule.map(|rule| Rule::AtRule(Box::new(rule))));
+                rest.extend(rest_rule.map(|rule| Rule::AtRule(Box::new(rule))));
+            }
+            rule => rest.push(rule),
+        }
+    }
+    *rules = rest;
+    Ok(critical)
+}
+
+struct Extractor<'a> {
+    config: &'a CriticalCssEntryConfig,
+    context: &'a Arc<Context>,
+}
+
+impl Extractor<'_> {
+    fn is_critical_module(&self, span: Span) -> bool {
+        if self.config.modules.is_empty() || span.is_dummy() {
+            return false;
+        }
+        let file = self.context.meta.css.cm.lookup_char_pos(span.lo).file;
+        let FileName::Real(path) = &*file.name else {
+            return false;
+        };
+        let path = path.to_string_lossy();
+        self.config
+            .modules
+            .iter()
+            .any(|glob| glob_match(glob.trim_start_matches("./"), &path))
+    }
+
+    fn is_critical(&self, rule: &QualifiedRule) -> Result<bool> {
+        if self.is_critical_module(rule.span) {
+            return Ok(true);
+        }
+        let QualifiedRulePrelude::SelectorList(list) = &rule.prelude else {
+            return Ok(false);
+        };
+        for selector in &list.children {
+            let selector = to_code(selector)?;
+            if self
+                .config
+                .selectors
+                .iter()
+                .any(|glob| glob_match(glob, &selector))
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // the critical and the rest parts of the at rule, e.g. the matched rules
+    // of a media query
+    fn split_at_rule(&self, mut at_rule: AtRule) -> Result<(Option<AtRule>, Option<AtRule>)> {
+        if self.is_critical_module(at_rule.span) {
+            return Ok((Some(at_rule), None));
+        }
+        let has_rules = at_rule.block.as_ref().is_some_and(|block| {
+            block.value.iter().any(|value| {
+                matches!(
+                    value,
+                    ComponentValue::QualifiedRule(_) | ComponentValue::AtRule(_)
+                )
+            })
+        });
+        if !has_rules {
+            return Ok((None, Some(at_rule)));
+        }
+        let block = at_rule.block.as_mut().unwrap();
+        let mut critical = vec![];
+        let mut rest = vec![];
+        for value in std::mem::take(&mut block.value) {
+            match value {
+                ComponentValue::QualifiedRule(qualified) => {
+                    if self.is_critical(&qualified)? {
+                        critical.push(ComponentValue::QualifiedRule(qualified));
+                    } else {
+                        rest.push(ComponentValue::QualifiedRule(qualified));
+                    }
+                }
+                ComponentValue::AtRule(nested) => {
+                    let (critical_rule, rest_rule) = self.split_at_rule(*nested)?;
+                    critical
+                        .extend(critical_rule.map(|rule| ComponentValue::AtRule(Box::new(rule))));
+                    rest.extend(rest_rule.map(|rule| ComponentValue::AtRule(Box::new(rule))));
+                }
+                value => rest.push(value),
+            }
+        }
+        let with = |values: Vec<ComponentValue>| {
+            (!values.is_empty()).then(|| {
+                let mut rule = at_rule.clone();
+                rule.block.as_mut().unwrap().value = values;
+                rule
+            })
+        };
+        Ok((with(critical), with(rest)))
+    }
+}
+
+/// The tags of a css file in the html, the critical rules are inlined and
+/// the file is loaded without blocking the render.
+pub(crate) fn style_tags(
+    file: &str,
+    href: &str,
+    nonce: Option<&str>,
+    context: &Arc<Context>,
+) -> String {
+    let critical_css = context.stats_info.critical_css.lock().unwrap();
+    let nonce = nonce_attr(nonce);
+    match critical_css.get(file) {
+        Some(critical) => format!(
+            r#"<style{nonce}>{critical}</style>
+<link rel="stylesheet" href="{href}"{nonce} media="print" onload="this.media='all'" />
+<noscript><link rel="stylesheet" href="{href}"{nonce} /></noscript>"#,
+        ),
+        None => format!(r#"<link rel="stylesheet" href="{}"{} />"#, href, nonce),
+    }
+}
+
+/// Record the critical css of the entry css chunks for the html and report
+/// the split sizes.
+pub(crate) fn record_critical_css(chunk_files: &[ChunkFile], context: &Arc<Context>) {
+    let mut critical_css = context.stats_info.critical_css.lock().unwrap();
+    for file in chunk_files {
+        let Some(critical) = &file.critical_css else {
+            continue;
+        };
+        let name = file.disk_name();
+        if !context.config.quiet {
+            println!(
+                "Critical CSS of {}: {} inlined, {} deferred",
+                name,
+                human_readable_size(critical.len() as u64),
+                human_readable_size(file.content.len() as u64)
+            );
+        }
+        critical_css.insert(name, critical.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::testing::compile_fixture;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_critical_css() {
+        let compiler = setup_compiler("test/build/critical-css", false);
+        compiler.compile().unwrap();
+        let context = &compiler.context;
+        let read = |name: &str| {
+            String::from_utf8(
+                context
+                    .output_fs
+                    .read(&context.config.output.path.join(name))
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+
+        let critical = context.stats_info.critical_css.lock().unwrap()["index.css"].clone();
+        let css = read("index.css");
+        let position = |code: &str, pattern: &str| code.find(pattern).unwrap();
+        // the rules of the modules and the matched selectors, in order
+        assert!(position(&critical, ".nav") < position(&critical, ".hero {"));
+        assert!(position(&critical, ".hero {") < position(&critical, ".hero-title"));
+        assert!(
+            critical.contains("@media (min-width: 100px)"),
+            "{}",
+            critical
+        );
+        // the whole rule with any matched selector
+        assert!(critical.contains(".footer .link"), "{}", critical);
+        assert!(!critical.contains("color: blue"), "{}", critical);
+        // the rest
+        assert!(!css.contains(".hero"), "{}", css);
+        assert!(!css.contains(".nav"), "{}", css);
+        assert!(position(&css, "color: blue") < position(&css, "margin: 0"));
+
+        let html = read("index.html");
+        assert!(
+            html.contains(&format!("<style>{}</style>", critical)),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains(r#"<link rel="stylesheet" href="/index.css" media="print" onload="this.media='all'" />"#),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains(r#"<script src="/index.js"></script>"#),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn test_public_path() {
+        let build = |public_path: &str| {
+            let overrides = format!(
+                r#"{{"mode":"production","hash":false,"minify":false,"publicPath":"{}"}}"#,
+                public_path
+            );
+            let compiler =
+                compile_fixture(Path::new("test/build/public-path"), Some(&overrides)).unwrap();
+            let context = &compiler.context;
+            let read = |name: &str| {
+                String::from_utf8(
+                    context
+                        .output_fs
+                        .read(&context.config.output.path.join(name))
+                        .unwrap(),
+                )
+                .unwrap()
+            };
+            let manifest: serde_json::Value =
+                serde_json::from_str(&read("asset-manifest.json")).unwrap();
+            (read("index.html"), manifest, read("index.js"))
+        };
+
+        for public_path in ["./", "/app/", "https://cdn.com/app/"] {
+            let (html, manifest, _) = build(public_path);
+            for tag in [
+                format!(r#"<link rel="icon" href="{}favicon.ico" />"#, public_path),
+                format!(
+                    r#"<link rel="stylesheet" href="{}index.css" />"#,
+                    public_path
+                ),
+                format!(r#"<script src="{}index.js"></script>"#, public_path),
+            ] {
+                assert!(html.contains(&tag), "{}", html);
+            }
+            assert_eq!(manifest["index.js"], format!("{}index.js", public_path));
+            assert_eq!(
+                manifest["entrypoints"]["index"],
+                serde_json::json!([
+                    format!("{}index.css", public_path),
+                    format!("{}index.js", public_path)
+                ])
+            );
+        }
+
+        // the chunks are loaded relative to the current script
+        let (_, _, js) = build("./");
+        assert!(js.contains("document.currentScript"), "{}", js);
+        assert!(
+            js.contains(r#"scriptUrl = new URL("./", document.baseURI).href;"#),
+            "{}",
+            js
+        );
+        let (_, _, js) = build("https://cdn.com/app/");
+        assert!(
+            js.contains(r#"requireModule.publicPath = "https://cdn.com/app/";"#),
+            "{}",
+            js
+        );
+
+        // resolved by the runtime, so the files are next to the html
+        let (html, manifest, _) = build("auto");
+        assert!(html.contains(r#"href="favicon.ico""#), "{}", html);
+        assert!(
+            html.contains(r#"<script src="index.js"></script>"#),
+            "{}",
+            html
+        );
+        assert_eq!(manifest["index.js"], "index.js");
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::compiler::Context;
+use crate::config::{ChunkLoading, Platform};
+use crate::plugins::html_entry::html_entry;
+use crate::utils::{base64_encode, create_cached_regex};
+
+/// A piece of inline code in the page, which the Content Security Policy
+/// allows by its hash, a nonce or the source expressions of its directive.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineCode {
+    /// the feature which emits it, e.g. `criticalCss`, or `html` for the
+    /// code of the html template and the plugins
+    pub origin: String,
+    pub kind: InlineCodeKind,
+    /// the file it's emitted in, e.g. `index.html`, or the file it's emitted
+    /// for, relative to the root
+    pub location: String,
+    /// the directive of the policy which applies to it, e.g. `script-src`
+    pub directive: String,
+    /// `sha256-...` of the content, none for the data urls and the eval
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// the config option or the global which sets a nonce on it instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum InlineCodeKind {
+    /// `<script>` without `src`
+    Script,
+    /// `<style>`, in the html or created at runtime
+    Style,
+    /// the event handler attributes, e.g. `onload`, whose hashes are only
+    /// allowed with `'unsafe-hashes'`
+    EventHandler,
+    /// `data:` urls of the inlined assets
+    DataUrl,
+    /// code evaluated by `Function`
+    Eval,
+}
+
+impl InlineCode {
+    /// The source expressions of the directive which allow it.
+    pub fn sources(&self) -> Vec<String> {
+        let hash = self.hash.iter().map(|hash| format!("'{}'", hash));
+        match self.kind {
+            InlineCodeKind::Script | InlineCodeKind::Style => hash.collect(),
+            InlineCodeKind::EventHandler => hash
+                .chain(std::iter::once("'unsafe-hashes'".to_string()))
+                .collect(),
+            InlineCodeKind::DataUrl => vec!["data:".to_string()],
+            // or the chunks are imported from blob urls
+            InlineCodeKind::Eval => vec!["'unsafe-eval'".to_string()],
+        }
+    }
+}
+
+/// The inline code reported by the features which emit it.
+#[derive(Debug, Default)]
+pub struct InlineCodeRegistry {
+    // reported during the generate, e.g. the html and the pushable chunks
+    generated: Mutex<BTreeSet<InlineCode>>,
+    // path of the module or the asset -> its inline code, which is reported
+    // when it's built, and replaced when it's built again
+    modules: Mutex<HashMap<String, InlineCode>>,
+}
+
+impl InlineCodeRegistry {
+    pub fn report(&self, code: InlineCode) {
+        self.generated.lock().unwrap().insert(code);
+    }
+
+    pub fn report_module(&self, path: &str, code: InlineCode) {
+        self.modules.lock().unwrap().insert(path.to_string(), code);
+    }
+
+    pub(crate) fn clear_generated(&self) {
+        self.generated.lock().unwrap().clear();
+    }
+
+    pub fn list(&self) -> Vec<InlineCode> {
+        let mut list = self
+            .generated
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .chain(self.modules.lock().unwrap().values().cloned())
+            .collect::<Vec<_>>();
+        list.sort();
+        list.dedup();
+        list
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CspReport {
+    inline: Vec<InlineCode>,
+    /// directive -> source expressions which allow the inline code
+    policy: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// `sha256-...` of the content, as a source expression without the quotes.
+pub(crate) fn sha256(content: &str) -> String {
+    format!(
+        "sha256-{}",
+        base64_encode(Sha256::digest(content.as_bytes()))
+    )
+}
+
+/// The nonce of the elements rendered in the html of the entry, which is
+/// the one of the bundled tags of the html entry, or cspReport.nonce.
+pub(crate) fn entry_nonce(name: &str, context: &Context) -> Option<String> {
+    html_entry(name, context)
+        .and_then(|entry| entry.nonce)
+        .or_else(|| {
+            context
+                .config
+                .csp_report
+                .as_ref()
+                .and_then(|config| config.nonce.clone())
+        })
+}
+
+/// The `nonce` attribute of the elements rendered in the html.
+pub(crate) fn nonce_attr(nonce: Option<&str>) -> String {
+    nonce
+        .map(|nonce| format!(r#" nonce="{}""#, nonce))
+        .unwrap_or_default()
+}
+
+/// The directive of a `data:` url by its mime type.
+pub(crate) fn data_url_directive(mime: &str) -> &'static str {
+    match mime.split('/').next().unwrap_or_default() {
+        "image" => "img-src",
+        "font" => "font-src",
+        "audio" | "video" => "media-src",
+        _ => "default-src",
+    }
+}
+
+fn element_regex() -> &'static Regex {
+    static ELEMENT_REGEXP: OnceLock<Regex> = OnceLock::new();
+
+    ELEMENT_REGEXP.get_or_init(|| {
+        Regex::new(
+            r"(?is)<!--.*?-->|<(script|style)\b([^>]*)>(.*?)</(?:script|style)\s*>|<[a-z][^>]*>",
+        )
+        .unwrap()
+    })
+}
+
+fn event_handler_regex() -> &'static Regex {
+    static EVENT_HANDLER_REGEXP: OnceLock<Regex> = OnceLock::new();
+
+    EVENT_HANDLER_REGEXP
+        .get_or_init(|| Regex::new(r#"(?is)\son[a-z]+\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap())
+}
+
+fn attr_value<'a>(attrs: &'a str, name: &str) -> Option<&'a str> {
+    let regex = create_cached_regex(&format!(
+        r#"(?i)\b{}\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#,
+        name
+    ));
+    let caps = regex.captures(attrs)?;
+    caps.get(1)
+        .or_else(|| caps.get(2))
+        .or_else(|| caps.get(3))
+        .map(|value| value.as_str())
+}
+
+// the attribute values are hashed after the character references are decoded
+fn decode_attr(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Report the inline scripts, styles and event handlers of the html, which
+/// are attributed to the features which render them by their content.
+pub(crate) fn report_html(name: &str, html: &str, context: &Arc<Context>) {
+    let registry = &context.stats_info.inline_code;
+    let critical_css = context.stats_info.critical_css.lock().unwrap();
+    let inlined = context.stats_info.inlined_chunks.lock().unwrap();
+    let is_inlined_chunk = |content: &str| inlined.iter().any(|chunk| chunk.content == content);
+    let config_nonce = context
+        .config
+        .csp_report
+        .as_ref()
+        .and_then(|config| config.nonce.as_ref())
+        .map(|_| "cspReport.nonce".to_string());
+    for caps in element_regex().captures_iter(html) {
+        let tag = &caps[0];
+        if tag.starts_with("<!--") {
+            continue;
+        }
+        let open_tag = tag.split_once('>').map_or(tag, |(open_tag, _)| open_tag);
+        for handler in event_handler_regex().captures_iter(open_tag) {
+            let code = decode_attr(handler.get(1).or_else(|| handler.get(2)).unwrap().as_str());
+            // the deferred stylesheets of the critical css
+            let origin = if code == "this.media='all'" {
+                "criticalCss"
+            } else {
+                "html"
+            };
+            registry.report(InlineCode {
+                origin: origin.to_string(),
+                kind: InlineCodeKind::EventHandler,
+                location: name.to_string(),
+                directive: "script-src".to_string(),
+                hash: Some(sha256(&code)),
+                nonce: None,
+            });
+        }
+        let (Some(element), Some(attrs), Some(content)) = (caps.get(1), caps.get(2), caps.get(3))
+        else {
+            continue;
+        };
+        let attrs = attrs.as_str();
+        let content = content.as_str();
+        let code = if element.as_str().eq_ignore_ascii_case("script") {
+            let script_type = attr_value(attrs, "type").unwrap_or_default().to_lowercase();
+            if attr_value(attrs, "src").is_some() {
+                continue;
+            }
+            let origin = match script_type.as_str() {
+                "importmap" => "importMap",
+                "" if is_inlined_chunk(content) => "inlineChunks",
+                "" | "module" | "text/javascript" | "application/javascript" => "html",
+                // the data blocks, e.g. `application/json`, are not executed
+                _ => continue,
+            };
+            InlineCode {
+                origin: origin.to_string(),
+                kind: InlineCodeKind::Script,
+                location: name.to_string(),
+                directive: "script-src".to_string(),
+                hash: Some(sha256(content)),
+                nonce: None,
+            }
+        } else {
+            let origin = if critical_css.values().any(|critical| critical == content) {
+                "criticalCss"
+            } else if is_inlined_chunk(content) {
+                "inlineChunks"
+            } else {
+                "html"
+            };
+            InlineCode {
+                origin: origin.to_string(),
+                kind: InlineCodeKind::Style,
+                location: name.to_string(),
+                directive: "style-src".to_string(),
+                hash: Some(sha256(content)),
+                nonce: None,
+            }
+        };
+        // the elements rendered by the build have the nonce of the config
+        let nonce = (code.origin != "html")
+            .then(|| config_nonce.clone())
+            .flatten();
+        registry.report(InlineCode { nonce, ..code });
+    }
+}
+
+/// Write the report of the inline code with the html of the entries, the
+/// code of the chunk loading is reported here as it's in every runtime.
+pub(crate) fn write_csp_report(htmls: &[(String, String)], context: &Arc<Context>) -> Result<()> {
+    let Some(config) = &context.config.csp_report else {
+        return Ok(());
+    };
+    for (name, html) in htmls {
+        report_html(name, html, context);
+    }
+    if context.config.platform == Platform::Browser
+        && context.config.output.chunk_loading == ChunkLoading::FetchEval
+    {
+        context.stats_info.inline_code.report(InlineCode {
+            origin: "chunkLoading".to_string(),
+            kind: InlineCodeKind::Eval,
+            location: "runtime".to_string(),
+            directive: "script-src".to_string(),
+            hash: None,
+            nonce: None,
+        });
+    }
+    let inline = context.stats_info.inline_code.list();
+    let mut policy: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for code in &inline {
+        policy
+            .entry(code.directive.clone())
+            .or_default()
+            .extend(code.sources());
+    }
+    let report = CspReport { inline, policy };
+    let path = context.config.output.path.join(&config.file_name);
+    context
+        .output_fs
+        .write(&path, serde_json::to_string_pretty(&report)?.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+    use serde_json::{json, Value};
+
+    use super::sha256;
+    use crate::testing::build_fixture;
+
+    const FILES: &[(&str, &str)] = &[
+        (
+            "src/index.ts",
+            r#"
+import "./index.css";
+import logo from "./logo.png";
+console.log(logo);
+import("./lazy");
+"#,
+        ),
+        (
+            "src/index.css",
+            ".hero { color: red; } .footer { color: blue; }",
+        ),
+        ("src/lazy.ts", r#"console.log("lazy");"#),
+        ("src/logo.png", "png"),
+        (
+            "template.html",
+            r#"<html><head>
+<!-- <script>commented();</script> -->
+<script>window.a = 1;</script>
+<script type="application/json">{}</script>
+</head><body></body></html>"#,
+        ),
+    ];
+
+    fn inline_code(report: &Value, origin: &str, kind: &str) -> Vec<Value> {
+        report["inline"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|code| code["origin"] == origin && code["kind"] == kind)
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_csp_report() {
+        let artifacts = build_fixture(
+            Some(
+                r#"{
+                    "minify": false,
+                    "criticalCss": {
+                        "entries": { "index": { "selectors": [".hero*"] } },
+                        "template": "template.html"
+                    },
+                    "output": { "pushableChunks": true, "chunkLoading": "fetch-eval" },
+                    "cspReport": { "nonce": "{{ nonce }}" }
+                }"#,
+            ),
+            FILES,
+        )
+        .unwrap();
+        let html = artifacts.asset("index.html").unwrap();
+        let report: Value =
+            serde_json::from_str(&artifacts.asset("csp-report.json").unwrap()).unwrap();
+
+        // the critical css has the nonce of the config
+        let critical = Regex::new(r#"(?s)<style nonce="\{\{ nonce \}\}">(.*?)</style>"#)
+            .unwrap()
+            .captures(&html)
+            .unwrap()[1]
+            .to_string();
+        let styles = inline_code(&report, "criticalCss", "style");
+        assert_eq!(styles.len(), 1);
+        assert_eq!(styles[0]["location"], "index.html");
+        assert_eq!(styles[0]["hash"], sha256(&critical));
+        assert_eq!(styles[0]["nonce"], "cspReport.nonce");
+        let handlers = inline_code(&report, "criticalCss", "eventHandler");
+        assert_eq!(handlers[0]["hash"], sha256("this.media='all'"));
+        assert!(handlers[0].get("nonce").is_none());
+
+        // the inline script of the template, but not the data block and
+        // the comment
+        let scripts = inline_code(&report, "html", "script");
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0]["hash"], sha256("window.a = 1;"));
+
+        let pushable = inline_code(&report, "pushableChunks", "script");
+        assert_eq!(pushable.len(), 1);
+        let name = pushable[0]["location"].as_str().unwrap();
+        let chunk: Value = serde_json::from_str(&artifacts.asset(name).unwrap()).unwrap();
+        assert_eq!(
+            pushable[0]["hash"],
+            sha256(chunk["script"].as_str().unwrap())
+        );
+
+        let data_urls = inline_code(&report, "inlineLimit", "dataUrl");
+        assert_eq!(data_urls[0]["location"], "./src/logo.png");
+        assert_eq!(data_urls[0]["directive"], "img-src");
+        assert_eq!(inline_code(&report, "chunkLoading", "eval").len(), 1);
+
+        assert_eq!(report["policy"]["img-src"], json!(["data:"]));
+        let script_src = report["policy"]["script-src"].as_array().unwrap();
+        assert!(script_src.contains(&json!("'unsafe-eval'")));
+        assert!(script_src.contains(&json!("'unsafe-hashes'")));
+        assert!(script_src.contains(&json!(format!("'{}'", sha256("window.a = 1;")))));
+    }
+
+    #[test]
+    fn test_csp_report_inline_css() {
+        let artifacts = build_fixture(
+            Some(r#"{ "inlineCSS": {}, "umd": "foo", "cspReport": { "fileName": "csp.json" } }"#),
+            FILES,
+        )
+        .unwrap();
+        let report: Value = serde_json::from_str(&artifacts.asset("csp.json").unwrap()).unwrap();
+        let styles = inline_code(&report, "inlineCSS", "style");
+        assert_eq!(styles.len(), 1);
+        assert_eq!(styles[0]["location"], "./src/index.css");
+        assert_eq!(styles[0]["nonce"], "__mako_nonce__");
+        // the css in the style element
+        let js = artifacts.asset("index.js").unwrap();
+        assert!(js.contains("__mako_nonce__"));
+        assert!(inline_code(&report, "chunkLoading", "eval").is_empty());
+    }
+}
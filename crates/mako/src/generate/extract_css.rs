@@ -0,0 +1,313 @@
+use anyhow::{anyhow, Result};
+use glob_match::glob_match;
+use hashlink::LinkedHashSet;
+use indexmap::{IndexMap, IndexSet};
+use tracing::debug;
+
+use crate::compiler::Compiler;
+use crate::config::ExtractCssGroup;
+use crate::generate::chunk::{Chunk, ChunkId, ChunkType};
+use crate::generate::chunk_graph::ChunkGraph;
+use crate::generate::chunk_placement::ChunkPlacement;
+use crate::module::{relative_to_root, ModuleAst, ModuleId};
+use crate::module_graph::ModuleGraph;
+use crate::utils::file_request::decode_path;
+
+impl Compiler {
+    /// Move the css modules of the chunks loaded by the entries to the css
+    /// only chunks of the groups of extractCss, which are loaded by the
+    /// entries as the shared chunks, so the css files don't change with the
+    /// js and the reverse. The order of the css is the order of the chunks
+    /// loaded by the entries, then the order of the modules in the chunks.
+    pub fn extract_css(&self) -> Result<()> {
+        crate::mako_profile_function!();
+        let Some(extract_css) = &self.context.config.extract_css else {
+            return Ok(());
+        };
+        // the css follows the chunks in watch mode for the hot updates
+        if self.context.args.watch {
+            return Ok(());
+        }
+
+        let module_graph = self.context.module_graph.read().unwrap();
+        let mut chunk_graph = self.context.chunk_graph.write().unwrap();
+
+        // group name -> css module -> chunks it's moved from
+        let mut groups: IndexMap<&str, IndexMap<ModuleId, Vec<ChunkId>>> = extract_css
+            .groups
+            .iter()
+            .map(|group| (group.name.as_str(), IndexMap::new()))
+            .collect();
+        // group name -> entry chunks which load it
+        let mut group_entries: IndexMap<&str, IndexSet<ChunkId>> = IndexMap::new();
+
+        for chunk_id in self.css_source_chunks(&chunk_graph, &module_graph) {
+            let chunk = chunk_graph.chunk(&chunk_id).unwrap();
+            let entries = chunk_graph
+                .entry_ancestors_chunk(&chunk_id)
+                .into_iter()
+                .filter(|id| self.is_css_entry(&chunk_graph, id))
+                .collect::<Vec<_>>();
+            if entries.is_empty() {
+                continue;
+            }
+            for module_id in chunk.get_modules() {
+                if !is_css_module(&module_graph, module_id) {
+                    continue;
+                }
+                let Some(group) = self.css_group(&extract_css.groups, module_id) else {
+                    continue;
+                };
+                let modules = groups.get_mut(group.name.as_str()).unwrap();
+                // the first chunk which loads it decides the order
+                modules
+                    .entry(module_id.clone())
+                    .or_default()
+                    .push(chunk_id.clone());
+                group_entries
+                    .entry(group.name.as_str())
+                    .or_default()
+                    .extend(entries.iter().cloned());
+            }
+        }
+
+        for (name, modules) in groups {
+            if modules.is_empty() {
+                continue;
+            }
+            let group_chunk_id = ChunkId { id: name.into() };
+            if chunk_graph.has_chunk(&group_chunk_id) {
+                return Err(anyhow!(
+                    "The extractCss group `{}` collides with the chunk of the same name",
+                    name
+                ));
+            }
+            debug!("extract {} css modules to chunk {}", modules.len(), name);
+            chunk_graph.add_chunk(Chunk {
+                modules: modules.keys().cloned().collect::<LinkedHashSet<_>>(),
+                hash_function: self.context.config.output.hash_function,
+                hash_length: self.context.config.output.hash_length,
+                ..Chunk::new(
+                    group_chunk_id.clone(),
+                    ChunkType::Entry(group_chunk_id.clone(), name.to_string(), true),
+                )
+            });
+            for (module_id, chunk_ids) in &modules {
+                let mut parents = chunk_ids
+                    .iter()
+                    .map(|id| chunk_graph.chunk(id).unwrap().name())
+                    .collect::<Vec<_>>();
+                parents.sort();
+                chunk_graph.set_placement(
+                    &group_chunk_id,
+                    module_id,
+                    ChunkPlacement::Css {
+                        group: name.to_string(),
+                        parents,
+                    },
+                );
+                for chunk_id in chunk_ids {
+                    chunk_graph.remove_placement(chunk_id, module_id);
+                    chunk_graph
+                        .mut_chunk(chunk_id)
+                        .unwrap()
+                        .remove_module(module_id);
+                }
+            }
+            // after the shared chunks of the entries, in the order of the
+            // groups
+            for entry in &group_entries[name] {
+                chunk_graph.add_edge(entry, &group_chunk_id);
+            }
+        }
+        Ok(())
+    }
+
+    // the chunks in the order they are loaded, the chunks of the entries go
+    // first, then the chunks they install, the css of the workers and of the
+    // dynamically imported css files are kept
+    fn css_source_chunks(
+        &self,
+        chunk_graph: &ChunkGraph,
+        module_graph: &ModuleGraph,
+    ) -> IndexSet<ChunkId> {
+        let mut chunk_ids = IndexSet::new();
+        for entrypoint in chunk_graph.entrypoints() {
+            if self.context.config.is_webworker_entry(&entrypoint.name) {
+                continue;
+            }
+            let entry = entrypoint.chunks.last().unwrap().clone();
+            chunk_ids.extend(entrypoint.chunks);
+            chunk_ids.extend(chunk_graph.installable_descendants_chunk(&entry));
+        }
+        chunk_ids.extend(
+            chunk_graph
+                .get_chunks()
+                .iter()
+                .map(|chunk| chunk.id.clone()),
+        );
+        chunk_ids.retain(|chunk_id| {
+            let chunk = chunk_graph.chunk(chunk_id).unwrap();
+            match &chunk.chunk_type {
+                ChunkType::Worker(_) => false,
+                ChunkType::Entry(_, name, false) => !self.context.config.is_webworker_entry(name),
+                // the root is the last module of the async chunk
+                ChunkType::Async => !chunk
+                    .modules
+                    .iter()
+                    .last()
+                    .is_some_and(|root| is_css_module(module_graph, root)),
+                _ => true,
+            }
+        });
+        chunk_ids
+    }
+
+    fn is_css_entry(&self, chunk_graph: &ChunkGraph, chunk_id: &ChunkId) -> bool {
+        chunk_graph
+            .chunk(chunk_id)
+            .is_some_and(|chunk| match &chunk.chunk_type {
+                ChunkType::Entry(_, name, false) => !self.context.config.is_webworker_entry(name),
+                _ => false,
+            })
+    }
+
+    fn css_group<'a>(
+        &self,
+        groups: &'a [ExtractCssGroup],
+        module_id: &ModuleId,
+    ) -> Option<&'a ExtractCssGroup> {
+        let path = relative_to_root(&decode_path(module_id.path()), &self.context.root);
+        let path = path.trim_start_matches("./");
+        groups.iter().find(|group| {
+            group.test.is_empty()
+                || group
+                    .test
+                    .iter()
+                    .any(|glob| glob_match(glob.trim_start_matches("./"), path))
+        })
+    }
+}
+
+fn is_css_module(module_graph: &ModuleGraph, module_id: &ModuleId) -> bool {
+    module_graph
+        .get_module(module_id)
+        .and_then(|module| module.info.as_ref())
+        .is_some_and(|info| matches!(info.ast, ModuleAst::Css(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::testing::{build_fixture, compile_fixture, write_files, TempDir};
+
+    const FILES: &[(&str, &str)] = &[
+        (
+            "src/index.ts",
+            r#"
+import "antd/dist/reset.css";
+import "./index.css";
+import("./lazy");
+import("./lazy.css");
+"#,
+        ),
+        ("src/index.css", ".index { color: red; }"),
+        (
+            "src/lazy.ts",
+            r#"
+import "antd/dist/button.css";
+import "./lazy-style.css";
+console.log("lazy");
+"#,
+        ),
+        ("src/lazy-style.css", ".lazy { color: blue; }"),
+        ("src/lazy.css", ".dynamic { color: green; }"),
+        ("node_modules/antd/package.json", r#"{"name":"antd"}"#),
+        ("node_modules/antd/dist/reset.css", ".reset { margin: 0; }"),
+        (
+            "node_modules/antd/dist/button.css",
+            ".button { padding: 0; }",
+        ),
+    ];
+
+    #[test]
+    fn test_extract_css() {
+        let artifacts = build_fixture(
+            Some(
+                r#"{
+                    "extractCss": {
+                        "groups": [
+                            { "name": "antd", "test": ["node_modules/antd/**"] },
+                            { "name": "styles" }
+                        ]
+                    },
+                    "manifest": {}
+                }"#,
+            ),
+            FILES,
+        )
+        .unwrap();
+        // css only, loaded by the entry
+        assert!(artifacts.assets.contains_key("antd.css"));
+        assert!(artifacts.assets.contains_key("styles.css"));
+        assert!(!artifacts.assets.contains_key("antd.js"));
+        assert!(!artifacts.assets.contains_key("styles.js"));
+        // the css of the entry goes before the css of the async chunk
+        let antd = artifacts.asset("antd.css").unwrap();
+        assert!(antd.find(".reset").unwrap() < antd.find(".button").unwrap());
+        let styles = artifacts.asset("styles.css").unwrap();
+        assert!(styles.find(".index").unwrap() < styles.find(".lazy").unwrap());
+        // the async chunk has no css, and the entry doesn't install it
+        assert!(!artifacts.assets.contains_key("src_lazy_ts-async.css"));
+        assert!(!artifacts
+            .asset("index.js")
+            .unwrap()
+            .contains("src_lazy_ts-async.css"));
+        // the dynamically imported css is kept
+        assert!(artifacts.assets.contains_key("src_lazy_css-async.css"));
+        let manifest: serde_json::Value =
+            serde_json::from_str(&artifacts.asset("asset-manifest.json").unwrap()).unwrap();
+        assert_eq!(
+            manifest["entrypoints"]["index"],
+            serde_json::json!(["antd.css", "styles.css", "index.js"])
+        );
+    }
+
+    #[test]
+    fn test_extract_css_hash() {
+        // the hashes depend on the root, so both builds are in the same one,
+        // and the names mangled by the minifier differ between the builds
+        // running at the same time
+        let root = TempDir::new("extract-css");
+        write_files(&root, FILES).unwrap();
+        let build = |css: &str| {
+            fs::write(root.join("src/lazy-style.css"), css).unwrap();
+            let compiler = compile_fixture(
+                &root,
+                Some(r#"{"extractCss": {}, "mode": "production", "hash": true, "minify": false}"#),
+            )
+            .unwrap();
+            let output = &compiler.context.config.output.path;
+            let mut names = compiler
+                .context
+                .output_fs
+                .list(output)
+                .unwrap()
+                .into_iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+                .filter(|name| !name.ends_with(".map"))
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        let a = build(".lazy { color: blue; }");
+        let b = build(".lazy { color: black; }");
+        let (a_js, a_css): (Vec<_>, Vec<_>) = a.into_iter().partition(|name| name.ends_with(".js"));
+        let (b_js, b_css): (Vec<_>, Vec<_>) = b.into_iter().partition(|name| name.ends_with(".js"));
+        // the js files don't change with the extracted css
+        assert_eq!(a_js, b_js);
+        assert!(a_css.iter().any(|name| name.starts_with("styles.")));
+        assert_ne!(a_css, b_css);
+    }
+}
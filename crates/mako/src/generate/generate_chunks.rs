@@ -1,24 +1,28 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hasher;
 use std::path::Path;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use nanoid::nanoid;
 use rayon::prelude::*;
 use swc_core::common::DUMMY_SP;
 use swc_core::css::ast::Stylesheet;
 use swc_core::ecma::ast::{Expr, KeyValueProp, Prop, PropName, PropOrSpread, Str};
-use tracing::warn;
+use tracing::{debug, warn};
 use twox_hash::XxHash64;
 
 use crate::compiler::{Compiler, Context};
+use crate::config::{has_content_hash, OutputConfig};
 use crate::generate::chunk::{Chunk, ChunkType};
-use crate::generate::chunk_pot::util::file_content_hash;
-use crate::generate::chunk_pot::{get_css_chunk_filename, ChunkPot, CHUNK_FILE_NAME_HASH_LENGTH};
+use crate::generate::chunk_pot::{get_css_chunk_filename, ChunkPot};
 use crate::generate::transform::transform_css_generate;
+use crate::generate::webworker::inject_precache_manifests;
 use crate::module::{ModuleAst, ModuleId};
+use crate::stats::{ChunkAlias, InFlightChunkBytes};
+use crate::utils::chunk_budget::peak_in_flight_bytes;
+use crate::utils::hash::chunk_content_hash;
 use crate::utils::thread_pool;
 
 #[derive(Clone)]
@@ -38,6 +42,11 @@ pub struct ChunkFile {
     pub chunk_id: String,
     pub file_type: ChunkFileType,
     pub file_name_template: Option<String>,
+    // path and emitted bytes of the modules in the content, for `analyze`
+    pub module_sizes: Vec<(String, u64)>,
+    // the rules of an entry css chunk inlined in the html by criticalCss,
+    // which are not in the content
+    pub critical_css: Option<String>,
 }
 
 impl ChunkFile {
@@ -49,6 +58,16 @@ impl ChunkFile {
         }
     }
 
+    /// Whether the disk name has the content hash, the hash is not rendered
+    /// if the template has no `[hash]` or `[contenthash]`.
+    pub fn is_content_hashed(&self) -> bool {
+        self.hash.is_some()
+            && self
+                .file_name_template
+                .as_deref()
+                .map_or(true, has_content_hash)
+    }
+
     pub fn source_map_disk_name(&self) -> String {
         format!("{}.map", self.disk_name())
     }
@@ -57,6 +76,29 @@ impl ChunkFile {
         format!("{}.map", self.file_name)
     }
 
+    /// A uuid derived from the content and the source map, so it's stable
+    /// across builds and does not affect the hash of the file name.
+    pub fn debug_id(&self) -> String {
+        let mut context = md5::Context::new();
+        context.consume(&self.content);
+        if let Some(source_map) = &self.source_map {
+            context.consume(source_map);
+        }
+        let mut bytes = context.compute().0;
+        // version 4 and the rfc 4122 variant
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        let hex = format!("{:x}", md5::Digest(bytes));
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+
     fn default_disk_name(&self) -> String {
         let format_file_name = hash_too_long_file_name(&self.file_name);
 
@@ -90,22 +132,109 @@ impl Compiler {
         let (entry_chunks, normal_chunks): (Vec<&Chunk>, Vec<&Chunk>) = chunks
             .into_iter()
             .partition(|chunk| match chunk.chunk_type {
+                // executed by the runtime of the entries it depends on
+                ChunkType::Entry(_, _, false) if chunk.is_dependent_entry() => false,
                 ChunkType::Entry(_, _, false) | ChunkType::Worker(_) => true,
                 ChunkType::Entry(_, _, true) => false,
                 _ => false,
             });
-
-        let (entry_chunk_files_with_placeholder, normal_chunk_files) = thread_pool::join(
-            || self.generate_entry_chunk_files(entry_chunks, hmr_hash),
-            || self.generate_normal_chunk_files(normal_chunks),
+        let async_chunk_ids = normal_chunks
+            .iter()
+            .filter(|chunk| matches!(chunk.chunk_type, ChunkType::Async))
+            .map(|chunk| chunk.id.id.to_string())
+            .collect::<HashSet<_>>();
+
+        // the batches of the entry and the normal chunks are generated one
+        // after another within the budget, the files are in the order of the
+        // chunks as the unthrottled generation
+        let sizes = {
+            let module_graph = self.context.module_graph.read().unwrap();
+            entry_chunks
+                .iter()
+                .chain(normal_chunks.iter())
+                .map(|chunk| {
+                    chunk
+                        .modules
+                        .iter()
+                        .filter_map(|id| module_graph.get_module(id))
+                        .map(|module| module.get_module_size())
+                        .sum()
+                })
+                .collect::<Vec<usize>>()
+        };
+        let budget = &self.context.chunk_bytes_budget;
+        let batches = budget.batches(&sizes);
+        let peak = peak_in_flight_bytes(&batches, &sizes);
+        debug!(
+            "generate {} chunks in {} batches, peak in-flight chunk bytes: {} of {}",
+            sizes.len(),
+            batches.len(),
+            peak,
+            budget.budget()
         );
+        *self
+            .context
+            .stats_info
+            .in_flight_chunk_bytes
+            .lock()
+            .unwrap() = InFlightChunkBytes {
+            budget: budget.budget() as u64,
+            peak: peak as u64,
+        };
+
+        let entry_count = entry_chunks.len();
+        let mut entry_slots = (0..entry_count).map(|_| None).collect::<Vec<_>>();
+        let mut normal_slots = (0..normal_chunks.len()).map(|_| None).collect::<Vec<_>>();
+        for batch in batches {
+            crate::mako_profile_scope!("generate chunks batch", &batch.len().to_string());
+            let (entry_indexes, normal_indexes): (Vec<usize>, Vec<usize>) =
+                batch.into_iter().partition(|index| *index < entry_count);
+            let normal_indexes = normal_indexes
+                .into_iter()
+                .map(|index| index - entry_count)
+                .collect::<Vec<_>>();
+            let (entry_files, normal_files) = thread_pool::join(
+                || {
+                    self.generate_entry_chunk_files(
+                        entry_indexes.iter().map(|i| entry_chunks[*i]).collect(),
+                        hmr_hash,
+                    )
+                },
+                || {
+                    self.generate_normal_chunk_files(
+                        normal_indexes.iter().map(|i| normal_chunks[*i]).collect(),
+                    )
+                },
+            );
+            for (index, files) in entry_indexes.into_iter().zip(entry_files?) {
+                entry_slots[index] = Some(files);
+            }
+            for (index, files) in normal_indexes.into_iter().zip(normal_files?) {
+                normal_slots[index] = Some(files);
+            }
+        }
 
-        let normal_chunk_files = normal_chunk_files?;
+        let mut normal_chunk_files = normal_slots
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect::<Vec<_>>();
 
-        let mut entry_chunk_files_with_placeholder = entry_chunk_files_with_placeholder?;
+        let mut entry_chunk_files_with_placeholder =
+            entry_slots.into_iter().flatten().collect::<Vec<_>>();
 
         if self.context.config.hash {
-            let (js_chunks_hash_replacer, css_chunks_hash_replacer) =
+            let chunk_loading_global =
+                serde_json::to_string(&self.context.config.output.chunk_loading_global)?;
+            let chunk_aliases = dedupe_async_chunk_files(
+                &mut normal_chunk_files,
+                &async_chunk_ids,
+                &chunk_loading_global,
+                self.context.codegen_salt,
+                &self.context.config.output,
+            );
+
+            let (mut js_chunks_hash_replacer, mut css_chunks_hash_replacer) =
                 normal_chunk_files.iter().fold(
                     (ChunksHashReplacer::new(), ChunksHashReplacer::new()),
                     |(mut acc_js, mut acc_css), chunk_file| {
@@ -120,6 +249,16 @@ impl Compiler {
                         (acc_js, acc_css)
                     },
                 );
+            // aliased chunk ids are loaded from the file of the chunk they alias
+            for alias in &chunk_aliases {
+                if let Some(disk_name) = js_chunks_hash_replacer.get(&alias.alias_of).cloned() {
+                    js_chunks_hash_replacer.insert(alias.chunk_id.clone(), disk_name);
+                }
+                if let Some(disk_name) = css_chunks_hash_replacer.get(&alias.alias_of).cloned() {
+                    css_chunks_hash_replacer.insert(alias.chunk_id.clone(), disk_name);
+                }
+            }
+            self.context.stats_info.set_chunk_aliases(chunk_aliases);
 
             entry_chunk_files_with_placeholder
         .par_iter_mut()
@@ -135,8 +274,13 @@ impl Compiler {
               css_chunks_hash_placeholder,
               &css_chunks_hash_replacer,
             )?;
-            chunk_files.iter_mut().for_each(|cf| {
-              cf.hash = Some(file_content_hash(&cf.content));
+            // the webworker entries are not hashed
+            chunk_files.iter_mut().filter(|cf| cf.hash.is_some()).for_each(|cf| {
+              cf.hash = Some(chunk_content_hash(
+                &cf.content,
+                self.context.codegen_salt,
+                &self.context.config.output,
+              ));
             });
 
             Ok(())
@@ -149,7 +293,12 @@ impl Compiler {
             .flat_map(|e| e.0)
             .collect();
 
-        Ok([entry_chunk_files, normal_chunk_files].concat())
+        drop(chunk_graph);
+        let mut chunk_files = [entry_chunk_files, normal_chunk_files].concat();
+        self.handle_license_comments(&mut chunk_files)?;
+        inject_precache_manifests(&mut chunk_files, &self.context)?;
+
+        Ok(chunk_files)
     }
 
     fn generate_entry_chunk_files(
@@ -163,11 +312,16 @@ impl Compiler {
                 let context = self.context.clone();
                 let module_graph = context.module_graph.read().unwrap();
                 let chunk_graph = self.context.chunk_graph.read().unwrap();
+                let hash_length = self.context.config.output.hash_length;
 
-                let (js_chunks_hash_placeholder, css_chunks_hash_placeholder) = chunk_graph
-                    .installable_descendants_chunk(&chunk.id)
-                    .iter()
-                    .fold(
+                // the chunks of the entries depending on it are loaded by its runtime
+                let mut installable_chunks = chunk_graph.installable_descendants_chunk(&chunk.id);
+                for dependent in chunk_graph.depend_on_dependents_chunk(&chunk.id) {
+                    installable_chunks
+                        .extend(chunk_graph.installable_descendants_chunk(&dependent));
+                }
+                let (js_chunks_hash_placeholder, css_chunks_hash_placeholder) =
+                    installable_chunks.iter().fold(
                         (ChunksHashPlaceholder::new(), ChunksHashPlaceholder::new()),
                         |(mut acc_js, mut acc_css), descendant_chunk_id| {
                             let descendant_chunk = chunk_graph.chunk(descendant_chunk_id).unwrap();
@@ -177,20 +331,20 @@ impl Compiler {
                                 ChunkPot::from(descendant_chunk, &module_graph, &context);
 
                             if self.context.config.hash {
-                                let placeholder = nanoid!(CHUNK_FILE_NAME_HASH_LENGTH);
+                                let placeholder = nanoid!(hash_length);
 
                                 let js_filename = chunk_pot.js_name;
 
                                 if chunk_pot.stylesheet.is_some() {
                                     let css_filename = get_css_chunk_filename(&js_filename);
                                     acc_css.insert(
-                                        descendant_chunk_id.id.clone(),
+                                        descendant_chunk_id.id.to_string(),
                                         hash_file_name(&css_filename, &placeholder),
                                     );
                                 }
 
                                 acc_js.insert(
-                                    descendant_chunk_id.id.clone(),
+                                    descendant_chunk_id.id.to_string(),
                                     hash_file_name(&js_filename, &placeholder),
                                 );
                             } else {
@@ -198,10 +352,11 @@ impl Compiler {
 
                                 if chunk_pot.stylesheet.is_some() {
                                     let css_filename = get_css_chunk_filename(&js_filename);
-                                    acc_css.insert(descendant_chunk_id.id.clone(), css_filename);
+                                    acc_css
+                                        .insert(descendant_chunk_id.id.to_string(), css_filename);
                                 }
 
-                                acc_js.insert(descendant_chunk_id.id.clone(), js_filename);
+                                acc_js.insert(descendant_chunk_id.id.to_string(), js_filename);
                             }
                             (acc_js, acc_css)
                         },
@@ -217,7 +372,20 @@ impl Compiler {
                             chunk,
                             hmr_hash,
                         )
-                        .map(|chunk_files| {
+                        .map(|mut chunk_files| {
+                            // the webworker entries are emitted at the stable
+                            // urls, e.g. to be registered as service workers
+                            if let ChunkType::Entry(_, name, false) = &chunk.chunk_type
+                                && context.config.is_webworker_entry(name)
+                            {
+                                for file in chunk_files
+                                    .iter_mut()
+                                    .filter(|file| matches!(file.file_type, ChunkFileType::JS))
+                                {
+                                    file.hash = None;
+                                    file.file_name_template = None;
+                                }
+                            }
                             (
                                 chunk_files,
                                 js_chunks_hash_placeholder,
@@ -252,7 +420,8 @@ impl Compiler {
         Ok(chunk_files)
     }
 
-    fn generate_normal_chunk_files(&self, chunks: Vec<&Chunk>) -> Result<Vec<ChunkFile>> {
+    // the files of each chunk, in the order of the chunks
+    fn generate_normal_chunk_files(&self, chunks: Vec<&Chunk>) -> Result<Vec<Vec<ChunkFile>>> {
         let chunk_file_results: Vec<_> = chunks
             .par_iter()
             .map(|chunk| {
@@ -273,7 +442,7 @@ impl Compiler {
             (Vec::new(), Vec::new()),
             |(mut chunk_files, mut err_msgs), result| {
                 match result {
-                    Ok(cfs) => chunk_files.extend(cfs),
+                    Ok(cfs) => chunk_files.push(cfs),
                     Err(e) => err_msgs.push(e),
                 }
                 (chunk_files, err_msgs)
@@ -292,6 +461,126 @@ impl Compiler {
     }
 }
 
+/// Async chunks with the same content, e.g. dynamic imports of different
+/// barrel files which are reduced to the same module, are emitted as one
+/// file, the file registers the ids of all of them when loaded.
+fn dedupe_async_chunk_files(
+    chunk_files: &mut Vec<ChunkFile>,
+    async_chunk_ids: &HashSet<String>,
+    chunk_loading_global: &str,
+    salt: u64,
+    output: &OutputConfig,
+) -> Vec<ChunkAlias> {
+    // chunk id -> (js file, css file)
+    let mut chunks: IndexMap<&str, (Option<&ChunkFile>, Option<&ChunkFile>)> = IndexMap::new();
+    for chunk_file in chunk_files.iter() {
+        if !async_chunk_ids.contains(&chunk_file.chunk_id) {
+            continue;
+        }
+        let entry = chunks.entry(chunk_file.chunk_id.as_str()).or_default();
+        match chunk_file.file_type {
+            ChunkFileType::JS => entry.0 = Some(chunk_file),
+            ChunkFileType::Css => entry.1 = Some(chunk_file),
+        }
+    }
+
+    // (js content without the chunk id, css content) -> canonical chunk id
+    let mut canonical_chunks: HashMap<(Vec<u8>, Option<&[u8]>), &str> = HashMap::new();
+    let mut chunk_aliases = vec![];
+    for (chunk_id, (js_file, css_file)) in chunks {
+        let js_file = match js_file {
+            Some(js_file) => js_file,
+            None => continue,
+        };
+        let key = (
+            strip_chunk_id(&js_file.content, chunk_id),
+            css_file.map(|css_file| css_file.content.as_slice()),
+        );
+        match canonical_chunks.get(&key) {
+            Some(alias_of) => chunk_aliases.push(ChunkAlias {
+                chunk_id: chunk_id.to_string(),
+                alias_of: alias_of.to_string(),
+                files: vec![],
+            }),
+            None => {
+                canonical_chunks.insert(key, chunk_id);
+            }
+        }
+    }
+
+    if chunk_aliases.is_empty() {
+        return chunk_aliases;
+    }
+
+    let aliased_chunk_ids = chunk_aliases
+        .iter()
+        .map(|alias| alias.chunk_id.clone())
+        .collect::<HashSet<_>>();
+    let mut removed_files = vec![];
+    chunk_files.retain(|chunk_file| {
+        if aliased_chunk_ids.contains(&chunk_file.chunk_id) {
+            removed_files.push(chunk_file.clone());
+            false
+        } else {
+            true
+        }
+    });
+
+    for chunk_file in chunk_files.iter_mut() {
+        let alias_ids = chunk_aliases
+            .iter()
+            .filter(|alias| alias.alias_of == chunk_file.chunk_id)
+            .map(|alias| serde_json::to_string(&alias.chunk_id).unwrap())
+            .collect::<Vec<_>>();
+        if alias_ids.is_empty() || !matches!(chunk_file.file_type, ChunkFileType::JS) {
+            continue;
+        }
+        // appended after the content so that the source map is still valid
+        let register_code = format!(
+            "\n((typeof globalThis !== 'undefined' ? globalThis : self)[{}] = (typeof globalThis !== 'undefined' ? globalThis : self)[{}] || []).push([[{}], {{}}]);",
+            chunk_loading_global,
+            chunk_loading_global,
+            alias_ids.join(", ")
+        );
+        chunk_file
+            .content
+            .extend_from_slice(register_code.as_bytes());
+        chunk_file.hash = Some(chunk_content_hash(&chunk_file.content, salt, output));
+    }
+
+    for alias in chunk_aliases.iter_mut() {
+        alias.files = removed_files
+            .iter()
+            .filter(|removed| removed.chunk_id == alias.chunk_id)
+            .filter_map(|removed| {
+                chunk_files
+                    .iter()
+                    .find(|cf| {
+                        cf.chunk_id == alias.alias_of
+                            && std::mem::discriminant(&cf.file_type)
+                                == std::mem::discriminant(&removed.file_type)
+                    })
+                    .map(|canonical| (removed.file_name.clone(), canonical.disk_name()))
+            })
+            .collect();
+    }
+
+    chunk_aliases
+}
+
+// the chunk id is only different in the `push([['chunk_id'], ...` prefix of
+// chunks with same modules
+fn strip_chunk_id(content: &[u8], chunk_id: &str) -> Vec<u8> {
+    let mut content = content.to_vec();
+    if let Some(pos) = content
+        .windows(chunk_id.len())
+        .position(|w| w == chunk_id.as_bytes())
+    {
+        content.drain(pos..pos + chunk_id.len());
+    }
+    content
+}
+
 fn replace_chunks_placeholder(
     chunk_files: &mut [ChunkFile],
     chunks_hash_placeholder: &ChunksHashPlaceholder,
@@ -384,7 +673,7 @@ pub fn modules_to_js_stmts(
             {
                 merged_css_modules.remove(index);
             }
-            merged_css_modules.push((module.id.id.clone(), ast.ast.clone()));
+            merged_css_modules.push((module.id.id.to_string(), ast.ast.clone()));
         }
     }
     if !merged_css_modules.is_empty() {
@@ -437,7 +726,10 @@ fn hash_too_long_file_name(file_name: &String) -> String {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Mutex;
+
     use super::*;
+    use crate::plugin::{Plugin, PluginEmittedSourceMap};
 
     #[test]
     fn test_simple_template_render() {
@@ -451,8 +743,153 @@ mod tests {
             chunk_id: "c_id".to_string(),
             file_type: ChunkFileType::JS,
             file_name_template: Some("[name].[hash].[id].js".to_string()),
+            module_sizes: vec![],
+            critical_css: None,
         };
 
         assert_eq!(chunk_file.disk_name(), "chunk.hash999.c_id.js");
     }
+
+    #[test]
+    fn test_dedupe_async_chunk_files() {
+        let output = crate::config::Config::default().output;
+        let chunk_file = |chunk_id: &str, content: &str, file_type: ChunkFileType| ChunkFile {
+            raw_hash: 0,
+            content: content.as_bytes().to_vec(),
+            source_map: None,
+            hash: Some(chunk_content_hash(content, 0, &output)),
+            chunk_name: chunk_id.to_string(),
+            file_name: match file_type {
+                ChunkFileType::JS => format!("{}.js", chunk_id),
+                ChunkFileType::Css => format!("{}.css", chunk_id),
+            },
+            chunk_id: chunk_id.to_string(),
+            file_type,
+            file_name_template: None,
+            module_sizes: vec![],
+            critical_css: None,
+        };
+        let mut chunk_files = vec![
+            chunk_file("a", "push([['a'], {m: 1}]);", ChunkFileType::JS),
+            chunk_file("a", ".m {}", ChunkFileType::Css),
+            chunk_file("b", "push([['b'], {m: 1}]);", ChunkFileType::JS),
+            chunk_file("b", ".m {}", ChunkFileType::Css),
+            chunk_file("c", "push([['c'], {m: 2}]);", ChunkFileType::JS),
+            // not async
+            chunk_file("d", "push([['d'], {m: 1}]);", ChunkFileType::JS),
+        ];
+        let async_chunk_ids = ["a", "b", "c"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<HashSet<_>>();
+
+        let aliases =
+            dedupe_async_chunk_files(&mut chunk_files, &async_chunk_ids, "\"g\"", 0, &output);
+
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases[0].chunk_id, "b");
+        assert_eq!(aliases[0].alias_of, "a");
+        let names = chunk_files
+            .iter()
+            .map(|cf| cf.disk_name())
+            .collect::<Vec<_>>();
+        assert_eq!(names.len(), 4);
+        assert!(!names.iter().any(|name| name.starts_with("b.")));
+        let a_js = &chunk_files[0];
+        assert!(String::from_utf8_lossy(&a_js.content).ends_with(".push([[\"b\"], {}]);"));
+        assert_eq!(
+            a_js.hash,
+            Some(chunk_content_hash(&a_js.content, 0, &output))
+        );
+        assert_eq!(
+            aliases[0].files,
+            vec![
+                ("b.js".to_string(), a_js.disk_name()),
+                ("b.css".to_string(), chunk_files[1].disk_name()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_debug_id() {
+        let chunk_file = |content: &str| ChunkFile {
+            raw_hash: 0,
+            content: content.as_bytes().to_vec(),
+            source_map: Some(b"{}".to_vec()),
+            hash: None,
+            chunk_name: "index".to_string(),
+            file_name: "index.js".to_string(),
+            chunk_id: "index".to_string(),
+            file_type: ChunkFileType::JS,
+            file_name_template: None,
+            module_sizes: vec![],
+            critical_css: None,
+        };
+        let debug_id = chunk_file("a").debug_id();
+        assert_eq!(debug_id.len(), 36);
+        assert_eq!(&debug_id[14..15], "4");
+        assert_eq!(debug_id, chunk_file("a").debug_id());
+        assert_ne!(debug_id, chunk_file("b").debug_id());
+    }
+
+    struct EmittedSourceMaps(Mutex<Vec<PluginEmittedSourceMap>>);
+
+    impl Plugin for EmittedSourceMaps {
+        fn name(&self) -> &str {
+            "emitted_source_maps"
+        }
+
+        fn after_emit(
+            &self,
+            source_maps: &[PluginEmittedSourceMap],
+            _context: &Arc<Context>,
+        ) -> Result<()> {
+            self.0.lock().unwrap().extend_from_slice(source_maps);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_debug_ids() {
+        let plugin = Arc::new(EmittedSourceMaps(Mutex::new(vec![])));
+        let compiler = crate::utils::test_helper::setup_compiler_with_plugins(
+            "test/build/debug-ids",
+            vec![plugin.clone()],
+        );
+        compiler.compile().unwrap();
+
+        let source_maps = plugin.0.lock().unwrap().clone();
+        assert_eq!(source_maps.len(), 2);
+        for source_map in source_maps {
+            let debug_id = source_map.debug_id.unwrap();
+            let asset = std::fs::read_to_string(&source_map.asset_path).unwrap();
+            let comment = if source_map.asset_path.extension().unwrap() == "css" {
+                format!("/*# debugId={}*/", debug_id)
+            } else {
+                format!("//# debugId={}", debug_id)
+            };
+            assert!(asset.contains(&comment), "{}", asset);
+            let map: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(&source_map.source_map_path).unwrap())
+                    .unwrap();
+            assert_eq!(map["debug_id"], debug_id.as_str());
+        }
+    }
+
+    #[test]
+    fn test_entry_shebang() {
+        let compiler = crate::utils::test_helper::setup_compiler("test/build/shebang", false);
+        compiler.compile().unwrap();
+
+        let cli = compiler.context.config.output.path.join("cli.js");
+        let content = std::fs::read_to_string(&cli).unwrap();
+        assert!(content.starts_with("#!/usr/bin/env node\n"));
+        assert_eq!(content.matches("#!").count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&cli).unwrap().permissions().mode();
+            assert_eq!(mode & 0o111, 0o111);
+        }
+    }
 }
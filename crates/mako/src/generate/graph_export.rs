@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use anyhow::Result;
+use indexmap::IndexSet;
+use serde::Serialize;
+
+use crate::compiler::Context;
+use crate::config::{GraphConfig, GraphFormat};
+use crate::generate::chunk::ChunkType;
+use crate::module::{ModuleId, ResolveType};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+enum EdgeKind {
+    Static,
+    Dynamic,
+    Worker,
+}
+
+impl From<&ResolveType> for EdgeKind {
+    fn from(resolve_type: &ResolveType) -> Self {
+        match resolve_type {
+            ResolveType::DynamicImport(_) => EdgeKind::Dynamic,
+            ResolveType::Worker(_) => EdgeKind::Worker,
+            _ => EdgeKind::Static,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct GraphNode {
+    id: String,
+    size: usize,
+    chunks: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct GraphEdge {
+    from: String,
+    to: String,
+    kind: EdgeKind,
+}
+
+#[derive(Serialize, Debug)]
+struct GraphChunk {
+    id: String,
+    #[serde(rename = "type")]
+    chunk_type: String,
+    modules: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct GraphChunkEdge {
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Graph {
+    modules: Vec<GraphNode>,
+    dependencies: Vec<GraphEdge>,
+    chunks: Vec<GraphChunk>,
+    chunk_dependencies: Vec<GraphChunkEdge>,
+}
+
+pub struct GraphExport {}
+
+impl GraphExport {
+    pub fn write_graph(config: &GraphConfig, context: &Arc<Context>) -> Result<()> {
+        let graph = Self::collect(config, context);
+        let (content, ext) = match config.format {
+            GraphFormat::Json => (serde_json::to_string_pretty(&graph)?, "json"),
+            GraphFormat::Dot => (to_dot(&graph), "dot"),
+        };
+        let graph_path = context.config.output.path.join(format!("graph.{}", ext));
+        context.output_fs.write(&graph_path, content.as_bytes())?;
+        if !context.config.quiet {
+            println!("Graph generated at: {}", graph_path.to_string_lossy());
+        }
+        Ok(())
+    }
+
+    fn collect(config: &GraphConfig, context: &Arc<Context>) -> Graph {
+        let module_graph = context.module_graph.read().unwrap();
+        let chunk_graph = context.chunk_graph.read().unwrap();
+        let node_id = |module_id: &ModuleId| {
+            let path = module_id.relative_display(&context.root);
+            if config.collapse_node_modules {
+                package_of(&path).unwrap_or(path)
+            } else {
+                path
+            }
+        };
+
+        let chunks = chunk_graph.get_chunks();
+        let mut module_chunks: BTreeMap<String, IndexSet<String>> = BTreeMap::new();
+        let graph_chunks = chunks
+            .iter()
+            .map(|chunk| {
+                let modules = chunk.modules.iter().map(&node_id).collect::<IndexSet<_>>();
+                for module in &modules {
+                    module_chunks
+                        .entry(module.clone())
+                        .or_default()
+                        .insert(chunk.filename());
+                }
+                GraphChunk {
+                    id: chunk.filename(),
+                    chunk_type: match chunk.chunk_type {
+                        ChunkType::Runtime => "runtime",
+                        ChunkType::Entry(..) => "entry",
+                        ChunkType::Async => "async",
+                        ChunkType::Sync => "sync",
+                        ChunkType::Worker(_) => "worker",
+                    }
+                    .to_string(),
+                    modules: modules.into_iter().collect(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let chunk_dependencies = chunk_graph
+            .graph
+            .edge_indices()
+            .filter_map(|edge| chunk_graph.graph.edge_endpoints(edge))
+            .map(|(from, to)| GraphChunkEdge {
+                from: chunk_graph.graph[from].filename(),
+                to: chunk_graph.graph[to].filename(),
+            })
+            .collect();
+
+        let mut sizes: BTreeMap<String, usize> = BTreeMap::new();
+        let mut edges = BTreeSet::new();
+        for module in module_graph.modules() {
+            let from = node_id(&module.id);
+            *sizes.entry(from.clone()).or_default() +=
+                module.info.as_ref().map_or(0, |info| info.raw.len());
+            for (dep_id, dep) in module_graph.get_dependencies(&module.id) {
+                let to = node_id(dep_id);
+                // collapsed packages may import themselves
+                if from != to {
+                    edges.insert((from.clone(), to, EdgeKind::from(&dep.resolve_type)));
+                }
+            }
+        }
+        let modules = sizes
+            .into_iter()
+            .map(|(id, size)| GraphNode {
+                chunks: module_chunks
+                    .get(&id)
+                    .map(|chunks| chunks.iter().cloned().collect())
+                    .unwrap_or_default(),
+                id,
+                size,
+            })
+            .collect();
+        let dependencies = edges
+            .into_iter()
+            .map(|(from, to, kind)| GraphEdge { from, to, kind })
+            .collect();
+
+        Graph {
+            modules,
+            dependencies,
+            chunks: graph_chunks,
+            chunk_dependencies,
+        }
+    }
+}
+
+// ./node_modules/@a/b/lib/index.js -> ./node_modules/@a/b
+fn package_of(path: &str) -> Option<String> {
+    let index = path.rfind("node_modules/")? + "node_modules/".len();
+    let segments = if path[index..].starts_with('@') { 2 } else { 1 };
+    let name = path[index..]
+        .split('/')
+        .take(segments)
+        .collect::<Vec<_>>()
+        .join("/");
+    Some(format!("{}{}", &path[..index], name))
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// modules are clustered by the first chunk they belong to
+fn to_dot(graph: &Graph) -> String {
+    let mut clusters: BTreeMap<usize, Vec<&GraphNode>> = BTreeMap::new();
+    let mut orphans = vec![];
+    for node in &graph.modules {
+        match node
+            .chunks
+            .first()
+            .and_then(|chunk| graph.chunks.iter().position(|c| &c.id == chunk))
+        {
+            Some(index) => clusters.entry(index).or_default().push(node),
+            None => orphans.push(node),
+        }
+    }
+    let node_line = |node: &GraphNode| {
+        format!(
+            "{} [label={}];",
+            quote(&node.id),
+            quote(&format!("{} ({} B)", node.id, node.size))
+        )
+    };
+
+    let mut lines = vec![
+        "digraph mako {".to_string(),
+        "  node [shape=box];".to_string(),
+    ];
+    for (index, nodes) in clusters {
+        let chunk = &graph.chunks[index];
+        lines.push(format!("  subgraph cluster_{} {{", index));
+        lines.push(format!(
+            "    label={};",
+            quote(&format!("{} ({})", chunk.id, chunk.chunk_type))
+        ));
+        lines.extend(
+            nodes
+                .into_iter()
+                .map(|node| format!("    {}", node_line(node))),
+        );
+        lines.push("  }".to_string());
+    }
+    lines.extend(
+        orphans
+            .into_iter()
+            .map(|node| format!("  {}", node_line(node))),
+    );
+    for edge in &graph.dependencies {
+        let style = match edge.kind {
+            EdgeKind::Static => "",
+            EdgeKind::Dynamic => " [style=dashed]",
+            EdgeKind::Worker => " [style=dotted]",
+        };
+        lines.push(format!(
+            "  {} -> {}{};",
+            quote(&edge.from),
+            quote(&edge.to),
+            style
+        ));
+    }
+    lines.push("}".to_string());
+    format!("{}\n", lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_package_of() {
+        assert_eq!(
+            package_of("./node_modules/foo/index.js"),
+            Some("./node_modules/foo".to_string())
+        );
+        assert_eq!(
+            package_of("./node_modules/@a/b/lib/index.js"),
+            Some("./node_modules/@a/b".to_string())
+        );
+        assert_eq!(
+            package_of("./node_modules/foo/node_modules/bar/index.js"),
+            Some("./node_modules/foo/node_modules/bar".to_string())
+        );
+        assert_eq!(package_of("./src/index.js"), None);
+    }
+
+    #[test]
+    fn test_graph_export() {
+        let compiler = setup_compiler("test/build/graph-export", false);
+        compiler.compile().unwrap();
+        let graph_json = compiler
+            .context
+            .output_fs
+            .read(&compiler.context.config.output.path.join("graph.json"))
+            .unwrap();
+        let graph_json: serde_json::Value = serde_json::from_slice(&graph_json).unwrap();
+        assert!(graph_json["chunkDependencies"].is_array());
+
+        let config = GraphConfig {
+            format: GraphFormat::Json,
+            collapse_node_modules: false,
+        };
+        let graph = GraphExport::collect(&config, &compiler.context);
+        let edge = |from: &str, to: &str| {
+            graph
+                .dependencies
+                .iter()
+                .find(|e| e.from == from && e.to == to)
+                .map(|e| e.kind)
+        };
+        assert_eq!(edge("./index.ts", "./foo.ts"), Some(EdgeKind::Static));
+        assert_eq!(edge("./index.ts", "./lazy.ts"), Some(EdgeKind::Dynamic));
+        assert_eq!(
+            edge("./index.ts", "./worker.ts?asworker"),
+            Some(EdgeKind::Worker)
+        );
+        let index = graph.modules.iter().find(|m| m.id == "./index.ts").unwrap();
+        assert!(index.size > 0);
+        assert_eq!(index.chunks, vec!["index.js"]);
+        assert!(graph
+            .chunks
+            .iter()
+            .any(|c| c.chunk_type == "async" && c.modules.contains(&"./lazy.ts".to_string())));
+        assert!(graph
+            .chunk_dependencies
+            .iter()
+            .any(|e| e.from == "index.js"));
+
+        let config = GraphConfig {
+            format: GraphFormat::Dot,
+            collapse_node_modules: true,
+        };
+        let graph = GraphExport::collect(&config, &compiler.context);
+        assert!(graph.modules.iter().any(|m| m.id == "./node_modules/pkg"));
+        assert!(!graph.modules.iter().any(|m| m.id.ends_with(".js")));
+        let dot = to_dot(&graph);
+        assert!(dot.contains("subgraph cluster_0"));
+        assert!(dot.contains("\"./index.ts\" -> \"./lazy.ts\" [style=dashed];"));
+    }
+}
@@ -1,15 +1,16 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use std::vec;
 
 use tracing::debug;
 
-use crate::ast::file::parse_path;
 use crate::compiler::Compiler;
 use crate::dev::update::UpdateResult;
 use crate::generate::chunk::{Chunk, ChunkId, ChunkType};
 use crate::generate::chunk_graph::ChunkGraph;
-use crate::module::{generate_module_id, ImportOptions, ModuleId, ResolveType};
+use crate::generate::chunk_placement::ChunkPlacement;
+use crate::module::{generate_module_id, ImportMode, ImportOptions, ModuleId, ResolveType};
+use crate::utils::file_request::FileRequest;
 
 pub type GroupUpdateResult = Option<(Vec<ChunkId>, Vec<(ModuleId, ChunkId, ChunkType)>)>;
 
@@ -24,29 +25,40 @@ impl Compiler {
         let mut chunk_graph = self.context.chunk_graph.write().unwrap();
         chunk_graph.clear();
 
-        let entries = module_graph.get_entry_modules();
+        let entries = self.sort_entries_by_depend_on(module_graph.get_entry_modules());
         debug!("entries: {:?}", entries);
-        for entry in entries {
-            let mut entry_chunk_name = "index";
-
-            for (key, value) in &self.context.config.entry {
-                // hmr entry id has query '?hmr'
-                if parse_path(&value.to_string_lossy()).unwrap().0
-                    == parse_path(&entry.id).unwrap().0
+        let mut entry_chunk_ids = HashMap::new();
+        for (entry, entry_chunk_name) in entries {
+            let depend_on = self
+                .entry_depend_on(&entry_chunk_name)
+                .iter()
+                .filter_map(|name| entry_chunk_ids.get(name).cloned())
+                .collect::<Vec<ChunkId>>();
+            // the modules of the entries it depends on are loaded before it
+            let mut shared_chunk_names = vec![];
+            for chunk_id in &depend_on {
+                for chunk_id in chunk_graph
+                    .depend_on_chunks(chunk_id)
+                    .iter()
+                    .chain(std::iter::once(chunk_id))
                 {
-                    entry_chunk_name = key;
-                    break;
+                    let chunk_name = chunk_graph.chunk(chunk_id).unwrap().filename();
+                    if !shared_chunk_names.contains(&chunk_name) {
+                        shared_chunk_names.push(chunk_name);
+                    }
                 }
             }
 
-            let (chunk, dynamic_dependencies, worker_dependencies) = self.create_chunk(
+            let (mut chunk, dynamic_dependencies, worker_dependencies) = self.create_chunk(
                 &entry,
-                ChunkType::Entry(entry.clone(), entry_chunk_name.to_string(), false),
+                ChunkType::Entry(entry.clone(), entry_chunk_name.clone(), false),
                 &mut chunk_graph,
-                vec![],
+                shared_chunk_names.clone(),
                 &ImportOptions::default(),
             );
-            let chunk_name = chunk.filename();
+            chunk.depend_on = depend_on;
+            entry_chunk_ids.insert(entry_chunk_name, chunk.id.clone());
+            shared_chunk_names.insert(0, chunk.filename());
             visited.insert((chunk.id.clone(), ImportOptions::default()));
             edges.extend(
                 [dynamic_dependencies.clone(), worker_dependencies.clone()]
@@ -74,7 +86,7 @@ impl Compiler {
 
             // 抽离成两个函数处理动态依赖中可能有 worker 依赖、worker 依赖中可能有动态依赖的复杂情况
             self.handle_dynamic_dependencies(
-                &chunk_name,
+                &shared_chunk_names,
                 dynamic_dependencies,
                 &visited,
                 &mut edges,
@@ -82,7 +94,7 @@ impl Compiler {
                 &mut visited_workers,
             );
             self.handle_worker_dependencies(
-                &chunk_name,
+                &shared_chunk_names,
                 worker_dependencies,
                 &visited,
                 &mut edges,
@@ -94,11 +106,66 @@ impl Compiler {
         for (from, to) in &edges {
             chunk_graph.add_edge(from, to);
         }
+        chunk_graph.retain_loaded_dynamic_imports();
+    }
+
+    fn entry_depend_on(&self, entry_chunk_name: &str) -> &[String] {
+        self.context
+            .config
+            .entry
+            .get(entry_chunk_name)
+            .map_or(&[], |entry| entry.depend_on.as_slice())
+    }
+
+    /// The entry modules with their chunk names, the entries go after the
+    /// entries they depend on, so that the chunks of those are created first.
+    fn sort_entries_by_depend_on(&self, entries: Vec<ModuleId>) -> Vec<(ModuleId, String)> {
+        let mut pending = entries
+            .into_iter()
+            .map(|entry| {
+                let mut entry_chunk_name = "index";
+
+                for (key, value) in &self.context.config.entry {
+                    // hmr entry id has query '?hmr'
+                    if FileRequest::parse(&value.import.to_string_lossy())
+                        .unwrap()
+                        .path
+                        == FileRequest::parse(&entry.id).unwrap().path
+                    {
+                        entry_chunk_name = key;
+                        break;
+                    }
+                }
+
+                (entry, entry_chunk_name.to_string())
+            })
+            .collect::<Vec<_>>();
+
+        let mut sorted = vec![];
+        while !pending.is_empty() {
+            let pending_names = pending
+                .iter()
+                .map(|(_, name)| name.clone())
+                .collect::<HashSet<_>>();
+            let (ready, rest): (Vec<_>, Vec<_>) = pending.into_iter().partition(|(_, name)| {
+                self.entry_depend_on(name)
+                    .iter()
+                    .all(|depend_on| !pending_names.contains(depend_on))
+            });
+            // the dependOn cycles are rejected by the config
+            if ready.is_empty() {
+                sorted.extend(rest);
+                break;
+            }
+            sorted.extend(ready);
+            pending = rest;
+        }
+        sorted
     }
 
     fn handle_dynamic_dependencies(
         &self,
-        chunk_name: &str,
+        shared_chunk_names: &[String],
         dynamic_dependencies: Vec<(ModuleId, ImportOptions)>,
         visited: &HashSet<(ModuleId, ImportOptions)>,
         edges: &mut Vec<(ModuleId, ModuleId)>,
@@ -110,7 +177,7 @@ impl Compiler {
                 &head.0,
                 ChunkType::Async,
                 chunk_graph,
-                vec![chunk_name.to_string()],
+                shared_chunk_names.to_vec(),
                 &head.1,
             );
 
@@ -143,7 +210,7 @@ impl Compiler {
             }
 
             self.handle_worker_dependencies(
-                chunk_name,
+                shared_chunk_names,
                 worker_dependencies,
                 visited,
                 edges,
@@ -157,7 +224,7 @@ impl Compiler {
 
     fn handle_worker_dependencies(
         &self,
-        chunk_name: &str,
+        shared_chunk_names: &[String],
         worker_dependencies: Vec<(ModuleId, ImportOptions)>,
         visited: &HashSet<(ModuleId, ImportOptions)>,
         edges: &mut Vec<(ModuleId, ModuleId)>,
@@ -169,7 +236,7 @@ impl Compiler {
                 &head.0,
                 ChunkType::Worker(head.0.clone()),
                 chunk_graph,
-                vec![chunk_name.to_string()],
+                shared_chunk_names.to_vec(),
                 &head.1,
             );
 
@@ -204,7 +271,7 @@ impl Compiler {
             visited_workers.insert(head.clone());
 
             self.handle_dynamic_dependencies(
-                chunk_name,
+                shared_chunk_names,
                 dynamic_dependencies,
                 visited,
                 edges,
@@ -220,9 +287,15 @@ impl Compiler {
         crate::mako_profile_function!();
         debug!("group_hot_update_chunk");
 
-        // unique for queried file modules
-        let modified_files = update_result
+        // the modules whose dependency requests are unchanged stay in their chunks
+        let deps_changed_modules = update_result
             .modified
+            .iter()
+            .filter(|m| !update_result.deps_unchanged.contains(m))
+            .collect::<Vec<_>>();
+
+        // unique for queried file modules
+        let modified_files = deps_changed_modules
             .iter()
             // ex. ["a.module.css?modules", "a.module.css?asmodule"] => ["a.module.css"]
             .map(|m| m.id.split('?').next().unwrap())
@@ -244,7 +317,7 @@ impl Compiler {
             // remove chunk if it is the entry module of chunk
             for module_id in &update_result.removed {
                 let chunk_id = ChunkId {
-                    id: module_id.generate(&self.context),
+                    id: module_id.generate(&self.context).into(),
                 };
 
                 if let Some(chunk) = chunk_graph.chunk(&chunk_id) {
@@ -275,7 +348,10 @@ impl Compiler {
         // handle added modules
         if !update_result.added.is_empty() && !update_result.modified.is_empty() {
             // NOTE: currently we only support single modified module
-            let first_modified_module: &ModuleId = update_result.modified.iter().next().unwrap();
+            let first_modified_module: &ModuleId = deps_changed_modules
+                .first()
+                .copied()
+                .unwrap_or_else(|| update_result.modified.iter().next().unwrap());
 
             // add new modules for dependent chunks of modified module
             let modules_in_chunk =
@@ -291,7 +367,9 @@ impl Compiler {
                         .get_dependents(module_id)
                         .iter()
                         .find_map(|(_, dep)| match &dep.resolve_type {
-                            ResolveType::DynamicImport(import_options) => {
+                            ResolveType::DynamicImport(import_options)
+                                if dep.resolve_type.is_async_chunk() =>
+                            {
                                 Some((module_id.clone(), import_options.clone()))
                             }
                             _ => None,
@@ -339,10 +417,15 @@ impl Compiler {
         for (chunk_id, _) in &module_chunks {
             let chunk = chunk_graph.chunk(chunk_id).unwrap();
 
-            if let ChunkType::Entry(_, _, _) = chunk.chunk_type {
-                ret.push(chunk.filename());
+            let entry_chunk_ids = if let ChunkType::Entry(_, _, _) = chunk.chunk_type {
+                vec![chunk.id.clone()]
             } else {
-                for chunk_id in chunk_graph.entry_ancestors_chunk(&chunk.id) {
+                chunk_graph.entry_ancestors_chunk(&chunk.id)
+            };
+            for chunk_id in entry_chunk_ids {
+                ret.push(chunk_graph.chunk(&chunk_id).unwrap().filename());
+                // the modules of the entries it depends on are loaded too
+                for chunk_id in chunk_graph.depend_on_chunks(&chunk_id) {
                     ret.push(chunk_graph.chunk(&chunk_id).unwrap().filename());
                 }
             }
@@ -367,10 +450,14 @@ impl Compiler {
                 .get_dependencies(head)
                 .into_iter()
                 .filter(|(_, dep)| {
-                    !matches!(
-                        dep.resolve_type,
-                        ResolveType::DynamicImport(_) | ResolveType::Worker(_)
-                    )
+                    !dep.resolve_type.is_async_chunk()
+                        && !matches!(
+                            dep.resolve_type,
+                            ResolveType::DynamicImport(ImportOptions {
+                                mode: ImportMode::Weak,
+                                ..
+                            })
+                        )
                 })
                 .collect::<Vec<_>>();
             let mut next_module_ids = vec![];
@@ -389,6 +476,8 @@ impl Compiler {
 
                     // add new module to all parent chunks
                     for (chunk_id, _) in &module_chunks {
+                        // placed in the chunk for the same reason as the importer
+                        let placement = chunk_graph.placement(chunk_id, head).cloned();
                         let module_chunk = chunk_graph.mut_chunk(chunk_id).unwrap();
 
                         if !module_chunk.has_module(dep_module_id) {
@@ -400,6 +489,9 @@ impl Compiler {
                                 module_chunk.chunk_type.clone(),
                             ));
                             is_new_module = true;
+                            if let Some(placement) = placement {
+                                chunk_graph.set_placement(chunk_id, dep_module_id, placement);
+                            }
                         }
                     }
 
@@ -444,7 +536,7 @@ impl Compiler {
         Vec<(ModuleId, ImportOptions)>,
     ) {
         crate::mako_profile_function!(&chunk_id.id);
-        let mut dynamic_entries = vec![];
+        let mut dynamic_imports = vec![];
         let mut worker_entries = vec![];
 
         let chunk_id_str = match import_options.get_chunk_name() {
@@ -452,6 +544,8 @@ impl Compiler {
             None => chunk_id.generate(&self.context),
         };
         let mut chunk = Chunk::new(chunk_id_str.into(), chunk_type.clone());
+        chunk.hash_function = self.context.config.output.hash_function;
+        chunk.hash_length = self.context.config.output.hash_length;
 
         let module_graph = self.context.module_graph.read().unwrap();
 
@@ -460,12 +554,24 @@ impl Compiler {
 
             for (dep_module_id, dep) in module_graph.get_dependencies(head) {
                 match &dep.resolve_type {
-                    ResolveType::DynamicImport(chunk_group) => {
-                        dynamic_entries.push((dep_module_id.clone(), chunk_group.clone()));
+                    // not bundled, it's resolved at runtime if other chunks loaded it
+                    ResolveType::DynamicImport(ImportOptions {
+                        mode: ImportMode::Weak,
+                        ..
+                    }) => {}
+                    ResolveType::DynamicImport(chunk_group)
+                        if chunk_group.mode != ImportMode::Eager =>
+                    {
+                        dynamic_imports.push((
+                            head.clone(),
+                            dep_module_id.clone(),
+                            chunk_group.clone(),
+                        ));
                     }
                     ResolveType::Worker(chunk_group) => {
                         worker_entries.push((dep_module_id.clone(), chunk_group.clone()));
                     }
+                    // eager dynamic imports are bundled as the static ones
                     // skip shared modules from entry chunks, but except worker chunk modules
                     _ if matches!(chunk_type, ChunkType::Worker(_))
                         || !self.is_entry_shared_module(
@@ -483,11 +589,31 @@ impl Compiler {
             next_module_ids
         });
 
+        let root = chunk_id.id.to_string();
+        let placement = match &chunk_type {
+            ChunkType::Entry(..) => ChunkPlacement::Entry { entry: root },
+            ChunkType::Worker(_) => ChunkPlacement::Worker { root },
+            _ => ChunkPlacement::Async { root },
+        };
         // add modules to chunk as dfs order
         while let Some(dep) = chunk_deps.pop() {
+            chunk_graph.set_placement(&chunk.id, &dep, placement.clone());
             chunk.add_module(dep);
         }
 
+        // the modules imported by the chunk itself, e.g. self imports or the
+        // dynamic cycles, are loaded already, they don't create chunks
+        let mut dynamic_entries = vec![];
+        for (importer, dep_module_id, chunk_group) in dynamic_imports {
+            if chunk.has_module(&dep_module_id)
+                || self.is_entry_shared_module(&dep_module_id, &shared_chunk_names, chunk_graph)
+            {
+                chunk_graph.add_loaded_dynamic_import(&importer, &dep_module_id);
+            } else {
+                dynamic_entries.push((dep_module_id, chunk_group));
+            }
+        }
+
         (chunk, dynamic_entries, worker_entries)
     }
 
@@ -599,3 +725,251 @@ where
 
     right_first_dfs_ret
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::generate::chunk::ChunkType;
+    use crate::module::ModuleId;
+    use crate::testing::compile_fixture;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_import_modes() {
+        let compiler = setup_compiler("test/build/import-mode", false);
+        compiler.compile().unwrap();
+        let chunk_graph = compiler.context.chunk_graph.read().unwrap();
+        let chunks = chunk_graph.get_chunks();
+        let chunks_of = |file: &str| {
+            chunks
+                .iter()
+                .filter(|chunk| chunk.modules.iter().any(|m| m.id.ends_with(file)))
+                .collect::<Vec<_>>()
+        };
+        // eager is in the chunk of the importer
+        let eager = chunks_of("eager.js");
+        assert_eq!(eager.len(), 1);
+        assert!(matches!(eager[0].chunk_type, ChunkType::Entry(..)));
+        // weak is not bundled
+        assert!(chunks_of("weak.js").is_empty());
+        let lazy = chunks_of("lazy.js");
+        assert_eq!(lazy.len(), 1);
+        assert!(matches!(lazy[0].chunk_type, ChunkType::Async));
+        // lazy-once puts all the matched modules in the chunk of the context module
+        let en = chunks_of("locales/en.js");
+        assert_eq!(en.len(), 1);
+        assert!(matches!(en[0].chunk_type, ChunkType::Async));
+        assert_eq!(en[0].id, chunks_of("locales/zh.js")[0].id);
+        assert!(en[0]
+            .modules
+            .iter()
+            .any(|m| m.query().iter().any(|(key, _)| key == "context")));
+    }
+
+    #[test]
+    fn test_loaded_dynamic_imports() {
+        let compiler = setup_compiler("test/build/dynamic-import-self", false);
+        compiler.compile().unwrap();
+        let context = &compiler.context;
+        let chunk_graph = context.chunk_graph.read().unwrap();
+        let chunks = chunk_graph.get_all_chunks();
+        // no chunk for the self import, and one for the root of the cycle
+        assert_eq!(chunks.len(), 2);
+        let async_chunk = chunks
+            .iter()
+            .find(|chunk| matches!(chunk.chunk_type, ChunkType::Async))
+            .unwrap();
+        assert!(async_chunk.modules.iter().any(|m| m.id.ends_with("a.js")));
+        assert!(async_chunk.modules.iter().any(|m| m.id.ends_with("b.js")));
+        let module_id = |name: &str| ModuleId::from(context.root.join(name));
+        assert!(
+            chunk_graph.is_loaded_dynamic_import(&module_id("index.js"), &module_id("index.js"))
+        );
+        assert!(chunk_graph.is_loaded_dynamic_import(&module_id("b.js"), &module_id("a.js")));
+        assert!(!chunk_graph.is_loaded_dynamic_import(&module_id("index.js"), &module_id("a.js")));
+
+        let read = |name: &str| {
+            String::from_utf8(
+                context
+                    .output_fs
+                    .read(&context.config.output.path.join(name))
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+        let index = read("index.js");
+        assert!(
+            !index.contains(r#"__mako_require__.ensure("index.js")"#),
+            "{}",
+            index
+        );
+        assert!(
+            index.contains(r#"__mako_require__.ensure("a.js")"#),
+            "{}",
+            index
+        );
+        let a = read("a_js-async.js");
+        assert!(!a.contains("__mako_require__.ensure"), "{}", a);
+        assert!(a.contains("Promise.resolve()"), "{}", a);
+    }
+
+    #[test]
+    fn test_async_only_deps() {
+        let compiler = setup_compiler("test/build/async-only-deps", false);
+        compiler.compile().unwrap();
+        let chunk_graph = compiler.context.chunk_graph.read().unwrap();
+        let chunks = chunk_graph.get_chunks();
+        let chunk_of = |chunk_type: fn(&ChunkType) -> bool| {
+            let chunk = chunks
+                .iter()
+                .find(|chunk| chunk_type(&chunk.chunk_type))
+                .unwrap();
+            chunk
+                .modules
+                .iter()
+                .map(|m| m.id.clone())
+                .collect::<Vec<_>>()
+        };
+        let entry = chunk_of(|t| matches!(t, ChunkType::Entry(..)));
+        let route = chunk_of(|t| matches!(t, ChunkType::Async));
+        // only reachable from the entry across the dynamic import
+        assert!(!entry.iter().any(|m| m.contains("node_modules/heavy")));
+        assert!(route.iter().any(|m| m.contains("node_modules/heavy")));
+        // reachable from the entry statically, so it's not in the async chunk
+        assert!(entry.iter().any(|m| m.ends_with("shared.ts")));
+        assert!(!route.iter().any(|m| m.ends_with("shared.ts")));
+
+        let output = &compiler.context.config.output.path;
+        let code = String::from_utf8(
+            compiler
+                .context
+                .output_fs
+                .read(&output.join("index.js"))
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(!code.contains("HEAVY_LIBRARY_CHART"));
+    }
+
+    #[test]
+    fn test_async_only_deps_code_splitting() {
+        // the group is loaded with the entry, e.g. the framework group of granular
+        let compiler = compile_fixture(
+            Path::new("test/build/async-only-deps"),
+            Some(
+                r#"{"mode":"production","hash":false,"minify":false,"manifest":{},"codeSplitting":{"strategy":"advanced","options":{"groups":[{"name":"framework","allowChunks":"all","minSize":1,"test":"node_modules"}]}}}"#,
+            ),
+        )
+        .unwrap();
+        let context = &compiler.context;
+        {
+            let chunk_graph = context.chunk_graph.read().unwrap();
+            let chunks_of = |package: &str| {
+                chunk_graph
+                    .get_chunks()
+                    .into_iter()
+                    .filter(|chunk| {
+                        chunk
+                            .modules
+                            .iter()
+                            .any(|m| m.id.contains(&format!("node_modules/{}", package)))
+                    })
+                    .map(|chunk| chunk.chunk_type.clone())
+                    .collect::<Vec<_>>()
+            };
+            assert!(matches!(
+                chunks_of("ui").as_slice(),
+                [ChunkType::Entry(_, _, true)]
+            ));
+            // only reachable across the dynamic import, so it stays in the
+            // async chunk instead of the group loaded with the entry
+            assert_eq!(chunks_of("heavy"), vec![ChunkType::Async]);
+        }
+
+        let read = |name: &str| {
+            String::from_utf8(
+                context
+                    .output_fs
+                    .read(&context.config.output.path.join(name))
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+        let manifest: serde_json::Value =
+            serde_json::from_str(&read("asset-manifest.json")).unwrap();
+        let entry_files = manifest["entrypoints"]["index"].as_array().unwrap();
+        assert_eq!(
+            entry_files,
+            &vec![
+                serde_json::json!("framework.js"),
+                serde_json::json!("index.js")
+            ]
+        );
+        for file in entry_files {
+            let code = read(file.as_str().unwrap());
+            assert!(!code.contains("HEAVY_LIBRARY_CHART"), "{}", code);
+        }
+        assert!(read("framework.js").contains("UI_BUTTON"));
+    }
+
+    #[test]
+    fn test_entry_depend_on() {
+        let compiler = setup_compiler("test/build/entry-depend-on", false);
+        compiler.compile().unwrap();
+        let context = &compiler.context;
+        {
+            let chunk_graph = context.chunk_graph.read().unwrap();
+            let chunks = chunk_graph.get_chunks();
+            let chunks_of = |file: &str| {
+                chunks
+                    .iter()
+                    .filter(|chunk| chunk.modules.iter().any(|m| m.id.ends_with(file)))
+                    .map(|chunk| chunk.name())
+                    .collect::<Vec<_>>()
+            };
+            // the shared module is only in the chunk of the entry depended on
+            assert_eq!(chunks_of("shared.js"), vec!["bootstrap"]);
+            assert_eq!(chunks_of("index.js"), vec!["main"]);
+            let main = chunks.iter().find(|chunk| chunk.name() == "main").unwrap();
+            assert!(main.is_dependent_entry());
+        }
+
+        let read = |name: &str| {
+            context
+                .output_fs
+                .read(&context.config.output.path.join(name))
+                .unwrap()
+        };
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&read("asset-manifest.json")).unwrap();
+        assert_eq!(
+            manifest["entrypoints"]["main"],
+            serde_json::json!(["bootstrap.js", "main.js"])
+        );
+        let main = String::from_utf8(read("main.js")).unwrap();
+        assert!(!main.contains("function createRuntime"), "{}", main);
+
+        // the dependent entry is executed after the runtime of the entry it
+        // depends on even if it's loaded first
+        let output_path = &context.config.output.path;
+        let output = std::process::Command::new("node")
+            .arg("-e")
+            .arg(format!(
+                "process.on('unhandledRejection', () => {{}}); require({:?}); require({:?}); console.log(JSON.stringify(globalThis.order));",
+                output_path.join("main.js"),
+                output_path.join("bootstrap.js"),
+            ))
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            r#"["shared","bootstrap","main"]"#
+        );
+    }
+}
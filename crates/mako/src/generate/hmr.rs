@@ -9,6 +9,7 @@ use crate::ast::js_ast::{JSAstGenerated, JsAst};
 use crate::compiler::Compiler;
 use crate::generate::chunk::Chunk;
 use crate::generate::generate_chunks::modules_to_js_stmts;
+use crate::generate::runtime::downlevel_runtime_code;
 use crate::module::ModuleId;
 
 impl Compiler {
@@ -32,7 +33,12 @@ impl Compiler {
 
         let content = content
             .replace("__CHUNK_ID__", &chunk.id.id)
+            .replace(
+                "__UNIQUE_NAME__",
+                &serde_json::to_string(&self.context.config.output.unique_name)?,
+            )
             .replace("__runtime_code__", &runtime_code_snippets.join("\n"));
+        let content = downlevel_runtime_code(content, &self.context)?;
 
         let mut js_ast = JsAst::build(filename, content.as_str(), self.context.clone())
             /* safe */
@@ -0,0 +1,247 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+
+use crate::compiler::Context;
+use crate::config::static_public_path;
+use crate::generate::critical_css::style_tags;
+use crate::generate::csp_report::{entry_nonce, nonce_attr};
+use crate::generate::inline_chunks::{self, InlinedChunk};
+use crate::plugins::html_entry::{has_html_entry, html_entry};
+use crate::plugins::import_map::import_map_tag;
+
+pub(crate) const DEFAULT_HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8" />
+</head>
+<body>
+</body>
+</html>
+"#;
+
+/// The files of an entry in the load order, the inlined chunks are in
+/// place of their files.
+#[derive(Debug, Clone)]
+pub(crate) enum EntryFile {
+    File(String),
+    Inlined(InlinedChunk),
+}
+
+impl EntryFile {
+    pub fn is_css(&self) -> bool {
+        match self {
+            EntryFile::File(file) => file.ends_with(".css"),
+            EntryFile::Inlined(chunk) => chunk.is_css,
+        }
+    }
+
+    pub fn is_js(&self) -> bool {
+        match self {
+            EntryFile::File(file) => file.ends_with(".js"),
+            EntryFile::Inlined(chunk) => !chunk.is_css,
+        }
+    }
+}
+
+/// The scripts of the legacy build of dualBuild, which are loaded by the
+/// html of the modern build with `nomodule`.
+pub(crate) struct LegacyScripts {
+    pub prefix: String,
+    /// entry name -> files in load order
+    pub entrypoints: Vec<(String, Vec<String>)>,
+}
+
+/// Whether `<entry>.html` is rendered by the build, which is for the chunks
+/// inlined in it, the critical css, the injected import map or the html
+/// entries, and not for dualBuild, whose html is rendered after all builds
+/// with the legacy scripts.
+pub(crate) fn has_html(context: &Context) -> bool {
+    let config = &context.config;
+    let inject_import_map = config.import_map.as_ref().is_some_and(|c| c.inject);
+    (inline_chunks::is_enabled(context)
+        || config.critical_css.is_some()
+        || inject_import_map
+        || has_html_entry(config))
+        && config.dual_build.is_none()
+}
+
+/// Write `<entry>.html` of the entries, see [render_html].
+pub(crate) fn write_html(
+    context: &Arc<Context>,
+    legacy: Option<&LegacyScripts>,
+) -> Result<Vec<(String, String)>> {
+    let htmls = render_html(context, legacy)?;
+    for (name, html) in &htmls {
+        let path = context.config.output.path.join(name);
+        context.output_fs.write(&path, html.as_bytes())?;
+    }
+    Ok(htmls)
+}
+
+/// `<entry>.html` -> html of the entries, which loads the files of the entry
+/// in the template, with the critical css, the import map and the nonce of
+/// the entry. The scripts are modules with the legacy ones of dualBuild.
+pub(crate) fn render_html(
+    context: &Arc<Context>,
+    legacy: Option<&LegacyScripts>,
+) -> Result<Vec<(String, String)>> {
+    let template = html_template(context)?;
+    // the public path is resolved at runtime, the files are relative to the
+    // html then
+    let prefix = static_public_path(&context.config.public_path);
+    let mut htmls = vec![];
+    for (name, files) in entry_files(context) {
+        // the workers are not loaded by the html
+        if context.config.is_webworker_entry(&name) {
+            continue;
+        }
+        let template = with_public_path(
+            &html_entry(&name, context).map_or_else(|| template.clone(), |entry| entry.template),
+            prefix,
+        );
+        let nonce = entry_nonce(&name, context);
+        let nonce = nonce.as_deref();
+        let styles = import_map_tag(nonce, context)?
+            .into_iter()
+            .chain(
+                files
+                    .iter()
+                    .filter(|file| file.is_css())
+                    .map(|file| match file {
+                        EntryFile::File(file) => {
+                            style_tags(file, &format!("{}{}", prefix, file), nonce, context)
+                        }
+                        EntryFile::Inlined(chunk) => chunk.tag(nonce),
+                    }),
+            )
+            .collect::<Vec<_>>();
+        let script_type = if legacy.is_some() {
+            r#" type="module""#
+        } else {
+            ""
+        };
+        let mut scripts = files
+            .iter()
+            .filter(|file| file.is_js())
+            .map(|file| match file {
+                EntryFile::File(file) => format!(
+                    r#"<script{} src="{}{}"{}></script>"#,
+                    script_type,
+                    prefix,
+                    file,
+                    nonce_attr(nonce)
+                ),
+                EntryFile::Inlined(chunk) => chunk.tag(nonce),
+            })
+            .collect::<Vec<_>>();
+        if let Some(legacy) = legacy
+            && let Some((_, files)) = legacy.entrypoints.iter().find(|(n, _)| *n == name)
+        {
+            scripts.extend(
+                files
+                    .iter()
+                    .filter(|file| file.ends_with(".js"))
+                    .map(|file| {
+                        format!(
+                            r#"<script nomodule src="{}{}"{}></script>"#,
+                            legacy.prefix,
+                            file,
+                            nonce_attr(nonce)
+                        )
+                    }),
+            );
+        }
+        let html = inject(&template, "</head>", &styles);
+        let html = inject(&html, "</body>", &scripts);
+        htmls.push((format!("{}.html", name), html));
+    }
+    Ok(htmls)
+}
+
+fn html_template(context: &Context) -> Result<String> {
+    let config = &context.config;
+    let (option, template) = match (&config.dual_build, &config.critical_css) {
+        (Some(dual_build), _) => ("dualBuild.template", dual_build.template.as_ref()),
+        (None, Some(critical_css)) => ("criticalCss.template", critical_css.template.as_ref()),
+        (None, None) => ("", None),
+    };
+    match template {
+        Some(template) => std::fs::read_to_string(context.root.join(template))
+            .map_err(|e| anyhow!("Read {} {:?} failed: {}", option, template, e)),
+        None => Ok(DEFAULT_HTML_TEMPLATE.to_string()),
+    }
+}
+
+/// The files of the entries in the load order of
+/// [ChunkGraph::entrypoints](crate::generate::chunk_graph::ChunkGraph::entrypoints),
+/// the css before the js of each chunk, the inlined chunks are in place of
+/// their files.
+pub(crate) fn entry_files(context: &Context) -> Vec<(String, Vec<EntryFile>)> {
+    let chunk_graph = context.chunk_graph.read().unwrap();
+    let inlined = context.stats_info.inlined_chunks.lock().unwrap();
+    chunk_graph
+        .entrypoints()
+        .into_iter()
+        .map(|entrypoint| {
+            let files = entrypoint
+                .chunks
+                .iter()
+                .flat_map(|chunk_id| {
+                    let mut files = context
+                        .stats_info
+                        .get_chunks_assets(std::slice::from_ref(chunk_id))
+                        .into_iter()
+                        .map(|asset| EntryFile::File(asset.hashname))
+                        .chain(
+                            inlined
+                                .iter()
+                                .filter(|chunk| chunk.chunk_id == chunk_id.id)
+                                .cloned()
+                                .map(EntryFile::Inlined),
+                        )
+                        .collect::<Vec<_>>();
+                    files.sort_by_key(|file| !file.is_css());
+                    files
+                })
+                .collect();
+            (entrypoint.name, files)
+        })
+        .collect()
+}
+
+/// Entry name -> files in load order, without the inlined chunks.
+pub(crate) fn entrypoint_files(context: &Context) -> Vec<(String, Vec<String>)> {
+    let chunk_graph = context.chunk_graph.read().unwrap();
+    chunk_graph
+        .entrypoints()
+        .into_iter()
+        .map(|entrypoint| {
+            let files = context
+                .stats_info
+                .get_chunks_assets(&entrypoint.chunks)
+                .into_iter()
+                .map(|asset| asset.hashname)
+                .collect();
+            (entrypoint.name, files)
+        })
+        .collect()
+}
+
+/// Replace `{{ publicPath }}` of the html template, e.g. for the favicon
+/// copied from the public directory.
+pub(crate) fn with_public_path(template: &str, public_path: &str) -> String {
+    template.replace("{{ publicPath }}", public_path)
+}
+
+// insert the tags before the closing tag, or append them if it's missing
+pub(crate) fn inject(html: &str, closing_tag: &str, tags: &[String]) -> String {
+    if tags.is_empty() {
+        return html.to_string();
+    }
+    let tags = tags.join("\n");
+    match html.rfind(closing_tag) {
+        Some(index) => format!("{}{}\n{}", &html[..index], tags, &html[index..]),
+        None => format!("{}{}\n", html, tags),
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+
+use crate::compiler::Context;
+use crate::generate::chunk::ChunkType;
+use crate::generate::csp_report::nonce_attr;
+use crate::generate::generate_chunks::{ChunkFile, ChunkFileType};
+use crate::utils::{base64_encode, create_cached_regex};
+
+/// The chunk name of the runtime which is split out of the entry chunks by
+/// inlineRuntime, or by inlineChunks which matches it.
+pub(crate) const RUNTIME_CHUNK_NAME: &str = "runtime";
+
+/// A chunk file of the entries which is inlined in the html instead of
+/// being emitted.
+#[derive(Debug, Clone)]
+pub struct InlinedChunk {
+    pub chunk_id: String,
+    /// the name of the file it would be emitted as
+    pub file_name: String,
+    pub is_css: bool,
+    /// the content which is safe in the `<script>` or the `<style>` element
+    pub content: String,
+}
+
+impl InlinedChunk {
+    pub fn tag(&self, nonce: Option<&str>) -> String {
+        let element = if self.is_css { "style" } else { "script" };
+        format!(
+            "<{}{}>{}</{}>",
+            element,
+            nonce_attr(nonce),
+            self.content,
+            element
+        )
+    }
+
+    /// `sha256-...` of the content, which the Content Security Policy allows
+    /// it by.
+    pub fn hash(&self) -> String {
+        format!(
+            "sha256-{}",
+            base64_encode(Sha256::digest(self.content.as_bytes()))
+        )
+    }
+}
+
+pub(crate) fn is_enabled(context: &Context) -> bool {
+    // the dev server serves the files, which are hot updated
+    !context.args.watch
+        && (context.config.inline_runtime || !context.config.inline_chunks.is_empty())
+}
+
+fn matches_inline_chunks(chunk_name: &str, context: &Context) -> bool {
+    context
+        .config
+        .inline_chunks
+        .iter()
+        .any(|pattern| create_cached_regex(pattern).is_match(chunk_name))
+}
+
+/// Whether the runtime is split out of the entry chunks to be inlined.
+pub(crate) fn inline_runtime(context: &Context) -> bool {
+    is_enabled(context)
+        && (context.config.inline_runtime || matches_inline_chunks(RUNTIME_CHUNK_NAME, context))
+}
+
+/// Take the chunk files which are inlined out of the files to emit, they are
+/// the runtime and the chunks of the entries which match inlineChunks, the
+/// async chunks are loaded by the runtime and not inlined.
+pub(crate) fn take_inlined_chunks(chunk_files: &mut Vec<ChunkFile>, context: &Arc<Context>) {
+    let mut inlined = context.stats_info.inlined_chunks.lock().unwrap();
+    inlined.clear();
+    if !is_enabled(context) {
+        return;
+    }
+    let entry_chunk_ids = {
+        let chunk_graph = context.chunk_graph.read().unwrap();
+        chunk_graph
+            .get_chunks()
+            .into_iter()
+            .filter(|chunk| matches!(chunk.chunk_type, ChunkType::Entry(_, _, _)))
+            .flat_map(|chunk| {
+                let mut chunk_ids = chunk_graph.entry_dependencies_chunk(&chunk.id);
+                chunk_ids.push(chunk.id.clone());
+                chunk_ids
+            })
+            .map(|chunk_id| chunk_id.id)
+            .collect::<HashSet<_>>()
+    };
+    let runtime = inline_runtime(context);
+    chunk_files.retain(|file| {
+        let is_inlined = entry_chunk_ids.contains(file.chunk_id.as_str())
+            && ((runtime && file.chunk_name == RUNTIME_CHUNK_NAME)
+                || matches_inline_chunks(&file.chunk_name, context));
+        if !is_inlined {
+            return true;
+        }
+        let content = String::from_utf8_lossy(&file.content);
+        let is_css = matches!(file.file_type, ChunkFileType::Css);
+        inlined.push(InlinedChunk {
+            chunk_id: file.chunk_id.clone(),
+            file_name: file.disk_name(),
+            is_css,
+            content: if is_css {
+                inline_style(&content)
+            } else {
+                inline_script(&content)
+            },
+        });
+        false
+    });
+}
+
+// the code which doesn't end the <script> element or start a comment in it,
+// `</script` and `<!--` only appear in the strings, the regexes and the
+// comments of the chunks, where the escaped ones mean the same
+pub(crate) fn inline_script(code: &str) -> String {
+    create_cached_regex(r"(?i)</(script)")
+        .replace_all(code, r"<\/$1")
+        .replace("<!--", r"<\!--")
+}
+
+// the css which doesn't end the <style> element, `</style` only appears in
+// the strings and the comments, where the escaped one means the same
+fn inline_style(code: &str) -> String {
+    create_cached_regex(r"(?i)</(style)")
+        .replace_all(code, r"<\/$1")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::utils::output_fs::{MemoryFileSystem, OutputFileSystem};
+    use crate::utils::test_helper::setup_compiler_with_output_fs;
+
+    #[test]
+    fn test_inline_script() {
+        assert_eq!(
+            inline_script(r#"a("</script>");b("</SCRIPT>");c("<!--")"#),
+            r#"a("<\/script>");b("<\/SCRIPT>");c("<\!--")"#
+        );
+        assert_eq!(
+            inline_style("a{content:'</style>'}"),
+            r"a{content:'<\/style>'}"
+        );
+    }
+
+    #[test]
+    fn test_inline_runtime() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        let compiler = setup_compiler_with_output_fs("test/build/inline-runtime", fs.clone());
+        compiler.compile().unwrap();
+        let output = compiler.context.config.output.path.clone();
+        let read = |name: &str| String::from_utf8(fs.read(&output.join(name)).unwrap()).unwrap();
+
+        assert!(!fs.exists(&output.join("runtime~index.js")));
+        let index = read("index.js");
+        assert!(index.contains("</script>"));
+        assert!(!index.contains("function createRuntime"));
+
+        let html = read("index.html");
+        let css = html
+            .find(r#"<link rel="stylesheet" href="/index.css" />"#)
+            .unwrap();
+        let js = html.find(r#"<script src="/index.js"></script>"#).unwrap();
+        let runtime = html.find("function createRuntime").unwrap();
+        assert!(css < js && js < runtime);
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&read("asset-manifest.json")).unwrap();
+        assert_eq!(manifest["index.js"], "index.js");
+        assert!(manifest["inlined"]["runtime~index.js"]
+            .as_str()
+            .unwrap()
+            .starts_with("sha256-"));
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use indexmap::IndexSet;
+use regex::Regex;
+
+use crate::compiler::Compiler;
+use crate::config::LicenseCommentsConfig;
+use crate::generate::generate_chunks::{ChunkFile, ChunkFileType};
+use crate::module::{ModuleAst, ModuleInfo};
+use crate::resolve::{ResolvedResource, ResolverResource};
+
+const THIRD_PARTY_NOTICES: &str = "THIRD-PARTY-NOTICES.txt";
+
+fn comment_regex() -> &'static Regex {
+    static COMMENT_REGEX: OnceLock<Regex> = OnceLock::new();
+    COMMENT_REGEX.get_or_init(|| Regex::new(r"(?s)/\*.*?\*/|//[^\r\n]*").unwrap())
+}
+
+fn is_license_comment(comment: &str) -> bool {
+    comment.starts_with("/*!")
+        || comment.starts_with("//!")
+        || comment.contains("@license")
+        || comment.contains("@preserve")
+}
+
+// e.g. /*! foo v1.0.0 | MIT */, /** @license MIT */, // @preserve
+pub fn extract_license_comments(code: &str) -> Vec<String> {
+    comment_regex()
+        .find_iter(code)
+        .map(|m| m.as_str())
+        .filter(|c| is_license_comment(c))
+        .map(|c| c.to_string())
+        .collect()
+}
+
+// name@version from the nearest package.json
+fn package_of(info: &ModuleInfo) -> Option<String> {
+    match &info.resolved_resource {
+        Some(ResolverResource::Resolved(ResolvedResource(resolution))) => {
+            let package_json = resolution.package_json()?;
+            let name = package_json.name.clone()?;
+            let version = package_json
+                .raw_json()
+                .get("version")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown");
+            Some(format!("{}@{}", name, version))
+        }
+        _ => None,
+    }
+}
+
+impl Compiler {
+    pub(crate) fn handle_license_comments(&self, chunk_files: &mut [ChunkFile]) -> Result<()> {
+        let config = match &self.context.config.license_comments {
+            Some(config) => config,
+            None => return Ok(()),
+        };
+        // comments are kept by codegen when not minified
+        if *config == LicenseCommentsConfig::Inline && self.context.config.minify.is_none() {
+            return Ok(());
+        }
+
+        let chunk_graph = self.context.chunk_graph.read().unwrap();
+        let module_graph = self.context.module_graph.read().unwrap();
+        let mut notices: BTreeMap<String, IndexSet<String>> = BTreeMap::new();
+
+        for chunk_file in chunk_files.iter_mut() {
+            let chunk = match chunk_graph.chunk(&chunk_file.chunk_id.as_str().into()) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            let is_css_file = matches!(chunk_file.file_type, ChunkFileType::Css);
+            let mut comments = IndexSet::new();
+            for module_id in chunk.get_modules() {
+                let info = match module_graph
+                    .get_module(module_id)
+                    .and_then(|m| m.info.as_ref())
+                {
+                    Some(info) => info,
+                    None => continue,
+                };
+                if matches!(info.ast, ModuleAst::Css(_)) != is_css_file {
+                    continue;
+                }
+                let module_comments = extract_license_comments(&info.raw);
+                if module_comments.is_empty() {
+                    continue;
+                }
+                if let Some(package) = package_of(info) {
+                    notices
+                        .entry(package)
+                        .or_default()
+                        .extend(module_comments.iter().cloned());
+                }
+                comments.extend(module_comments);
+            }
+            if comments.is_empty() {
+                continue;
+            }
+
+            let comments = comments.into_iter().collect::<Vec<_>>();
+            // append instead of prepend, so that the source map is not affected
+            let appended = match config {
+                LicenseCommentsConfig::Inline => comments.join("\n"),
+                LicenseCommentsConfig::Extract => {
+                    let license_file = format!("{}.LICENSE.txt", chunk_file.disk_name());
+                    self.write_to_dist(&license_file, comments.join("\n\n"));
+                    format!("/*! For license information please see {} */", license_file)
+                }
+            };
+            chunk_file.content.push(b'\n');
+            chunk_file.content.extend(appended.as_bytes());
+        }
+
+        if *config == LicenseCommentsConfig::Extract && !notices.is_empty() {
+            let content = notices
+                .into_iter()
+                .map(|(package, comments)| {
+                    format!(
+                        "{}\n\n{}",
+                        package,
+                        comments.into_iter().collect::<Vec<_>>().join("\n\n")
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n---\n\n");
+            self.write_to_dist(THIRD_PARTY_NOTICES, format!("{}\n", content));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_extract_license_comments() {
+        let code = r#"
+/*! foo v1.0.0 | MIT */
+/**
+ * @license React
+ */
+// @preserve bar
+// normal comment
+/* normal comment */
+const a = 1;
+"#;
+        assert_eq!(
+            extract_license_comments(code),
+            vec![
+                "/*! foo v1.0.0 | MIT */",
+                "/**\n * @license React\n */",
+                "// @preserve bar"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_license_comments_extract() {
+        let compiler = setup_compiler("test/build/license-comments", false);
+        compiler.compile().unwrap();
+        let output_path = &compiler.context.config.output.path;
+        let output_fs = &compiler.context.output_fs;
+
+        let index =
+            String::from_utf8(output_fs.read(&output_path.join("index.js")).unwrap()).unwrap();
+        assert!(index.contains("/*! For license information please see index.js.LICENSE.txt */"));
+
+        let license = String::from_utf8(
+            output_fs
+                .read(&output_path.join("index.js.LICENSE.txt"))
+                .unwrap(),
+        )
+        .unwrap();
+        // deduplicated
+        assert_eq!(license.matches("@license foo").count(), 1);
+        assert!(license.contains("/*! bar | MIT */"));
+        assert!(!license.contains("normal comment"));
+
+        let notices = String::from_utf8(
+            output_fs
+                .read(&output_path.join(THIRD_PARTY_NOTICES))
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(notices.contains("foo@1.2.3"));
+        assert!(notices.contains("bar@0.1.0"));
+    }
+}
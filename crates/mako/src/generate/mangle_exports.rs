@@ -0,0 +1,600 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use serde::Serialize;
+use swc_core::common::DUMMY_SP;
+use swc_core::ecma::ast::{
+    Decl, ExportNamedSpecifier, ExportSpecifier, Expr, Id, Ident, ImportDecl, ImportSpecifier, Lit,
+    MemberExpr, MemberProp, Module, ModuleDecl, ModuleExportName, ModuleItem, NamedExport, Stmt,
+};
+use swc_core::ecma::utils::find_pat_ids;
+use swc_core::ecma::visit::{Visit, VisitMut, VisitMutWith, VisitWith};
+use tracing::debug;
+
+use crate::compiler::Compiler;
+use crate::module::{ModuleAst, ModuleId, ModuleSystem, ResolveType};
+use crate::module_graph::ModuleGraph;
+
+// the names are short identifiers, which are valid property names in es5
+const FIRST_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_$";
+const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_$0123456789";
+const KEYWORDS: &[&str] = &[
+    "do", "if", "in", "for", "let", "new", "try", "var", "case", "else", "enum", "eval", "null",
+    "this", "true", "void", "with",
+];
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MangleExportsStats {
+    pub modules: usize,
+    pub exports: usize,
+    // estimated by the references of the mangled names before minification
+    pub saved_bytes: usize,
+}
+
+impl Compiler {
+    /// Rename the exports of the internal es modules to the short names, which
+    /// are the properties of `exports` read by the importers. The names only
+    /// depend on the export names of the module, so they are stable across
+    /// builds if the module is unchanged. The exports of the entries, the
+    /// externals, the modules loaded by `require()`, `import()` or `export *`,
+    /// and the modules whose namespace objects are used as values, e.g.
+    /// `ns[key]`, are kept since their names can be observed.
+    pub fn mangle_exports(&self) -> Result<()> {
+        crate::mako_profile_function!();
+
+        let mut module_graph = self.context.module_graph.write().unwrap();
+        let mangled = mangled_names(&module_graph);
+        let module_ids = module_graph
+            .modules()
+            .into_iter()
+            .filter(|module| {
+                module
+                    .info
+                    .as_ref()
+                    .is_some_and(|info| matches!(info.ast, ModuleAst::Script(_)))
+            })
+            .map(|module| module.id.clone())
+            .collect::<Vec<_>>();
+
+        let mut stats = MangleExportsStats {
+            modules: mangled.len(),
+            exports: mangled
+                .values()
+                .map(|names| names.iter().filter(|(name, new)| name != new).count())
+                .sum(),
+            saved_bytes: 0,
+        };
+        for module_id in module_ids {
+            let sources = module_graph
+                .get_dependencies(&module_id)
+                .into_iter()
+                .filter_map(|(id, dep)| {
+                    mangled
+                        .get(id)
+                        .map(|names| (dep.source.clone(), names.clone()))
+                })
+                .collect::<HashMap<_, _>>();
+            let own = mangled.get(&module_id);
+            if own.is_none() && sources.is_empty() {
+                continue;
+            }
+            let module = module_graph.get_module_mut(&module_id).unwrap();
+            let ast = module.info.as_mut().unwrap().ast.as_script_ast_mut();
+            let mut mangler = ExportsMangler {
+                own,
+                sources: &sources,
+                namespaces: HashMap::new(),
+                imported: HashMap::new(),
+                saved_bytes: 0,
+            };
+            ast.visit_mut_with(&mut mangler);
+            let mut references = References {
+                imported: &mangler.imported,
+                saved_bytes: 0,
+            };
+            ast.visit_with(&mut references);
+            stats.saved_bytes += mangler.saved_bytes + references.saved_bytes;
+        }
+        debug!("mangle exports: {:?}", stats);
+        self.context.stats_info.set_mangle_exports(stats);
+        Ok(())
+    }
+}
+
+// module id -> export name -> mangled name, of the modules whose exports can
+// be mangled
+fn mangled_names(module_graph: &ModuleGraph) -> HashMap<ModuleId, HashMap<String, String>> {
+    let entries = module_graph
+        .get_entry_modules()
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let analyses = module_graph
+        .modules()
+        .into_iter()
+        .filter_map(|module| {
+            let info = module.info.as_ref()?;
+            let ModuleAst::Script(ast) = &info.ast else {
+                return None;
+            };
+            let mut analysis = ExportsAnalysis::default();
+            analysis.analyze(&ast.ast);
+            Some((module.id.clone(), analysis))
+        })
+        .collect::<HashMap<_, _>>();
+
+    analyses
+        .iter()
+        .filter_map(|(module_id, analysis)| {
+            let info = module_graph.get_module(module_id)?.info.as_ref()?;
+            if entries.contains(module_id)
+                || info.external.is_some()
+                || info.module_system != ModuleSystem::ESModule
+            {
+                return None;
+            }
+            let exports = analysis.exports.as_ref().filter(|e| !e.is_empty())?;
+            let observed =
+                module_graph
+                    .get_dependents(module_id)
+                    .into_iter()
+                    .any(|(importer, dep)| {
+                        !matches!(
+                            dep.resolve_type,
+                            ResolveType::Import(_) | ResolveType::ExportNamed(_)
+                        ) || analyses
+                            .get(importer)
+                            .map_or(true, |a| a.dynamic_namespaces.contains(&dep.source))
+                    });
+            if observed {
+                return None;
+            }
+            Some((module_id.clone(), mangle(exports)))
+        })
+        .collect()
+}
+
+/// Deterministic short names of the export names, the names which are not
+/// longer than the short names are kept.
+fn mangle(exports: &HashSet<String>) -> HashMap<String, String> {
+    let mut names = exports.iter().cloned().collect::<Vec<_>>();
+    names.sort();
+    let mut used = names
+        .iter()
+        .filter(|name| name.len() <= 2)
+        .cloned()
+        .collect::<HashSet<_>>();
+    names
+        .into_iter()
+        .map(|name| {
+            if used.contains(&name) {
+                return (name.clone(), name);
+            }
+            let mangled = (0..)
+                .map(|attempt: usize| short_name(&name, attempt))
+                .find(|short| !used.contains(short) && !KEYWORDS.contains(&short.as_str()))
+                .unwrap();
+            used.insert(mangled.clone());
+            (name, mangled)
+        })
+        .collect()
+}
+
+// 2 characters first, which is 3456 names, 1 more character every 8 attempts
+fn short_name(name: &str, attempt: usize) -> String {
+    let digest = md5::compute(format!("{}:{}", name, attempt));
+    let len = 2 + attempt / 8;
+    digest
+        .iter()
+        .cycle()
+        .take(len)
+        .enumerate()
+        .map(|(i, byte)| {
+            let chars = if i == 0 { FIRST_CHARS } else { CHARS };
+            chars[*byte as usize % chars.len()] as char
+        })
+        .collect()
+}
+
+fn export_name(name: &ModuleExportName) -> Option<String> {
+    match name {
+        ModuleExportName::Ident(ident) => Some(ident.sym.to_string()),
+        ModuleExportName::Str(_) => None,
+    }
+}
+
+fn export_ident(name: &str) -> ModuleExportName {
+    ModuleExportName::Ident(Ident::new_no_ctxt(name.into(), DUMMY_SP))
+}
+
+#[derive(Default)]
+struct ExportsAnalysis {
+    // the export names of the module itself except `default`, none if the
+    // exports can't be mangled, e.g. `export *` or `export { a as "a-b" }`
+    exports: Option<HashSet<String>>,
+    // the sources whose namespace objects are used as values
+    dynamic_namespaces: HashSet<String>,
+    namespaces: HashMap<Id, String>,
+}
+
+impl ExportsAnalysis {
+    fn analyze(&mut self, module: &Module) {
+        let mut exports = Some(HashSet::new());
+        for item in &module.body {
+            let ModuleItem::ModuleDecl(decl) = item else {
+                continue;
+            };
+            match decl {
+                ModuleDecl::Import(import) => {
+                    for specifier in &import.specifiers {
+                        if let ImportSpecifier::Namespace(namespace) = specifier {
+                            self.namespaces
+                                .insert(namespace.local.to_id(), import.src.value.to_string());
+                        }
+                    }
+                }
+                ModuleDecl::ExportDecl(export) => {
+                    if let Some(exports) = &mut exports {
+                        exports.extend(decl_names(&export.decl));
+                    }
+                }
+                ModuleDecl::ExportNamed(export) => {
+                    for specifier in &export.specifiers {
+                        let name = match specifier {
+                            ExportSpecifier::Named(named) => {
+                                export_name(named.exported.as_ref().unwrap_or(&named.orig))
+                            }
+                            ExportSpecifier::Namespace(namespace) => {
+                                if let Some(src) = &export.src {
+                                    self.dynamic_namespaces.insert(src.value.to_string());
+                                }
+                                export_name(&namespace.name)
+                            }
+                            ExportSpecifier::Default(_) => None,
+                        };
+                        match (name, &mut exports) {
+                            (Some(name), Some(exports)) => {
+                                if name != "default" {
+                                    exports.insert(name);
+                                }
+                            }
+                            (None, _) => exports = None,
+                            _ => {}
+                        }
+                    }
+                }
+                ModuleDecl::ExportAll(_) => exports = None,
+                _ => {}
+            }
+        }
+        self.exports = exports;
+        module.visit_with(self);
+    }
+}
+
+impl Visit for ExportsAnalysis {
+    fn visit_import_decl(&mut self, _: &ImportDecl) {}
+
+    fn visit_member_expr(&mut self, n: &MemberExpr) {
+        if let Expr::Ident(obj) = &*n.obj {
+            if self.namespaces.contains_key(&obj.to_id()) && static_prop(&n.prop).is_some() {
+                return;
+            }
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_ident(&mut self, n: &Ident) {
+        if let Some(src) = self.namespaces.get(&n.to_id()) {
+            self.dynamic_namespaces.insert(src.clone());
+        }
+    }
+}
+
+// e.g. `ns.a` and `ns["a"]`
+fn static_prop(prop: &MemberProp) -> Option<String> {
+    match prop {
+        MemberProp::Ident(ident) => Some(ident.sym.to_string()),
+        MemberProp::Computed(computed) => match &*computed.expr {
+            Expr::Lit(Lit::Str(str)) => Some(str.value.to_string()),
+            _ => None,
+        },
+        MemberProp::PrivateName(_) => None,
+    }
+}
+
+fn decl_names(decl: &Decl) -> Vec<String> {
+    decl_ids(decl)
+        .into_iter()
+        .map(|ident| ident.sym.to_string())
+        .collect()
+}
+
+fn decl_ids(decl: &Decl) -> Vec<Ident> {
+    match decl {
+        Decl::Class(class) => vec![class.ident.clone()],
+        Decl::Fn(func) => vec![func.ident.clone()],
+        Decl::Var(var) => find_pat_ids(&var.decls),
+        _ => vec![],
+    }
+}
+
+struct ExportsMangler<'a> {
+    // the mangled names of the module itself
+    own: Option<&'a HashMap<String, String>>,
+    // source -> the mangled names of the imported module
+    sources: &'a HashMap<String, HashMap<String, String>>,
+    namespaces: HashMap<Id, &'a HashMap<String, String>>,
+    // the imported bindings whose names are mangled -> the saved bytes of
+    // each reference, i.e. `_m.name` in commonjs
+    imported: HashMap<Id, usize>,
+    saved_bytes: usize,
+}
+
+impl ExportsMangler<'_> {
+    fn own_name(&mut self, name: &str) -> String {
+        let mangled = self
+            .own
+            .and_then(|own| own.get(name))
+            .cloned()
+            .unwrap_or_else(|| name.to_string());
+        self.saved_bytes += name.len().saturating_sub(mangled.len());
+        mangled
+    }
+}
+
+impl VisitMut for ExportsMangler<'_> {
+    fn visit_mut_module_items(&mut self, items: &mut Vec<ModuleItem>) {
+        let mut body = Vec::with_capacity(items.len());
+        for mut item in std::mem::take(items) {
+            // export const a = 1 -> const a = 1; export { a as b }
+            if let (ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)), Some(own)) =
+                (&item, self.own)
+            {
+                let ids = decl_ids(&export.decl);
+                if ids
+                    .iter()
+                    .any(|id| own.get(&id.sym.to_string()) != Some(&id.sym.to_string()))
+                {
+                    let ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) = item else {
+                        unreachable!();
+                    };
+                    let specifiers = ids
+                        .into_iter()
+                        .map(|id| {
+                            let name = id.sym.to_string();
+                            let mangled = self.own_name(&name);
+                            ExportSpecifier::Named(ExportNamedSpecifier {
+                                span: DUMMY_SP,
+                                exported: (mangled != name).then(|| export_ident(&mangled)),
+                                orig: ModuleExportName::Ident(id),
+                                is_type_only: false,
+                            })
+                        })
+                        .collect();
+                    body.push(ModuleItem::Stmt(Stmt::Decl(export.decl)));
+                    body.push(ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(
+                        NamedExport {
+                            span: export.span,
+                            specifiers,
+                            src: None,
+                            type_only: false,
+                            with: None,
+                        },
+                    )));
+                    continue;
+                }
+            }
+            item.visit_mut_with(self);
+            body.push(item);
+        }
+        *items = body;
+    }
+
+    fn visit_mut_import_decl(&mut self, n: &mut ImportDecl) {
+        let Some(names) = self.sources.get(&n.src.value.to_string()) else {
+            return;
+        };
+        for specifier in &mut n.specifiers {
+            match specifier {
+                ImportSpecifier::Named(named) => {
+                    let name = named
+                        .imported
+                        .as_ref()
+                        .map_or(Some(named.local.sym.to_string()), export_name);
+                    if let Some(mangled) = name.as_ref().and_then(|name| names.get(name)) {
+                        let name = name.as_ref().unwrap();
+                        if mangled != name {
+                            self.imported.insert(
+                                named.local.to_id(),
+                                name.len().saturating_sub(mangled.len()),
+                            );
+                            named.imported = Some(export_ident(mangled));
+                        }
+                    }
+                }
+                ImportSpecifier::Namespace(namespace) => {
+                    self.namespaces.insert(namespace.local.to_id(), names);
+                }
+                ImportSpecifier::Default(_) => {}
+            }
+        }
+    }
+
+    fn visit_mut_named_export(&mut self, n: &mut NamedExport) {
+        let names = n
+            .src
+            .as_ref()
+            .and_then(|src| self.sources.get(&src.value.to_string()));
+        for specifier in &mut n.specifiers {
+            match specifier {
+                ExportSpecifier::Named(named) => {
+                    let (Some(orig), Some(exported)) = (
+                        export_name(&named.orig),
+                        export_name(named.exported.as_ref().unwrap_or(&named.orig)),
+                    ) else {
+                        continue;
+                    };
+                    // the name of the re-exported module
+                    let orig = match names.and_then(|names| names.get(&orig)) {
+                        Some(mangled) => {
+                            self.saved_bytes += orig.len().saturating_sub(mangled.len());
+                            named.orig = export_ident(mangled);
+                            mangled.clone()
+                        }
+                        None => orig,
+                    };
+                    let exported = self.own_name(&exported);
+                    named.exported = (exported != orig).then(|| export_ident(&exported));
+                }
+                ExportSpecifier::Namespace(namespace) => {
+                    if let Some(name) = export_name(&namespace.name) {
+                        namespace.name = export_ident(&self.own_name(&name));
+                    }
+                }
+                ExportSpecifier::Default(_) => {}
+            }
+        }
+    }
+
+    fn visit_mut_member_expr(&mut self, n: &mut MemberExpr) {
+        if let Expr::Ident(obj) = &*n.obj {
+            if let Some(names) = self.namespaces.get(&obj.to_id()) {
+                if let Some(mangled) = static_prop(&n.prop).and_then(|prop| {
+                    names
+                        .get(&prop)
+                        .filter(|mangled| **mangled != prop)
+                        .map(|mangled| (prop, mangled))
+                }) {
+                    let (prop, mangled) = mangled;
+                    self.saved_bytes += prop.len().saturating_sub(mangled.len());
+                    n.prop = MemberProp::Ident(mangled.as_str().into());
+                }
+                return;
+            }
+        }
+        n.visit_mut_children_with(self);
+    }
+}
+
+// counts the references of the imported bindings whose names are mangled
+struct References<'a> {
+    imported: &'a HashMap<Id, usize>,
+    saved_bytes: usize,
+}
+
+impl Visit for References<'_> {
+    fn visit_import_decl(&mut self, _: &ImportDecl) {}
+
+    fn visit_ident(&mut self, n: &Ident) {
+        if let Some(saved) = self.imported.get(&n.to_id()) {
+            self.saved_bytes += saved;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::compiler::Args;
+    use crate::config::Config;
+
+    #[test]
+    fn test_mangle() {
+        let exports = ["a", "longName", "anotherName", "$x", "value"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<HashSet<_>>();
+        let names = mangle(&exports);
+        assert_eq!(names, mangle(&exports));
+        assert_eq!(names["a"], "a");
+        assert_eq!(names["$x"], "$x");
+        for name in ["longName", "anotherName", "value"] {
+            assert_eq!(names[name].len(), 2);
+        }
+        let unique = names.values().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), exports.len());
+        // the names don't depend on the other exports unless they collide
+        let one = mangle(&["longName".to_string()].into_iter().collect());
+        assert_eq!(one["longName"], names["longName"]);
+    }
+
+    #[test]
+    fn test_mangle_collisions() {
+        let exports = (0..5000)
+            .map(|i| format!("export{}", i))
+            .collect::<HashSet<_>>();
+        let names = mangle(&exports);
+        let unique = names.values().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), exports.len());
+        assert!(names
+            .values()
+            .all(|name| !KEYWORDS.contains(&name.as_str()) && !name.starts_with(char::is_numeric)));
+    }
+
+    fn build(concatenate_modules: bool) -> (Compiler, String, String) {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/mangle-exports");
+        let config = Config::new(
+            &root,
+            None,
+            Some(&format!(
+                r#"{{"optimization":{{"concatenateModules":{}}}}}"#,
+                concatenate_modules
+            )),
+        )
+        .unwrap();
+        let compiler = Compiler::new(config, root.clone(), Args { watch: false }, None).unwrap();
+        compiler.compile().unwrap();
+        let output_path = compiler.context.config.output.path.clone();
+        let js = std::fs::read_to_string(output_path.join("index.js")).unwrap();
+        let output = std::process::Command::new("node")
+            .arg(output_path.join("index.js"))
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        std::fs::remove_dir_all(&output_path).unwrap();
+        (
+            compiler,
+            js,
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        )
+    }
+
+    #[test]
+    fn test_mangle_exports() {
+        let (compiler, js, output) = build(false);
+        assert_eq!(output, "$1.50 6 red legacy");
+        // the importers read the mangled names, the re-export too
+        for name in ["formatCurrency", "parseCurrency", "multiplyNumbers"] {
+            assert!(!js.contains(&format!(".{}", name)), "{} {}", name, js);
+        }
+        // the names of the entry, the namespace with the dynamic key and the
+        // required module are kept
+        for name in ["\"entryExportName\"", "\"primaryColor\"", "\"legacyValue\""] {
+            assert!(js.contains(name), "{} {}", name, js);
+        }
+        let stats = compiler
+            .context
+            .stats_info
+            .mangle_exports
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap();
+        assert_eq!(stats.modules, 3);
+        assert_eq!(stats.exports, 4);
+        assert!(stats.saved_bytes > 0);
+
+        // the concatenated modules are not imported by the names
+        let (_, _, output) = build(true);
+        assert_eq!(output, "$1.50 6 red legacy");
+    }
+}
@@ -3,11 +3,14 @@ use std::sync::Arc;
 use anyhow::Result;
 use swc_core::base::try_with_handler;
 use swc_core::common::errors::HANDLER;
-use swc_core::common::GLOBALS;
+use swc_core::common::{DUMMY_SP, GLOBALS};
 use swc_core::css::ast::Stylesheet;
 use swc_core::css::minifier;
+use swc_core::ecma::ast::{Expr, Ident, IdentName, MemberExpr, MemberProp};
 use swc_core::ecma::minifier::optimize;
-use swc_core::ecma::minifier::option::{ExtraOptions, MinifyOptions};
+use swc_core::ecma::minifier::option::{
+    CompressOptions, ExtraOptions, MangleOptions, MinifyOptions,
+};
 use swc_core::ecma::transforms::base::fixer::{fixer, paren_remover};
 use swc_core::ecma::transforms::base::helpers::{Helpers, HELPERS};
 use swc_core::ecma::transforms::base::resolver;
@@ -39,14 +42,30 @@ pub fn minify_js(ast: &mut JsAst, context: &Arc<Context>) -> Result<()> {
                         ));
                         ast.ast.visit_mut_with(&mut paren_remover(Some(comments)));
 
+                        let keep_names = context
+                            .config
+                            .minify
+                            .as_ref()
+                            .map(|minify| minify.keep_names.clone())
+                            .unwrap_or_default();
+
                         let mut minified = optimize(
                             ast.ast.clone().into(),
                             context.meta.script.cm.clone(),
                             Some(comments),
                             None,
                             &MinifyOptions {
-                                compress: Some(Default::default()),
-                                mangle: Some(Default::default()),
+                                compress: Some(CompressOptions {
+                                    pure_funcs: pure_funcs(context),
+                                    keep_classnames: keep_names.class_names,
+                                    keep_fnames: keep_names.function_names,
+                                    ..Default::default()
+                                }),
+                                mangle: Some(MangleOptions {
+                                    keep_class_names: keep_names.class_names,
+                                    keep_fn_names: keep_names.function_names,
+                                    ..Default::default()
+                                }),
                                 ..Default::default()
                             },
                             &ExtraOptions {
@@ -67,6 +86,33 @@ pub fn minify_js(ast: &mut JsAst, context: &Arc<Context>) -> Result<()> {
     })
 }
 
+// optimization.pureFunctions, e.g. console.log
+// boxed as required by CompressOptions
+#[allow(clippy::vec_box)]
+fn pure_funcs(context: &Arc<Context>) -> Vec<Box<Expr>> {
+    let pure_functions = match &context.config.optimization {
+        Some(optimization) => optimization.pure_functions.as_deref().unwrap_or_default(),
+        None => &[],
+    };
+    pure_functions
+        .iter()
+        .map(|name| {
+            let mut parts = name.split('.');
+            let mut expr: Box<Expr> =
+                Ident::new_no_ctxt(parts.next().unwrap_or_default().into(), DUMMY_SP).into();
+            for part in parts {
+                expr = MemberExpr {
+                    span: DUMMY_SP,
+                    obj: expr,
+                    prop: MemberProp::Ident(IdentName::new(part.into(), DUMMY_SP)),
+                }
+                .into();
+            }
+            expr
+        })
+        .collect()
+}
+
 pub fn minify_css(stylesheet: &mut Stylesheet, context: &Arc<Context>) -> Result<()> {
     crate::mako_profile_function!();
     GLOBALS.set(&context.meta.css.globals, || {
@@ -80,3 +126,99 @@ pub fn minify_css(stylesheet: &mut Stylesheet, context: &Arc<Context>) -> Result
         })
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::minify_js;
+    use crate::ast::js_ast::JsAst;
+    use crate::compiler::Context;
+    use crate::config::{KeepNamesConfig, MinifyConfig, OptimizationConfig};
+
+    fn run(code: &str, pure_functions: Option<Vec<String>>) -> String {
+        let mut context: Context = Default::default();
+        context.config.optimization = Some(OptimizationConfig {
+            skip_modules: None,
+            side_effects: None,
+            concatenate_modules: None,
+            concatenate_css: None,
+            pure_functions,
+            mangle_exports: None,
+        });
+        minify(code, context)
+    }
+
+    fn minify(code: &str, mut context: Context) -> String {
+        context.config.devtool = None;
+        let context = Arc::new(context);
+        let mut ast = JsAst::build("test.js", code, context.clone()).unwrap();
+        minify_js(&mut ast, &context).unwrap();
+        ast.generate(context).unwrap().code
+    }
+
+    #[test]
+    fn test_pure_annotation() {
+        let code = run(
+            r#"
+/*#__PURE__*/ foo();
+/*#__PURE__*/ (0, foo.bar)();
+(/*#__PURE__*/ bar());
+baz();
+"#,
+            None,
+        );
+        assert!(!code.contains("foo"));
+        assert!(!code.contains("bar"));
+        assert!(code.contains("baz()"));
+    }
+
+    #[test]
+    fn test_pure_functions() {
+        let code = run(
+            r#"
+console.log("a");
+invariant(true, "b");
+console.info("c");
+"#,
+            Some(vec!["console.log".to_string(), "invariant".to_string()]),
+        );
+        assert_eq!(code.trim(), r#"console.info("c");"#);
+    }
+
+    #[test]
+    fn test_keep_names() {
+        let code = r#"
+(function () {
+    function foo() {}
+    class Bar {}
+    console.log(foo, Bar);
+})();
+"#;
+        let mangled = minify(code, Default::default());
+        assert!(!mangled.contains("foo"));
+        assert!(!mangled.contains("Bar"));
+
+        let mut context: Context = Default::default();
+        context.config.minify = Some(MinifyConfig {
+            keep_names: KeepNamesConfig {
+                class_names: true,
+                function_names: true,
+            },
+        });
+        let kept = minify(code, context);
+        assert!(kept.contains("function foo"));
+        assert!(kept.contains("class Bar"));
+
+        let mut context: Context = Default::default();
+        context.config.minify = Some(MinifyConfig {
+            keep_names: KeepNamesConfig {
+                class_names: true,
+                function_names: false,
+            },
+        });
+        let kept = minify(code, context);
+        assert!(!kept.contains("foo"));
+        assert!(kept.contains("class Bar"));
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::string::String;
 
 use hashlink::LinkedHashSet;
@@ -9,12 +9,15 @@ use crate::compiler::Compiler;
 use crate::config::{
     AllowChunks, ChunkGroup, ChunkNameSuffixStrategy, CodeSplitting, CodeSplittingAdvancedOptions,
     CodeSplittingGranularOptions, CodeSplittingStrategy, CodeSplittingStrategyOptions,
-    GenericUsizeDefault,
+    GenericUsizeDefault, HashFunction,
 };
 use crate::generate::chunk::{Chunk, ChunkId, ChunkType};
+use crate::generate::chunk_graph::ChunkGraph;
+use crate::generate::chunk_placement::ChunkPlacement;
 use crate::generate::group_chunk::GroupUpdateResult;
 use crate::module::{Module, ModuleId, ModuleInfo};
 use crate::resolve::{ResolvedResource, ResolverResource};
+use crate::utils::hash::ContentHasher;
 use crate::utils::{create_cached_regex, url_safe_base64_encode};
 
 pub struct OptimizeChunksInfo {
@@ -49,6 +52,9 @@ impl Compiler {
             // stage: size
             self.optimize_chunk_size(&mut optimize_chunks_infos);
 
+            // stage: names
+            self.disambiguate_group_names(&mut optimize_chunks_infos);
+
             // stage: apply
             self.apply_optimize_infos(&optimize_chunks_infos);
 
@@ -127,6 +133,12 @@ impl Compiler {
                 _ => None,
             })
             .collect::<Vec<_>>();
+        // the modules reachable from the entries without crossing a dynamic import
+        let entry_modules = chunks
+            .iter()
+            .filter(|chunk| matches!(chunk.chunk_type, ChunkType::Entry(_, _, false)))
+            .flat_map(|chunk| chunk.modules.iter())
+            .collect::<HashSet<_>>();
         let modules_in_chunk = match modules_in_chunk {
             Some(modules_in_chunk) => modules_in_chunk,
             None => chunks.iter().fold(vec![], |mut acc, chunk| {
@@ -168,6 +180,14 @@ impl Compiler {
                     continue;
                 }
 
+                // the chunk of the group is loaded with the entries, the modules
+                // only needed by the async chunks stay out of it
+                if optimize_info.group_options.allow_chunks != AllowChunks::Async
+                    && !entry_modules.contains(module_id)
+                {
+                    continue;
+                }
+
                 // check test regex
                 if let Some(test) = &optimize_info.group_options.test {
                     if !create_cached_regex(test).is_match(&module_id.id) {
@@ -212,10 +232,14 @@ impl Compiler {
                         .keys()
                         .cloned()
                         .collect::<LinkedHashSet<_>>(),
-                    id: ChunkId { id: "".to_string() },
+                    id: ChunkId { id: "".into() },
                     chunk_type: ChunkType::Sync,
                     content: None,
                     source_map: None,
+                    hash_function: self.context.config.output.hash_function,
+                    hash_length: self.context.config.output.hash_length,
+                    depend_on: vec![],
+                    collision_index: None,
                 };
 
                 (
@@ -242,7 +266,7 @@ impl Compiler {
             let mut package_size_map = chunk_modules.iter().fold(
                 IndexMap::<String, (usize, IndexMap<ModuleId, Vec<ChunkId>>)>::new(),
                 |mut size_map, mtc| {
-                    let pkg_name = self.get_package_name(mtc.0).unwrap_or(mtc.0.id.clone());
+                    let pkg_name = self.get_package_name(mtc.0).unwrap_or(mtc.0.id.to_string());
 
                     let module_size = module_graph.get_module(mtc.0).unwrap().get_module_size();
 
@@ -322,8 +346,9 @@ impl Compiler {
             if let Some(name_suffix) = &info.group_options.name_suffix {
                 match name_suffix {
                     ChunkNameSuffixStrategy::PackageName => {
-                        let mut module_to_package_map: HashMap<String, Vec<ModuleId>> =
-                            HashMap::new();
+                        // by names, so the chunks are created in the same order
+                        let mut module_to_package_map: BTreeMap<String, Vec<ModuleId>> =
+                            BTreeMap::new();
                         info.module_to_chunks.keys().for_each(|module_id| {
                             if let Some(package_name) = self.get_package_name(module_id) {
                                 let package_entry =
@@ -370,15 +395,18 @@ impl Compiler {
                             });
                     }
                     ChunkNameSuffixStrategy::DependentsHash => {
-                        let mut module_to_dependents_md5_map: HashMap<String, Vec<ModuleId>> =
-                            HashMap::new();
+                        let mut module_to_dependents_md5_map: BTreeMap<String, Vec<ModuleId>> =
+                            BTreeMap::new();
                         info.module_to_chunks
                             .iter()
                             .for_each(|(module_id, dependents)| {
                                 let mut stable_dependents = dependents.clone();
                                 stable_dependents.sort();
 
-                                let dependents_md5 = md5_chunk_ids(&stable_dependents);
+                                let dependents_md5 = dependents_hash(
+                                    &stable_dependents,
+                                    self.context.config.output.hash_function,
+                                );
 
                                 let package_entry = module_to_dependents_md5_map
                                     .entry(dependents_md5)
@@ -420,13 +448,14 @@ impl Compiler {
     }
 
     fn apply_optimize_infos(&self, optimize_chunks_infos: &Vec<OptimizeChunksInfo>) {
-        let mut edges_map: HashMap<ModuleId, IndexSet<ModuleId>> = HashMap::new();
+        // in the order of the infos, so the edges are added in the same order
+        let mut edges_map: IndexMap<ModuleId, IndexSet<ModuleId>> = IndexMap::new();
         let mut chunk_graph = self.context.chunk_graph.write().unwrap();
 
         for info in optimize_chunks_infos {
             // create new chunk
             let info_chunk_id = ChunkId {
-                id: info.group_options.name.clone(),
+                id: info.group_options.name.as_str().into(),
             };
             let info_chunk_type = if matches!(info.group_options.allow_chunks, AllowChunks::Async) {
                 ChunkType::Sync
@@ -443,6 +472,10 @@ impl Compiler {
                 chunk_type: info_chunk_type,
                 content: None,
                 source_map: None,
+                hash_function: self.context.config.output.hash_function,
+                hash_length: self.context.config.output.hash_length,
+                depend_on: vec![],
+                collision_index: None,
             };
 
             if chunk_graph.has_chunk(&info_chunk_id) {
@@ -453,7 +486,10 @@ impl Compiler {
 
             // remove modules from original chunks and add edge to new chunk
             for (module_id, chunk_ids) in &info.module_to_chunks {
+                let placement = split_placement(&info.group_options, chunk_ids, &chunk_graph);
+                chunk_graph.set_placement(&info_chunk_id, module_id, placement);
                 for chunk_id in chunk_ids {
+                    chunk_graph.remove_placement(chunk_id, module_id);
                     let chunk = chunk_graph.mut_chunk(chunk_id).unwrap();
 
                     chunk.remove_module(module_id);
@@ -478,7 +514,7 @@ impl Compiler {
     }
 
     fn apply_hot_update_optimize_infos(&self, optimize_chunks_infos: &Vec<OptimizeChunksInfo>) {
-        let mut edges = HashMap::new();
+        let mut edges = IndexMap::new();
         let mut chunk_graph = self.context.chunk_graph.write().unwrap();
         for info in optimize_chunks_infos {
             // update group chunk
@@ -486,7 +522,7 @@ impl Compiler {
                 // get chunk
                 let info_chunk = chunk_graph
                     .mut_chunk(&ChunkId {
-                        id: info.group_options.name.clone(),
+                        id: info.group_options.name.as_str().into(),
                     })
                     .unwrap();
                 let info_chunk_id = info_chunk.id.clone();
@@ -496,8 +532,17 @@ impl Compiler {
                     info_chunk.add_module(module_id.clone());
                 }
 
+                let parents = chunk_ids
+                    .iter()
+                    .filter(|c| c.id != info_chunk_id.id)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let placement = split_placement(&info.group_options, &parents, &chunk_graph);
+                chunk_graph.set_placement(&info_chunk_id, module_id, placement);
+
                 // remove modules from original chunks and add edge to new chunk
                 for chunk_id in chunk_ids.iter().filter(|c| c.id != info_chunk_id.id) {
+                    chunk_graph.remove_placement(chunk_id, module_id);
                     let chunk = chunk_graph.mut_chunk(chunk_id).unwrap();
 
                     chunk.remove_module(module_id);
@@ -655,12 +700,35 @@ fn code_splitting_strategy_granular(
     }
 }
 
-fn md5_chunk_ids(chunk_ids: &[ChunkId]) -> String {
-    let mut context = md5::Context::new();
+fn dependents_hash(chunk_ids: &[ChunkId], function: HashFunction) -> String {
+    let mut hasher = ContentHasher::new(function);
     chunk_ids.iter().for_each(|cd| {
-        context.consume(cd.id.as_bytes());
+        hasher.update(cd.id.as_bytes());
     });
-    let digest = context.compute();
-    let hash = url_safe_base64_encode(digest.0);
+    let hash = url_safe_base64_encode(hasher.finish());
     hash[..8].to_string()
 }
+
+/// The module is moved to the chunk of the group from the chunks, by the test
+/// of the group or because it's shared by them.
+fn split_placement(
+    group_options: &ChunkGroup,
+    chunk_ids: &[ChunkId],
+    chunk_graph: &ChunkGraph,
+) -> ChunkPlacement {
+    let mut parents = chunk_ids
+        .iter()
+        .filter_map(|chunk_id| chunk_graph.chunk(chunk_id))
+        .map(|chunk| chunk.name())
+        .collect::<Vec<_>>();
+    parents.sort();
+    let group = group_options.name.clone();
+    match &group_options.test {
+        Some(test) => ChunkPlacement::Rule {
+            group,
+            test: test.clone(),
+            parents,
+        },
+        None => ChunkPlacement::Shared { group, parents },
+    }
+}
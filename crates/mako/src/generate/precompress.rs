@@ -0,0 +1,163 @@
+use std::hash::Hasher;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rayon::prelude::*;
+use tracing::debug;
+use twox_hash::XxHash64;
+
+use crate::compiler::Context;
+use crate::config::{PrecompressAlgorithm, PrecompressConfig};
+
+const COMPRESSIBLE_EXTENSIONS: [&str; 5] = ["js", "css", "html", "svg", "json"];
+
+/// The compressed siblings of an emitted file, empty if it's not compressible
+/// or smaller than the threshold.
+pub(crate) fn precompressed_variants(
+    file: &str,
+    size: u64,
+    config: &PrecompressConfig,
+) -> Vec<String> {
+    let compressible = Path::new(file)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| COMPRESSIBLE_EXTENSIONS.contains(&ext));
+    if !compressible || size < config.threshold as u64 {
+        return vec![];
+    }
+    config
+        .algorithms
+        .iter()
+        .map(|algorithm| format!("{}.{}", file, algorithm.extension()))
+        .collect()
+}
+
+/// Write the compressed siblings of the emitted files, the files unchanged
+/// since they were last compressed are skipped.
+pub(crate) fn precompress_files(files: &[String], context: &Arc<Context>) -> Result<()> {
+    crate::mako_profile_function!();
+
+    let Some(config) = &context.config.output.precompress else {
+        return Ok(());
+    };
+    files.par_iter().try_for_each(|file| -> Result<()> {
+        let path = context.config.output.path.join(file);
+        let content = context.file_handles.run(|| context.output_fs.read(&path))?;
+        let variants = precompressed_variants(file, content.len() as u64, config);
+        if variants.is_empty() {
+            return Ok(());
+        }
+        let mut hasher: XxHash64 = Default::default();
+        hasher.write(&content);
+        let hash = hasher.finish();
+        if context.precompressed.lock().unwrap().get(file) == Some(&hash) {
+            return Ok(());
+        }
+        debug!("precompress {}", file);
+        for (algorithm, variant) in config.algorithms.iter().zip(variants) {
+            let compressed = compress(&content, algorithm, config)?;
+            let to = context.config.output.path.join(variant);
+            context
+                .file_handles
+                .run(|| context.output_fs.write(&to, &compressed))?;
+        }
+        context
+            .precompressed
+            .lock()
+            .unwrap()
+            .insert(file.clone(), hash);
+        Ok(())
+    })
+}
+
+fn compress(
+    content: &[u8],
+    algorithm: &PrecompressAlgorithm,
+    config: &PrecompressConfig,
+) -> Result<Vec<u8>> {
+    match algorithm {
+        PrecompressAlgorithm::Gzip => {
+            let mut encoder = GzEncoder::new(vec![], Compression::new(config.gzip_level));
+            encoder.write_all(content)?;
+            Ok(encoder.finish()?)
+        }
+        PrecompressAlgorithm::Brotli => {
+            let params = brotli::enc::BrotliEncoderParams {
+                quality: config.brotli_quality as i32,
+                ..Default::default()
+            };
+            let mut compressed = vec![];
+            brotli::BrotliCompress(&mut &content[..], &mut compressed, &params)?;
+            Ok(compressed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::{precompress_files, precompressed_variants};
+    use crate::config::{PrecompressAlgorithm, PrecompressConfig};
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_precompressed_variants() {
+        let config: PrecompressConfig =
+            vec![PrecompressAlgorithm::Gzip, PrecompressAlgorithm::Brotli].into();
+        assert_eq!(
+            precompressed_variants("index.js", 2048, &config),
+            vec!["index.js.gz", "index.js.br"]
+        );
+        assert!(precompressed_variants("index.js", 100, &config).is_empty());
+        assert!(precompressed_variants("index.js.map", 2048, &config).is_empty());
+        assert!(precompressed_variants("logo.png", 2048, &config).is_empty());
+    }
+
+    #[test]
+    fn test_precompress() {
+        let compiler = setup_compiler("test/build/precompress", false);
+        compiler.compile().unwrap();
+        let context = &compiler.context;
+        let output = &context.config.output.path;
+        let read = |file: &str| context.output_fs.read(&output.join(file)).unwrap();
+
+        let code = read("index.js");
+        let mut gunzipped = vec![];
+        flate2::read::GzDecoder::new(&read("index.js.gz")[..])
+            .read_to_end(&mut gunzipped)
+            .unwrap();
+        assert_eq!(gunzipped, code);
+        let mut decompressed = vec![];
+        brotli::Decompressor::new(&read("index.js.br")[..], 4096)
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, code);
+        // below the threshold
+        assert!(!context.output_fs.exists(&output.join("index.css.gz")));
+        assert!(!context.output_fs.exists(&output.join("index.js.map.gz")));
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&read("asset-manifest.json")).unwrap();
+        assert_eq!(manifest["index.js.gz"], "index.js.gz");
+        assert_eq!(manifest["index.js.br"], "index.js.br");
+
+        // only the changed files are compressed again
+        let gz = output.join("index.js.gz");
+        context.output_fs.remove(&gz).unwrap();
+        precompress_files(&["index.js".to_string()], context).unwrap();
+        assert!(!context.output_fs.exists(&gz));
+        let mut changed = code.clone();
+        changed.extend_from_slice(b"\nconsole.log(1);");
+        context
+            .output_fs
+            .write(&output.join("index.js"), &changed)
+            .unwrap();
+        precompress_files(&["index.js".to_string()], context).unwrap();
+        assert!(context.output_fs.exists(&gz));
+    }
+}
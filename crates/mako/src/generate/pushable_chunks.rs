@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::compiler::Context;
+use crate::generate::chunk::{ChunkId, ChunkType};
+use crate::generate::csp_report::{sha256, InlineCode, InlineCodeKind};
+use crate::generate::generate_chunks::{ChunkFile, ChunkFileType};
+use crate::generate::inline_chunks::inline_script;
+
+/// The wrapper of an async js chunk which the server can embed in the html,
+/// e.g. `<script>{script}</script>` in the ssr stream, so that the chunk is
+/// registered before the app asks for it. The script pushes the chunk to
+/// `output.chunkLoadingGlobal`, so it can be embedded before or after the
+/// runtime is loaded.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PushableChunk<'a> {
+    chunk_id: &'a str,
+    script: String,
+}
+
+pub(crate) struct PushableChunkFile {
+    pub name: String,
+    pub content: Vec<u8>,
+    // of the chunk, so the unchanged files are not written again in watch mode
+    pub raw_hash: u64,
+}
+
+/// The `.push.json` files of the async js chunks with
+/// `output.pushableChunks`.
+pub(crate) fn pushable_chunk_files(
+    chunk_files: &[ChunkFile],
+    context: &Arc<Context>,
+) -> Vec<PushableChunkFile> {
+    if !context.config.output.pushable_chunks {
+        return vec![];
+    }
+    let chunk_graph = context.chunk_graph.read().unwrap();
+    chunk_files
+        .iter()
+        .filter(|file| matches!(file.file_type, ChunkFileType::JS))
+        .filter(|file| {
+            chunk_graph
+                .chunk(&ChunkId {
+                    id: file.chunk_id.as_str().into(),
+                })
+                .is_some_and(|chunk| matches!(chunk.chunk_type, ChunkType::Async | ChunkType::Sync))
+        })
+        .map(|file| {
+            let chunk = PushableChunk {
+                chunk_id: &file.chunk_id,
+                script: inline_script(&String::from_utf8_lossy(&file.content)),
+            };
+            let name = pushable_name(&file.disk_name());
+            if context.config.csp_report.is_some() {
+                context.stats_info.inline_code.report(InlineCode {
+                    origin: "pushableChunks".to_string(),
+                    kind: InlineCodeKind::Script,
+                    location: name.clone(),
+                    directive: "script-src".to_string(),
+                    hash: Some(sha256(&chunk.script)),
+                    nonce: None,
+                });
+            }
+            PushableChunkFile {
+                name,
+                content: serde_json::to_vec(&chunk).unwrap(),
+                raw_hash: file.raw_hash,
+            }
+        })
+        .collect()
+}
+
+// a.js -> a.push.json
+fn pushable_name(disk_name: &str) -> String {
+    format!(
+        "{}.push.json",
+        disk_name.strip_suffix(".js").unwrap_or(disk_name)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_inline_script() {
+        assert_eq!(
+            inline_script(r#"var a = "</script><!--", b = "</SCRIPT>";"#),
+            r#"var a = "<\/script><\!--", b = "<\/SCRIPT>";"#
+        );
+        assert_eq!(pushable_name("lazy_ts-async.js"), "lazy_ts-async.push.json");
+    }
+
+    #[test]
+    fn test_pushable_chunks() {
+        let compiler = setup_compiler("test/build/pushable-chunks", false);
+        compiler.compile().unwrap();
+        let context = &compiler.context;
+        let output = &context.config.output.path;
+        let read = |file: &str| context.output_fs.read(&output.join(file));
+
+        let pushable: serde_json::Value =
+            serde_json::from_slice(&read("lazy_ts-async.push.json").unwrap()).unwrap();
+        assert_eq!(pushable["chunkId"], "lazy.ts");
+        let script = pushable["script"].as_str().unwrap();
+        assert!(script.contains(&context.config.output.chunk_loading_global));
+        assert!(script.contains(".push(["));
+        assert!(script.contains(r"<\/script>"));
+        assert!(!script.to_lowercase().contains("</script"));
+        // the entry chunk is not pushable
+        assert!(read("index.push.json").is_err());
+    }
+}
@@ -1,17 +1,676 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
 use sailfish::TemplateOnce;
+use swc_core::base::try_with_handler;
+use swc_core::common::errors::HANDLER;
+use swc_core::common::{Mark, GLOBALS};
+use swc_core::ecma::ast::{CallExpr, Callee, Expr, Lit, MemberExpr, MemberProp, Module};
+use swc_core::ecma::transforms::base::fixer::fixer;
+use swc_core::ecma::transforms::base::helpers::{Helpers, HELPERS};
+use swc_core::ecma::transforms::base::hygiene::hygiene;
+use swc_core::ecma::transforms::base::resolver;
+use swc_core::ecma::transforms::compat::{
+    es2015, es2016, es2017, es2018, es2019, es2020, es2021, es2022,
+};
+use swc_core::ecma::visit::{Fold, Visit, VisitMutWith, VisitWith};
+use swc_node_comments::SwcComments;
+
+use crate::ast::js_ast::JsAst;
+use crate::compiler::Context;
+use crate::config::RuntimeTarget;
+use crate::generate::chunk_pot::util::render_module_js;
+use crate::module::ModuleId;
+use crate::share::helpers::SWC_HELPERS;
 
 #[derive(TemplateOnce)]
 #[template(path = "app_runtime.stpl")]
 pub struct AppRuntimeTemplate {
     pub has_dynamic_chunks: bool,
     pub has_hmr: bool,
+    // the fragments of the runtime, see `RuntimeFeatures`
+    pub has_chunk_loader: bool,
+    pub has_css_loader: bool,
+    pub has_dynamic_import: bool,
     pub umd: Option<String>,
     pub umd_export: Vec<String>,
     pub cjs: bool,
     pub pkg_name: Option<String>,
     pub chunk_loading_global: String,
+    pub unique_name: String,
+    pub chunk_loading_timeout: u64,
     pub is_browser: bool,
+    pub is_webworker: bool,
     pub concatenate_enabled: bool,
     pub cross_origin_loading: Option<String>,
+    pub chunk_loading: String,
     pub global_module_registry: bool,
+    pub is_dev: bool,
+    pub devtools_timing: bool,
+    pub pre_bundled: bool,
+    pub unload_chunk_css: bool,
+    // json of the sync dependency chunks of the async chunks
+    pub chunk_dependencies: String,
+}
+
+/// The parts of the runtime the generated modules use, recorded during the
+/// codegen of them, so the runtime only includes the fragments of them, e.g.
+/// no chunk loader without `import()` or the chunks split from the entry. In
+/// watch mode the runtime includes all of them, since it's not updated with
+/// the hot updates.
+#[derive(Default)]
+pub struct RuntimeFeatures {
+    // the swc helpers the modules require
+    helpers: Mutex<HashSet<String>>,
+    // the members of `__mako_require__` the modules use, e.g. `ensure`
+    members: Mutex<HashSet<String>>,
+}
+
+impl RuntimeFeatures {
+    pub fn clear(&self) {
+        self.helpers.lock().unwrap().clear();
+        self.members.lock().unwrap().clear();
+    }
+
+    /// Record the usages of the transformed module, whose requires are
+    /// replaced by `__mako_require__` with the module ids.
+    pub fn record(&self, ast: &Module, context: &Arc<Context>) {
+        let helper_ids = SWC_HELPERS
+            .iter()
+            .map(|helper| {
+                (
+                    ModuleId::from(helper.to_string()).generate(context),
+                    *helper,
+                )
+            })
+            .collect();
+        let mut visitor = RuntimeUsageVisitor {
+            helper_ids,
+            helpers: HashSet::new(),
+            members: HashSet::new(),
+        };
+        ast.visit_with(&mut visitor);
+        self.helpers.lock().unwrap().extend(visitor.helpers);
+        self.members.lock().unwrap().extend(visitor.members);
+    }
+
+    pub fn uses_helper(&self, helper: &str, context: &Context) -> bool {
+        context.args.watch || self.helpers.lock().unwrap().contains(helper)
+    }
+
+    pub fn uses_member(&self, member: &str, context: &Context) -> bool {
+        context.args.watch || self.members.lock().unwrap().contains(member)
+    }
+}
+
+struct RuntimeUsageVisitor {
+    // generated module id -> helper
+    helper_ids: Vec<(String, &'static str)>,
+    helpers: HashSet<String>,
+    members: HashSet<String>,
+}
+
+impl Visit for RuntimeUsageVisitor {
+    fn visit_call_expr(&mut self, n: &CallExpr) {
+        if let Callee::Expr(box Expr::Ident(callee)) = &n.callee
+            && callee.sym == *"__mako_require__"
+            && let Some(box Expr::Lit(Lit::Str(source))) = n.args.first().map(|arg| &arg.expr)
+            && let Some((_, helper)) = self.helper_ids.iter().find(|(id, _)| *id == *source.value)
+        {
+            self.helpers.insert(helper.to_string());
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_member_expr(&mut self, n: &MemberExpr) {
+        if let (Expr::Ident(obj), MemberProp::Ident(prop)) = (&*n.obj, &n.prop)
+            && obj.sym == *"__mako_require__"
+        {
+            self.members.insert(prop.sym.to_string());
+        }
+        n.visit_children_with(self);
+    }
+}
+
+/// The compat passes of the syntax newer than the target, newest first.
+pub(crate) fn runtime_target_folders(
+    target: RuntimeTarget,
+    unresolved_mark: Mark,
+    comments: SwcComments,
+) -> Vec<Box<dyn Fold>> {
+    let mut folders: Vec<Box<dyn Fold>> = vec![
+        Box::new(es2022::es2022(
+            Some(comments.clone()),
+            Default::default(),
+            unresolved_mark,
+        )),
+        Box::new(es2021::es2021()),
+    ];
+    if target == RuntimeTarget::Es2020 {
+        return folders;
+    }
+    folders.push(Box::new(es2020::es2020(
+        Default::default(),
+        unresolved_mark,
+    )));
+    folders.push(Box::new(es2019::es2019()));
+    folders.push(Box::new(es2018::es2018(Default::default())));
+    folders.push(Box::new(es2017::es2017(
+        Default::default(),
+        Some(comments.clone()),
+        unresolved_mark,
+    )));
+    folders.push(Box::new(es2016::es2016()));
+    if target == RuntimeTarget::Es2015 {
+        return folders;
+    }
+    folders.push(Box::new(es2015::es2015(
+        unresolved_mark,
+        Some(comments),
+        Default::default(),
+    )));
+    folders
+}
+
+/// Compile the code generated by mako, e.g. the runtime, down to
+/// `output.runtimeTarget`. The helpers are inlined since the code runs before
+/// the modules are registered.
+pub(crate) fn downlevel_runtime_code(code: String, context: &Arc<Context>) -> Result<String> {
+    crate::mako_profile_function!();
+
+    let Some(target) = context.config.output.runtime_target else {
+        return Ok(code);
+    };
+    let mut ast = JsAst::build("_mako_internal/runtime.js", &code, context.clone())?;
+    let comments = context
+        .meta
+        .script
+        .origin_comments
+        .read()
+        .unwrap()
+        .get_swc_comments()
+        .clone();
+    GLOBALS.set(&context.meta.script.globals, || {
+        try_with_handler(
+            context.meta.script.cm.clone(),
+            Default::default(),
+            |handler| {
+                HELPERS.set(&Helpers::new(false), || {
+                    HANDLER.set(handler, || {
+                        let unresolved_mark = ast.unresolved_mark;
+                        ast.ast.visit_mut_with(&mut resolver(
+                            unresolved_mark,
+                            ast.top_level_mark,
+                            false,
+                        ));
+                        let mut folders = runtime_target_folders(target, unresolved_mark, comments);
+                        ast.transform(&mut vec![], &mut folders, true, context.clone())?;
+                        ast.ast.visit_mut_with(&mut hygiene());
+                        ast.ast.visit_mut_with(&mut fixer(None));
+                        Ok(())
+                    })
+                })
+            },
+        )
+    })?;
+    let (buf, _) = render_module_js(&ast.ast, context)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_core::ecma::ast::{
+        ArrowExpr, AssignExpr, AssignOp, BinExpr, BinaryOp, CatchClause, Class, ClassProp,
+        Function, ObjectLit, OptChainExpr, Pat, PropOrSpread, SpreadElement, Tpl, VarDecl,
+        VarDeclKind,
+    };
+    use swc_core::ecma::visit::{Visit, VisitWith};
+
+    use super::downlevel_runtime_code;
+    use crate::ast::js_ast::JsAst;
+    use crate::compiler::{Args, Compiler};
+    use crate::config::{Config, Mode, Platform, RuntimeTarget};
+    use crate::generate::chunk_pot::util::runtime_code;
+    use crate::testing::{compile_fixture, write_files, TempDir};
+
+    // syntax of every level above es5 which the compat passes handle
+    const MODERN_CODE: &str = r#"
+const load = async (id, options = {}) => {
+    let { retries, ...rest } = options;
+    const url = `${id}.js`;
+    const files = [...rest.files, url];
+    retries ??= 3;
+    return files?.length ** 2 ?? retries;
+};
+class Chunk { loaded = false; }
+try { load(1); } catch { new Chunk(); }
+"#;
+
+    #[derive(Default)]
+    struct SyntaxLinter {
+        es2015: Vec<&'static str>,
+        es2016_to_es2020: Vec<&'static str>,
+        es2021_and_later: Vec<&'static str>,
+    }
+
+    impl Visit for SyntaxLinter {
+        fn visit_arrow_expr(&mut self, n: &ArrowExpr) {
+            self.es2015.push("arrow function");
+            n.visit_children_with(self);
+        }
+        fn visit_var_decl(&mut self, n: &VarDecl) {
+            if n.kind != VarDeclKind::Var {
+                self.es2015.push("let or const");
+            }
+            n.visit_children_with(self);
+        }
+        fn visit_class(&mut self, n: &Class) {
+            self.es2015.push("class");
+            n.visit_children_with(self);
+        }
+        fn visit_tpl(&mut self, n: &Tpl) {
+            self.es2015.push("template literal");
+            n.visit_children_with(self);
+        }
+        fn visit_spread_element(&mut self, n: &SpreadElement) {
+            self.es2015.push("spread");
+            n.visit_children_with(self);
+        }
+        fn visit_pat(&mut self, n: &Pat) {
+            match n {
+                Pat::Object(_) | Pat::Array(_) => self.es2015.push("destructuring"),
+                Pat::Assign(_) => self.es2015.push("default parameter"),
+                Pat::Rest(_) => self.es2015.push("rest"),
+                _ => {}
+            }
+            n.visit_children_with(self);
+        }
+        fn visit_function(&mut self, n: &Function) {
+            if n.is_generator {
+                self.es2015.push("generator");
+            }
+            if n.is_async {
+                self.es2016_to_es2020.push("async function");
+            }
+            n.visit_children_with(self);
+        }
+        fn visit_bin_expr(&mut self, n: &BinExpr) {
+            match n.op {
+                BinaryOp::Exp => self.es2016_to_es2020.push("exponentiation"),
+                BinaryOp::NullishCoalescing => self.es2016_to_es2020.push("nullish coalescing"),
+                _ => {}
+            }
+            n.visit_children_with(self);
+        }
+        fn visit_object_lit(&mut self, n: &ObjectLit) {
+            if n.props
+                .iter()
+                .any(|prop| matches!(prop, PropOrSpread::Spread(_)))
+            {
+                self.es2016_to_es2020.push("object spread");
+            }
+            n.visit_children_with(self);
+        }
+        fn visit_opt_chain_expr(&mut self, n: &OptChainExpr) {
+            self.es2016_to_es2020.push("optional chaining");
+            n.visit_children_with(self);
+        }
+        fn visit_catch_clause(&mut self, n: &CatchClause) {
+            if n.param.is_none() {
+                self.es2016_to_es2020.push("optional catch binding");
+            }
+            n.visit_children_with(self);
+        }
+        fn visit_assign_expr(&mut self, n: &AssignExpr) {
+            if matches!(
+                n.op,
+                AssignOp::AndAssign | AssignOp::OrAssign | AssignOp::NullishAssign
+            ) {
+                self.es2021_and_later.push("logical assignment");
+            }
+            n.visit_children_with(self);
+        }
+        fn visit_class_prop(&mut self, n: &ClassProp) {
+            self.es2021_and_later.push("class field");
+            n.visit_children_with(self);
+        }
+    }
+
+    fn lint(code: &str, compiler: &Compiler) -> SyntaxLinter {
+        let ast = JsAst::build("runtime.js", code, compiler.context.clone()).unwrap();
+        let mut linter = SyntaxLinter::default();
+        ast.ast.visit_with(&mut linter);
+        linter
+    }
+
+    fn setup_compiler(runtime_target: Option<RuntimeTarget>) -> Compiler {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/output-fs");
+        let mut config = Config::new(&root, None, None).unwrap();
+        // the node runtime loads chunks with arrow functions
+        config.platform = Platform::Node;
+        config.output.runtime_target = runtime_target;
+        Compiler::new(config, root, Args { watch: true }, None).unwrap()
+    }
+
+    #[test]
+    fn test_runtime_target() {
+        let compiler = setup_compiler(None);
+        let linter = lint(&runtime_code(&compiler.context).unwrap(), &compiler);
+        assert!(linter.es2015.contains(&"arrow function"));
+
+        for target in [
+            RuntimeTarget::Es5,
+            RuntimeTarget::Es2015,
+            RuntimeTarget::Es2020,
+        ] {
+            let compiler = setup_compiler(Some(target));
+            let context = &compiler.context;
+            let runtime = runtime_code(context).unwrap();
+            let code = downlevel_runtime_code(MODERN_CODE.to_string(), context).unwrap();
+            for code in [runtime, code] {
+                let linter = lint(&code, &compiler);
+                assert!(linter.es2021_and_later.is_empty(), "{:?}", target);
+                if target != RuntimeTarget::Es2020 {
+                    assert!(linter.es2016_to_es2020.is_empty(), "{:?}", target);
+                }
+                if target == RuntimeTarget::Es5 {
+                    assert!(
+                        linter.es2015.is_empty(),
+                        "{:?}: {:?}\n{}",
+                        target,
+                        linter.es2015,
+                        code
+                    );
+                }
+            }
+        }
+        let compiler = setup_compiler(Some(RuntimeTarget::Es2020));
+        let code = downlevel_runtime_code(MODERN_CODE.to_string(), &compiler.context).unwrap();
+        assert!(!lint(&code, &compiler).es2016_to_es2020.is_empty());
+    }
+
+    #[test]
+    fn test_dev_module_registry() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/dev-registry");
+        let config = Config::new(&root, None, None).unwrap();
+        let output_path = config.output.path.clone();
+        let compiler = Compiler::new(config, root.clone(), Args { watch: false }, None).unwrap();
+        compiler.compile().unwrap();
+        let output = std::process::Command::new("node")
+            .arg(output_path.join("index.js"))
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&output_path).unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        // the cached exports are dropped by invalidate, so the module is
+        // executed again
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            r#"{"cached":true,"invalidated":true,"executions":[1,2]}"#
+        );
+
+        let mut config = Config::new(&root, None, None).unwrap();
+        config.mode = Mode::Production;
+        let compiler = Compiler::new(config, root, Args { watch: false }, None).unwrap();
+        let runtime = runtime_code(&compiler.context).unwrap();
+        assert!(!runtime.contains("requireModule.c = modulesRegistry"));
+    }
+
+    #[test]
+    fn test_devtools_timing() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/devtools-timing");
+        let config = Config::new(&root, None, None).unwrap();
+        let output_path = config.output.path.clone();
+        let compiler = Compiler::new(config, root.clone(), Args { watch: false }, None).unwrap();
+        compiler.compile().unwrap();
+        let output = std::process::Command::new("node")
+            .arg(output_path.join("index.js"))
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&output_path).unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        // the factories keep this and the errors
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            r#"{"self":true,"error":"thrown","slowest":true,"total":true,"chunks":["index.js"]}"#
+        );
+
+        let mut config = Config::new(&root, None, None).unwrap();
+        config.mode = Mode::Production;
+        let compiler = Compiler::new(config, root, Args { watch: false }, None).unwrap();
+        let runtime = runtime_code(&compiler.context).unwrap();
+        assert!(!runtime.contains("__MAKO_TIMINGS__"));
+    }
+
+    #[test]
+    fn test_chunk_loading() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/chunk-loading");
+        let compile = |chunk_loading: &str| {
+            let config = Config::new(
+                &root,
+                None,
+                Some(&format!(
+                    r#"{{"output":{{"chunkLoading":"{}"}}}}"#,
+                    chunk_loading
+                )),
+            )
+            .unwrap();
+            let compiler =
+                Compiler::new(config, root.clone(), Args { watch: false }, None).unwrap();
+            compiler.compile().unwrap();
+            compiler
+        };
+        // run.cjs runs the bundle without the script elements
+        let run = || {
+            let output = std::process::Command::new("node")
+                .arg(root.join("run.cjs"))
+                .output()
+                .unwrap();
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+
+        for chunk_loading in ["fetch-eval", "import"] {
+            let compiler = compile(chunk_loading);
+            let runtime = runtime_code(&compiler.context).unwrap();
+            assert_eq!(runtime.contains("fetch(url"), chunk_loading == "fetch-eval");
+            assert!(!runtime.contains("document.createElement('script')"));
+            assert_eq!(run(), "lazy", "{}", chunk_loading);
+            // the failure is reported as a ChunkLoadError like the scripts
+            let output_path = &compiler.context.config.output.path;
+            std::fs::remove_file(output_path.join("lazy_js-async.js")).unwrap();
+            assert_eq!(run(), "ChunkLoadError error", "{}", chunk_loading);
+            std::fs::remove_dir_all(output_path).unwrap();
+        }
+
+        let compiler = compile("jsonp");
+        let runtime = runtime_code(&compiler.context).unwrap();
+        assert!(runtime.contains("document.createElement('script')"));
+        assert!(!runtime.contains("fetch(url"));
+        std::fs::remove_dir_all(&compiler.context.config.output.path).unwrap();
+    }
+
+    // compiled in a temp root, the runtime of it is rendered after the codegen
+    fn compile_files(name: &str, files: &[(&str, &str)]) -> Compiler {
+        let root = TempDir::new(name);
+        write_files(&root, files).unwrap();
+        compile_fixture(&root, Some(r#"{"mode": "production"}"#)).unwrap()
+    }
+
+    #[test]
+    fn test_runtime_features() {
+        let esm_only = compile_files(
+            "runtime-features-esm",
+            &[
+                (
+                    "src/index.ts",
+                    r#"import { a } from "./a"; console.log(a);"#,
+                ),
+                ("src/a.ts", "export const a = 1;"),
+            ],
+        );
+        let runtime = runtime_code(&esm_only.context).unwrap();
+        // unminified
+        assert!(runtime.len() < 3 * 1024, "{}\n{}", runtime.len(), runtime);
+        for fragment in [
+            "mako/runtime/ensure chunk",
+            "mako/runtime/ensure load css chunk",
+            "mako/runtime/load script",
+            "mako/runtime/helpers",
+            "requireModule.dr",
+            "requireModule.weak",
+            "_interop_require_wildcard",
+        ] {
+            assert!(!runtime.contains(fragment), "{}", fragment);
+        }
+        // nothing to load, the chunk api resolves
+        assert!(runtime.contains("global.__mako_chunk_load__ = requireModule.ensureChunk"));
+
+        // the split chunks are loaded through the chunk api without `import()`
+        let split = compile_files(
+            "runtime-features-split",
+            &[
+                (
+                    "mako.config.json",
+                    r#"{"entry":{"a":"./src/a.ts","b":"./src/b.ts"},"codeSplitting":{"strategy":"advanced","options":{"groups":[{"name":"shared","allowChunks":"entry","minSize":1,"test":"shared"}]}}}"#,
+                ),
+                (
+                    "src/a.ts",
+                    r#"import { shared } from "./shared"; console.log(shared);"#,
+                ),
+                (
+                    "src/b.ts",
+                    r#"import { shared } from "./shared"; console.log(shared);"#,
+                ),
+                ("src/shared.ts", "export const shared = 1;"),
+            ],
+        );
+        assert!(split
+            .context
+            .chunk_graph
+            .read()
+            .unwrap()
+            .get_chunk_by_name(&"shared.js".to_string())
+            .is_some());
+        let runtime = runtime_code(&split.context).unwrap();
+        for fragment in [
+            "mako/runtime/ensure chunk",
+            "return requireModule.ensure(chunkId).then(",
+            "global.__mako_chunk_load__ = requireModule.ensure;",
+        ] {
+            assert!(runtime.contains(fragment), "{}", fragment);
+        }
+
+        // one `import()` pulls in the chunk loader and the interop of the
+        // namespace, but no css loader
+        let with_import = compile_files(
+            "runtime-features-import",
+            &[
+                ("src/index.ts", r#"import("./a").then(console.log);"#),
+                ("src/a.ts", "export const a = 1;"),
+            ],
+        );
+        let runtime = runtime_code(&with_import.context).unwrap();
+        for fragment in [
+            "mako/runtime/ensure chunk",
+            "mako/runtime/load script",
+            "requireModule.dr",
+            "_interop_require_wildcard",
+        ] {
+            assert!(runtime.contains(fragment), "{}", fragment);
+        }
+        for fragment in [
+            "mako/runtime/ensure load css chunk",
+            "_interop_require_default",
+        ] {
+            assert!(!runtime.contains(fragment), "{}", fragment);
+        }
+
+        // the css of an async chunk, and the interop of a commonjs module
+        let with_css = compile_files(
+            "runtime-features-css",
+            &[
+                (
+                    "src/index.ts",
+                    r#"import("./a").then(console.log); import b from "./b"; console.log(b);"#,
+                ),
+                ("src/a.ts", r#"import "./a.css"; export const a = 1;"#),
+                ("src/a.css", ".a { color: red; }"),
+                ("src/b.js", "module.exports = 1;"),
+            ],
+        );
+        let runtime = runtime_code(&with_css.context).unwrap();
+        assert!(runtime.contains("mako/runtime/ensure load css chunk"));
+        assert!(runtime.contains("_interop_require_default"));
+
+        // all of them in watch mode, which isn't re-rendered with the hot
+        // updates
+        let compiler = setup_compiler(None);
+        let runtime = runtime_code(&compiler.context).unwrap();
+        assert!(runtime.contains("mako/runtime/ensure load css chunk"));
+        assert!(runtime.contains("_interop_require_wildcard"));
+    }
+
+    #[test]
+    fn test_unload_chunk_css() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/unload-chunk-css");
+        let config = Config::new(&root, None, None).unwrap();
+        let output_path = config.output.path.clone();
+        let compiler = Compiler::new(config, root.clone(), Args { watch: false }, None).unwrap();
+        compiler.compile().unwrap();
+        // run.cjs loads the links and the scripts of a fake document
+        let output = std::process::Command::new("node")
+            .arg(root.join("run.cjs"))
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&output_path).unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        // the shared css is kept until both of the chunks are released
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            concat!(
+                r#"{"loaded":["/a_js-async.css","/b_js-async.css","/shared-async.css"],"#,
+                r#""afterOne":["/a_js-async.css","/b_js-async.css","/shared-async.css"],"#,
+                r#""afterA":["/b_js-async.css","/shared-async.css"],"afterB":[],"#,
+                r#""reloaded":["/a_js-async.css","/shared-async.css"]}"#
+            )
+        );
+
+        let compiler = Compiler::new(
+            Config::new(&root, None, Some(r#"{"output":{"unloadChunkCss":false}}"#)).unwrap(),
+            root,
+            Args { watch: false },
+            None,
+        )
+        .unwrap();
+        let runtime = runtime_code(&compiler.context).unwrap();
+        assert!(!runtime.contains("unloadChunkCss"));
+    }
 }
@@ -0,0 +1,297 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use cached::{Cached, SizedCache};
+use swc_core::base::sourcemap::RawToken;
+
+use crate::ast::sourcemap::{encoded_source_map_to_buf, RawSourceMap};
+use crate::compiler::Context;
+use crate::utils::memory::{sized_cache_usage, sweep_stale_entries, CacheUsage};
+
+/// The source maps of the js chunks and the encoded mappings of their
+/// modules, kept between the builds of the watch mode. The chunks whose
+/// modules are not changed reuse their serialized maps, and the map of a
+/// changed chunk is assembled from the mappings of its modules, so only the
+/// mappings of the changed modules are encoded again.
+pub struct SourceMapCache {
+    // chunk key -> serialized map
+    chunks: Mutex<SizedCache<String, Arc<Vec<u8>>>>,
+    // module key -> encoded mappings
+    modules: Mutex<SizedCache<String, Arc<ModuleMappings>>>,
+}
+
+impl Default for SourceMapCache {
+    fn default() -> Self {
+        Self {
+            chunks: Mutex::new(SizedCache::with_size(500)),
+            modules: Mutex::new(SizedCache::with_size(20000)),
+        }
+    }
+}
+
+/// A module of a chunk, with the line of the chunk where the module's map
+/// starts.
+pub struct ChunkModule<'a> {
+    pub key: String,
+    pub map: &'a Arc<RawSourceMap>,
+    pub dst_line: u32,
+}
+
+impl SourceMapCache {
+    /// The serialized map of the chunk, which is built by `build` if it's not
+    /// cached.
+    pub fn chunk_map(
+        &self,
+        key: &str,
+        context: &Context,
+        build: impl FnOnce() -> Vec<u8>,
+    ) -> Vec<u8> {
+        if let Some(buf) = self.chunks.lock().unwrap().cache_get(key) {
+            context.stats_info.add_reused_source_map();
+            return buf.as_ref().clone();
+        }
+        let buf = build();
+        self.chunks
+            .lock()
+            .unwrap()
+            .cache_set(key.to_string(), Arc::new(buf.clone()));
+        buf
+    }
+
+    /// Assemble the map of the chunk from the mappings of the modules, or
+    /// none if the sources must be optimized, see `encoded_source_map_to_buf`.
+    pub fn assemble(&self, modules: &[ChunkModule], context: &Context) -> Option<Vec<u8>> {
+        crate::mako_profile_function!();
+        let start = Instant::now();
+
+        let mut mappings = String::new();
+        let mut state = MappingState::default();
+        let mut sources = vec![];
+        let mut sources_content = vec![];
+        let mut names = vec![];
+        for module in modules {
+            let module_mappings = self.module_mappings(&module.key, module.map)?;
+            let offset = Offset {
+                dst_line: module.dst_line,
+                src_id: sources.len() as u32,
+                name_id: names.len() as u32,
+            };
+            module_mappings.write(&mut mappings, &mut state, &offset);
+
+            let map = module.map;
+            sources.extend(map.sources.iter().map(String::as_str));
+            sources_content.extend(map.sources_content.iter().map(Option::as_deref));
+            names.extend(map.names.iter().map(String::as_str));
+        }
+
+        let buf =
+            encoded_source_map_to_buf(&sources, &sources_content, &names, &mappings, context)?;
+        context.stats_info.add_source_map_duration(start.elapsed());
+        Some(buf)
+    }
+
+    // none if the module has range mappings, which are not encoded here
+    fn module_mappings(&self, key: &str, map: &Arc<RawSourceMap>) -> Option<Arc<ModuleMappings>> {
+        if let Some(mappings) = self.modules.lock().unwrap().cache_get(key) {
+            return Some(mappings.clone());
+        }
+        let mappings = Arc::new(ModuleMappings::new(map.clone())?);
+        self.modules
+            .lock()
+            .unwrap()
+            .cache_set(key.to_string(), mappings.clone());
+        Some(mappings)
+    }
+
+    /// Drop the entries of the chunks and the modules which are not used in
+    /// the last build, see `sweep_stale_entries`.
+    pub fn sweep(&self) {
+        sweep_stale_entries(&mut self.chunks.lock().unwrap());
+        sweep_stale_entries(&mut self.modules.lock().unwrap());
+    }
+
+    pub fn usages(&self) -> Vec<CacheUsage> {
+        vec![
+            sized_cache_usage("chunk source maps", &self.chunks.lock().unwrap(), |buf| {
+                buf.len()
+            }),
+            sized_cache_usage(
+                "module source map mappings",
+                &self.modules.lock().unwrap(),
+                |mappings| mappings.tail.len(),
+            ),
+        ]
+    }
+}
+
+/// The mappings of a module, which are relative to the previous ones in the
+/// chunk. Only the tokens up to the first one with a source and the first
+/// one with a name depend on the modules before it, the rest are encoded
+/// once and copied to the mappings of every chunk of the module.
+pub struct ModuleMappings {
+    map: Arc<RawSourceMap>,
+    // count of the tokens encoded with the chunk
+    head: usize,
+    tail: String,
+    // of the last token of the module
+    end: MappingState,
+    has_source: bool,
+    has_name: bool,
+}
+
+impl ModuleMappings {
+    fn new(map: Arc<RawSourceMap>) -> Option<Self> {
+        if map.tokens.iter().any(|token| token.is_range) {
+            return None;
+        }
+        let first_source = map.tokens.iter().position(has_source);
+        let first_name = map.tokens.iter().position(|token| has_name(token, &map));
+        let head = match (first_source, first_name) {
+            (Some(source), Some(name)) => source.max(name) + 1,
+            (Some(source), None) => source + 1,
+            _ => map.tokens.len(),
+        };
+
+        let mut state = MappingState::default();
+        let mut head_mappings = String::new();
+        for token in &map.tokens[..head] {
+            state.encode(&mut head_mappings, *token, has_name(token, &map));
+        }
+        let mut tail = String::new();
+        for token in &map.tokens[head..] {
+            state.encode(&mut tail, *token, has_name(token, &map));
+        }
+
+        Some(Self {
+            head,
+            tail,
+            end: state,
+            has_source: first_source.is_some(),
+            has_name: first_name.is_some(),
+            map,
+        })
+    }
+
+    fn write(&self, mappings: &mut String, state: &mut MappingState, offset: &Offset) {
+        if self.map.tokens.is_empty() {
+            return;
+        }
+        for token in &self.map.tokens[..self.head] {
+            state.encode(mappings, offset.apply(*token), has_name(token, &self.map));
+        }
+        mappings.push_str(&self.tail);
+
+        let end = &self.end;
+        state.dst_line = end.dst_line + offset.dst_line;
+        state.dst_col = end.dst_col;
+        if self.has_source {
+            state.src_id = end.src_id + offset.src_id;
+            state.src_line = end.src_line;
+            state.src_col = end.src_col;
+        }
+        if self.has_name {
+            state.name_id = end.name_id + offset.name_id;
+        }
+        state.last = end.last.map(|token| offset.apply(token));
+    }
+}
+
+struct Offset {
+    dst_line: u32,
+    src_id: u32,
+    name_id: u32,
+}
+
+impl Offset {
+    fn apply(&self, token: RawToken) -> RawToken {
+        RawToken {
+            dst_line: token.dst_line + self.dst_line,
+            src_id: if token.src_id == !0 {
+                !0
+            } else {
+                token.src_id + self.src_id
+            },
+            name_id: if token.name_id == !0 {
+                !0
+            } else {
+                token.name_id + self.name_id
+            },
+            ..token
+        }
+    }
+}
+
+fn has_source(token: &RawToken) -> bool {
+    token.src_id != !0
+}
+
+// the names are written with the sources only
+fn has_name(token: &RawToken, map: &RawSourceMap) -> bool {
+    has_source(token) && token.name_id != !0 && (token.name_id as usize) < map.names.len()
+}
+
+// the values of the last token written, which the values of the next one
+// are relative to
+#[derive(Default, Clone, Copy)]
+struct MappingState {
+    dst_line: u32,
+    dst_col: u32,
+    src_id: u32,
+    src_line: u32,
+    src_col: u32,
+    name_id: u32,
+    last: Option<RawToken>,
+}
+
+impl MappingState {
+    // the same as the sourcemap crate serializes the mappings, the tokens
+    // same as the previous one are skipped
+    fn encode(&mut self, out: &mut String, token: RawToken, has_name: bool) {
+        let last = self.last.replace(token);
+        if token.dst_line != self.dst_line {
+            self.dst_col = 0;
+            while token.dst_line != self.dst_line {
+                out.push(';');
+                self.dst_line += 1;
+            }
+        } else if let Some(last) = last {
+            if last == token {
+                return;
+            }
+            out.push(',');
+        }
+
+        encode_vlq_diff(out, token.dst_col, self.dst_col);
+        self.dst_col = token.dst_col;
+        if has_source(&token) {
+            encode_vlq_diff(out, token.src_id, self.src_id);
+            self.src_id = token.src_id;
+            encode_vlq_diff(out, token.src_line, self.src_line);
+            self.src_line = token.src_line;
+            encode_vlq_diff(out, token.src_col, self.src_col);
+            self.src_col = token.src_col;
+            if has_name {
+                encode_vlq_diff(out, token.name_id, self.name_id);
+                self.name_id = token.name_id;
+            }
+        }
+    }
+}
+
+const B64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_vlq_diff(out: &mut String, a: u32, b: u32) {
+    let num = i64::from(a) - i64::from(b);
+    let mut num = if num < 0 { ((-num) << 1) + 1 } else { num << 1 };
+    loop {
+        let mut digit = num & 0b11111;
+        num >>= 5;
+        if num > 0 {
+            digit |= 1 << 5;
+        }
+        out.push(B64_CHARS[digit as usize] as char);
+        if num == 0 {
+            break;
+        }
+    }
+}
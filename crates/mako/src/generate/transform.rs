@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::mpsc::channel;
 use std::sync::Arc;
 use std::time::Instant;
@@ -21,7 +21,12 @@ use tracing::debug;
 
 use crate::ast::js_ast::JsAst;
 use crate::compiler::{Compiler, Context};
-use crate::module::{generate_module_id, Dependency, ModuleAst, ModuleId, ModuleType, ResolveType};
+use crate::config::ModuleKind;
+use crate::module::{
+    generate_module_id, Dependency, ImportMode, ModuleAst, ModuleId, ModuleSystem, ModuleType,
+    ResolveType,
+};
+use crate::module_graph::ModuleGraph;
 use crate::share::helpers::SWC_HELPERS;
 use crate::utils::thread_pool;
 use crate::visitors::async_module::{mark_async, AsyncModule};
@@ -31,12 +36,14 @@ use crate::visitors::dep_replacer::{DepReplacer, DependenciesToReplace, Resolved
 use crate::visitors::dynamic_import::DynamicImport;
 use crate::visitors::mako_require::MakoRequire;
 use crate::visitors::meta_url_replacer::MetaUrlReplacer;
+use crate::visitors::node_interop::NodeInterop;
 use crate::visitors::optimize_define_utils::OptimizeDefineUtils;
 
 impl Compiler {
     pub fn transform_all(&self, async_deps_map: HashMap<ModuleId, Vec<Dependency>>) -> Result<()> {
         let t = Instant::now();
         let context = &self.context;
+        context.runtime_features.clear();
         let module_ids = {
             let module_graph = context.module_graph.read().unwrap();
             module_graph
@@ -100,24 +107,45 @@ pub fn transform_modules_in_thread(
                             chunk_id: None,
                             to_replace_source: chunk_name,
                             resolved_module_id: id.clone(),
+                            import_mode: ImportMode::default(),
+                        }
+                    }
+                    // the module is loaded already, e.g. a self import
+                    ResolveType::DynamicImport(_)
+                        if context
+                            .chunk_graph
+                            .read()
+                            .unwrap()
+                            .is_loaded_dynamic_import(&module_id, id) =>
+                    {
+                        ResolvedReplaceInfo {
+                            chunk_id: None,
+                            to_replace_source: id.generate(&context),
+                            resolved_module_id: id.clone(),
+                            import_mode: ImportMode::Eager,
                         }
                     }
                     ResolveType::DynamicImport(import_options) => {
-                        let chunk_id = Some(match import_options.get_chunk_name() {
-                            Some(chunk_name) => generate_module_id(chunk_name, &context),
-                            None => id.generate(&context),
+                        // eager and weak imports have no chunk to load
+                        let chunk_id = dep.resolve_type.is_async_chunk().then(|| {
+                            match import_options.get_chunk_name() {
+                                Some(chunk_name) => generate_module_id(chunk_name, &context),
+                                None => id.generate(&context),
+                            }
                         });
 
                         ResolvedReplaceInfo {
                             chunk_id,
                             to_replace_source: id.generate(&context),
                             resolved_module_id: id.clone(),
+                            import_mode: import_options.mode,
                         }
                     }
                     _ => ResolvedReplaceInfo {
                         chunk_id: None,
                         to_replace_source: id.generate(&context),
                         resolved_module_id: id.clone(),
+                        import_mode: ImportMode::default(),
                     },
                 };
 
@@ -128,12 +156,20 @@ pub fn transform_modules_in_thread(
                             (None, _) => {}
                             (Some(id), _) => info.chunk_id = Some(id.clone()),
                         }
+                        if dep.resolve_type.is_dynamic_esm() {
+                            info.import_mode = replace_info.import_mode;
+                        }
                     })
                     .or_insert(replace_info);
             });
             insert_swc_helper_replace(&mut resolved_deps, &context);
             let module = module_graph.get_module(&module_id).unwrap();
             let info = module.info.as_ref().unwrap();
+            let commonjs_sources = if info.file.module_kind == Some(ModuleKind::Esm) {
+                get_commonjs_sources(&module_id, &module_graph)
+            } else {
+                HashSet::new()
+            };
             let ast = info.ast.clone();
             let deps_to_replace = DependenciesToReplace {
                 resolved: resolved_deps,
@@ -150,6 +186,7 @@ pub fn transform_modules_in_thread(
                     async_deps: &async_deps,
                     wrap_async,
                     top_level_await: info.top_level_await,
+                    commonjs_sources: &commonjs_sources,
                 });
                 let message = match ret {
                     Ok(_) => Ok((module_id, ModuleAst::Script(ast))),
@@ -177,6 +214,22 @@ pub fn transform_modules_in_thread(
     Ok(())
 }
 
+// the sources of the commonjs deps, which are imported by the rules of node
+// in es modules
+fn get_commonjs_sources(module_id: &ModuleId, module_graph: &ModuleGraph) -> HashSet<String> {
+    module_graph
+        .get_dependencies(module_id)
+        .into_iter()
+        .filter(|(id, _)| {
+            module_graph
+                .get_module(id)
+                .and_then(|module| module.info.as_ref())
+                .is_some_and(|info| info.module_system == ModuleSystem::CommonJS)
+        })
+        .map(|(_, dep)| dep.source.clone())
+        .collect()
+}
+
 fn insert_swc_helper_replace(
     map: &mut HashMap<String, ResolvedReplaceInfo>,
     context: &Arc<Context>,
@@ -184,11 +237,12 @@ fn insert_swc_helper_replace(
     SWC_HELPERS.into_iter().for_each(|h| {
         let m_id: ModuleId = h.to_string().into();
         map.insert(
-            m_id.id.clone(),
+            m_id.id.to_string(),
             ResolvedReplaceInfo {
                 chunk_id: None,
                 to_replace_source: m_id.generate(context),
                 resolved_module_id: m_id,
+                import_mode: ImportMode::default(),
             },
         );
     });
@@ -202,6 +256,7 @@ pub struct TransformJsParam<'a> {
     pub async_deps: &'a Vec<Dependency>,
     pub wrap_async: bool,
     pub top_level_await: bool,
+    pub commonjs_sources: &'a HashSet<String>,
 }
 
 pub fn transform_js_generate(transform_js_param: TransformJsParam) -> Result<()> {
@@ -214,6 +269,7 @@ pub fn transform_js_generate(transform_js_param: TransformJsParam) -> Result<()>
         async_deps,
         wrap_async,
         top_level_await,
+        commonjs_sources,
     } = transform_js_param;
     GLOBALS.set(&context.meta.script.globals, || {
         try_with_handler(
@@ -236,6 +292,11 @@ pub fn transform_js_generate(transform_js_param: TransformJsParam) -> Result<()>
                             import_interop,
                         ));
 
+                        if !commonjs_sources.is_empty() {
+                            ast.ast
+                                .visit_mut_with(&mut NodeInterop { commonjs_sources });
+                        }
+
                         ast.ast.visit_mut_with(&mut OptimizeDefineUtils {
                             top_level_mark,
                             unresolved_mark,
@@ -275,6 +336,7 @@ pub fn transform_js_generate(transform_js_param: TransformJsParam) -> Result<()>
                             context: context.clone(),
                         };
                         ast.ast.visit_mut_with(&mut mako_require);
+                        context.runtime_features.record(&ast.ast, context);
 
                         ast.ast
                             .visit_mut_with(&mut hygiene_with_config(hygiene::Config {
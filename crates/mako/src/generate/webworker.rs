@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Serialize;
+use twox_hash::XxHash64;
+
+use crate::compiler::Context;
+use crate::config::Config;
+use crate::generate::chunk::{Chunk, ChunkType};
+use crate::generate::chunk_graph::ChunkGraph;
+use crate::generate::generate_chunks::{ChunkFile, ChunkFileType};
+
+/// An entry of `self.__MAKO_MANIFEST__`, in the format of the precache
+/// manifest of Workbox, the url is relative to the worker.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct PrecacheEntry {
+    pub url: String,
+    pub revision: String,
+}
+
+/// Whether the chunk is run in a worker, the chunk of a webworker entry or
+/// the chunks loaded by the webworker entries only.
+pub(crate) fn is_webworker_chunk(chunk: &Chunk, chunk_graph: &ChunkGraph, config: &Config) -> bool {
+    let is_webworker_entry = |chunk: &Chunk| matches!(&chunk.chunk_type, ChunkType::Entry(_, name, false) if config.is_webworker_entry(name));
+    match &chunk.chunk_type {
+        ChunkType::Entry(_, _, false) => is_webworker_entry(chunk),
+        ChunkType::Async | ChunkType::Sync => {
+            let entries = chunk_graph.entry_ancestors_chunk(&chunk.id);
+            !entries.is_empty()
+                && entries
+                    .iter()
+                    .all(|chunk_id| chunk_graph.chunk(chunk_id).is_some_and(is_webworker_entry))
+        }
+        _ => false,
+    }
+}
+
+/// Prepend `self.__MAKO_MANIFEST__` to the webworker entries with
+/// `precacheManifest`, which lists the other emitted files with the hashes of
+/// their contents, so a service worker precaches them without a post-build
+/// step. The files of the workers and the source maps are not listed.
+pub(crate) fn inject_precache_manifests(
+    chunk_files: &mut [ChunkFile],
+    context: &Arc<Context>,
+) -> Result<()> {
+    let chunk_graph = context.chunk_graph.read().unwrap();
+    let precache_entries = chunk_graph
+        .get_chunks()
+        .into_iter()
+        .filter(|chunk| match &chunk.chunk_type {
+            ChunkType::Entry(_, name, false) => context
+                .config
+                .entry
+                .get(name)
+                .is_some_and(|entry| entry.is_webworker() && entry.precache_manifest),
+            _ => false,
+        })
+        .map(|chunk| chunk.id.id.clone())
+        .collect::<HashSet<_>>();
+    if precache_entries.is_empty() {
+        return Ok(());
+    }
+    let webworker_chunks = chunk_graph
+        .get_chunks()
+        .into_iter()
+        .filter(|chunk| is_webworker_chunk(chunk, &chunk_graph, &context.config))
+        .map(|chunk| chunk.id.id.clone())
+        .collect::<HashSet<_>>();
+    drop(chunk_graph);
+
+    let mut revisions = BTreeMap::new();
+    for file in chunk_files.iter() {
+        if !webworker_chunks.contains(file.chunk_id.as_str()) {
+            revisions.insert(file.disk_name(), revision(&file.content));
+        }
+    }
+    if context.config.emit_assets {
+        for (name, origin_path) in context.emitted_assets() {
+            let path = origin_path.split('?').next().unwrap_or(&origin_path);
+            revisions.insert(name, revision(&fs::read(path)?));
+        }
+    }
+    let manifest = revisions
+        .into_iter()
+        .map(|(url, revision)| PrecacheEntry { url, revision })
+        .collect::<Vec<_>>();
+    let line = format!(
+        "self.__MAKO_MANIFEST__ = {};\n",
+        serde_json::to_string(&manifest)?
+    );
+
+    for file in chunk_files.iter_mut().filter(|file| {
+        matches!(file.file_type, ChunkFileType::JS)
+            && precache_entries.contains(file.chunk_id.as_str())
+    }) {
+        file.content = [line.as_bytes(), &file.content].concat();
+        // the prepended line has no mappings
+        if let Some(source_map) = &file.source_map {
+            let mut source_map: serde_json::Value = serde_json::from_slice(source_map)?;
+            if let Some(serde_json::Value::String(mappings)) = source_map.get_mut("mappings") {
+                mappings.insert(0, ';');
+            }
+            file.source_map = Some(serde_json::to_vec(&source_map)?);
+        }
+    }
+    Ok(())
+}
+
+fn revision(content: &[u8]) -> String {
+    let mut hasher: XxHash64 = Default::default();
+    hasher.write(content);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use crate::compiler::{Args, Compiler};
+    use crate::config::Config;
+
+    #[test]
+    fn test_webworker() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/webworker");
+        let config = Config::new(&root, None, None).unwrap();
+        let output_path = config.output.path.clone();
+        let compiler = Compiler::new(config, root, Args { watch: false }, None).unwrap();
+        compiler.compile().unwrap();
+        let read = |name: &str| std::fs::read_to_string(output_path.join(name)).unwrap();
+        let files = std::fs::read_dir(&output_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        let file = |prefix: &str, ext: &str| {
+            files
+                .iter()
+                .find(|file| file.starts_with(prefix) && file.ends_with(ext))
+                .unwrap()
+                .clone()
+        };
+
+        // the worker is registered at a stable url, the others are hashed
+        let sw = read("sw.js");
+        let index_js = file("index.", ".js");
+        assert_ne!(index_js, "index.js");
+        // the files of the worker are not precached
+        let manifest = sw.lines().next().unwrap();
+        assert!(
+            manifest.starts_with("self.__MAKO_MANIFEST__ = [{\"url\":\"index."),
+            "{}",
+            manifest
+        );
+        assert!(manifest.contains(&index_js), "{}", manifest);
+        assert!(manifest.contains(&file("index.", ".css")), "{}", manifest);
+        assert!(!manifest.contains("sw"), "{}", manifest);
+        assert!(!manifest.contains("offline"), "{}", manifest);
+        // no script tags nor stylesheets in the worker
+        assert!(!sw.contains("document.createElement"), "{}", sw);
+        // the worker is not in the html
+        let html = read("index.html");
+        assert!(!html.contains("sw."), "{}", html);
+
+        // run the worker with the chunks loaded by importScripts
+        let sw_css = file("sw.", ".css");
+        let output = Command::new("node")
+            .arg("-e")
+            .arg(format!(
+                r#"
+globalThis.self = globalThis;
+self.addEventListener = () => {{}};
+self.importScripts = (url) => require({:?} + url);
+require({:?});
+"#,
+                output_path.to_string_lossy().to_string() + "/",
+                output_path.join("sw.js").to_string_lossy()
+            ))
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&output_path).unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        // the css import is the url of the css file of the worker
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            format!(r#"{{"offline":"offline page","offlineCss":"/{}"}}"#, sw_css)
+        );
+    }
+}
@@ -7,18 +7,28 @@ mod build;
 pub mod cli;
 pub mod compiler;
 pub mod config;
+pub mod deps;
 pub mod dev;
+pub mod diagnostics;
 mod features;
 mod generate;
 pub mod module;
 mod module_graph;
+pub mod module_graph_view;
+pub mod multi_compiler;
 pub mod plugin;
 mod plugins;
+pub mod preview;
 pub mod resolve;
 pub mod share;
 pub mod stats;
+pub mod sub_modules;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod utils;
+pub mod virtual_modules;
 mod visitors;
+mod why;
 
 pub use {swc_core, swc_malloc};
 
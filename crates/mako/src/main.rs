@@ -11,10 +11,11 @@ use mako::compiler::{self, Args};
 #[cfg(not(feature = "profile"))]
 use mako::dev;
 use mako::utils::logger::init_logger;
+use mako::utils::output_fs::MemoryFileSystem;
 #[cfg(feature = "profile")]
 use mako::utils::profile_gui::ProfileApp;
 use mako::utils::tokio_runtime;
-use mako::{cli, config};
+use mako::{cli, config, multi_compiler, preview};
 use tracing::debug;
 
 fn main() -> Result<()> {
@@ -29,39 +30,129 @@ async fn run() -> Result<()> {
 
     // cli
     let cli = cli::Cli::parse();
+    let root = match &cli.command {
+        Some(cli::Command::Why { root, .. })
+        | Some(cli::Command::Deps { root, .. })
+        | Some(cli::Command::Preview { root, .. }) => root.clone(),
+        None => cli.root.clone().unwrap(),
+    };
     debug!(
         "cli: watch = {}, mode = {}, root = {}",
         cli.watch,
         cli.mode,
-        cli.root.to_str().unwrap()
+        root.to_str().unwrap()
     );
-    let root = if cli.root.is_absolute() {
-        cli.root
+    let root = if root.is_absolute() {
+        root
     } else {
-        std::env::current_dir()?.join(cli.root)
+        std::env::current_dir()?.join(root)
     };
     let root = root
         .canonicalize()
         .map_err(|_| anyhow!("Root directory {:?} not found", root))?;
 
     // config
-    let cli_args = format!(
-        r#"
-        {{
-            "mode": "{}"
-        }}
-        "#,
-        cli.mode
-    );
-    let mut config = config::Config::new(&root, None, Some(cli_args.as_str()))
-        .map_err(|e| anyhow!(format!("Load config failed: {}", e)))?;
-
-    config.mode = cli.mode;
+    let mut cli_args = serde_json::json!({ "mode": cli.mode.to_string() });
+    if let Some(env) = &cli.env {
+        cli_args["envName"] = serde_json::json!(env);
+    }
+    if let Some(format) = &cli.graph {
+        cli_args["graph"] = serde_json::json!({
+            "format": format,
+            "collapseNodeModules": cli.graph_collapse_node_modules,
+        });
+    }
+    if cli.analyze {
+        cli_args["analyze"] = serde_json::json!({});
+    }
+    if cli.stats_packages {
+        cli_args["stats"] = serde_json::json!({ "packages": true });
+    }
+    let cli_args = cli_args.to_string();
+    let load_config = || -> Result<config::Config> {
+        let mut config = config::Config::new(&root, None, Some(cli_args.as_str()))
+            .map_err(|e| anyhow!(format!("Load config failed: {}", e)))?;
+        config.mode = cli.mode.clone();
+        // stdout is for the summary only
+        if cli.json.is_some() {
+            config.quiet = true;
+            config.progress = None;
+        }
+        Ok(config)
+    };
+    let config = load_config()?;
 
     debug!("config: {:?}", config);
 
+    match &cli.show_config {
+        Some(None) => {
+            println!("{}", serde_json::to_string_pretty(&config)?);
+            return Ok(());
+        }
+        Some(Some(file)) => {
+            let module_rules = config::ModuleRules::new(&config.module_rules)?;
+            println!("{}", module_rules.format_matched(&root.join(file), &root));
+            return Ok(());
+        }
+        None => {}
+    }
+
+    if let Some(cli::Command::Why {
+        path, max_chains, ..
+    }) = &cli.command
+    {
+        // only the module graph is needed, so outputs are kept in memory
+        let compiler = compiler::Compiler::new_with_output_fs(
+            config,
+            root,
+            Args { watch: false },
+            None,
+            Some(Arc::new(MemoryFileSystem::new())),
+        )?;
+        compiler.compile()?;
+        let chains = compiler.why(path, *max_chains)?;
+        println!("{}", compiler.format_import_chains(&chains));
+        return Ok(());
+    }
+
+    if let Some(cli::Command::Deps { path, .. }) = &cli.command {
+        // nothing is built, the outputs are kept in memory anyway
+        let compiler = compiler::Compiler::new_with_output_fs(
+            config,
+            root,
+            Args { watch: false },
+            None,
+            Some(Arc::new(MemoryFileSystem::new())),
+        )?;
+        let deps = compiler.deps(path)?;
+        println!("{}", serde_json::to_string_pretty(&deps)?);
+        return Ok(());
+    }
+
+    if let Some(cli::Command::Preview { port, https, .. }) = &cli.command {
+        let server = preview::PreviewServer::new(&config, *port, *https)?;
+        return server.serve().await;
+    }
+
+    // the dev server only serves the modern build
+    if config.dual_build.is_some() && !cli.watch {
+        if cli.json.is_some() {
+            return Err(anyhow!("--json is not supported with dualBuild"));
+        }
+        let compiler =
+            multi_compiler::MultiCompiler::dual_build(config, load_config()?, root, None)?;
+        if let Err(e) = compiler.compile() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // compiler
     let compiler = compiler::Compiler::new(config, root.clone(), Args { watch: cli.watch }, None)?;
+    if !cli.only_entry.is_empty() {
+        compiler.only_entries(&cli.only_entry)?;
+    }
     let compiler = Arc::new(compiler);
 
     #[cfg(feature = "profile")]
@@ -78,12 +169,29 @@ async fn run() -> Result<()> {
 
     #[cfg(not(feature = "profile"))]
     {
-        if let Err(e) = compiler.compile() {
+        let result = compiler.compile();
+        if let Some(json) = &cli.json {
+            let summary =
+                serde_json::to_string_pretty(&compiler.build_summary(result.as_ref().err()))?;
+            match json {
+                Some(path) => std::fs::write(path, summary)?,
+                None => println!("{}", summary),
+            }
+        }
+        if let Err(e) = result {
             eprintln!("{}", e);
             std::process::exit(1);
         }
+        if let Some(filter) = &cli.verbose_chunks {
+            println!("{}", compiler.format_chunk_placements(filter));
+        }
+        if cli.memory_report {
+            println!("{}", compiler.memory_report());
+        }
         if cli.watch {
-            let d = dev::DevServer::new(root.clone(), compiler);
+            let d = dev::DevServer::new(root.clone(), compiler)
+                .restart_on_config_change(true)
+                .memory_report(cli.memory_report);
             // TODO: when in Dev Mode, Dev Server should start asap, and provider a loading  while in first compiling
             d.serve().await;
         }
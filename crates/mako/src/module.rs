@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
@@ -13,14 +13,17 @@ use swc_core::ecma::ast::{
     ModuleExportName, NamedExport,
 };
 use swc_core::ecma::utils::quote_ident;
+use url::form_urlencoded;
 
 use crate::ast::css_ast::CssAst;
 use crate::ast::file::{win_path, File};
 use crate::ast::js_ast::JsAst;
 use crate::build::analyze_deps::AnalyzeDepsResult;
 use crate::compiler::Context;
-use crate::config::ModuleIdStrategy;
-use crate::resolve::ResolverResource;
+use crate::config::{ModuleIdStrategy, ModuleKind};
+use crate::resolve::{ResolvedResource, ResolverResource};
+use crate::utils::file_request::{decode_path, encode_path, normalize_id_query};
+use crate::utils::interner::{InternedStr, Interner};
 
 pub type Dependencies = HashSet<Dependency>;
 
@@ -40,6 +43,36 @@ pub enum ModuleSystem {
     Custom,
 }
 
+/// Why a module is bailed out of the optimizations which break the code it
+/// evaluates at runtime, e.g. the concatenation.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BailoutReason {
+    /// direct `eval(...)`, which evaluates in the scope of the module
+    #[serde(rename = "eval")]
+    DirectEval,
+    /// `new Function(...)` or `Function(...)`
+    #[serde(rename = "new Function")]
+    NewFunction,
+}
+
+impl std::fmt::Display for BailoutReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BailoutReason::DirectEval => write!(f, "uses direct eval"),
+            BailoutReason::NewFunction => write!(f, "uses new Function"),
+        }
+    }
+}
+
+impl From<ModuleKind> for ModuleSystem {
+    fn from(kind: ModuleKind) -> Self {
+        match kind {
+            ModuleKind::Esm => ModuleSystem::ESModule,
+            ModuleKind::Commonjs => ModuleSystem::CommonJS,
+        }
+    }
+}
+
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Default)]
     pub struct ResolveTypeFlags: u16 {
@@ -137,6 +170,21 @@ impl From<&NamedExport> for NamedExportType {
 pub struct ImportOptions {
     pub chunk_name: Option<String>,
     pub ignore: bool,
+    pub mode: ImportMode,
+}
+
+/// How the module of `import()` is bundled, by the `webpackMode` magic comment.
+#[derive(Eq, Hash, PartialEq, Serialize, Debug, Clone, Copy, Default)]
+pub enum ImportMode {
+    /// in an async chunk of its own
+    #[default]
+    Lazy,
+    /// all the modules matched by a dynamic expression are in one async chunk
+    LazyOnce,
+    /// in the chunk of the importer, but still imported with a promise
+    Eager,
+    /// not bundled, it's resolved only if it's already loaded by other chunks
+    Weak,
 }
 
 impl ImportOptions {
@@ -180,6 +228,17 @@ impl ResolveType {
     pub fn is_dynamic_esm(&self) -> bool {
         matches!(self, ResolveType::DynamicImport(_))
     }
+
+    /// Whether the dependency is bundled in an async chunk of its own.
+    pub fn is_async_chunk(&self) -> bool {
+        match self {
+            ResolveType::DynamicImport(import_options) => {
+                matches!(import_options.mode, ImportMode::Lazy | ImportMode::LazyOnce)
+            }
+            ResolveType::Worker(_) => true,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -190,6 +249,9 @@ pub struct ModuleInfo {
     pub external: Option<String>,
     pub raw: String,
     pub raw_hash: u64,
+    /// The hash of the dependency requests, with which the rebuild skips
+    /// the graph diffing and the chunk regrouping, only calculated in watch mode
+    pub deps_hash: u64,
     /// Modules with top-level-await
     pub top_level_await: bool,
     /// The top-level-await module must be an async module, in addition, for example, wasm is also an async module
@@ -200,6 +262,17 @@ pub struct ModuleInfo {
     /// The transformed source map chain of this module
     pub source_map_chain: Vec<Vec<u8>>,
     pub module_system: ModuleSystem,
+    /// Directives of config.moduleDirectives at the top of the module, e.g. "use client"
+    pub directives: Vec<String>,
+    /// Files other than the module itself which its output depends on, e.g.
+    /// the tsconfig.json consulted when resolving its deps
+    pub build_dependencies: Vec<PathBuf>,
+    /// The errors of a module which failed to build in watch mode, whose
+    /// module throws them at runtime
+    pub diagnostics: Vec<String>,
+    /// Direct eval and new Function of the module, which is excluded from the
+    /// concatenation
+    pub bailouts: Vec<BailoutReason>,
 }
 
 impl Default for ModuleInfo {
@@ -212,11 +285,16 @@ impl Default for ModuleInfo {
             external: None,
             raw: "".to_string(),
             raw_hash: 0,
+            deps_hash: 0,
             top_level_await: false,
             is_async: false,
             resolved_resource: None,
             source_map_chain: vec![],
             is_ignored: false,
+            directives: vec![],
+            build_dependencies: vec![],
+            diagnostics: vec![],
+            bailouts: vec![],
         }
     }
 }
@@ -231,12 +309,8 @@ fn md5_hash(source_str: &str, lens: usize) -> String {
 pub fn generate_module_id(origin_module_id: &str, context: &Arc<Context>) -> String {
     match context.config.module_id_strategy {
         ModuleIdStrategy::Hashed => md5_hash(origin_module_id, 8),
-        ModuleIdStrategy::Named => {
-            // readable ids for debugging usage
-            let absolute_path = PathBuf::from(origin_module_id);
-            let relative_path = diff_paths(&absolute_path, &context.root).unwrap_or(absolute_path);
-            win_path(relative_path.to_str().unwrap())
-        }
+        // readable ids for debugging usage
+        ModuleIdStrategy::Named => relative_module_id(origin_module_id, &context.root),
         ModuleIdStrategy::Numeric => {
             let numeric_ids_map = context.numeric_ids_map.read().unwrap();
             if let Some(numeric_id) = numeric_ids_map.get(origin_module_id) {
@@ -245,9 +319,27 @@ pub fn generate_module_id(origin_module_id: &str, context: &Arc<Context>) -> Str
                 md5_hash(origin_module_id, 8)
             }
         }
+        ModuleIdStrategy::Deterministic => {
+            let deterministic_ids_map = context.deterministic_ids_map.read().unwrap();
+            if let Some(deterministic_id) = deterministic_ids_map.get(origin_module_id) {
+                deterministic_id.clone()
+            } else {
+                // e.g. chunk names and modules added in watch mode
+                md5_hash(&relative_module_id(origin_module_id, &context.root), 8)
+            }
+        }
     }
 }
 
+// the length of deterministic ids without collisions
+pub const DETERMINISTIC_ID_LENGTH: usize = 6;
+
+pub fn relative_module_id(origin_module_id: &str, root: &Path) -> String {
+    let absolute_path = PathBuf::from(origin_module_id);
+    let relative_path = diff_paths(&absolute_path, root).unwrap_or(absolute_path);
+    win_path(relative_path.to_str().unwrap())
+}
+
 pub fn relative_to_root(module_path: &String, root: &PathBuf) -> String {
     let absolute_path = PathBuf::from(module_path);
     let relative_path = diff_paths(&absolute_path, root).unwrap_or(absolute_path);
@@ -262,31 +354,29 @@ pub fn relative_to_root(module_path: &String, root: &PathBuf) -> String {
     }
 }
 
-#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+// the ids of the module graph are interned, see `ModuleId::intern`, so the
+// clones in the chunks and the caches share the strings of the module graph
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Ord, PartialOrd)]
 pub struct ModuleId {
-    pub id: String,
-}
-
-impl Ord for ModuleId {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.id.cmp(&other.id)
-    }
-}
-
-impl PartialOrd for ModuleId {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
+    pub id: InternedStr,
 }
 
 impl ModuleId {
-    // we use absolute path as module id now
+    // we use absolute path as module id now, the query is normalized so
+    // that the same query in the other orders is the same module, the
+    // original one is `File::raw_query` of the module
     pub fn new(id: String) -> Self {
         Self {
-            id: win_path(id.as_str()),
+            id: normalize_id(&id).into(),
         }
     }
 
+    /// Share the string of the equal id added to the module graph before,
+    /// with the interner of the compiler.
+    pub fn intern(&mut self, interner: &Interner) {
+        self.id = interner.intern(&self.id);
+    }
+
     pub fn generate(&self, context: &Arc<Context>) -> String {
         // TODO: 如果是 Hashed 的话，stats 拿不到原始的 chunk_id
         generate_module_id(&self.id, context)
@@ -294,7 +384,7 @@ impl ModuleId {
 
     pub fn from_path(path_buf: PathBuf) -> Self {
         Self {
-            id: path_buf.to_string_lossy().to_string(),
+            id: normalize_id(&path_buf.to_string_lossy()).into(),
         }
     }
 
@@ -302,26 +392,157 @@ impl ModuleId {
     pub fn to_path(&self) -> PathBuf {
         PathBuf::from(self.id.clone())
     }
+
+    /// The id of a resolved file, the absolute path with `/` separators and
+    /// without `.` or `..` segments, followed by the query if it's not empty,
+    /// e.g. `/root/src/a.css?modules`. A relative path is resolved from `cwd`.
+    /// The `%`, `?` and `#` of the path are encoded, see `encode_path`.
+    pub fn from_resolved(path: &str, query: Option<&str>, cwd: &Path) -> Self {
+        let path = normalize_separators(path);
+        let path = if is_absolute_path(&path) {
+            path
+        } else {
+            format!(
+                "{}/{}",
+                normalize_separators(&cwd.to_string_lossy()).trim_end_matches('/'),
+                path
+            )
+        };
+        let path = encode_path(&normalize_segments(&path));
+        match query
+            .map(|query| normalize_id_query(query.trim_start_matches('?')))
+            .filter(|query| !query.is_empty())
+        {
+            Some(query) => Self {
+                id: format!("{}?{}", path, query).into(),
+            },
+            None => Self { id: path.into() },
+        }
+    }
+
+    /// The path of the id without the query.
+    pub fn path(&self) -> &str {
+        self.id.split_once('?').map_or(&self.id, |(path, _)| path)
+    }
+
+    /// The path relative to the root with `/` separators and the query, e.g.
+    /// `./src/a.css?modules`, which is how the modules are shown in the
+    /// messages, the stats and the comments of the chunks.
+    pub fn relative_display(&self, root: &Path) -> String {
+        let path = normalize_separators(self.path());
+        let root = normalize_separators(&root.to_string_lossy());
+        let relative_path = PathBuf::from(&path);
+        let relative_path = diff_paths(&relative_path, &root).unwrap_or(relative_path);
+        let relative_path = normalize_separators(&relative_path.to_string_lossy());
+        let mut display =
+            if relative_path.starts_with("../") || relative_path == ".." || relative_path == "." {
+                relative_path
+            } else {
+                format!("./{}", relative_path.trim_start_matches("./"))
+            };
+        if let Some((_, query)) = self.id.split_once('?') {
+            display.push('?');
+            display.push_str(query);
+        }
+        display
+    }
+
+    /// The path of the file on the disk, without the query.
+    pub fn absolute_path(&self, cwd: &Path) -> PathBuf {
+        let path = decode_path(self.path());
+        let path = path.as_str();
+        if is_absolute_path(&normalize_separators(path)) {
+            PathBuf::from(path)
+        } else {
+            cwd.join(path)
+        }
+    }
+
+    /// The pairs of the query in the normalized order, e.g. `[("modules", "")]`
+    /// of `a.css?modules`.
+    pub fn query(&self) -> Vec<(String, String)> {
+        match self.id.split_once('?') {
+            Some((_, query)) => form_urlencoded::parse(query.as_bytes())
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            None => vec![],
+        }
+    }
+}
+
+// the id with the normalized query, see `normalize_id_query`
+fn normalize_id(id: &str) -> String {
+    let id = win_path(id);
+    let Some((path, query)) = id.split_once('?') else {
+        return id;
+    };
+    let (query, fragment) = match query.split_once('#') {
+        Some((query, fragment)) => (query, Some(fragment)),
+        None => (query, None),
+    };
+    let mut normalized = path.to_string();
+    let query = normalize_id_query(query);
+    if !query.is_empty() {
+        normalized.push('?');
+        normalized.push_str(&query);
+    }
+    if let Some(fragment) = fragment {
+        normalized.push('#');
+        normalized.push_str(fragment);
+    }
+    normalized
+}
+
+fn normalize_separators(path: &str) -> String {
+    path.trim_start_matches("\\\\?\\").replace('\\', "/")
+}
+
+// `/a` or `C:/a`
+fn is_absolute_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/')
+        || (bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":/")
+}
+
+// resolve the `.` and `..` segments of an absolute path lexically
+fn normalize_segments(path: &str) -> String {
+    let (prefix, rest) = match path.split_once('/') {
+        Some((prefix, rest)) => (prefix, rest),
+        None => return path.to_string(),
+    };
+    let mut segments: Vec<&str> = vec![];
+    for segment in rest.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    format!("{}/{}", prefix, segments.join("/"))
 }
 
 impl From<String> for ModuleId {
     fn from(id: String) -> Self {
         Self {
-            id: win_path(id.as_str()),
+            id: normalize_id(&id).into(),
         }
     }
 }
 
 impl From<&str> for ModuleId {
     fn from(id: &str) -> Self {
-        Self { id: win_path(id) }
+        Self {
+            id: normalize_id(id).into(),
+        }
     }
 }
 
 impl From<PathBuf> for ModuleId {
     fn from(path: PathBuf) -> Self {
         Self {
-            id: win_path(path.to_string_lossy().to_string().as_str()),
+            id: normalize_id(&path.to_string_lossy()).into(),
         }
     }
 }
@@ -426,6 +647,26 @@ impl Module {
             .map_or(false, |info| info.external.is_some())
     }
 
+    /// The condition of `resolve.conditionNames` which selected the file of
+    /// the module
+    pub fn selected_condition(&self) -> Option<String> {
+        match self.info.as_ref()?.resolved_resource.as_ref()? {
+            ResolverResource::Resolved(ResolvedResource(resolution)) => {
+                resolution.condition.clone()
+            }
+            _ => None,
+        }
+    }
+
+    /// The directory of `resolve.roots` which the file of the module is
+    /// resolved in
+    pub fn resolved_root(&self) -> Option<PathBuf> {
+        match self.info.as_ref()?.resolved_resource.as_ref()? {
+            ResolverResource::Resolved(ResolvedResource(resolution)) => resolution.root.clone(),
+            _ => None,
+        }
+    }
+
     pub fn is_placeholder(&self) -> bool {
         self.get_module_type() == ModuleType::PlaceHolder
     }
@@ -533,3 +774,141 @@ fn empty_module_fn_expr() -> FnExpr {
         function: func.into(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::ModuleId;
+    use crate::utils::file_request::decode_path;
+
+    const ROOTS: [&str; 4] = [
+        "/root/project",
+        "/root/project/",
+        "C:/project",
+        "C:\\project",
+    ];
+    const PATHS: [&[&str]; 10] = [
+        &["src", "a.js"],
+        &["src", "components", "Button", "index.tsx"],
+        &["node_modules", "@scope", "pkg", "index.js"],
+        &["src", "with space.js"],
+        &[".", "src", "..", "src", "b.js"],
+        &["src", "styles", "a.module.css"],
+        // the file names with `?`, `%` and `#`
+        &["src", "a?b.js"],
+        &["src", "a%3Fb.js"],
+        &["src", "a#b", "index.js"],
+        &["src", "中文", "文件.js"],
+    ];
+    const QUERIES: [Option<&str>; 5] = [
+        None,
+        Some(""),
+        Some("modules"),
+        Some("?raw"),
+        Some("b=2&a=1"),
+    ];
+
+    // every combination of the roots, the paths, the queries and the separators
+    fn for_each_case(f: impl Fn(&str, &str, &str, Option<&str>)) {
+        for root in ROOTS {
+            for segments in PATHS {
+                for query in QUERIES {
+                    for separator in ["/", "\\"] {
+                        f(root, &segments.join(separator), separator, query);
+                    }
+                }
+            }
+        }
+    }
+
+    fn absolute(root: &str, path: &str, separator: &str) -> String {
+        format!(
+            "{}{}{}",
+            root.trim_end_matches(['/', '\\']),
+            separator,
+            path
+        )
+    }
+
+    #[test]
+    fn test_from_resolved_normalized() {
+        for_each_case(|root, path, separator, query| {
+            let cwd = Path::new(root);
+            let id = ModuleId::from_resolved(&absolute(root, path, separator), query, cwd);
+            assert!(!id.id.contains('\\'), "{}", id.id);
+            assert!(!id.id.contains("/./"), "{}", id.id);
+            assert!(!id.id.contains("/../"), "{}", id.id);
+            assert!(!id.id.ends_with('?'), "{}", id.id);
+            // the same id with the other separators
+            let slash = absolute(root, &path.replace('\\', "/"), "/");
+            assert_eq!(ModuleId::from_resolved(&slash, query, cwd), id);
+            // the same id of the relative path resolved from the cwd
+            assert_eq!(ModuleId::from_resolved(path, query, cwd), id);
+            // the only `?` is of the query
+            assert_eq!(id.id.contains('?'), id.id.len() > id.path().len());
+            // idempotent, and the cwd is not used for the absolute paths
+            let query = id.id.split_once('?').map(|(_, query)| query);
+            assert_eq!(
+                ModuleId::from_resolved(&decode_path(id.path()), query, Path::new("/elsewhere")),
+                id
+            );
+        });
+    }
+
+    #[test]
+    fn test_relative_display_round_trip() {
+        for_each_case(|root, path, separator, query| {
+            let cwd = Path::new(root);
+            let id = ModuleId::from_resolved(&absolute(root, path, separator), query, cwd);
+            let display = id.relative_display(cwd);
+            assert!(display.starts_with("./"), "{}", display);
+            assert!(!display.contains('\\'), "{}", display);
+            let (path, query) = match display.split_once('?') {
+                Some((path, query)) => (path, Some(query)),
+                None => (display.as_str(), None),
+            };
+            assert_eq!(ModuleId::from_resolved(&decode_path(path), query, cwd), id);
+            // the roots with the other separators
+            assert_eq!(
+                id.relative_display(Path::new(&root.replace('\\', "/"))),
+                display
+            );
+        });
+    }
+
+    #[test]
+    fn test_absolute_path_and_query() {
+        for_each_case(|root, path, separator, query| {
+            let cwd = Path::new(root);
+            let id = ModuleId::from_resolved(&absolute(root, path, separator), query, cwd);
+            let absolute_path = id.absolute_path(Path::new("/elsewhere"));
+            assert_eq!(absolute_path.to_string_lossy(), decode_path(id.path()));
+            assert!(absolute_path.ends_with(path.rsplit(['/', '\\']).next().unwrap()));
+            assert!(!id.path().contains('?'));
+            let expected: Vec<(String, String)> = match query.map(|q| q.trim_start_matches('?')) {
+                Some("modules") => vec![("modules".into(), "".into())],
+                Some("raw") => vec![("raw".into(), "".into())],
+                // in the normalized order
+                Some("b=2&a=1") => vec![("a".into(), "1".into()), ("b".into(), "2".into())],
+                _ => vec![],
+            };
+            assert_eq!(id.query(), expected);
+        });
+    }
+
+    #[test]
+    fn test_relative_display() {
+        let root = Path::new("/root/project");
+        let display = |id: &str| ModuleId::new(id.to_string()).relative_display(root);
+        assert_eq!(
+            display("/root/project/src/a.css?modules"),
+            "./src/a.css?modules"
+        );
+        assert_eq!(display("/root/other/a.js"), "../other/a.js");
+        assert_eq!(
+            ModuleId::new("src/a.js".to_string()).absolute_path(root),
+            root.join("src/a.js")
+        );
+    }
+}
@@ -1,16 +1,31 @@
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt;
 
 use fixedbitset::FixedBitSet;
 use petgraph::graph::{DefaultIx, NodeIndex};
 use petgraph::prelude::{Dfs, EdgeRef};
 use petgraph::stable_graph::{StableDiGraph, WalkNeighbors};
-use petgraph::visit::IntoEdgeReferences;
+use petgraph::visit::{IntoEdgeReferences, VisitMap, Visitable};
 use petgraph::Direction;
 use tracing::{debug, warn};
 
 use crate::module::{Dependencies, Dependency, Module, ModuleId};
 
+/// An import chain from an entry to a module, steps are empty when the module
+/// is the entry itself.
+#[derive(Debug, Clone)]
+pub struct ImportChain {
+    pub entry: ModuleId,
+    pub steps: Vec<ImportStep>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportStep {
+    pub from: ModuleId,
+    pub to: ModuleId,
+    pub dependency: Dependency,
+}
+
 #[derive(Debug)]
 pub struct ModuleGraph {
     pub id_index_map: HashMap<ModuleId, NodeIndex<DefaultIx>>,
@@ -57,6 +72,18 @@ impl ModuleGraph {
         self.graph.node_weights().collect()
     }
 
+    pub fn modules_with_directive(&self, directive: &str) -> Vec<&Module> {
+        self.graph
+            .node_weights()
+            .filter(|module| {
+                module
+                    .info
+                    .as_ref()
+                    .is_some_and(|info| info.directives.iter().any(|d| d == directive))
+            })
+            .collect()
+    }
+
     pub fn remove_module_and_deps(&mut self, module_id: &ModuleId) -> Module {
         let mut deps_module_ids = vec![];
         self.get_dependencies(module_id)
@@ -398,6 +425,86 @@ impl ModuleGraph {
         references
     }
 
+    // shortest first, at most max_chains chains which pass through different modules
+    pub fn import_chains(&self, target: &ModuleId, max_chains: usize) -> Vec<ImportChain> {
+        if !self.has_module(target) {
+            return vec![];
+        }
+
+        // only the modules which can reach the target are visited
+        let mut reachable = HashSet::from([target.clone()]);
+        let mut stack = vec![target.clone()];
+        while let Some(module_id) = stack.pop() {
+            for dependent in self.dependant_module_ids(&module_id) {
+                if reachable.insert(dependent.clone()) {
+                    stack.push(dependent);
+                }
+            }
+        }
+
+        let mut queue = self
+            .entries
+            .iter()
+            .filter(|entry| reachable.contains(*entry))
+            .map(|entry| ImportChain {
+                entry: entry.clone(),
+                steps: vec![],
+            })
+            .collect::<VecDeque<_>>();
+        let mut chains = vec![];
+        while let Some(chain) = queue.pop_front() {
+            if chains.len() >= max_chains {
+                break;
+            }
+            let current = chain.steps.last().map_or(&chain.entry, |step| &step.to);
+            if current == target {
+                chains.push(chain);
+                continue;
+            }
+            let mut visited = HashSet::new();
+            for (module_id, dependency) in self.get_dependencies(current) {
+                // one step for the imports of the same module, and no cycles
+                if !reachable.contains(module_id)
+                    || !visited.insert(module_id)
+                    || *module_id == chain.entry
+                    || chain.steps.iter().any(|step| &step.to == module_id)
+                {
+                    continue;
+                }
+                let mut steps = chain.steps.clone();
+                steps.push(ImportStep {
+                    from: current.clone(),
+                    to: module_id.clone(),
+                    dependency: dependency.clone(),
+                });
+                queue.push_back(ImportChain {
+                    entry: chain.entry.clone(),
+                    steps,
+                });
+            }
+        }
+        chains
+    }
+
+    /// The modules which can't be reached from the entries anymore, e.g. the
+    /// imports of them are removed in watch mode.
+    pub fn unreachable_module_ids(&self) -> Vec<ModuleId> {
+        let mut reachable = self.graph.visit_map();
+        for entry in &self.entries {
+            let Some(index) = self.id_index_map.get(entry) else {
+                continue;
+            };
+            let mut dfs = Dfs::from_parts(vec![*index], reachable);
+            while dfs.next(&self.graph).is_some() {}
+            reachable = dfs.discovered;
+        }
+        self.graph
+            .node_indices()
+            .filter(|index| !reachable.is_visited(index))
+            .map(|index| self.graph[index].id.clone())
+            .collect()
+    }
+
     pub fn dfs(&self, start: &ModuleId) -> Dfs<NodeIndex, FixedBitSet> {
         Dfs::new(&self.graph, *self.id_index_map.get(start).unwrap())
     }
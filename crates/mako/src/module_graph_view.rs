@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+
+use crate::ast::file::{Content, File};
+use crate::compiler::{Compiler, Context};
+use crate::module::{Dependency, ImportType, ModuleId, ResolveType};
+
+/// Read access to the module graph for the plugins, e.g. in `transform_js`,
+/// which runs in the worker threads while the graph is being built.
+///
+/// Every call takes the read lock of the graph for itself only, so it sees a
+/// consistent graph but later calls may see more modules and edges. During
+/// the build, an importer and its edges are added before its dependencies
+/// are built, so `dependents_of` of a module being transformed has at least
+/// the importer which triggered the build, and `dependencies_of` of a module
+/// is empty until the module is built. Don't use it in the hooks which get
+/// the graph itself, e.g. `optimize_module_graph`, which hold the write lock.
+pub struct ModuleGraphView<'a> {
+    context: &'a Context,
+}
+
+/// A module of the graph when it's read.
+#[derive(Debug, Clone)]
+pub struct ModuleSnapshot {
+    pub id: ModuleId,
+    pub is_entry: bool,
+    // false until the module is built, or for the externals and the ignored
+    pub built: bool,
+    pub path: Option<PathBuf>,
+    pub external: Option<String>,
+}
+
+/// A graph change requested by a plugin, applied when the build of the
+/// current batch of modules is done.
+#[derive(Debug, Clone)]
+pub enum GraphMutation {
+    /// a virtual module with the content, which is built as a dependency of
+    /// the importer, it's removed by tree shaking unless the importer imports
+    /// it, e.g. with an import added in `transform_js`
+    AddVirtualModule {
+        id: String,
+        content: Content,
+        importer: Option<ModuleId>,
+    },
+    /// an edge between two modules of the graph
+    AddDependency {
+        from: ModuleId,
+        to: ModuleId,
+        dependency: Dependency,
+    },
+}
+
+#[derive(Default)]
+pub struct GraphMutations {
+    queue: Mutex<Vec<GraphMutation>>,
+}
+
+impl Context {
+    pub fn module_graph_view(&self) -> ModuleGraphView<'_> {
+        ModuleGraphView { context: self }
+    }
+}
+
+impl ModuleGraphView<'_> {
+    pub fn get_module(&self, module_id: &ModuleId) -> Option<ModuleSnapshot> {
+        let module_graph = self.context.module_graph.read().unwrap();
+        let module = module_graph.get_module(module_id)?;
+        let info = module.info.as_ref();
+        Some(ModuleSnapshot {
+            id: module.id.clone(),
+            is_entry: module.is_entry,
+            built: info.is_some_and(|info| info.external.is_none()),
+            path: info.map(|info| info.file.pathname.clone()),
+            external: info.and_then(|info| info.external.clone()),
+        })
+    }
+
+    pub fn dependencies_of(&self, module_id: &ModuleId) -> Vec<(ModuleId, Dependency)> {
+        let module_graph = self.context.module_graph.read().unwrap();
+        if !module_graph.has_module(module_id) {
+            return vec![];
+        }
+        module_graph
+            .get_dependencies(module_id)
+            .into_iter()
+            .map(|(id, dependency)| (id.clone(), dependency.clone()))
+            .collect()
+    }
+
+    pub fn dependents_of(&self, module_id: &ModuleId) -> Vec<(ModuleId, Dependency)> {
+        let module_graph = self.context.module_graph.read().unwrap();
+        if !module_graph.has_module(module_id) {
+            return vec![];
+        }
+        module_graph
+            .get_dependents(module_id)
+            .into_iter()
+            .map(|(id, dependency)| (id.clone(), dependency.clone()))
+            .collect()
+    }
+
+    /// Queue a graph change, which is applied at the end of the build, before
+    /// the build returns its modules. The edges added to a module are dropped
+    /// when the module is rebuilt in watch mode.
+    pub fn queue(&self, mutation: GraphMutation) {
+        self.context
+            .graph_mutations
+            .queue
+            .lock()
+            .unwrap()
+            .push(mutation);
+    }
+}
+
+impl Compiler {
+    /// Apply the graph changes queued by the plugins, the virtual modules are
+    /// built, which may queue more changes.
+    pub(crate) fn apply_graph_mutations(&self) -> Result<HashSet<ModuleId>> {
+        let mut module_ids = HashSet::new();
+        loop {
+            let mutations =
+                std::mem::take(&mut *self.context.graph_mutations.queue.lock().unwrap());
+            if mutations.is_empty() {
+                return Ok(module_ids);
+            }
+            for mutation in mutations {
+                match mutation {
+                    GraphMutation::AddVirtualModule {
+                        id,
+                        content,
+                        importer,
+                    } => {
+                        self.context
+                            .virtual_modules
+                            .register(&id, move |_| Ok(content.clone()));
+                        let module_id = ModuleId::new(id.clone());
+                        let exists = self
+                            .context
+                            .module_graph
+                            .read()
+                            .unwrap()
+                            .has_module(&module_id);
+                        if !exists {
+                            module_ids.extend(
+                                self.build(vec![File::new(id.clone(), self.context.clone())])?,
+                            );
+                        }
+                        if let Some(importer) = importer {
+                            let dependency = Dependency {
+                                source: id,
+                                resolve_as: None,
+                                resolve_type: ResolveType::Import(ImportType::SideEffect),
+                                order: usize::MAX,
+                                span: None,
+                            };
+                            self.add_queued_dependency(&importer, &module_id, dependency)?;
+                        }
+                    }
+                    GraphMutation::AddDependency {
+                        from,
+                        to,
+                        dependency,
+                    } => self.add_queued_dependency(&from, &to, dependency)?,
+                }
+            }
+        }
+    }
+
+    fn add_queued_dependency(
+        &self,
+        from: &ModuleId,
+        to: &ModuleId,
+        dependency: Dependency,
+    ) -> Result<()> {
+        let mut module_graph = self.context.module_graph.write().unwrap();
+        for id in [from, to] {
+            if !module_graph.has_module(id) {
+                return Err(anyhow!(
+                    "Module {} of the queued dependency is not in the module graph",
+                    id.id
+                ));
+            }
+        }
+        module_graph.add_dependency(from, to, dependency);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+    use swc_core::ecma::ast::Module;
+
+    use super::GraphMutation;
+    use crate::ast::file::{Content, JsContent};
+    use crate::compiler::Context;
+    use crate::module::ModuleId;
+    use crate::plugin::{Plugin, PluginTransformJsParam};
+    use crate::utils::test_helper::setup_compiler_with_plugins;
+
+    #[derive(Default)]
+    struct GraphReaderPlugin {
+        // module -> importers when it's transformed
+        importers: Mutex<HashMap<String, Vec<String>>>,
+    }
+
+    fn file_name(id: &ModuleId) -> String {
+        Path::new(&id.id)
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    }
+
+    impl Plugin for GraphReaderPlugin {
+        fn name(&self) -> &str {
+            "graph_reader"
+        }
+
+        fn transform_js(
+            &self,
+            param: &PluginTransformJsParam,
+            _ast: &mut Module,
+            context: &Arc<Context>,
+        ) -> Result<()> {
+            let view = context.module_graph_view();
+            let module_id = ModuleId::new(param.path.to_string());
+            let mut importers = view
+                .dependents_of(&module_id)
+                .iter()
+                .map(|(id, _)| file_name(id))
+                .collect::<Vec<_>>();
+            importers.sort();
+            if param.path.ends_with("index.js") {
+                view.queue(GraphMutation::AddVirtualModule {
+                    id: "virtual:graph-view".to_string(),
+                    content: Content::Js(JsContent {
+                        content: "console.log(\"virtual\");".to_string(),
+                        ..Default::default()
+                    }),
+                    importer: Some(module_id.clone()),
+                });
+            }
+            self.importers
+                .lock()
+                .unwrap()
+                .insert(file_name(&module_id), importers);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_module_graph_view() {
+        let plugin = Arc::new(GraphReaderPlugin::default());
+        let compiler =
+            setup_compiler_with_plugins("test/build/module-graph-view", vec![plugin.clone()]);
+        compiler.compile().unwrap();
+
+        // the modules are transformed in the worker threads, after their
+        // importers are added to the graph
+        let importers = plugin.importers.lock().unwrap();
+        assert_eq!(importers["index.js"], Vec::<String>::new());
+        assert_eq!(importers["a.js"], vec!["index.js"]);
+        assert_eq!(importers["b.js"], vec!["index.js"]);
+        assert!(!importers["c.js"].is_empty());
+
+        let context = &compiler.context;
+        let view = context.module_graph_view();
+        let index_id = ModuleId::from(context.root.join("index.js"));
+        let index = view.get_module(&index_id).unwrap();
+        assert!(index.is_entry && index.built);
+        let mut dependencies = view
+            .dependencies_of(&index_id)
+            .iter()
+            .map(|(id, _)| id.id.clone())
+            .collect::<Vec<_>>();
+        dependencies.sort();
+        assert_eq!(dependencies.last().unwrap(), "virtual:graph-view");
+        assert_eq!(
+            view.dependents_of(&ModuleId::new("virtual:graph-view".to_string()))
+                .len(),
+            1
+        );
+        let js = context
+            .output_fs
+            .read(&context.config.output.path.join("index.js"))
+            .unwrap();
+        assert!(String::from_utf8(js)
+            .unwrap()
+            .contains("console.log(\"virtual\")"));
+    }
+}
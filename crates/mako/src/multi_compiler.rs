@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::{anyhow, Result};
+
+use crate::build::load::FileContentCache;
+use crate::compiler::{Args, Compiler};
+use crate::config::{Config, DualBuildConfig, PublicPathKind};
+use crate::generate::csp_report::write_csp_report;
+use crate::generate::html::{entrypoint_files, write_html, LegacyScripts};
+use crate::plugin::Plugin;
+
+/// Runs several builds of the same project in one invocation, e.g. the modern
+/// and the legacy bundles of `dualBuild`.
+pub struct MultiCompiler {
+    pub compilers: Vec<Compiler>,
+    // whether to clean the output of each compiler before all builds start,
+    // since the outputs may be nested
+    clean: Vec<bool>,
+    dual_build: Option<DualBuildConfig>,
+}
+
+impl MultiCompiler {
+    /// The compilers share the resolvers, the file handle limit and the file
+    /// contents, so the configs should only differ in the transforms, targets
+    /// and outputs.
+    pub fn new(
+        configs: Vec<Config>,
+        root: PathBuf,
+        extra_plugins: Option<Vec<Arc<dyn Plugin>>>,
+    ) -> Result<Self> {
+        let file_contents = Arc::new(FileContentCache::default());
+        let mut clean = vec![];
+        let mut compilers: Vec<Compiler> = vec![];
+        for mut config in configs {
+            clean.push(config.clean);
+            config.clean = false;
+            let mut compiler = Compiler::new(
+                config,
+                root.clone(),
+                Args { watch: false },
+                extra_plugins.clone(),
+            )?;
+            let context = Arc::get_mut(&mut compiler.context)
+                .ok_or_else(|| anyhow!("context of a new compiler should not be shared"))?;
+            if let Some(first) = compilers.first() {
+                context.resolvers = first.context.resolvers.clone();
+                context.file_handles = first.context.file_handles.clone();
+            }
+            context.file_contents = Some(file_contents.clone());
+            compilers.push(compiler);
+        }
+        Ok(Self {
+            compilers,
+            clean,
+            dual_build: None,
+        })
+    }
+
+    /// Modern bundles with the config, and `nomodule` legacy bundles with
+    /// `dualBuild.targets` under `dualBuild.outputDir`. The legacy config is
+    /// loaded from the same sources as the modern one.
+    pub fn dual_build(
+        config: Config,
+        mut legacy_config: Config,
+        root: PathBuf,
+        extra_plugins: Option<Vec<Arc<dyn Plugin>>>,
+    ) -> Result<Self> {
+        let dual_build = config
+            .dual_build
+            .clone()
+            .ok_or_else(|| anyhow!("dualBuild is not enabled"))?;
+        let output_dir = dual_build.output_dir.trim_matches('/').to_string();
+        legacy_config.dual_build = None;
+        legacy_config.targets = dual_build.targets.clone();
+        legacy_config.output.path = config.output.path.join(&output_dir);
+        if PublicPathKind::of(&legacy_config.public_path).is_static() {
+            legacy_config.public_path = format!("{}{}/", legacy_config.public_path, output_dir);
+        }
+        if let Some(manifest) = &mut legacy_config.manifest {
+            let base_path = manifest.base_path.trim_end_matches('/');
+            manifest.base_path = if base_path.is_empty() {
+                format!("{}/", output_dir)
+            } else {
+                format!("{}/{}/", base_path, output_dir)
+            };
+        }
+        // the legacy outputs are cleaned with the modern ones
+        legacy_config.clean = false;
+        legacy_config.copy = vec![];
+        // the critical css is inlined in the html of the modern build
+        legacy_config.critical_css = None;
+        // the import map is written and injected by the modern build
+        legacy_config.import_map = None;
+
+        let mut compiler = Self::new(vec![config, legacy_config], root, extra_plugins)?;
+        compiler.dual_build = Some(dual_build);
+        Ok(compiler)
+    }
+
+    /// Every build runs in its own thread and spreads its modules over the
+    /// shared worker pool, so the builds are interleaved instead of serial.
+    pub fn compile(&self) -> Result<()> {
+        for (compiler, clean) in self.compilers.iter().zip(&self.clean) {
+            if *clean {
+                compiler.clean_dist()?;
+            }
+        }
+        let results = thread::scope(|s| {
+            let handles = self
+                .compilers
+                .iter()
+                .map(|compiler| s.spawn(|| compiler.compile()))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        let errors = results
+            .into_iter()
+            .filter_map(|result| result.err())
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(anyhow!(errors.join("\n")));
+        }
+
+        if self.dual_build.is_some() {
+            self.write_dual_build_html()?;
+        }
+        Ok(())
+    }
+
+    fn write_dual_build_html(&self) -> Result<()> {
+        let (modern, legacy) = (&self.compilers[0], &self.compilers[1]);
+        let dual_build = self.dual_build.as_ref().unwrap();
+        let context = &modern.context;
+        let prefix = if !PublicPathKind::of(&context.config.public_path).is_static() {
+            format!("{}/", dual_build.output_dir.trim_matches('/'))
+        } else {
+            legacy.context.config.public_path.clone()
+        };
+        let legacy = LegacyScripts {
+            prefix,
+            entrypoints: entrypoint_files(&legacy.context),
+        };
+        let htmls = write_html(context, Some(&legacy))?;
+        // the report of the modern build is written again with the html
+        write_csp_report(&htmls, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use super::MultiCompiler;
+    use crate::config::Config;
+    use crate::testing::TempDir;
+    use crate::utils::output_fs::{MemoryFileSystem, OutputFileSystem};
+
+    fn read(output_fs: &MemoryFileSystem, path: &Path) -> String {
+        String::from_utf8(output_fs.read(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_dual_build() {
+        let root = TempDir::new("dual-build");
+        fs::write(
+            root.join("mako.config.json"),
+            r#"{ "dualBuild": {}, "manifest": {}, "targets": { "chrome": 100 } }"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("index.js"),
+            "const f = () => 1; console.log(f());",
+        )
+        .unwrap();
+
+        let config = Config::new(&root, None, None).unwrap();
+        let legacy_config = Config::new(&root, None, None).unwrap();
+        let mut compiler =
+            MultiCompiler::dual_build(config, legacy_config, root.to_path_buf(), None).unwrap();
+        let output_fs = Arc::new(MemoryFileSystem::new());
+        for compiler in compiler.compilers.iter_mut() {
+            Arc::get_mut(&mut compiler.context).unwrap().output_fs = output_fs.clone();
+        }
+        compiler.compile().unwrap();
+
+        let dist = root.join("dist");
+        let modern = read(&output_fs, &dist.join("index.js"));
+        let legacy = read(&output_fs, &dist.join("legacy/index.js"));
+        assert!(modern.contains("=>"));
+        assert!(!legacy.contains("=>"));
+        assert!(read(&output_fs, &dist.join("legacy/asset-manifest.json"))
+            .contains(r#""legacy/index.js": "index.js""#));
+        let html = read(&output_fs, &dist.join("index.html"));
+        assert!(html.contains(r#"<script type="module" src="/index.js"></script>"#));
+        assert!(html.contains(r#"<script nomodule src="/legacy/index.js"></script>"#));
+        let resolvers = &compiler.compilers[0].context.resolvers;
+        assert!(Arc::ptr_eq(
+            resolvers,
+            &compiler.compilers[1].context.resolvers
+        ));
+    }
+}
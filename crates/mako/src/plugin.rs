@@ -1,5 +1,5 @@
 use std::any::Any;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -11,6 +11,8 @@ use swc_core::ecma::ast::Module;
 use crate::ast::file::{Content, File};
 use crate::compiler::{Args, Compiler, Context};
 use crate::config::Config;
+use crate::dev::DevServerRoute;
+pub use crate::generate::artifacts::{GenerateArtifacts, GenerateManifest};
 use crate::generate::chunk_graph::ChunkGraph;
 use crate::generate::generate_chunks::ChunkFile;
 use crate::module::{Dependency, ModuleAst, ModuleId};
@@ -23,6 +25,17 @@ pub struct PluginLoadParam<'a> {
     pub file: &'a File,
 }
 
+/// A file split into the modules, e.g. the script, the style and the template
+/// of a single file component, see `Plugin::load_sub_modules`.
+#[derive(Debug)]
+pub struct PluginSubModules {
+    /// the content of the file itself, which imports the sub-modules with the
+    /// queries, e.g. `import "./App.sfc?type=style"`
+    pub content: Content,
+    /// query -> content of the sub-module, e.g. `type=style` -> the css
+    pub sub_modules: Vec<(String, Content)>,
+}
+
 #[derive(Debug)]
 pub struct PluginResolveIdParams {
     pub is_entry: bool,
@@ -32,6 +45,11 @@ pub struct PluginParseParam<'a> {
     pub file: &'a File,
 }
 
+pub struct PluginReplaceByDirectiveParam<'a> {
+    pub file: &'a File,
+    pub directives: &'a [String],
+}
+
 pub struct PluginTransformJsParam<'a> {
     pub handler: &'a Handler,
     pub path: &'a str,
@@ -47,6 +65,16 @@ pub struct PluginGenerateEndParams {
     pub stats: StatsJsonMap,
 }
 
+/// A chunk written with its source map file.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginEmittedSourceMap {
+    pub asset_path: PathBuf,
+    pub source_map_path: PathBuf,
+    /// with `output.debugIds`
+    pub debug_id: Option<String>,
+}
+
 pub trait Plugin: Any + Send + Sync {
     fn name(&self) -> &str;
 
@@ -62,6 +90,16 @@ pub trait Plugin: Any + Send + Sync {
         Ok(None)
     }
 
+    /// Split the file into the sub-modules, which is called for the files
+    /// not loaded by `load`, return none quickly for the other files.
+    fn load_sub_modules(
+        &self,
+        _param: &PluginLoadParam,
+        _context: &Arc<Context>,
+    ) -> Result<Option<PluginSubModules>> {
+        Ok(None)
+    }
+
     fn load_transform(
         &self,
         _content: &mut Content,
@@ -93,6 +131,17 @@ pub trait Plugin: Any + Send + Sync {
         Ok(None)
     }
 
+    /// Replace the content of the module with directives, e.g. replace a
+    /// `"use server"` module with a client proxy, the replaced content is
+    /// parsed again and its deps are analyzed instead.
+    fn replace_by_directive(
+        &self,
+        _param: &PluginReplaceByDirectiveParam,
+        _context: &Arc<Context>,
+    ) -> Result<Option<Content>> {
+        Ok(None)
+    }
+
     fn transform_js(
         &self,
         _param: &PluginTransformJsParam,
@@ -127,6 +176,17 @@ pub trait Plugin: Any + Send + Sync {
         Ok(())
     }
 
+    /// Change the chunks, the assets, the html and the manifest before they
+    /// are written, e.g. to inject a script to the html for an SSR framework.
+    /// Not called in watch mode and bundless mode.
+    fn after_generate(
+        &self,
+        _artifacts: &mut GenerateArtifacts,
+        _context: &Arc<Context>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     fn build_success(&self, _stats: &StatsJsonMap, _context: &Arc<Context>) -> Result<()> {
         Ok(())
     }
@@ -151,6 +211,16 @@ pub trait Plugin: Any + Send + Sync {
         Ok(())
     }
 
+    /// After the chunks and their source maps are written, e.g. to upload the
+    /// source maps and remove them from the output.
+    fn after_emit(
+        &self,
+        _source_maps: &[PluginEmittedSourceMap],
+        _context: &Arc<Context>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     fn watch_changes(&self, _id: &str, _event: &str, _context: &Arc<Context>) -> Result<()> {
         Ok(())
     }
@@ -196,6 +266,12 @@ pub trait Plugin: Any + Send + Sync {
     fn after_update(&self, _compiler: &Compiler) -> Result<()> {
         Ok(())
     }
+
+    /// The custom endpoints of the dev server, which are matched before the
+    /// hmr and the static handlers.
+    fn dev_server_routes(&self) -> Vec<DevServerRoute> {
+        Vec::new()
+    }
 }
 
 #[derive(Default)]
@@ -244,6 +320,20 @@ impl PluginDriver {
         Ok(None)
     }
 
+    pub fn load_sub_modules(
+        &self,
+        param: &PluginLoadParam,
+        context: &Arc<Context>,
+    ) -> Result<Option<PluginSubModules>> {
+        for plugin in &self.plugins {
+            let ret = plugin.load_sub_modules(param, context)?;
+            if ret.is_some() {
+                return Ok(ret);
+            }
+        }
+        Ok(None)
+    }
+
     pub fn parse(
         &self,
         param: &PluginParseParam,
@@ -258,6 +348,20 @@ impl PluginDriver {
         Ok(None)
     }
 
+    pub fn replace_by_directive(
+        &self,
+        param: &PluginReplaceByDirectiveParam,
+        context: &Arc<Context>,
+    ) -> Result<Option<Content>> {
+        for plugin in &self.plugins {
+            let ret = plugin.replace_by_directive(param, context)?;
+            if ret.is_some() {
+                return Ok(ret);
+            }
+        }
+        Ok(None)
+    }
+
     pub fn transform_js(
         &self,
         param: &PluginTransformJsParam,
@@ -328,6 +432,17 @@ impl PluginDriver {
         Ok(())
     }
 
+    pub fn after_generate(
+        &self,
+        artifacts: &mut GenerateArtifacts,
+        context: &Arc<Context>,
+    ) -> Result<()> {
+        for plugin in &self.plugins {
+            plugin.after_generate(artifacts, context)?;
+        }
+        Ok(())
+    }
+
     pub fn build_start(&self, context: &Arc<Context>) -> Result<()> {
         for plugin in &self.plugins {
             plugin.build_start(context)?;
@@ -353,6 +468,17 @@ impl PluginDriver {
         Ok(())
     }
 
+    pub fn after_emit(
+        &self,
+        source_maps: &[PluginEmittedSourceMap],
+        context: &Arc<Context>,
+    ) -> Result<()> {
+        for plugin in &self.plugins {
+            plugin.after_emit(source_maps, context)?;
+        }
+        Ok(())
+    }
+
     pub fn watch_changes(&self, id: &str, event: &str, context: &Arc<Context>) -> Result<()> {
         for plugin in &self.plugins {
             plugin.watch_changes(id, event, context)?;
@@ -456,4 +582,11 @@ impl PluginDriver {
         }
         Ok(())
     }
+
+    pub fn dev_server_routes(&self) -> Vec<DevServerRoute> {
+        self.plugins
+            .iter()
+            .flat_map(|plugin| plugin.dev_server_routes())
+            .collect()
+    }
 }
@@ -1,5 +1,8 @@
 pub mod async_runtime;
+pub mod build_info;
+pub mod build_manifest;
 pub mod bundless_compiler;
+pub mod cache_rules;
 pub mod case_sensitive;
 pub mod central_ensure;
 pub mod context_module;
@@ -7,17 +10,30 @@ pub mod copy;
 pub mod detect_circular_dependence;
 pub mod duplicate_package_checker;
 pub mod emotion;
+pub mod empty_module;
+pub mod eval_bailout;
 pub mod graphviz;
 pub mod hmr_runtime;
+pub mod html_entry;
 pub mod ignore;
+pub mod image;
 pub mod import;
+pub mod import_map;
 pub mod imports_checker;
+pub mod inspect;
 pub mod invalid_webpack_syntax;
+pub mod json_exports_check;
+pub mod loadable_manifest;
 pub mod manifest;
 pub mod minifish;
+pub mod peer_dependency_check;
+pub mod pre_bundle;
 pub mod progress;
+pub mod remote_css;
 pub mod require_context;
 pub mod runtime;
 pub mod ssu;
+pub mod stub;
 pub mod tree_shaking;
+pub mod type_declaration;
 pub mod wasm_runtime;
@@ -0,0 +1,164 @@
+use std::hash::Hasher;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use anyhow::Result;
+use twox_hash::XxHash64;
+
+use crate::ast::file::{Content, File, JsContent};
+use crate::compiler::{Compiler, Context};
+use crate::config::Mode;
+use crate::module::ModuleId;
+use crate::plugin::Plugin;
+
+pub const BUILD_INFO_MODULE: &str = "virtual:mako-build-info";
+
+/// Provides `virtual:mako-build-info` with the build hash, git commit, mode
+/// and build time.
+#[derive(Default)]
+pub struct BuildInfoPlugin {
+    // hash of all modules except the build info module itself
+    hash: Arc<Mutex<String>>,
+}
+
+impl BuildInfoPlugin {
+    // None if the build info module is not used
+    fn modules_hash(context: &Arc<Context>) -> Option<String> {
+        let module_graph = context.module_graph.read().unwrap();
+        let build_info_id = ModuleId::new(BUILD_INFO_MODULE.to_string());
+        if !module_graph.has_module(&build_info_id) {
+            return None;
+        }
+        let mut modules = module_graph
+            .modules()
+            .into_iter()
+            .filter(|module| module.id != build_info_id)
+            .collect::<Vec<_>>();
+        modules.sort_by(|a, b| a.id.id.cmp(&b.id.id));
+        let mut hasher: XxHash64 = Default::default();
+        for module in modules {
+            hasher.write(module.id.id.as_bytes());
+            if let Some(info) = &module.info {
+                hasher.write(info.raw.as_bytes());
+            }
+        }
+        Some(format!("{:016x}", hasher.finish()))
+    }
+}
+
+impl Plugin for BuildInfoPlugin {
+    fn name(&self) -> &str {
+        "build_info"
+    }
+
+    fn build_start(&self, context: &Arc<Context>) -> Result<()> {
+        let hash = self.hash.clone();
+        let commit = OnceLock::new();
+        context
+            .virtual_modules
+            .register(BUILD_INFO_MODULE, move |context| {
+                let commit = commit.get_or_init(|| git_commit(&context.root));
+                Ok(Content::Js(JsContent {
+                    content: render(
+                        &hash.lock().unwrap(),
+                        commit.as_deref(),
+                        &context.config.mode,
+                        chrono::Local::now().timestamp_millis(),
+                    )?,
+                    ..Default::default()
+                }))
+            });
+        Ok(())
+    }
+
+    fn after_build(&self, context: &Arc<Context>, _compiler: &Compiler) -> Result<()> {
+        // the hash is only known after all other modules are built, so the
+        // module is rebuilt with it
+        if let Some(hash) = Self::modules_hash(context) {
+            *self.hash.lock().unwrap() = hash;
+            let file = File::new(BUILD_INFO_MODULE.to_string(), context.clone());
+            let mut module = Compiler::build_module(&file, None, context.clone())?;
+            module.id.intern(&context.interner);
+            context.module_graph.write().unwrap().replace_module(module);
+        }
+        Ok(())
+    }
+
+    fn after_update(&self, compiler: &Compiler) -> Result<()> {
+        let context = &compiler.context;
+        if let Some(hash) = Self::modules_hash(context) {
+            let mut current = self.hash.lock().unwrap();
+            // the rebuild of the build info module itself won't change the hash
+            if *current != hash {
+                *current = hash;
+                context.virtual_modules.invalidate(BUILD_INFO_MODULE);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn git_commit(root: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(root)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!commit.is_empty()).then_some(commit)
+}
+
+fn render(hash: &str, commit: Option<&str>, mode: &Mode, build_time: i64) -> Result<String> {
+    Ok(format!(
+        r#"export const hash = {};
+export const commit = {};
+export const mode = {};
+export const buildTime = {};
+export default {{ hash, commit, mode, buildTime }};
+"#,
+        serde_json::to_string(hash)?,
+        serde_json::to_string(&commit)?,
+        serde_json::to_string(&mode.to_string())?,
+        build_time
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render("abc", None, &Mode::Production, 1).unwrap(),
+            r#"export const hash = "abc";
+export const commit = null;
+export const mode = "production";
+export const buildTime = 1;
+export default { hash, commit, mode, buildTime };
+"#
+        );
+        assert!(render("abc", Some("123"), &Mode::Development, 1)
+            .unwrap()
+            .contains(r#"export const commit = "123";"#));
+    }
+
+    #[test]
+    fn test_build_info() {
+        let compiler = setup_compiler("test/build/build-info", false);
+        compiler.compile().unwrap();
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let module = module_graph
+            .get_module(&ModuleId::new(BUILD_INFO_MODULE.to_string()))
+            .unwrap();
+        let raw = &module.info.as_ref().unwrap().raw;
+        let hash = BuildInfoPlugin::modules_hash(&compiler.context).unwrap();
+        assert!(raw.contains(&format!(r#"export const hash = "{}";"#, hash)));
+        assert!(raw.contains(r#"export const mode = "production";"#));
+    }
+}
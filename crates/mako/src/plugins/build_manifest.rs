@@ -0,0 +1,363 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::hash::Hasher;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use twox_hash::XxHash64;
+
+use crate::compiler::Context;
+use crate::config::Config;
+use crate::plugin::Plugin;
+use crate::stats::StatsJsonMap;
+
+/// Writes the deployment metadata of the output files with `buildManifest`,
+/// the content hash, the size and the entrypoints and chunks referencing each
+/// file, and prints the delta from the manifest of the previous build.
+pub struct BuildManifestPlugin {
+    // read before the output path is cleaned, it may be the manifest of the
+    // last build in place
+    previous: Option<BuildManifest>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq)]
+pub struct BuildManifest {
+    /// hash of the names and the hashes of all assets
+    pub hash: String,
+    /// file name relative to `output.path` -> asset
+    pub assets: BTreeMap<String, BuildManifestAsset>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq)]
+pub struct BuildManifestAsset {
+    pub hash: String,
+    pub size: u64,
+    /// names of the entries which load the asset, sync or async
+    #[serde(default)]
+    pub entrypoints: BTreeSet<String>,
+    /// ids of the chunks the asset belongs to
+    #[serde(default)]
+    pub chunks: BTreeSet<String>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct BuildManifestDelta {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    /// the total size of the assets minus the previous one
+    pub size: i64,
+}
+
+pub(crate) fn default_build_manifest_file_name() -> String {
+    "build-manifest.json".to_string()
+}
+
+impl BuildManifestPlugin {
+    pub fn new(config: &Config, root: &Path) -> Result<Self> {
+        let previous = match config
+            .build_manifest
+            .as_ref()
+            .and_then(|build_manifest| build_manifest.previous.as_ref())
+        {
+            // the first build has no previous manifest, all assets are added
+            Some(path) if root.join(path).exists() => {
+                let path = root.join(path);
+                let content = fs::read_to_string(&path)?;
+                Some(
+                    serde_json::from_str(&content)
+                        .map_err(|e| anyhow!("Invalid build manifest {}: {}", path.display(), e))?,
+                )
+            }
+            Some(_) => Some(BuildManifest::default()),
+            None => None,
+        };
+        Ok(Self { previous })
+    }
+}
+
+impl Plugin for BuildManifestPlugin {
+    fn name(&self) -> &str {
+        "build_manifest"
+    }
+
+    fn build_success(&self, _stats: &StatsJsonMap, context: &Arc<Context>) -> Result<()> {
+        // the chunks of the watch mode are served from the memory
+        if context.args.watch {
+            return Ok(());
+        }
+        let Some(config) = &context.config.build_manifest else {
+            return Ok(());
+        };
+        let manifest = generate_build_manifest(&config.file_name, context)?;
+        let output_path = context.config.output.path.join(&config.file_name);
+        context.output_fs.write(
+            &output_path,
+            serde_json::to_string_pretty(&manifest)?.as_bytes(),
+        )?;
+        if let Some(previous) = &self.previous {
+            let delta = BuildManifestDelta::new(previous, &manifest);
+            if !context.config.quiet {
+                delta.print();
+            }
+            *context.stats_info.build_manifest_delta.lock().unwrap() = Some(delta);
+        }
+        Ok(())
+    }
+}
+
+/// The manifest of all files under `output.path` except the manifest itself,
+/// the assets and their references are sorted so it's stable from build to
+/// build.
+fn generate_build_manifest(file_name: &str, context: &Arc<Context>) -> Result<BuildManifest> {
+    let references = asset_references(context);
+    let output_path = &context.config.output.path;
+    let mut manifest = BuildManifest::default();
+    for path in context.output_fs.list(output_path)? {
+        let name = path
+            .strip_prefix(output_path)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        if name == file_name {
+            continue;
+        }
+        let content = context.output_fs.read(&path)?;
+        let mut hasher: XxHash64 = Default::default();
+        hasher.write(&content);
+        // the precompressed files are referenced as the original ones
+        let original = name
+            .strip_suffix(".gz")
+            .or_else(|| name.strip_suffix(".br"))
+            .unwrap_or(&name);
+        let (entrypoints, chunks) = references
+            .get(&name)
+            .or_else(|| references.get(original))
+            .cloned()
+            .unwrap_or_default();
+        manifest.assets.insert(
+            name,
+            BuildManifestAsset {
+                hash: format!("{:016x}", hasher.finish()),
+                size: content.len() as u64,
+                entrypoints,
+                chunks,
+            },
+        );
+    }
+    let mut hasher: XxHash64 = Default::default();
+    for (name, asset) in &manifest.assets {
+        hasher.write(name.as_bytes());
+        hasher.write(asset.hash.as_bytes());
+    }
+    manifest.hash = format!("{:016x}", hasher.finish());
+    Ok(manifest)
+}
+
+// file name -> (entrypoints, chunks), the files of the chunks and the emitted
+// assets of the modules in them
+fn asset_references(
+    context: &Arc<Context>,
+) -> HashMap<String, (BTreeSet<String>, BTreeSet<String>)> {
+    let chunk_graph = context.chunk_graph.read().unwrap();
+    // the aliased chunks share the files of the chunks they alias
+    let aliases = context
+        .stats_info
+        .get_chunk_aliases()
+        .into_iter()
+        .map(|alias| (alias.chunk_id, alias.alias_of))
+        .collect::<HashMap<_, _>>();
+    let files_chunk_id = |id: &str| aliases.get(id).cloned().unwrap_or_else(|| id.to_string());
+
+    let mut chunk_entrypoints: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for entrypoint in chunk_graph.entrypoints() {
+        let chunks = entrypoint.chunks.iter().flat_map(|chunk_id| {
+            std::iter::once(chunk_id.clone())
+                .chain(chunk_graph.installable_descendants_chunk(chunk_id))
+        });
+        for chunk_id in chunks {
+            chunk_entrypoints
+                .entry(files_chunk_id(&chunk_id.id))
+                .or_default()
+                .insert(entrypoint.name.clone());
+        }
+    }
+
+    let mut references: HashMap<String, (BTreeSet<String>, BTreeSet<String>)> = HashMap::new();
+    let mut add = |file: String, chunk_id: &str| {
+        let chunk_id = files_chunk_id(chunk_id);
+        let reference = references.entry(file).or_default();
+        if let Some(entrypoints) = chunk_entrypoints.get(&chunk_id) {
+            reference.0.extend(entrypoints.iter().cloned());
+        }
+        reference.1.insert(chunk_id);
+    };
+    for asset in context.stats_info.get_assets() {
+        if !asset.chunk_id.is_empty() {
+            add(asset.hashname, &asset.chunk_id);
+        }
+    }
+    for (file, origin_path) in context.emitted_assets() {
+        for chunk in chunk_graph.get_chunks() {
+            if chunk
+                .get_modules()
+                .iter()
+                .any(|module_id| module_id.id.split('?').next() == Some(origin_path.as_str()))
+            {
+                add(file.clone(), &chunk.id.id);
+            }
+        }
+    }
+    references
+}
+
+impl BuildManifestDelta {
+    pub fn new(previous: &BuildManifest, current: &BuildManifest) -> Self {
+        let mut delta = Self::default();
+        for (name, asset) in &current.assets {
+            match previous.assets.get(name) {
+                None => delta.added.push(name.clone()),
+                Some(previous) if previous.hash != asset.hash => delta.changed.push(name.clone()),
+                _ => {}
+            }
+        }
+        delta.removed = previous
+            .assets
+            .keys()
+            .filter(|name| !current.assets.contains_key(*name))
+            .cloned()
+            .collect();
+        let total_size =
+            |manifest: &BuildManifest| manifest.assets.values().map(|a| a.size as i64).sum::<i64>();
+        delta.size = total_size(current) - total_size(previous);
+        delta
+    }
+
+    fn print(&self) {
+        println!(
+            "Build manifest: {} added, {} changed, {} removed",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len()
+        );
+        for name in &self.added {
+            println!("  {} {}", "+".green(), name);
+        }
+        for name in &self.changed {
+            println!("  {} {}", "~".yellow(), name);
+        }
+        for name in &self.removed {
+            println!("  {} {}", "-".red(), name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::compiler::Compiler;
+    use crate::testing::compile_fixture;
+
+    fn build(root: &Path, previous: Option<&str>) -> (Compiler, BuildManifest) {
+        let overrides = json!({ "minify": false, "buildManifest": { "previous": previous } });
+        let compiler = compile_fixture(root, Some(&overrides.to_string())).unwrap();
+        let context = &compiler.context;
+        let manifest = context
+            .output_fs
+            .read(&context.config.output.path.join("build-manifest.json"))
+            .unwrap();
+        let manifest = serde_json::from_slice(&manifest).unwrap();
+        (compiler, manifest)
+    }
+
+    #[test]
+    fn test_build_manifest() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/build-manifest");
+        let (compiler, manifest) = build(&root, None);
+        let context = &compiler.context;
+        let content = context
+            .output_fs
+            .read(&context.config.output.path.join("index.js"))
+            .unwrap();
+        let index = &manifest.assets["index.js"];
+        assert_eq!(index.size, content.len() as u64);
+        assert_eq!(index.hash.len(), 16);
+        assert_eq!(index.entrypoints, BTreeSet::from(["index".to_string()]));
+        assert_eq!(index.chunks, BTreeSet::from(["src/index.js".to_string()]));
+        // the async chunk is referenced by the entry which loads it
+        let (name, lazy) = manifest
+            .assets
+            .iter()
+            .find(|(name, _)| name.contains("lazy") && name.ends_with(".js"))
+            .unwrap();
+        assert_eq!(
+            lazy.entrypoints,
+            BTreeSet::from(["index".to_string()]),
+            "{}",
+            name
+        );
+        // the emitted asset is referenced by the chunk of its module
+        let (_, image) = manifest
+            .assets
+            .iter()
+            .find(|(name, _)| name.ends_with(".png"))
+            .unwrap();
+        assert_eq!(image.entrypoints, BTreeSet::from(["index".to_string()]));
+        assert!(!manifest.assets.contains_key("build-manifest.json"));
+
+        // stable between builds
+        let (_, again) = build(&root, None);
+        assert_eq!(manifest, again);
+    }
+
+    #[test]
+    fn test_build_manifest_previous() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/build-manifest");
+        let (compiler, _) = build(&root, Some("previous-build-manifest.json"));
+        let previous: BuildManifest = serde_json::from_str(
+            &fs::read_to_string(root.join("previous-build-manifest.json")).unwrap(),
+        )
+        .unwrap();
+        let context = &compiler.context;
+        let current: BuildManifest = serde_json::from_slice(
+            &context
+                .output_fs
+                .read(&context.config.output.path.join("build-manifest.json"))
+                .unwrap(),
+        )
+        .unwrap();
+        let delta = BuildManifestDelta::new(&previous, &current);
+        assert_eq!(delta.changed, vec!["index.js".to_string()]);
+        assert_eq!(delta.removed, vec!["removed.js".to_string()]);
+        assert!(delta.added.iter().any(|name| name.ends_with(".png")));
+        assert!(!delta.added.contains(&"index.js".to_string()));
+    }
+
+    #[test]
+    fn test_build_manifest_invalid_previous() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/build-manifest");
+        let mut config = Config::new(&root, None, None).unwrap();
+        config.build_manifest = Some(crate::config::BuildManifestConfig {
+            file_name: default_build_manifest_file_name(),
+            previous: Some("src/index.js".into()),
+        });
+        let err = BuildManifestPlugin::new(&config, &root).err().unwrap();
+        assert!(
+            err.to_string().contains("Invalid build manifest"),
+            "{}",
+            err
+        );
+    }
+}
@@ -1,6 +1,4 @@
-use std::collections::HashMap;
-use std::fs;
-use std::fs::create_dir_all;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -10,22 +8,29 @@ use rayon::prelude::*;
 use swc_core::base::try_with_handler;
 use swc_core::common::errors::HANDLER;
 use swc_core::common::GLOBALS;
+use swc_core::css::ast::{ImportHref, ImportPrelude, Str as CssStr};
+use swc_core::css::visit::{VisitMut as CssVisitMut, VisitMutWith as _};
+use swc_core::ecma::ast::{CallExpr, Expr, ExprOrSpread, Lit, Str};
 use swc_core::ecma::transforms::base::fixer::fixer;
 use swc_core::ecma::transforms::base::helpers::{Helpers, HELPERS};
 use swc_core::ecma::transforms::base::hygiene;
 use swc_core::ecma::transforms::base::hygiene::hygiene_with_config;
 use swc_core::ecma::transforms::module::import_analysis::import_analyzer;
 use swc_core::ecma::transforms::module::util::ImportInterop;
-use swc_core::ecma::visit::VisitMutWith;
+use swc_core::ecma::visit::{VisitMut, VisitMutWith};
 use tracing::warn;
 
 use crate::ast::js_ast::JsAst;
+use crate::ast::utils::is_dynamic_import;
+use crate::build::load::JS_EXTENSIONS;
 use crate::compiler::{Args, Context};
 use crate::config::Config;
-use crate::module::{ModuleAst, ModuleId};
-use crate::plugin::{Plugin, PluginTransformJsParam};
+use crate::module::{ImportMode, Module, ModuleAst, ModuleId};
+use crate::plugin::{NextBuildParam, Plugin, PluginTransformJsParam};
 use crate::visitors::dep_replacer::{DepReplacer, DependenciesToReplace, ResolvedReplaceInfo};
 use crate::visitors::dynamic_import::DynamicImport;
+use crate::visitors::import_meta_check::preserve_import_meta;
+use crate::visitors::meta_url_replacer::MetaUrlReplacer;
 
 pub struct BundlessCompiler {
     context: Arc<Context>,
@@ -47,37 +52,24 @@ impl BundlessCompiler {
                 let module_graph = context.module_graph.read().unwrap();
                 let deps = module_graph.get_dependencies(module_id);
 
-                let module_dist_path = to_dist_path(&module_id.id, context)
-                    .parent()
-                    .unwrap()
-                    .to_path_buf();
+                let module = module_graph.get_module(module_id).unwrap();
+                // the externalized modules of preserveModules
+                let Some(module_dist_path) = dist_path(module, context) else {
+                    return Ok(());
+                };
+                let module_dist_path = module_dist_path.parent().unwrap().to_path_buf();
 
                 let resolved_deps = deps
                     .clone()
                     .into_iter()
-                    // .map(|(id, dep)| (dep.source.clone(), id.generate(context)))
                     .map(|(id, dep)| {
-                        let dep_dist_path = to_dist_path(&id.id, context);
-
-                        let rel_path =
-                            diff_paths(&dep_dist_path, &module_dist_path).ok_or_else(|| {
-                                anyhow!(
-                                    "failed to get relative path from {:?} to {:?}",
-                                    dep_dist_path,
-                                    module_dist_path
-                                )
-                            })?;
-
-                        let rel_path = normalize_extension(rel_path);
-
-                        let replacement: String = {
-                            let mut to_path = rel_path.to_str().unwrap().to_string();
-                            if to_path.starts_with("./") || to_path.starts_with("../") {
-                                to_path
-                            } else {
-                                to_path.insert_str(0, "./");
-                                to_path
+                        let dep_module = module_graph.get_module(id).unwrap();
+                        let replacement = match dist_path(dep_module, context) {
+                            Some(dep_dist_path) => {
+                                relative_source(&dep_dist_path, &module_dist_path)?
                             }
+                            // the imports of the externalized modules are kept
+                            None => dep.source.clone(),
                         };
 
                         Ok((
@@ -86,6 +78,7 @@ impl BundlessCompiler {
                                 chunk_id: None,
                                 to_replace_source: replacement,
                                 resolved_module_id: id.clone(),
+                                import_mode: ImportMode::default(),
                             },
                         ))
                     })
@@ -108,14 +101,22 @@ impl BundlessCompiler {
                     missing: info.deps.missing_deps.clone(),
                 };
 
-                if let ModuleAst::Script(ast) = ast {
-                    transform_js_generate(
-                        &module.id,
-                        context,
-                        ast,
-                        &deps_to_replace,
-                        module.is_entry,
-                    );
+                match ast {
+                    ModuleAst::Script(ast) => {
+                        transform_js_generate(
+                            &module.id,
+                            context,
+                            ast,
+                            &deps_to_replace,
+                            module.is_entry,
+                        );
+                    }
+                    ModuleAst::Css(ast) if context.config.output.preserve_modules => {
+                        ast.ast.visit_mut_with(&mut CSSImportReplacer {
+                            to_replace: &deps_to_replace,
+                        });
+                    }
+                    _ => {}
                 }
 
                 Ok(())
@@ -124,33 +125,28 @@ impl BundlessCompiler {
         Ok(())
     }
 
-    fn write_to_dist<P: AsRef<std::path::Path>, C: AsRef<[u8]>>(&self, filename: P, content: C) {
-        let to = self.context.config.output.path.join(&filename);
-        let to = normalize_extension(to);
-
+    fn write_to_dist<C: AsRef<[u8]>>(&self, to: PathBuf, content: C) {
         self.context
             .plugin_driver
             .before_write_fs(&to, content.as_ref(), &self.context)
             .unwrap();
 
         if !self.context.config.output.skip_write {
-            fs::write(to, content).unwrap();
+            self.context.output_fs.write(&to, content.as_ref()).unwrap();
         }
     }
 
     pub(crate) fn generate(&self) -> Result<()> {
         self.transform_all()?;
 
+        if self.context.config.output.preserve_modules {
+            return self.generate_preserved_modules();
+        }
+
         let mg = self.context.module_graph.read().unwrap();
 
         let ids = mg.get_module_ids();
 
-        // TODO try tokio fs later
-        ids.iter().for_each(|id| {
-            let target = to_dist_path(&id.id, &self.context);
-            create_dir_all(target.parent().unwrap()).unwrap();
-        });
-
         ids.par_iter().for_each(|id| {
             let module = mg.get_module(id).expect("module not exits");
 
@@ -163,13 +159,13 @@ impl BundlessCompiler {
                         // todo: generate resolved AJSON
                     } else {
                         let code = js_ast.generate(self.context.clone()).unwrap().code;
-                        let target = to_dist_path(&id.id, &self.context);
+                        let target = normalize_extension(to_dist_path(&id.id, &self.context));
                         self.write_to_dist(target, code);
                     }
                 }
                 ModuleAst::Css(_style) => {}
                 ModuleAst::None => {
-                    let target = to_dist_path(&id.id, &self.context);
+                    let target = normalize_extension(to_dist_path(&id.id, &self.context));
                     self.write_to_dist(target, &info.raw);
                 }
             }
@@ -177,15 +173,64 @@ impl BundlessCompiler {
 
         Ok(())
     }
+
+    // one file per module, the other files, e.g. the assets, are copied with
+    // the assets referenced by the css
+    fn generate_preserved_modules(&self) -> Result<()> {
+        let mg = self.context.module_graph.read().unwrap();
+
+        let mut copies = BTreeMap::new();
+        for (origin_path, output_path) in self.context.assets_info.lock().unwrap().iter() {
+            copies.insert(
+                self.context.config.output.path.join(output_path),
+                PathBuf::from(origin_path),
+            );
+        }
+
+        for module in mg.modules() {
+            let Some(target) = dist_path(module, &self.context) else {
+                continue;
+            };
+            let info = module.info.as_ref().expect("module info missing");
+            match (preserved_kind(module), &info.ast) {
+                (PreservedKind::Script, ModuleAst::Script(js_ast)) => {
+                    let code = js_ast.generate(self.context.clone())?.code;
+                    self.write_to_dist(target, code);
+                }
+                (PreservedKind::Css, ModuleAst::Css(css_ast)) => {
+                    let code = css_ast.generate(self.context.clone())?.code;
+                    self.write_to_dist(target, code);
+                }
+                _ => {
+                    copies.insert(target, info.file.pathname.clone());
+                }
+            }
+        }
+
+        for (to, from) in copies {
+            let content = std::fs::read(&from)
+                .map_err(|e| anyhow!("failed to copy {:?} to {:?}: {}", from, to, e))?;
+            self.write_to_dist(to, content);
+        }
+
+        Ok(())
+    }
 }
 
-pub struct BundlessCompilerPlugin {}
+pub struct BundlessCompilerPlugin {
+    pub preserve_modules: bool,
+}
 
 impl Plugin for BundlessCompilerPlugin {
     fn name(&self) -> &str {
         "bundless_compiler"
     }
 
+    // node_modules are externalized with preserveModules
+    fn next_build(&self, next_build_param: &NextBuildParam) -> bool {
+        !(self.preserve_modules && next_build_param.next_file.is_under_node_modules)
+    }
+
     fn modify_config(&self, config: &mut Config, root: &Path, _args: &Args) -> Result<()> {
         if config.output.preserve_modules {
             let preserve_path = config.output.preserve_modules_root.clone();
@@ -245,8 +290,20 @@ fn transform_js_generate(
                             };
                             ast.ast.visit_mut_with(&mut dep_replacer);
 
-                            let mut dynamic_import = DynamicImport::new(context.clone(), dep_map);
-                            ast.ast.visit_mut_with(&mut dynamic_import);
+                            if context.config.output.preserve_modules {
+                                ast.ast.visit_mut_with(&mut RelativeDynamicImport {
+                                    to_replace: dep_map,
+                                });
+                            } else {
+                                let mut dynamic_import =
+                                    DynamicImport::new(context.clone(), dep_map);
+                                ast.ast.visit_mut_with(&mut dynamic_import);
+                            }
+
+                            // the targets don't support import.meta
+                            if !preserve_import_meta(&context.config) {
+                                ast.ast.visit_mut_with(&mut MetaUrlReplacer {});
+                            }
 
                             ast.ast
                                 .visit_mut_with(&mut hygiene_with_config(hygiene::Config {
@@ -317,3 +374,179 @@ fn normalize_extension(to: PathBuf) -> PathBuf {
     }
     to
 }
+
+/// How a module is emitted with preserveModules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreservedKind {
+    Script,
+    Css,
+    /// copied as is, e.g. the assets and the json
+    Copy,
+    /// not emitted and the imports of it are kept, e.g. node_modules
+    External,
+}
+
+fn preserved_kind(module: &Module) -> PreservedKind {
+    let Some(info) = module.info.as_ref() else {
+        return PreservedKind::External;
+    };
+    if info.external.is_some() {
+        return PreservedKind::External;
+    }
+    match &info.ast {
+        ModuleAst::Css(_) => PreservedKind::Css,
+        ModuleAst::Script(_)
+            if JS_EXTENSIONS.contains(&info.file.extname.as_str())
+                || info.file.has_param("asmodule") =>
+        {
+            PreservedKind::Script
+        }
+        _ => PreservedKind::Copy,
+    }
+}
+
+/// The emitted file of a module, or `None` if it's externalized.
+fn dist_path(module: &Module, context: &Arc<Context>) -> Option<PathBuf> {
+    if !context.config.output.preserve_modules {
+        return Some(normalize_extension(to_dist_path(&module.id.id, context)));
+    }
+    // the query is dropped, e.g. `a.module.css?modules` => `a.module.css`
+    let pathname = &module.info.as_ref()?.file.pathname;
+    let to = to_dist_path(pathname.to_string_lossy(), context);
+    match preserved_kind(module) {
+        PreservedKind::External => None,
+        PreservedKind::Script => Some(preserved_script_path(to)),
+        PreservedKind::Css | PreservedKind::Copy => Some(to),
+    }
+}
+
+// e.g. `a.ts` => `a.js`, `a.module.css?asmodule` => `a.module.css.js`
+fn preserved_script_path(to: PathBuf) -> PathBuf {
+    match to.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if JS_EXTENSIONS.contains(&ext) => to.with_extension("js"),
+        _ => {
+            let mut to = to.into_os_string();
+            to.push(".js");
+            PathBuf::from(to)
+        }
+    }
+}
+
+/// The import source of `to` in the files of `from_dir`, e.g. `./a.js`.
+pub fn relative_source(to: &Path, from_dir: &Path) -> Result<String> {
+    let rel_path = diff_paths(to, from_dir).ok_or_else(|| {
+        anyhow!(
+            "failed to get relative path from {:?} to {:?}",
+            to,
+            from_dir
+        )
+    })?;
+    let to_path = rel_path.to_string_lossy().replace('\\', "/");
+    if to_path.starts_with("./") || to_path.starts_with("../") {
+        Ok(to_path)
+    } else {
+        Ok(format!("./{}", to_path))
+    }
+}
+
+/// The output name of an asset with preserveModules, which mirrors the source
+/// tree like the modules.
+pub fn preserved_asset_name(path: &Path, context: &Arc<Context>) -> String {
+    let to = to_dist_path(path.to_string_lossy(), context);
+    to.strip_prefix(&context.config.output.path)
+        .unwrap_or(&to)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+// `import("./a.ts")` => `import("./a.js")`, which loads the emitted file
+// natively instead of the chunks of the runtime
+struct RelativeDynamicImport<'a> {
+    to_replace: &'a DependenciesToReplace,
+}
+
+impl VisitMut for RelativeDynamicImport<'_> {
+    fn visit_mut_call_expr(&mut self, call_expr: &mut CallExpr) {
+        if is_dynamic_import(call_expr)
+            && let Some(ExprOrSpread {
+                expr: box Expr::Lit(Lit::Str(source)),
+                ..
+            }) = call_expr.args.first_mut()
+            && let Some(replacement) = self.to_replace.resolved.get(source.value.as_ref())
+        {
+            let span = source.span;
+            *source = Str::from(replacement.to_replace_source.clone());
+            source.span = span;
+        }
+        call_expr.visit_mut_children_with(self);
+    }
+}
+
+// `@import "@/b.css"` => `@import "../b.css"`, the css files are emitted one by one
+struct CSSImportReplacer<'a> {
+    to_replace: &'a DependenciesToReplace,
+}
+
+impl CssVisitMut for CSSImportReplacer<'_> {
+    fn visit_mut_import_prelude(&mut self, prelude: &mut ImportPrelude) {
+        if let box ImportHref::Str(href) = &mut prelude.href
+            && let Some(replacement) = self.to_replace.resolved.get(href.value.as_ref())
+        {
+            *href = CssStr {
+                span: href.span,
+                value: replacement.to_replace_source.clone().into(),
+                raw: None,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_preserve_modules() {
+        let compiler = setup_compiler("test/build/preserve-modules", false);
+        compiler.compile().unwrap();
+        let output_path = &compiler.context.config.output.path;
+        let read = |name: &str| {
+            std::fs::read_to_string(output_path.join(name))
+                .unwrap_or_else(|_| panic!("{} not emitted", name))
+        };
+
+        let index = read("index.js");
+        assert!(index.contains(r#"from "./utils/math.js""#), "{}", index);
+        assert!(index.contains(r#"import "./style.css""#), "{}", index);
+        assert!(index.contains(r#"from "./logo.png""#), "{}", index);
+        assert!(
+            index.contains(r#"from "./button.module.css.js""#),
+            "{}",
+            index
+        );
+        // node_modules are externalized
+        assert!(index.contains(r#"from "dep""#), "{}", index);
+        assert!(!output_path.join("node_modules").exists());
+        // loaded natively instead of by the runtime
+        assert!(index.contains(r#"import("./lazy.js")"#), "{}", index);
+        assert!(!index.contains("__mako_require__"), "{}", index);
+
+        assert!(read("utils/math.js").contains("export const add"));
+        assert!(read("lazy.js").contains("export default"));
+
+        let style = read("style.css");
+        assert!(style.contains(r#"@import "./utils/base.css""#), "{}", style);
+        assert!(style.contains("url(./utils/bg.png)"), "{}", style);
+        assert!(read("utils/base.css").contains(".base"));
+        assert!(output_path.join("utils/bg.png").exists());
+        assert!(output_path.join("logo.png").exists());
+
+        let button = read("button.module.css.js");
+        assert!(
+            button.contains(r#"import "./button.module.css""#),
+            "{}",
+            button
+        );
+        assert!(read("button.module.css").contains("color: blue"));
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use anyhow::Result;
+use glob_match::glob_match;
+use serde::Serialize;
+
+use crate::compiler::Context;
+use crate::config::{CacheRule, CacheRulesConfig};
+use crate::plugin::Plugin;
+use crate::stats::StatsJsonMap;
+
+const HEADERS_FILE_NAME: &str = "_headers";
+
+/// Writes the cache rules of the output files with `cacheRules` for the CDN,
+/// the files whose names have the content hashes as they are emitted are
+/// `immutable`, and the others, e.g. the html, the entries without the hashes
+/// and the manifests, are `revalidate`.
+pub struct CacheRulesPlugin {}
+
+#[derive(Serialize, Default, Debug, PartialEq, Eq)]
+pub struct CacheRules {
+    /// file name relative to `output.path` -> rule
+    pub assets: BTreeMap<String, CacheRulesAsset>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheRulesAsset {
+    pub rule: CacheRule,
+    pub cache_control: String,
+}
+
+pub(crate) fn default_cache_rules_file_name() -> String {
+    "cache-rules.json".to_string()
+}
+
+impl Plugin for CacheRulesPlugin {
+    fn name(&self) -> &str {
+        "cache_rules"
+    }
+
+    fn build_success(&self, _stats: &StatsJsonMap, context: &Arc<Context>) -> Result<()> {
+        // the chunks of the watch mode are served from the memory
+        if context.args.watch {
+            return Ok(());
+        }
+        let Some(config) = &context.config.cache_rules else {
+            return Ok(());
+        };
+        let cache_rules = generate_cache_rules(config, context)?;
+        let output_path = &context.config.output.path;
+        context.output_fs.write(
+            &output_path.join(&config.file_name),
+            serde_json::to_string_pretty(&cache_rules)?.as_bytes(),
+        )?;
+        if config.headers {
+            context.output_fs.write(
+                &output_path.join(HEADERS_FILE_NAME),
+                render_headers(&cache_rules).as_bytes(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn generate_cache_rules(config: &CacheRulesConfig, context: &Arc<Context>) -> Result<CacheRules> {
+    let output_path = &context.config.output.path;
+    let mut cache_rules = CacheRules::default();
+    for path in context.output_fs.list(output_path)? {
+        let name = path
+            .strip_prefix(output_path)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        if name == config.file_name || (config.headers && name == HEADERS_FILE_NAME) {
+            continue;
+        }
+        let rule = cache_rule(&name, config, context);
+        cache_rules.assets.insert(
+            name,
+            CacheRulesAsset {
+                rule,
+                cache_control: rule.cache_control().to_string(),
+            },
+        );
+    }
+    Ok(cache_rules)
+}
+
+fn cache_rule(name: &str, config: &CacheRulesConfig, context: &Context) -> CacheRule {
+    if let Some(rule) = config
+        .overrides
+        .iter()
+        .find(|o| glob_match(o.pattern.trim_start_matches("./"), name))
+    {
+        return rule.rule;
+    }
+    // the precompressed files are named after the original ones
+    let original = name
+        .strip_suffix(".gz")
+        .or_else(|| name.strip_suffix(".br"))
+        .unwrap_or(name);
+    if context.stats_info.is_content_hashed(original) {
+        CacheRule::Immutable
+    } else {
+        CacheRule::Revalidate
+    }
+}
+
+// https://docs.netlify.com/routing/headers/
+fn render_headers(cache_rules: &CacheRules) -> String {
+    let mut headers = String::new();
+    for (name, asset) in &cache_rules.assets {
+        let _ = write!(
+            headers,
+            "/{}\n  Cache-Control: {}\n",
+            name, asset.cache_control
+        );
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::testing::build_fixture;
+
+    const FILES: &[(&str, &str)] = &[
+        (
+            "src/index.ts",
+            "import logo from './logo.png';\nimport('./lazy');\nconsole.log(logo);",
+        ),
+        ("src/lazy.ts", "export default 'lazy';"),
+        ("src/logo.png", "png"),
+    ];
+
+    #[test]
+    fn test_cache_rules() {
+        let artifacts = build_fixture(
+            Some(
+                r#"{
+                    "mode": "production",
+                    "moduleIdStrategy": "named",
+                    "inlineLimit": 0,
+                    "manifest": {},
+                    "cacheRules": { "overrides": [{ "pattern": "**/*.map", "rule": "revalidate" }] }
+                }"#,
+            ),
+            FILES,
+        )
+        .unwrap();
+        let cache_rules: serde_json::Value =
+            serde_json::from_str(&artifacts.asset("cache-rules.json").unwrap()).unwrap();
+        // the hashes in the names are not stable
+        let hash = regex::Regex::new(r"\.[0-9a-f]{8}\.").unwrap();
+        let rules = cache_rules["assets"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(name, asset)| {
+                (
+                    hash.replace(name, ".[hash].").to_string(),
+                    asset["rule"].as_str().unwrap(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            rules,
+            BTreeMap::from(
+                [
+                    ("asset-manifest.json", "revalidate"),
+                    ("index.[hash].js", "immutable"),
+                    ("index.[hash].js.map", "revalidate"),
+                    ("logo.[hash].png", "immutable"),
+                    ("src_lazy_ts-async.[hash].js", "immutable"),
+                    ("src_lazy_ts-async.[hash].js.map", "revalidate"),
+                ]
+                .map(|(name, rule)| (name.to_string(), rule))
+            )
+        );
+        assert_eq!(
+            cache_rules["assets"]["asset-manifest.json"]["cacheControl"],
+            "no-cache"
+        );
+    }
+
+    #[test]
+    fn test_cache_rules_without_hash() {
+        let artifacts = build_fixture(
+            Some(
+                r#"{
+                    "mode": "production",
+                    "hash": false,
+                    "inlineLimit": 0,
+                    "output": { "assetFilename": "[name].[ext]" },
+                    "cacheRules": { "headers": true }
+                }"#,
+            ),
+            FILES,
+        )
+        .unwrap();
+        let headers = artifacts.asset("_headers").unwrap();
+        assert!(
+            headers.contains("/index.js\n  Cache-Control: no-cache\n"),
+            "{}",
+            headers
+        );
+        assert!(
+            headers.contains("/logo.png\n  Cache-Control: no-cache\n"),
+            "{}",
+            headers
+        );
+        assert!(!headers.contains("immutable"), "{}", headers);
+        assert!(!headers.contains("cache-rules.json"), "{}", headers);
+    }
+}
@@ -7,6 +7,7 @@ use anyhow::{anyhow, Result};
 
 use crate::ast::file::Content;
 use crate::compiler::Context;
+use crate::diagnostics::{Diagnostic, DiagnosticCode, Severity};
 use crate::plugin::{Plugin, PluginLoadParam};
 
 pub struct CaseSensitivePlugin {
@@ -83,11 +84,17 @@ impl Plugin for CaseSensitivePlugin {
         if self.is_checkable(load_param, root) {
             let dist_path = self.check_case_sensitive(load_param.file.path.as_path(), root);
             if !dist_path.is_empty() {
-                return Err(anyhow!(
+                let message = format!(
                     "{} does not match the corresponding path on disk [{}]",
-                    load_param.file.path.to_string_lossy().to_string(),
+                    load_param.file.path.to_string_lossy(),
                     dist_path
-                ));
+                );
+                let diagnostic = Diagnostic::new(DiagnosticCode::CaseMismatch, message.clone())
+                    .paths(vec![load_param.file.path.clone()]);
+                // fails the loading at once instead of after the build
+                if context.diagnostics.report(diagnostic, context) == Some(Severity::Error) {
+                    return Err(anyhow!(message));
+                }
             }
         }
         Ok(None)
@@ -1,24 +1,32 @@
 use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::Result;
 use glob::glob;
-use swc_core::common::{Mark, DUMMY_SP};
+use percent_encoding::utf8_percent_encode;
+use swc_core::common::errors::HANDLER;
+use swc_core::common::{Mark, Span, Spanned, DUMMY_SP};
 use swc_core::ecma::ast::{
     BinExpr, BinaryOp, CallExpr, Expr, ExprOrSpread, Lit, ParenExpr, TplElement,
 };
 use swc_core::ecma::utils::{member_expr, quote_ident, quote_str, ExprExt, ExprFactory};
 use swc_core::ecma::visit::{VisitMut, VisitMutWith};
 
+use crate::ast::error::{code_frame, ErrorSpan};
 use crate::ast::file::{win_path, Content, JsContent};
 use crate::ast::utils::{is_commonjs_require, is_dynamic_import};
 use crate::ast::DUMMY_CTXT;
 use crate::build::load::JS_EXTENSIONS;
 use crate::compiler::Context;
+use crate::config::{matched_ignore_rule, FullyDynamicImportConfig};
+use crate::diagnostics::{Diagnostic, DiagnosticCode, Severity};
+use crate::module::{relative_to_root, ImportMode};
 use crate::plugin::{Plugin, PluginLoadParam};
 use crate::resolve::get_module_extensions;
+use crate::utils::file_request::QUERY_COMPONENT;
+use crate::visitors::dep_analyzer::{leading_comment_texts, magic_comment_import_mode};
 
 pub struct ContextModulePlugin {}
 
@@ -27,7 +35,7 @@ impl Plugin for ContextModulePlugin {
         "context_module"
     }
 
-    fn load(&self, param: &PluginLoadParam, _context: &Arc<Context>) -> Result<Option<Content>> {
+    fn load(&self, param: &PluginLoadParam, context: &Arc<Context>) -> Result<Option<Content>> {
         if let (Some(glob_pattern), true) = (
             param
                 .file
@@ -38,17 +46,27 @@ impl Plugin for ContextModulePlugin {
         ) {
             let glob_pattern = param.file.pathname.clone().join(glob_pattern);
             let paths = glob(glob_pattern.to_str().unwrap())?;
+            let prefix = param.file.param("prefix").unwrap_or_default();
 
             let mut key_values = BTreeMap::new();
-            let load_by = if param.file.has_param("async") {
-                "import"
+            let load_by = if !param.file.has_param("async") {
+                "require("
+            } else if param.file.has_param("eager") {
+                // in the chunk of the context module, for lazy-once and eager
+                r#"import(/* webpackMode: "eager" */ "#
             } else {
-                "require"
+                "import("
             };
 
             for path in paths {
                 let path = path?;
+                if matched_ignore_rule(&context.config.ignore, &context.root, &path).is_some() {
+                    continue;
+                }
                 let rlt_path = path.strip_prefix(&param.file.pathname)?;
+                if !rlt_path.to_string_lossy().starts_with(&prefix) {
+                    continue;
+                }
                 let is_file = path.is_file();
 
                 // full path `./i18n/jzh_CN.json`
@@ -97,7 +115,7 @@ impl Plugin for ContextModulePlugin {
 
                 for key in keys {
                     let map_entry =
-                        format!("'{}': () => {}('{}')", key, load_by, path.to_string_lossy());
+                        format!("'{}': () => {}'{}')", key, load_by, path.to_string_lossy());
 
                     key_values.insert(key, map_entry);
                 }
@@ -112,7 +130,7 @@ const map = {{
 module.exports = (id) => {{
     if (map[id]) return map[id]();
     else {{
-        const e = new Error("Cannot find module '" + id + "'");
+        const e = new Error("Cannot find module '" + id + {} + Object.keys(map).join(', '));
         e.code = 'MODULE_NOT_FOUND';
         throw e;
     }}
@@ -122,7 +140,14 @@ module.exports = (id) => {{
                     .into_values()
                     .map(|v| win_path(v.as_str()))
                     .collect::<Vec<String>>()
-                    .join(",\n")
+                    .join(",\n"),
+                serde_json::to_string(&format!(
+                    "' in {}, available modules: ",
+                    win_path(&relative_to_root(
+                        &param.file.pathname.to_string_lossy().to_string(),
+                        &context.root
+                    ))
+                ))?
             );
             Ok(Some(Content::Js(JsContent {
                 content,
@@ -136,6 +161,73 @@ module.exports = (id) => {{
 
 pub struct ContextModuleVisitor {
     pub unresolved_mark: Mark,
+    // how to report `import()` without static prefix, None to skip
+    pub fully_dynamic_import: Option<FullyDynamicImportConfig>,
+    // how to report `require()` without static prefix, None to skip
+    pub fully_dynamic_require: Option<FullyDynamicImportConfig>,
+    // the file being transformed, where the diagnostics are reported
+    pub path: PathBuf,
+    pub context: Arc<Context>,
+}
+
+impl ContextModuleVisitor {
+    fn report_fully_dynamic_import(&self, span: Span) {
+        let message = "The argument of import() has no static prefix, so the imported module can't be found at build time, and it fails at runtime if the module is not bundled, use a static prefix instead, e.g. import(`./locales/${lang}.json`)";
+        self.report(
+            self.fully_dynamic_import,
+            DiagnosticCode::DynamicImport,
+            span,
+            message,
+        );
+    }
+
+    fn report_fully_dynamic_require(&self, span: Span) {
+        let message = "The argument of require() has no static prefix, so the required module can't be found at build time, and it fails at runtime if the module is not bundled, use a static prefix instead, e.g. require('./handlers/' + name + '.js')";
+        self.report(
+            self.fully_dynamic_require,
+            DiagnosticCode::DynamicRequire,
+            span,
+            message,
+        );
+    }
+
+    fn report(
+        &self,
+        config: Option<FullyDynamicImportConfig>,
+        code: DiagnosticCode,
+        span: Span,
+        message: &str,
+    ) {
+        let severity = match config {
+            Some(FullyDynamicImportConfig::Error) => Severity::Error,
+            Some(FullyDynamicImportConfig::Warn) => Severity::Warning,
+            None => return,
+        };
+        let line = self.context.meta.script.cm.lookup_char_pos(span.lo).line;
+        let diagnostic = Diagnostic::new(
+            code,
+            code_frame(ErrorSpan::Js(span), message, self.context.clone()),
+        )
+        .severity(severity)
+        .paths(vec![self.path.clone()])
+        .locations(vec![(self.path.clone(), line)]);
+        // fails the transform at once like the other syntax errors
+        if self.context.diagnostics.report(diagnostic, &self.context) == Some(Severity::Error) {
+            HANDLER.with(|handler| {
+                handler.struct_span_err(span, message).emit();
+            });
+        }
+    }
+}
+
+impl ContextModuleVisitor {
+    fn import_mode(&self, arg_span: Span) -> ImportMode {
+        if self.context.config.experimental.magic_comment {
+            magic_comment_import_mode(&leading_comment_texts(&self.context, arg_span.lo))
+        } else {
+            ImportMode::default()
+        }
+    }
 }
 
 impl VisitMut for ContextModuleVisitor {
@@ -152,13 +244,25 @@ impl VisitMut for ContextModuleVisitor {
         };
 
         if (commonjs_require || dynamic_import) && first_non_str_arg.is_some() {
-            if let Some((from, glob)) = try_replace_context_arg(
-                &mut *first_non_str_arg.unwrap(),
-                false,
-            )
-            .map(|(prefix, suffix)| (prefix, format!("**/*{}", suffix.unwrap_or("".to_string()),)))
+            let first_non_str_arg = first_non_str_arg.unwrap();
+            let arg_span = first_non_str_arg.span();
+            // e.g. import(`./a.js`) which is not dynamic
+            let is_static_tpl =
+                matches!(&**first_non_str_arg, Expr::Tpl(tpl) if tpl.exprs.is_empty());
+            if let Some((from, remainder, suffix)) =
+                try_replace_context_arg(&mut *first_non_str_arg, false)
             {
-                let args_literals = format!("{}?context&glob={}", from, glob);
+                let mut args_literals =
+                    format!("{}?context&glob=**/*{}", from, suffix.unwrap_or_default());
+                // only the files starting with the static part of the name,
+                // e.g. `messages_` of `./locales/messages_${lang}.json`, the
+                // dynamic part may be in the sub directories
+                if !remainder.is_empty() {
+                    args_literals.push_str(&format!(
+                        "&prefix={}",
+                        utf8_percent_encode(&remainder, QUERY_COMPONENT)
+                    ));
+                }
 
                 let mut ctxt_call_expr = CallExpr {
                     ctxt: Default::default(),
@@ -172,9 +276,16 @@ impl VisitMut for ContextModuleVisitor {
                     // require('./i18n' + n) -> require('./i18n?context&glob=**/*')('.' + n)
                     expr.callee = ctxt_call_expr.as_callee();
                 } else {
-                    // mark async import in params
+                    // mark async import in params, the span is kept for the
+                    // magic comments of the import
+                    let params = match self.import_mode(arg_span) {
+                        ImportMode::LazyOnce | ImportMode::Eager => "async&eager",
+                        _ => "async",
+                    };
                     ctxt_call_expr.args =
-                        vec![quote_str!(format!("{}&{}", args_literals, "async")).as_arg()];
+                        vec![
+                            quote_str!(arg_span, format!("{}&{}", args_literals, params)).as_arg(),
+                        ];
 
                     // import('./i18n' + n) -> import('./i18n?context&glob=**/*').then(m => m('.' + n))
                     expr.callee = member_expr!(
@@ -193,6 +304,10 @@ impl VisitMut for ContextModuleVisitor {
                         .into_lazy_arrow(vec![quote_ident!("m").into()])
                         .as_arg()]
                 }
+            } else if dynamic_import && !is_static_tpl {
+                self.report_fully_dynamic_import(expr.span);
+            } else if commonjs_require && !is_static_tpl {
+                self.report_fully_dynamic_require(expr.span);
             }
         }
 
@@ -202,14 +317,15 @@ impl VisitMut for ContextModuleVisitor {
 
 /**
  * try to find valid context arg
- * and return prefix, suffix and replace first string literal with `./`
+ * and return prefix, the static part of the name after it, suffix and
+ * replace first string literal with `./`
  * why we need to replace with `./` prefix?
  * because the context module map is a relative path map, to reduce bundle size
  */
 fn try_replace_context_arg(
     mut o_expr: &mut Expr,
     has_visit_top_bin: bool,
-) -> Option<(String, Option<String>)> {
+) -> Option<(String, String, Option<String>)> {
     match &mut o_expr {
         // handle `(...)`
         Expr::Paren(ParenExpr {
@@ -224,7 +340,7 @@ fn try_replace_context_arg(
             ..
         }) => {
             // handle suffix of `'./foo/' + bar + '.ext'`
-            try_replace_context_arg(left_expr, true).map(|(prefix, _)| {
+            try_replace_context_arg(left_expr, true).map(|(prefix, remainder, _)| {
                 let suffix =
                     if let (Expr::Lit(Lit::Str(str)), false) = (&**right_expr, has_visit_top_bin) {
                         Some(str.value.to_string())
@@ -232,7 +348,7 @@ fn try_replace_context_arg(
                         None
                     };
 
-                (prefix, suffix)
+                (prefix, remainder, suffix)
             })
         }
 
@@ -250,7 +366,7 @@ fn try_replace_context_arg(
             if !prefix.ends_with('/') {
                 prefix = pre_quasis;
             }
-            Some((prefix, None))
+            Some((prefix, remainder, None))
         }
 
         // handle `./foo/${bar}.ext`
@@ -284,7 +400,7 @@ fn try_replace_context_arg(
                         suffix = Some(raw.to_string());
                     }
                 }
-                Some((prefix, suffix))
+                Some((prefix, remainder, suffix))
             } else {
                 None
             }
@@ -312,3 +428,100 @@ fn has_index_file_in_directory(dir_path: &Path) -> bool {
         })
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_template_literal_import() {
+        let compiler = setup_compiler("test/build/context-module-import", false);
+        compiler.compile().unwrap();
+        let root = &compiler.context.root;
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let chunk_graph = compiler.context.chunk_graph.read().unwrap();
+        // each candidate is loaded in an async chunk of its own
+        for locale in [
+            "locales/en.json",
+            "locales/zh.json",
+            "locales/messages_en.json",
+            "locales/c++/c++_en.json",
+        ] {
+            let chunk = chunk_graph
+                .get_chunk_for_module(&root.join(locale).into())
+                .unwrap_or_else(|| panic!("{} should be bundled", locale));
+            assert!(chunk.filename().ends_with("-async.js"), "{}", locale);
+            assert_eq!(chunk.get_modules().len(), 1, "{}", locale);
+        }
+        let context_module = |param: &str, expected: &str| {
+            let module = module_graph
+                .modules()
+                .into_iter()
+                .find(|module| {
+                    module
+                        .id
+                        .query()
+                        .iter()
+                        .any(|(key, value)| key == param && value == expected)
+                })
+                .unwrap_or_else(|| panic!("no context module of {}", expected));
+            module.info.as_ref().unwrap().raw.clone()
+        };
+        let raw = context_module("glob", "**/*.json");
+        assert!(raw.contains("' in ./locales, available modules: "));
+        // only the candidates of the static part of the name
+        let raw = context_module("prefix", "messages_");
+        assert!(raw.contains("'./messages_en.json'"), "{}", raw);
+        assert!(!raw.contains("'./en.json'"), "{}", raw);
+        // the `+` of the prefix is encoded, not decoded to a space
+        let raw = context_module("prefix", "c++_");
+        assert!(raw.contains("'./c++_en.json'"), "{}", raw);
+        assert!(!raw.contains("'./c_en.json'"), "{}", raw);
+    }
+
+    #[test]
+    fn test_concatenated_require() {
+        let compiler = setup_compiler("test/build/context-module-require", false);
+        compiler.compile().unwrap();
+        let root = &compiler.context.root;
+        let chunk_graph = compiler.context.chunk_graph.read().unwrap();
+        let context_modules = compiler
+            .context
+            .module_graph
+            .read()
+            .unwrap()
+            .modules()
+            .iter()
+            .filter(|module| module.id.query().iter().any(|(key, _)| key == "context"))
+            .map(|module| module.id.id.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(context_modules.len(), 2, "{:?}", context_modules);
+        // require is sync, so the candidates are in the chunk of the entry
+        for handler in ["handlers/a.js", "handlers/b.js"] {
+            let chunk = chunk_graph
+                .get_chunk_for_module(&root.join(handler).into())
+                .unwrap();
+            assert_eq!(chunk.filename(), "index.js", "{}", handler);
+        }
+
+        let js = std::fs::read_to_string(root.join("dist/index.js")).unwrap();
+        assert!(!js.contains("require.resolve"), "{}", js);
+        assert!(js.contains(r#"console.log("handlers/a.js")"#), "{}", js);
+    }
+
+    #[test]
+    fn test_fully_dynamic_require_error() {
+        let compiler = setup_compiler("test/build/fully-dynamic-require", false);
+        let err = compiler.compile().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("argument of require() has no static prefix"));
+    }
+
+    #[test]
+    fn test_fully_dynamic_import_error() {
+        let compiler = setup_compiler("test/build/fully-dynamic-import", false);
+        let err = compiler.compile().unwrap_err();
+        assert!(err.to_string().contains("has no static prefix"));
+    }
+}
@@ -15,6 +15,7 @@ use crate::compiler::Context;
 use crate::config::CopyConfig;
 use crate::plugin::Plugin;
 use crate::stats::StatsJsonMap;
+use crate::utils::file_handles::FileHandleLimiter;
 use crate::utils::tokio_runtime;
 
 pub struct CopyPlugin {}
@@ -73,7 +74,7 @@ impl CopyPlugin {
                 CopyConfig::Basic(src) => {
                     let src = context.root.join(src);
                     debug!("copy {:?} to {:?}", src, dest);
-                    copy(&src, dest)?;
+                    copy(&src, dest, &context.file_handles)?;
                 }
 
                 CopyConfig::Advanced { from, to } => {
@@ -95,7 +96,7 @@ impl CopyPlugin {
                     }
 
                     debug!("copy {:?} to {:?}", src, target);
-                    copy(&src, &target)?;
+                    copy(&src, &target, &context.file_handles)?;
                 }
             }
         }
@@ -117,7 +118,7 @@ impl Plugin for CopyPlugin {
     }
 }
 
-fn copy(src: &Path, dest: &Path) -> Result<()> {
+fn copy(src: &Path, dest: &Path, file_handles: &FileHandleLimiter) -> Result<()> {
     let src = win_path(src.to_str().unwrap());
     let paths = glob(&src)?;
 
@@ -129,14 +130,26 @@ fn copy(src: &Path, dest: &Path) -> Result<()> {
                 .content_only(true)
                 .skip_exist(false)
                 .overwrite(true);
-            fs_extra::dir::copy(&entry, dest, &options)?;
+            file_handles
+                .run(|| fs_extra::dir::copy(&entry, dest, &options).map_err(fs_extra_error))?;
         } else {
             let file_name = entry.file_name().unwrap();
             let options = fs_extra::file::CopyOptions::new()
                 .skip_exist(false)
                 .overwrite(true);
-            fs_extra::file::copy(&entry, dest.join(file_name), &options)?;
+            file_handles.run(|| {
+                fs_extra::file::copy(&entry, dest.join(file_name), &options).map_err(fs_extra_error)
+            })?;
         }
     }
     Ok(())
 }
+
+// keep the io error so that EMFILE could be retried
+fn fs_extra_error(e: fs_extra::error::Error) -> anyhow::Error {
+    let message = e.to_string();
+    match e.kind {
+        fs_extra::error::ErrorKind::Io(e) => e.into(),
+        _ => anyhow!(message),
+    }
+}
@@ -1,11 +1,9 @@
-use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Result;
-use colored::Colorize;
-use pathdiff::diff_paths;
 
 use crate::compiler::Context;
+use crate::diagnostics::{Diagnostic, DiagnosticCode, Severity};
 use crate::plugin::Plugin;
 
 pub struct LoopDetector {}
@@ -21,6 +19,7 @@ impl Plugin for LoopDetector {
         {
             let module_graph = context.module_graph.read().unwrap();
             let (_, loops) = module_graph.toposort();
+            let cm = &context.meta.script.cm;
 
             let ignore_regexes = detect_loop
                 .ignores
@@ -42,35 +41,62 @@ impl Plugin for LoopDetector {
                         true
                     }
                 })
-                .map(|module_ids| {
+                .filter_map(|module_ids| {
                     let loop_end = module_ids.first().unwrap().clone();
 
-                    module_ids
+                    let cycle = module_ids
                         .iter()
                         .chain(std::iter::once(&loop_end))
-                        .map(|id| {
-                            let absolute_path = PathBuf::from(id.id.clone());
-                            let relative_path =
-                                diff_paths(&absolute_path, &context.root).unwrap_or(absolute_path);
-                            let relative_path = relative_path.to_string_lossy().to_string();
-
-                            format!(r#""{}""#, relative_path)
-                        })
+                        .collect::<Vec<_>>();
+                    let line = cycle
+                        .iter()
+                        .map(|id| format!(r#""{}""#, id.relative_display(&context.root)))
                         .collect::<Vec<_>>()
-                        .join(" -> ")
+                        .join(" -> ");
+                    let diagnostic = Diagnostic::new(
+                        DiagnosticCode::Circular,
+                        format!("Circular Dependencies: {}", line),
+                    )
+                    .paths(
+                        module_ids
+                            .iter()
+                            .map(|id| id.absolute_path(&context.root))
+                            .collect(),
+                    )
+                    // the lines of the imports in the cycle, so that one of them
+                    // can be suppressed with `mako-ignore-next-line`
+                    .locations(
+                        cycle
+                            .windows(2)
+                            .flat_map(|pair| {
+                                let (from, to) = (pair[0], pair[1]);
+                                let path = from.absolute_path(&context.root);
+                                module_graph
+                                    .get_dependencies(from)
+                                    .into_iter()
+                                    .filter(|(id, _)| *id == to)
+                                    .filter_map(|(_, dep)| dep.span)
+                                    .filter(|span| !span.is_dummy())
+                                    .map(|span| {
+                                        let line = cm.lookup_char_pos(span.lo).line;
+                                        (path.clone(), line)
+                                    })
+                                    .collect::<Vec<_>>()
+                            })
+                            .collect(),
+                    );
+                    context
+                        .diagnostics
+                        .report(diagnostic, context)
+                        .filter(|severity| *severity != Severity::Suppressed)
+                        .map(|_| line)
                 })
                 .collect::<Vec<_>>();
 
-            if !loop_lines.is_empty() {
-                for l in &loop_lines {
-                    println!("{} Circular Dependencies: {}", "Warning".yellow(), l);
-                }
-
-                if detect_loop.graphviz {
-                    let dot_content = loop_lines.join("\n");
-                    let dot = format!("digraph Loop {{\n{}\n}}\n", dot_content);
-                    std::fs::write(context.root.join("_mako_loop_detector.dot"), dot)?;
-                }
+            if detect_loop.graphviz && !loop_lines.is_empty() {
+                let dot_content = loop_lines.join("\n");
+                let dot = format!("digraph Loop {{\n{}\n}}\n", dot_content);
+                std::fs::write(context.root.join("_mako_loop_detector.dot"), dot)?;
             }
         }
         Ok(())
@@ -1,14 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
 use semver::Version;
 
 use crate::compiler::Context;
-use crate::module::Module;
+use crate::diagnostics::{Diagnostic, DiagnosticCode, Severity};
+use crate::module::{Module, ModuleId};
 use crate::module_graph::ModuleGraph;
 use crate::plugin::Plugin;
 use crate::resolve::ResolverResource;
+use crate::stats::{human_readable_size, DuplicatePackage, DuplicatePackageCopy};
 
 #[derive(Debug, Clone)]
 struct PackageInfo {
@@ -17,11 +19,19 @@ struct PackageInfo {
     path: PathBuf,
 }
 
+// modules of a package dir
+struct PackageCopy {
+    info: PackageInfo,
+    modules: Vec<ModuleId>,
+    size: u64,
+}
+
 #[derive(Default)]
 pub struct DuplicatePackageCheckerPlugin {
     verbose: bool,
     show_help: bool,
     emit_error: bool,
+    allow: Vec<String>,
 }
 
 /// Cleans the path by replacing /node_modules/ or \node_modules\ with /~/
@@ -34,7 +44,7 @@ fn clean_path(module_path: &Path) -> PathBuf {
 }
 
 /// Makes the cleaned path relative to the given context
-fn clean_path_relative_to_context(module_path: &Path, context: &Path) -> PathBuf {
+pub(crate) fn clean_path_relative_to_context(module_path: &Path, context: &Path) -> PathBuf {
     let cleaned_path = clean_path(module_path);
     let context_str = context.to_str().unwrap();
     let cleaned_path_str = cleaned_path.to_str().unwrap();
@@ -47,28 +57,42 @@ fn clean_path_relative_to_context(module_path: &Path, context: &Path) -> PathBuf
     }
 }
 
+/// The package of the nearest package.json of a resolved module.
+pub(crate) struct NearestPackage {
+    pub name: String,
+    pub version: Option<String>,
+    pub path: PathBuf,
+}
+
+pub(crate) fn nearest_package(module: &Module) -> Option<NearestPackage> {
+    let info = module.info.as_ref()?;
+    let ResolverResource::Resolved(resource) = info.resolved_resource.as_ref()? else {
+        return None;
+    };
+    let package_json = resource.0.package_json()?;
+    let name = package_json.name.clone()?;
+    let version = package_json
+        .raw_json()
+        .as_object()
+        .and_then(|raw_json| raw_json.get("version"))
+        .and_then(|version| version.as_str())
+        .map(|version| version.to_string());
+    Some(NearestPackage {
+        name,
+        version,
+        path: package_json.path.clone(),
+    })
+}
+
 fn extract_package_info(module: &Module) -> Option<PackageInfo> {
-    module
-        .info
-        .as_ref()
-        .and_then(|info| info.resolved_resource.as_ref())
-        .and_then(|resolver_resource| {
-            if let ResolverResource::Resolved(resource) = resolver_resource {
-                let package_json = resource.0.package_json()?;
-                let name = package_json.name.clone()?;
-                let raw_json = package_json.raw_json();
-                let version = raw_json.as_object()?.get("version")?;
-                let version = semver::Version::parse(version.as_str().unwrap()).ok()?;
-
-                Some(PackageInfo {
-                    name,
-                    version,
-                    path: package_json.path.clone(),
-                })
-            } else {
-                None
-            }
-        })
+    let package = nearest_package(module)?;
+    let version = semver::Version::parse(package.version.as_ref()?).ok()?;
+
+    Some(PackageInfo {
+        name: package.name,
+        version,
+        path: package.path,
+    })
 }
 
 impl DuplicatePackageCheckerPlugin {
@@ -91,39 +115,96 @@ impl DuplicatePackageCheckerPlugin {
         self
     }
 
-    fn find_duplicates(packages: Vec<PackageInfo>) -> HashMap<String, Vec<PackageInfo>> {
-        let mut package_map: HashMap<String, Vec<PackageInfo>> = HashMap::new();
-
-        for package in packages {
-            package_map
-                .entry(package.name.clone())
-                .or_default()
-                .push(package);
-        }
-
-        package_map
-            .into_iter()
-            .filter(|(_, versions)| versions.len() > 1)
-            .collect()
+    pub fn allow(mut self, allow: Vec<String>) -> Self {
+        self.allow = allow;
+        self
     }
 
-    fn check_duplicates(
-        &self,
-        module_graph: &RwLock<ModuleGraph>,
-    ) -> HashMap<String, Vec<PackageInfo>> {
-        let mut packages = Vec::new();
+    // package name -> copies with different versions, sorted by version
+    fn find_duplicates(&self, module_graph: &ModuleGraph) -> BTreeMap<String, Vec<PackageCopy>> {
+        let mut copies: HashMap<PathBuf, PackageCopy> = HashMap::new();
+        for module in module_graph.modules() {
+            let Some(info) = extract_package_info(module) else {
+                continue;
+            };
+            if self.allow.contains(&info.name) {
+                continue;
+            }
+            let size = module.info.as_ref().map_or(0, |info| info.raw.len() as u64);
+            let copy = copies
+                .entry(info.path.clone())
+                .or_insert_with(|| PackageCopy {
+                    info,
+                    modules: vec![],
+                    size: 0,
+                });
+            copy.modules.push(module.id.clone());
+            copy.size += size;
+        }
 
-        module_graph
-            .read()
-            .unwrap()
-            .modules()
-            .into_iter()
-            .filter_map(extract_package_info)
-            .for_each(|package_info| {
-                packages.push(package_info);
+        let mut packages: BTreeMap<String, Vec<PackageCopy>> = BTreeMap::new();
+        for copy in copies.into_values() {
+            packages
+                .entry(copy.info.name.clone())
+                .or_default()
+                .push(copy);
+        }
+        packages.retain(|_, copies| {
+            copies.sort_by(|a, b| {
+                (&a.info.version, &a.info.path).cmp(&(&b.info.version, &b.info.path))
             });
+            copies
+                .iter()
+                .any(|copy| copy.info.version != copies[0].info.version)
+        });
+        packages
+    }
 
-        Self::find_duplicates(packages)
+    fn to_stats(
+        duplicates: &BTreeMap<String, Vec<PackageCopy>>,
+        module_graph: &ModuleGraph,
+        root: &Path,
+    ) -> Vec<DuplicatePackage> {
+        duplicates
+            .iter()
+            .map(|(name, copies)| DuplicatePackage {
+                name: name.clone(),
+                copies: copies
+                    .iter()
+                    .map(|copy| {
+                        let mut modules = copy.modules.clone();
+                        modules.sort();
+                        // the shortest chain to any module of the copy
+                        let import_chain = modules
+                            .iter()
+                            .flat_map(|module_id| module_graph.import_chains(module_id, 1))
+                            .min_by_key(|chain| chain.steps.len())
+                            .map(|chain| {
+                                std::iter::once(&chain.entry)
+                                    .chain(chain.steps.iter().map(|step| &step.to))
+                                    .map(|id| {
+                                        clean_path_relative_to_context(Path::new(&id.id), root)
+                                            .to_string_lossy()
+                                            .to_string()
+                                    })
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+                        DuplicatePackageCopy {
+                            version: copy.info.version.to_string(),
+                            path: clean_path_relative_to_context(
+                                copy.info.path.parent().unwrap_or(&copy.info.path),
+                                root,
+                            )
+                            .to_string_lossy()
+                            .to_string(),
+                            size: copy.size,
+                            import_chain,
+                        }
+                    })
+                    .collect(),
+            })
+            .collect()
     }
 }
 
@@ -137,35 +218,59 @@ impl Plugin for DuplicatePackageCheckerPlugin {
         context: &Arc<Context>,
         _compiler: &crate::compiler::Compiler,
     ) -> anyhow::Result<()> {
-        let duplicates = self.check_duplicates(&context.module_graph);
-
-        if !duplicates.is_empty() && self.verbose {
-            let mut message = String::new();
-
-            for (name, instances) in duplicates {
-                message.push_str(&format!("\nMultiple versions of {} found:\n", name));
-                for instance in instances {
-                    let mut line = format!("  {} {}", instance.version, instance.name);
-                    let path = instance.path.clone();
-                    line.push_str(&format!(
-                        " from {}",
-                        clean_path_relative_to_context(&path, &context.root).display()
-                    ));
-                    message.push_str(&line);
-                    message.push('\n');
+        let module_graph = context.module_graph.read().unwrap();
+        let duplicates = self.find_duplicates(&module_graph);
+        let duplicate_packages = Self::to_stats(&duplicates, &module_graph, &context.root);
+        drop(module_graph);
+
+        for (package, copies) in duplicate_packages.iter().zip(duplicates.values()) {
+            let mut message = format!("Multiple versions of {} found:\n", package.name);
+            for (instance, copy) in package.copies.iter().zip(copies) {
+                message.push_str(&format!(
+                    "  {} {} from {} ({})\n",
+                    instance.version,
+                    package.name,
+                    instance.path,
+                    human_readable_size(instance.size)
+                ));
+                if !instance.import_chain.is_empty() {
+                    message.push_str(&format!("    {}\n", instance.import_chain.join(" > ")));
+                }
+                if self.verbose {
+                    let mut modules = copy
+                        .modules
+                        .iter()
+                        .map(|id| clean_path_relative_to_context(Path::new(&id.id), &context.root))
+                        .collect::<Vec<_>>();
+                    modules.sort();
+                    for module in modules {
+                        message.push_str(&format!("    - {}\n", module.display()));
+                    }
                 }
             }
-
             if self.show_help {
                 message.push_str("\nCheck how you can resolve duplicate packages: \nhttps://github.com/darrenscerri/duplicate-package-checker-webpack-plugin#resolving-duplicate-packages-in-your-bundle\n");
             }
-
-            if !self.emit_error {
-                println!("{}", message);
-            } else {
-                eprintln!("{}", message);
+            let mut diagnostic = Diagnostic::new(DiagnosticCode::DuplicatePackage, message).paths(
+                copies
+                    .iter()
+                    .map(|copy| {
+                        copy.info
+                            .path
+                            .parent()
+                            .unwrap_or(&copy.info.path)
+                            .to_path_buf()
+                    })
+                    .collect(),
+            );
+            if self.emit_error {
+                diagnostic = diagnostic.severity(Severity::Error);
             }
+            context.diagnostics.report(diagnostic, context);
         }
+        context
+            .stats_info
+            .set_duplicate_packages(duplicate_packages);
 
         Ok(())
     }
@@ -173,23 +278,12 @@ impl Plugin for DuplicatePackageCheckerPlugin {
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
-
-    use crate::config::{Config, DuplicatePackageCheckerConfig};
     use crate::plugin::Plugin;
     use crate::plugins::duplicate_package_checker::DuplicatePackageCheckerPlugin;
     use crate::utils::test_helper::setup_compiler;
 
     #[test]
     fn test_duplicate_package_checker() {
-        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/build/duplicate-package");
-        let mut config = Config::new(&root, None, None).unwrap();
-        config.check_duplicate_package = Some(DuplicatePackageCheckerConfig {
-            verbose: true,
-            emit_error: false,
-            show_help: true,
-        });
-
         let compiler = setup_compiler("test/build/duplicate-package", false);
         let plugin = DuplicatePackageCheckerPlugin::new()
             .verbose(true)
@@ -203,5 +297,42 @@ mod tests {
         let result = plugin.after_build(&compiler.context, &compiler);
 
         assert!(result.is_ok());
+        let duplicates = compiler
+            .context
+            .stats_info
+            .duplicate_packages
+            .lock()
+            .unwrap()
+            .clone();
+        assert_eq!(
+            duplicates
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        let a = &duplicates[0].copies;
+        assert_eq!(
+            a.iter()
+                .map(|c| (c.version.as_str(), c.path.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("1.0.0", "./~/a"), ("2.0.0", "./~/b/~/a")]
+        );
+        assert_eq!(
+            a[1].import_chain,
+            vec!["./index.ts", "./~/b/index.js", "./~/b/~/a/index.js"]
+        );
+        assert!(a[1].size > 0);
+
+        let plugin = DuplicatePackageCheckerPlugin::new().allow(vec!["a".to_string()]);
+        plugin.after_build(&compiler.context, &compiler).unwrap();
+        let duplicates = compiler
+            .context
+            .stats_info
+            .duplicate_packages
+            .lock()
+            .unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].name, "b");
     }
 }
@@ -31,6 +31,7 @@ impl Plugin for EmotionPlugin {
                 import_source: "@emotion/react".into(),
                 pragma_frag: config.react.pragma_frag.clone(),
                 runtime: config.react.runtime.clone(),
+                remove_prop_types: config.react.remove_prop_types.take(),
             }
         }
         Ok(())
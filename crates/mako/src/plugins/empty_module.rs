@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use swc_core::ecma::ast::{Module as SwcModule, ModuleDecl, ModuleItem, Stmt};
+
+use crate::compiler::{Compiler, Context};
+use crate::module::{relative_to_root, ImportType, Module, ModuleAst, ModuleId, ResolveType};
+use crate::module_graph::ModuleGraph;
+use crate::plugin::Plugin;
+
+/// Prune modules which are empty after transform, e.g. ts files which only
+/// contain ambient declarations and interfaces, and drop the imports of them.
+pub struct EmptyModulePlugin {}
+
+impl Plugin for EmptyModulePlugin {
+    fn name(&self) -> &str {
+        "empty_module"
+    }
+
+    fn after_build(&self, context: &Arc<Context>, _compiler: &Compiler) -> Result<()> {
+        // modules are diffed by the update process in watch mode
+        if context.args.watch {
+            return Ok(());
+        }
+
+        let mut module_graph = context.module_graph.write().unwrap();
+        let mut pruned = 0;
+        let mut errors = vec![];
+        let mut kept = HashSet::new();
+        // importers may become empty after the imports are dropped
+        loop {
+            let empty_modules = module_graph
+                .modules()
+                .into_iter()
+                .filter(|module| !kept.contains(&module.id) && is_empty_module(module))
+                .map(|module| module.id.clone())
+                .collect::<Vec<_>>();
+            let mut pruned_in_round = 0;
+            for module_id in empty_modules {
+                if prune(&mut module_graph, &module_id, context, &mut errors) {
+                    pruned_in_round += 1;
+                } else {
+                    kept.insert(module_id);
+                }
+            }
+            if pruned_in_round == 0 {
+                break;
+            }
+            pruned += pruned_in_round;
+        }
+
+        if !errors.is_empty() {
+            return Err(anyhow!(errors.join("\n")));
+        }
+        context.stats_info.add_pruned_empty_modules(pruned);
+        Ok(())
+    }
+}
+
+fn is_empty_module(module: &Module) -> bool {
+    if module.is_entry {
+        return false;
+    }
+    match &module.info {
+        Some(info) if info.external.is_none() && !info.is_ignored && !info.is_async => {
+            match &info.ast {
+                ModuleAst::Script(ast) => is_empty_ast(&ast.ast),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+// empty, or only `export {}`
+fn is_empty_ast(ast: &SwcModule) -> bool {
+    ast.body.iter().all(|item| match item {
+        ModuleItem::Stmt(Stmt::Empty(_)) => true,
+        ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(export)) => {
+            export.specifiers.is_empty() && export.src.is_none()
+        }
+        _ => false,
+    })
+}
+
+// returns whether the module is pruned
+fn prune(
+    module_graph: &mut ModuleGraph,
+    module_id: &ModuleId,
+    context: &Arc<Context>,
+    errors: &mut Vec<String>,
+) -> bool {
+    let dependents = module_graph
+        .get_dependents(module_id)
+        .into_iter()
+        .map(|(id, dep)| (id.clone(), dep.clone()))
+        .collect::<Vec<_>>();
+    let path = relative_to_root(&module_id.id.to_string(), &context.root);
+
+    let mut has_error = false;
+    for (importer, dep) in &dependents {
+        match &dep.resolve_type {
+            ResolveType::Import(import_type) if *import_type == ImportType::SideEffect => {}
+            ResolveType::ExportAll => {}
+            // type-only imports are already removed by the transform, so what
+            // is left must be a runtime value
+            ResolveType::Import(_) => {
+                errors.push(format!(
+                    "{} imports runtime values from \"{}\", but {} is empty after transform",
+                    relative_to_root(&importer.id.to_string(), &context.root),
+                    dep.source,
+                    path
+                ));
+                has_error = true;
+            }
+            // the transform can't tell re-exported types from values
+            ResolveType::ExportNamed(_) => {
+                eprintln!(
+                    "{}: {} re-exports from \"{}\", but {} is empty after transform, the re-export is dropped",
+                    "Warning".yellow(),
+                    relative_to_root(&importer.id.to_string(), &context.root),
+                    dep.source,
+                    path
+                );
+            }
+            // require, dynamic import and worker need the module at runtime
+            _ => return false,
+        }
+    }
+    if has_error {
+        return false;
+    }
+
+    for (importer, dep) in &dependents {
+        if let Some(ModuleAst::Script(ast)) = module_graph
+            .get_module_mut(importer)
+            .and_then(|module| module.info.as_mut())
+            .map(|info| &mut info.ast)
+        {
+            ast.ast.body.retain(|item| !is_import_of(item, &dep.source));
+        }
+        if let Some(info) = module_graph
+            .get_module_mut(importer)
+            .and_then(|module| module.info.as_mut())
+        {
+            info.deps
+                .resolved_deps
+                .retain(|resolved| resolved.dependency.source != dep.source);
+        }
+    }
+    module_graph.remove_module(module_id);
+    true
+}
+
+fn is_import_of(item: &ModuleItem, source: &str) -> bool {
+    match item {
+        ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => import.src.value == *source,
+        ModuleItem::ModuleDecl(ModuleDecl::ExportAll(export)) => export.src.value == *source,
+        ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(export)) => {
+            export.src.as_ref().is_some_and(|src| src.value == *source)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_helper::setup_compiler;
+
+    fn module_ids(compiler: &Compiler) -> Vec<String> {
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        module_graph
+            .modules()
+            .iter()
+            .map(|module| relative_to_root(&module.id.id.to_string(), &compiler.context.root))
+            .collect()
+    }
+
+    #[test]
+    fn test_prune_empty_modules() {
+        let compiler = setup_compiler("test/build/empty-module", false);
+        compiler.compile().unwrap();
+        let modules = module_ids(&compiler);
+        assert!(modules.contains(&"./index.ts".to_string()));
+        assert!(modules.contains(&"./required.ts".to_string()));
+        assert!(!modules.contains(&"./types.ts".to_string()));
+        assert!(!modules.contains(&"./global.d.ts".to_string()));
+        // only re-exports the types, and becomes empty too
+        assert!(!modules.contains(&"./barrel.ts".to_string()));
+        assert_eq!(
+            *compiler
+                .context
+                .stats_info
+                .pruned_empty_modules
+                .lock()
+                .unwrap(),
+            3
+        );
+
+        let index = compiler
+            .context
+            .output_fs
+            .read(&compiler.context.config.output.path.join("index.js"))
+            .unwrap();
+        let index = String::from_utf8(index).unwrap();
+        assert!(!index.contains("types.ts"));
+        assert!(!index.contains("barrel.ts"));
+    }
+
+    #[test]
+    fn test_runtime_import_from_empty_module() {
+        let compiler = setup_compiler("test/build/empty-module-runtime-import", false);
+        let err = compiler.compile().unwrap_err().to_string();
+        assert!(err.contains("./index.ts imports runtime values from \"./types\""));
+    }
+}
@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::compiler::Context;
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::module::relative_to_root;
+use crate::module_graph::ModuleGraph;
+use crate::plugin::Plugin;
+use crate::stats::BailoutModule;
+
+/// Warns of the modules with direct eval or new Function in development,
+/// which are usually unintentional, e.g. the templates of lodash and the old
+/// analytics snippets.
+pub struct EvalBailoutPlugin {}
+
+impl Plugin for EvalBailoutPlugin {
+    fn name(&self) -> &str {
+        "eval_bailout"
+    }
+
+    fn generate_begin(&self, context: &Arc<Context>) -> Result<()> {
+        let module_graph = context.module_graph.read().unwrap();
+        for module in bailout_modules(&module_graph, &context.root) {
+            let reasons = module
+                .reasons
+                .iter()
+                .map(|reason| reason.to_string())
+                .collect::<Vec<_>>()
+                .join(" and ");
+            let diagnostic = Diagnostic::new(
+                DiagnosticCode::EvalBailout,
+                format!(
+                    "{} {}, which is excluded from the concatenation",
+                    module.path, reasons
+                ),
+            )
+            .paths(vec![context.root.join(&module.path)]);
+            context.diagnostics.report(diagnostic, context);
+        }
+        Ok(())
+    }
+}
+
+/// The modules with direct eval or new Function, sorted by path.
+pub fn bailout_modules(module_graph: &ModuleGraph, root: &PathBuf) -> Vec<BailoutModule> {
+    let mut modules = module_graph
+        .modules()
+        .into_iter()
+        .filter_map(|module| {
+            let info = module.info.as_ref()?;
+            (!info.bailouts.is_empty()).then(|| BailoutModule {
+                path: relative_to_root(&info.file.pathname.to_string_lossy().to_string(), root),
+                reasons: info.bailouts.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+    modules.sort_by(|a, b| a.path.cmp(&b.path));
+    modules
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::compiler::Compiler;
+    use crate::diagnostics::DiagnosticCode;
+    use crate::module::BailoutReason;
+    use crate::testing::compile_fixture;
+
+    fn compile(mode: &str) -> Compiler {
+        compile_fixture(
+            Path::new("test/build/eval-bailout"),
+            Some(&format!(r#"{{ "mode": "{}" }}"#, mode)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_eval_bailout() {
+        let compiler = compile("production");
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let ids = module_graph
+            .modules()
+            .iter()
+            .map(|module| module.id.id.clone())
+            .collect::<Vec<_>>();
+        // the bailout is not concatenated, but the others are
+        assert!(
+            ids.iter().any(|id| id.ends_with("/template.js")),
+            "{:?}",
+            ids
+        );
+        assert!(!ids.iter().any(|id| id.ends_with("/math.js")), "{:?}", ids);
+        drop(module_graph);
+
+        let stats = serde_json::to_value(compiler.create_stats_info()).unwrap();
+        assert_eq!(
+            stats["bailouts"],
+            serde_json::json!([{ "path": "./template.js", "reasons": ["eval", "new Function"] }])
+        );
+
+        // the evaluated code is kept as it is
+        let context = &compiler.context;
+        let files = context.output_fs.list(&context.config.output.path).unwrap();
+        let file = files
+            .iter()
+            .find(|file| file.extension().is_some_and(|ext| ext == "js"))
+            .unwrap();
+        let js = String::from_utf8(context.output_fs.read(file).unwrap()).unwrap();
+        assert!(js.contains("return x + process.env.NODE_ENV"), "{}", js);
+        // the names in the scope of eval are not mangled
+        assert!(
+            js.contains(r#"function render(data){return eval("data.name");}"#),
+            "{}",
+            js
+        );
+        assert!(context.diagnostics.get_reported().is_empty());
+    }
+
+    #[test]
+    fn test_eval_bailout_warning() {
+        let compiler = compile("development");
+        let reported = compiler.context.diagnostics.get_reported();
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].code, DiagnosticCode::EvalBailout);
+        assert!(reported[0]
+            .message
+            .starts_with("./template.js uses direct eval and uses new Function"));
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let template = module_graph
+            .modules()
+            .into_iter()
+            .find(|module| module.id.id.ends_with("/template.js"))
+            .unwrap();
+        assert_eq!(
+            template.info.as_ref().unwrap().bailouts,
+            vec![BailoutReason::DirectEval, BailoutReason::NewFunction]
+        );
+    }
+}
@@ -9,6 +9,11 @@
   let currentParents = [];
   let currentChildModule;
   requireModule.hmrC = {};
+  // called with the ids of the chunks loaded by jsonp
+  requireModule.chunkLoadedListeners = [];
+  // chunk id -> the hash of the hot update which changed the chunk before
+  // it's loaded
+  requireModule.outdatedChunks = {};
   const createHmrRequire = (require, moduleId) => {
     const me = modulesRegistry[moduleId];
     if (!me) return require;
@@ -33,10 +38,28 @@
     Object.assign(fn, require);
     return fn;
   };
+  // data passed by the dispose handlers to the new modules
+  const moduleHotData = {};
   const applyHotUpdate = (_chunkId, update) => {
-    const { modules, removedModules } = update;
+    const { modules } = update;
+    updateModules(Object.keys(modules), () => registerModules(modules));
+  };
+  // the modules removed from the graph, e.g. deleted or not imported anymore,
+  // are disposed and their importers are updated up to the accept boundaries
+  const removeModules = (moduleIds) => {
+    updateModules(
+      moduleIds,
+      () => {
+        for (const moduleId of moduleIds) {
+          delete makoModules[moduleId];
+        }
+      },
+      moduleIds,
+    );
+  };
+  const updateModules = (moduleIds, register, removedModules = []) => {
     const outdatedModules = [];
-    for (const moduleId of Object.keys(modules)) {
+    for (const moduleId of moduleIds) {
       if (!modulesRegistry[moduleId]) continue;
       if (outdatedModules.includes(moduleId)) continue;
       outdatedModules.push(moduleId);
@@ -48,7 +71,7 @@
         if (module.hot._main) {
           location.reload();
         }
-        if (module.hot._selfAccepted) {
+        if (module.hot._selfAccepted && !removedModules.includes(item)) {
           continue;
         }
         for (const parentModule of module.parents) {
@@ -61,15 +84,17 @@
     const outdatedSelfAcceptedModules = [];
     for (const moduleId of outdatedModules) {
       const module = modulesRegistry[moduleId];
-      if (module.hot._selfAccepted) {
+      if (module.hot._selfAccepted && !removedModules.includes(moduleId)) {
         outdatedSelfAcceptedModules.push(module);
       }
     }
     for (const moduleId of outdatedModules) {
       const module = modulesRegistry[moduleId];
+      const data = {};
       for (const handler of module.hot._disposeHandlers) {
-        handler();
+        handler(data);
       }
+      moduleHotData[moduleId] = data;
       module.hot.active = false;
       delete modulesRegistry[moduleId];
       for (const childModule of module.children) {
@@ -81,9 +106,19 @@
         }
       }
     }
-    registerModules(modules);
+    register();
+    const invalidatedParents = [];
     for (const module of outdatedSelfAcceptedModules) {
       module.hot._requireSelf();
+      // e.g. the class names of a css module are changed, so its parents
+      // are updated too
+      const newModule = modulesRegistry[module.id];
+      if (newModule && newModule.hot._selfInvalidated) {
+        invalidatedParents.push(...newModule.parents);
+      }
+    }
+    if (invalidatedParents.length) {
+      updateModules(invalidatedParents, () => {});
     }
   };
   const createModuleHotObject = (moduleId, me) => {
@@ -95,6 +130,7 @@
       _selfDeclined: false,
       _selfInvalidated: false,
       _disposeHandlers: [],
+      data: moduleHotData[moduleId],
       _requireSelf: function () {
         currentParents = me.parents.slice();
         currentChildModule = _main ? undefined : moduleId;
@@ -108,7 +144,9 @@
       dispose(callback) {
         this._disposeHandlers.push(callback);
       },
-      invalidate() {},
+      invalidate() {
+        this._selfInvalidated = true;
+      },
       updateChunksUrlMap() {
         const current_hash = requireModule.currentHash();
         return fetch(
@@ -120,40 +158,59 @@
             Object.assign(cssChunksIdToUrlMap, chunksUrlMap.css);
           });
       },
-      check() {
+      // the update filtered for the client is sent by the dev server with
+      // the next hash, the older servers send the hash only
+      check(filteredUpdate, nextHash) {
         const current_hash = requireModule.currentHash();
-        return fetch(
-          `${requireModule.publicPath}${current_hash}.hot-update.json`,
-        )
-          .then((res) => {
-            return res.json();
-          })
-          .then((update) => {
-            return Promise.all(
-              update.c.map((chunk) => {
-                let parts = chunk.split('.');
-                let l = parts.length;
-                let left = parts.slice(0, parts.length - 1).join('.');
-                let ext = parts[l - 1];
-                const hotChunkName = [
-                  left,
-                  current_hash,
-                  'hot-update',
-                  ext,
-                ].join('.');
-                return new Promise((done) => {
-                  const url = `${requireModule.publicPath}${hotChunkName}`;
-                  requireModule.loadScript(url, done);
-                });
-              }),
-            );
+        const manifest = filteredUpdate
+          ? Promise.resolve(filteredUpdate)
+          : fetch(
+              `${requireModule.publicPath}${current_hash}.hot-update.json`,
+            ).then((res) => {
+              return res.json();
+            });
+        return manifest.then((update) => {
+          for (const chunkId of update.o || []) {
+            if (!requireModule.loadedChunks[chunkId]) {
+              requireModule.outdatedChunks[chunkId] = nextHash || Date.now();
+            }
+          }
+          return Promise.all(
+            update.c.map((chunk) => {
+              let parts = chunk.split('.');
+              let l = parts.length;
+              let left = parts.slice(0, parts.length - 1).join('.');
+              let ext = parts[l - 1];
+              const hotChunkName = [
+                left,
+                current_hash,
+                'hot-update',
+                ext,
+              ].join('.');
+              return new Promise((done) => {
+                const url = `${requireModule.publicPath}${hotChunkName}`;
+                requireModule.loadScript(url, done);
+              });
+            }),
+          ).then(() => {
+            // after the importers are updated, so they don't require the
+            // removed modules again
+            if (update.m && update.m.length) {
+              removeModules(update.m);
+            }
+            // no hot update chunk sets the hash if none is loaded
+            if (nextHash) {
+              requireModule._h = nextHash;
+            }
           });
+        });
       },
       apply(update) {
         return applyHotUpdate(update);
       },
     };
     currentChildModule = undefined;
+    delete moduleHotData[moduleId];
     return hot;
   };
   requireModule.hmrC.jsonp = (chunkId, update, promises) => {
@@ -180,6 +237,8 @@
                 newLink.rel = 'stylesheet';
                 newLink.as = null;
                 oldLink.parentNode.removeChild(oldLink);
+                const stylesheet = requireModule.stylesheets[url];
+                if (stylesheet) stylesheet.link = newLink;
                 resolve();
               },
               reject,
@@ -0,0 +1,484 @@
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use lol_html::html_content::{ContentType, Element};
+use lol_html::{element, rewrite_str, text, HandlerResult, RewriteStrSettings};
+
+use crate::ast::file::{Content, File, JsContent};
+use crate::ast::utils::is_remote_or_data_or_hash;
+use crate::build::load::Load;
+use crate::compiler::Context;
+use crate::config::{static_public_path, Config};
+use crate::plugin::{Plugin, PluginLoadParam, PluginSubModules};
+
+// the query of the sub-modules of the inline module scripts
+const INLINE_SCRIPT_QUERY: &str = "html-script";
+
+/// The html entries, e.g. `{ entry: { index: "./index.html" } }`, which are
+/// the source of the html of the entries. The module scripts and the
+/// stylesheets of the html are imported by the entry module, the inline
+/// module scripts are its sub-modules, and the html without them is the
+/// template which the tags of the entry chunks are injected to.
+pub struct HtmlEntryPlugin {}
+
+impl Plugin for HtmlEntryPlugin {
+    fn name(&self) -> &str {
+        "html_entry"
+    }
+
+    fn load_sub_modules(
+        &self,
+        param: &PluginLoadParam,
+        context: &Arc<Context>,
+    ) -> Result<Option<PluginSubModules>> {
+        let file = param.file;
+        if file.extname != "html"
+            || !context
+                .config
+                .entry
+                .values()
+                .any(|entry| entry.import == file.pathname)
+        {
+            return Ok(None);
+        }
+        let html = std::fs::read_to_string(&file.pathname)?;
+        let dir = file.pathname.parent().unwrap();
+        let public_path = static_public_path(&context.config.public_path);
+        let resolve = |url: &str| resolve_url(url, dir, &context.root);
+        // the first asset which fails to emit, e.g. for a name conflict
+        let emit_error = RefCell::new(None);
+        let split = split_html(&html, |url| {
+            let path = resolve(url)?;
+            match Load::emit_asset(
+                &File::new(path.to_string_lossy().to_string(), context.clone()),
+                context.clone(),
+            ) {
+                Ok(name) => Some(format!("{}{}", public_path, name)),
+                Err(e) => {
+                    emit_error.borrow_mut().get_or_insert(e);
+                    None
+                }
+            }
+        })?;
+        if let Some(e) = emit_error.into_inner() {
+            return Err(e);
+        }
+
+        let file_name = file.pathname.file_name().unwrap().to_string_lossy();
+        let mut imports = vec![];
+        let mut sub_modules = vec![];
+        for dep in split.deps {
+            match dep {
+                HtmlDep::Url(url) => {
+                    // the urls starting with `/` are relative to the root
+                    let source = if url.starts_with('/') {
+                        resolve(&url)
+                            .map(|path| path.to_string_lossy().to_string())
+                            .unwrap_or(url)
+                    } else if url.starts_with('.') {
+                        url
+                    } else {
+                        format!("./{}", url)
+                    };
+                    imports.push(source);
+                }
+                HtmlDep::Inline(code) => {
+                    let query = format!("{}={}", INLINE_SCRIPT_QUERY, sub_modules.len());
+                    imports.push(format!("./{}?{}", file_name, query));
+                    sub_modules.push((query, js(code)));
+                }
+            }
+        }
+        context.html_entries.write().unwrap().insert(
+            file.pathname.clone(),
+            HtmlEntry {
+                template: split.template,
+                nonce: split.nonce,
+            },
+        );
+        Ok(Some(PluginSubModules {
+            content: js(imports
+                .iter()
+                .map(|source| format!("import {:?};", source))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            sub_modules,
+        }))
+    }
+}
+
+fn js(content: String) -> Content {
+    Content::Js(JsContent {
+        content,
+        is_jsx: false,
+    })
+}
+
+/// The html entry of the entry, none if the entry is not an html.
+pub(crate) fn html_entry(name: &str, context: &Context) -> Option<HtmlEntry> {
+    let entry = context.config.entry.get(name)?;
+    context
+        .html_entries
+        .read()
+        .unwrap()
+        .get(&entry.import)
+        .cloned()
+}
+
+pub(crate) fn has_html_entry(config: &Config) -> bool {
+    config
+        .entry
+        .values()
+        .any(|entry| entry.import.extension().is_some_and(|ext| ext == "html"))
+}
+
+// the path of a local url of the html, the urls starting with `/` are
+// relative to the root
+fn resolve_url(url: &str, dir: &Path, root: &Path) -> Option<PathBuf> {
+    if !is_local_url(url) {
+        return None;
+    }
+    let path = match url.strip_prefix('/') {
+        Some(url) => root.join(url),
+        None => dir.join(url),
+    };
+    path.is_file().then_some(path)
+}
+
+fn is_local_url(url: &str) -> bool {
+    !url.is_empty() && !url.starts_with("//") && !is_remote_or_data_or_hash(url)
+}
+
+#[derive(Debug, Clone)]
+pub struct HtmlEntry {
+    /// the html without the bundled tags
+    pub template: String,
+    /// the nonce of the bundled tags, which is kept for the injected tags
+    pub nonce: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum HtmlDep {
+    /// the src of a module script or the href of a stylesheet
+    Url(String),
+    /// the code of an inline module script
+    Inline(String),
+}
+
+#[derive(Debug)]
+struct SplitHtml {
+    template: String,
+    deps: Vec<HtmlDep>,
+    nonce: Option<String>,
+}
+
+// split the module scripts and the stylesheets out of the html, and rewrite
+// the urls of the assets, e.g. the images and the icons, by `asset_url`
+fn split_html(html: &str, asset_url: impl Fn(&str) -> Option<String>) -> Result<SplitHtml> {
+    let deps = RefCell::new(vec![]);
+    let nonce = RefCell::new(None);
+    // index of the inline module script in the deps whose code is being read
+    let inline = Cell::new(None);
+    let remove = |el: &mut Element| {
+        if let Some(value) = el.get_attribute("nonce") {
+            nonce.borrow_mut().get_or_insert(value);
+        }
+        el.replace(REMOVED, ContentType::Html);
+    };
+    let template = rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![
+                element!("script", |el| {
+                    inline.set(None);
+                    if !is_attr(el, "type", "module") {
+                        return Ok(());
+                    }
+                    let mut deps = deps.borrow_mut();
+                    match el.get_attribute("src") {
+                        Some(src) if is_local_url(&src) => deps.push(HtmlDep::Url(src)),
+                        Some(_) => return Ok(()),
+                        None => {
+                            inline.set(Some(deps.len()));
+                            deps.push(HtmlDep::Inline(String::new()));
+                        }
+                    }
+                    remove(el);
+                    Ok(())
+                }),
+                text!("script", |text| {
+                    if let Some(index) = inline.get()
+                        && let HtmlDep::Inline(code) = &mut deps.borrow_mut()[index]
+                    {
+                        code.push_str(text.as_str());
+                    }
+                    Ok(())
+                }),
+                element!("link[href]", |el| {
+                    if has_token(el, "rel", "stylesheet") {
+                        let href = el.get_attribute("href").unwrap();
+                        if is_local_url(&href) {
+                            deps.borrow_mut().push(HtmlDep::Url(href));
+                            remove(el);
+                        }
+                    } else if ASSET_RELS.iter().any(|rel| has_token(el, "rel", rel)) {
+                        rewrite_attrs(el, &["href"], &asset_url)?;
+                    }
+                    Ok(())
+                }),
+                element!("img, source", |el| {
+                    rewrite_attrs(el, &["src", "srcset"], &asset_url)
+                }),
+                element!("video", |el| {
+                    rewrite_attrs(el, &["src", "poster"], &asset_url)
+                }),
+                element!("audio, input", |el| rewrite_attrs(el, &["src"], &asset_url)),
+            ],
+            ..RewriteStrSettings::default()
+        },
+    )?;
+
+    // remove the lines of the removed tags if they are the only ones in the
+    // lines, so no blank line is left
+    let mut result = String::with_capacity(template.len());
+    let mut last = 0;
+    for (index, _) in template.match_indices(REMOVED) {
+        let range = line_range(&template, index..index + REMOVED.len());
+        result.push_str(&template[last..range.start]);
+        last = range.end;
+    }
+    result.push_str(&template[last..]);
+    let deps = deps
+        .into_inner()
+        .into_iter()
+        .filter_map(|dep| match dep {
+            HtmlDep::Inline(code) => {
+                let code = code.trim();
+                (!code.is_empty()).then(|| HtmlDep::Inline(code.to_string()))
+            }
+            dep => Some(dep),
+        })
+        .collect();
+    Ok(SplitHtml {
+        template: result,
+        deps,
+        nonce: nonce.into_inner(),
+    })
+}
+
+// the placeholder of the removed tags, which can't be in the html
+const REMOVED: &str = "\0mako-removed\0";
+
+// the whole line of the tag if it's the only one in the line, so no blank
+// line is left when it's removed
+fn line_range(html: &str, range: Range<usize>) -> Range<usize> {
+    let before = &html[..range.start];
+    let start = before.trim_end_matches([' ', '\t']).len();
+    let after = &html[range.end..];
+    let end = range.end + (after.len() - after.trim_start_matches([' ', '\t']).len());
+    if (start == 0 || before[..start].ends_with('\n')) && html[end..].starts_with('\n') {
+        start..end + 1
+    } else {
+        range
+    }
+}
+
+fn is_attr(el: &Element, name: &str, value: &str) -> bool {
+    el.get_attribute(name)
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case(value))
+}
+
+// e.g. `rel="shortcut icon"`
+fn has_token(el: &Element, name: &str, token: &str) -> bool {
+    el.get_attribute(name).is_some_and(|v| {
+        v.split_ascii_whitespace()
+            .any(|t| t.eq_ignore_ascii_case(token))
+    })
+}
+
+fn rewrite_attrs(
+    el: &mut Element,
+    names: &[&str],
+    asset_url: &impl Fn(&str) -> Option<String>,
+) -> HandlerResult {
+    for name in names {
+        let Some(value) = el.get_attribute(name) else {
+            continue;
+        };
+        let rewritten = if *name == "srcset" {
+            rewrite_srcset(&value, asset_url)
+        } else {
+            rewrite_url(&value, asset_url)
+        };
+        if let Some(rewritten) = rewritten {
+            el.set_attribute(name, &rewritten)?;
+        }
+    }
+    Ok(())
+}
+
+const ASSET_RELS: &[&str] = &[
+    "icon",
+    "apple-touch-icon",
+    "apple-touch-startup-image",
+    "mask-icon",
+    "manifest",
+];
+
+// keep the query and the fragment, e.g. `sprite.svg#icon`
+fn rewrite_url(url: &str, asset_url: &impl Fn(&str) -> Option<String>) -> Option<String> {
+    if !is_local_url(url) {
+        return None;
+    }
+    let index = url.find(['?', '#']).unwrap_or(url.len());
+    let (path, suffix) = url.split_at(index);
+    asset_url(path).map(|path| format!("{}{}", path, suffix))
+}
+
+// e.g. `logo.png 1x, logo@2x.png 2x`
+fn rewrite_srcset(srcset: &str, asset_url: &impl Fn(&str) -> Option<String>) -> Option<String> {
+    let mut rewritten = false;
+    let candidates = srcset
+        .split(',')
+        .map(|candidate| {
+            let candidate = candidate.trim();
+            let (url, descriptor) = candidate
+                .split_once(char::is_whitespace)
+                .unwrap_or((candidate, ""));
+            match rewrite_url(url, asset_url) {
+                Some(url) => {
+                    rewritten = true;
+                    format!("{} {}", url, descriptor.trim()).trim().to_string()
+                }
+                None => candidate.to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+    rewritten.then(|| candidates.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+    use crate::compiler::{Args, Compiler};
+    use crate::utils::output_fs::{MemoryFileSystem, OutputFileSystem};
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_split_html() {
+        let html = r#"<!DOCTYPE html>
+<html>
+<head>
+  <link rel="icon" href="./favicon.png">
+  <link rel="stylesheet" href="./style.css" />
+  <link rel="stylesheet" href="https://cdn.com/reset.css" />
+  <!-- <script type="module" src="./commented.js"></script> -->
+  <script type="module" src="/src/main.js" nonce="abc"></script>
+</head>
+<body>
+  <img src='logo.png?v=1' srcset="logo.png 1x, logo@2x.png 2x" alt="logo">
+  <img src="https://cdn.com/remote.png">
+  <script src="./legacy.js"></script>
+  <script type="module">
+    import './inline';
+  </script>
+</body>
+</html>"#;
+        let split = split_html(html, |url| {
+            (!url.contains("missing")).then(|| format!("/{}.hash", url.trim_start_matches("./")))
+        })
+        .unwrap();
+        assert_eq!(
+            split.deps,
+            vec![
+                HtmlDep::Url("./style.css".to_string()),
+                HtmlDep::Url("/src/main.js".to_string()),
+                HtmlDep::Inline("import './inline';".to_string()),
+            ]
+        );
+        assert_eq!(split.nonce, Some("abc".to_string()));
+        let template = split.template;
+        assert!(template.contains(r#"<link rel="icon" href="/favicon.png.hash">"#));
+        assert!(template.contains(
+            r#"<img src="/logo.png.hash?v=1" srcset="/logo.png.hash 1x, /logo@2x.png.hash 2x" alt="logo">"#
+        ));
+        assert!(template.contains(r#"<img src="https://cdn.com/remote.png">"#));
+        assert!(template.contains(r#"href="https://cdn.com/reset.css""#));
+        assert!(template.contains(r#"<script type="module" src="./commented.js">"#));
+        assert!(template.contains(r#"<script src="./legacy.js"></script>"#));
+        assert!(!template.contains("./style.css"));
+        assert!(!template.contains("/src/main.js"));
+        assert!(!template.contains("./inline"));
+        assert!(!template.contains("\n\n"), "{}", template);
+    }
+
+    #[test]
+    fn test_html_entry() {
+        let compiler = setup_compiler("test/build/html-entry", false);
+        compiler.compile().unwrap();
+        let output_path = &compiler.context.config.output.path;
+        let read = |name: &str| std::fs::read_to_string(output_path.join(name)).unwrap();
+
+        let html = read("index.html");
+        assert!(
+            html.contains(r#"<script src="/index.js" nonce="{{nonce}}"></script>"#),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains(r#"<link rel="stylesheet" href="/index.css" nonce="{{nonce}}" />"#),
+            "{}",
+            html
+        );
+        assert!(!html.contains(r#"type="module""#), "{}", html);
+        assert!(html.contains("<title>html entry</title>"), "{}", html);
+        let logo = Regex::new(r#"<img src="/(logo\.\w+\.png)""#)
+            .unwrap()
+            .captures(&html)
+            .unwrap_or_else(|| panic!("{}", html))[1]
+            .to_string();
+        assert!(output_path.join(logo).exists());
+
+        let js = read("index.js");
+        assert!(js.contains("main script"), "{}", js);
+        assert!(js.contains("inline script"), "{}", js);
+        let css = read("index.css");
+        assert!(css.contains("color: red"), "{}", css);
+    }
+
+    #[test]
+    fn test_html_entry_with_hmr() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/html-entry");
+        let config = Config::new(&root, None, Some(r#"{"mode":"development"}"#)).unwrap();
+        assert!(config.hmr.is_some());
+        let output_fs = Arc::new(MemoryFileSystem::new());
+        let compiler = Compiler::new_with_output_fs(
+            config,
+            root,
+            Args { watch: true },
+            None,
+            Some(output_fs.clone()),
+        )
+        .unwrap();
+        compiler.compile().unwrap();
+        let output_path = &compiler.context.config.output.path;
+
+        // the entry is wrapped with the hmr client
+        let read = |name: &str| {
+            String::from_utf8(output_fs.read(&output_path.join(name)).unwrap()).unwrap()
+        };
+        let js = read("index.js");
+        assert!(js.contains("index.html?hmr"), "{}", js);
+        assert!(js.contains("main script"), "{}", js);
+        assert!(js.contains("inline script"), "{}", js);
+        let css = read("index.css");
+        assert!(css.contains("color: red"), "{}", css);
+    }
+}
@@ -0,0 +1,286 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+
+use crate::ast::file::{Content, File, JsContent};
+use crate::build::load::Load;
+use crate::compiler::Context;
+use crate::plugin::{Plugin, PluginLoadParam};
+use crate::utils::hash::content_hash;
+
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+const CACHE_DIR: &str = "node_modules/.cache_mako/images";
+
+/// Optimize, resize and convert image assets.
+///
+/// e.g.
+/// import a from './a.png?format=webp&w=800'; // => url of resized webp
+/// import b from './b.png?srcset=400,800'; // => { 400: url, 800: url }
+pub struct ImagePlugin {}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ImageOptions {
+    format: ImageFormat,
+    width: Option<u32>,
+    quality: u8,
+}
+
+impl ImageOptions {
+    fn cache_key(&self, source: &[u8]) -> String {
+        let mut context = md5::Context::new();
+        context.consume(source);
+        context.consume(format!(
+            "{:?}:{:?}:{}",
+            self.format, self.width, self.quality
+        ));
+        format!("{:x}", context.compute())
+    }
+}
+
+impl Plugin for ImagePlugin {
+    fn name(&self) -> &str {
+        "image"
+    }
+
+    fn load(&self, param: &PluginLoadParam, context: &Arc<Context>) -> Result<Option<Content>> {
+        let file = param.file;
+        if !IMAGE_EXTENSIONS.contains(&file.extname.as_str()) {
+            return Ok(None);
+        }
+        let has_query = file.has_param("format") || file.has_param("w") || file.has_param("srcset");
+        if !has_query {
+            // only optimize images which will be emitted, small ones are inlined as base64
+            let should_optimize = context.config.image_optimization.is_some()
+                && file.get_file_size()? > context.config.inline_limit as u64;
+            if !should_optimize {
+                return Ok(None);
+            }
+        }
+
+        let format = match file.param("format") {
+            Some(format) => parse_format(&format)?,
+            None => parse_format(&file.extname)?,
+        };
+        let quality = match file.param("quality") {
+            Some(quality) => quality
+                .parse::<u8>()
+                .map_err(|_| anyhow!("Invalid image quality \"{}\"", quality))?,
+            None => {
+                context
+                    .config
+                    .image_optimization
+                    .clone()
+                    .unwrap_or_default()
+                    .quality
+            }
+        };
+        let width = file.param("w").map(|w| parse_width(&w)).transpose()?;
+        let options = ImageOptions {
+            format,
+            width,
+            quality,
+        };
+
+        let content = if let Some(srcset) = file.param("srcset") {
+            let urls = srcset
+                .split(',')
+                .map(|w| {
+                    let width = parse_width(w.trim())?;
+                    let options = ImageOptions {
+                        width: Some(width),
+                        ..options.clone()
+                    };
+                    Ok(format!(
+                        "\"{}\": {}",
+                        width,
+                        emit_image(file, &options, context)?
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            format!("module.exports = {{{}}};", urls.join(","))
+        } else {
+            format!("module.exports = {};", emit_image(file, &options, context)?)
+        };
+        Ok(Some(Content::Js(JsContent {
+            content,
+            ..Default::default()
+        })))
+    }
+}
+
+fn parse_format(format: &str) -> Result<ImageFormat> {
+    match format {
+        "png" => Ok(ImageFormat::Png),
+        "jpg" | "jpeg" => Ok(ImageFormat::Jpeg),
+        "webp" => Ok(ImageFormat::WebP),
+        _ => Err(anyhow!("Unsupported image format \"{}\"", format)),
+    }
+}
+
+fn parse_width(width: &str) -> Result<u32> {
+    width
+        .parse::<u32>()
+        .ok()
+        .filter(|w| *w > 0)
+        .ok_or_else(|| anyhow!("Invalid image width \"{}\"", width))
+}
+
+// returns the url expression of emitted image
+fn emit_image(file: &File, options: &ImageOptions, context: &Arc<Context>) -> Result<String> {
+    let final_file_name = match optimize_with_cache(file, options, context) {
+        Ok((cache_path, content)) => {
+            let ext = options.format.extensions_str()[0];
+            let stem = file
+                .pathname
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let final_file_name = match options.width {
+                Some(width) => format!(
+                    "{}-{}.{}.{}",
+                    stem,
+                    width,
+                    content_hash(&content, &context.config.output),
+                    ext
+                ),
+                None => format!(
+                    "{}.{}.{}",
+                    stem,
+                    content_hash(&content, &context.config.output),
+                    ext
+                ),
+            };
+            context.emit_assets(
+                cache_path.to_string_lossy().to_string(),
+                final_file_name,
+                true,
+            )?
+        }
+        Err(e) => {
+            // fallback to the original image, exotic images should not fail the build
+            eprintln!(
+                "{} Optimize image {} failed, the original image is used: {}",
+                "Warning".yellow(),
+                file.pathname.display(),
+                e
+            );
+            Load::emit_asset(file, context.clone())?
+        }
+    };
+    Ok(format!("`${{require.publicPath}}{}`", final_file_name))
+}
+
+// processing is expensive, so the result is cached on disk by content hash + options
+fn optimize_with_cache(
+    file: &File,
+    options: &ImageOptions,
+    context: &Arc<Context>,
+) -> Result<(PathBuf, Vec<u8>)> {
+    let source = fs::read(&file.pathname)?;
+    let cache_path = context.root.join(CACHE_DIR).join(format!(
+        "{}.{}",
+        options.cache_key(&source),
+        options.format.extensions_str()[0]
+    ));
+    if let Ok(content) = fs::read(&cache_path) {
+        return Ok((cache_path, content));
+    }
+    let content = optimize(&source, options)?;
+    fs::create_dir_all(cache_path.parent().unwrap())?;
+    fs::write(&cache_path, &content)?;
+    Ok((cache_path, content))
+}
+
+fn optimize(source: &[u8], options: &ImageOptions) -> Result<Vec<u8>> {
+    let source_format = image::guess_format(source)?;
+    let image = image::load_from_memory_with_format(source, source_format)?;
+    // never upscale
+    let (image, resized) = match options.width {
+        Some(width) if width < image.width() => {
+            (image.resize(width, u32::MAX, FilterType::Lanczos3), true)
+        }
+        _ => (image, false),
+    };
+    let content = encode(&image, options)?;
+    // decoding and encoding again already strips the metadata, but a lossless
+    // re-encode may still be larger than the original one
+    if !resized && source_format == options.format && content.len() >= source.len() {
+        return Ok(source.to_vec());
+    }
+    Ok(content)
+}
+
+fn encode(image: &DynamicImage, options: &ImageOptions) -> Result<Vec<u8>> {
+    let mut buf = Cursor::new(vec![]);
+    match options.format {
+        ImageFormat::Png => image.write_with_encoder(PngEncoder::new_with_quality(
+            &mut buf,
+            CompressionType::Best,
+            PngFilterType::Adaptive,
+        ))?,
+        // jpeg has no alpha channel
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut buf, options.quality))?,
+        ImageFormat::WebP => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut buf))?,
+        _ => unreachable!(),
+    }
+    Ok(buf.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(parse_format("jpg").unwrap(), ImageFormat::Jpeg);
+        assert!(parse_format("gif").is_err());
+        assert_eq!(parse_width("800").unwrap(), 800);
+        assert!(parse_width("0").is_err());
+        assert!(parse_width("abc").is_err());
+    }
+
+    #[test]
+    fn test_image_optimization() {
+        let compiler = setup_compiler("test/build/image-optimization", false);
+        fs::remove_dir_all(compiler.context.root.join(CACHE_DIR)).ok();
+        compiler.compile().unwrap();
+
+        let assets_info = compiler.context.assets_info.lock().unwrap();
+        let mut outputs = assets_info.values().cloned().collect::<Vec<_>>();
+        outputs.sort();
+        let output_path = &compiler.context.config.output.path;
+        let find = |prefix: &str, ext: &str| {
+            outputs
+                .iter()
+                .find(|o| o.starts_with(prefix) && o.ends_with(ext))
+                .map(|o| output_path.join(o))
+                .unwrap_or_else(|| panic!("{}*{} not found in {:?}", prefix, ext, outputs))
+        };
+
+        let webp = image::open(find("hero-16.", ".webp")).unwrap();
+        assert_eq!((webp.width(), webp.height()), (16, 16));
+        let srcset = image::open(find("hero-8.", ".png")).unwrap();
+        assert_eq!(srcset.width(), 8);
+        find("hero-16.", ".png");
+        find("hero.", ".jpg");
+        // broken image falls back to the original one
+        let broken = find("broken.", ".png");
+        assert_eq!(
+            fs::read(broken).unwrap(),
+            fs::read(compiler.context.root.join("broken.png")).unwrap()
+        );
+        assert!(compiler.context.root.join(CACHE_DIR).exists());
+    }
+}
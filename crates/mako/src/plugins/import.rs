@@ -1,12 +1,15 @@
+use std::collections::HashMap;
+
 use anyhow;
+use colored::Colorize;
 use swc_core::ecma::ast::{
-    ImportDefaultSpecifier, ImportNamedSpecifier, ImportSpecifier, Module, ModuleDecl,
+    ImportDecl, ImportDefaultSpecifier, ImportNamedSpecifier, ImportSpecifier, Module, ModuleDecl,
     ModuleExportName, ModuleItem, Str,
 };
 use swc_core::ecma::atoms::JsWord;
 use swc_core::ecma::visit::{VisitMut, VisitMutWith};
 
-use crate::config::{TransformImportConfig, TransformImportStyle};
+use crate::config::{TransformImportConfig, TransformImportStyle, TransformImportsConfig};
 use crate::plugin::Plugin;
 
 pub struct ImportVisitor<'a> {
@@ -160,6 +163,128 @@ impl<'a> VisitMut for ImportVisitor<'a> {
     }
 }
 
+/// Rewrites the named imports of the packages in `transformImports` to the
+/// default imports of the files of the members, so the barrel files with side
+/// effects are not in the graph. The default and namespace imports of them
+/// are kept, with warnings.
+pub struct TransformImportsVisitor<'a> {
+    config: &'a HashMap<String, TransformImportsConfig>,
+    pub warnings: Vec<String>,
+    pub error: Option<anyhow::Error>,
+}
+
+impl<'a> TransformImportsVisitor<'a> {
+    pub fn new(config: &'a HashMap<String, TransformImportsConfig>) -> Self {
+        Self {
+            config,
+            warnings: vec![],
+            error: None,
+        }
+    }
+
+    fn transform(
+        &mut self,
+        mut decl: ImportDecl,
+        config: &TransformImportsConfig,
+    ) -> anyhow::Result<Vec<ModuleItem>> {
+        let package = decl.src.value.to_string();
+        let mut kept = vec![];
+        let mut expanded = vec![];
+        for specifier in std::mem::take(&mut decl.specifiers) {
+            let member = match &specifier {
+                ImportSpecifier::Named(named) if !named.is_type_only => match &named.imported {
+                    Some(ModuleExportName::Ident(ident)) => ident.sym.to_string(),
+                    Some(ModuleExportName::Str(str)) => str.value.to_string(),
+                    None => named.local.sym.to_string(),
+                },
+                ImportSpecifier::Named(_) => {
+                    kept.push(specifier);
+                    continue;
+                }
+                ImportSpecifier::Default(_) => "default".to_string(),
+                ImportSpecifier::Namespace(_) => {
+                    self.warnings.push(format!(
+                        "the namespace import of {} is not transformed by transformImports, the whole package is bundled",
+                        package
+                    ));
+                    kept.push(specifier);
+                    continue;
+                }
+            };
+            if member == "default" {
+                self.warnings.push(format!(
+                    "the default import of {} is not transformed by transformImports, the whole package is bundled",
+                    package
+                ));
+            }
+            if member == "default" || config.skip.contains(&member) {
+                kept.push(specifier);
+                continue;
+            }
+            let ImportSpecifier::Named(named) = specifier else {
+                unreachable!()
+            };
+            let (source, style) = config.render(&member)?;
+            let mut member_decl = decl.clone();
+            member_decl.specifiers = vec![ImportSpecifier::Default(ImportDefaultSpecifier {
+                span: named.span,
+                local: named.local,
+            })];
+            *member_decl.src = Str {
+                value: JsWord::from(source),
+                span: decl.src.span,
+                raw: None,
+            };
+            expanded.push(ModuleItem::ModuleDecl(ModuleDecl::Import(member_decl)));
+            if let Some(style) = style {
+                let mut style_decl = decl.clone();
+                *style_decl.src = Str {
+                    value: JsWord::from(style),
+                    span: decl.src.span,
+                    raw: None,
+                };
+                expanded.push(ModuleItem::ModuleDecl(ModuleDecl::Import(style_decl)));
+            }
+        }
+        let mut items = vec![];
+        // the side effect imports and the imports with the kept specifiers
+        if !kept.is_empty() || expanded.is_empty() {
+            decl.specifiers = kept;
+            items.push(ModuleItem::ModuleDecl(ModuleDecl::Import(decl)));
+        }
+        items.extend(expanded);
+        Ok(items)
+    }
+}
+
+impl<'a> VisitMut for TransformImportsVisitor<'a> {
+    fn visit_mut_module(&mut self, module: &mut Module) {
+        let mut body = Vec::with_capacity(module.body.len());
+        for item in std::mem::take(&mut module.body) {
+            let config = match &item {
+                ModuleItem::ModuleDecl(ModuleDecl::Import(decl))
+                    if !decl.type_only && !decl.specifiers.is_empty() =>
+                {
+                    self.config.get(decl.src.value.as_ref())
+                }
+                _ => None,
+            };
+            match (item, config) {
+                (ModuleItem::ModuleDecl(ModuleDecl::Import(decl)), Some(config)) => {
+                    match self.transform(decl, config) {
+                        Ok(items) => body.extend(items),
+                        Err(err) => {
+                            self.error.get_or_insert(err);
+                        }
+                    }
+                }
+                (item, _) => body.push(item),
+            }
+        }
+        module.body = body;
+    }
+}
+
 pub struct ImportPlugin {}
 
 impl Plugin for ImportPlugin {
@@ -174,13 +299,31 @@ impl Plugin for ImportPlugin {
         context: &std::sync::Arc<crate::compiler::Context>,
     ) -> anyhow::Result<()> {
         // skip node_modules to keep behavior same as umi, and skip if no config
-        if param.path.contains("node_modules") || context.config.transform_import.is_empty() {
+        if param.path.contains("node_modules") {
             return Ok(());
         }
 
-        ast.visit_mut_with(&mut ImportVisitor {
-            config: &context.config.transform_import,
-        });
+        if !context.config.transform_import.is_empty() {
+            ast.visit_mut_with(&mut ImportVisitor {
+                config: &context.config.transform_import,
+            });
+        }
+
+        if !context.config.transform_imports.is_empty() {
+            let mut visitor = TransformImportsVisitor::new(&context.config.transform_imports);
+            ast.visit_mut_with(&mut visitor);
+            if let Some(err) = visitor.error {
+                return Err(err);
+            }
+            for warning in visitor.warnings {
+                println!(
+                    "{}: {}, in {}",
+                    "warning".to_string().yellow(),
+                    warning,
+                    param.path
+                );
+            }
+        }
 
         Ok(())
     }
@@ -188,14 +331,16 @@ impl Plugin for ImportPlugin {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::sync::Arc;
 
     use swc_core::ecma::visit::VisitMutWith;
 
     use crate::ast::js_ast::JsAst;
     use crate::compiler::Context;
-    use crate::config::{TransformImportConfig, TransformImportStyle};
-    use crate::plugins::import::ImportVisitor;
+    use crate::config::{TransformImportConfig, TransformImportStyle, TransformImportsConfig};
+    use crate::plugins::import::{ImportVisitor, TransformImportsVisitor};
+    use crate::utils::test_helper::setup_compiler;
 
     #[test]
     fn test_multi() {
@@ -352,6 +497,140 @@ import DatePicker from "antd/lib/date-picker";
         );
     }
 
+    #[test]
+    fn test_transform_imports() {
+        let config = HashMap::from([(
+            "antd".to_string(),
+            TransformImportsConfig {
+                transform: "antd/es/{{ kebabCase member }}".to_string(),
+                style: Some("antd/es/{{kebabCase member}}/style".to_string()),
+                skip: vec!["version".to_string()],
+            },
+        )]);
+        let (code, warnings) = generate_transform_imports(
+            r#"
+import { Button, DatePicker as Picker, version } from "antd";
+import { useState } from "react";
+        "#,
+            &config,
+        );
+        assert_eq!(
+            code,
+            r#"
+import { version } from "antd";
+import Button from "antd/es/button";
+import "antd/es/button/style";
+import Picker from "antd/es/date-picker";
+import "antd/es/date-picker/style";
+import { useState } from "react";
+
+//# sourceMappingURL=/test/path.map
+        "#
+            .trim(),
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_transform_imports_helpers() {
+        let config = TransformImportsConfig {
+            transform: "icons/{{ camelCase member }}/{{ member }}".to_string(),
+            style: None,
+            skip: vec![],
+        };
+        assert_eq!(
+            config.render("ArrowLeft").unwrap(),
+            ("icons/arrowLeft/ArrowLeft".to_string(), None)
+        );
+        let config = TransformImportsConfig {
+            transform: "icons/{{ snakeCase member }}".to_string(),
+            style: None,
+            skip: vec![],
+        };
+        assert!(config
+            .render("ArrowLeft")
+            .unwrap_err()
+            .to_string()
+            .contains("unknown helper snakeCase"));
+    }
+
+    #[test]
+    fn test_transform_imports_warnings() {
+        let config = HashMap::from([(
+            "antd".to_string(),
+            TransformImportsConfig {
+                transform: "antd/es/{{ kebabCase member }}".to_string(),
+                style: None,
+                skip: vec![],
+            },
+        )]);
+        let (code, warnings) = generate_transform_imports(
+            r#"
+import antd, { Button } from "antd";
+import * as all from "antd";
+import "antd";
+        "#,
+            &config,
+        );
+        assert_eq!(
+            code,
+            r#"
+import antd from "antd";
+import Button from "antd/es/button";
+import * as all from "antd";
+import "antd";
+
+//# sourceMappingURL=/test/path.map
+        "#
+            .trim(),
+        );
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("default import of antd"));
+        assert!(warnings[1].contains("namespace import of antd"));
+    }
+
+    #[test]
+    fn test_transform_imports_graph() {
+        let compiler = setup_compiler("test/build/transform-imports", false);
+        compiler.compile().unwrap();
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let mut ids = module_graph
+            .modules()
+            .iter()
+            .filter_map(|module| {
+                module
+                    .id
+                    .id
+                    .split_once("node_modules/")
+                    .map(|(_, id)| id.to_string())
+            })
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                "ui-lib/es/date-picker/index.js",
+                "ui-lib/es/date-picker/style/index.css",
+            ]
+        );
+    }
+
+    fn generate_transform_imports(
+        code: &str,
+        config: &HashMap<String, TransformImportsConfig>,
+    ) -> (String, Vec<String>) {
+        let path = "/test/path";
+        let context: Arc<Context> = Arc::new(Default::default());
+        let mut ast = JsAst::build(path, code, context.clone()).unwrap();
+        let mut visitor = TransformImportsVisitor::new(config);
+        ast.ast.visit_mut_with(&mut visitor);
+        assert!(visitor.error.is_none());
+        (
+            ast.generate(context.clone()).unwrap().code,
+            visitor.warnings,
+        )
+    }
+
     fn generate(code: &str, config: &Vec<TransformImportConfig>) -> String {
         let path = "/test/path";
         let context: Arc<Context> = Arc::new(Default::default());
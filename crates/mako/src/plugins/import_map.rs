@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use semver::Version;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::compiler::Context;
+use crate::generate::csp_report::nonce_attr;
+use crate::plugin::Plugin;
+use crate::resolve::get_module_external_specifiers;
+
+pub(crate) const IMPORT_MAP_FILE_NAME: &str = "importmap.json";
+
+/// Write `importmap.json` for the externals with the `module` type, which are
+/// imported natively and resolved by the browser.
+pub struct ImportMapPlugin {}
+
+#[derive(Serialize, Debug)]
+struct ImportMap {
+    imports: BTreeMap<String, String>,
+}
+
+impl Plugin for ImportMapPlugin {
+    fn name(&self) -> &str {
+        "import_map"
+    }
+
+    // fail before the modules are built if any external can't be mapped
+    fn build_start(&self, context: &Arc<Context>) -> Result<()> {
+        import_map(context)?;
+        Ok(())
+    }
+
+    fn write_bundle(&self, context: &Arc<Context>) -> Result<()> {
+        let import_map = import_map(context)?;
+        let path = context.config.output.path.join(IMPORT_MAP_FILE_NAME);
+        context
+            .output_fs
+            .write(&path, serde_json::to_string_pretty(&import_map)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// The `<script type="importmap">` tag for the html if importMap.inject, it
+/// must come before the scripts which import the externals.
+pub(crate) fn import_map_tag(
+    nonce: Option<&str>,
+    context: &Arc<Context>,
+) -> Result<Option<String>> {
+    if !context
+        .config
+        .import_map
+        .as_ref()
+        .is_some_and(|config| config.inject)
+    {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        r#"<script type="importmap"{}>{}</script>"#,
+        nonce_attr(nonce),
+        serde_json::to_string(&import_map(context)?)?
+    )))
+}
+
+fn import_map(context: &Arc<Context>) -> Result<ImportMap> {
+    let config = context.config.import_map.as_ref().unwrap();
+    let specifiers = get_module_external_specifiers(&context.config.externals);
+    if specifiers.is_empty() {
+        return Ok(ImportMap {
+            imports: BTreeMap::new(),
+        });
+    }
+    let package_json = read_json(&context.root.join("package.json"))
+        .ok_or_else(|| anyhow!("importMap requires package.json in {:?}", context.root))?;
+    let mut imports = BTreeMap::new();
+    for specifier in specifiers {
+        let (name, path) = split_specifier(&specifier);
+        let version = package_version(&package_json, &specifier, name, context)?;
+        let url = config
+            .url
+            .replace("{name}", name)
+            .replace("{version}", &version)
+            .replace("{path}", path);
+        imports.insert(specifier, url);
+    }
+    Ok(ImportMap { imports })
+}
+
+// react-dom/client -> (react-dom, /client), @scope/name keeps the scope
+fn split_specifier(specifier: &str) -> (&str, &str) {
+    let name_segments = if specifier.starts_with('@') { 2 } else { 1 };
+    match specifier.match_indices('/').nth(name_segments - 1) {
+        Some((index, _)) => specifier.split_at(index),
+        None => (specifier, ""),
+    }
+}
+
+// the installed version, or the declared one if it's exact
+fn package_version(
+    package_json: &Value,
+    specifier: &str,
+    name: &str,
+    context: &Arc<Context>,
+) -> Result<String> {
+    let declared = ["dependencies", "devDependencies", "peerDependencies"]
+        .iter()
+        .find_map(|field| package_json.get(field)?.get(name)?.as_str())
+        .ok_or_else(|| {
+            anyhow!(
+                "External {} is not in the dependencies of package.json, add {} to map it in importMap",
+                specifier,
+                name
+            )
+        })?;
+    let installed = read_json(
+        &context
+            .root
+            .join("node_modules")
+            .join(name)
+            .join("package.json"),
+    )
+    .and_then(|json| json.get("version")?.as_str().map(String::from));
+    installed
+        .or_else(|| {
+            let version = declared.trim_start_matches(['^', '~', '=', 'v']);
+            Version::parse(version).ok().map(|_| version.to_string())
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "Version of external {} can't be resolved from {}@{}, install it or use an exact version in package.json",
+                specifier,
+                name,
+                declared
+            )
+        })
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_specifier;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_split_specifier() {
+        assert_eq!(split_specifier("react"), ("react", ""));
+        assert_eq!(
+            split_specifier("react-dom/client"),
+            ("react-dom", "/client")
+        );
+        assert_eq!(split_specifier("@scope/name"), ("@scope/name", ""));
+        assert_eq!(split_specifier("@scope/name/a/b"), ("@scope/name", "/a/b"));
+    }
+
+    #[test]
+    fn test_import_map() {
+        let compiler = setup_compiler("test/build/import-map", false);
+        compiler.compile().unwrap();
+        let context = &compiler.context;
+        let read = |name: &str| {
+            String::from_utf8(
+                context
+                    .output_fs
+                    .read(&context.config.output.path.join(name))
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+
+        let import_map: serde_json::Value = serde_json::from_str(&read("importmap.json")).unwrap();
+        assert_eq!(
+            import_map,
+            serde_json::json!({
+                "imports": {
+                    // the installed version
+                    "foo": "https://esm.sh/foo@1.2.3",
+                    // the declared version, with the subpath
+                    "@scope/bar/client": "https://esm.sh/@scope/bar@2.0.0/client",
+                }
+            })
+        );
+        let js = read("index.js");
+        assert!(js.contains(r#"import("foo")"#), "{}", js);
+        let html = read("index.html");
+        assert!(
+            html.find(r#"<script type="importmap">"#).unwrap()
+                < html.find(r#"<script src="/index.js">"#).unwrap(),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn test_import_map_missing_dependency() {
+        let compiler = setup_compiler("test/build/import-map-missing", false);
+        let err = compiler.compile().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("External baz is not in the dependencies of package.json"),
+            "{}",
+            err
+        );
+    }
+}
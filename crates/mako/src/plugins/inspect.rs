@@ -0,0 +1,260 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use serde::Serialize;
+use url::form_urlencoded;
+
+use crate::compiler::Context;
+use crate::dev::{DevRequest, DevResponse, DevServerHandle, DevServerRoute};
+use crate::module::{relative_module_id, Dependency, ModuleId};
+use crate::plugin::Plugin;
+
+/// Serves a viewer of the module graph at `/__inspect` of the dev server,
+/// the modules with their dependencies and dependents, and `graph.json` for
+/// the tools. The module page can rebuild the module as if it was modified.
+pub struct InspectPlugin {}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectModule {
+    pub id: String,
+    pub is_entry: bool,
+    pub external: Option<String>,
+    pub dependencies: Vec<String>,
+}
+
+impl Plugin for InspectPlugin {
+    fn name(&self) -> &str {
+        "inspect"
+    }
+
+    fn dev_server_routes(&self) -> Vec<DevServerRoute> {
+        vec![
+            DevServerRoute::get("/__inspect", |_, handle| {
+                Ok(DevResponse::html(render_index(handle.context())))
+            }),
+            DevServerRoute::get("/__inspect/graph.json", |_, handle| {
+                DevResponse::json(&inspect_modules(handle.context()))
+            }),
+            DevServerRoute::get("/__inspect/module", |request, handle| {
+                let Some(id) = request.query_param("id") else {
+                    return Ok(DevResponse::text("Missing id").status(400));
+                };
+                Ok(match render_module(&ModuleId::new(id), handle.context()) {
+                    Some(html) => DevResponse::html(html),
+                    None => DevResponse::text("Module not found").status(404),
+                })
+            }),
+            DevServerRoute::post("/__inspect/rebuild", rebuild),
+        ]
+    }
+}
+
+fn rebuild(request: &DevRequest, handle: &DevServerHandle) -> Result<DevResponse> {
+    let Some(id) = request.query_param("id") else {
+        return Ok(DevResponse::text("Missing id").status(400));
+    };
+    let module = handle
+        .context()
+        .module_graph_view()
+        .get_module(&ModuleId::new(id));
+    // the externals and the ignored have no files to rebuild
+    let Some(path) = module.filter(|module| module.built).and_then(|m| m.path) else {
+        return Ok(DevResponse::text("Module not found").status(404));
+    };
+    Ok(if handle.rebuild(vec![path]) {
+        DevResponse::text("Rebuilding").status(202)
+    } else {
+        DevResponse::text("Not watching").status(503)
+    })
+}
+
+pub fn inspect_modules(context: &Context) -> Vec<InspectModule> {
+    let module_graph = context.module_graph.read().unwrap();
+    let mut modules = module_graph
+        .modules()
+        .into_iter()
+        .map(|module| InspectModule {
+            id: module.id.id.to_string(),
+            is_entry: module.is_entry,
+            external: module.info.as_ref().and_then(|info| info.external.clone()),
+            dependencies: module_graph
+                .get_dependencies(&module.id)
+                .into_iter()
+                .map(|(id, _)| id.id.to_string())
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    modules.sort_by(|a, b| a.id.cmp(&b.id));
+    modules
+}
+
+fn render_index(context: &Context) -> String {
+    let modules = inspect_modules(context);
+    let mut dependents = std::collections::HashMap::<&str, usize>::new();
+    for module in &modules {
+        for dependency in &module.dependencies {
+            *dependents.entry(dependency.as_str()).or_default() += 1;
+        }
+    }
+    let mut rows = String::new();
+    for module in &modules {
+        let _ = write!(
+            rows,
+            "<tr><td>{}{}</td><td>{}</td><td>{}</td></tr>",
+            module_link(&module.id, context),
+            if module.is_entry { " (entry)" } else { "" },
+            module.dependencies.len(),
+            dependents.get(module.id.as_str()).unwrap_or(&0),
+        );
+    }
+    render_page(
+        &format!("Modules ({})", modules.len()),
+        &format!(
+            "<table><tr><th>Module</th><th>Dependencies</th><th>Dependents</th></tr>{}</table>",
+            rows
+        ),
+    )
+}
+
+fn render_module(id: &ModuleId, context: &Context) -> Option<String> {
+    let view = context.module_graph_view();
+    let module = view.get_module(id)?;
+    let mut body = String::new();
+    if let Some(path) = &module.path {
+        let _ = write!(body, "<p>{}</p>", escape(&path.to_string_lossy()));
+    }
+    if let Some(external) = &module.external {
+        let _ = write!(body, "<p>external: {}</p>", escape(external));
+    }
+    let render_edges = |title: &str, edges: Vec<(ModuleId, Dependency)>| {
+        let mut items = String::new();
+        for (id, dependency) in &edges {
+            let _ = write!(
+                items,
+                "<li>{} <code>{}</code></li>",
+                module_link(&id.id, context),
+                escape(&dependency.source)
+            );
+        }
+        format!("<h2>{} ({})</h2><ul>{}</ul>", title, edges.len(), items)
+    };
+    body.push_str(&render_edges("Dependencies", view.dependencies_of(id)));
+    body.push_str(&render_edges("Dependents", view.dependents_of(id)));
+    if module.built {
+        let _ = write!(
+            body,
+            r#"<form method="post" action="/__inspect/rebuild?id={}"><button>Rebuild</button></form>"#,
+            encode(&id.id)
+        );
+    }
+    Some(render_page(
+        &relative_module_id(&id.id, &context.root),
+        &body,
+    ))
+}
+
+fn render_page(title: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>{title} - Mako Inspect</title></head><body><p><a href="/__inspect">Modules</a> | <a href="/__inspect/graph.json">graph.json</a></p><h1>{title}</h1>{body}</body></html>"#,
+        title = escape(title),
+        body = body
+    )
+}
+
+fn module_link(id: &str, context: &Context) -> String {
+    format!(
+        r#"<a href="/__inspect/module?id={}">{}</a>"#,
+        encode(id),
+        escape(&relative_module_id(id, &context.root))
+    )
+}
+
+fn encode(value: &str) -> String {
+    form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hyper::{HeaderMap, Method};
+
+    use super::*;
+    use crate::dev::DevServerHandle;
+    use crate::utils::test_helper::setup_compiler;
+
+    fn request(route: &DevServerRoute, handle: &DevServerHandle, query: &str) -> DevResponse {
+        let request = DevRequest {
+            method: route.method.clone().unwrap(),
+            path: route.path.clone(),
+            query: Some(query.to_string()),
+            headers: HeaderMap::new(),
+            body: vec![],
+        };
+        (route.handler)(&request, handle).unwrap()
+    }
+
+    #[test]
+    fn test_inspect() {
+        let compiler = setup_compiler("test/build/inspect", false);
+        compiler.compile().unwrap();
+        let handle = DevServerHandle::new(Arc::new(compiler));
+        let routes = InspectPlugin {}.dev_server_routes();
+        let route = |method: Method, path: &str| {
+            routes
+                .iter()
+                .find(|route| route.matches(&method, path))
+                .unwrap()
+        };
+        let root = handle.context().root.clone();
+        let index = root.join("src/index.ts").to_string_lossy().to_string();
+        let foo = root.join("src/foo.ts").to_string_lossy().to_string();
+
+        let modules = inspect_modules(handle.context());
+        let entry = modules.iter().find(|module| module.id == index).unwrap();
+        assert!(entry.is_entry);
+        assert_eq!(entry.dependencies, vec![foo.clone()]);
+
+        let response = request(route(Method::GET, "/__inspect"), &handle, "");
+        let html = String::from_utf8(response.body).unwrap();
+        assert!(
+            html.contains("src/foo.ts</a></td><td>0</td><td>1</td>"),
+            "{}",
+            html
+        );
+
+        let response = request(
+            route(Method::GET, "/__inspect/module"),
+            &handle,
+            &format!("id={}", encode(&foo)),
+        );
+        assert_eq!(response.status, 200);
+        let html = String::from_utf8(response.body).unwrap();
+        assert!(html.contains("<h2>Dependents (1)</h2>"), "{}", html);
+        assert!(html.contains("<code>./foo</code>"), "{}", html);
+
+        let response = request(
+            route(Method::GET, "/__inspect/module"),
+            &handle,
+            "id=missing",
+        );
+        assert_eq!(response.status, 404);
+
+        // no watcher in the build mode
+        let response = request(
+            route(Method::POST, "/__inspect/rebuild"),
+            &handle,
+            &format!("id={}", encode(&foo)),
+        );
+        assert_eq!(response.status, 503);
+    }
+}
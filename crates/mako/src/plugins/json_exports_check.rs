@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use swc_core::common::Span;
+use swc_core::ecma::ast::{
+    AssignExpr, AssignTarget, ExportSpecifier, Expr, ExprStmt, ImportDecl, ImportSpecifier,
+    ModuleExportName, ModuleItem, NamedExport, ObjectLit, Prop, PropName, PropOrSpread,
+    SimpleAssignTarget, Stmt,
+};
+use swc_core::ecma::visit::{Visit, VisitWith};
+
+use crate::ast::error::{code_frame, ErrorSpan};
+use crate::build::load::JS_EXTENSIONS;
+use crate::compiler::{Compiler, Context};
+use crate::config::JsonExportsCheckConfig;
+use crate::module::ModuleInfo;
+use crate::plugin::Plugin;
+
+// only list a part of the keys for large json files
+const MAX_LISTED_KEYS: usize = 20;
+
+/// Check the named imports of json, yaml, toml, xml and asset modules, their
+/// exports are synthesized as `module.exports = {...}` when loading, so named
+/// imports of missing keys are silently `undefined` at runtime.
+pub struct JsonExportsCheckPlugin {
+    pub config: JsonExportsCheckConfig,
+}
+
+impl Plugin for JsonExportsCheckPlugin {
+    fn name(&self) -> &str {
+        "json_exports_check"
+    }
+
+    fn after_build(&self, context: &Arc<Context>, _compiler: &Compiler) -> Result<()> {
+        let module_graph = context.module_graph.read().unwrap();
+        let mut errors = vec![];
+
+        for module in module_graph.modules() {
+            let info = match &module.info {
+                Some(info) if !info.file.is_under_node_modules => info,
+                _ => continue,
+            };
+            let ast = match info.ast.as_script() {
+                Some(ast) => ast,
+                None => continue,
+            };
+            let mut collector = CollectNamedImports { imports: vec![] };
+            ast.ast.visit_with(&mut collector);
+
+            for (source, imported, span) in collector.imports {
+                let keys = match module_graph
+                    .get_dependency_module_by_source(&module.id, &source)
+                    .and_then(|dep_id| module_graph.get_module(dep_id))
+                    .and_then(|dep| dep.info.as_ref())
+                    .and_then(synthesized_keys)
+                {
+                    Some(keys) => keys,
+                    None => continue,
+                };
+                if keys.contains(&imported) {
+                    continue;
+                }
+                let message = if keys.is_empty() {
+                    format!(
+                        "\"{}\" is not exported by \"{}\", it only has a default export",
+                        imported, source
+                    )
+                } else {
+                    format!(
+                        "\"{}\" is not exported by \"{}\", available keys: {}",
+                        imported,
+                        source,
+                        format_keys(&keys)
+                    )
+                };
+                errors.push(code_frame(ErrorSpan::Js(span), &message, context.clone()));
+            }
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        match self.config {
+            JsonExportsCheckConfig::Error => Err(anyhow!(errors.join("\n"))),
+            JsonExportsCheckConfig::Warn => {
+                for error in errors {
+                    eprintln!("{}: {}", "Warning".yellow(), error);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// keys of `module.exports = {...}` generated by load, None if the module is
+// not a json or asset module
+fn synthesized_keys(info: &ModuleInfo) -> Option<Vec<String>> {
+    // the exports of externals are unknown
+    if info.external.is_some() {
+        return None;
+    }
+    let file = &info.file;
+    if JS_EXTENSIONS.contains(&file.extname.as_str()) && !file.has_param("raw") {
+        return None;
+    }
+    let ast = info.ast.as_script()?;
+    let right = match ast.ast.body.as_slice() {
+        [ModuleItem::Stmt(Stmt::Expr(ExprStmt {
+            expr: box Expr::Assign(AssignExpr { left, right, .. }),
+            ..
+        }))] if is_module_exports(left) => right,
+        _ => return None,
+    };
+    match &**right {
+        Expr::Object(ObjectLit { props, .. }) => props
+            .iter()
+            .map(|prop| match prop {
+                PropOrSpread::Prop(box Prop::KeyValue(kv)) => match &kv.key {
+                    PropName::Str(str) => Some(str.value.to_string()),
+                    PropName::Ident(ident) => Some(ident.sym.to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect(),
+        // arrays, strings and urls of assets
+        _ => Some(vec![]),
+    }
+}
+
+fn is_module_exports(target: &AssignTarget) -> bool {
+    match target {
+        AssignTarget::Simple(SimpleAssignTarget::Member(member)) => {
+            member.obj.as_ident().is_some_and(|obj| obj.sym == "module")
+                && member
+                    .prop
+                    .as_ident()
+                    .is_some_and(|prop| prop.sym == "exports")
+        }
+        _ => false,
+    }
+}
+
+fn format_keys(keys: &[String]) -> String {
+    let mut listed = keys
+        .iter()
+        .take(MAX_LISTED_KEYS)
+        .map(|key| format!("\"{}\"", key))
+        .collect::<Vec<_>>();
+    if keys.len() > MAX_LISTED_KEYS {
+        listed.push(format!("... {} more", keys.len() - MAX_LISTED_KEYS));
+    }
+    listed.join(", ")
+}
+
+// (source, imported, span) of named imports and re-exports, default and
+// namespace imports are always valid
+pub(crate) struct CollectNamedImports {
+    pub imports: Vec<(String, String, Span)>,
+}
+
+impl Visit for CollectNamedImports {
+    fn visit_import_decl(&mut self, node: &ImportDecl) {
+        if node.type_only {
+            return;
+        }
+        for specifier in &node.specifiers {
+            if let ImportSpecifier::Named(named) = specifier {
+                if named.is_type_only {
+                    continue;
+                }
+                let imported = match &named.imported {
+                    Some(imported) => export_name(imported),
+                    None => named.local.sym.to_string(),
+                };
+                if imported != "default" {
+                    self.imports
+                        .push((node.src.value.to_string(), imported, named.span));
+                }
+            }
+        }
+    }
+
+    fn visit_named_export(&mut self, node: &NamedExport) {
+        let src = match &node.src {
+            Some(src) if !node.type_only => src,
+            _ => return,
+        };
+        for specifier in &node.specifiers {
+            if let ExportSpecifier::Named(named) = specifier {
+                let imported = export_name(&named.orig);
+                if imported != "default" {
+                    self.imports
+                        .push((src.value.to_string(), imported, named.span));
+                }
+            }
+        }
+    }
+}
+
+fn export_name(name: &ModuleExportName) -> String {
+    match name {
+        ModuleExportName::Ident(ident) => ident.sym.to_string(),
+        ModuleExportName::Str(str) => str.value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_keys;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_format_keys() {
+        let keys = (0..22).map(|i| format!("k{}", i)).collect::<Vec<_>>();
+        assert_eq!(format_keys(&keys[..2]), "\"k0\", \"k1\"");
+        assert!(format_keys(&keys).ends_with("\"k19\", ... 2 more"));
+    }
+
+    #[test]
+    fn test_json_exports_check() {
+        let compiler = setup_compiler("test/build/json-exports-check", false);
+        let err = compiler.compile().unwrap_err().to_string();
+        assert!(err.contains(
+            "\"verison\" is not exported by \"./package.json\", available keys: \"name\", \"version\""
+        ));
+        assert!(err.contains(
+            "\"height\" is not exported by \"./logo.png\", it only has a default export"
+        ));
+        assert!(err.contains("\"missing\" is not exported by \"./data.yaml\""));
+        // existing keys, default and namespace imports
+        assert!(!err.contains("\"version\" is not exported"));
+        assert!(!err.contains("\"title\" is not exported"));
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::compiler::Context;
+use crate::generate::chunk::ChunkId;
+use crate::module::{generate_module_id, ResolveType};
+use crate::plugin::Plugin;
+use crate::stats::StatsJsonMap;
+
+/// Writes the files which the dynamically imported modules need with
+/// `loadableManifest`, for the ssr to flush the chunks of the lazy components
+/// it renders into the html, like the manifest of `@loadable/webpack-plugin`.
+/// The `import()`s report the ids of the modules they import to
+/// `globalThis.__MAKO_LOADABLE_REPORT__` of the server, which are the keys of
+/// the manifest.
+pub struct LoadableManifestPlugin {}
+
+#[derive(Serialize, Default, Debug, PartialEq, Eq)]
+pub struct LoadableManifest {
+    /// module id -> file names relative to `output.path`, in the order to
+    /// load, the shared chunks first and css before js in each chunk
+    pub modules: BTreeMap<String, Vec<String>>,
+    /// chunk name of the magic comments, e.g. `webpackChunkName` -> file names
+    pub chunks: BTreeMap<String, Vec<String>>,
+}
+
+pub(crate) fn default_loadable_manifest_file_name() -> String {
+    "loadable-manifest.json".to_string()
+}
+
+const LOADABLE_RUNTIME: &str = r#"
+  // reports the ids of the dynamically imported modules, e.g. of the lazy
+  // components rendered by the ssr, see loadableManifest
+  requireModule.loadable = function(moduleId, promise) {
+    var report = global.__MAKO_LOADABLE_REPORT__;
+    if (typeof report === 'function') report(moduleId);
+    return promise;
+  };
+"#;
+
+impl Plugin for LoadableManifestPlugin {
+    fn name(&self) -> &str {
+        "loadable_manifest"
+    }
+
+    fn runtime_plugins(&self, _context: &Arc<Context>) -> Result<Vec<String>> {
+        Ok(vec![LOADABLE_RUNTIME.to_string()])
+    }
+
+    fn build_success(&self, _stats: &StatsJsonMap, context: &Arc<Context>) -> Result<()> {
+        // the chunks of the watch mode are served from the memory
+        if context.args.watch {
+            return Ok(());
+        }
+        let Some(config) = &context.config.loadable_manifest else {
+            return Ok(());
+        };
+        let manifest = generate_loadable_manifest(context);
+        context.output_fs.write(
+            &context.config.output.path.join(&config.file_name),
+            serde_json::to_string_pretty(&manifest)?.as_bytes(),
+        )?;
+        Ok(())
+    }
+}
+
+fn generate_loadable_manifest(context: &Arc<Context>) -> LoadableManifest {
+    let module_graph = context.module_graph.read().unwrap();
+    let chunk_graph = context.chunk_graph.read().unwrap();
+    // the aliased chunks share the files of the chunks they alias
+    let aliases = context
+        .stats_info
+        .get_chunk_aliases()
+        .into_iter()
+        .map(|alias| (alias.chunk_id, alias.alias_of))
+        .collect::<HashMap<_, _>>();
+
+    let mut manifest = LoadableManifest::default();
+    for module_id in module_graph.get_module_ids() {
+        for (id, dependency) in module_graph.get_dependencies(&module_id) {
+            let ResolveType::DynamicImport(import_options) = &dependency.resolve_type else {
+                continue;
+            };
+            // the eager and the weak imports load no chunks
+            if !dependency.resolve_type.is_async_chunk() {
+                continue;
+            }
+            let chunk_id: ChunkId = match import_options.get_chunk_name() {
+                Some(chunk_name) => generate_module_id(chunk_name, context),
+                None => id.generate(context),
+            }
+            .into();
+            if !chunk_graph.has_chunk(&chunk_id) {
+                continue;
+            }
+            // the same as the chunks ensured by the import
+            let chunk_ids = chunk_graph
+                .sync_dependencies_chunk(&chunk_id)
+                .into_iter()
+                .chain(std::iter::once(chunk_id.clone()))
+                .filter(|chunk_id| {
+                    chunk_graph
+                        .chunk(chunk_id)
+                        .is_some_and(|chunk| !chunk.modules.is_empty())
+                })
+                .map(|chunk_id| match aliases.get(chunk_id.id.as_str()) {
+                    Some(alias_of) => alias_of.as_str().into(),
+                    None => chunk_id,
+                })
+                .collect::<Vec<ChunkId>>();
+            let mut files = vec![];
+            for asset in context.stats_info.get_chunks_assets(&chunk_ids) {
+                if !files.contains(&asset.hashname) {
+                    files.push(asset.hashname);
+                }
+            }
+            if let Some(chunk_name) = import_options.get_chunk_name() {
+                manifest.chunks.insert(chunk_name.clone(), files.clone());
+            }
+            manifest.modules.insert(id.generate(context), files);
+        }
+    }
+    manifest
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::build_fixture;
+
+    #[test]
+    fn test_loadable_manifest() {
+        let artifacts = build_fixture(
+            Some(
+                r#"{
+                    "mode": "production",
+                    "minify": false,
+                    "hash": false,
+                    "moduleIdStrategy": "named",
+                    "loadableManifest": {},
+                    "codeSplitting": {
+                        "strategy": "advanced",
+                        "options": {
+                            "minSize": 1,
+                            "groups": [{ "name": "shared", "test": "shared", "minChunks": 2, "minSize": 1 }]
+                        }
+                    }
+                }"#,
+            ),
+            &[
+                (
+                    "src/index.ts",
+                    "import(/* webpackChunkName: 'named' */ './a');\nimport('./b');",
+                ),
+                ("src/a.ts", "import './shared';\nimport './a.css';"),
+                ("src/b.ts", "import './shared';"),
+                ("src/shared.ts", "export default 'shared';"),
+                ("src/a.css", ".a { color: red; }"),
+            ],
+        )
+        .unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_str(&artifacts.asset("loadable-manifest.json").unwrap()).unwrap();
+        assert_eq!(
+            manifest,
+            serde_json::json!({
+                "modules": {
+                    "src/a.ts": ["shared-async.js", "named-async.css", "named-async.js"],
+                    "src/b.ts": ["shared-async.js", "src_b_ts-async.js"],
+                },
+                "chunks": {
+                    "named": ["shared-async.js", "named-async.css", "named-async.js"],
+                },
+            })
+        );
+
+        let index = artifacts.asset("index.js").unwrap();
+        assert!(
+            index.contains(r#"__mako_require__.loadable("src/b.ts", "#),
+            "{}",
+            index
+        );
+        assert!(index.contains("__MAKO_LOADABLE_REPORT__"), "{}", index);
+    }
+}
@@ -1,17 +1,31 @@
 use std::collections::BTreeMap;
-use std::fs;
 use std::sync::Arc;
 
 use anyhow::Result;
 use regex::Regex;
+use serde::Serialize;
 use serde_json;
 
 use crate::compiler::Context;
+use crate::config::static_public_path;
+use crate::generate::artifacts::has_artifacts;
+use crate::generate::precompress::precompressed_variants;
 use crate::plugin::Plugin;
 use crate::stats::StatsJsonMap;
 
 pub struct ManifestPlugin {}
 
+#[derive(Serialize, Default)]
+struct Manifest {
+    #[serde(flatten)]
+    files: BTreeMap<String, String>,
+    // entry name -> files in load order
+    entrypoints: BTreeMap<String, Vec<String>>,
+    /// file name of the chunks inlined in the html -> hash of the content
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    inlined: BTreeMap<String, String>,
+}
+
 pub(crate) fn default_manifest_file_name() -> String {
     "asset-manifest.json".to_string()
 }
@@ -22,27 +36,87 @@ impl Plugin for ManifestPlugin {
     }
 
     fn build_success(&self, _stats: &StatsJsonMap, context: &Arc<Context>) -> Result<()> {
-        if let Some(manifest_config) = &context.config.manifest {
-            let assets = &context.stats_info.get_assets();
-            let mut manifest: BTreeMap<String, String> = BTreeMap::new();
-            let file_name = manifest_config.file_name.clone();
-            let base_path = manifest_config.base_path.clone();
+        // written with the artifacts of the generate
+        if has_artifacts(context) {
+            return Ok(());
+        }
+        if let Some((file_name, manifest)) = generate_manifest(context)? {
+            let manifest_json = serde_json::to_string_pretty(&manifest)?;
+            let output_path = context.config.output.path.join(file_name);
+            context
+                .output_fs
+                .write(&output_path, manifest_json.as_bytes())?;
+        }
+        Ok(())
+    }
+}
 
-            let path = normalize_path(base_path);
+/// The file name and the content of the manifest, with `manifest`.
+pub(crate) fn generate_manifest(
+    context: &Arc<Context>,
+) -> Result<Option<(String, serde_json::Value)>> {
+    let Some(manifest_config) = &context.config.manifest else {
+        return Ok(None);
+    };
+    let assets = &context.stats_info.get_assets();
+    let mut manifest = Manifest::default();
+    let file_name = manifest_config.file_name.clone();
+    let base_path = manifest_config.base_path.clone();
 
-            for asset in assets {
-                let key = format!("{}{}", path, remove_key_hash(&asset.hashname));
-                manifest.insert(key, asset.hashname.clone());
-            }
+    let path = normalize_path(base_path);
+    let hash_length = context.config.output.hash_length;
+    let public_path = if manifest_config.public_path {
+        static_public_path(&context.config.public_path)
+    } else {
+        ""
+    };
+    let file = |name: &str| format!("{}{}", public_path, name);
 
-            let manifest_json = serde_json::to_string_pretty(&manifest)?;
+    for asset in assets {
+        let key = format!("{}{}", path, remove_key_hash(&asset.hashname, hash_length));
+        manifest.files.insert(key, file(&asset.hashname));
+    }
+    if let Some(precompress) = context
+        .config
+        .output
+        .precompress
+        .as_ref()
+        .filter(|_| manifest_config.precompressed)
+    {
+        for asset in assets {
+            for variant in precompressed_variants(&asset.hashname, asset.size, precompress) {
+                let key = format!("{}{}", path, remove_key_hash(&variant, hash_length));
+                manifest.files.insert(key, file(&variant));
+            }
+        }
+    }
+    // files of aliased chunks point to the shared file
+    for alias in context.stats_info.get_chunk_aliases() {
+        for (file_name, disk_name) in alias.files {
+            manifest
+                .files
+                .insert(format!("{}{}", path, file_name), file(&disk_name));
+        }
+    }
 
-            let output_path = context.config.output.path.join(file_name);
+    let chunk_graph = context.chunk_graph.read().unwrap();
+    for entrypoint in chunk_graph.entrypoints() {
+        let files = context
+            .stats_info
+            .get_chunks_assets(&entrypoint.chunks)
+            .into_iter()
+            .map(|asset| file(&asset.hashname))
+            .collect();
+        manifest.entrypoints.insert(entrypoint.name, files);
+    }
 
-            fs::write(output_path, manifest_json).unwrap();
-        }
-        Ok(())
+    for chunk in context.stats_info.inlined_chunks.lock().unwrap().iter() {
+        manifest
+            .inlined
+            .insert(format!("{}{}", path, chunk.file_name), chunk.hash());
     }
+
+    Ok(Some((file_name, serde_json::to_value(&manifest)?)))
 }
 
 fn normalize_path(mut path: String) -> String {
@@ -53,8 +127,40 @@ fn normalize_path(mut path: String) -> String {
     path
 }
 
-fn remove_key_hash(key: &str) -> String {
-    let reg = Regex::new(r"[a-fA-F0-9]{8}\.?").unwrap();
+fn remove_key_hash(key: &str, hash_length: usize) -> String {
+    let reg = Regex::new(&format!(r"[a-fA-F0-9]{{{}}}\.?", hash_length)).unwrap();
     let val = reg.replace_all(key, "").to_string();
     val
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_entrypoints() {
+        let compiler = setup_compiler("test/build/entrypoints", false);
+        compiler.compile().unwrap();
+        let manifest = compiler
+            .context
+            .output_fs
+            .read(
+                &compiler
+                    .context
+                    .config
+                    .output
+                    .path
+                    .join("asset-manifest.json"),
+            )
+            .unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&manifest).unwrap();
+        assert_eq!(manifest["a.js"], "a.js");
+        assert_eq!(
+            manifest["entrypoints"],
+            serde_json::json!({
+                "a": ["common.css", "common.js", "a.js"],
+                "b": ["common.css", "common.js", "b.js"],
+            })
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use semver::{Version, VersionReq};
+
+use crate::compiler::{Compiler, Context};
+use crate::config::PeerDependencyCheckConfig;
+use crate::module::ModuleId;
+use crate::module_graph::ModuleGraph;
+use crate::plugin::Plugin;
+use crate::plugins::duplicate_package_checker::{clean_path_relative_to_context, nearest_package};
+
+/// Check the `peerDependencies` of the bundled packages against the versions
+/// of the peers they import in the graph.
+pub struct PeerDependencyCheckPlugin {
+    pub config: PeerDependencyCheckConfig,
+}
+
+// a peer dependency whose range is not satisfied
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Conflict {
+    package: String,
+    peer: String,
+    range: String,
+    version: String,
+    // the module of the package which imports the peer, and the peer module
+    importer: ModuleId,
+    imported: ModuleId,
+}
+
+impl Plugin for PeerDependencyCheckPlugin {
+    fn name(&self) -> &str {
+        "peer_dependency_check"
+    }
+
+    fn after_build(&self, context: &Arc<Context>, _compiler: &Compiler) -> Result<()> {
+        let module_graph = context.module_graph.read().unwrap();
+        let conflicts = find_conflicts(&module_graph);
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+        let messages = conflicts
+            .iter()
+            .map(|conflict| format_conflict(conflict, &module_graph, &context.root))
+            .collect::<Vec<_>>();
+        match self.config {
+            PeerDependencyCheckConfig::Error => Err(anyhow!(messages.join("\n"))),
+            PeerDependencyCheckConfig::Warn => {
+                for message in messages {
+                    eprintln!("{}: {}", "Warning".yellow(), message);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn find_conflicts(module_graph: &ModuleGraph) -> BTreeSet<Conflict> {
+    let mut conflicts = BTreeSet::new();
+    // the resolver drops peerDependencies from its package.json cache
+    let mut peer_dependencies: HashMap<PathBuf, BTreeMap<String, String>> = HashMap::new();
+    // each copy of a peer is only reported once for a package
+    let mut reported: BTreeSet<(PathBuf, String, PathBuf)> = BTreeSet::new();
+    for module in module_graph.modules() {
+        let Some(package) = nearest_package(module) else {
+            continue;
+        };
+        let peers = peer_dependencies
+            .entry(package.path.clone())
+            .or_insert_with(|| read_peer_dependencies(&package.path));
+        if peers.is_empty() {
+            continue;
+        }
+        for (dep_id, _) in module_graph.get_dependencies(&module.id) {
+            let Some(dep) = module_graph
+                .get_module(dep_id)
+                .and_then(nearest_package)
+                .filter(|dep| dep.path != package.path)
+            else {
+                continue;
+            };
+            let (Some(range), Some(version)) = (peers.get(&dep.name), &dep.version) else {
+                continue;
+            };
+            if satisfies(version, range) != Some(false) {
+                continue;
+            }
+            if !reported.insert((package.path.clone(), dep.name.clone(), dep.path.clone())) {
+                continue;
+            }
+            conflicts.insert(Conflict {
+                package: match &package.version {
+                    Some(version) => format!("{}@{}", package.name, version),
+                    None => package.name.clone(),
+                },
+                peer: dep.name,
+                range: range.clone(),
+                version: version.clone(),
+                importer: module.id.clone(),
+                imported: dep_id.clone(),
+            });
+        }
+    }
+    conflicts
+}
+
+fn read_peer_dependencies(package_json: &Path) -> BTreeMap<String, String> {
+    fs::read_to_string(package_json)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|json| {
+            let peers = json.get("peerDependencies")?.as_object()?;
+            Some(
+                peers
+                    .iter()
+                    .filter_map(|(name, range)| Some((name.clone(), range.as_str()?.to_string())))
+                    .collect(),
+            )
+        })
+        .unwrap_or_default()
+}
+
+fn format_conflict(conflict: &Conflict, module_graph: &ModuleGraph, root: &Path) -> String {
+    let mut message = format!(
+        "{} requires {}@{}, but {}@{} is bundled",
+        conflict.package, conflict.peer, conflict.range, conflict.peer, conflict.version
+    );
+    if let Some(chain) = module_graph.import_chains(&conflict.importer, 1).first() {
+        let chain = std::iter::once(&chain.entry)
+            .chain(chain.steps.iter().map(|step| &step.to))
+            .chain(std::iter::once(&conflict.imported))
+            .map(|id| {
+                clean_path_relative_to_context(Path::new(&id.id), root)
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        message.push_str(&format!("\n  {}", chain.join(" > ")));
+    }
+    message
+}
+
+/// Whether the version satisfies the npm range, None if either of them can't
+/// be parsed, e.g. `workspace:*` or a tag.
+fn satisfies(version: &str, range: &str) -> Option<bool> {
+    let version = Version::parse(version.trim().trim_start_matches('v')).ok()?;
+    let mut any = false;
+    for alternative in range.split("||") {
+        let req = to_version_req(alternative)?;
+        any = any || req.matches(&version);
+    }
+    Some(any)
+}
+
+// `>=1.2 <2`, `1.2.3 - 2` and `1.x` of npm to the comma separated
+// comparators of semver
+fn to_version_req(range: &str) -> Option<VersionReq> {
+    let range = range.trim();
+    if range.is_empty() || range == "*" || range == "latest" {
+        return Some(VersionReq::STAR);
+    }
+    let comparators = match range.split_once(" - ") {
+        Some((from, to)) => vec![format!(">={}", from.trim()), format!("<={}", to.trim())],
+        None => {
+            let mut comparators: Vec<String> = vec![];
+            let mut operator = String::new();
+            for token in range.split_whitespace() {
+                // `>= 1.2` with the operator apart
+                if token.chars().all(|c| "<>=~^".contains(c)) {
+                    operator.push_str(token);
+                    continue;
+                }
+                let token = format!("{}{}", std::mem::take(&mut operator), token);
+                let (operator, version) =
+                    token.split_at(token.find(|c| !"<>=~^".contains(c)).unwrap_or(0));
+                let version = version.trim_start_matches('v');
+                // a bare version is exact in npm but caret in semver
+                let operator = if operator.is_empty() { "=" } else { operator };
+                comparators.push(format!("{}{}", operator, version));
+            }
+            comparators
+        }
+    };
+    VersionReq::parse(&comparators.join(", ")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::satisfies;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_satisfies() {
+        assert_eq!(satisfies("18.2.0", "^18.0.0"), Some(true));
+        assert_eq!(satisfies("17.0.2", "^18.0.0"), Some(false));
+        assert_eq!(satisfies("17.0.2", "^16.8.0 || ^17.0.0"), Some(true));
+        assert_eq!(satisfies("17.0.2", ">=16.8 <17"), Some(false));
+        assert_eq!(satisfies("17.0.2", ">= 16.8"), Some(true));
+        assert_eq!(satisfies("17.0.2", "16 - 17"), Some(true));
+        assert_eq!(satisfies("17.0.2", "17.x"), Some(true));
+        assert_eq!(satisfies("17.0.2", "17.0.1"), Some(false));
+        assert_eq!(satisfies("17.0.2", "*"), Some(true));
+        assert_eq!(satisfies("17.0.2", "workspace:*"), None);
+    }
+
+    #[test]
+    fn test_peer_dependency_check() {
+        let compiler = setup_compiler("test/build/peer-dependency-check", false);
+        let err = compiler.compile().unwrap_err().to_string();
+        assert!(
+            err.contains("ui@1.0.0 requires react@^18.0.0, but react@17.0.2 is bundled"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("./index.ts > ./~/ui/index.js > ./~/react/index.js"),
+            "{}",
+            err
+        );
+        // satisfied
+        assert!(!err.contains("hooks"), "{}", err);
+    }
+}
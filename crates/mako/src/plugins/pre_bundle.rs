@@ -0,0 +1,473 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use anyhow::Result;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::ast::file::File;
+use crate::build::load::JS_EXTENSIONS;
+use crate::compiler::{Args, Compiler, Context};
+use crate::config::{sorted_json, Config, PreBundleConfig};
+use crate::generate::chunk_pot::util::hash_vec;
+use crate::plugin::{Plugin, PluginResolveIdParams};
+use crate::resolve::{
+    ExternalResource, Resolution, ResolvedResource, ResolverResource, ResolverType, LOCK_FILES,
+};
+use crate::utils::output_fs::{MemoryFileSystem, OutputFileSystem};
+
+const CACHE_DIR: &str = "node_modules/.cache_mako/deps";
+const METADATA_FILE: &str = "_metadata.json";
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+struct Metadata {
+    // of the lockfile and the config the artifacts are built with
+    hash: u64,
+    deps: BTreeMap<String, PreBundledDep>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct PreBundledDep {
+    // the file the specifier is resolved to
+    file: PathBuf,
+    // none if the dependency isn't bundled into a single js file, e.g. it
+    // imports css, which is then built as usual
+    artifact: Option<PathBuf>,
+}
+
+/// Bundle the packages imported by the app into one file each on the cold
+/// start of the dev server, so that the rebuilds and the restarts don't
+/// transform their modules again. The artifacts are rebuilt when the
+/// lockfile, the config or the imported packages change.
+///
+/// e.g. `import debounce from 'lodash/debounce'` is resolved to
+/// node_modules/.cache_mako/deps/lodash+debounce/index.js, in which the
+/// other pre-bundled packages are required instead of bundled.
+pub struct PreBundlePlugin {
+    exclude: Vec<String>,
+    deps: RwLock<BTreeMap<String, PreBundledDep>>,
+}
+
+impl PreBundlePlugin {
+    pub fn new(config: &PreBundleConfig) -> Self {
+        Self {
+            exclude: config.exclude.clone(),
+            deps: Default::default(),
+        }
+    }
+
+    fn is_excluded(&self, specifier: &str) -> bool {
+        self.exclude.iter().any(|exclude| {
+            specifier == exclude
+                || specifier
+                    .strip_prefix(exclude.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    // the bare imports of the files reachable from the entries without
+    // passing node_modules, with the files they're resolved to
+    fn scan(&self, context: &Arc<Context>) -> BTreeMap<String, PathBuf> {
+        let mut deps = BTreeMap::new();
+        let mut visited = HashSet::new();
+        let mut queue = context
+            .config
+            .entry
+            .values()
+            .map(|entry| {
+                File::new_entry(entry.import.to_string_lossy().to_string(), context.clone())
+            })
+            .collect::<VecDeque<_>>();
+        while let Some(file) = queue.pop_front() {
+            if !visited.insert(file.path.clone()) {
+                continue;
+            }
+            // the errors are reported by the build
+            let Ok(module) = Compiler::build_module(&file, None, context.clone()) else {
+                continue;
+            };
+            let Some(info) = module.info else {
+                continue;
+            };
+            for dep in info.deps.resolved_deps {
+                let ResolverResource::Resolved(ResolvedResource(resolution)) =
+                    &dep.resolver_resource
+                else {
+                    continue;
+                };
+                let source = &dep.dependency.source;
+                if !is_under_node_modules(&resolution.path) {
+                    queue.push_back(File::new(
+                        resolution.full_path().to_string_lossy().to_string(),
+                        context.clone(),
+                    ));
+                } else if is_bare(source)
+                    && !self.is_excluded(source)
+                    && resolution.query.is_none()
+                    && is_js(&resolution.path)
+                {
+                    deps.entry(source.clone())
+                        .or_insert_with(|| resolution.path.clone());
+                }
+            }
+        }
+        deps
+    }
+
+    fn pre_bundle(&self, context: &Arc<Context>) -> Result<()> {
+        let t = Instant::now();
+        let cache_dir = context.root.join(CACHE_DIR);
+        let metadata_path = cache_dir.join(METADATA_FILE);
+        let scanned = self.scan(context);
+        let hash = metadata_hash(context);
+
+        let cached = fs::read_to_string(&metadata_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Metadata>(&content).ok())
+            .filter(|metadata| {
+                metadata.hash == hash
+                    && metadata.deps.len() == scanned.len()
+                    && metadata.deps.iter().all(|(specifier, dep)| {
+                        scanned.get(specifier) == Some(&dep.file)
+                            && dep.artifact.as_ref().map_or(true, |a| a.exists())
+                    })
+            });
+        let metadata = match cached {
+            Some(metadata) => metadata,
+            None => {
+                if cache_dir.exists() {
+                    fs::remove_dir_all(&cache_dir)?;
+                }
+                fs::create_dir_all(&cache_dir)?;
+                let deps = scanned
+                    .iter()
+                    .map(|(specifier, file)| {
+                        let artifact = bundle(context, specifier, file, &scanned, &cache_dir)
+                            .unwrap_or_else(|e| {
+                                eprintln!(
+                                    "{} Pre-bundle {} failed, it's built as usual: {}",
+                                    "Warning".yellow(),
+                                    specifier,
+                                    e
+                                );
+                                None
+                            });
+                        (
+                            specifier.clone(),
+                            PreBundledDep {
+                                file: file.clone(),
+                                artifact,
+                            },
+                        )
+                    })
+                    .collect();
+                let metadata = Metadata { hash, deps };
+                fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?)?;
+                if !context.config.quiet {
+                    println!(
+                        "Pre-bundled {} dependencies in {}ms",
+                        metadata
+                            .deps
+                            .values()
+                            .filter(|dep| dep.artifact.is_some())
+                            .count(),
+                        t.elapsed().as_millis()
+                    );
+                }
+                metadata
+            }
+        };
+        *self.deps.write().unwrap() = metadata.deps;
+        Ok(())
+    }
+}
+
+impl Plugin for PreBundlePlugin {
+    fn name(&self) -> &str {
+        "pre_bundle"
+    }
+
+    fn build_start(&self, context: &Arc<Context>) -> Result<()> {
+        self.pre_bundle(context)
+    }
+
+    fn resolve_id(
+        &self,
+        source: &str,
+        importer: &str,
+        _params: &PluginResolveIdParams,
+        context: &Arc<Context>,
+    ) -> Result<Option<ResolverResource>> {
+        let deps = self.deps.read().unwrap();
+        let Some(PreBundledDep {
+            file,
+            artifact: Some(artifact),
+        }) = deps.get(source)
+        else {
+            return Ok(None);
+        };
+        // e.g. another version of the package nested in node_modules
+        if !resolves_to(context, importer, source, file) {
+            return Ok(None);
+        }
+        Ok(Some(ResolverResource::Resolved(ResolvedResource(
+            Resolution {
+                path: artifact.clone(),
+                query: None,
+                fragment: None,
+                package_json: None,
+                condition: None,
+                root: None,
+            },
+        ))))
+    }
+}
+
+// requires the other pre-bundled packages in the artifact, which are resolved
+// to their own artifacts in the app
+struct PreBundledExternalsPlugin {
+    deps: BTreeMap<String, PathBuf>,
+}
+
+impl Plugin for PreBundledExternalsPlugin {
+    fn name(&self) -> &str {
+        "pre_bundled_externals"
+    }
+
+    fn resolve_id(
+        &self,
+        source: &str,
+        importer: &str,
+        _params: &PluginResolveIdParams,
+        context: &Arc<Context>,
+    ) -> Result<Option<ResolverResource>> {
+        match self.deps.get(source) {
+            Some(file) if resolves_to(context, importer, source, file) => {
+                Ok(Some(ResolverResource::External(ExternalResource {
+                    source: source.to_string(),
+                    external: format!("require(\"{}\")", source),
+                    script: None,
+                })))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+// bundle the file of the specifier into <cache_dir>/<name>/index.js, returns
+// none if the output isn't a single js file
+fn bundle(
+    context: &Arc<Context>,
+    specifier: &str,
+    file: &Path,
+    deps: &BTreeMap<String, PathBuf>,
+    cache_dir: &Path,
+) -> Result<Option<PathBuf>> {
+    let output_path = cache_dir.join(specifier.replace('/', "+"));
+    let mut bundle_config = shared_config(&context.config);
+    bundle_config["entry"] = json!({ "index": file });
+    bundle_config["output"] = json!({ "path": output_path });
+    let mut config =
+        Config::from_sources(&context.root, None, None, Some(&bundle_config.to_string()))?;
+    config.quiet = true;
+    config.pre_bundled = true;
+    let mut externals = deps.clone();
+    externals.remove(specifier);
+    let output_fs = Arc::new(MemoryFileSystem::new());
+    let compiler = Compiler::new_with_output_fs(
+        config,
+        context.root.clone(),
+        Args { watch: false },
+        Some(vec![Arc::new(PreBundledExternalsPlugin {
+            deps: externals,
+        })]),
+        Some(output_fs.clone()),
+    )?;
+    compiler.compile()?;
+
+    let js = output_path.join("index.js");
+    let map = output_path.join("index.js.map");
+    let files = output_fs.list(&output_path)?;
+    if !files.iter().all(|f| f == &js || f == &map) {
+        return Ok(None);
+    }
+    fs::create_dir_all(&output_path)?;
+    for path in files {
+        fs::write(&path, output_fs.read(&path)?)?;
+    }
+    Ok(Some(js))
+}
+
+// the options of the app which change the artifacts, the artifacts are
+// single commonjs files with their source maps, since the app requires them
+fn shared_config(config: &Config) -> Value {
+    json!({
+        "mode": config.mode,
+        "envName": config.env_name,
+        "platform": config.platform,
+        "resolve": config.resolve,
+        "define": config.define,
+        "externals": config.externals,
+        "targets": config.targets,
+        "providers": config.providers,
+        "cjs": true,
+        "devtool": "source-map",
+        "minify": false,
+        "hmr": false,
+        "devServer": false,
+        "progress": false,
+        "clean": false,
+        "copy": [],
+        "hash": false,
+        "dynamicImportToRequire": true,
+    })
+}
+
+fn metadata_hash(context: &Arc<Context>) -> u64 {
+    let mut sources = vec![
+        env!("CARGO_PKG_VERSION").to_string(),
+        sorted_json(shared_config(&context.config)).to_string(),
+    ];
+    sources.extend(LOCK_FILES.iter().map(|name| {
+        fs::read(context.root.join(name))
+            .map(|content| String::from_utf8_lossy(&content).to_string())
+            .unwrap_or_default()
+    }));
+    hash_vec(&sources)
+}
+
+// whether the source is resolved to the file from the importer, as it's
+// resolved by the build
+fn resolves_to(context: &Arc<Context>, importer: &str, source: &str, file: &Path) -> bool {
+    let Some(dir) = Path::new(importer).parent() else {
+        return false;
+    };
+    context
+        .resolvers
+        .get(&ResolverType::Esm)
+        .and_then(|resolver| resolver.resolve(dir, source).ok())
+        .is_some_and(|resolution| resolution.path() == file)
+}
+
+fn is_bare(source: &str) -> bool {
+    !source.starts_with('.') && !Path::new(source).is_absolute() && !source.contains(':')
+}
+
+fn is_under_node_modules(path: &Path) -> bool {
+    path.components()
+        .any(|component| component.as_os_str() == "node_modules")
+}
+
+fn is_js(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| JS_EXTENSIONS.contains(&ext.to_string_lossy().as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn compile(root: &Path) -> Compiler {
+        let config = Config::new(root, None, None).unwrap();
+        let compiler =
+            Compiler::new(config, root.to_path_buf(), Args { watch: true }, None).unwrap();
+        compiler.compile().unwrap();
+        compiler
+    }
+
+    #[test]
+    fn test_pre_bundle() {
+        let root = TempDir::new("pre-bundle");
+        write(
+            &root,
+            "mako.config.json",
+            r#"{"entry":{"index":"./index.js"},"preBundle":{"exclude":["excluded"]}}"#,
+        );
+        write(
+            &root,
+            "index.js",
+            "import { add } from 'dep-a'; import b from 'dep-b'; import c from 'excluded'; console.log(add(b, c));",
+        );
+        write(
+            &root,
+            "node_modules/dep-a/index.js",
+            "exports.add = require('./util').add;",
+        );
+        write(
+            &root,
+            "node_modules/dep-a/util.js",
+            "exports.add = function add(a, b) { return a + b; };",
+        );
+        write(
+            &root,
+            "node_modules/dep-b/index.js",
+            "module.exports = require('dep-a').add(1, 2);",
+        );
+        write(
+            &root,
+            "node_modules/excluded/index.js",
+            "module.exports = 3;",
+        );
+
+        let compiler = compile(&root);
+        let cache_dir = root.join(CACHE_DIR);
+        let metadata: Metadata =
+            serde_json::from_slice(&fs::read(cache_dir.join(METADATA_FILE)).unwrap()).unwrap();
+        assert_eq!(
+            metadata.deps.keys().collect::<Vec<_>>(),
+            vec!["dep-a", "dep-b"]
+        );
+        let artifact_a = cache_dir.join("dep-a/index.js");
+        let artifact_b = cache_dir.join("dep-b/index.js");
+        let code_a = fs::read_to_string(&artifact_a).unwrap();
+        assert!(code_a.contains("function add(a, b)"));
+        assert!(!code_a.contains("__mako_require_module__ ="));
+        assert!(cache_dir.join("dep-a/index.js.map").exists());
+        let code_b = fs::read_to_string(&artifact_b).unwrap();
+        assert!(code_b.contains(r#"require("dep-a")"#));
+        assert!(!code_b.contains("function add(a, b)"));
+
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        assert!(module_graph.has_module(&artifact_a.clone().into()));
+        assert!(module_graph.has_module(&artifact_b.clone().into()));
+        // the requires in the artifacts are of their own runtimes, except the
+        // other pre-bundled packages
+        assert!(module_graph
+            .get_dependencies(&artifact_a.clone().into())
+            .is_empty());
+        assert_eq!(
+            module_graph
+                .get_dependencies(&artifact_b.into())
+                .iter()
+                .map(|(id, _)| id.id.clone())
+                .collect::<Vec<_>>(),
+            vec![artifact_a.to_string_lossy().to_string()]
+        );
+        assert!(!module_graph.has_module(&root.join("node_modules/dep-a/index.js").into()));
+        assert!(module_graph.has_module(&root.join("node_modules/excluded/index.js").into()));
+        drop(module_graph);
+
+        // the artifacts are reused until the lockfile changes
+        fs::write(&artifact_a, format!("{}\n// reused", code_a)).unwrap();
+        compile(&root);
+        assert!(fs::read_to_string(&artifact_a)
+            .unwrap()
+            .contains("// reused"));
+        write(&root, "package-lock.json", "{}");
+        compile(&root);
+        assert!(!fs::read_to_string(&artifact_a)
+            .unwrap()
+            .contains("// reused"));
+    }
+}
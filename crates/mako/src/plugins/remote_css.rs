@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::compiler::{Compiler, Context};
+use crate::plugin::Plugin;
+
+/// Writes the lockfile of the remote css and fonts downloaded by
+/// `remoteCSS: "download"`, the urls which are not downloaded by the full
+/// build are removed from it, the rebuilds only add the new ones.
+pub struct RemoteCSSPlugin {}
+
+impl Plugin for RemoteCSSPlugin {
+    fn name(&self) -> &str {
+        "remote_css"
+    }
+
+    fn after_build(&self, context: &Arc<Context>, _compiler: &Compiler) -> Result<()> {
+        context.remote_assets.write_lock(true)
+    }
+
+    fn after_update(&self, compiler: &Compiler) -> Result<()> {
+        compiler.context.remote_assets.write_lock(false)
+    }
+}
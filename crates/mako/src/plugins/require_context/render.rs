@@ -10,6 +10,7 @@ use regex::RegexBuilder;
 use super::param::ContextLoadMode;
 use crate::ast::file::win_path;
 use crate::compiler::Context;
+use crate::config::matched_ignore_rule;
 use crate::module::ModuleId;
 
 pub struct VirtualContextModuleRender {
@@ -142,6 +143,9 @@ impl VirtualContextModuleRender {
             .build()?;
 
         for matched in glob.filter_map(Result::ok) {
+            if matched_ignore_rule(&context.config.ignore, &context.root, &matched).is_some() {
+                continue;
+            }
             if let Some(p) = diff_paths(&matched, &root_path) {
                 let mut source = p.to_string_lossy().to_string();
                 if !source.starts_with('.') {
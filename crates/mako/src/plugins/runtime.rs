@@ -3,6 +3,7 @@ use std::sync::Arc;
 use anyhow::{anyhow, Result};
 
 use crate::compiler::Context;
+use crate::config::{Platform, PublicPathKind, RelativeUrlBase};
 use crate::generate::swc_helpers::SwcHelpers;
 use crate::module::ModuleId;
 use crate::plugin::Plugin;
@@ -26,37 +27,21 @@ impl Plugin for MakoRuntime {
 impl MakoRuntime {
     fn public_path(&self, context: &Arc<Context>) -> String {
         let public_path = context.config.public_path.clone();
-        match public_path.as_str() {
-            "runtime" => {
+        match PublicPathKind::of(&public_path) {
+            PublicPathKind::Runtime => {
             r#"/* mako/runtime/publicPath */
   !function () {{
     requireModule.publicPath= (typeof globalThis !== 'undefined' ? globalThis : self).publicPath || '/';
   }}();"#.to_string()
             }
-            "auto" => {
-r#"/* mako/runtime/publicPath */
-!function() {{
-  var scriptUrl;
-  if (!self.document && self.importScripts) {
-    scriptUrl = self.location + "";
-  }
-  if (!scriptUrl && document) {
-    if (document.currentScript && document.currentScript.tagName.toUpperCase() === 'SCRIPT')
-      scriptUrl = document.currentScript.src;
-      if (!scriptUrl) {
-  	  var scripts = document.getElementsByTagName("script");
-  	  if(scripts.length) {
-  		var i = scripts.length - 1;
-  		  while (i > -1 && (!scriptUrl || !/^http(s?):/.test(scriptUrl))) scriptUrl = scripts[i--].src;
-  	  }
-    }
-  }
-  if (!scriptUrl) throw new Error("Automatic publicPath is not supported in this browser");
-  scriptUrl = scriptUrl.replace(/#.*$/, "").replace(/\?.*$/, "").replace(/\/[^\/]+$/, "/");
-  requireModule.publicPath = scriptUrl;
-}}();"#
+            PublicPathKind::Auto => Self::relative_public_path("./", context),
+            // the output files are in the same directory as the current
+            // script by default, which may be not the directory of the html
+            PublicPathKind::Relative
+                if matches!(context.config.platform, Platform::Browser) =>
+            {
+                Self::relative_public_path(&public_path, context)
             }
-            .to_string(),
             _ => format!(
                 r#"
   /* mako/runtime/publicPath */
@@ -68,9 +53,55 @@ r#"/* mako/runtime/publicPath */
         }
     }
 
+    fn relative_public_path(public_path: &str, context: &Arc<Context>) -> String {
+        let public_path = serde_json::to_string(public_path).unwrap();
+        match context.config.output.relative_url_base {
+            RelativeUrlBase::Script => Self::script_public_path(&public_path),
+            RelativeUrlBase::Document => Self::document_public_path(&public_path),
+        }
+    }
+
+    // the directory of the current script, or the public path resolved
+    // against the page if it's not found, e.g. in a module script
+    fn script_public_path(public_path: &str) -> String {
+        format!(
+            r#"/* mako/runtime/publicPath */
+!function() {{
+  var scriptUrl;
+  if (!self.document && self.importScripts) {{
+    scriptUrl = self.location + "";
+  }}
+  if (!scriptUrl && self.document && document.currentScript && document.currentScript.tagName.toUpperCase() === 'SCRIPT') {{
+    scriptUrl = document.currentScript.src;
+  }}
+  if (scriptUrl) {{
+    scriptUrl = scriptUrl.replace(/#.*$/, "").replace(/\?.*$/, "").replace(/\/[^\/]+$/, "/");
+  }} else {{
+    scriptUrl = new URL({}, document.baseURI).href;
+  }}
+  requireModule.publicPath = scriptUrl;
+}}();"#,
+            public_path
+        )
+    }
+
+    // the public path resolved against the page, or the worker script
+    fn document_public_path(public_path: &str) -> String {
+        format!(
+            r#"/* mako/runtime/publicPath */
+!function() {{
+  var baseUrl = self.document ? document.baseURI : self.location + "";
+  requireModule.publicPath = new URL({}, baseUrl).href;
+}}();"#,
+            public_path
+        )
+    }
+
     fn helper_runtime(&self, context: &Arc<Context>) -> Result<String> {
+        // only the ones the modules require
         let helpers = SwcHelpers::full_helpers()
             .into_iter()
+            .filter(|source| context.runtime_features.uses_helper(source, context))
             .map(|source| {
                 let code = Self::get_swc_helper_code(&source).unwrap();
                 let module_id: ModuleId = source.into();
@@ -79,6 +110,9 @@ r#"/* mako/runtime/publicPath */
             })
             .collect::<Vec<_>>()
             .join(",\n");
+        if helpers.is_empty() {
+            return Ok(String::new());
+        }
 
         Ok(format!(
             r#"
@@ -185,14 +219,34 @@ function(module, exports, __mako_require__) {
             return _export_star;
         }
     });
+    // the names of the exports from export *, to the exports they're from,
+    // or null if they're ambiguous
+    var starExports = typeof WeakMap === "function" ? new WeakMap() : null;
+    function origin(exports, k) {
+        var stars = starExports.get(exports);
+        return stars && stars[k] ? origin(stars[k], k) : exports;
+    }
     function _export_star(from, to) {
+        var stars = starExports && starExports.get(to);
+        if (starExports && !stars) starExports.set(to, stars = Object.create(null));
         Object.keys(from).forEach(function(k) {
-            if (k !== "default" && !Object.prototype.hasOwnProperty.call(to, k)) Object.defineProperty(to, k, {
-                enumerable: true,
-                get: function() {
-                    return from[k];
-                }
-            });
+            if (k === "default") return;
+            if (!Object.prototype.hasOwnProperty.call(to, k)) {
+                if (stars && stars[k] === null) return;
+                Object.defineProperty(to, k, {
+                    enumerable: true,
+                    configurable: true,
+                    get: function() {
+                        return from[k];
+                    }
+                });
+                if (stars) stars[k] = from;
+            } else if (stars && stars[k] && origin(stars[k], k) !== origin(from, k) && to[k] !== from[k]) {
+                // exported by multiple export * with different bindings, which
+                // is excluded like esm
+                delete to[k];
+                stars[k] = null;
+            }
         });
         return from;
     }
@@ -15,11 +15,10 @@ use tracing::debug;
 use crate::ast::file::{Content, File, JsContent};
 use crate::compiler::{Args, Compiler, Context};
 use crate::config::{
-    AllowChunks, ChunkGroup, CodeSplitting, CodeSplittingAdvancedOptions, CodeSplittingStrategy,
-    CodeSplittingStrategyOptions, Config,
+    AllowChunks, CacheKeys, ChunkGroup, CodeSplitting, CodeSplittingAdvancedOptions,
+    CodeSplittingStrategy, CodeSplittingStrategyOptions, Config,
 };
 use crate::generate::chunk::ChunkType;
-use crate::generate::chunk_pot::util::{hash_hashmap, hash_vec};
 use crate::generate::generate_chunks::{ChunkFile, ChunkFileType};
 use crate::generate::transform::transform_modules;
 use crate::module::ModuleId;
@@ -28,7 +27,7 @@ use crate::resolve::ResolverResource;
 
 #[derive(Serialize, Deserialize, Debug, Default)]
 struct CacheState {
-    config_hash: u64,
+    cache_keys: CacheKeys,
     reversed_required_files: HashSet<String>,
     cached_boundaries: HashMap<String, String>,
     js_patch_map: HashMap<String, String>,
@@ -37,11 +36,9 @@ struct CacheState {
 
 impl CacheState {
     pub fn valid_with(&self, other: &Self) -> bool {
-        if self.config_hash != other.config_hash {
-            debug!(
-                "config_hash changed: {} -> {}",
-                self.config_hash, other.config_hash
-            );
+        // the cached chunks are resolved, transformed, generated and minified
+        if let Some(group) = self.cache_keys.first_changed(&other.cache_keys) {
+            debug!("the {} options are changed", group);
             return false;
         }
 
@@ -132,13 +129,6 @@ impl SUPlus {
         None
     }
 
-    fn config_hash(config: &Config) -> u64 {
-        let alias_hash = hash_vec(&config.resolve.alias);
-        let external_hash = hash_hashmap(&config.externals);
-
-        alias_hash.wrapping_add(external_hash)
-    }
-
     fn in_updating_stage(&self) {
         let mut s = self.stage.lock().unwrap();
         *s = SSUScanStage::Updating;
@@ -165,7 +155,8 @@ impl Plugin for SUPlus {
     }
 
     fn modify_config(&self, config: &mut Config, _root: &Path, _args: &Args) -> Result<()> {
-        for p in config.entry.values_mut() {
+        for entry in config.entry.values_mut() {
+            let p = &mut entry.import;
             *p = PathBuf::from(format!("{SSU_ENTRY_PREFIX}{}", p.to_string_lossy()));
         }
 
@@ -549,7 +540,7 @@ module.export = Promise.all(
             *state = content;
         }
 
-        self.current_state.lock().unwrap().config_hash = Self::config_hash(&context.config);
+        self.current_state.lock().unwrap().cache_keys = context.cache_keys;
 
         Ok(())
     }
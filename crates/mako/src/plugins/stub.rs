@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use swc_core::ecma::visit::VisitWith;
+
+use crate::ast::error::{code_frame, ErrorSpan};
+use crate::ast::file::{Content, JsContent};
+use crate::compiler::{Compiler, Context};
+use crate::config::{StubConfig, STUB_NOOP};
+use crate::module::ModuleId;
+use crate::plugin::{Plugin, PluginResolveIdParams};
+use crate::plugins::json_exports_check::CollectNamedImports;
+use crate::plugins::tree_shaking::shake::check_named_exports::did_you_mean;
+use crate::resolve::ResolverResource;
+
+const STUB_PREFIX: &str = "virtual:mako-stub:";
+const STUB_LOCAL: &str = "__mako_stub__";
+
+/// Replace the packages of the `stub` config with the generated modules,
+/// which export the configured names and a default object of them, and
+/// fail the build if a name which is not configured is imported.
+pub struct StubPlugin {}
+
+impl Plugin for StubPlugin {
+    fn name(&self) -> &str {
+        "stub"
+    }
+
+    fn build_start(&self, context: &Arc<Context>) -> Result<()> {
+        for name in context.config.stub.keys() {
+            let name = name.clone();
+            context
+                .virtual_modules
+                .register(&stub_id(&name), move |context| {
+                    Ok(Content::Js(JsContent {
+                        content: render(&context.config.stub[&name])?,
+                        ..Default::default()
+                    }))
+                });
+        }
+        Ok(())
+    }
+
+    fn resolve_id(
+        &self,
+        source: &str,
+        _importer: &str,
+        _params: &PluginResolveIdParams,
+        context: &Arc<Context>,
+    ) -> Result<Option<ResolverResource>> {
+        if !context.config.stub.contains_key(source) {
+            return Ok(None);
+        }
+        Ok(Some(ResolverResource::Virtual(PathBuf::from(stub_id(
+            source,
+        )))))
+    }
+
+    fn after_build(&self, context: &Arc<Context>, _compiler: &Compiler) -> Result<()> {
+        let module_graph = context.module_graph.read().unwrap();
+        let mut errors = vec![];
+
+        for module in module_graph.modules() {
+            let ast = match module.info.as_ref().and_then(|info| info.ast.as_script()) {
+                Some(ast) => ast,
+                None => continue,
+            };
+            let mut collector = CollectNamedImports { imports: vec![] };
+            ast.ast.visit_with(&mut collector);
+
+            for (source, imported, span) in collector.imports {
+                let exports = match module_graph
+                    .get_dependency_module_by_source(&module.id, &source)
+                    .and_then(stub_name)
+                    .and_then(|name| context.config.stub.get(name))
+                {
+                    Some(config) => exported_names(config),
+                    None => continue,
+                };
+                if exports.contains(&imported) {
+                    continue;
+                }
+                let mut message = format!("\"{}\" is not exported by \"{}\"", imported, source);
+                if let Some(suggestion) = did_you_mean(&imported, &exports) {
+                    message.push_str(&format!(", did you mean \"{}\"?", suggestion));
+                }
+                message.push_str(&format!(", add it to `stub[\"{}\"].exports`", source));
+                errors.push(code_frame(ErrorSpan::Js(span), &message, context.clone()));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(errors.join("\n")))
+        }
+    }
+}
+
+fn stub_id(name: &str) -> String {
+    format!("{}{}", STUB_PREFIX, name)
+}
+
+// the stubbed package of the module, None if it's not a stub
+fn stub_name(id: &ModuleId) -> Option<&str> {
+    id.id.strip_prefix(STUB_PREFIX)
+}
+
+fn exported_names(config: &StubConfig) -> HashSet<String> {
+    config
+        .exports
+        .iter()
+        .filter(|name| *name != "default" && is_identifier_name(name))
+        .cloned()
+        .collect()
+}
+
+fn render(config: &StubConfig) -> Result<String> {
+    let value = match config.value.as_str() {
+        Some(STUB_NOOP) => "function () {}".to_string(),
+        _ => format!("({})", serde_json::to_string(&config.value)?),
+    };
+    let mut names = config
+        .exports
+        .iter()
+        .filter(|name| *name != "default")
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+
+    let mut content = format!("const {} = {};\n", STUB_LOCAL, value);
+    // the string export names are not supported by tree shaking, so the
+    // names which are not identifiers are only the keys of the default export
+    for name in names.iter().filter(|name| is_identifier_name(name)) {
+        content.push_str(&format!("export {{ {} as {} }};\n", STUB_LOCAL, name));
+    }
+    let props = names
+        .iter()
+        .map(|name| Ok(format!("{}: {}", serde_json::to_string(name)?, STUB_LOCAL)))
+        .collect::<Result<Vec<_>>>()?;
+    if props.is_empty() {
+        content.push_str("export default {};\n");
+    } else {
+        content.push_str(&format!("export default {{ {} }};\n", props.join(", ")));
+    }
+    Ok(content)
+}
+
+// names which can be exported without quotes, reserved words included
+fn is_identifier_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::build_fixture;
+
+    const CONFIG: &str = r#"{
+        "stub": {
+            "analytics-sdk": { "exports": ["init", "track"] },
+            "feature-flags": { "exports": ["enabled", "some-flag"], "value": false }
+        }
+    }"#;
+
+    #[test]
+    fn test_render() {
+        let config = StubConfig {
+            exports: vec!["track".to_string(), "init".to_string(), "a-b".to_string()],
+            value: json!(STUB_NOOP),
+        };
+        assert_eq!(
+            render(&config).unwrap(),
+            r#"const __mako_stub__ = function () {};
+export { __mako_stub__ as init };
+export { __mako_stub__ as track };
+export default { "a-b": __mako_stub__, "init": __mako_stub__, "track": __mako_stub__ };
+"#
+        );
+        let config = StubConfig {
+            exports: vec!["default".to_string()],
+            value: json!({ "on": true }),
+        };
+        assert_eq!(
+            render(&config).unwrap(),
+            "const __mako_stub__ = ({\"on\":true});\nexport default {};\n"
+        );
+    }
+
+    #[test]
+    fn test_stub() {
+        let artifacts = build_fixture(
+            Some(CONFIG),
+            &[(
+                "src/index.ts",
+                r#"
+import sdk, { init, track } from "analytics-sdk";
+import * as flags from "feature-flags";
+export { enabled } from "feature-flags";
+init();
+track("view");
+console.log(sdk, flags.default["some-flag"]);
+"#,
+            )],
+        )
+        .unwrap();
+        // concatenated into the importer
+        let index = artifacts.asset("index.js").unwrap();
+        assert!(index.contains("CONCATENATED MODULE: ./virtual:mako-stub:analytics-sdk"));
+        assert!(index.contains("CONCATENATED MODULE: ./virtual:mako-stub:feature-flags"));
+        assert!(index.contains("const __mako_stub__ = function() {};"));
+        assert!(index.contains("const __mako_stub___1 = false;"));
+    }
+
+    #[test]
+    fn test_stub_missing_export() {
+        let err = build_fixture(
+            Some(CONFIG),
+            &[(
+                "src/index.ts",
+                r#"
+import { init, trak, identify } from "analytics-sdk";
+console.log(init, trak, identify);
+"#,
+            )],
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains(
+            "\"trak\" is not exported by \"analytics-sdk\", did you mean \"track\"?, add it to `stub[\"analytics-sdk\"].exports`"
+        ));
+        assert!(err.contains("\"identify\" is not exported by \"analytics-sdk\", add it"));
+        assert!(!err.contains("\"init\" is not exported"));
+    }
+}
@@ -13,7 +13,7 @@ mod collect_explicit_prop;
 mod module;
 mod module_side_effects_flag;
 mod remove_useless_stmts;
-mod shake;
+pub(crate) mod shake;
 mod statement_graph;
 
 pub struct FarmTreeShake {}
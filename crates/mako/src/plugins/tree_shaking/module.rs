@@ -1,10 +1,11 @@
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Display;
 
 use swc_core::common::SyntaxContext;
 use swc_core::ecma::ast::{Module as SwcModule, ModuleItem};
 
 use crate::module::{Module, ModuleId, ModuleSystem};
+use crate::plugins::tree_shaking::statement_graph::pure_checker::PureChecker;
 use crate::plugins::tree_shaking::statement_graph::{
     ExportInfo, ExportInfoMatch, ExportSource, ExportSpecifierInfo, ImportInfo, StatementGraph,
     StatementId,
@@ -87,31 +88,6 @@ impl Default for AllExports {
 }
 
 impl AllExports {
-    fn all_specifiers(&self) -> Vec<String> {
-        match self {
-            AllExports::Precise(ids) => ids
-                .iter()
-                .filter(|&id| id != "default")
-                .cloned()
-                .collect::<Vec<_>>(),
-
-            AllExports::Ambiguous(ids) => ids
-                .iter()
-                .filter(|&id| id != "default")
-                .cloned()
-                .collect::<Vec<_>>(),
-        }
-    }
-
-    pub fn to_all_specifier(&self) -> ExportSpecifierInfo {
-        let sps = self.all_specifiers();
-
-        match self {
-            AllExports::Precise(_) => ExportSpecifierInfo::All(sps),
-            AllExports::Ambiguous(_) => ExportSpecifierInfo::Ambiguous(sps),
-        }
-    }
-
     pub fn add_idents<I: IntoIterator<Item = String>>(&mut self, idents: I) {
         match self {
             AllExports::Precise(s) => s.extend(idents),
@@ -119,30 +95,6 @@ impl AllExports {
         }
     }
 
-    pub fn extends(&mut self, other: AllExports) {
-        match self {
-            AllExports::Precise(self_set) => match other {
-                AllExports::Precise(other_set) => {
-                    self_set.extend(other_set);
-                }
-                AllExports::Ambiguous(other_set) => {
-                    let mut new_set = HashSet::new();
-                    new_set.extend(self_set.drain());
-                    new_set.extend(other_set);
-                    *self = AllExports::Ambiguous(new_set);
-                }
-            },
-            AllExports::Ambiguous(self_set) => match other {
-                AllExports::Precise(other_set) => {
-                    self_set.extend(other_set);
-                }
-                AllExports::Ambiguous(other_set) => {
-                    self_set.extend(other_set);
-                }
-            },
-        }
-    }
-
     pub fn as_ambiguous(&mut self) {
         match self {
             AllExports::Precise(s) => {
@@ -164,16 +116,21 @@ pub struct TreeShakeModule {
     used_exports: UsedExports,
     pub module_system: ModuleSystem,
     pub all_exports: AllExports,
+    // the names re-exported by multiple `export *` from different origins,
+    // which are excluded like esm, with the modules declaring them
+    pub ambiguous_exports: HashMap<String, Vec<ModuleId>>,
     pub is_async: bool,
     pub topo_order: usize,
     pub updated_ast: Option<SwcModule>,
     pub side_effect_dep_sources: HashSet<String>,
-    pub unresolved_ctxt: SyntaxContext,
+    pub pure_checker: PureChecker,
 }
 
 impl TreeShakeModule {
     pub fn update_stmt_graph(&mut self, module: &SwcModule) {
-        let stmt_graph = StatementGraph::new(module, self.unresolved_ctxt);
+        // the imports may be changed, e.g. by skip modules
+        self.pure_checker = self.pure_checker.with_namespaces(module);
+        let stmt_graph = StatementGraph::new(module, &self.pure_checker);
 
         self.stmt_graph = stmt_graph;
     }
@@ -273,10 +230,10 @@ impl TreeShakeModule {
         self.used_exports.is_empty()
     }
 
-    pub fn new(module: &Module, order: usize) -> Self {
+    pub fn new(module: &Module, order: usize, pure_checker: &PureChecker) -> Self {
         let module_info = module.info.as_ref().unwrap();
 
-        let mut unresolved_ctxt = SyntaxContext::empty();
+        let mut pure_checker = pure_checker.clone();
         // 1. generate statement graph
         let module_system = module_info.module_system.clone();
         let stmt_graph = match &module_info.ast {
@@ -287,8 +244,12 @@ impl TreeShakeModule {
                     .iter()
                     .any(|s| matches!(s, ModuleItem::ModuleDecl(_)));
                 if is_esm {
-                    unresolved_ctxt = unresolved_ctxt.apply_mark(module.unresolved_mark);
-                    StatementGraph::new(&module.ast, unresolved_ctxt)
+                    pure_checker = pure_checker
+                        .with_unresolved_ctxt(
+                            SyntaxContext::empty().apply_mark(module.unresolved_mark),
+                        )
+                        .with_namespaces(&module.ast);
+                    StatementGraph::new(&module.ast, &pure_checker)
                 } else {
                     StatementGraph::empty()
                 }
@@ -317,10 +278,11 @@ impl TreeShakeModule {
                     AllExports::Ambiguous(Default::default())
                 }
             },
+            ambiguous_exports: Default::default(),
             module_system,
             topo_order: order,
             updated_ast: None,
-            unresolved_ctxt,
+            pure_checker,
         }
     }
 
@@ -96,7 +96,10 @@ fn match_glob_pattern(pattern: &str, path: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use super::match_glob_pattern;
+    use crate::testing::compile_fixture;
     use crate::utils::test_helper::{get_module, setup_compiler};
 
     #[test]
@@ -145,4 +148,24 @@ mod tests {
         assert!(!four.info.unwrap().get_side_effects_flag());
         assert!(four_s.info.unwrap().get_side_effects_flag());
     }
+
+    #[test]
+    fn test_side_effects_disabled() {
+        let compile = |edit: &str| {
+            let compiler =
+                compile_fixture(Path::new("test/build/side-effects-disabled"), Some(edit)).unwrap();
+            let index = compiler
+                .context
+                .output_fs
+                .read(&compiler.context.config.output.path.join("index.js"))
+                .unwrap();
+            String::from_utf8(index).unwrap()
+        };
+        let honored = compile(r#"{"mode":"production","hash":false,"minify":false}"#);
+        assert!(!honored.contains("registered by b"), "{}", honored);
+        let disabled = compile(
+            r#"{"mode":"production","hash":false,"minify":false,"optimization":{"sideEffects":false}}"#,
+        );
+        assert!(disabled.contains("registered by b"), "{}", disabled);
+    }
 }
@@ -45,7 +45,7 @@ pub fn remove_useless_stmts(
             stmt_id,
             module_item,
             Some(used_defined_idents.clone()),
-            tree_shake_module.unresolved_ctxt,
+            &tree_shake_module.pure_checker,
         );
 
         if let Some(import_info) = import_info {
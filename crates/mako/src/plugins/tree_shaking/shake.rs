@@ -1,3 +1,4 @@
+pub(crate) mod check_named_exports;
 mod find_export_source;
 mod module_concatenate;
 mod skip_module;
@@ -5,6 +6,7 @@ mod skip_module;
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::ops::DerefMut;
+use std::rc::Rc;
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -13,12 +15,14 @@ use swc_core::common::util::take::Take;
 use swc_core::common::GLOBALS;
 use swc_core::ecma::transforms::base::helpers::{Helpers, HELPERS};
 
+use self::check_named_exports::check_named_exports;
 use self::skip_module::skip_module_optimize;
 use crate::compiler::Context;
 use crate::module::{ModuleAst, ModuleId, ModuleSystem, ModuleType, ResolveType};
 use crate::module_graph::ModuleGraph;
 use crate::plugins::tree_shaking::module::{AllExports, TreeShakeModule};
 use crate::plugins::tree_shaking::shake::module_concatenate::optimize_module_graph;
+use crate::plugins::tree_shaking::statement_graph::pure_checker::PureChecker;
 use crate::plugins::tree_shaking::statement_graph::{ExportInfo, ExportSpecifierInfo, ImportInfo};
 use crate::plugins::tree_shaking::{module, remove_useless_stmts, statement_graph};
 use crate::{mako_profile_function, mako_profile_scope};
@@ -59,6 +63,22 @@ pub fn optimize_modules(module_graph: &mut ModuleGraph, context: &Arc<Context>)
             false
         });
 
+    let pure_checker = PureChecker::new(
+        context.meta.script.origin_comments.read().unwrap().clone(),
+        context
+            .config
+            .optimization
+            .as_ref()
+            .and_then(|optimization| optimization.pure_functions.as_deref())
+            .unwrap_or_default(),
+    );
+    // whether the sideEffects of package.json are honored
+    let side_effects_flag = context
+        .config
+        .optimization
+        .as_ref()
+        .and_then(|optimization| optimization.side_effects)
+        .unwrap_or(true);
     let tree_shake_modules_map = {
         mako_profile_scope!("init_tree_shake_modules_map");
         tree_shake_modules_ids
@@ -69,9 +89,13 @@ pub fn optimize_modules(module_graph: &mut ModuleGraph, context: &Arc<Context>)
 
                 let module = module_graph.get_module(module_id).unwrap();
 
-                let tree_shake_module = GLOBALS.set(&context.meta.script.globals, || {
-                    TreeShakeModule::new(module, index)
+                let mut tree_shake_module = GLOBALS.set(&context.meta.script.globals, || {
+                    TreeShakeModule::new(module, index, &pure_checker)
                 });
+                // the side effects are only the ones of the code then
+                if !side_effects_flag {
+                    tree_shake_module.described_side_effects = None;
+                }
 
                 (module_id.clone(), RefCell::new(tree_shake_module))
             })
@@ -115,6 +139,16 @@ pub fn optimize_modules(module_graph: &mut ModuleGraph, context: &Arc<Context>)
         module_graph,
     );
 
+    // check before the imports are rewritten by skip modules
+    check_named_exports(
+        context.config.named_exports_check,
+        context.config.default_import_check,
+        &tree_shake_modules_ids,
+        &tree_shake_modules_map,
+        module_graph,
+        context,
+    )?;
+
     if let Some(optimization) = &context.config.optimization
         && optimization.skip_modules.unwrap_or(false)
     {
@@ -161,6 +195,7 @@ pub fn optimize_modules(module_graph: &mut ModuleGraph, context: &Arc<Context>)
             let mut tsm = tsm.borrow_mut();
 
             if tsm.not_used() {
+                context.stats_info.add_tree_shaken_module(module_id);
                 module_graph.remove_module(module_id);
             } else if let Some(swc_module) = &mut tsm.updated_ast {
                 module_graph
@@ -358,6 +393,37 @@ fn add_used_exports_by_export_info(
     None
 }
 
+/// The module which declares an export, and the name of it in the module.
+type ExportOrigin = (ModuleId, String);
+
+/// The exports of a module resolved like esm: the exports of the module
+/// shadow the ones of `export *`, `default` is never re-exported by
+/// `export *`, and a name re-exported by multiple `export *` from different
+/// origins is ambiguous, i.e. it has more than one origin.
+struct ExportMap {
+    exports: HashMap<String, Vec<ExportOrigin>>,
+    // the names which are not from `export *`
+    own: HashSet<String>,
+    // false if the exports are not statically known, e.g. `export *` from commonjs
+    precise: bool,
+}
+
+impl ExportMap {
+    fn new(precise: bool) -> Self {
+        Self {
+            exports: Default::default(),
+            own: Default::default(),
+            precise,
+        }
+    }
+
+    fn is_resolved(&self, name: &str) -> bool {
+        self.exports
+            .get(name)
+            .is_some_and(|origins| origins.len() == 1)
+    }
+}
+
 fn fill_all_export_start_export_info(
     tree_shake_modules_ids: &[ModuleId],
     tree_shake_modules_map: &HashMap<ModuleId, RefCell<TreeShakeModule>>,
@@ -365,6 +431,8 @@ fn fill_all_export_start_export_info(
 ) {
     mako_profile_function!();
 
+    let mut export_maps = HashMap::new();
+
     for module_id in tree_shake_modules_ids.iter() {
         let tsm = tree_shake_modules_map.get(module_id).unwrap().borrow();
 
@@ -372,59 +440,98 @@ fn fill_all_export_start_export_info(
             continue;
         }
 
-        let mut all_exports = AllExports::default();
+        let exports = tsm.exports();
+
+        drop(tsm);
+
+        let export_map = build_export_map(
+            module_id,
+            tree_shake_modules_map,
+            module_graph,
+            &mut export_maps,
+            &mut HashSet::new(),
+        );
 
         let mut exports_stmts_export_info = vec![];
 
-        for exp_info in tsm.exports() {
-            if let Some(source) = exp_info.source {
-                for sp_info in exp_info.specifiers {
-                    match sp_info {
-                        // export * from "xx"
-                        ExportSpecifierInfo::All(_) | ExportSpecifierInfo::Ambiguous(_) => {
-                            if let Some(dependent_id) =
-                                module_graph.get_dependency_module_by_source(module_id, &source)
-                            {
-                                if tree_shake_modules_map.contains_key(dependent_id) {
-                                    let mut dep_all_exports = AllExports::default();
-                                    let mut visited = HashSet::new();
-
-                                    collect_all_exports_of(
-                                        dependent_id,
-                                        tree_shake_modules_map,
-                                        module_graph,
-                                        &mut dep_all_exports,
-                                        &mut visited,
-                                    );
-
-                                    exports_stmts_export_info.push(ExportInfo {
-                                        source: Some(source.clone()),
-                                        specifiers: vec![dep_all_exports.to_all_specifier()],
-                                        stmt_id: exp_info.stmt_id,
-                                    });
-
-                                    all_exports.extends(dep_all_exports);
-                                }
-                            }
-                        }
-                        _ => {
-                            all_exports.add_idents(sp_info.to_idents());
-                        }
-                    }
-                }
-            } else {
-                for exp_sp in exp_info.specifiers {
-                    let idents = exp_sp.to_idents();
-                    all_exports.add_idents(idents);
-                }
+        for exp_info in exports {
+            let source = match &exp_info.source {
+                Some(source) => source,
+                None => continue,
+            };
+            // export * from "xx"
+            if !matches!(
+                exp_info.specifiers.first(),
+                Some(ExportSpecifierInfo::All(_) | ExportSpecifierInfo::Ambiguous(_))
+            ) {
+                continue;
+            }
+            if let Some(dependent_id) =
+                module_graph.get_dependency_module_by_source(module_id, source)
+                && tree_shake_modules_map.contains_key(dependent_id)
+            {
+                let dep_export_map = build_export_map(
+                    dependent_id,
+                    tree_shake_modules_map,
+                    module_graph,
+                    &mut export_maps,
+                    &mut HashSet::new(),
+                );
+                // the shadowed and the ambiguous names are not re-exported
+                let idents = dep_export_map
+                    .exports
+                    .keys()
+                    .filter(|&ident| {
+                        ident != "default"
+                            && !export_map.own.contains(ident)
+                            && export_map.is_resolved(ident)
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                exports_stmts_export_info.push(ExportInfo {
+                    source: Some(source.clone()),
+                    specifiers: vec![if dep_export_map.precise {
+                        ExportSpecifierInfo::All(idents)
+                    } else {
+                        ExportSpecifierInfo::Ambiguous(idents)
+                    }],
+                    stmt_id: exp_info.stmt_id,
+                });
             }
         }
 
-        drop(tsm);
+        let mut all_exports = AllExports::default();
+        if !export_map.precise {
+            all_exports.as_ambiguous();
+        }
+        all_exports.add_idents(
+            export_map
+                .exports
+                .keys()
+                .filter(|ident| export_map.is_resolved(ident))
+                .cloned(),
+        );
+
+        let ambiguous_exports = export_map
+            .exports
+            .iter()
+            .filter(|(_, origins)| origins.len() > 1)
+            .map(|(ident, origins)| {
+                let mut module_ids: Vec<ModuleId> = vec![];
+                for (origin_module_id, _) in origins {
+                    if !module_ids.contains(origin_module_id) {
+                        module_ids.push(origin_module_id.clone());
+                    }
+                }
+                (ident.clone(), module_ids)
+            })
+            .collect();
 
         let mut tsm = tree_shake_modules_map.get(module_id).unwrap().borrow_mut();
 
         tsm.all_exports = all_exports;
+        tsm.ambiguous_exports = ambiguous_exports;
 
         for exp_info in exports_stmts_export_info {
             let stmt_id = exp_info.stmt_id;
@@ -433,61 +540,101 @@ fn fill_all_export_start_export_info(
     }
 }
 
-fn collect_all_exports_of(
+fn build_export_map(
     module_id: &ModuleId,
     tree_shake_modules_map: &HashMap<ModuleId, RefCell<TreeShakeModule>>,
     module_graph: &ModuleGraph,
-    all_exports: &mut AllExports,
-    visited: &mut HashSet<ModuleId>,
-) {
-    let tsm = tree_shake_modules_map.get(module_id).unwrap().borrow();
-
-    if visited.contains(module_id) {
-        return;
+    export_maps: &mut HashMap<ModuleId, Rc<ExportMap>>,
+    visiting: &mut HashSet<ModuleId>,
+) -> Rc<ExportMap> {
+    if let Some(export_map) = export_maps.get(module_id) {
+        return export_map.clone();
     }
-
-    visited.insert(module_id.clone());
-
-    if tsm.module_system != ModuleSystem::ESModule {
-        all_exports.as_ambiguous();
+    // a cycle of `export *` re-exports nothing
+    if !visiting.insert(module_id.clone()) {
+        return Rc::new(ExportMap::new(true));
     }
 
+    let tsm = tree_shake_modules_map.get(module_id).unwrap().borrow();
+    let mut export_map = ExportMap::new(tsm.module_system == ModuleSystem::ESModule);
+    let mut star_dependent_ids = vec![];
+
     for exp_info in tsm.exports() {
-        if let Some(source) = exp_info.source {
-            for sp_info in exp_info.specifiers {
-                match sp_info {
-                    // export * from "xx"
-                    ExportSpecifierInfo::All(_) | ExportSpecifierInfo::Ambiguous(_) => {
-                        if let Some(dependent_id) =
-                            module_graph.get_dependency_module_by_source(module_id, &source)
-                        {
-                            if tree_shake_modules_map.contains_key(dependent_id) {
-                                let mut dep_all_exports = AllExports::default();
-
-                                collect_all_exports_of(
-                                    dependent_id,
-                                    tree_shake_modules_map,
-                                    module_graph,
-                                    &mut dep_all_exports,
-                                    visited,
-                                );
-
-                                all_exports.extends(dep_all_exports);
-                            }
-                        }
+        let dependent_id = exp_info
+            .source
+            .as_ref()
+            .and_then(|source| module_graph.get_dependency_module_by_source(module_id, source))
+            .filter(|&dependent_id| tree_shake_modules_map.contains_key(dependent_id));
+
+        for sp_info in &exp_info.specifiers {
+            match sp_info {
+                // export * from "xx"
+                ExportSpecifierInfo::All(_) | ExportSpecifierInfo::Ambiguous(_) => {
+                    if let Some(dependent_id) = dependent_id {
+                        star_dependent_ids.push(dependent_id.clone());
                     }
-                    _ => {
-                        all_exports.add_idents(sp_info.to_idents());
+                }
+                _ => {
+                    // export { a as b } from "xx", the origin is the one of a in "xx"
+                    let dep_origin = match (sp_info, dependent_id) {
+                        (ExportSpecifierInfo::Named { local, .. }, Some(dependent_id)) => {
+                            let dep_export_map = build_export_map(
+                                dependent_id,
+                                tree_shake_modules_map,
+                                module_graph,
+                                export_maps,
+                                visiting,
+                            );
+                            dep_export_map
+                                .exports
+                                .get(&strip_context(local))
+                                .filter(|origins| origins.len() == 1)
+                                .map(|origins| origins[0].clone())
+                        }
+                        _ => None,
+                    };
+                    for ident in sp_info.to_idents() {
+                        let origin = dep_origin
+                            .clone()
+                            .unwrap_or_else(|| (module_id.clone(), ident.clone()));
+                        export_map.own.insert(ident.clone());
+                        export_map.exports.insert(ident, vec![origin]);
                     }
                 }
             }
-        } else {
-            for exp_sp in exp_info.specifiers {
-                let idents = exp_sp.to_idents();
-                all_exports.add_idents(idents);
+        }
+    }
+
+    drop(tsm);
+
+    let mut star_exports: HashMap<String, Vec<ExportOrigin>> = HashMap::new();
+    for dependent_id in star_dependent_ids {
+        let dep_export_map = build_export_map(
+            &dependent_id,
+            tree_shake_modules_map,
+            module_graph,
+            export_maps,
+            visiting,
+        );
+        export_map.precise &= dep_export_map.precise;
+        for (ident, origins) in &dep_export_map.exports {
+            if ident == "default" || export_map.own.contains(ident) {
+                continue;
+            }
+            let star_origins = star_exports.entry(ident.clone()).or_default();
+            for origin in origins {
+                if !star_origins.contains(origin) {
+                    star_origins.push(origin.clone());
+                }
             }
         }
     }
+    export_map.exports.extend(star_exports);
+
+    visiting.remove(module_id);
+    let export_map = Rc::new(export_map);
+    export_maps.insert(module_id.clone(), export_map.clone());
+    export_map
 }
 
 fn shake_module(
@@ -0,0 +1,347 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+
+use super::{strip_context, TreeShakingModuleMap};
+use crate::ast::error::{code_frame, ErrorSpan};
+use crate::compiler::Context;
+use crate::config::{DefaultImportCheckConfig, NamedExportsCheckConfig};
+use crate::module::{relative_to_root, ModuleId, ModuleSystem};
+use crate::module_graph::ModuleGraph;
+use crate::plugins::tree_shaking::statement_graph::{ExportSpecifierInfo, ImportSpecifierInfo};
+
+const MAX_LISTED_EXPORTS: usize = 10;
+
+/// Check that every named import exists in the exports of the target module,
+/// targets whose exports are not statically known (commonjs, or `export *`
+/// from them) are skipped. The imports of the names which are ambiguous by
+/// `export *` are errors like esm, even without `namedExportsCheck`. The
+/// default imports are checked by `defaultImportCheck` if it's enabled, since
+/// they're namespace confusions more often than typos.
+pub fn check_named_exports(
+    config: Option<NamedExportsCheckConfig>,
+    default_config: Option<DefaultImportCheckConfig>,
+    tree_shake_modules_ids: &[ModuleId],
+    tree_shake_modules_map: &TreeShakingModuleMap,
+    module_graph: &ModuleGraph,
+    context: &Arc<Context>,
+) -> Result<()> {
+    let mut errors = vec![];
+    let mut warnings = vec![];
+
+    for module_id in tree_shake_modules_ids {
+        let module = module_graph.get_module(module_id).unwrap();
+        // not fixable by users
+        if module
+            .info
+            .as_ref()
+            .is_some_and(|info| info.file.is_under_node_modules)
+        {
+            continue;
+        }
+
+        let tsm = tree_shake_modules_map.get(module_id).unwrap().borrow();
+        for stmt in tsm.stmt_graph.stmts() {
+            let import_info = match &stmt.import_info {
+                Some(import_info) => import_info,
+                None => continue,
+            };
+            let dep_id = match module_graph
+                .get_dependency_module_by_source(module_id, &import_info.source)
+            {
+                Some(dep_id) => dep_id,
+                None => continue,
+            };
+            let ambiguous_exports = tree_shake_modules_map
+                .get(dep_id)
+                .map(|dep_tsm| dep_tsm.borrow().ambiguous_exports.clone())
+                .unwrap_or_default();
+            // the ignored modules are empty on purpose
+            let is_ignored = module_graph
+                .get_module(dep_id)
+                .and_then(|dep| dep.info.as_ref())
+                .is_some_and(|info| info.is_ignored);
+            let has_default = !is_ignored
+                && import_info
+                    .specifiers
+                    .iter()
+                    .any(|specifier| matches!(specifier, ImportSpecifierInfo::Default(_)));
+            let exports = if config.is_some() || (has_default && default_config.is_some()) {
+                collect_exports(
+                    dep_id,
+                    tree_shake_modules_map,
+                    module_graph,
+                    &mut HashSet::new(),
+                )
+            } else {
+                None
+            };
+
+            for specifier in &import_info.specifiers {
+                let imported = match specifier {
+                    ImportSpecifierInfo::Named { local, imported } => {
+                        strip_context(imported.as_ref().unwrap_or(local))
+                    }
+                    ImportSpecifierInfo::Default(_) => "default".to_string(),
+                    ImportSpecifierInfo::Namespace(_) => continue,
+                };
+                if let Some(module_ids) = ambiguous_exports.get(&imported) {
+                    let sources = module_ids
+                        .iter()
+                        .map(|id| {
+                            format!(
+                                "\"{}\"",
+                                relative_to_root(&id.id.to_string(), &context.root)
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    let message = format!(
+                        "\"{}\" is ambiguous in \"{}\", it's re-exported by `export *` from both {}",
+                        imported,
+                        import_info.source,
+                        sources.join(" and ")
+                    );
+                    let error = code_frame(ErrorSpan::Js(stmt.span), &message, context.clone());
+                    match config {
+                        Some(NamedExportsCheckConfig::Warn) => warnings.push(error),
+                        _ => errors.push(error),
+                    }
+                    continue;
+                }
+                let exports = match &exports {
+                    Some(exports) => exports,
+                    None => continue,
+                };
+                if exports.contains(&imported) {
+                    continue;
+                }
+                if let (ImportSpecifierInfo::Default(local), Some(default_config)) =
+                    (specifier, default_config)
+                {
+                    if is_ignored {
+                        continue;
+                    }
+                    let message =
+                        default_import_message(&strip_context(local), &import_info.source, exports);
+                    let error = code_frame(ErrorSpan::Js(stmt.span), &message, context.clone());
+                    match default_config {
+                        DefaultImportCheckConfig::Error => errors.push(error),
+                        DefaultImportCheckConfig::Warn => warnings.push(error),
+                    }
+                    continue;
+                }
+                let Some(config) = config else {
+                    continue;
+                };
+                let mut message = format!(
+                    "\"{}\" is not exported by \"{}\"",
+                    imported, import_info.source
+                );
+                if let Some(suggestion) = did_you_mean(&imported, exports) {
+                    message.push_str(&format!(", did you mean \"{}\"?", suggestion));
+                }
+                let error = code_frame(ErrorSpan::Js(stmt.span), &message, context.clone());
+                match config {
+                    NamedExportsCheckConfig::Error => errors.push(error),
+                    NamedExportsCheckConfig::Warn => warnings.push(error),
+                }
+            }
+        }
+    }
+
+    for warning in warnings {
+        eprintln!("{}: {}", "Warning".yellow(), warning);
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(errors.join("\n")))
+    }
+}
+
+// e.g. `import React from "react"` of an esm module without a default export,
+// whose binding is undefined at runtime
+fn default_import_message(local: &str, source: &str, exports: &HashSet<String>) -> String {
+    let mut exports = exports.iter().cloned().collect::<Vec<_>>();
+    exports.sort();
+    if exports.is_empty() {
+        return format!(
+            "\"default\" is not exported by \"{}\", which has no exports, use `import \"{}\"` to only run it",
+            source, source
+        );
+    }
+    let listed = if exports.len() > MAX_LISTED_EXPORTS {
+        format!(
+            "{}, ... ({} in total)",
+            exports[..MAX_LISTED_EXPORTS].join(", "),
+            exports.len()
+        )
+    } else {
+        exports.join(", ")
+    };
+    format!(
+        "\"default\" is not exported by \"{}\", which only has the named exports: {}\n  use `import * as {} from \"{}\"` for all the exports, or import the names, e.g. `import {{ {} }} from \"{}\"`",
+        source, listed, local, source, exports[0], source
+    )
+}
+
+// None means the exports can't be known statically
+fn collect_exports(
+    module_id: &ModuleId,
+    tree_shake_modules_map: &TreeShakingModuleMap,
+    module_graph: &ModuleGraph,
+    visited: &mut HashSet<ModuleId>,
+) -> Option<HashSet<String>> {
+    if !visited.insert(module_id.clone()) {
+        return Some(HashSet::new());
+    }
+    let tsm = tree_shake_modules_map.get(module_id)?.borrow();
+    if tsm.module_system != ModuleSystem::ESModule {
+        return None;
+    }
+
+    let mut exports = HashSet::new();
+    for export_info in tsm.exports() {
+        for specifier in &export_info.specifiers {
+            match specifier {
+                // export * from "xx"
+                ExportSpecifierInfo::All(_) | ExportSpecifierInfo::Ambiguous(_) => {
+                    let source = export_info.source.as_ref()?;
+                    let dep_id = module_graph.get_dependency_module_by_source(module_id, source)?;
+                    let dep_exports =
+                        collect_exports(dep_id, tree_shake_modules_map, module_graph, visited)?;
+                    // default is not re-exported by `export *`
+                    exports.extend(dep_exports.into_iter().filter(|e| e != "default"));
+                }
+                _ => exports.extend(specifier.to_idents()),
+            }
+        }
+    }
+    Some(exports)
+}
+
+pub(crate) fn did_you_mean(name: &str, exports: &HashSet<String>) -> Option<String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    exports
+        .iter()
+        .map(|export| (strsim::damerau_levenshtein(name, export), export))
+        .filter(|(distance, _)| *distance <= max_distance)
+        // sort by name too, so that the result is stable
+        .min_by(|a, b| a.cmp(b))
+        .map(|(_, export)| export.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+    use crate::compiler::{Args, Compiler};
+    use crate::config::Config;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_did_you_mean() {
+        let exports: HashSet<String> = ["useHistory", "useLocation", "default"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            did_you_mean("useHistroy", &exports),
+            Some("useHistory".to_string())
+        );
+        assert_eq!(
+            did_you_mean("uselocation", &exports),
+            Some("useLocation".to_string())
+        );
+        assert_eq!(did_you_mean("Link", &exports), None);
+    }
+
+    #[test]
+    fn test_default_import_message() {
+        let exports: HashSet<String> = (0..12).map(|i| format!("e{:02}", i)).collect();
+        let message = default_import_message("x", "pkg", &exports);
+        assert!(
+            message.contains("e00, e01, e02, e03, e04, e05, e06, e07, e08, e09, ... (12 in total)")
+        );
+        assert!(message.contains("`import { e00 } from \"pkg\"`"));
+        assert!(default_import_message("x", "./init", &HashSet::new())
+            .contains("which has no exports, use `import \"./init\"` to only run it"));
+    }
+
+    #[test]
+    fn test_check_named_exports() {
+        let compiler = setup_compiler("test/build/named-exports-check", false);
+        let err = compiler.compile().unwrap_err().to_string();
+        assert!(err.contains(
+            "\"useHistroy\" is not exported by \"./router\", did you mean \"useHistory\"?"
+        ));
+        assert!(err.contains(
+            "\"default\" is not exported by \"./router\", which only has the named exports: Link, useHistory, useParams"
+        ));
+        assert!(err.contains("use `import * as router from \"./router\"` for all the exports"));
+        // re-exported by `export *`
+        assert!(!err.contains("\"useParams\""));
+        // commonjs and `export *` from commonjs are skipped
+        assert!(!err.contains("\"./cjs\""));
+        assert!(!err.contains("\"./reexport-cjs\""));
+    }
+
+    #[test]
+    fn test_ambiguous_star_exports() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/export-star-ambiguous");
+        // the runtime excludes the ambiguous names without tree shaking too
+        for mode in ["production", "development"] {
+            let config =
+                Config::new(&root, None, Some(&format!(r#"{{"mode":"{}"}}"#, mode))).unwrap();
+            let output_path = config.output.path.clone();
+            let compiler =
+                Compiler::new(config, root.clone(), Args { watch: false }, None).unwrap();
+            compiler.compile().unwrap();
+            let output = Command::new("node")
+                .arg(output_path.join("index.js"))
+                .output()
+                .unwrap();
+            std::fs::remove_dir_all(&output_path).unwrap();
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout).trim(),
+                r#"{"keys":["Local","Only","Shared"],"Local":"local","Only":"d","Shared":"shared"}"#,
+                "{}",
+                mode
+            );
+        }
+
+        // without namedExportsCheck
+        let config = Config::new(
+            &root,
+            None,
+            Some(r#"{"entry":{"index":"./import-ambiguous.js"}}"#),
+        )
+        .unwrap();
+        let compiler = Compiler::new(config, root, Args { watch: false }, None).unwrap();
+        let err = compiler.compile().unwrap_err().to_string();
+        assert!(
+            err.contains(
+                r#""Button" is ambiguous in "./ds", it's re-exported by `export *` from both "./ds/a.js" and "./ds/b.js""#
+            ),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains(
+                r#""Icon" is ambiguous in "./ds", it's re-exported by `export *` from both "./ds/nested/c.js" and "./ds/nested/d.js""#
+            ),
+            "{}",
+            err
+        );
+    }
+}
@@ -480,7 +480,7 @@ mod tests {
         };
 
         GLOBALS.set(&context.meta.script.globals, || {
-            TreeShakeModule::new(&mako_module, 0)
+            TreeShakeModule::new(&mako_module, 0, &Default::default())
         })
     }
 }
@@ -21,6 +21,7 @@ use self::concatenate_context::EsmDependantFlags;
 use self::utils::uniq_module_prefix;
 use crate::ast::js_ast::JsAst;
 use crate::compiler::Context;
+use crate::config::ModuleKind;
 use crate::module::{Dependency, ImportType, ModuleId, ModuleSystem, ResolveType};
 use crate::module_graph::ModuleGraph;
 use crate::plugins::tree_shaking::module::{AllExports, TreeShakeModule};
@@ -92,12 +93,32 @@ pub fn optimize_module_graph(
             if matches!(tsm.all_exports, AllExports::Ambiguous(_)) || has_export_star {
                 can_be_inner = false;
             }
+            // the runtime excludes the ambiguous names of `export *`, which the
+            // concatenation doesn't
+            if !tsm.ambiguous_exports.is_empty() {
+                can_be_root = false;
+            }
 
             module_graph
                 .get_module(module_id)
                 .and_then(|module| module.info.as_ref())
                 .inspect(|info| {
-                    if info.is_async || info.is_ignored {
+                    // the evaluated code refers to the names of the module,
+                    // which the concatenation renames
+                    if info.is_async || info.is_ignored || !info.bailouts.is_empty() {
+                        can_be_inner = false;
+                        can_be_root = false;
+                    }
+                    // commonjs deps are imported by the rules of node, which
+                    // concatenation doesn't follow
+                    let imports_commonjs = info.file.module_kind == Some(ModuleKind::Esm)
+                        && deps.iter().any(|(id, _, _)| {
+                            module_graph
+                                .get_module(id)
+                                .and_then(|module| module.info.as_ref())
+                                .is_some_and(|info| info.module_system == ModuleSystem::CommonJS)
+                        });
+                    if imports_commonjs {
                         can_be_inner = false;
                         can_be_root = false;
                     }
@@ -283,7 +304,7 @@ pub fn optimize_module_graph(
                             (cjs_name.clone(), cjs_name)
                         };
 
-                        let require_src = id.id.clone();
+                        let require_src = id.id.to_string();
                         module_items.extend(interop.inject_external_export_decl(
                             &require_src,
                             &exposed_names,
@@ -404,7 +425,7 @@ pub fn optimize_module_graph(
                         ast: root_module_ast.clone(),
                         unresolved_mark,
                         top_level_mark,
-                        path: config.root.id.clone(),
+                        path: config.root.id.to_string(),
                         contains_top_level_await: false,
                     };
 
@@ -429,6 +450,9 @@ pub fn optimize_module_graph(
                 let ast_script = ast.script_mut().unwrap();
                 ast_script.ast = root_module_ast;
 
+                context
+                    .stats_info
+                    .add_concatenated_modules(&config.root, &config.inners);
                 for inner in config.inners.iter() {
                     module_graph.remove_module(inner);
                 }
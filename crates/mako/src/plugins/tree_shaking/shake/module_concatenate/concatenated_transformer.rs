@@ -91,7 +91,7 @@ impl<'a> ConcatenatedTransform<'a> {
 
                     text: format!(
                         " CONCATENATED MODULE: {}",
-                        relative_to_root(&self.module_id.id, &self.context.root)
+                        relative_to_root(&self.module_id.id.to_string(), &self.context.root)
                     )
                     .into(),
                     span: DUMMY_SP,
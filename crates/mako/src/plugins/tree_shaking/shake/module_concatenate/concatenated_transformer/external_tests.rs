@@ -276,10 +276,14 @@ fn run_test(code: &str, ccn_ctx: &mut ConcatenateContext) -> String {
             devtool: None,
             optimization: Some(OptimizationConfig {
                 concatenate_modules: Some(true),
+                concatenate_css: None,
+                pure_functions: None,
                 skip_modules: Some(true),
+                side_effects: None,
+                mangle_exports: None,
             }),
             mode: Mode::Production,
-            minify: true,
+            minify: Some(Default::default()),
             ..Default::default()
         },
         ..Default::default()
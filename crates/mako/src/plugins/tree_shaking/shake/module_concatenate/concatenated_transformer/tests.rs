@@ -726,10 +726,14 @@ fn inner_trans_code_imported_as(
             devtool: None,
             optimization: Some(OptimizationConfig {
                 concatenate_modules: Some(true),
+                concatenate_css: None,
+                pure_functions: None,
                 skip_modules: Some(true),
+                side_effects: None,
+                mangle_exports: None,
             }),
             mode: Mode::Production,
-            minify: false,
+            minify: None,
             ..Default::default()
         },
         ..Default::default()
@@ -36,26 +36,26 @@ impl ReExportReplace {
             ReExportType::Default => {
                 quote!("export { default as $ident } from \"$from\";" as ModuleItem,
                     ident: Ident = ident,
-                    from: Str = quote_str!(self.from_module_id.id.clone())
+                    from: Str = quote_str!(self.from_module_id.id.to_string())
                 )
             }
             ReExportType::Namespace => {
                 quote!("export * as $ident from \"$from\";" as ModuleItem,
                     ident: Ident = ident,
-                    from: Str = quote_str!(self.from_module_id.id.clone())
+                    from: Str = quote_str!(self.from_module_id.id.to_string())
                 )
             }
             ReExportType::Named(local) => {
                 if ident.sym.eq(local) {
                     quote!("export { $ident } from \"$from\";" as ModuleItem,
                         ident: Ident = ident,
-                        from: Str = quote_str!(self.from_module_id.id.clone())
+                        from: Str = quote_str!(self.from_module_id.id.to_string())
                     )
                 } else {
                     quote!("export { $local as $ident } from \"$from\";" as ModuleItem,
                         local: Ident = quote_ident!(DUMMY_CTXT, local.clone()),
                         ident: Ident = ident,
-                        from: Str = quote_str!(self.from_module_id.id.clone())
+                        from: Str = quote_str!(self.from_module_id.id.to_string())
                     )
                 }
             }
@@ -66,7 +66,7 @@ impl ReExportReplace {
         let import_type: ImportType = (&self.re_export_source.re_export_type).into();
 
         Dependency {
-            source: self.from_module_id.id.clone(),
+            source: self.from_module_id.id.to_string(),
             span: Some(span),
             order: 0,
             resolve_as: None,
@@ -82,7 +82,7 @@ impl ReExportReplace {
         };
 
         Dependency {
-            source: self.from_module_id.id.clone(),
+            source: self.from_module_id.id.to_string(),
             resolve_as: None,
             resolve_type,
             order: 0,
@@ -95,27 +95,27 @@ impl ReExportReplace {
             ReExportType::Default => {
                 quote!("import $ident from \"$from\";" as ModuleItem,
                     ident: Ident = ident,
-                    from: Str = quote_str!(self.from_module_id.id.clone())
+                    from: Str = quote_str!(self.from_module_id.id.to_string())
                 )
             }
             ReExportType::Named(local) => {
                 if ident.sym.eq(local) {
                     quote!("import { $ident } from \"$from\";" as ModuleItem,
                         ident: Ident = ident,
-                        from: Str = quote_str!(self.from_module_id.id.clone())
+                        from: Str = quote_str!(self.from_module_id.id.to_string())
                     )
                 } else {
                     quote!("import { $local as $ident } from \"$from\";" as ModuleItem,
                         local: Ident = quote_ident!(DUMMY_CTXT,local.clone()),
                         ident: Ident = ident,
-                        from: Str = quote_str!(self.from_module_id.id.clone())
+                        from: Str = quote_str!(self.from_module_id.id.to_string())
                     )
                 }
             }
             ReExportType::Namespace => {
                 quote!("import * as $ident from \"$from\";" as ModuleItem,
                     ident: Ident = ident,
-                    from: Str = quote_str!(self.from_module_id.id.clone())
+                    from: Str = quote_str!(self.from_module_id.id.to_string())
                 )
             }
         }
@@ -6,10 +6,12 @@ use swc_core::ecma::ast::{Module as SwcModule, ModuleItem};
 
 pub(crate) mod analyze_imports_and_exports;
 pub(crate) mod defined_idents_collector;
+pub(crate) mod pure_checker;
 pub(crate) mod used_idents_collector;
 
 use analyze_imports_and_exports::analyze_imports_and_exports;
-use swc_core::common::{Span, SyntaxContext};
+use pure_checker::PureChecker;
+use swc_core::common::Span;
 
 use crate::plugins::tree_shaking::module::{is_ident_equal, UsedIdent};
 use crate::plugins::tree_shaking::shake::strip_context;
@@ -247,12 +249,11 @@ pub struct Statement {
     pub is_self_executed: bool,
     #[allow(dead_code)]
     pub has_side_effects: bool,
-    #[allow(dead_code)]
     pub span: Span,
 }
 
 impl Statement {
-    pub fn new(id: StatementId, stmt: &ModuleItem, unresolved_ctxt: SyntaxContext) -> Self {
+    pub fn new(id: StatementId, stmt: &ModuleItem, pure_checker: &PureChecker) -> Self {
         let StatementInfo {
             import_info,
             export_info,
@@ -262,7 +263,7 @@ impl Statement {
             is_self_executed,
             span,
             has_side_effects,
-        } = analyze_imports_and_exports(&id, stmt, None, unresolved_ctxt);
+        } = analyze_imports_and_exports(&id, stmt, None, pure_checker);
 
         Self {
             id,
@@ -288,12 +289,12 @@ pub struct StatementGraph {
 }
 
 impl StatementGraph {
-    pub fn new(module: &SwcModule, unresolved_ctxt: SyntaxContext) -> Self {
+    pub fn new(module: &SwcModule, pure_checker: &PureChecker) -> Self {
         let mut g = petgraph::graph::Graph::new();
         let mut id_index_map = HashMap::new();
 
         for (index, stmt) in module.body.iter().enumerate() {
-            let statement = Statement::new(index, stmt, unresolved_ctxt);
+            let statement = Statement::new(index, stmt, pure_checker);
 
             let node = g.add_node(statement);
             id_index_map.insert(index, node);
@@ -501,3 +502,41 @@ impl StatementGraph {
         used_statements
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_tree_shake_inner_graph() {
+        let compiler = setup_compiler("test/build/inner-graph", false);
+        compiler.compile().unwrap();
+        let index = compiler
+            .context
+            .output_fs
+            .read(&compiler.context.config.output.path.join("index.js"))
+            .unwrap();
+        let index = String::from_utf8(index).unwrap();
+        for kept in ["USED", "SIDE_EFFECT_MODULE", "DISPLAY_NAME_MODULE"] {
+            assert!(index.contains(kept), "{} should be kept\n{}", kept, index);
+        }
+        // the declarations only referenced by the unused exports are removed
+        // with their imports, and so are the modules
+        for removed in [
+            "HEAVY_MODULE",
+            "DEP_MODULE",
+            "CLASS_MODULE",
+            "HELPER_MODULE",
+            "CIRCULAR_MODULE",
+            "NAMESPACE_MODULE",
+            "DEFAULT_MODULE",
+        ] {
+            assert!(
+                !index.contains(removed),
+                "{} should be removed\n{}",
+                removed,
+                index
+            );
+        }
+    }
+}
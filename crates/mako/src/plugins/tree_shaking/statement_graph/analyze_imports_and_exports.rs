@@ -1,13 +1,13 @@
 use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 
-use swc_core::common::{Span, SyntaxContext, DUMMY_SP};
+use swc_core::common::{Span, DUMMY_SP};
 use swc_core::ecma::ast as swc_ecma_ast;
 use swc_core::ecma::ast::{Expr, ModuleExportName, ModuleItem, VarDecl};
-use swc_core::ecma::utils::{ExprCtx, ExprExt};
 use swc_core::ecma::visit::VisitWith;
 
 use super::defined_idents_collector::DefinedIdentsCollector;
+use super::pure_checker::PureChecker;
 use super::used_idents_collector::{self, UsedIdentsCollector};
 use super::{ExportInfo, ExportSpecifierInfo, ImportInfo, ImportSpecifierInfo, StatementId};
 
@@ -27,7 +27,7 @@ pub fn analyze_imports_and_exports(
     id: &StatementId,
     stmt: &ModuleItem,
     used_defined_idents: Option<HashSet<String>>,
-    unresolve_ctxt: SyntaxContext,
+    pure_checker: &PureChecker,
 ) -> StatementInfo {
     let mut defined_idents = HashSet::new();
     let mut used_idents = HashSet::new();
@@ -157,7 +157,7 @@ pub fn analyze_imports_and_exports(
                     swc_ecma_ast::Decl::Var(var_decl) => {
                         let mut specifiers = vec![];
 
-                        is_self_executed = !is_pure_var_decl(var_decl, unresolve_ctxt);
+                        is_self_executed = !is_pure_var_decl(var_decl, pure_checker);
 
                         for v_decl in &var_decl.decls {
                             let mut defined_idents_collector = DefinedIdentsCollector::new();
@@ -412,7 +412,7 @@ pub fn analyze_imports_and_exports(
                             defined_idents_map.insert(defined_ident.clone(), local_used_idents.clone());
                         }
 
-                        if !is_pure_var_decl(var_decl, unresolve_ctxt) {
+                        if !is_pure_var_decl(var_decl, pure_checker) {
                             is_self_executed = true;
                         }
                     }
@@ -422,7 +422,7 @@ pub fn analyze_imports_and_exports(
             swc_ecma_ast::Stmt::Expr(expr) => {
                 span = expr.span;
 
-                if !is_pure_expression(&expr.expr, unresolve_ctxt) {
+                if !pure_checker.is_pure(&expr.expr) {
                     is_self_executed = true;
                 }
                 analyze_and_insert_used_idents(expr, None);
@@ -442,19 +442,12 @@ pub fn analyze_imports_and_exports(
     }
 }
 
-fn is_pure_var_decl(var: &VarDecl, unresolved_ctxt: SyntaxContext) -> bool {
+fn is_pure_var_decl(var: &VarDecl, pure_checker: &PureChecker) -> bool {
     var.decls.iter().all(|decl| {
         if let Some(ref init) = decl.init {
-            is_pure_expression(init, unresolved_ctxt)
+            pure_checker.is_pure(init)
         } else {
             true
         }
     })
 }
-
-fn is_pure_expression(expr: &Expr, unresolved_ctxt: SyntaxContext) -> bool {
-    !expr.may_have_side_effects(&ExprCtx {
-        unresolved_ctxt,
-        is_unresolved_ref_safe: false,
-    })
-}
@@ -0,0 +1,276 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use swc_core::common::{Span, Spanned, SyntaxContext};
+use swc_core::ecma::ast::{
+    CallExpr, Callee, CondExpr, Expr, ExprOrSpread, Id, ImportSpecifier, Lit, MemberExpr,
+    MemberProp, Module, ModuleDecl, ModuleItem, NewExpr, ParenExpr, SeqExpr, TaggedTpl,
+};
+use swc_core::ecma::utils::{ExprCtx, ExprExt};
+
+use crate::ast::comments::Comments;
+
+/// Checks whether an expression can be removed if its value is unused, calls
+/// annotated with `/*#__PURE__*/` and calls of `optimization.pureFunctions`
+/// are treated as side effect free besides what swc can tell, so are the
+/// reads of the import namespaces, e.g. `ns.foo` of `import * as ns`.
+#[derive(Clone, Default)]
+pub struct PureChecker {
+    pub unresolved_ctxt: SyntaxContext,
+    comments: Arc<Comments>,
+    pure_functions: Arc<HashSet<String>>,
+    namespaces: Arc<HashSet<Id>>,
+}
+
+impl fmt::Debug for PureChecker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PureChecker")
+            .field("unresolved_ctxt", &self.unresolved_ctxt)
+            .field("pure_functions", &self.pure_functions)
+            .field("namespaces", &self.namespaces)
+            .finish()
+    }
+}
+
+impl PureChecker {
+    pub fn new(comments: Comments, pure_functions: &[String]) -> Self {
+        Self {
+            unresolved_ctxt: SyntaxContext::empty(),
+            comments: Arc::new(comments),
+            pure_functions: Arc::new(pure_functions.iter().cloned().collect()),
+            namespaces: Default::default(),
+        }
+    }
+
+    pub fn with_unresolved_ctxt(&self, unresolved_ctxt: SyntaxContext) -> Self {
+        Self {
+            unresolved_ctxt,
+            ..self.clone()
+        }
+    }
+
+    /// With the import namespaces of the module, whose reads are pure.
+    pub fn with_namespaces(&self, module: &Module) -> Self {
+        let namespaces = module
+            .body
+            .iter()
+            .filter_map(|item| match item {
+                ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => Some(&import.specifiers),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|specifier| match specifier {
+                ImportSpecifier::Namespace(namespace) => Some(namespace.local.to_id()),
+                _ => None,
+            })
+            .collect();
+        Self {
+            namespaces: Arc::new(namespaces),
+            ..self.clone()
+        }
+    }
+
+    pub fn is_pure(&self, expr: &Expr) -> bool {
+        match expr {
+            // e.g. (/*#__PURE__*/ foo())
+            Expr::Paren(ParenExpr { expr, .. }) => self.is_pure(expr),
+            // e.g. (/*#__PURE__*/ foo(), /*#__PURE__*/ bar())
+            Expr::Seq(SeqExpr { exprs, .. }) => exprs.iter().all(|expr| self.is_pure(expr)),
+            Expr::Cond(CondExpr {
+                test, cons, alt, ..
+            }) => self.is_pure(test) && self.is_pure(cons) && self.is_pure(alt),
+            Expr::Call(CallExpr {
+                span,
+                callee: Callee::Expr(callee),
+                args,
+                ..
+            }) if self.has_pure(*span)
+                || self.has_pure(callee.span())
+                || self.is_pure_function(callee) =>
+            {
+                self.are_pure_args(args)
+            }
+            Expr::New(NewExpr { span, args, .. }) if self.has_pure(*span) => {
+                args.as_ref().map_or(true, |args| self.are_pure_args(args))
+            }
+            // e.g. /*#__PURE__*/ styled.div`color: red;`
+            Expr::TaggedTpl(TaggedTpl { span, tpl, .. }) if self.has_pure(*span) => {
+                tpl.exprs.iter().all(|expr| self.is_pure(expr))
+            }
+            // e.g. /*#__PURE__*/ styled.div
+            Expr::Member(MemberExpr { span, .. }) if self.has_pure(*span) => true,
+            // e.g. ns.foo and ns["foo"], the exports of the namespace are plain
+            // getters of the bindings
+            Expr::Member(MemberExpr { obj, prop, .. }) if self.is_namespace_read(obj, prop) => true,
+            _ => !expr.may_have_side_effects(&ExprCtx {
+                unresolved_ctxt: self.unresolved_ctxt,
+                is_unresolved_ref_safe: false,
+            }),
+        }
+    }
+
+    fn are_pure_args(&self, args: &[ExprOrSpread]) -> bool {
+        // spread may call the iterator
+        args.iter()
+            .all(|arg| arg.spread.is_none() && self.is_pure(&arg.expr))
+    }
+
+    fn is_namespace_read(&self, obj: &Expr, prop: &MemberProp) -> bool {
+        let is_static_prop = match prop {
+            MemberProp::Ident(_) => true,
+            MemberProp::Computed(computed) => matches!(&*computed.expr, Expr::Lit(Lit::Str(_))),
+            MemberProp::PrivateName(_) => false,
+        };
+        is_static_prop
+            && matches!(obj, Expr::Ident(ident) if self.namespaces.contains(&ident.to_id()))
+    }
+
+    fn has_pure(&self, span: Span) -> bool {
+        self.comments.has_pure(span)
+    }
+
+    fn is_pure_function(&self, callee: &Expr) -> bool {
+        !self.pure_functions.is_empty()
+            && callee_name(callee).is_some_and(|name| self.pure_functions.contains(&name))
+    }
+}
+
+// e.g. console.log
+fn callee_name(callee: &Expr) -> Option<String> {
+    match callee {
+        Expr::Ident(ident) => Some(ident.sym.to_string()),
+        Expr::Member(MemberExpr {
+            obj,
+            prop: MemberProp::Ident(prop),
+            ..
+        }) => callee_name(obj).map(|obj| format!("{}.{}", obj, prop.sym)),
+        Expr::Paren(ParenExpr { expr, .. }) => callee_name(expr),
+        // e.g. (0, console.log)
+        Expr::Seq(SeqExpr { exprs, .. }) if exprs.len() == 2 && exprs[0].is_lit() => {
+            callee_name(&exprs[1])
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_core::common::GLOBALS;
+    use swc_core::ecma::ast::{Decl, ModuleItem, Stmt};
+
+    use super::*;
+    use crate::ast::tests::TestUtils;
+    use crate::utils::test_helper::setup_compiler;
+
+    fn is_pure(code: &str, pure_functions: &[&str]) -> bool {
+        let test_utils = TestUtils::gen_js_ast(code);
+        let ast = test_utils.ast.js();
+        let comments = test_utils
+            .context
+            .meta
+            .script
+            .origin_comments
+            .read()
+            .unwrap();
+        let pure_functions = pure_functions
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>();
+        let expr = ast
+            .ast
+            .body
+            .iter()
+            .find_map(|item| match item {
+                ModuleItem::Stmt(Stmt::Decl(Decl::Var(var))) => var.decls[0].init.as_ref(),
+                _ => None,
+            })
+            .expect("expect a var decl");
+        GLOBALS.set(&test_utils.context.meta.script.globals, || {
+            PureChecker::new(comments.clone(), &pure_functions)
+                .with_unresolved_ctxt(SyntaxContext::empty().apply_mark(ast.unresolved_mark))
+                .with_namespaces(&ast.ast)
+                .is_pure(expr)
+        })
+    }
+
+    #[test]
+    fn test_pure_annotation() {
+        assert!(!is_pure("const x = foo();", &[]));
+        assert!(is_pure("const x = /*#__PURE__*/ foo();", &[]));
+        assert!(is_pure("const x = /* @__PURE__ */ foo();", &[]));
+        assert!(is_pure("const x = /*#__PURE__*/ new Foo(1);", &[]));
+        assert!(is_pure(
+            "const x = /*#__PURE__*/ styled.div`color: red;`;",
+            &[]
+        ));
+        assert!(is_pure("const x = /*#__PURE__*/ styled.div;", &[]));
+        // args are still executed
+        assert!(!is_pure("const x = /*#__PURE__*/ foo(bar());", &[]));
+        assert!(is_pure("const x = /*#__PURE__*/ foo(() => bar());", &[]));
+    }
+
+    #[test]
+    fn test_pure_annotation_in_seq_and_paren() {
+        assert!(is_pure("const x = /*#__PURE__*/ (0, foo.bar)();", &[]));
+        assert!(is_pure("const x = (/*#__PURE__*/ foo());", &[]));
+        assert!(is_pure(
+            "const x = (/*#__PURE__*/ foo(), /*#__PURE__*/ bar());",
+            &[]
+        ));
+        assert!(!is_pure("const x = (/*#__PURE__*/ foo(), bar());", &[]));
+    }
+
+    #[test]
+    fn test_pure_functions() {
+        assert!(!is_pure("const x = invariant(1);", &[]));
+        assert!(is_pure("const x = invariant(1);", &["invariant"]));
+        assert!(is_pure("const x = console.log('a');", &["console.log"]));
+        assert!(is_pure(
+            "const x = (0, console.log)('a');",
+            &["console.log"]
+        ));
+        assert!(!is_pure("const x = console.info('a');", &["console.log"]));
+        assert!(!is_pure("const x = console.log(foo());", &["console.log"]));
+    }
+
+    #[test]
+    fn test_namespace_reads() {
+        assert!(is_pure(
+            "import * as ns from './a';\nconst x = ns.foo;",
+            &[]
+        ));
+        assert!(is_pure(
+            "import * as ns from './a';\nconst x = ns['foo'];",
+            &[]
+        ));
+        // the value of the export may have getters
+        assert!(!is_pure(
+            "import * as ns from './a';\nconst x = ns.foo.bar;",
+            &[]
+        ));
+        assert!(!is_pure(
+            "import * as ns from './a';\nconst x = ns[key];",
+            &[]
+        ));
+        assert!(!is_pure("import ns from './a';\nconst x = ns.foo;", &[]));
+        assert!(!is_pure("const x = ns.foo;", &[]));
+    }
+
+    #[test]
+    fn test_tree_shake_pure_annotations() {
+        let compiler = setup_compiler("test/build/pure-annotations", false);
+        compiler.compile().unwrap();
+        let index = compiler
+            .context
+            .output_fs
+            .read(&compiler.context.config.output.path.join("index.js"))
+            .unwrap();
+        let index = String::from_utf8(index).unwrap();
+        assert!(index.contains("used"));
+        assert!(index.contains("Kept"));
+        for removed in ["Title", "Box", "Seq", "Paren", "Both"] {
+            assert!(!index.contains(removed), "{} should be removed", removed);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde_json::Value;
+use swc_core::ecma::ast::{Expr, ModuleDecl, ModuleItem, Prop, PropName, PropOrSpread};
+use tracing::debug;
+
+use crate::compiler::{Compiler, Context};
+use crate::module::{Module, ModuleAst};
+use crate::plugin::Plugin;
+use crate::visitors::import_meta_env_replacer::IMPORT_META_ENV_PREFIX;
+
+const HEADER: &str = "// Generated by mako, do not edit.\n";
+
+const ASSET_EXTENSIONS: [&str; 20] = [
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "bmp", "ico", "woff", "woff2", "ttf", "otf",
+    "eot", "mp4", "webm", "ogg", "mp3", "wav", "flac", "aac",
+];
+
+const DATA_EXTENSIONS: [&str; 4] = ["yaml", "yml", "toml", "xml"];
+
+/// Write the declarations of `import.meta.env` and the asset imports with
+/// config.typeDeclaration, and of the class names of each css module with
+/// config.cssModules.declarations. The files are only written when their
+/// content changes, so the watch mode regenerates them when the keys change.
+pub struct TypeDeclarationPlugin {}
+
+impl Plugin for TypeDeclarationPlugin {
+    fn name(&self) -> &str {
+        "type_declaration"
+    }
+
+    fn after_build(&self, context: &Arc<Context>, _compiler: &Compiler) -> Result<()> {
+        write_declarations(context)
+    }
+
+    fn after_update(&self, compiler: &Compiler) -> Result<()> {
+        write_declarations(&compiler.context)
+    }
+}
+
+fn write_declarations(context: &Arc<Context>) -> Result<()> {
+    crate::mako_profile_function!();
+
+    if let Some(config) = &context.config.type_declaration {
+        write_if_changed(
+            &context.root.join(&config.file_name),
+            &env_declaration(context),
+        )?;
+    }
+    if context.config.css_modules.declarations {
+        let module_graph = context.module_graph.read().unwrap();
+        for (path, class_names) in module_graph.modules().into_iter().filter_map(css_module) {
+            let mut path = path.into_os_string();
+            path.push(".d.ts");
+            write_if_changed(Path::new(&path), &css_module_declaration(&class_names))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_if_changed(path: &Path, content: &str) -> Result<()> {
+    if fs::read_to_string(path).is_ok_and(|existing| existing == content) {
+        return Ok(());
+    }
+    debug!("write type declaration {:?}", path);
+    fs::write(path, content)?;
+    Ok(())
+}
+
+fn env_declaration(context: &Arc<Context>) -> String {
+    let config = &context.config;
+    let mut env = config
+        .define
+        .iter()
+        .filter_map(|(key, value)| {
+            let key = key.strip_prefix(IMPORT_META_ENV_PREFIX)?;
+            (key != "MODE").then(|| (key, define_type(value)))
+        })
+        .collect::<Vec<_>>();
+    env.sort();
+
+    let mut content = HEADER.to_string();
+    content.push_str("\ninterface ImportMetaEnv {\n  readonly MODE: string;\n");
+    for (key, ty) in env {
+        content.push_str(&format!("  readonly {}: {};\n", quote_key(key), ty));
+    }
+    content.push_str("}\n\ninterface ImportMeta {\n  readonly env: ImportMetaEnv;\n}\n");
+
+    for ext in ASSET_EXTENSIONS {
+        content.push_str(&format!(
+            "\ndeclare module '*.{}' {{\n  const src: string;\n  export default src;\n}}\n",
+            ext
+        ));
+    }
+    content.push_str(
+        r#"
+declare module '*.svg' {
+  import type { FunctionComponent, SVGProps } from 'react';
+  export const ReactComponent: FunctionComponent<SVGProps<SVGSVGElement> & { title?: string }>;
+  const src: string;
+  export default src;
+}
+
+declare module '*?raw' {
+  const content: string;
+  export default content;
+}
+"#,
+    );
+    for ext in DATA_EXTENSIONS {
+        content.push_str(&format!(
+            "\ndeclare module '*.{}' {{\n  const data: any;\n  export default data;\n}}\n",
+            ext
+        ));
+    }
+    if config.mdx {
+        content.push_str(
+            r#"
+declare module '*.mdx' {
+  import type { ComponentType } from 'react';
+  const Component: ComponentType<Record<string, unknown>>;
+  export default Component;
+}
+"#,
+        );
+    }
+    let mut css_modules = vec![
+        "*.module.css",
+        "*.module.less",
+        "*.css?modules",
+        "*.less?modules",
+    ];
+    if config.auto_css_modules {
+        css_modules.extend(["*.css", "*.less"]);
+    }
+    for pattern in css_modules {
+        content.push_str(&format!(
+            "\ndeclare module '{}' {{\n  const classes: {{ readonly [key: string]: string }};\n  export default classes;\n}}\n",
+            pattern
+        ));
+    }
+    content
+}
+
+// the type of a define value, which is an expression when it's a string
+fn define_type(value: &Value) -> &'static str {
+    let value = match value {
+        Value::String(expr) => match serde_json::from_str::<Value>(expr) {
+            Ok(value) => value,
+            Err(_) => return "any",
+        },
+        value => value.clone(),
+    };
+    match value {
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Bool(_) => "boolean",
+        _ => "any",
+    }
+}
+
+// the css file and the class names of an ?asmodule module of the project
+fn css_module(module: &Module) -> Option<(PathBuf, Vec<String>)> {
+    let info = module.info.as_ref()?;
+    if !info.file.has_param("asmodule") || info.file.is_under_node_modules {
+        return None;
+    }
+    let ModuleAst::Script(ast) = &info.ast else {
+        return None;
+    };
+    let class_names = ast.ast.body.iter().find_map(|item| match item {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(export)) => match &*export.expr {
+            Expr::Object(object) => Some(
+                object
+                    .props
+                    .iter()
+                    .filter_map(|prop| match prop {
+                        PropOrSpread::Prop(box Prop::KeyValue(kv)) => match &kv.key {
+                            PropName::Str(str) => Some(str.value.to_string()),
+                            PropName::Ident(ident) => Some(ident.sym.to_string()),
+                            _ => None,
+                        },
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        },
+        _ => None,
+    })?;
+    Some((info.file.pathname.clone(), class_names))
+}
+
+fn css_module_declaration(class_names: &[String]) -> String {
+    let mut class_names = class_names.to_vec();
+    class_names.sort();
+    let mut content = HEADER.to_string();
+    content.push_str("declare const classes: {\n");
+    for class_name in class_names {
+        content.push_str(&format!("  readonly {}: string;\n", quote_key(&class_name)));
+    }
+    content.push_str("};\nexport default classes;\n");
+    content
+}
+
+fn quote_key(key: &str) -> String {
+    let is_ident = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_ident {
+        key.to_string()
+    } else {
+        serde_json::to_string(key).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+
+    use super::define_type;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_define_type() {
+        assert_eq!(define_type(&json!("\"/api\"")), "string");
+        assert_eq!(define_type(&json!("1")), "number");
+        assert_eq!(define_type(&json!(true)), "boolean");
+        assert_eq!(define_type(&json!("window.API")), "any");
+    }
+
+    #[test]
+    fn test_type_declaration() {
+        let compiler = setup_compiler("test/build/type-declaration", false);
+        let root = compiler.context.root.clone();
+        let _ = fs::remove_file(root.join("mako-env.d.ts"));
+        let _ = fs::remove_file(root.join("index.module.css.d.ts"));
+        compiler.compile().unwrap();
+
+        let env = fs::read_to_string(root.join("mako-env.d.ts")).unwrap();
+        assert!(env.contains("  readonly MODE: string;\n  readonly API_URL: string;\n  readonly DEBUG: boolean;\n"), "{}", env);
+        assert!(!env.contains("NODE_ENV"), "{}", env);
+        assert!(env.contains("declare module '*.png'"), "{}", env);
+        assert!(env.contains("export const ReactComponent"), "{}", env);
+        assert!(env.contains("declare module '*.module.css'"), "{}", env);
+
+        let css = fs::read_to_string(root.join("index.module.css.d.ts")).unwrap();
+        assert_eq!(
+            css,
+            "// Generated by mako, do not edit.\ndeclare const classes: {\n  readonly \"app-title\": string;\n  readonly container: string;\n};\nexport default classes;\n"
+        );
+
+        let js = compiler
+            .context
+            .output_fs
+            .read(&root.join("dist/index.js"))
+            .unwrap();
+        assert!(String::from_utf8(js)
+            .unwrap()
+            .contains("\"https://example.com\""));
+    }
+}
@@ -7,6 +7,7 @@ use anyhow;
 use wasmparser::{Import, Parser, Payload};
 
 use crate::ast::file::{Content, JsContent};
+use crate::build::load::Load;
 use crate::compiler::Context;
 use crate::plugin::{Plugin, PluginLoadParam};
 
@@ -38,24 +39,23 @@ impl Plugin for WasmRuntimePlugin {
     fn load(
         &self,
         param: &PluginLoadParam,
-        _context: &Arc<Context>,
+        context: &Arc<Context>,
     ) -> anyhow::Result<Option<Content>> {
         let file = param.file;
 
         if WASM_EXTENSIONS.contains(&file.extname.as_str()) {
-            let final_file_name = format!(
-                "{}.{}.{}",
-                file.get_file_stem(),
-                file.get_content_hash()?,
-                file.extname
-            );
-            _context.emit_assets(
+            let (final_file_name, hashed) = Load::asset_file_name(file, context)?;
+            let final_file_name = context.emit_assets(
                 file.pathname.to_string_lossy().to_string(),
-                final_file_name.clone(),
-            );
-
-            let mut buffer = Vec::new();
-            File::open(&file.path)?.read_to_end(&mut buffer)?;
+                final_file_name,
+                hashed,
+            )?;
+
+            let buffer = context.file_handles.run(|| {
+                let mut buffer = Vec::new();
+                File::open(&file.path)?.read_to_end(&mut buffer)?;
+                Ok(buffer)
+            })?;
             // Parse wasm file to get imports
             let mut wasm_import_object_map: HashMap<&str, Vec<String>> = HashMap::new();
             Parser::new(0).parse_all(&buffer).for_each(|payload| {
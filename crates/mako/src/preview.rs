@@ -0,0 +1,621 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use hyper::header::{
+    ACCEPT, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, LOCATION, VARY,
+};
+use hyper::server::conn::Http;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use percent_encoding::percent_decode_str;
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::crypto::ring::default_provider;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+use tracing::debug;
+
+use crate::config::{static_public_path, Config};
+use crate::dev::DevServer;
+use crate::utils::tokio_runtime;
+
+/// `mako preview`, serves the output of a build from disk like a static host,
+/// i.e. with the precompressed siblings and the fallback to `index.html`, and
+/// without the rebuilds and the hmr of the dev server.
+pub struct PreviewServer {
+    output_path: PathBuf,
+    // the path part of publicPath, e.g. `/app/`
+    base: String,
+    port: u16,
+    https: bool,
+    // entry name -> file of the entry, relative to the output path
+    entries: BTreeMap<String, String>,
+}
+
+// the encodings of the precompressed siblings, in the order of preference
+const ENCODINGS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+#[derive(Debug, PartialEq)]
+enum Resolved {
+    File {
+        path: PathBuf,
+        // the precompressed sibling served for it, e.g. `gzip` for `.gz`
+        encoding: Option<&'static str>,
+    },
+    Redirect(String),
+    NotFound,
+}
+
+impl PreviewServer {
+    pub fn new(config: &Config, port: u16, https: bool) -> Result<Self> {
+        let output_path = config.output.path.clone();
+        if !output_path.is_dir() {
+            return Err(anyhow!(
+                "Output directory {} not found, build it before the preview",
+                output_path.display()
+            ));
+        }
+        Ok(Self {
+            entries: entry_files(config),
+            base: base_path(&config.public_path),
+            output_path,
+            port,
+            https,
+        })
+    }
+
+    pub async fn serve(&self) -> Result<()> {
+        let port = DevServer::find_available_port("127.0.0.1".to_string(), self.port);
+        let addr: SocketAddr = ([127, 0, 0, 1], port).into();
+        let origin = format!(
+            "{}://localhost:{}",
+            if self.https { "https" } else { "http" },
+            port
+        );
+        println!();
+        if self.port != port {
+            println!(
+                "{}",
+                format!("Port {} is in use, using {} instead.", self.port, port).yellow()
+            );
+        }
+        println!("Local:   {}", format!("{}{}", origin, self.base).cyan());
+        for (name, file) in &self.entries {
+            println!(
+                "Entry:   {} {}",
+                name,
+                format!("{}{}{}", origin, self.base, file).cyan()
+            );
+        }
+        println!();
+
+        let output_path = Arc::new(self.output_path.clone());
+        let base = Arc::new(self.base.clone());
+        if !self.https {
+            let make_svc = make_service_fn(move |_conn| {
+                let output_path = output_path.clone();
+                let base = base.clone();
+                async move {
+                    Ok::<_, hyper::Error>(service_fn(move |req| {
+                        let response = handle_request(&req, &output_path, &base);
+                        async move { Ok::<_, hyper::Error>(response) }
+                    }))
+                }
+            });
+            Server::bind(&addr).serve(make_svc).await?;
+            return Ok(());
+        }
+
+        let acceptor = TlsAcceptor::from(Arc::new(self_signed_tls_config()?));
+        let listener = TcpListener::bind(addr).await?;
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let acceptor = acceptor.clone();
+            let output_path = output_path.clone();
+            let base = base.clone();
+            tokio_runtime::spawn(async move {
+                let stream = match acceptor.accept(stream).await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        // e.g. the browser rejects the certificate before it's trusted
+                        debug!("tls handshake failed: {}", e);
+                        return;
+                    }
+                };
+                let service = service_fn(move |req| {
+                    let response = handle_request(&req, &output_path, &base);
+                    async move { Ok::<_, hyper::Error>(response) }
+                });
+                if let Err(e) = Http::new().serve_connection(stream, service).await {
+                    debug!("connection error: {}", e);
+                }
+            });
+        }
+    }
+}
+
+fn handle_request(req: &Request<Body>, output_path: &Path, base: &str) -> Response<Body> {
+    debug!("> {} {}", req.method(), req.uri().path());
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .body(Body::empty())
+            .unwrap();
+    }
+    let header = |name| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+    let resolved = resolve_request(
+        output_path,
+        base,
+        req.uri().path(),
+        header(ACCEPT_ENCODING),
+        header(ACCEPT).contains("text/html"),
+    );
+    match resolved {
+        Resolved::File { path, encoding } => {
+            let served = match encoding {
+                Some(encoding) => precompressed_path(&path, encoding),
+                None => path.clone(),
+            };
+            let Ok(content) = std::fs::read(&served) else {
+                return not_found();
+            };
+            let mut response = Response::builder()
+                .status(StatusCode::OK)
+                .header(CONTENT_TYPE, content_type(&path))
+                .header(CONTENT_LENGTH, content.len());
+            if let Some(encoding) = encoding {
+                response = response.header(CONTENT_ENCODING, encoding);
+            }
+            if has_precompressed(&path) {
+                response = response.header(VARY, "Accept-Encoding");
+            }
+            let body = if req.method() == Method::HEAD {
+                Body::empty()
+            } else {
+                Body::from(content)
+            };
+            response.body(body).unwrap()
+        }
+        Resolved::Redirect(location) => Response::builder()
+            .status(StatusCode::MOVED_PERMANENTLY)
+            .header(LOCATION, location)
+            .body(Body::empty())
+            .unwrap(),
+        Resolved::NotFound => not_found(),
+    }
+}
+
+fn not_found() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(Body::from("Not Found"))
+        .unwrap()
+}
+
+// the file of the request path, the requests for the pages, i.e. accepting
+// html, which don't match a file fall back to the `index.html` of the output
+fn resolve_request(
+    output_path: &Path,
+    base: &str,
+    path: &str,
+    accept_encoding: &str,
+    accept_html: bool,
+) -> Resolved {
+    if base != "/" && path == base.trim_end_matches('/') {
+        return Resolved::Redirect(base.to_string());
+    }
+    let Some(relative) = path.strip_prefix(base) else {
+        return Resolved::NotFound;
+    };
+    let relative = percent_decode_str(relative).decode_utf8_lossy();
+    let relative = Path::new(relative.as_ref());
+    // no escaping from the output path
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Resolved::NotFound;
+    }
+    let mut file = output_path.join(relative);
+    if file.is_dir() {
+        file = file.join("index.html");
+    }
+    if !file.is_file() {
+        let index = output_path.join("index.html");
+        if !accept_html || !index.is_file() {
+            return Resolved::NotFound;
+        }
+        file = index;
+    }
+    let encoding = negotiate_encoding(&file, accept_encoding);
+    Resolved::File {
+        path: file,
+        encoding,
+    }
+}
+
+// the most preferred encoding which is accepted and has a precompressed sibling
+fn negotiate_encoding(file: &Path, accept_encoding: &str) -> Option<&'static str> {
+    // encoding -> q, `*` for the others
+    let accepted = accept_encoding
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let encoding = parts.next().filter(|encoding| !encoding.is_empty())?;
+            let q = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((encoding.to_lowercase(), q))
+        })
+        .collect::<BTreeMap<_, _>>();
+    ENCODINGS
+        .iter()
+        .find(|(encoding, _)| {
+            let q = accepted
+                .get(*encoding)
+                .or_else(|| accepted.get("*"))
+                .copied()
+                .unwrap_or(0.0);
+            q > 0.0 && precompressed_path(file, encoding).is_file()
+        })
+        .map(|(encoding, _)| *encoding)
+}
+
+fn precompressed_path(file: &Path, encoding: &str) -> PathBuf {
+    let (_, ext) = ENCODINGS
+        .iter()
+        .find(|(name, _)| *name == encoding)
+        .unwrap();
+    let mut path = file.as_os_str().to_owned();
+    path.push(".");
+    path.push(ext);
+    PathBuf::from(path)
+}
+
+fn has_precompressed(file: &Path) -> bool {
+    ENCODINGS
+        .iter()
+        .any(|(encoding, _)| precompressed_path(file, encoding).is_file())
+}
+
+fn content_type(file: &Path) -> String {
+    let mime = mime_guess::from_path(file).first_or_octet_stream();
+    let essence = mime.essence_str();
+    if mime.type_() == mime_guess::mime::TEXT || essence == "application/json" {
+        format!("{}; charset=utf-8", essence)
+    } else {
+        essence.to_string()
+    }
+}
+
+// `/app/` of `/app` and `https://cdn.com/app/`, `/` of the relative ones
+fn base_path(public_path: &str) -> String {
+    let public_path = static_public_path(public_path);
+    let path = match public_path.find("://") {
+        Some(i) => public_path[i + 3..]
+            .find('/')
+            .map_or("/", |j| &public_path[i + 3 + j..]),
+        None if public_path.starts_with('/') => public_path,
+        None => "/",
+    };
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", path)
+    }
+}
+
+// the page of each entry, i.e. its html, or the entry chunk if there's no
+// html, by the manifest or the build manifest of the build
+fn entry_files(config: &Config) -> BTreeMap<String, String> {
+    let output_path = &config.output.path;
+    let read_json = |file_name: &str| -> Option<serde_json::Value> {
+        let content = std::fs::read_to_string(output_path.join(file_name)).ok()?;
+        serde_json::from_str(&content).ok()
+    };
+    // entry name -> files
+    let mut entrypoints: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    if let Some(manifest) = config
+        .manifest
+        .as_ref()
+        .and_then(|manifest| read_json(&manifest.file_name))
+        && let Some(manifest_entrypoints) = manifest["entrypoints"].as_object()
+    {
+        for (name, files) in manifest_entrypoints {
+            let files = files.as_array().into_iter().flatten();
+            entrypoints.insert(
+                name.clone(),
+                files
+                    .filter_map(|file| file.as_str().map(String::from))
+                    .collect(),
+            );
+        }
+    } else if let Some(build_manifest) = config
+        .build_manifest
+        .as_ref()
+        .and_then(|build_manifest| read_json(&build_manifest.file_name))
+        && let Some(assets) = build_manifest["assets"].as_object()
+    {
+        for (file, asset) in assets {
+            for name in asset["entrypoints"].as_array().into_iter().flatten() {
+                if let Some(name) = name.as_str() {
+                    entrypoints
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(file.clone());
+                }
+            }
+        }
+    } else {
+        for name in config.entry.keys() {
+            entrypoints.insert(name.clone(), vec![]);
+        }
+    }
+
+    // the files in the manifest may be prefixed with the publicPath
+    let public_path = static_public_path(&config.public_path);
+    let base = base_path(&config.public_path);
+    entrypoints
+        .into_iter()
+        .filter_map(|(name, files)| {
+            let files = files
+                .iter()
+                .map(|file| {
+                    file.strip_prefix(public_path)
+                        .or_else(|| file.strip_prefix(&base))
+                        .unwrap_or(file)
+                        .to_string()
+                })
+                .collect::<Vec<_>>();
+            let html = format!("{}.html", name);
+            let file = if output_path.join(&html).is_file() {
+                Some(html)
+            } else {
+                files
+                    .iter()
+                    .find(|file| file.ends_with(".html"))
+                    .or_else(|| files.iter().rev().find(|file| file.ends_with(".js")))
+                    .cloned()
+            };
+            file.map(|file| (name, file))
+        })
+        .collect()
+}
+
+// a certificate of localhost generated for each run, for testing the features
+// only available in the secure contexts, e.g. the service workers, after it's
+// trusted in the browser
+fn self_signed_tls_config() -> Result<ServerConfig> {
+    let certified =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string(), "127.0.0.1".to_string()])?;
+    let cert = CertificateDer::from(certified.cert.der().to_vec());
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+    let mut config = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(vec![cert], key)?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn setup_output(name: &str) -> TempDir {
+        let output = TempDir::new(name);
+        fs::create_dir_all(output.join("docs")).unwrap();
+        for (file, content) in [
+            ("index.html", "<div id=\"root\"></div>"),
+            ("docs/index.html", "docs"),
+            ("index.js", "console.log(1);"),
+            ("index.js.gz", "gzip"),
+            ("index.js.br", "brotli"),
+            ("index.css", "body {}"),
+            ("index.css.gz", "gzip"),
+            ("logo.png", "png"),
+            ("my logo.svg", "<svg></svg>"),
+        ] {
+            fs::write(output.join(file), content).unwrap();
+        }
+        output
+    }
+
+    fn file(output: &Path, name: &str, encoding: Option<&'static str>) -> Resolved {
+        Resolved::File {
+            path: output.join(name),
+            encoding,
+        }
+    }
+
+    #[test]
+    fn test_base_path() {
+        assert_eq!(base_path("/"), "/");
+        assert_eq!(base_path("/app"), "/app/");
+        assert_eq!(base_path("/app/"), "/app/");
+        assert_eq!(base_path("https://cdn.com/app/"), "/app/");
+        assert_eq!(base_path("https://cdn.com"), "/");
+        assert_eq!(base_path("./"), "/");
+        assert_eq!(base_path("auto"), "/");
+    }
+
+    #[test]
+    fn test_resolve_request_with_base() {
+        let output = setup_output("preview-base");
+        let resolve = |path: &str, accept_html: bool| {
+            resolve_request(&output, "/app/", path, "", accept_html)
+        };
+        assert_eq!(
+            resolve("/app/index.js", false),
+            file(&output, "index.js", None)
+        );
+        assert_eq!(resolve("/app/", true), file(&output, "index.html", None));
+        assert_eq!(
+            resolve("/app", true),
+            Resolved::Redirect("/app/".to_string())
+        );
+        assert_eq!(
+            resolve("/app/docs", true),
+            file(&output, "docs/index.html", None)
+        );
+        assert_eq!(
+            resolve("/app/my%20logo.svg", false),
+            file(&output, "my logo.svg", None)
+        );
+        // out of the base
+        assert_eq!(resolve("/index.js", false), Resolved::NotFound);
+        assert_eq!(resolve("/other/", true), Resolved::NotFound);
+        // out of the output path
+        assert_eq!(resolve("/app/../index.js", false), Resolved::NotFound);
+        assert_eq!(resolve("/app/%2e%2e/index.js", false), Resolved::NotFound);
+        // the pages fall back to the index.html, the others don't
+        assert_eq!(
+            resolve("/app/about/team", true),
+            file(&output, "index.html", None)
+        );
+        assert_eq!(resolve("/app/missing.js", false), Resolved::NotFound);
+
+        let resolve = |path: &str| resolve_request(&output, "/", path, "", false);
+        assert_eq!(resolve("/index.js"), file(&output, "index.js", None));
+        assert_eq!(resolve("/app/index.js"), Resolved::NotFound);
+    }
+
+    #[test]
+    fn test_negotiate_encoding() {
+        let output = setup_output("preview-encoding");
+        let negotiate = |name: &str, accept_encoding: &str| {
+            negotiate_encoding(&output.join(name), accept_encoding)
+        };
+        assert_eq!(negotiate("index.js", "gzip, deflate, br"), Some("br"));
+        assert_eq!(negotiate("index.js", "gzip"), Some("gzip"));
+        assert_eq!(negotiate("index.js", "br;q=0, gzip;q=0.8"), Some("gzip"));
+        assert_eq!(negotiate("index.js", "*"), Some("br"));
+        assert_eq!(negotiate("index.js", "br;q=0, *"), Some("gzip"));
+        assert_eq!(negotiate("index.js", "identity"), None);
+        assert_eq!(negotiate("index.js", ""), None);
+        // only the existing siblings
+        assert_eq!(negotiate("index.css", "br, gzip"), Some("gzip"));
+        assert_eq!(negotiate("logo.png", "br, gzip"), None);
+    }
+
+    #[test]
+    fn test_handle_request() {
+        let output = setup_output("preview-request");
+        let request = |method: Method, path: &str, headers: &[(&str, &str)]| {
+            let mut req = Request::builder().method(method).uri(path);
+            for (name, value) in headers {
+                req = req.header(*name, *value);
+            }
+            handle_request(&req.body(Body::empty()).unwrap(), &output, "/app/")
+        };
+        let body = |res: Response<Body>| {
+            let bytes = tokio_runtime::block_on(hyper::body::to_bytes(res.into_body())).unwrap();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+
+        let res = request(
+            Method::GET,
+            "/app/index.js",
+            &[("accept-encoding", "gzip, br")],
+        );
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_ENCODING], "br");
+        assert_eq!(
+            res.headers()[CONTENT_TYPE],
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(res.headers()[VARY], "Accept-Encoding");
+        assert_eq!(body(res), "brotli");
+
+        let res = request(Method::GET, "/app/index.js", &[]);
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(res.headers()[VARY], "Accept-Encoding");
+        assert_eq!(body(res), "console.log(1);");
+
+        let res = request(Method::GET, "/app/logo.png", &[("accept-encoding", "gzip")]);
+        assert_eq!(res.headers()[CONTENT_TYPE], "image/png");
+        assert!(res.headers().get(VARY).is_none());
+
+        let res = request(Method::GET, "/app/about", &[("accept", "text/html")]);
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/html; charset=utf-8");
+        assert_eq!(body(res), "<div id=\"root\"></div>");
+
+        let res = request(
+            Method::HEAD,
+            "/app/index.css",
+            &[("accept-encoding", "gzip")],
+        );
+        assert_eq!(res.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(res.headers()[CONTENT_LENGTH], "4");
+        assert_eq!(body(res), "");
+
+        let res = request(Method::GET, "/app", &[]);
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(res.headers()[LOCATION], "/app/");
+
+        assert_eq!(
+            request(Method::GET, "/app/missing.js", &[]).status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            request(Method::POST, "/app/index.js", &[]).status(),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+    }
+
+    #[test]
+    fn test_entry_files() {
+        let root = TempDir::new("preview-entries");
+        fs::create_dir_all(root.join("dist")).unwrap();
+        fs::write(root.join("index.js"), "").unwrap();
+        fs::write(root.join("admin.js"), "").unwrap();
+        fs::write(
+            root.join("mako.config.json"),
+            r#"{
+                "entry": { "index": "./index.js", "admin": "./admin.js" },
+                "publicPath": "/app/",
+                "manifest": { "publicPath": true }
+            }"#,
+        )
+        .unwrap();
+        fs::write(root.join("dist/index.html"), "").unwrap();
+        fs::write(
+            root.join("dist/asset-manifest.json"),
+            r#"{
+                "entrypoints": {
+                    "index": ["/app/index.css", "/app/index.1a2b3c4d.js"],
+                    "admin": ["/app/vendors.5e6f7a8b.js", "/app/admin.9c0d1e2f.js"]
+                }
+            }"#,
+        )
+        .unwrap();
+        let config = Config::new(&root, None, None).unwrap();
+        assert_eq!(
+            entry_files(&config),
+            BTreeMap::from([
+                ("admin".to_string(), "admin.9c0d1e2f.js".to_string()),
+                ("index".to_string(), "index.html".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_self_signed_tls_config() {
+        let config = self_signed_tls_config().unwrap();
+        assert_eq!(config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+    }
+}
@@ -1,30 +1,37 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::vec;
 
 use anyhow::{anyhow, Result};
 use convert_case::{Case, Casing};
-use oxc_resolver::{Alias, AliasValue, ResolveError as OxcResolveError, ResolveOptions, Resolver};
+use oxc_resolver::{
+    Alias, AliasValue, ResolveContext, ResolveError as OxcResolveError, ResolveOptions, Resolver,
+    TsconfigOptions, TsconfigReferences,
+};
+use path_clean::PathClean;
 use regex::Captures;
 use thiserror::Error;
 use tracing::debug;
 
+mod cache;
 mod resolution;
 mod resource;
+pub use cache::ResolveCache;
 pub use resolution::Resolution;
 pub use resource::{ExternalResource, ResolvedResource, ResolverResource};
 
-use crate::ast::file::parse_path;
 use crate::compiler::Context;
 use crate::config::{
     Config, ExternalAdvancedSubpathConverter, ExternalAdvancedSubpathTarget, ExternalConfig,
-    Platform,
+    NativeAddonsConfig, Platform,
 };
+use crate::features::node::Node;
 use crate::features::rsc::Rsc;
 use crate::module::{Dependency, ResolveType};
 use crate::plugin::PluginResolveIdParams;
 use crate::utils::create_cached_regex;
+use crate::utils::file_request::FileRequest;
 
 #[derive(Debug, Error)]
 #[error("Resolve {path:?} failed from {from:?}")]
@@ -33,6 +40,15 @@ struct ResolveError {
     from: String,
 }
 
+#[derive(Debug, Error)]
+#[error("Resolve {path:?} failed from {from:?}, it matches none of the \"imports\" of {package_json:?}: {patterns}")]
+struct PackageImportsError {
+    path: String,
+    from: String,
+    package_json: String,
+    patterns: String,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum ResolverType {
     Cjs,
@@ -69,10 +85,117 @@ pub fn resolve(
         return Ok(ResolverResource::Virtual(PathBuf::from(&dep.source)));
     }
 
-    let has_context_query = parse_path(&dep.source)?
-        .2
+    // the dependencies of the rebuilt modules are mostly resolved before
+    let cache_key = context.args.watch.then(|| ResolveCache::key(path, dep));
+    if let Some(resource) = cache_key
+        .as_ref()
+        .and_then(|key| context.resolve_cache.get(key))
+    {
+        return Ok(resource);
+    }
+
+    let resolver = get_dep_resolver(dep, resolvers)?;
+    let source = dep.resolve_as.as_ref().unwrap_or(&dep.source);
+
+    let externals = &context.config.externals;
+    let mut resource = match get_external_target(externals, source)
+        .is_none()
+        .then(|| resolve_in_roots(path, source, resolver, &context.config))
+        .flatten()
+    {
+        Some(resource) => resource,
+        None => do_resolve(path, source, resolver, Some(externals))?,
+    };
+    let conditions = &context.config.resolve.condition_names;
+    if !conditions.is_empty()
+        && let ResolverResource::Resolved(ResolvedResource(resolution)) = &mut resource
+    {
+        resolution.condition = get_selected_condition(source, resolution, conditions);
+    }
+    // the packages with the native addons are required at runtime
+    if context.config.platform == Platform::Node
+        && context.config.native_addons == NativeAddonsConfig::External
+        && is_bare_specifier(source)
+        && let ResolverResource::Resolved(ResolvedResource(resolution)) = &resource
+        && resolution
+            .package_json()
+            .is_some_and(|package_json| Node::is_native_addon_package(&package_json.path))
+    {
+        resource = ResolverResource::External(ExternalResource {
+            source: source.to_string(),
+            external: format!("require({:?})", source),
+            script: None,
+        });
+    }
+    if let Some(key) = cache_key {
+        context.resolve_cache.insert(key, resource.clone());
+    }
+    Ok(resource)
+}
+
+// the condition which selected the resolved file, either by the suffix of the
+// file, e.g. `./Button` to `Button.web.tsx`, or by the "exports" of its package
+fn get_selected_condition(
+    source: &str,
+    resolution: &Resolution,
+    conditions: &[String],
+) -> Option<String> {
+    let stem = resolution.path.file_stem()?.to_string_lossy().to_string();
+    let request = source.rsplit('/').next().unwrap_or(source);
+    if let Some(condition) = conditions.iter().find(|condition| {
+        let suffix = format!(".{}", condition);
+        stem.ends_with(&suffix) && !request.contains(&suffix)
+    }) {
+        return Some(condition.clone());
+    }
+
+    let package_json = resolution.package_json()?;
+    let exports = package_json.raw_json().get("exports")?;
+    let relative = resolution
+        .path
+        .strip_prefix(package_json.directory())
+        .ok()?
+        .to_string_lossy()
+        .replace('\\', "/");
+    conditions
         .iter()
-        .any(|(k, _)| *k == "context");
+        .find(|condition| {
+            let mut targets = vec![];
+            collect_condition_targets(exports, condition, false, &mut targets);
+            targets.iter().any(|target| {
+                let target = target.trim_start_matches("./");
+                match target.split_once('*') {
+                    Some((prefix, suffix)) => {
+                        relative.starts_with(prefix) && relative.ends_with(suffix)
+                    }
+                    None => target == relative,
+                }
+            })
+        })
+        .cloned()
+}
+
+// the targets of "exports" under the condition, including the nested ones
+fn collect_condition_targets<'a>(
+    value: &'a serde_json::Value,
+    condition: &str,
+    matched: bool,
+    targets: &mut Vec<&'a str>,
+) {
+    match value {
+        serde_json::Value::String(target) if matched => targets.push(target),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_condition_targets(value, condition, matched, targets)),
+        serde_json::Value::Object(map) => map.iter().for_each(|(key, value)| {
+            collect_condition_targets(value, condition, matched || key == condition, targets)
+        }),
+        _ => {}
+    }
+}
+
+fn get_dep_resolver<'a>(dep: &Dependency, resolvers: &'a Resolvers) -> Result<&'a Resolver> {
+    let has_context_query = FileRequest::parse(&dep.source)?.has_param("context");
     let resolver = if has_context_query {
         resolvers.get(&ResolverType::Ctxt)
     } else if dep.resolve_type == ResolveType::Require {
@@ -83,10 +206,176 @@ pub fn resolve(
         resolvers.get(&ResolverType::Esm)
     }
     .unwrap();
+    Ok(resolver)
+}
+
+/// The lockfiles of the package managers, which are changed when the packages
+/// under node_modules are installed, updated or removed.
+pub(crate) const LOCK_FILES: [&str; 4] = [
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+];
+
+pub(crate) fn is_lock_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| LOCK_FILES.contains(&name))
+}
 
+/// Whether the source is resolved from node_modules or by the aliases, e.g.
+/// `react` or `@scope/pkg/x`, instead of a relative or absolute path.
+pub(crate) fn is_bare_specifier(source: &str) -> bool {
+    !source.starts_with('.') && !Path::new(source).is_absolute()
+}
+
+/// Files and directories probed when resolving `dep` from `path`, creating or
+/// changing any of them may change the resolution result, e.g. `./foo.tsx`,
+/// `./foo/index.ts` or `node_modules/foo/package.json`, and the ones in each
+/// directory of `resolve.roots` for the requests under them.
+///
+/// They are the negative dependencies of a failed resolution in watch mode.
+pub fn get_probed_paths(
+    path: &str,
+    dep: &Dependency,
+    resolvers: &Resolvers,
+    config: &Config,
+) -> HashSet<PathBuf> {
+    let resolver = match get_dep_resolver(dep, resolvers) {
+        Ok(resolver) => resolver,
+        Err(_) => return HashSet::new(),
+    };
+    let source = dep.resolve_as.as_ref().unwrap_or(&dep.source);
+    let parent = Path::new(path).parent().unwrap();
+    let mut paths = probe(resolver, parent, source);
+    if let Some((request, _)) = get_root_request(path, source, config) {
+        for root in &config.resolve.roots {
+            paths.extend(probe(
+                resolver,
+                parent,
+                &root.join(&request).to_string_lossy(),
+            ));
+        }
+    }
+    paths
+}
+
+/// Files and directories probed in the directories of `resolve.roots` before
+/// the one `resolution` is resolved in, creating any of them overrides the
+/// resolved file, e.g. `brands/acme/Button.tsx` of `./Button` resolved to
+/// `src/Button.tsx`.
+///
+/// They are the negative dependencies of a resolution in watch mode.
+pub fn get_shadowed_paths(
+    path: &str,
+    dep: &Dependency,
+    resolution: &Resolution,
+    resolvers: &Resolvers,
+    config: &Config,
+) -> HashSet<PathBuf> {
+    let Some(resolved_root) = &resolution.root else {
+        return HashSet::new();
+    };
     let source = dep.resolve_as.as_ref().unwrap_or(&dep.source);
+    let (Ok(resolver), Some((request, _))) = (
+        get_dep_resolver(dep, resolvers),
+        get_root_request(path, source, config),
+    ) else {
+        return HashSet::new();
+    };
+    let parent = Path::new(path).parent().unwrap();
+    config
+        .resolve
+        .roots
+        .iter()
+        .take_while(|root| *root != resolved_root)
+        .flat_map(|root| probe(resolver, parent, &root.join(&request).to_string_lossy()))
+        .collect()
+}
+
+fn probe(resolver: &Resolver, parent: &Path, source: &str) -> HashSet<PathBuf> {
+    let mut resolve_context = ResolveContext::default();
+    let _ = resolver.resolve_with_context(parent, source, &mut resolve_context);
+    resolve_context
+        .file_dependencies
+        .into_iter()
+        .chain(resolve_context.missing_dependencies)
+        .collect()
+}
+
+/// The request of `source` from `path` under the directories of
+/// `resolve.roots`, as the path relative to the innermost one of them and the
+/// query, for the relative, absolute and aliased requests, e.g. `./Button`
+/// from `src/Header.tsx` is `Button` under `src`. The packages of
+/// node_modules are never under the roots.
+fn get_root_request(path: &str, source: &str, config: &Config) -> Option<(PathBuf, String)> {
+    let roots = &config.resolve.roots;
+    if roots.is_empty() || FileRequest::parse(source).ok()?.has_param("context") {
+        return None;
+    }
+    let (request, query) = match source.split_once('?') {
+        Some((request, query)) => (request, format!("?{}", query)),
+        None => (source, String::new()),
+    };
+    let request = if request.starts_with('.') {
+        Path::new(path).parent()?.join(request)
+    } else if Path::new(request).is_absolute() {
+        PathBuf::from(request)
+    } else {
+        let (to, rest) = config.resolve.alias.iter().find_map(|(from, to)| {
+            if request == from {
+                Some((to, ""))
+            } else {
+                request
+                    .strip_prefix(from.as_str())
+                    .and_then(|rest| rest.strip_prefix('/'))
+                    .map(|rest| (to, rest))
+            }
+        })?;
+        // the aliases to the packages
+        if !Path::new(to).is_absolute() {
+            return None;
+        }
+        Path::new(to).join(rest)
+    }
+    .clean();
+    if request
+        .components()
+        .any(|component| component.as_os_str() == "node_modules")
+    {
+        return None;
+    }
+    roots
+        .iter()
+        .filter_map(|root| request.strip_prefix(root).ok())
+        .min_by_key(|relative| relative.components().count())
+        .map(|relative| (relative.to_path_buf(), query))
+}
 
-    do_resolve(path, source, resolver, Some(&context.config.externals))
+// the request under the roots is resolved in each of them in order, with the
+// extensions and the suffixes of the conditions, the first one wins
+fn resolve_in_roots(
+    path: &str,
+    source: &str,
+    resolver: &Resolver,
+    config: &Config,
+) -> Option<ResolverResource> {
+    let (request, query) = get_root_request(path, source, config)?;
+    let parent = Path::new(path).parent()?;
+    config.resolve.roots.iter().find_map(|root| {
+        let source = format!("{}{}", root.join(&request).to_string_lossy(), query);
+        let resolution = resolver.resolve(parent, &source).ok()?;
+        // the resolvers may cache the removed files
+        if !resolution.path().exists() {
+            return None;
+        }
+        debug!("resolve {:?} in root {:?}", source, root);
+        Some(ResolverResource::Resolved(ResolvedResource(Resolution {
+            root: Some(root.clone()),
+            ..to_resolution(resolution)
+        })))
+    })
 }
 
 fn get_external_target(
@@ -104,6 +393,8 @@ fn get_external_target(
                     "''".to_string()
                 } else if external.starts_with("commonjs ") {
                     format!("require(\"{}\")", external.replace("commonjs ", ""))
+                } else if let Some(specifier) = external.strip_prefix("module ") {
+                    get_external_target_from_import(specifier)
                 } else {
                     get_external_target_from_global_obj(global_obj, external)
                 },
@@ -114,6 +405,8 @@ fn get_external_target(
                     "''".to_string()
                 } else if config.module_type.as_ref().is_some_and(|t| t == "commonjs") {
                     format!("require(\"{}\")", config.root)
+                } else if config.module_type.as_ref().is_some_and(|t| t == "module") {
+                    get_external_target_from_import(&config.root)
                 } else {
                     get_external_target_from_global_obj(global_obj, &config.root)
                 },
@@ -213,6 +506,37 @@ fn get_external_target(
     }
 }
 
+/// The specifiers of the externals loaded as native ES modules, i.e. with the
+/// `module` type, they are resolved by the browser, e.g. with an import map.
+pub(crate) fn get_module_external_specifiers(
+    externals: &HashMap<String, ExternalConfig>,
+) -> Vec<String> {
+    let mut specifiers = externals
+        .values()
+        .filter_map(|external| match external {
+            ExternalConfig::Basic(external) => external.strip_prefix("module ").map(String::from),
+            ExternalConfig::Advanced(config)
+                if config.module_type.as_ref().is_some_and(|t| t == "module") =>
+            {
+                Some(config.root.clone())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    specifiers.sort();
+    specifiers.dedup();
+    specifiers
+}
+
+// the namespace is loaded at runtime, the module is async like the externals
+// with script
+fn get_external_target_from_import(specifier: &str) -> String {
+    format!(
+        "import(\"{}\").then((m) => ({{ __esModule: true, ...m }}))",
+        specifier
+    )
+}
+
 fn get_external_target_from_global_obj(global_obj_name: &str, external: &str) -> String {
     let external = if external.contains('.') || (external.contains('[') && external.contains(']')) {
         /*
@@ -260,12 +584,9 @@ fn do_resolve(
                 // TODO: 临时方案，需要改成删除文件时删 resolve cache 里的内容
                 // 比如把 util.ts 改名为 util.tsx，目前应该是还有问题的
                 if resolution.path().exists() {
-                    Ok(ResolverResource::Resolved(ResolvedResource(Resolution {
-                        package_json: resolution.package_json().cloned(),
-                        path: resolution.clone().into_path_buf(),
-                        query: resolution.query().map(|q| q.to_string()),
-                        fragment: resolution.fragment().map(|f| f.to_string()),
-                    })))
+                    Ok(ResolverResource::Resolved(ResolvedResource(to_resolution(
+                        resolution,
+                    ))))
                 } else {
                     Err(anyhow!(ResolveError {
                         path: source.to_string(),
@@ -278,6 +599,15 @@ fn do_resolve(
                     debug!("resolve ignored: {:?}", source);
                     Ok(ResolverResource::Ignored(path))
                 }
+                // e.g. #utils/* in the "imports" field of package.json
+                OxcResolveError::PackageImportNotDefined(_, package_json) => {
+                    Err(anyhow!(PackageImportsError {
+                        path: source.to_string(),
+                        from: path.to_string_lossy().to_string(),
+                        patterns: get_imports_patterns(&package_json).join(", "),
+                        package_json: package_json.to_string_lossy().to_string(),
+                    }))
+                }
                 _ => {
                     eprintln!(
                         "failed to resolve `{}` from `{}` with resolver err: {:?}",
@@ -295,6 +625,31 @@ fn do_resolve(
     }
 }
 
+fn to_resolution(resolution: oxc_resolver::Resolution) -> Resolution {
+    Resolution {
+        package_json: resolution.package_json().cloned(),
+        path: resolution.clone().into_path_buf(),
+        query: resolution.query().map(|q| q.to_string()),
+        fragment: resolution.fragment().map(|f| f.to_string()),
+        condition: None,
+        root: None,
+    }
+}
+
+fn get_imports_patterns(package_json: &Path) -> Vec<String> {
+    std::fs::read_to_string(package_json)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|package_json| {
+            package_json.get("imports").and_then(|imports| {
+                imports
+                    .as_object()
+                    .map(|imports| imports.keys().map(|key| format!("\"{}\"", key)).collect())
+            })
+        })
+        .unwrap_or_default()
+}
+
 pub fn get_resolvers(config: &Config) -> Resolvers {
     let cjs_resolver = get_resolver(config, ResolverType::Cjs);
     let esm_resolver = get_resolver(config, ResolverType::Esm);
@@ -322,11 +677,26 @@ pub fn get_module_extensions() -> Vec<String> {
     ]
 }
 
+// the extensions with the suffixes of the conditions first, e.g. `.web.tsx`
+fn get_conditional_extensions(conditions: &[String]) -> Vec<String> {
+    let extensions = get_module_extensions();
+    conditions
+        .iter()
+        .flat_map(|condition| {
+            extensions
+                .iter()
+                .map(move |extension| format!(".{}{}", condition, extension))
+        })
+        .chain(extensions.iter().cloned())
+        .collect()
+}
+
 fn get_resolver(config: &Config, resolver_type: ResolverType) -> Resolver {
     let alias = parse_alias(config.resolve.alias.clone());
     let is_browser = config.platform == Platform::Browser;
-    let extensions = get_module_extensions();
-    let options = match (resolver_type, is_browser) {
+    let extensions = get_conditional_extensions(&config.resolve.condition_names);
+    let is_script = matches!(resolver_type, ResolverType::Cjs | ResolverType::Esm);
+    let mut options = match (resolver_type, is_browser) {
         (ResolverType::Cjs, true) => ResolveOptions {
             alias,
             extensions,
@@ -413,6 +783,18 @@ fn get_resolver(config: &Config, resolver_type: ResolverType) -> Resolver {
             ..Default::default()
         },
     };
+    if is_script {
+        // custom conditions take precedence over the default ones
+        options
+            .condition_names
+            .splice(0..0, config.resolve.condition_names.iter().cloned());
+    }
+    if is_script && let Some(tsconfig) = &config.resolve.tsconfig {
+        options.tsconfig = Some(TsconfigOptions {
+            config_file: tsconfig.clone(),
+            references: TsconfigReferences::Auto,
+        });
+    }
 
     Resolver::new(options)
 }
@@ -435,12 +817,15 @@ pub fn clear_resolver_cache(resolvers: &Resolvers) {
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::path::Path;
 
     use crate::config::{
         Config, ExternalAdvanced, ExternalAdvancedSubpath, ExternalAdvancedSubpathConverter,
-        ExternalAdvancedSubpathRule, ExternalAdvancedSubpathTarget, ExternalConfig,
+        ExternalAdvancedSubpathRule, ExternalAdvancedSubpathTarget, ExternalConfig, Platform,
     };
+    use crate::module::{Dependency, ResolveType};
     use crate::resolve::ResolverType;
+    use crate::utils::test_helper::setup_compiler;
 
     #[test]
     fn test_resolve() {
@@ -599,6 +984,51 @@ mod tests {
         assert_eq!(x, ("empty".to_string(), Some("''".to_string()), None));
     }
 
+    #[test]
+    fn test_resolve_module_externals() {
+        let externals = HashMap::from([
+            (
+                "react".to_string(),
+                ExternalConfig::Basic("module react".to_string()),
+            ),
+            (
+                "lodash".to_string(),
+                ExternalConfig::Advanced(ExternalAdvanced {
+                    root: "lodash-es".to_string(),
+                    module_type: Some("module".to_string()),
+                    script: None,
+                    subpath: None,
+                }),
+            ),
+        ]);
+        let x = external_resolve(
+            "test/resolve/normal",
+            None,
+            Some(&externals),
+            "index.ts",
+            "react",
+        );
+        assert_eq!(
+            x.1.unwrap(),
+            r#"import("react").then((m) => ({ __esModule: true, ...m }))"#
+        );
+        let x = external_resolve(
+            "test/resolve/normal",
+            None,
+            Some(&externals),
+            "index.ts",
+            "lodash",
+        );
+        assert_eq!(
+            x.1.unwrap(),
+            r#"import("lodash-es").then((m) => ({ __esModule: true, ...m }))"#
+        );
+        assert_eq!(
+            super::get_module_external_specifiers(&externals),
+            vec!["lodash-es", "react"]
+        );
+    }
+
     #[test]
     fn test_resolve_advanced_externals() {
         let externals = HashMap::from([
@@ -756,6 +1186,187 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_package_imports() {
+        let base = "test/resolve/imports";
+        // wildcards
+        let x = resolve(base, None, None, "src/index.ts", "#utils/format");
+        assert_eq!(x, "src/utils/format.ts".to_string());
+        let x = resolve(base, None, None, "src/index.ts", "#internal/a.js");
+        assert_eq!(x, "src/internal/a.js".to_string());
+        // external bare specifier
+        let x = resolve(base, None, None, "src/index.ts", "#foo");
+        assert_eq!(x, "node_modules/foo/index.js".to_string());
+    }
+
+    #[test]
+    fn test_resolve_package_imports_conditions() {
+        let base = "test/resolve/imports";
+        let resolve_config = |resolver_type: ResolverType, platform: Platform| {
+            let config = Config {
+                platform,
+                ..Default::default()
+            };
+            base_resolve_with_config(
+                base,
+                &config,
+                None,
+                "src/index.ts",
+                "#config",
+                resolver_type,
+            )
+            .0
+        };
+        assert_eq!(
+            resolve_config(ResolverType::Esm, Platform::Browser),
+            "src/config.browser.ts"
+        );
+        // the nested conditions of "import" don't match, fallback to "default"
+        assert_eq!(
+            resolve_config(ResolverType::Cjs, Platform::Browser),
+            "src/config.ts"
+        );
+        assert_eq!(
+            resolve_config(ResolverType::Esm, Platform::Node),
+            "src/config.node.ts"
+        );
+    }
+
+    #[test]
+    fn test_resolve_package_imports_not_matched() {
+        let fixture = std::env::current_dir()
+            .unwrap()
+            .join("test/resolve/imports");
+        let resolver = super::get_resolver(&Default::default(), ResolverType::Esm);
+        let err = super::do_resolve(
+            &fixture.join("src/index.ts").to_string_lossy(),
+            "#missing",
+            &resolver,
+            None,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains(r##""#utils/*", "#internal/*.js", "#config", "#foo""##));
+    }
+
+    #[test]
+    fn test_get_probed_paths() {
+        let fixture = std::env::current_dir().unwrap().join("test/resolve/normal");
+        let resolvers = super::get_resolvers(&Default::default());
+        let probed_paths = |source: &str| {
+            let dep = Dependency {
+                source: source.to_string(),
+                resolve_as: None,
+                resolve_type: ResolveType::Require,
+                order: 0,
+                span: None,
+            };
+            super::get_probed_paths(
+                &fixture.join("index.ts").to_string_lossy(),
+                &dep,
+                &resolvers,
+                &Default::default(),
+            )
+        };
+
+        let paths = probed_paths("./missing");
+        assert!(paths.contains(&fixture.join("missing")));
+        assert!(paths.contains(&fixture.join("missing.tsx")));
+        let paths = probed_paths("missing-module");
+        assert!(paths.contains(&fixture.join("node_modules/missing-module")));
+    }
+
+    #[test]
+    fn test_resolve_condition_names() {
+        let compiler = setup_compiler("test/build/condition-names", false);
+        compiler.compile().unwrap();
+        let root = &compiler.context.root;
+        let files = compiler
+            .context
+            .module_graph
+            .read()
+            .unwrap()
+            .modules()
+            .iter()
+            .map(|module| module.id.relative_display(root))
+            .collect::<Vec<_>>();
+        // the files of the other platforms are never built
+        assert!(files.contains(&"./Button.web.tsx".to_string()));
+        assert!(files.contains(&"./node_modules/ui/web.js".to_string()));
+        assert!(!files.iter().any(|file| file.contains("native")));
+        assert!(!files.contains(&"./Button.tsx".to_string()));
+
+        let modules = compiler.create_stats_info().modules;
+        assert_eq!(
+            modules.get("Button.web.tsx").unwrap().condition,
+            Some("web".to_string())
+        );
+        assert_eq!(
+            modules.get("node_modules/ui/web.js").unwrap().condition,
+            Some("web".to_string())
+        );
+        assert_eq!(modules.get("index.ts").unwrap().condition, None);
+
+        let chains = compiler.why(Path::new("Button.web.tsx"), 1).unwrap();
+        assert_eq!(
+            compiler.format_import_chains(&chains),
+            "./index.ts\n└─ import \"./Button\" ./Button.web.tsx (condition: web)"
+        );
+    }
+
+    #[test]
+    fn test_resolve_roots() {
+        let compiler = setup_compiler("test/build/resolve-roots", false);
+        compiler.compile().unwrap();
+        let root = &compiler.context.root;
+        let mut files = compiler
+            .context
+            .module_graph
+            .read()
+            .unwrap()
+            .modules()
+            .iter()
+            .map(|module| module.id.relative_display(root))
+            .collect::<Vec<_>>();
+        files.sort();
+        // the relative and aliased requests with the extensions and the
+        // index files fall back to src, the packages are not overridden
+        assert_eq!(
+            files,
+            vec![
+                "./brands/acme/Button.tsx",
+                "./brands/acme/theme/index.ts",
+                "./node_modules/foo/index.js",
+                "./src/Header.ts",
+                "./src/index.ts",
+                "./src/utils.ts",
+            ]
+        );
+
+        let modules = compiler.create_stats_info().modules;
+        assert_eq!(
+            modules.get("brands/acme/Button.tsx").unwrap().root,
+            Some("./brands/acme".to_string())
+        );
+        assert_eq!(
+            modules.get("src/Header.ts").unwrap().root,
+            Some("./src".to_string())
+        );
+        assert_eq!(modules.get("node_modules/foo/index.js").unwrap().root, None);
+
+        let chains = compiler
+            .why(Path::new("brands/acme/theme/index.ts"), 1)
+            .unwrap();
+        assert_eq!(
+            compiler.format_import_chains(&chains),
+            r#"
+./src/index.ts
+└─ import "@/Header" ./src/Header.ts (root: ./src)
+   └─ import "./theme" ./brands/acme/theme/index.ts (root: ./brands/acme)"#
+                .trim()
+        );
+    }
+
     fn resolve(
         base: &str,
         alias: Option<Vec<(String, String)>>,
@@ -794,13 +1405,24 @@ mod tests {
         source: &str,
         resolve_type: ResolverType,
     ) -> (String, Option<String>, Option<String>) {
-        let current_dir = std::env::current_dir().unwrap();
-        let fixture = current_dir.join(base);
         let mut config: Config = Default::default();
         if let Some(alias_config) = alias {
             config.resolve.alias = alias_config;
         }
-        let resolver = super::get_resolver(&config, resolve_type);
+        base_resolve_with_config(base, &config, externals, path, source, resolve_type)
+    }
+
+    fn base_resolve_with_config(
+        base: &str,
+        config: &Config,
+        externals: Option<&HashMap<String, ExternalConfig>>,
+        path: &str,
+        source: &str,
+        resolve_type: ResolverType,
+    ) -> (String, Option<String>, Option<String>) {
+        let current_dir = std::env::current_dir().unwrap();
+        let fixture = current_dir.join(base);
+        let resolver = super::get_resolver(config, resolve_type);
         let resource = super::do_resolve(
             &fixture.join(path).to_string_lossy(),
             source,
@@ -0,0 +1,130 @@
+use std::sync::Mutex;
+
+use cached::{Cached, SizedCache};
+
+use crate::module::Dependency;
+use crate::resolve::{clear_resolver_cache, ResolverResource, Resolvers};
+use crate::utils::memory::{sized_cache_usage, CacheUsage};
+
+const RESOLVE_CACHE_SIZE: usize = 20000;
+
+/// The resolved dependencies of the watch mode, which are reused when the
+/// modules are rebuilt. It's an LRU cache, and the path caches of the
+/// resolvers, which only grow, are cleared after the updates once as many
+/// dependencies as the cache holds are resolved by them.
+pub struct ResolveCache {
+    inner: Mutex<ResolveCacheInner>,
+}
+
+struct ResolveCacheInner {
+    resources: SizedCache<String, ResolverResource>,
+    // the dependencies resolved by the resolvers since their caches are cleared
+    misses: usize,
+}
+
+impl Default for ResolveCache {
+    fn default() -> Self {
+        Self::with_size(RESOLVE_CACHE_SIZE)
+    }
+}
+
+impl ResolveCache {
+    pub fn with_size(size: usize) -> Self {
+        Self {
+            inner: Mutex::new(ResolveCacheInner {
+                resources: SizedCache::with_size(size),
+                misses: 0,
+            }),
+        }
+    }
+
+    pub fn key(path: &str, dep: &Dependency) -> String {
+        let source = dep.resolve_as.as_ref().unwrap_or(&dep.source);
+        format!("{}|{:?}|{}", path, dep.resolve_type, source)
+    }
+
+    pub fn get(&self, key: &str) -> Option<ResolverResource> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.resources.cache_get(key).cloned()
+    }
+
+    pub fn insert(&self, key: String, resource: ResolverResource) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.resources.cache_set(key, resource);
+        inner.misses += 1;
+    }
+
+    /// Clear the path caches of the resolvers if they resolved more
+    /// dependencies than the cache holds since they were cleared.
+    pub fn trim(&self, resolvers: &Resolvers) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.misses
+            >= inner
+                .resources
+                .cache_capacity()
+                .unwrap_or(RESOLVE_CACHE_SIZE)
+        {
+            clear_resolver_cache(resolvers);
+            inner.misses = 0;
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.resources.cache_clear();
+        inner.misses = 0;
+    }
+
+    pub fn usage(&self) -> CacheUsage {
+        let inner = self.inner.lock().unwrap();
+        sized_cache_usage("resolved dependencies", &inner.resources, |resource| {
+            resource.get_resolved_path().len()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResolveCache;
+    use crate::module::{Dependency, ResolveType};
+    use crate::resolve::ResolverResource;
+
+    fn dep(source: &str, resolve_type: ResolveType) -> Dependency {
+        Dependency {
+            source: source.to_string(),
+            resolve_as: None,
+            resolve_type,
+            order: 0,
+            span: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_cache() {
+        let cache = ResolveCache::with_size(2);
+        let key = ResolveCache::key("/src/index.js", &dep("./a", ResolveType::Css));
+        assert_ne!(
+            key,
+            ResolveCache::key("/src/index.js", &dep("./a", ResolveType::Require))
+        );
+        cache.insert(key.clone(), ResolverResource::Ignored("/src/a.js".into()));
+        cache.insert(
+            "b".to_string(),
+            ResolverResource::Ignored("/src/b.js".into()),
+        );
+        assert!(cache.get(&key).is_some());
+        cache.insert(
+            "c".to_string(),
+            ResolverResource::Ignored("/src/c.js".into()),
+        );
+        // the least recently used one is evicted
+        assert!(cache.get("b").is_none());
+        assert!(cache.get(&key).is_some());
+        assert_eq!(cache.usage().entries, 2);
+        assert_eq!(cache.usage().bytes, "/src/a.js".len() + "/src/c.js".len());
+
+        cache.clear();
+        assert!(cache.get(&key).is_none());
+        assert_eq!(cache.usage().entries, 0);
+    }
+}
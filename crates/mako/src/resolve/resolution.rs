@@ -10,6 +10,11 @@ pub struct Resolution {
     pub query: Option<String>,
     pub fragment: Option<String>,
     pub package_json: Option<Arc<PackageJson>>,
+    /// The condition of `resolve.conditionNames` which selected the file, by
+    /// its suffix or the `exports` of its package
+    pub condition: Option<String>,
+    /// The directory of `resolve.roots` which the file is resolved in
+    pub root: Option<PathBuf>,
 }
 
 impl Resolution {
@@ -58,6 +63,8 @@ impl fmt::Debug for Resolution {
             .field("query", &self.query)
             .field("fragment", &self.fragment)
             .field("package_json", &self.package_json.as_ref().map(|p| &p.path))
+            .field("condition", &self.condition)
+            .field("root", &self.root)
             .finish()
     }
 }
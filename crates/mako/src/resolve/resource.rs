@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use crate::resolve::Resolution;
+use crate::utils::file_request::FileRequest;
 
 #[derive(Debug, Clone)]
 pub struct ExternalResource {
@@ -21,7 +22,30 @@ pub enum ResolverResource {
 }
 
 impl ResolverResource {
+    /// The path of the module id and the task, with the path of the file
+    /// encoded, e.g. `/src/a%3Fb.css?modules` of `/src/a?b.css` and the query
+    /// `?modules`, see `encode_path`.
     pub fn get_resolved_path(&self) -> String {
+        match self {
+            ResolverResource::Resolved(ResolvedResource(resolution)) => FileRequest {
+                path: resolution.path().to_string_lossy().to_string(),
+                search: resolution
+                    .query()
+                    .unwrap_or_default()
+                    .trim_start_matches('?')
+                    .to_string(),
+                params: vec![],
+                fragment: resolution
+                    .fragment()
+                    .map(|fragment| fragment.trim_start_matches('#').to_string()),
+            }
+            .to_string(),
+            _ => self.get_resolved_request(),
+        }
+    }
+    /// The path of the resolved file as is, to be resolved again in the
+    /// generated imports.
+    pub fn get_resolved_request(&self) -> String {
         match self {
             ResolverResource::External(ExternalResource { source, .. }) => source.to_string(),
             ResolverResource::Resolved(ResolvedResource(resolution)) => {
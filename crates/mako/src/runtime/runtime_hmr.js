@@ -1,4 +1,4 @@
-globalThis.makoModuleHotUpdate(
+globalThis.__mako_runtimes__[__UNIQUE_NAME__]._makoModuleHotUpdate(
   '__CHUNK_ID__',
   {
     modules: {},
@@ -40,6 +40,11 @@ self.$RefreshSig$ = () => (type) => type;
     if (process.env.SOCKET_SERVER) {
       return new URL(process.env.SOCKET_SERVER);
     }
+    // the dev server of the app, which may be embedded in a page of another
+    // origin, e.g. a micro frontend
+    if (/^(https?:)?\/\//.test(require.publicPath)) {
+      return new URL(require.publicPath, location.href);
+    }
     return location;
   }
 
@@ -52,17 +57,43 @@ self.$RefreshSig$ = () => (type) => type;
 
   const socket = new WebSocket(getSocketUrl());
 
+  // the dev server sends the updates of the chunks loaded by the page only
+  function reportChunks(chunks) {
+    if (socket.readyState === WebSocket.OPEN) {
+      socket.send(JSON.stringify({ type: 'chunks', chunks }));
+    }
+  }
+  socket.addEventListener('open', () => {
+    reportChunks(Object.keys(require.loadedChunks || {}));
+  });
+  if (require.chunkLoadedListeners) {
+    require.chunkLoadedListeners.push((chunkId) => reportChunks([chunkId]));
+  }
+
   let latestHash = '';
   let updating = false;
+  // the filtered updates in the order they're sent, applied one by one
+  const pendingUpdates = [];
 
   function runHotUpdate() {
     if (hadRuntimeError) {
       location.reload();
     }
 
+    // e.g. the page is loaded after the update
+    while (
+      pendingUpdates.length &&
+      pendingUpdates[0].hash === require.currentHash()
+    ) {
+      pendingUpdates.shift();
+    }
     if (latestHash !== require.currentHash()) {
       updating = true;
-      return Promise.all([module.hot.check(), module.hot.updateChunksUrlMap()])
+      const next = pendingUpdates.shift();
+      return Promise.all([
+        next ? module.hot.check(next.update, next.hash) : module.hot.check(),
+        module.hot.updateChunksUrlMap(),
+      ])
         .then(() => {
           updating = false;
           return runHotUpdate();
@@ -79,6 +110,9 @@ self.$RefreshSig$ = () => (type) => type;
   socket.addEventListener('message', (rawMessage) => {
     const msg = JSON.parse(rawMessage.data);
     latestHash = msg.hash;
+    if (msg.update) {
+      pendingUpdates.push(msg);
+    }
 
     if (!updating) {
       runHotUpdate();
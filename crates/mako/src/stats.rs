@@ -1,20 +1,38 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
+mod duplicated_modules;
+mod packages;
+mod summary;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::Result;
 use colored::*;
 use indexmap::IndexMap;
 use pathdiff::diff_paths;
 use serde::Serialize;
-use swc_core::common::source_map::SmallPos;
+use swc_core::common::Span;
 
 use crate::ast::file::win_path;
+use crate::ast::sourcemap::SHARED_SOURCE_MAPS_DIR;
 use crate::compiler::{Compiler, Context};
+use crate::config::SourceMapFidelity;
 use crate::features::rsc::{RscClientInfo, RscCssModules};
-use crate::generate::chunk::ChunkType;
+use crate::generate::chunk::{ChunkId, ChunkType};
+use crate::generate::chunk_placement::ChunkPlacement;
+use crate::generate::csp_report::InlineCodeRegistry;
+use crate::generate::inline_chunks::InlinedChunk;
+use crate::generate::mangle_exports::MangleExportsStats;
+use crate::module::{relative_to_root, BailoutReason, ModuleType};
+use crate::plugins::build_manifest::BuildManifestDelta;
+use crate::plugins::eval_bailout::bailout_modules;
+use crate::stats::duplicated_modules::DuplicatedModules;
+pub use crate::stats::packages::PackageStats;
+use crate::stats::packages::{print_packages, PackageModule};
+pub use crate::stats::summary::BuildSummary;
 
 impl Compiler {
     pub fn create_stats_info(&self) -> StatsJsonMap {
@@ -28,7 +46,9 @@ impl Compiler {
         // 获取 output_path
         let output_path = context.config.output.path.to_string_lossy().to_string();
 
-        stats_map.built_at = chrono::Local::now().timestamp_millis();
+        if context.config.stats_timestamps() {
+            stats_map.built_at = chrono::Local::now().timestamp_millis();
+        }
         stats_map.hash = hash;
         stats_map.root_path = root_path;
         stats_map.output_path = output_path;
@@ -37,24 +57,22 @@ impl Compiler {
 
         // 把 context 中的静态资源信息加入到 stats_info 中
         self.context
-            .assets_info
-            .lock()
-            .unwrap()
+            .emitted_assets()
             .iter()
-            .for_each(|asset| {
-                let size = file_size(asset.0).unwrap();
+            .for_each(|(output_path, origin_path)| {
+                let size = file_size(origin_path).unwrap();
                 stats_info.add_assets(
                     size,
-                    asset.1.clone(),
+                    output_path.clone(),
                     "".to_string(),
                     self.context
                         .config
                         .output
                         .path
-                        .join(asset.1.clone())
+                        .join(output_path)
                         .to_string_lossy()
                         .to_string(),
-                    asset.1.clone(),
+                    output_path.clone(),
                 );
             });
 
@@ -74,6 +92,8 @@ impl Compiler {
         let module_graph = self.context.module_graph.read().unwrap();
         let chunks = chunk_graph.get_chunks();
 
+        let chunk_aliases = stats_info.get_chunk_aliases();
+
         // 在 chunks 中获取 modules
         let mut chunk_modules: Vec<StatsJsonChunkModuleItem> = Vec::new();
 
@@ -83,7 +103,7 @@ impl Compiler {
             .map(|chunk| {
                 let modules = chunk.get_modules();
                 let entry = matches!(chunk.chunk_type, ChunkType::Entry(_, _, _));
-                let id = chunk.id.id.clone();
+                let id = chunk.id.id.to_string();
                 let chunk_modules: Vec<StatsJsonChunkModuleItem> = modules
                     .iter()
                     .filter(|module| {
@@ -92,7 +112,7 @@ impl Compiler {
                         !module.id.contains("?modules")
                     })
                     .map(|module| {
-                        let id = module.id.clone();
+                        let id = module.id.to_string();
                         // 去拿 module 的文件 size 时，有可能 module 不存在，size 则设为 0
                         // 场景: xlsx 中引入了 fs 模块
                         let size = file_size(&id).unwrap_or_default();
@@ -101,22 +121,28 @@ impl Compiler {
                             size,
                             id,
                             // TODO: 现在是从每个 chunk 中找到包含的 module, 所以 chunk_id 是单个, 但是一个 module 有可能存在于多个 chunk 中
-                            chunks: vec![chunk.id.id.clone()],
+                            chunks: vec![chunk.id.id.to_string()],
+                            placement: chunk_graph.placement(&chunk.id, module).cloned(),
                         };
                         chunk_modules.push(module.clone());
                         module
                     })
                     .collect();
+                let alias_of = chunk_aliases
+                    .iter()
+                    .find(|alias| alias.chunk_id == id)
+                    .map(|alias| alias.alias_of.clone());
+                let files_chunk_id = alias_of.as_ref().unwrap_or(&id);
                 let files: Vec<String> = stats_info
                     .get_assets()
                     .iter()
-                    .filter(|asset| asset.chunk_id == id)
+                    .filter(|asset| &asset.chunk_id == files_chunk_id)
                     .map(|asset| asset.hashname.clone())
                     .collect();
                 let siblings = chunk_graph
                     .sync_dependencies_chunk(&chunk.id)
                     .iter()
-                    .map(|id| id.id.clone())
+                    .map(|id| id.id.to_string())
                     .collect::<Vec<_>>();
                 let origin_chunk_modules = match chunk.chunk_type {
                     // sync chunk is the common dependency of async chunk
@@ -141,8 +167,8 @@ impl Compiler {
                             origins_set.insert(
                                 unique_key,
                                 StatsJsonChunkOriginItem {
-                                    module: id.id.clone(),
-                                    module_identifier: id.id.clone(),
+                                    module: id.id.to_string(),
+                                    module_identifier: id.id.to_string(),
                                     module_name: module_graph
                                         .get_module(id)
                                         .and_then(|module| {
@@ -151,11 +177,15 @@ impl Compiler {
                                             })
                                         })
                                         .unwrap_or("".to_string()),
-                                    // -> "lo-hi"
                                     loc: dep
                                         .span
+                                        .filter(|span| !span.is_dummy())
                                         .map(|span| {
-                                            format!("{}-{}", span.lo.to_u32(), span.hi.to_u32())
+                                            let is_css =
+                                                module_graph.get_module(id).is_some_and(|module| {
+                                                    module.get_module_type() == ModuleType::Css
+                                                });
+                                            dependency_loc(span, is_css, &context)
                                         })
                                         .unwrap_or("".to_string()),
                                     request: dep.source.clone(),
@@ -169,6 +199,7 @@ impl Compiler {
                 StatsJsonChunkItem {
                     chunk_type: StatsJsonType::Chunk("chunk".to_string()),
                     id,
+                    alias_of,
                     files,
                     entry,
                     modules: chunk_modules,
@@ -177,34 +208,52 @@ impl Compiler {
                 }
             })
             .collect();
-        stats_map.entrypoints = chunks
-            .iter()
-            .filter_map(|chunk| match &chunk.chunk_type {
-                ChunkType::Entry(_, name, _) => {
-                    let mut chunks = chunk_graph
-                        .entry_dependencies_chunk(&chunk.id)
-                        .into_iter()
-                        .map(|id| id.id)
-                        .collect::<Vec<_>>();
-
-                    chunks.push(chunk.id.id.clone());
-
-                    Some((
-                        name.clone(),
-                        StatsJsonEntryItem {
-                            name: name.clone(),
-                            chunks,
-                        },
-                    ))
-                }
-                _ => None,
+        stats_map.entrypoints = chunk_graph
+            .entrypoints()
+            .into_iter()
+            .map(|entrypoint| {
+                let assets = stats_info
+                    .get_chunks_assets(&entrypoint.chunks)
+                    .into_iter()
+                    .map(|asset| StatsJsonEntryAssetItem {
+                        name: asset.hashname,
+                        size: asset.size,
+                    })
+                    .collect();
+                (
+                    entrypoint.name.clone(),
+                    StatsJsonEntryItem {
+                        name: entrypoint.name,
+                        chunks: entrypoint
+                            .chunks
+                            .into_iter()
+                            .map(|id| id.id.to_string())
+                            .collect(),
+                        assets,
+                    },
+                )
             })
-            .collect::<HashMap<_, _>>();
+            .collect();
         stats_map.chunk_modules = chunk_modules;
 
-        stats_map.modules = stats_info.get_modules();
+        stats_map.modules = stats_info.get_modules().into_iter().collect();
         stats_map.rsc_client_components = stats_info.get_rsc_client_components();
         stats_map.rsc_css_modules = stats_info.get_rsc_css_modules();
+        stats_map.pruned_empty_modules = *stats_info.pruned_empty_modules.lock().unwrap();
+        stats_map.side_effect_only_imports = stats_info.get_side_effect_only_imports();
+        if context
+            .config
+            .stats
+            .as_ref()
+            .is_some_and(|stats| stats.mangle_exports)
+        {
+            stats_map.mangle_exports = stats_info.mangle_exports.lock().unwrap().clone();
+        }
+        stats_map.duplicate_packages = stats_info.duplicate_packages.lock().unwrap().clone();
+        stats_map.duplicated_modules = stats_info.duplicated_modules.lock().unwrap().clone();
+        stats_map.bailouts = bailout_modules(&module_graph, &context.root);
+        stats_map.treemap = stats_info.get_treemap();
+        stats_map.packages = stats_info.get_packages();
 
         stats_map
     }
@@ -213,6 +262,11 @@ impl Compiler {
         let mut assets = self.context.stats_info.get_assets();
         // 按照产物名称排序
         assets.sort();
+        let source_maps_size = assets
+            .iter()
+            .filter(|asset| asset.hashname.ends_with(".map"))
+            .map(|asset| asset.size)
+            .sum::<u64>();
         // 产物路径需要按照 output.path 来
         let abs_path = &self.context.root;
         let output_path = &self.context.config.output.path;
@@ -227,11 +281,16 @@ impl Compiler {
         let mut max_length_name = String::new();
         let mut max_size = 0;
         let mut max_map_size = 0;
-        // 记录 name size map_size 的数组
-        let mut assets_vec: Vec<(String, u64, u64)> = vec![];
+        // 记录 name size map_size css_saved_size 的数组
+        let mut assets_vec: Vec<(String, u64, u64, u64)> = vec![];
+        let css_saved_bytes = self.context.stats_info.css_saved_bytes.lock().unwrap();
 
         // 生成 (name, size, map_size) 的 vec
         for asset in assets {
+            // the shared sections of the index maps are in the total of the maps
+            if asset.hashname.starts_with(SHARED_SOURCE_MAPS_DIR) {
+                continue;
+            }
             let name = asset.hashname.clone();
             let size_length = human_readable_size(asset.size).chars().count();
             // 记录较长的名字
@@ -249,7 +308,7 @@ impl Compiler {
                         if size_length > max_map_size {
                             max_map_size = size_length;
                         }
-                        *last = (last.0.clone(), last.1, asset.size);
+                        *last = (last.0.clone(), last.1, asset.size, last.3);
                         continue;
                     }
                 }
@@ -258,11 +317,12 @@ impl Compiler {
             if size_length > max_size {
                 max_size = size_length;
             }
-            assets_vec.push((asset.hashname.clone(), asset.size, 0));
+            let saved = css_saved_bytes.get(&asset.name).copied().unwrap_or(0);
+            assets_vec.push((asset.hashname.clone(), asset.size, 0, saved));
         }
 
         // Sort the output stats by their size in desc order
-        assets_vec.sort_by_key(|(_, size, _)| std::cmp::Reverse(*size));
+        assets_vec.sort_by_key(|(_, size, _, _)| std::cmp::Reverse(*size));
         // 输出 stats
         let mut s = String::new();
         for asset in assets_vec {
@@ -274,14 +334,25 @@ impl Compiler {
                 s if s.ends_with(".css") => file_name_str.magenta(),
                 _ => file_name_str.green(),
             };
+            // css 去重节省的大小, | deduped: -saved_size
+            let saved_str = if asset.3 > 0 {
+                format!(
+                    " {} {}",
+                    "│ deduped:".truecolor(128, 128, 128),
+                    format!("-{}", human_readable_size(asset.3)).truecolor(128, 128, 128)
+                )
+            } else {
+                String::new()
+            };
             // 没有 map 的输出
             if asset.2 == 0 {
                 let size = human_readable_size(asset.1);
                 s.push_str(
                     format!(
-                        "{} {}\n",
+                        "{} {}{}\n",
                         color_file_name_str,
                         pad_string(&size, max_size, true),
+                        saved_str,
                     )
                     .as_str(),
                 );
@@ -291,13 +362,14 @@ impl Compiler {
                 let map_size = human_readable_size(asset.2);
                 s.push_str(
                     format!(
-                        "{} {} {} {}\n",
+                        "{} {} {} {}{}\n",
                         color_file_name_str,
                         pad_string(&size, max_size, true)
                             .truecolor(128, 128, 128)
                             .bold(),
                         "│ map:".truecolor(128, 128, 128),
-                        pad_string(&map_size, max_map_size, true).truecolor(128, 128, 128)
+                        pad_string(&map_size, max_map_size, true).truecolor(128, 128, 128),
+                        saved_str,
                     )
                     .as_str(),
                 );
@@ -305,9 +377,129 @@ impl Compiler {
         }
 
         println!("{}", s.trim_end_matches('\n'));
+
+        let source_maps = self.context.stats_info.source_maps.lock().unwrap();
+        let vendor_reduced = self.context.config.source_map.vendor != SourceMapFidelity::Full;
+        if source_maps.saved > 0 || source_maps.reused > 0 || vendor_reduced {
+            let mut line = format!(
+                "source maps: {} in total",
+                human_readable_size(source_maps_size)
+            );
+            if source_maps.saved > 0 {
+                line.push_str(&format!(
+                    ", {} saved",
+                    human_readable_size(source_maps.saved)
+                ));
+            }
+            line.push_str(&format!(
+                ", built in {}ms",
+                source_maps.duration.as_millis()
+            ));
+            if source_maps.reused > 0 {
+                line.push_str(&format!(", {} reused", source_maps.reused));
+            }
+            println!("{}", line.truecolor(128, 128, 128));
+        }
+        drop(source_maps);
+
+        let pruned_empty_modules = *self.context.stats_info.pruned_empty_modules.lock().unwrap();
+        if pruned_empty_modules > 0 {
+            println!(
+                "{}",
+                format!("{} empty module(s) pruned", pruned_empty_modules).truecolor(128, 128, 128)
+            );
+        }
+
+        let side_effect_only_imports = self.context.stats_info.get_side_effect_only_imports();
+        if side_effect_only_imports > 0 {
+            println!(
+                "{}",
+                format!(
+                    "{} unused import(s) rewritten to side-effect-only",
+                    side_effect_only_imports
+                )
+                .truecolor(128, 128, 128)
+            );
+        }
+
+        if self
+            .context
+            .config
+            .stats
+            .as_ref()
+            .is_some_and(|stats| stats.mangle_exports)
+        {
+            if let Some(mangled) = self
+                .context
+                .stats_info
+                .mangle_exports
+                .lock()
+                .unwrap()
+                .as_ref()
+            {
+                println!(
+                    "{}",
+                    format!(
+                        "{} export(s) of {} module(s) mangled, {} saved",
+                        mangled.exports,
+                        mangled.modules,
+                        human_readable_size(mangled.saved_bytes as u64)
+                    )
+                    .truecolor(128, 128, 128)
+                );
+            }
+        }
+
+        if self
+            .context
+            .config
+            .stats
+            .as_ref()
+            .is_some_and(|stats| stats.packages)
+        {
+            print_packages(&self.context.stats_info.get_packages());
+        }
     }
 }
 
+#[derive(Debug, Default)]
+pub struct SourceMapsStats {
+    pub saved: u64,
+    // the time spent on building and writing the maps, summed over the
+    // threads
+    pub duration: Duration,
+    // the names of the shared sections of the index maps
+    pub shared: HashSet<String>,
+    // the maps of the chunks reused from the last build in watch mode
+    pub reused: usize,
+}
+
+/// The bytes of the chunks generated at the same time in the last build, see
+/// `maxInFlightChunkBytes`.
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InFlightChunkBytes {
+    pub budget: u64,
+    pub peak: u64,
+}
+
+/// The milliseconds of the phases of the last build.
+#[derive(Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildTimings {
+    pub build: u64,
+    pub tree_shaking: u64,
+    pub group_chunks: u64,
+    pub optimize_chunks: u64,
+    pub transform_modules: u64,
+    pub generate_chunks: u64,
+    // ast to code and write
+    pub emit: u64,
+    // building and writing the source maps, summed over the threads
+    pub source_maps: u64,
+    pub total: u64,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 // name 记录实际 filename , 用在 stats.json 中, hashname 用在产物描述和 manifest 中
 pub struct AssetsInfo {
@@ -331,11 +523,79 @@ impl PartialOrd for AssetsInfo {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ChunkAlias {
+    pub chunk_id: String,
+    // id of the chunk whose file is shared
+    pub alias_of: String,
+    // (file name of the aliased chunk, disk name of the shared file)
+    pub files: Vec<(String, String)>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BailoutModule {
+    /// relative to the root
+    pub path: String,
+    pub reasons: Vec<BailoutReason>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DuplicatePackage {
+    pub name: String,
+    pub copies: Vec<DuplicatePackageCopy>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatePackageCopy {
+    pub version: String,
+    /// dir of the package, relative to the root
+    pub path: String,
+    /// bytes of the bundled modules of the package
+    pub size: u64,
+    /// modules from an entry to the package
+    pub import_chain: Vec<String>,
+}
+
+/// An emitted chunk file in the `analyze` report.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TreemapChunk {
+    pub chunk_id: String,
+    pub file: String,
+    pub size: u64,
+    pub gzip_size: u64,
+    pub packages: Vec<TreemapPackage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TreemapPackage {
+    /// name of the package under node_modules, or of the project
+    pub name: String,
+    pub size: u64,
+    pub modules: Vec<TreemapModule>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TreemapModule {
+    /// relative to the root
+    pub path: String,
+    /// bytes of the module in the emitted file, minified in production
+    pub size: u64,
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub struct ModuleInfo {
     pub id: String,
     pub dependencies: Vec<String>,
     pub dependents: Vec<String>,
+    pub directives: Vec<String>,
+    /// the condition of `resolve.conditionNames` which selected the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    /// the directory of `resolve.roots` which the file is resolved in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
 }
 
 #[derive(Debug)]
@@ -344,6 +604,45 @@ pub struct StatsInfo {
     pub rsc_client_components: Mutex<Vec<RscClientInfo>>,
     pub rsc_css_modules: Mutex<Vec<RscCssModules>>,
     pub modules: Mutex<HashMap<String, ModuleInfo>>,
+    // chunk files of the entries inlined in the html by inlineRuntime and
+    // inlineChunks, in the order of the files
+    pub inlined_chunks: Mutex<Vec<InlinedChunk>>,
+    // css chunk file name -> bytes saved by optimization.concatenateCss
+    pub css_saved_bytes: Mutex<HashMap<String, u64>>,
+    // css chunk file name -> rules inlined in the html by criticalCss
+    pub critical_css: Mutex<HashMap<String, String>>,
+    // the inline code emitted by the build, for cspReport
+    pub inline_code: InlineCodeRegistry,
+    // count of modules which are empty after transform and pruned from the graph
+    pub pruned_empty_modules: Mutex<usize>,
+    // module path -> imports rewritten to side-effect-only by sideEffectOnlyImports
+    pub side_effect_only_imports: Mutex<HashMap<String, usize>>,
+    // modules, exports and bytes saved by optimization.mangleExports
+    pub mangle_exports: Mutex<Option<MangleExportsStats>>,
+    // bytes saved and shared sections written by the optimizations of sourceMap
+    pub source_maps: Mutex<SourceMapsStats>,
+    // async chunks emitted as the file of another chunk with the same content
+    pub chunk_aliases: Mutex<Vec<ChunkAlias>>,
+    // modules in more than one chunk after the chunks are optimized
+    pub duplicated_modules: Mutex<DuplicatedModules>,
+    // packages bundled with multiple versions, by duplicatePackageChecker
+    pub duplicate_packages: Mutex<Vec<DuplicatePackage>>,
+    // emitted chunk files with the sizes of their modules, by analyze
+    pub treemap: Mutex<Vec<TreemapChunk>>,
+    pub timings: Mutex<BuildTimings>,
+    pub in_flight_chunk_bytes: Mutex<InFlightChunkBytes>,
+    // the delta from the previous build manifest, by buildManifest.previous
+    pub build_manifest_delta: Mutex<Option<BuildManifestDelta>>,
+    // emitted file names with the content hashes, which are cached as
+    // immutable by cacheRules
+    content_hashed: Mutex<HashSet<String>>,
+    // module path -> package and sizes before tree shaking, by stats.packages
+    package_modules: Mutex<HashMap<String, PackageModule>>,
+    tree_shaken_modules: Mutex<HashSet<String>>,
+    // root module path -> paths of the modules concatenated into it
+    concatenated_modules: Mutex<HashMap<String, Vec<String>>>,
+    // module path -> bytes in the emitted chunks
+    module_sizes: Mutex<HashMap<String, u64>>,
 }
 
 impl StatsInfo {
@@ -353,6 +652,26 @@ impl StatsInfo {
             rsc_client_components: Mutex::new(vec![]),
             rsc_css_modules: Mutex::new(vec![]),
             modules: Mutex::new(HashMap::new()),
+            inlined_chunks: Mutex::new(vec![]),
+            css_saved_bytes: Mutex::new(HashMap::new()),
+            critical_css: Mutex::new(HashMap::new()),
+            inline_code: InlineCodeRegistry::default(),
+            pruned_empty_modules: Mutex::new(0),
+            side_effect_only_imports: Mutex::new(HashMap::new()),
+            mangle_exports: Mutex::new(None),
+            source_maps: Mutex::new(SourceMapsStats::default()),
+            chunk_aliases: Mutex::new(vec![]),
+            duplicated_modules: Mutex::new(DuplicatedModules::default()),
+            duplicate_packages: Mutex::new(vec![]),
+            treemap: Mutex::new(vec![]),
+            timings: Mutex::new(BuildTimings::default()),
+            in_flight_chunk_bytes: Mutex::new(InFlightChunkBytes::default()),
+            build_manifest_delta: Mutex::new(None),
+            content_hashed: Mutex::new(HashSet::new()),
+            package_modules: Mutex::new(HashMap::new()),
+            tree_shaken_modules: Mutex::new(HashSet::new()),
+            concatenated_modules: Mutex::new(HashMap::new()),
+            module_sizes: Mutex::new(HashMap::new()),
         }
     }
 
@@ -375,12 +694,132 @@ impl StatsInfo {
         });
     }
 
+    // the renamed files are not known to have the content hashes
+    pub fn rename_asset(&self, hashname: &str, to: String, path: String) {
+        self.content_hashed.lock().unwrap().remove(hashname);
+        let mut assets = self.assets.lock().unwrap();
+        for asset in assets.iter_mut().filter(|asset| asset.hashname == hashname) {
+            asset.hashname = to.clone();
+            asset.path = path.clone();
+        }
+    }
+
     pub fn clear_assets(&self) {
-        self.assets.lock().unwrap().clear()
+        self.assets.lock().unwrap().clear();
+        self.treemap.lock().unwrap().clear();
+        *self.source_maps.lock().unwrap() = SourceMapsStats::default();
+        self.content_hashed.lock().unwrap().clear();
+    }
+
+    pub fn add_content_hashed(&self, name: String) {
+        self.content_hashed.lock().unwrap().insert(name);
     }
 
+    pub fn is_content_hashed(&self, name: &str) -> bool {
+        self.content_hashed.lock().unwrap().contains(name)
+    }
+
+    pub fn add_css_saved_bytes(&self, name: String, size: u64) {
+        self.css_saved_bytes.lock().unwrap().insert(name, size);
+    }
+
+    pub fn add_pruned_empty_modules(&self, count: usize) {
+        *self.pruned_empty_modules.lock().unwrap() += count;
+    }
+
+    // keyed by the module, so the rebuilt modules are not counted twice
+    pub fn set_side_effect_only_imports(&self, path: String, count: usize) {
+        let mut side_effect_only_imports = self.side_effect_only_imports.lock().unwrap();
+        if count > 0 {
+            side_effect_only_imports.insert(path, count);
+        } else {
+            side_effect_only_imports.remove(&path);
+        }
+    }
+
+    pub fn get_side_effect_only_imports(&self) -> usize {
+        self.side_effect_only_imports.lock().unwrap().values().sum()
+    }
+
+    pub fn set_mangle_exports(&self, stats: MangleExportsStats) {
+        *self.mangle_exports.lock().unwrap() = Some(stats);
+    }
+
+    pub fn add_source_map_saved(&self, saved: u64) {
+        self.source_maps.lock().unwrap().saved += saved;
+    }
+
+    pub fn add_source_map_duration(&self, duration: Duration) {
+        self.source_maps.lock().unwrap().duration += duration;
+    }
+
+    pub fn add_reused_source_map(&self) {
+        self.source_maps.lock().unwrap().reused += 1;
+    }
+
+    /// Whether the shared section of the index maps is not written yet, or
+    /// it's counted as saved.
+    pub fn add_shared_source_map(&self, name: &str, size: u64) -> bool {
+        let mut source_maps = self.source_maps.lock().unwrap();
+        if source_maps.shared.insert(name.to_string()) {
+            true
+        } else {
+            source_maps.saved += size;
+            false
+        }
+    }
+
+    pub fn set_chunk_aliases(&self, chunk_aliases: Vec<ChunkAlias>) {
+        *self.chunk_aliases.lock().unwrap() = chunk_aliases;
+    }
+
+    pub fn get_chunk_aliases(&self) -> Vec<ChunkAlias> {
+        self.chunk_aliases.lock().unwrap().clone()
+    }
+
+    pub fn set_duplicate_packages(&self, duplicate_packages: Vec<DuplicatePackage>) {
+        *self.duplicate_packages.lock().unwrap() = duplicate_packages;
+    }
+
+    pub fn add_treemap_chunk(&self, chunk: TreemapChunk) {
+        self.treemap.lock().unwrap().push(chunk);
+    }
+
+    pub fn get_treemap(&self) -> Vec<TreemapChunk> {
+        let mut treemap = self.treemap.lock().unwrap().clone();
+        treemap.sort_by(|a, b| a.file.cmp(&b.file));
+        treemap
+    }
+
+    /// The assets by their names, they are added in the order of being
+    /// written, which varies from build to build.
     pub fn get_assets(&self) -> Vec<AssetsInfo> {
-        self.assets.lock().unwrap().iter().cloned().collect()
+        let mut assets = self.assets.lock().unwrap().clone();
+        assets.sort_by(|a, b| {
+            a.hashname
+                .cmp(&b.hashname)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        assets
+    }
+
+    /// Assets of the chunks in order for the html to load, css before js in
+    /// each chunk, source maps are excluded.
+    pub fn get_chunks_assets(&self, chunk_ids: &[ChunkId]) -> Vec<AssetsInfo> {
+        let assets = self.assets.lock().unwrap();
+        chunk_ids
+            .iter()
+            .flat_map(|chunk_id| {
+                let mut chunk_assets = assets
+                    .iter()
+                    .filter(|asset| asset.chunk_id == chunk_id.id && !asset.name.ends_with(".map"))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                chunk_assets
+                    .sort_by_key(|asset| (!asset.name.ends_with(".css"), asset.name.clone()));
+                chunk_assets
+            })
+            .collect()
     }
 
     pub fn parse_modules(&self, context: Arc<Context>) {
@@ -392,11 +831,22 @@ impl StatsInfo {
                 .iter()
                 .map(|(id, _dep)| id.generate(&context))
                 .collect::<Vec<_>>();
-            let dependents = module_graph
+            // in the order the dependents were built, which varies
+            let mut dependents = module_graph
                 .get_dependents(&module.id)
                 .iter()
                 .map(|(id, _dep)| id.generate(&context))
                 .collect::<Vec<_>>();
+            dependents.sort();
+            let directives = module
+                .info
+                .as_ref()
+                .map(|info| info.directives.clone())
+                .unwrap_or_default();
+            let condition = module.selected_condition();
+            let root = module
+                .resolved_root()
+                .map(|root| relative_to_root(&root.to_string_lossy().to_string(), &context.root));
             let id = module.id.generate(&context);
             modules.insert(
                 id.clone(),
@@ -404,6 +854,9 @@ impl StatsInfo {
                     id,
                     dependencies,
                     dependents,
+                    directives,
+                    condition,
+                    root,
                 },
             );
         });
@@ -471,6 +924,9 @@ pub struct StatsJsonChunkModuleItem {
     pub size: u64,
     pub id: String,
     pub chunks: Vec<String>,
+    // why the module is placed in the chunk
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub placement: Option<ChunkPlacement>,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -487,6 +943,8 @@ pub struct StatsJsonChunkItem {
     #[serde(flatten)]
     pub chunk_type: StatsJsonType,
     pub id: String,
+    #[serde(rename = "aliasOf", skip_serializing_if = "Option::is_none")]
+    pub alias_of: Option<String>,
     pub files: Vec<String>,
     pub entry: bool,
     pub modules: Vec<StatsJsonChunkModuleItem>,
@@ -497,6 +955,12 @@ pub struct StatsJsonChunkItem {
 pub struct StatsJsonEntryItem {
     pub name: String,
     pub chunks: Vec<String>,
+    pub assets: Vec<StatsJsonEntryAssetItem>,
+}
+#[derive(Serialize, Debug, Clone)]
+pub struct StatsJsonEntryAssetItem {
+    pub name: String,
+    pub size: u64,
 }
 #[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -507,12 +971,23 @@ pub struct StatsJsonMap {
     output_path: String,
     assets: Vec<StatsJsonAssetsItem>,
     chunk_modules: Vec<StatsJsonChunkModuleItem>,
-    modules: HashMap<String, ModuleInfo>,
+    pub modules: BTreeMap<String, ModuleInfo>,
     chunks: Vec<StatsJsonChunkItem>,
-    entrypoints: HashMap<String, StatsJsonEntryItem>,
+    entrypoints: BTreeMap<String, StatsJsonEntryItem>,
     rsc_client_components: Vec<RscClientInfo>,
     #[serde(rename = "rscCSSModules")]
     rsc_css_modules: Vec<RscCssModules>,
+    pruned_empty_modules: usize,
+    // imports rewritten to side-effect-only by sideEffectOnlyImports
+    side_effect_only_imports: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mangle_exports: Option<MangleExportsStats>,
+    duplicate_packages: Vec<DuplicatePackage>,
+    duplicated_modules: DuplicatedModules,
+    // the modules with direct eval or new Function
+    bailouts: Vec<BailoutModule>,
+    pub treemap: Vec<TreemapChunk>,
+    pub packages: Vec<PackageStats>,
     pub start_time: i64,
     pub end_time: i64,
 }
@@ -525,12 +1000,20 @@ impl StatsJsonMap {
             root_path: String::new(),
             output_path: String::new(),
             assets: vec![],
-            modules: HashMap::new(),
+            modules: BTreeMap::new(),
             chunk_modules: vec![],
             chunks: vec![],
-            entrypoints: HashMap::new(),
+            entrypoints: BTreeMap::new(),
             rsc_client_components: vec![],
             rsc_css_modules: vec![],
+            pruned_empty_modules: 0,
+            side_effect_only_imports: 0,
+            mangle_exports: None,
+            duplicate_packages: vec![],
+            duplicated_modules: DuplicatedModules::default(),
+            bailouts: vec![],
+            treemap: vec![],
+            packages: vec![],
             start_time: 0,
             end_time: 0,
         }
@@ -571,6 +1054,27 @@ fn pad_string(text: &str, max_length: usize, front: bool) -> String {
         padded_text
     }
 }
+// "line:column-column", or "line:column-line:column" if it spans lines, like
+// webpack, the byte positions of the spans depend on the order the files are
+// loaded in
+fn dependency_loc(span: Span, is_css: bool, context: &Context) -> String {
+    let cm = if is_css {
+        &context.meta.css.cm
+    } else {
+        &context.meta.script.cm
+    };
+    let lo = cm.lookup_char_pos(span.lo);
+    let hi = cm.lookup_char_pos(span.hi);
+    if lo.line == hi.line {
+        format!("{}:{}-{}", lo.line, lo.col_display, hi.col_display)
+    } else {
+        format!(
+            "{}:{}-{}:{}",
+            lo.line, lo.col_display, hi.line, hi.col_display
+        )
+    }
+}
+
 fn file_size(path: &str) -> Result<u64> {
     let metadata = fs::metadata(path)?;
     Ok(metadata.len())
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use super::human_readable_size;
+use super::packages::transformed_size;
+use crate::compiler::{Compiler, Context};
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::generate::chunk_graph::ChunkGraph;
+use crate::module::ModuleId;
+use crate::module_graph::ModuleGraph;
+use crate::share::helpers::SWC_HELPERS;
+
+/// The modules which are in more than one chunk, e.g. the modules shared by
+/// the async chunks which are not extracted by the code splitting.
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatedModules {
+    /// bytes of the extra copies of all the modules
+    pub total_bytes: u64,
+    /// sorted by the bytes of the extra copies
+    pub modules: Vec<DuplicatedModule>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatedModule {
+    pub path: String,
+    /// bytes of a copy after transform, before minification
+    pub size: u64,
+    pub chunks: Vec<String>,
+}
+
+impl DuplicatedModule {
+    fn extra_bytes(&self) -> u64 {
+        self.size * (self.chunks.len() as u64 - 1)
+    }
+}
+
+pub(crate) fn duplicated_modules(
+    chunk_graph: &ChunkGraph,
+    module_graph: &ModuleGraph,
+    context: &Arc<Context>,
+) -> DuplicatedModules {
+    crate::mako_profile_function!();
+
+    let mut chunks_of_module = BTreeMap::<&ModuleId, Vec<String>>::new();
+    for chunk in chunk_graph.get_chunks() {
+        for module_id in chunk.get_modules() {
+            chunks_of_module
+                .entry(module_id)
+                .or_default()
+                .push(chunk.id.id.to_string());
+        }
+    }
+    let mut modules = chunks_of_module
+        .into_iter()
+        // the helpers are in the runtime of each entry by design
+        .filter(|(module_id, chunks)| {
+            chunks.len() > 1 && !SWC_HELPERS.contains(&module_id.id.as_str())
+        })
+        .filter_map(|(module_id, mut chunks)| {
+            let module = module_graph.get_module(module_id)?;
+            chunks.sort();
+            Some(DuplicatedModule {
+                path: module_id.relative_display(&context.root),
+                size: transformed_size(module, context),
+                chunks,
+            })
+        })
+        .collect::<Vec<_>>();
+    modules.sort_by(|a, b| {
+        b.extra_bytes()
+            .cmp(&a.extra_bytes())
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    DuplicatedModules {
+        total_bytes: modules.iter().map(DuplicatedModule::extra_bytes).sum(),
+        modules,
+    }
+}
+
+impl Compiler {
+    /// Record the modules in more than one chunk after the chunks are
+    /// optimized, and warn if the extra copies are more than
+    /// `duplicatedModules.maxBytes`.
+    pub(crate) fn check_duplicated_modules(&self) {
+        let duplicated = {
+            let chunk_graph = self.context.chunk_graph.read().unwrap();
+            let module_graph = self.context.module_graph.read().unwrap();
+            duplicated_modules(&chunk_graph, &module_graph, &self.context)
+        };
+        if let Some(config) = &self.context.config.duplicated_modules
+            && duplicated.total_bytes > config.max_bytes
+        {
+            let top = duplicated
+                .modules
+                .iter()
+                .take(5)
+                .map(|module| {
+                    format!(
+                        "  {} {} x {} chunks",
+                        module.path,
+                        human_readable_size(module.size),
+                        module.chunks.len()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let diagnostic = Diagnostic::new(
+                DiagnosticCode::DuplicatedModules,
+                format!(
+                    "{} of the modules are duplicated in the chunks, which is over {}:\n{}",
+                    human_readable_size(duplicated.total_bytes),
+                    human_readable_size(config.max_bytes),
+                    top
+                ),
+            )
+            .paths(
+                duplicated
+                    .modules
+                    .iter()
+                    .map(|module| self.context.root.join(&module.path))
+                    .collect(),
+            );
+            self.context.diagnostics.report(diagnostic, &self.context);
+        }
+        *self.context.stats_info.duplicated_modules.lock().unwrap() = duplicated;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_duplicated_modules() {
+        let compiler = setup_compiler("test/build/duplicated-modules", false);
+        let err = compiler.compile().unwrap_err().to_string();
+        assert!(err.contains("error[MAKO_DUPLICATED_MODULES]"), "{}", err);
+        assert!(err.contains("./format.js"), "{}", err);
+
+        let duplicated = compiler
+            .context
+            .stats_info
+            .duplicated_modules
+            .lock()
+            .unwrap()
+            .clone();
+        assert_eq!(duplicated.modules.len(), 1, "{:?}", duplicated);
+        let format = &duplicated.modules[0];
+        assert_eq!(format.path, "./format.js");
+        assert_eq!(format.chunks.len(), 2);
+        assert!(format.size > 0);
+        assert_eq!(duplicated.total_bytes, format.size);
+    }
+}
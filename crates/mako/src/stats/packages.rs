@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::Arc;
+
+use colored::*;
+use serde::Serialize;
+use swc_core::ecma::codegen::text_writer::JsWriter;
+use swc_core::ecma::codegen::{Config as JsCodegenConfig, Emitter};
+
+use super::{human_readable_size, pad_string, StatsInfo};
+use crate::compiler::Context;
+use crate::module::{Module, ModuleAst, ModuleId};
+use crate::module_graph::ModuleGraph;
+use crate::plugins::duplicate_package_checker::nearest_package;
+use crate::utils::get_pkg_name;
+
+/// The sizes of a bundled package, by `stats.packages`.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageStats {
+    /// name of the nearest package.json, or of the project
+    pub name: String,
+    pub version: Option<String>,
+    /// bytes of the source files of all the modules
+    pub source_size: u64,
+    /// bytes of all the modules after transform, before tree shaking
+    pub transformed_size: u64,
+    /// bytes of the modules in the emitted chunks, minified in production
+    pub minified_size: u64,
+    /// bytes after transform of the modules removed by tree shaking
+    pub tree_shaken_size: u64,
+    pub kept_modules: usize,
+    pub dropped_modules: usize,
+}
+
+// a module of the graph before tree shaking
+#[derive(Debug, Clone)]
+pub(crate) struct PackageModule {
+    name: String,
+    version: Option<String>,
+    source_size: u64,
+    transformed_size: u64,
+}
+
+impl StatsInfo {
+    /// Record the package and the sizes of each module, before tree shaking
+    /// removes any of them.
+    pub fn record_package_modules(&self, module_graph: &ModuleGraph, context: &Arc<Context>) {
+        crate::mako_profile_function!();
+
+        let project = get_pkg_name(&context.root).unwrap_or_else(|| ".".to_string());
+        let package_modules = module_graph
+            .modules()
+            .into_iter()
+            .filter_map(|module| {
+                let info = module.info.as_ref()?;
+                if info.external.is_some() {
+                    return None;
+                }
+                let (name, version) = match nearest_package(module) {
+                    Some(package) => (package.name, package.version),
+                    None => (project.clone(), None),
+                };
+                let source_size = if info.file.is_virtual {
+                    None
+                } else {
+                    fs::metadata(&info.file.path).ok().map(|m| m.len())
+                };
+                let package_module = PackageModule {
+                    name,
+                    version,
+                    source_size: source_size.unwrap_or(info.raw.len() as u64),
+                    transformed_size: transformed_size(module, context),
+                };
+                Some((module.id.id.to_string(), package_module))
+            })
+            .collect();
+        *self.package_modules.lock().unwrap() = package_modules;
+        self.tree_shaken_modules.lock().unwrap().clear();
+        self.concatenated_modules.lock().unwrap().clear();
+        self.module_sizes.lock().unwrap().clear();
+    }
+
+    pub fn add_tree_shaken_module(&self, module_id: &ModuleId) {
+        self.tree_shaken_modules
+            .lock()
+            .unwrap()
+            .insert(module_id.id.to_string());
+    }
+
+    pub fn add_concatenated_modules(&self, root: &ModuleId, inners: &HashSet<ModuleId>) {
+        let mut inners = inners
+            .iter()
+            .map(|inner| inner.id.to_string())
+            .collect::<Vec<_>>();
+        inners.sort();
+        self.concatenated_modules
+            .lock()
+            .unwrap()
+            .insert(root.id.to_string(), inners);
+    }
+
+    /// Add the emitted sizes of the modules of a chunk file.
+    pub fn add_module_sizes(&self, sizes: &[(String, u64)]) {
+        let mut module_sizes = self.module_sizes.lock().unwrap();
+        for (path, size) in sizes {
+            *module_sizes.entry(path.clone()).or_insert(0) += size;
+        }
+    }
+
+    /// The packages by their emitted sizes in desc order.
+    pub fn get_packages(&self) -> Vec<PackageStats> {
+        let package_modules = self.package_modules.lock().unwrap();
+        let tree_shaken_modules = self.tree_shaken_modules.lock().unwrap();
+        let module_sizes = self.attributed_module_sizes(&package_modules);
+
+        let mut packages: HashMap<(String, Option<String>), PackageStats> = HashMap::new();
+        for (id, module) in package_modules.iter() {
+            let package = packages
+                .entry((module.name.clone(), module.version.clone()))
+                .or_insert_with(|| PackageStats {
+                    name: module.name.clone(),
+                    version: module.version.clone(),
+                    source_size: 0,
+                    transformed_size: 0,
+                    minified_size: 0,
+                    tree_shaken_size: 0,
+                    kept_modules: 0,
+                    dropped_modules: 0,
+                });
+            package.source_size += module.source_size;
+            package.transformed_size += module.transformed_size;
+            if tree_shaken_modules.contains(id) {
+                package.tree_shaken_size += module.transformed_size;
+                package.dropped_modules += 1;
+            } else {
+                package.minified_size += module_sizes.get(id.as_str()).copied().unwrap_or(0);
+                package.kept_modules += 1;
+            }
+        }
+        let mut packages = packages.into_values().collect::<Vec<_>>();
+        packages.sort_by(|a, b| {
+            b.minified_size
+                .cmp(&a.minified_size)
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.version.cmp(&b.version))
+        });
+        packages
+    }
+
+    // the emitted size of a concatenated module is shared by the modules
+    // merged into it, by their sizes after transform
+    fn attributed_module_sizes<'a>(
+        &self,
+        package_modules: &'a HashMap<String, PackageModule>,
+    ) -> HashMap<&'a str, u64> {
+        let module_sizes = self.module_sizes.lock().unwrap();
+        let concatenated_modules = self.concatenated_modules.lock().unwrap();
+        let mut sizes = HashMap::new();
+        for (root, size) in module_sizes.iter() {
+            let Some((root, _)) = package_modules.get_key_value(root) else {
+                continue;
+            };
+            let group = std::iter::once(root)
+                .chain(concatenated_modules.get(root).into_iter().flatten())
+                .filter_map(|id| package_modules.get_key_value(id))
+                .collect::<Vec<_>>();
+            let total = group
+                .iter()
+                .map(|(_, module)| module.transformed_size)
+                .sum::<u64>();
+            if total == 0 {
+                sizes.insert(root.as_str(), *size);
+                continue;
+            }
+            let mut rest = *size;
+            for (id, module) in group.iter().skip(1) {
+                let share = size * module.transformed_size / total;
+                rest -= share;
+                sizes.insert(id.as_str(), share);
+            }
+            sizes.insert(root.as_str(), rest);
+        }
+        sizes
+    }
+}
+
+// bytes of the module after transform, without minify and comments
+pub(super) fn transformed_size(module: &Module, context: &Arc<Context>) -> u64 {
+    let Some(info) = module.info.as_ref() else {
+        return 0;
+    };
+    match &info.ast {
+        ModuleAst::Script(ast) => {
+            let cm = context.meta.script.cm.clone();
+            let mut buf = vec![];
+            let mut emitter = Emitter {
+                cfg: JsCodegenConfig::default().with_target(context.config.output.es_version),
+                cm: cm.clone(),
+                comments: None,
+                wr: Box::new(JsWriter::new(cm, "\n", &mut buf, None)),
+            };
+            match emitter.emit_module(&ast.ast) {
+                Ok(_) => buf.len() as u64,
+                Err(_) => info.raw.len() as u64,
+            }
+        }
+        ModuleAst::Css(ast) => ast
+            .generate(context.clone())
+            .map(|generated| generated.code.len() as u64)
+            .unwrap_or(info.raw.len() as u64),
+        ModuleAst::None => info.raw.len() as u64,
+    }
+}
+
+/// The table of the packages for `--stats-packages`.
+pub(crate) fn print_packages(packages: &[PackageStats]) {
+    if packages.is_empty() {
+        return;
+    }
+    let header = [
+        "package".to_string(),
+        "version".to_string(),
+        "source".to_string(),
+        "transformed".to_string(),
+        "minified".to_string(),
+        "tree shaken".to_string(),
+        "modules".to_string(),
+    ];
+    let rows = packages
+        .iter()
+        .map(|package| {
+            [
+                package.name.clone(),
+                package.version.clone().unwrap_or_default(),
+                human_readable_size(package.source_size),
+                human_readable_size(package.transformed_size),
+                human_readable_size(package.minified_size),
+                human_readable_size(package.tree_shaken_size),
+                format!(
+                    "{}/{}",
+                    package.kept_modules,
+                    package.kept_modules + package.dropped_modules
+                ),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let widths = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .chain(std::iter::once(&header))
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    // names on the left, sizes on the right
+    let format_row = |row: &[String; 7]| {
+        row.iter()
+            .enumerate()
+            .map(|(i, cell)| pad_string(cell, widths[i], i > 1))
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    let mut s = format!("{}\n", format_row(&header).truecolor(128, 128, 128));
+    for row in &rows {
+        s.push_str(&format!("{}\n", format_row(row)));
+    }
+    println!("{}", s.trim_end_matches('\n'));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::testing::compile_fixture;
+
+    #[test]
+    fn test_packages() {
+        let compiler = compile_fixture(
+            Path::new("test/build/stats-packages"),
+            Some(r#"{ "mode": "production" }"#),
+        )
+        .unwrap();
+        let packages = compiler.create_stats_info().packages;
+        let bar = packages.iter().find(|p| p.name == "bar").unwrap();
+        assert_eq!(bar.version, Some("2.1.0".to_string()));
+        // the re-exports in index.js and unused.js are removed
+        assert_eq!((bar.kept_modules, bar.dropped_modules), (1, 2));
+        assert!(bar.tree_shaken_size > 0);
+        // used.js is concatenated into index.js of the project, but still has
+        // its share of the emitted bytes
+        assert!(bar.minified_size > 0);
+        assert!(bar.minified_size < bar.transformed_size - bar.tree_shaken_size);
+        let app = packages
+            .iter()
+            .find(|p| p.name == "stats-packages")
+            .unwrap();
+        assert_eq!((app.kept_modules, app.dropped_modules), (1, 0));
+        assert!(app.source_size > 0);
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hasher;
+
+use pathdiff::diff_paths;
+use regex::Regex;
+use serde::Serialize;
+use twox_hash::XxHash64;
+
+use crate::ast::file::win_path;
+use crate::compiler::Compiler;
+use crate::diagnostics::{DiagnosticCode, Severity};
+use crate::generate::analyze::gzip_size;
+use crate::module::relative_to_root;
+use crate::plugins::build_manifest::BuildManifestDelta;
+use crate::stats::{BuildTimings, InFlightChunkBytes};
+
+/// The version of the schema of [BuildSummary], which is bumped when a field
+/// is removed or its meaning is changed, adding fields keeps the version.
+pub const BUILD_SUMMARY_VERSION: u32 = 1;
+
+/// The summary of the build printed by `--json`, i.e. the output of the
+/// terminal in a stable schema, for the CI to report without scraping it.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildSummary {
+    pub version: u32,
+    pub success: bool,
+    /// the error which failed the build, without colors
+    pub error: Option<String>,
+    pub config: ConfigDigest,
+    pub timings: BuildTimings,
+    /// the budget and the peak of the bytes of the chunks generated at the
+    /// same time
+    pub in_flight_chunk_bytes: InFlightChunkBytes,
+    pub diagnostics: Vec<SummaryDiagnostic>,
+    /// count of the diagnostics ignored by `diagnostics.ignore`
+    pub ignored_diagnostics: usize,
+    /// sorted by name
+    pub assets: Vec<SummaryAsset>,
+    pub entrypoints: BTreeMap<String, SummaryEntrypoint>,
+    /// the delta from `buildManifest.previous`, null without it
+    pub delta: Option<BuildManifestDelta>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDigest {
+    /// hash of the resolved config
+    pub hash: String,
+    pub mode: String,
+    pub platform: serde_json::Value,
+    /// relative to the root
+    pub output_path: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SummaryDiagnostic {
+    pub code: DiagnosticCode,
+    pub severity: Severity,
+    pub message: String,
+    /// relative to the root
+    pub paths: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryAsset {
+    /// relative to `output.path`
+    pub name: String,
+    pub size: u64,
+    /// null for the source maps
+    pub gzip_size: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryEntrypoint {
+    /// the files loaded by the entry synchronously, without the source maps
+    pub assets: Vec<String>,
+    pub size: u64,
+    pub gzip_size: u64,
+}
+
+impl Compiler {
+    /// The summary of the last build, with the error if it's failed, the
+    /// assets and the entrypoints are empty then.
+    pub fn build_summary(&self, error: Option<&anyhow::Error>) -> BuildSummary {
+        let context = &self.context;
+        let config = &context.config;
+
+        let mut hasher: XxHash64 = Default::default();
+        hasher.write(serde_json::to_string(config).unwrap_or_default().as_bytes());
+        let output_path = diff_paths(&config.output.path, &context.root)
+            .unwrap_or_else(|| config.output.path.clone());
+        let config_digest = ConfigDigest {
+            hash: format!("{:016x}", hasher.finish()),
+            mode: config.mode.to_string(),
+            platform: serde_json::to_value(&config.platform).unwrap_or_default(),
+            output_path: win_path(&output_path.to_string_lossy()),
+        };
+
+        let diagnostics = context
+            .diagnostics
+            .get_reported()
+            .into_iter()
+            .map(|diagnostic| SummaryDiagnostic {
+                code: diagnostic.code,
+                severity: diagnostic.severity,
+                message: strip_colors(&diagnostic.message),
+                paths: diagnostic
+                    .paths
+                    .iter()
+                    .map(|path| {
+                        relative_to_root(&path.to_string_lossy().to_string(), &context.root)
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let mut assets = vec![];
+        let mut entrypoints = BTreeMap::new();
+        if error.is_none() {
+            let mut stats_assets = context.stats_info.get_assets();
+            stats_assets.sort_by(|a, b| a.hashname.cmp(&b.hashname));
+            stats_assets.dedup_by(|a, b| a.hashname == b.hashname);
+            assets = stats_assets
+                .into_iter()
+                .map(|asset| {
+                    let gzip_size = if asset.hashname.ends_with(".map") {
+                        None
+                    } else {
+                        context
+                            .output_fs
+                            .read(&config.output.path.join(&asset.hashname))
+                            .ok()
+                            .and_then(|content| gzip_size(&content).ok())
+                    };
+                    SummaryAsset {
+                        name: asset.hashname,
+                        size: asset.size,
+                        gzip_size,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let assets_by_name = assets
+                .iter()
+                .map(|asset| (asset.name.as_str(), asset))
+                .collect::<HashMap<_, _>>();
+            let chunk_graph = context.chunk_graph.read().unwrap();
+            for entrypoint in chunk_graph.entrypoints() {
+                let names = context
+                    .stats_info
+                    .get_chunks_assets(&entrypoint.chunks)
+                    .into_iter()
+                    .map(|asset| asset.hashname)
+                    .collect::<Vec<_>>();
+                let (size, gzip_size) = names
+                    .iter()
+                    .filter_map(|name| assets_by_name.get(name.as_str()))
+                    .fold((0, 0), |(size, gzip_size), asset| {
+                        (size + asset.size, gzip_size + asset.gzip_size.unwrap_or(0))
+                    });
+                entrypoints.insert(
+                    entrypoint.name,
+                    SummaryEntrypoint {
+                        assets: names,
+                        size,
+                        gzip_size,
+                    },
+                );
+            }
+        }
+
+        BuildSummary {
+            version: BUILD_SUMMARY_VERSION,
+            success: error.is_none(),
+            error: error.map(|error| strip_colors(&format!("{:#}", error))),
+            config: config_digest,
+            timings: context.stats_info.timings.lock().unwrap().clone(),
+            in_flight_chunk_bytes: context
+                .stats_info
+                .in_flight_chunk_bytes
+                .lock()
+                .unwrap()
+                .clone(),
+            diagnostics,
+            ignored_diagnostics: context.diagnostics.ignored(),
+            assets,
+            entrypoints,
+            delta: context
+                .stats_info
+                .build_manifest_delta
+                .lock()
+                .unwrap()
+                .clone(),
+        }
+    }
+}
+
+fn strip_colors(text: &str) -> String {
+    let re = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+    re.replace_all(text, "").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::utils::output_fs::MemoryFileSystem;
+    use crate::utils::test_helper::setup_compiler_with_output_fs;
+
+    #[test]
+    fn test_build_summary() {
+        let compiler = setup_compiler_with_output_fs(
+            "test/build/build-summary",
+            Arc::new(MemoryFileSystem::new()),
+        );
+        compiler.compile().unwrap();
+        let summary = serde_json::to_value(compiler.build_summary(None)).unwrap();
+
+        assert_eq!(summary["version"], 1);
+        assert_eq!(summary["success"], true);
+        assert_eq!(summary["config"]["mode"], "production");
+        assert_eq!(summary["config"]["outputPath"], "dist");
+        assert!(
+            summary["timings"]["total"].as_u64().unwrap()
+                >= summary["timings"]["build"].as_u64().unwrap()
+        );
+        let assets = summary["assets"].as_array().unwrap();
+        let names = assets
+            .iter()
+            .map(|asset| asset["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["index.css", "index.js", "lazy_js-async.js"]);
+        let index_js = &assets[1];
+        assert!(index_js["gzipSize"].as_u64().unwrap() > 0);
+        assert!(index_js["gzipSize"].as_u64().unwrap() < index_js["size"].as_u64().unwrap());
+        // the async chunk is not loaded by the entry
+        let index = &summary["entrypoints"]["index"];
+        assert_eq!(
+            index["assets"],
+            serde_json::json!(["index.css", "index.js"])
+        );
+        assert_eq!(
+            index["size"].as_u64().unwrap(),
+            assets[0]["size"].as_u64().unwrap() + index_js["size"].as_u64().unwrap()
+        );
+        let diagnostics = summary["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["code"], "MAKO_CIRCULAR");
+        assert_eq!(diagnostics[0]["severity"], "warning");
+        assert!(summary["delta"].is_null());
+
+        let error = anyhow::anyhow!("\x1b[31mBuild failed.\x1b[0m");
+        let summary = serde_json::to_value(compiler.build_summary(Some(&error))).unwrap();
+        assert_eq!(summary["success"], false);
+        assert_eq!(summary["error"], "Build failed.");
+        assert_eq!(summary["assets"], serde_json::json!([]));
+    }
+}
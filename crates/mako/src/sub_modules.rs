@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
+
+use crate::ast::file::{Content, File};
+use crate::compiler::Context;
+use crate::plugin::PluginLoadParam;
+use crate::utils::file_request::FileRequest;
+
+/// The files split into the sub-modules by `Plugin::load_sub_modules`, e.g.
+/// the script, the style and the template of a single file component.
+///
+/// The file itself is the primary module, which imports the sub-modules with
+/// the queries, e.g. `import "./App.sfc?type=style"`. A file is split once
+/// and the sub-modules are loaded from the split, it's split again when the
+/// file is changed in watch mode and all the sub-modules are rebuilt then.
+#[derive(Default)]
+pub struct SubModules {
+    files: RwLock<HashMap<String, SplitFile>>,
+}
+
+struct SplitFile {
+    content: Content,
+    // normalized query -> content
+    sub_modules: HashMap<String, Content>,
+}
+
+impl SubModules {
+    /// Whether the file is split by the plugins.
+    pub fn has(&self, path: &str) -> bool {
+        self.files.read().unwrap().contains_key(path)
+    }
+
+    /// Split the file again when it's loaded next time, e.g. it's changed.
+    pub fn invalidate(&self, path: &str) {
+        self.files.write().unwrap().remove(path);
+    }
+
+    /// The content of the file or the sub-module of the query, none if the
+    /// file is not split by the plugins or the query is not a sub-module of
+    /// the file, e.g. `?raw`.
+    pub fn load(&self, file: &File, context: &Arc<Context>) -> Result<Option<Content>> {
+        if file.is_virtual {
+            return Ok(None);
+        }
+        let path = file.pathname.to_string_lossy().to_string();
+        let query = FileRequest {
+            params: file.params.clone(),
+            ..Default::default()
+        }
+        .normalized_query();
+        let find = |split: &SplitFile| {
+            if query.is_empty() {
+                Some(split.content.clone())
+            } else {
+                split.sub_modules.get(&query).cloned()
+            }
+        };
+        if let Some(split) = self.files.read().unwrap().get(&path) {
+            return Ok(find(split));
+        }
+
+        let primary = if query.is_empty() {
+            None
+        } else {
+            Some(File::new(path.clone(), context.clone()))
+        };
+        let param = PluginLoadParam {
+            file: primary.as_ref().unwrap_or(file),
+        };
+        let Some(result) = context.plugin_driver.load_sub_modules(&param, context)? else {
+            return Ok(None);
+        };
+        let split = SplitFile {
+            content: result.content,
+            sub_modules: result
+                .sub_modules
+                .into_iter()
+                .map(|(query, content)| (normalize_query(&query), content))
+                .collect(),
+        };
+        let content = find(&split);
+        self.files.write().unwrap().insert(path, split);
+        Ok(content)
+    }
+}
+
+// e.g. `?type=style&lang=css` -> `lang=css&type=style`
+fn normalize_query(query: &str) -> String {
+    FileRequest::parse(&format!("?{}", query.trim_start_matches('?')))
+        .map(|request| request.normalized_query())
+        .unwrap_or_else(|_| query.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use regex::Regex;
+
+    use crate::ast::file::{Content, JsContent};
+    use crate::compiler::{Args, Compiler, Context};
+    use crate::config::Config;
+    use crate::module::ModuleId;
+    use crate::plugin::{Plugin, PluginLoadParam, PluginSubModules};
+    use crate::testing::TempDir;
+
+    // a toy single file component with the <script>, <style> and <template>
+    struct SfcPlugin {}
+
+    impl Plugin for SfcPlugin {
+        fn name(&self) -> &str {
+            "sfc"
+        }
+
+        fn load_sub_modules(
+            &self,
+            param: &PluginLoadParam,
+            _context: &Arc<Context>,
+        ) -> Result<Option<PluginSubModules>> {
+            if param.file.extname != "sfc" {
+                return Ok(None);
+            }
+            let source = fs::read_to_string(&param.file.pathname)?;
+            let block = |tag: &str| {
+                Regex::new(&format!(r"(?s)<{tag}>(.*)</{tag}>"))
+                    .unwrap()
+                    .captures(&source)
+                    .map(|captures| captures[1].trim().to_string())
+                    .unwrap_or_default()
+            };
+            let js = |content: String| {
+                Content::Js(JsContent {
+                    content,
+                    is_jsx: false,
+                })
+            };
+            Ok(Some(PluginSubModules {
+                content: js(format!(
+                    "import './{0}?type=style';\nimport template from './{0}?type=template';\n{1}",
+                    param.file.pathname.file_name().unwrap().to_string_lossy(),
+                    block("script")
+                )),
+                sub_modules: vec![
+                    ("type=style".to_string(), Content::Css(block("style"))),
+                    (
+                        "?type=template".to_string(),
+                        js(format!("export default {:?};", block("template"))),
+                    ),
+                ],
+            }))
+        }
+    }
+
+    fn setup(name: &str, watch: bool) -> (TempDir, Compiler) {
+        let root = TempDir::new(name);
+        fs::write(root.join("index.js"), "import './App.sfc';").unwrap();
+        write_sfc(&root, "hello", "red");
+        let config = Config::new(&root, None, Some(r#"{"mode":"development"}"#)).unwrap();
+        let compiler = Compiler::new(
+            config,
+            root.to_path_buf(),
+            Args { watch },
+            Some(vec![Arc::new(SfcPlugin {})]),
+        )
+        .unwrap();
+        compiler.compile().unwrap();
+        (root, compiler)
+    }
+
+    fn write_sfc(root: &Path, text: &str, color: &str) {
+        fs::write(
+            root.join("App.sfc"),
+            format!(
+                "<script>\nconsole.log(template);\n</script>\n<style>\n.app {{ color: {color}; }}\n</style>\n<template>\n<div class=\"app\">{text}</div>\n</template>\n"
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_sub_modules() {
+        let (root, compiler) = setup("sub-modules", false);
+        let js = fs::read_to_string(root.join("dist/index.js")).unwrap();
+        assert!(js.contains(r#"<div class=\"app\">hello</div>"#), "{}", js);
+        let css = fs::read_to_string(root.join("dist/index.css")).unwrap();
+        assert!(css.contains("color: red"), "{}", css);
+
+        // the style is a css module in the graph
+        let style = format!("{}?type=style", root.join("App.sfc").to_string_lossy());
+        assert!(compiler
+            .context
+            .module_graph
+            .read()
+            .unwrap()
+            .has_module(&ModuleId::new(style)));
+    }
+
+    #[test]
+    fn test_sub_modules_update() {
+        let (root, compiler) = setup("sub-modules-update", true);
+        let sfc = root.join("App.sfc").to_string_lossy().to_string();
+
+        write_sfc(&root, "world", "blue");
+        let result = compiler.update(vec![root.join("App.sfc")]).unwrap();
+        // the script is unchanged, so the file itself is skipped
+        assert!(!result.modified.contains(&ModuleId::new(sfc.clone())));
+        for id in [format!("{sfc}?type=style"), format!("{sfc}?type=template")] {
+            assert!(
+                result.modified.contains(&ModuleId::new(id.clone())),
+                "{} {:?}",
+                id,
+                result.modified
+            );
+        }
+        compiler.emit_dev_chunks(1, 0).unwrap();
+        let js = fs::read_to_string(root.join("dist/index.js")).unwrap();
+        assert!(js.contains(r#"<div class=\"app\">world</div>"#), "{}", js);
+        let css = fs::read_to_string(root.join("dist/index.css")).unwrap();
+        assert!(css.contains("color: blue"), "{}", css);
+    }
+}
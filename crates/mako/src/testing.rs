@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::compiler::{Args, Compiler};
+use crate::config::Config;
+use crate::diagnostics::Diagnostic;
+use crate::generate::chunk::ChunkType;
+use crate::utils::output_fs::MemoryFileSystem;
+
+static TEMP_DIR_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary dir of a test, which is unique to the test, so the tests can
+/// run at the same time, and removed when it's dropped. For the tests which
+/// change the files between the builds, e.g. of the watch mode, the others
+/// use [build_fixture] or the fixtures of `test/build`.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "mako-{}-{}-{}",
+            name,
+            std::process::id(),
+            TEMP_DIR_INDEX.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The outputs of [build_fixture], the paths are relative to the root of the
+/// fixture, and the ones in the messages of the diagnostics too, so that
+/// they can be compared with the snapshots.
+#[derive(Serialize, Debug)]
+pub struct BuiltArtifacts {
+    /// file name relative to the output path -> content
+    pub assets: BTreeMap<String, Vec<u8>>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// module id -> the ids of its dependencies in the order of the imports
+    pub modules: BTreeMap<String, Vec<String>>,
+    /// sorted by the file names
+    pub chunks: Vec<ChunkDescription>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct ChunkDescription {
+    pub file_name: String,
+    /// entry, async, sync, worker or runtime
+    pub kind: String,
+    pub modules: Vec<String>,
+}
+
+impl BuiltArtifacts {
+    pub fn asset(&self, name: &str) -> Option<String> {
+        self.assets
+            .get(name)
+            .map(|content| String::from_utf8_lossy(content).to_string())
+    }
+
+    pub fn chunk(&self, file_name: &str) -> Option<&ChunkDescription> {
+        self.chunks
+            .iter()
+            .find(|chunk| chunk.file_name == file_name)
+    }
+}
+
+/// Write the inline files of a fixture to `root`, e.g. a [TempDir].
+pub fn write_files(root: &Path, files: &[(&str, &str)]) -> Result<()> {
+    for (path, content) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+/// Build the project of `root` with the whole pipeline and return the
+/// compiler for the assertions of its context, the outputs are emitted to the
+/// memory. `root` is relative to the crate, e.g. `test/build/entrypoints`, or
+/// absolute, e.g. a [TempDir]. `config_overrides` is the json which overrides
+/// the config, the same as the one of the cli.
+pub fn compile_fixture(root: &Path, config_overrides: Option<&str>) -> Result<Compiler> {
+    let root = std::env::current_dir()?.join(root);
+    let config = Config::new(&root, None, config_overrides)?;
+    let compiler = Compiler::new_with_output_fs(
+        config,
+        root,
+        Args { watch: false },
+        None,
+        Some(Arc::new(MemoryFileSystem::new())),
+    )?;
+    compiler.compile()?;
+    Ok(compiler)
+}
+
+/// Build a project of the inline files with the whole pipeline, e.g.
+/// `build_fixture(Some(r#"{"mode":"production"}"#), &[("src/index.ts", "...")])`.
+///
+/// The files are written to a [TempDir], since the resolver reads them from
+/// the disk, and built with [compile_fixture], a `mako.config.json` in the
+/// files is read too. The file names of the production have the content
+/// hashes, set `"hash": false` for the snapshots of them.
+pub fn build_fixture(
+    config_overrides: Option<&str>,
+    files: &[(&str, &str)],
+) -> Result<BuiltArtifacts> {
+    let temp_dir = TempDir::new("fixture");
+    let root = temp_dir.to_path_buf();
+    write_files(&root, files)?;
+    // canonicalized as the module ids, e.g. /tmp is a link on macOS
+    let root = root.canonicalize()?;
+
+    let compiler = compile_fixture(&root, config_overrides)?;
+
+    let output_fs = &compiler.context.output_fs;
+    let output = &compiler.context.config.output.path;
+    let assets = output_fs
+        .list(output)?
+        .into_iter()
+        .map(|path| {
+            let content = output_fs.read(&path)?;
+            Ok((relative(&path, output), content))
+        })
+        .collect::<Result<_>>()?;
+
+    let prefix = format!("{}{}", root.to_string_lossy(), std::path::MAIN_SEPARATOR);
+    let relative_id = |id: &str| id.strip_prefix(&prefix).unwrap_or(id).to_string();
+
+    let diagnostics = compiler
+        .context
+        .diagnostics
+        .get_reported()
+        .into_iter()
+        .map(|mut diagnostic| {
+            diagnostic.message = diagnostic.message.replace(&prefix, "");
+            diagnostic.paths = diagnostic
+                .paths
+                .iter()
+                .map(|path| PathBuf::from(relative(path, &root)))
+                .collect();
+            diagnostic.locations = diagnostic
+                .locations
+                .iter()
+                .map(|(path, line)| (PathBuf::from(relative(path, &root)), *line))
+                .collect();
+            diagnostic
+        })
+        .collect();
+
+    let module_graph = compiler.context.module_graph.read().unwrap();
+    let modules = module_graph
+        .get_module_ids()
+        .iter()
+        .map(|id| {
+            let dependencies = module_graph
+                .get_dependencies(id)
+                .iter()
+                .map(|(dependency, _)| relative_id(&dependency.id))
+                .collect();
+            (relative_id(&id.id), dependencies)
+        })
+        .collect();
+
+    let chunk_graph = compiler.context.chunk_graph.read().unwrap();
+    let mut chunks = chunk_graph
+        .get_all_chunks()
+        .iter()
+        .map(|chunk| ChunkDescription {
+            file_name: chunk.filename(),
+            kind: match chunk.chunk_type {
+                ChunkType::Runtime => "runtime",
+                ChunkType::Entry(..) => "entry",
+                ChunkType::Async => "async",
+                ChunkType::Sync => "sync",
+                ChunkType::Worker(_) => "worker",
+            }
+            .to_string(),
+            modules: chunk.modules.iter().map(|id| relative_id(&id.id)).collect(),
+        })
+        .collect::<Vec<_>>();
+    chunks.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+    Ok(BuiltArtifacts {
+        assets,
+        diagnostics,
+        modules,
+        chunks,
+    })
+}
+
+fn relative(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::DiagnosticCode;
+
+    #[test]
+    fn test_build_fixture() {
+        let artifacts = build_fixture(
+            Some(r#"{"mode":"production","moduleIdStrategy":"named","minify":false,"hash":false}"#),
+            &[
+                (
+                    "src/index.ts",
+                    "import { a } from './a';\nimport('./lazy');\nconsole.log(a);",
+                ),
+                ("src/a.ts", "export const a = 'fixture a';"),
+                ("src/lazy.ts", "export default 'fixture lazy';"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            artifacts.modules.get("src/index.ts").unwrap(),
+            &vec!["src/a.ts".to_string(), "src/lazy.ts".to_string()]
+        );
+        assert_eq!(
+            artifacts
+                .chunks
+                .iter()
+                .map(|chunk| (chunk.file_name.as_str(), chunk.kind.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("index.js", "entry"), ("src_lazy_ts-async.js", "async")]
+        );
+        assert_eq!(
+            artifacts.chunk("src_lazy_ts-async.js").unwrap().modules,
+            vec!["src/lazy.ts"]
+        );
+        assert!(artifacts.asset("index.js").unwrap().contains("fixture a"));
+        assert!(artifacts.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_build_fixture_diagnostics() {
+        let artifacts = build_fixture(
+            None,
+            &[
+                ("src/index.js", "import './a';"),
+                ("src/a.js", "import './b';"),
+                ("src/b.js", "import './a';"),
+                (
+                    "mako.config.json",
+                    r#"{"experimental":{"detectCircularDependence":{"ignores":[],"graphviz":false}}}"#,
+                ),
+            ],
+        )
+        .unwrap();
+
+        let diagnostic = &artifacts.diagnostics[0];
+        assert_eq!(diagnostic.code, DiagnosticCode::Circular);
+        assert_eq!(
+            diagnostic.message,
+            r#"Circular Dependencies: "./src/a.js" -> "./src/b.js" -> "./src/a.js""#
+        );
+        assert_eq!(
+            diagnostic.paths,
+            vec![PathBuf::from("src/a.js"), PathBuf::from("src/b.js")]
+        );
+    }
+}
@@ -1,7 +1,15 @@
+pub mod chunk_budget;
+pub mod file_handles;
+pub mod file_request;
+pub mod hash;
 pub(crate) mod id_helper;
+pub mod interner;
 pub mod logger;
+pub mod memory;
+pub mod output_fs;
 #[cfg(feature = "profile")]
 pub mod profile_gui;
+pub mod remote_assets;
 #[cfg(test)]
 pub(crate) mod test_helper;
 pub mod thread_pool;
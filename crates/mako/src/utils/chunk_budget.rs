@@ -0,0 +1,174 @@
+use tracing::debug;
+
+// the asts, the minifier and the source maps of a chunk take many times the
+// memory of its code
+const MEMORY_PER_CHUNK_BYTE: usize = 32;
+const MIN_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+const DEFAULT_CHUNK_BYTES: usize = 64 * 1024 * 1024;
+
+/// Caps the bytes of the chunks which are generated and minified at the same
+/// time, so that the memory of a build with several large chunks doesn't
+/// spike past the limit of the container.
+pub struct ChunkBytesBudget {
+    budget: usize,
+}
+
+impl ChunkBytesBudget {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget: budget.max(1),
+        }
+    }
+
+    /// Sized from `maxInFlightChunkBytes`, or the available memory if it's
+    /// not configured.
+    pub fn from_config(max_in_flight_chunk_bytes: Option<usize>) -> Self {
+        let budget = max_in_flight_chunk_bytes.unwrap_or_else(|| match available_memory() {
+            Some(available) => (available / MEMORY_PER_CHUNK_BYTE).max(MIN_CHUNK_BYTES),
+            None => DEFAULT_CHUNK_BYTES,
+        });
+        debug!("in-flight chunk bytes budget: {}", budget);
+        Self::new(budget)
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Split the chunks of the sizes into the batches which are generated one
+    /// after another, the chunks of a batch are generated in parallel. The
+    /// largest chunks are placed first and the smaller ones fill the rest of
+    /// the budget of the batches, a chunk over the budget is generated alone.
+    /// All the chunks are in one batch if they fit in the budget.
+    ///
+    /// Returns the indexes of the sizes, in the order of the sizes in each
+    /// batch.
+    pub fn batches(&self, sizes: &[usize]) -> Vec<Vec<usize>> {
+        if sizes.iter().sum::<usize>() <= self.budget {
+            return vec![(0..sizes.len()).collect()];
+        }
+        let mut indexes = (0..sizes.len()).collect::<Vec<_>>();
+        // stable, the chunks of the same size keep their order
+        indexes.sort_by(|a, b| sizes[*b].cmp(&sizes[*a]));
+
+        let mut batches: Vec<(usize, Vec<usize>)> = vec![];
+        for index in indexes {
+            let size = sizes[index];
+            match batches
+                .iter_mut()
+                .find(|(bytes, _)| bytes + size <= self.budget)
+            {
+                Some((bytes, batch)) => {
+                    *bytes += size;
+                    batch.push(index);
+                }
+                None => batches.push((size, vec![index])),
+            }
+        }
+        batches
+            .into_iter()
+            .map(|(_, mut batch)| {
+                batch.sort();
+                batch
+            })
+            .collect()
+    }
+}
+
+impl Default for ChunkBytesBudget {
+    fn default() -> Self {
+        Self::from_config(None)
+    }
+}
+
+/// The bytes of the chunks of the largest batch, which are generated at the
+/// same time at most.
+pub fn peak_in_flight_bytes(batches: &[Vec<usize>], sizes: &[usize]) -> usize {
+    batches
+        .iter()
+        .map(|batch| batch.iter().map(|index| sizes[*index]).sum())
+        .max()
+        .unwrap_or(0)
+}
+
+// the available memory of the system, or the rest of the memory limit of the
+// cgroup if it's less, e.g. in a container of the CI
+#[cfg(target_os = "linux")]
+fn available_memory() -> Option<usize> {
+    let read = |path: &str| std::fs::read_to_string(path).ok();
+    let system = read("/proc/meminfo").and_then(|meminfo| {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix("MemAvailable:"))
+            .and_then(|kb| {
+                kb.trim()
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+            })
+            .map(|kb| kb * 1024)
+    });
+    let cgroup = [
+        // cgroup v2
+        ("/sys/fs/cgroup/memory.max", "/sys/fs/cgroup/memory.current"),
+        // cgroup v1
+        (
+            "/sys/fs/cgroup/memory/memory.limit_in_bytes",
+            "/sys/fs/cgroup/memory/memory.usage_in_bytes",
+        ),
+    ]
+    .into_iter()
+    .find_map(|(limit, usage)| {
+        // `max` if it's unlimited
+        let limit = read(limit)?.trim().parse::<usize>().ok()?;
+        let usage = read(usage)?.trim().parse::<usize>().ok()?;
+        Some(limit.saturating_sub(usage))
+    });
+    match (system, cgroup) {
+        (Some(system), Some(cgroup)) => Some(system.min(cgroup)),
+        (system, cgroup) => system.or(cgroup),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn available_memory() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches() {
+        let budget = ChunkBytesBudget::new(10);
+        // within the budget
+        assert_eq!(budget.batches(&[3, 2, 5]), vec![vec![0, 1, 2]]);
+        assert_eq!(budget.batches(&[]), vec![Vec::<usize>::new()]);
+        // the largest first, the smaller ones fill the rest
+        assert_eq!(
+            budget.batches(&[2, 8, 6, 3, 1]),
+            vec![vec![0, 1], vec![2, 3, 4]]
+        );
+        // over the budget alone
+        assert_eq!(budget.batches(&[4, 30, 5]), vec![vec![1], vec![0, 2]]);
+    }
+
+    #[test]
+    fn test_peak_in_flight_bytes() {
+        let budget = ChunkBytesBudget::new(10);
+        let sizes = [2, 8, 6, 3, 1];
+        let batches = budget.batches(&sizes);
+        assert_eq!(peak_in_flight_bytes(&batches, &sizes), 10);
+        let sizes = [4, 30, 5];
+        let batches = budget.batches(&sizes);
+        assert_eq!(peak_in_flight_bytes(&batches, &sizes), 30);
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert_eq!(ChunkBytesBudget::from_config(Some(1024)).budget(), 1024);
+        assert!(ChunkBytesBudget::from_config(None).budget() >= MIN_CHUNK_BYTES);
+    }
+}
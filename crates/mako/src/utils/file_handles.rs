@@ -0,0 +1,178 @@
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use std::{io, thread};
+
+use anyhow::Result;
+use tracing::debug;
+
+// leave room for the sockets, watchers and dylibs opened by others
+const RESERVED_FILE_HANDLES: usize = 64;
+const MIN_FILE_HANDLES: usize = 8;
+const DEFAULT_FILE_HANDLES: usize = 512;
+const MAX_RETRIES: u32 = 6;
+const RETRY_DELAY_MS: u64 = 10;
+
+/// Caps the files opened concurrently by the parallel build, and retries the
+/// operations failed with `EMFILE` or `ENFILE` instead of failing the module.
+pub struct FileHandleLimiter {
+    limit: usize,
+    open: Mutex<usize>,
+    released: Condvar,
+}
+
+impl FileHandleLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            open: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Sized from `maxOpenFiles`, or half of the soft `ulimit -n` if it's not
+    /// configured.
+    pub fn from_config(max_open_files: Option<usize>) -> Self {
+        let limit = max_open_files.unwrap_or_else(|| match soft_file_limit() {
+            Some(soft) => (soft.saturating_sub(RESERVED_FILE_HANDLES) / 2).max(MIN_FILE_HANDLES),
+            None => DEFAULT_FILE_HANDLES,
+        });
+        debug!("file handle limit: {}", limit);
+        Self::new(limit)
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn run<T, F>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        let _permit = self.acquire();
+        let mut retries = 0;
+        loop {
+            match op() {
+                Err(e) if is_too_many_open_files(&e) && retries < MAX_RETRIES => {
+                    debug!("too many open files, retry {}: {}", retries + 1, e);
+                    thread::sleep(Duration::from_millis(RETRY_DELAY_MS << retries));
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn acquire(&self) -> Permit<'_> {
+        let mut open = self.open.lock().unwrap();
+        while *open >= self.limit {
+            open = self.released.wait(open).unwrap();
+        }
+        *open += 1;
+        Permit { limiter: self }
+    }
+}
+
+impl Default for FileHandleLimiter {
+    fn default() -> Self {
+        Self::from_config(None)
+    }
+}
+
+struct Permit<'a> {
+    limiter: &'a FileHandleLimiter,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.limiter.open.lock().unwrap() -= 1;
+        self.limiter.released.notify_one();
+    }
+}
+
+fn is_too_many_open_files(e: &anyhow::Error) -> bool {
+    // EMFILE and ENFILE
+    e.downcast_ref::<io::Error>()
+        .is_some_and(|e| matches!(e.raw_os_error(), Some(24) | Some(23)))
+}
+
+#[cfg(unix)]
+fn soft_file_limit() -> Option<usize> {
+    let mut rlim = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit only writes to the given rlimit
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlim) } != 0 {
+        return None;
+    }
+    if rlim.rlim_cur == libc::RLIM_INFINITY {
+        return None;
+    }
+    Some(rlim.rlim_cur as usize)
+}
+
+#[cfg(not(unix))]
+fn soft_file_limit() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use std::{io, thread};
+
+    use super::FileHandleLimiter;
+
+    #[test]
+    fn test_limit_concurrent_operations() {
+        let limiter = FileHandleLimiter::new(3);
+        let open = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..16 {
+                s.spawn(|| {
+                    limiter
+                        .run(|| {
+                            let current = open.fetch_add(1, Ordering::SeqCst) + 1;
+                            peak.fetch_max(current, Ordering::SeqCst);
+                            thread::sleep(Duration::from_millis(5));
+                            open.fetch_sub(1, Ordering::SeqCst);
+                            Ok(())
+                        })
+                        .unwrap();
+                });
+            }
+        });
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn test_retry_too_many_open_files() {
+        let limiter = FileHandleLimiter::new(1);
+        let mut attempts = 0;
+        let result = limiter.run(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(io::Error::from_raw_os_error(24).into())
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut attempts = 0;
+        let result: anyhow::Result<()> = limiter.run(|| {
+            attempts += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound).into())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert_eq!(FileHandleLimiter::from_config(Some(16)).limit(), 16);
+        assert!(FileHandleLimiter::from_config(None).limit() >= 8);
+    }
+}
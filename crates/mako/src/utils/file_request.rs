@@ -0,0 +1,301 @@
+use std::fmt;
+
+use anyhow::Result;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use url::{form_urlencoded, Url};
+
+/// A module request split into the path, the query and the fragment, e.g.
+/// `./a.css?modules#b`, which is shared by the loading of the files and the
+/// naming of the chunks so that the queries are parsed the same everywhere.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileRequest {
+    pub path: String,
+    // without the leading `?`
+    pub search: String,
+    pub params: Vec<(String, String)>,
+    pub fragment: Option<String>,
+}
+
+impl FileRequest {
+    pub fn parse(path: &str) -> Result<Self> {
+        let path = win_path(path);
+        // only the unencoded `?` is the query, `%3F` is a `?` of the path, see
+        // `encode_path`
+        if path.contains('?') || has_hash_without_dot(path.as_str()) {
+            let (path, rest) = if path.contains('?') {
+                let (path, search) = path.split_once('?').unwrap_or((path.as_str(), ""));
+                (path, format!("?{}", search))
+            } else {
+                let (path, fragment) = path.split_once('#').unwrap_or((path.as_str(), ""));
+                (path, format!("#{}", fragment))
+            };
+            let base = "http://a.com/";
+            let base_url = Url::parse(base)?;
+            let full_url = base_url.join(rest.as_str())?;
+            let fragment = full_url.fragment().map(|s| s.to_string());
+            let search = full_url.query().unwrap_or("").to_string();
+            let params = full_url
+                .query_pairs()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            // dir or filename may contains space or other special characters
+            // so we need to decode it, e.g. "a%20b" -> "a b"
+            let path = percent_decode_str(path).decode_utf8()?;
+            Ok(Self {
+                path: path.to_string(),
+                search,
+                params,
+                fragment,
+            })
+        } else {
+            let path = percent_decode_str(&path).decode_utf8()?;
+            Ok(Self {
+                path: path.to_string(),
+                ..Default::default()
+            })
+        }
+    }
+
+    pub fn has_param(&self, key: &str) -> bool {
+        self.params.iter().any(|(k, _)| k == key)
+    }
+
+    /// The query with the keys sorted, so that `?b=1&a=2` and `?a=2&b=1` are
+    /// the same query, the values of a repeated key keep their order.
+    pub fn normalized_query(&self) -> String {
+        normalize_query(&self.params)
+    }
+}
+
+/// The request which [FileRequest::parse] parses back, with the path
+/// encoded, e.g. `a%3Fb.css?modules#c` of the path `a?b.css`.
+impl fmt::Display for FileRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", encode_path(&self.path))?;
+        if !self.search.is_empty() {
+            write!(f, "?{}", self.search)?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+/// The path of a file in the requests and the module ids, with `%`, `?` and
+/// `#` encoded so that they're not taken as the escapes, the query or the
+/// fragment, e.g. `a?b.js` is `a%3Fb.js` and `a%3Fb.js` is `a%253Fb.js`. The
+/// spaces and the unicode of the file names are kept.
+pub fn encode_path(path: &str) -> String {
+    let path = win_path(path);
+    let mut encoded = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '%' => encoded.push_str("%25"),
+            '?' => encoded.push_str("%3F"),
+            '#' => encoded.push_str("%23"),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+/// The path of the file on the disk of an encoded path, see [encode_path].
+pub fn decode_path(path: &str) -> String {
+    percent_decode_str(path).decode_utf8_lossy().to_string()
+}
+
+/// The query of the pairs with the keys sorted, see [FileRequest::normalized_query].
+pub fn normalize_query(params: &[(String, String)]) -> String {
+    let mut params = params.to_vec();
+    params.sort_by(|(a, _), (b, _)| a.cmp(b));
+    form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish()
+}
+
+// the delimiters of the query, `%` of the escapes and `+` of the spaces
+pub(crate) const QUERY_COMPONENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'+')
+    .add(b'=');
+
+/// The query of a module id, so that the requests of the same query in the
+/// other orders or encodings are the same module, e.g. `width=24&raw=` and
+/// `raw&width=%32%34` are both `raw&width=24`. The keys are sorted, the values
+/// of a repeated key keep their order, the pairs are percent-decoded and only
+/// the delimiters are encoded again, and the empty values are the bare keys.
+pub fn normalize_id_query(query: &str) -> String {
+    let mut params = form_urlencoded::parse(query.as_bytes()).collect::<Vec<_>>();
+    params.sort_by(|(a, _), (b, _)| a.cmp(b));
+    params
+        .iter()
+        .map(|(key, value)| {
+            let key = utf8_percent_encode(key, QUERY_COMPONENT);
+            if value.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}={}", key, utf8_percent_encode(value, QUERY_COMPONENT))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn has_hash_without_dot(input: &str) -> bool {
+    if let Some(pos) = input.find('#') {
+        let after_hash = &input[pos + 1..];
+        !after_hash.contains('.')
+    } else {
+        false
+    }
+}
+
+pub fn win_path(path: &str) -> String {
+    #[cfg(target_os = "windows")]
+    let path = {
+        let prefix = "\\\\?\\";
+        let path = path.trim_start_matches(prefix);
+        path.replace('\\', "/")
+    };
+    #[cfg(not(target_os = "windows"))]
+    let path = path.to_string();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_support_windows() {
+        let request = FileRequest::parse("C:\\a\\b\\c?foo").unwrap();
+        assert_eq!(request.path, "C:\\a\\b\\c");
+        assert_eq!(request.search, "foo");
+        assert_eq!(request.params, vec![("foo".to_string(), "".to_string())]);
+        assert_eq!(request.fragment, None);
+    }
+
+    #[test]
+    fn test_parse_with_fragment() {
+        assert_eq!(FileRequest::parse("foo.ts#bar").unwrap().path, "foo.ts");
+        assert_eq!(FileRequest::parse("foo#bar.ts").unwrap().path, "foo#bar.ts");
+    }
+
+    #[test]
+    fn test_normalized_query() {
+        let query = |path: &str| FileRequest::parse(path).unwrap().normalized_query();
+        assert_eq!(query("a.js?b=1&a=2"), query("a.js?a=2&b=1"));
+        assert_eq!(query("a.js?b=1&a=2&a=3"), "a=2&a=3&b=1");
+        assert_ne!(query("a.js?locale=zh-CN"), query("a.js?locale=zh-TW"));
+        assert_eq!(query("a.js"), "");
+    }
+
+    #[test]
+    fn test_normalize_id_query() {
+        assert_eq!(
+            normalize_id_query("width=24&height=24"),
+            "height=24&width=24"
+        );
+        assert_eq!(
+            normalize_id_query("width=24&height=24"),
+            normalize_id_query("height=24&width=24")
+        );
+        // the empty values
+        assert_eq!(normalize_id_query("raw=&width=24"), "raw&width=24");
+        assert_eq!(normalize_id_query("width=24&&raw"), "raw&width=24");
+        // the percent-decoding
+        assert_eq!(
+            normalize_id_query("width=%32%34&name=a%2Fb"),
+            "name=a/b&width=24"
+        );
+        assert_eq!(normalize_id_query("q=a+b&r=a%20b"), "q=a%20b&r=a%20b");
+        assert_eq!(normalize_id_query("q=1%2B1%3D2%26"), "q=1%2B1%3D2%26");
+        // the values of a repeated key keep their order
+        assert_eq!(normalize_id_query("b&a=2&a=1"), "a=2&a=1&b");
+        assert_eq!(normalize_id_query(""), "");
+        let query = "modules&type=style&lang=css";
+        let normalized = normalize_id_query(query);
+        assert_eq!(normalize_id_query(&normalized), normalized);
+    }
+
+    const PATHS: [&str; 8] = [
+        "/root/src/a.js",
+        "/root/src/a?b.js",
+        "/root/src/a%3Fb.js",
+        "/root/src/a#b.js",
+        "/root/src/with space.js",
+        "/root/src/中文/文件.js",
+        "/root/src/100%.js",
+        "/root/src/a%20b?c#d.js",
+    ];
+    const SEARCHES: [&str; 4] = ["", "raw", "modules&type=style", "q=a%20b"];
+    const FRAGMENTS: [Option<&str>; 2] = [None, Some("top")];
+
+    // every combination of the paths, the queries and the fragments
+    fn for_each_request(f: impl Fn(FileRequest)) {
+        for path in PATHS {
+            for search in SEARCHES {
+                for fragment in FRAGMENTS {
+                    f(FileRequest {
+                        path: path.to_string(),
+                        search: search.to_string(),
+                        params: form_urlencoded::parse(search.as_bytes())
+                            .map(|(k, v)| (k.to_string(), v.to_string()))
+                            .collect(),
+                        fragment: fragment.map(|s| s.to_string()),
+                    });
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_format_round_trip() {
+        for_each_request(|request| {
+            let formatted = request.to_string();
+            assert_eq!(
+                FileRequest::parse(&formatted).unwrap(),
+                request,
+                "{}",
+                formatted
+            );
+            // the query is after the only unencoded `?`
+            assert_eq!(
+                formatted.matches('?').count(),
+                usize::from(!request.search.is_empty()),
+                "{}",
+                formatted
+            );
+            assert!(!encode_path(&request.path).contains(['?', '#']));
+        });
+    }
+
+    #[test]
+    fn test_encode_path() {
+        assert_eq!(encode_path("/a?b.js"), "/a%3Fb.js");
+        assert_eq!(encode_path("/a%3Fb.js"), "/a%253Fb.js");
+        assert_eq!(encode_path("/a#b.js"), "/a%23b.js");
+        assert_eq!(encode_path("/with space/中文.js"), "/with space/中文.js");
+        for path in PATHS {
+            assert_eq!(decode_path(&encode_path(path)), path);
+        }
+        // the real file name with the escapes
+        assert_eq!(
+            FileRequest::parse(&encode_path("/a%3Fb.js")).unwrap().path,
+            "/a%3Fb.js"
+        );
+    }
+
+    #[test]
+    fn test_has_hash_without_dot() {
+        assert!(has_hash_without_dot("foo.ts#world"));
+        assert!(!has_hash_without_dot("foo#bar.ts"));
+        assert!(has_hash_without_dot("#no_dot"));
+        assert!(!has_hash_without_dot("no_hash"));
+        assert!(!has_hash_without_dot("#.dot_after_hash"));
+    }
+}
@@ -0,0 +1,235 @@
+use std::fmt::Write;
+use std::hash::Hasher;
+
+use md5::Digest;
+use sha2::Digest as _;
+use twox_hash::XxHash64;
+
+use crate::config::{Config, HashFunction, OutputConfig};
+
+/// Hashes the content incrementally with the hash function of the file names.
+pub enum ContentHasher {
+    Md5(md5::Context),
+    Xxhash(XxHash64),
+    Sha256(sha2::Sha256),
+}
+
+impl ContentHasher {
+    pub fn new(function: HashFunction) -> Self {
+        match function {
+            HashFunction::Md5 => ContentHasher::Md5(md5::Context::new()),
+            HashFunction::Xxhash => ContentHasher::Xxhash(Default::default()),
+            HashFunction::Sha256 => ContentHasher::Sha256(sha2::Sha256::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            ContentHasher::Md5(context) => context.consume(data),
+            ContentHasher::Xxhash(hasher) => hasher.write(data),
+            ContentHasher::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        match self {
+            ContentHasher::Md5(context) => {
+                let Digest(bytes) = context.compute();
+                bytes.to_vec()
+            }
+            ContentHasher::Xxhash(hasher) => hasher.finish().to_be_bytes().to_vec(),
+            ContentHasher::Sha256(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+
+    /// The hex digest truncated to output.hashLength.
+    pub fn finish_hex(self, output: &OutputConfig) -> String {
+        self.finish_hex_truncated(output.hash_length)
+    }
+
+    pub fn finish_hex_truncated(self, length: usize) -> String {
+        let mut hash = self.finish().iter().fold(String::new(), |mut hash, byte| {
+            let _ = write!(hash, "{:02x}", byte);
+            hash
+        });
+        hash.truncate(length);
+        hash
+    }
+}
+
+/// The hash of the content in the file names, e.g. `index.[hash].js`.
+pub fn content_hash<T: AsRef<[u8]>>(content: T, output: &OutputConfig) -> String {
+    let mut hasher = ContentHasher::new(output.hash_function);
+    hasher.update(content.as_ref());
+    hasher.finish_hex(output)
+}
+
+/// The hash of a chunk in the file names, the codegen salt is folded in so
+/// that the chunks are renamed when the code generation changes.
+pub fn chunk_content_hash<T: AsRef<[u8]>>(content: T, salt: u64, output: &OutputConfig) -> String {
+    let mut hasher = ContentHasher::new(output.hash_function);
+    hasher.update(&salt.to_be_bytes());
+    hasher.update(content.as_ref());
+    hasher.finish_hex(output)
+}
+
+/// Everything besides the modules which changes the generated chunks, the
+/// bundler version, the runtime template, the minify settings and the output
+/// format, e.g. the runtime helpers are renamed after an upgrade while no
+/// module is changed.
+pub fn codegen_salt(config: &Config, version: &str) -> u64 {
+    let mut hasher: XxHash64 = Default::default();
+    hasher.write(version.as_bytes());
+    hasher.write(include_str!("../../templates/app_runtime.stpl").as_bytes());
+    let settings = serde_json::json!({
+        "mode": config.mode,
+        "minify": config.minify,
+        "outputMode": config.output.mode,
+        "umd": config.umd,
+        "cjs": config.cjs,
+        "platform": config.platform,
+    });
+    hasher.write(settings.to_string().as_bytes());
+    hasher.finish()
+}
+
+/// The hash of a query in the async chunk names, e.g. `foo_ts_q_0cc175b9-async.js`,
+/// the query should be normalized, see [FileRequest::normalized_query].
+///
+/// [FileRequest::normalized_query]: crate::utils::file_request::FileRequest::normalized_query
+pub fn query_hash(query: &str, function: HashFunction, length: usize) -> String {
+    let mut hasher = ContentHasher::new(function);
+    hasher.update(query.as_bytes());
+    hasher.finish_hex_truncated(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use regex::Regex;
+
+    use super::{codegen_salt, content_hash, query_hash};
+    use crate::compiler::Compiler;
+    use crate::config::{Config, HashFunction};
+    use crate::utils::output_fs::{MemoryFileSystem, OutputFileSystem};
+
+    #[test]
+    fn test_content_hash() {
+        let mut output = Config::default().output;
+        // the md5 hashes of the previous versions
+        assert_eq!(content_hash("a", &output), "0cc175b9");
+        assert_eq!(query_hash("a", HashFunction::Md5, 8), "0cc175b9");
+
+        output.hash_function = HashFunction::Sha256;
+        output.hash_length = 16;
+        assert_eq!(content_hash("a", &output), "ca978112ca1bbdca");
+
+        output.hash_function = HashFunction::Xxhash;
+        assert_eq!(content_hash("a", &output).len(), 16);
+        assert_ne!(content_hash("a", &output), content_hash("b", &output));
+    }
+
+    #[test]
+    fn test_query_hash_collision() {
+        let mut hashes = std::collections::HashSet::new();
+        for locale in ["zh-CN", "zh-TW", "zh-HK", "en-US", "en-GB"] {
+            for version in 0..200 {
+                let query = format!("locale={}&v={}", locale, version);
+                assert!(
+                    hashes.insert(query_hash(&query, HashFunction::Md5, 8)),
+                    "{}",
+                    query
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash_function() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/hash-function");
+        let config = Config::new(&root, None, None).unwrap();
+        let compiler = Compiler::new(config, root.clone(), Default::default(), None).unwrap();
+        compiler.compile().unwrap();
+
+        let mut files = std::fs::read_dir(root.join("dist"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| !name.ends_with(".map"))
+            .collect::<Vec<_>>();
+        files.sort();
+        let re = Regex::new(r"^[\w-]+\.[0-9a-f]{16}\.(js|png)$").unwrap();
+        assert_eq!(files.len(), 3, "{:?}", files);
+        assert!(files.iter().all(|name| re.is_match(name)), "{:?}", files);
+        let logo = files.iter().find(|name| name.starts_with("logo.")).unwrap();
+        let output = &compiler.context.config.output;
+        assert_eq!(
+            logo,
+            &format!(
+                "logo.{}.png",
+                content_hash(std::fs::read(root.join("logo.png")).unwrap(), output)
+            )
+        );
+    }
+
+    #[test]
+    fn test_codegen_salt() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .join("test/build/hash-function");
+        // the file names and the module ids of a build with the bundler version
+        let build = |version: &str| {
+            let config = Config::new(&root, None, None).unwrap();
+            let output_fs = Arc::new(MemoryFileSystem::new());
+            let mut compiler = Compiler::new_with_output_fs(
+                config,
+                root.clone(),
+                Default::default(),
+                None,
+                Some(output_fs.clone()),
+            )
+            .unwrap();
+            let context = Arc::get_mut(&mut compiler.context).unwrap();
+            context.codegen_salt = codegen_salt(&context.config, version);
+            compiler.compile().unwrap();
+
+            let output_path = &compiler.context.config.output.path;
+            let mut files = output_fs
+                .list(output_path)
+                .unwrap()
+                .iter()
+                .map(|path| {
+                    path.strip_prefix(output_path)
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .filter(|name| !name.ends_with(".map"))
+                .collect::<Vec<_>>();
+            files.sort();
+            let module_graph = compiler.context.module_graph.read().unwrap();
+            let mut module_ids = module_graph
+                .modules()
+                .iter()
+                .map(|module| module.id.generate(&compiler.context))
+                .collect::<Vec<_>>();
+            module_ids.sort();
+            (files, module_ids)
+        };
+
+        let (files, module_ids) = build("0.1.0");
+        let (bumped_files, bumped_module_ids) = build("0.2.0");
+        assert_eq!(module_ids, bumped_module_ids);
+        assert_eq!(files.len(), 3, "{:?}", files);
+        for (file, bumped) in files.iter().zip(bumped_files.iter()) {
+            if file.ends_with(".js") {
+                assert_ne!(file, bumped);
+            } else {
+                // the assets are hashed by the content only
+                assert_eq!(file, bumped);
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::cmp::Ordering;
+use std::collections::HashSet;
 
 use petgraph::Direction::Incoming;
 
@@ -27,3 +28,68 @@ pub fn assign_numeric_ids<T>(
         .enumerate()
         .for_each(|(i, item)| assign_id(item, i))
 }
+
+/// Assigns the ids from the md5 hashes of the names, which are truncated to
+/// min_length and extended on collisions. Items are sorted by name first, so
+/// an id only changes when a new name collides with it.
+pub fn assign_deterministic_ids<T>(
+    items: Vec<T>,
+    name: impl Fn(&T) -> String,
+    min_length: usize,
+    mut assign_id: impl FnMut(&T, String),
+) {
+    let mut items = items
+        .into_iter()
+        .map(|item| (name(&item), item))
+        .collect::<Vec<_>>();
+    items.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    let mut used_ids = HashSet::new();
+    for (name, item) in items {
+        let hash = format!("{:x}", md5::compute(&name));
+        let mut length = min_length.min(hash.len());
+        while length < hash.len() && used_ids.contains(&hash[..length]) {
+            length += 1;
+        }
+        let id = hash[..length].to_string();
+        used_ids.insert(id.clone());
+        assign_id(&item, id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::assign_deterministic_ids;
+
+    fn assign(names: &[&str]) -> HashMap<String, String> {
+        let mut ids = HashMap::new();
+        assign_deterministic_ids(
+            names.iter().map(|name| name.to_string()).collect(),
+            |name| name.clone(),
+            1,
+            |name, id| {
+                ids.insert(name.clone(), id);
+            },
+        );
+        ids
+    }
+
+    #[test]
+    fn test_assign_deterministic_ids() {
+        let names = (0..20).map(|i| format!("src/{}.js", i)).collect::<Vec<_>>();
+        let names = names.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+        let ids = assign(&names);
+        // 20 names can't be unique with 1 hex char, collisions are extended
+        assert_eq!(
+            ids.values().collect::<std::collections::HashSet<_>>().len(),
+            20
+        );
+        assert!(ids.values().any(|id| id.len() > 1));
+        // the order of the names doesn't matter
+        let mut reversed = names.clone();
+        reversed.reverse();
+        assert_eq!(assign(&reversed), ids);
+    }
+}
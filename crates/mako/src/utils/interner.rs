@@ -0,0 +1,266 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use serde::{Serialize, Serializer};
+
+use crate::utils::memory::CacheUsage;
+
+// the interner is pruned when it grows over the size, which is doubled then
+const MIN_PRUNE_SIZE: usize = 1024;
+
+/// The interned strings of a compiler, e.g. the ids of the modules added to
+/// the module graph, which are kept by the chunks, the caches and the
+/// dependencies of each other, so their clones share the allocations of the
+/// module graph.
+pub struct Interner {
+    strings: DashMap<Arc<str>, ()>,
+    prune_size: AtomicUsize,
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self {
+            strings: DashMap::new(),
+            prune_size: AtomicUsize::new(MIN_PRUNE_SIZE),
+        }
+    }
+}
+
+impl Interner {
+    /// The string with the allocation of the equal one interned before.
+    pub fn intern(&self, value: &InternedStr) -> InternedStr {
+        if let Some(entry) = self.strings.get(value.as_str()) {
+            return InternedStr(entry.key().clone());
+        }
+        let interned = self
+            .strings
+            .entry(value.0.clone())
+            .or_insert(())
+            .key()
+            .clone();
+        if self.strings.len() > self.prune_size.load(AtomicOrdering::Relaxed) {
+            self.prune();
+        }
+        InternedStr(interned)
+    }
+
+    /// Drop the strings which are only kept by the interner, e.g. the ids of
+    /// the modules removed in watch mode.
+    pub fn prune(&self) {
+        self.strings.retain(|value, _| Arc::strong_count(value) > 1);
+        self.prune_size.store(
+            (self.strings.len() * 2).max(MIN_PRUNE_SIZE),
+            AtomicOrdering::Relaxed,
+        );
+    }
+
+    /// The count and the bytes of the interned strings.
+    pub fn usage(&self) -> CacheUsage {
+        let bytes = self.strings.iter().map(|entry| entry.key().len()).sum();
+        CacheUsage::new("interned strings", self.strings.len(), bytes)
+    }
+}
+
+/// A string whose clones share one allocation, e.g. the module ids. The
+/// equal strings interned by the [Interner] of the compiler share the same
+/// allocation too, so the equality is mostly a pointer comparison, while the
+/// hash and the order are the ones of the content, so the hashes of the
+/// chunks are the same as with the `String`s.
+#[derive(Clone)]
+pub struct InternedStr(Arc<str>);
+
+impl InternedStr {
+    pub fn new(value: &str) -> Self {
+        Self(Arc::from(value))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for InternedStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for InternedStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for InternedStr {
+    fn as_ref(&self) -> &Path {
+        Path::new(&*self.0)
+    }
+}
+
+impl AsRef<OsStr> for InternedStr {
+    fn as_ref(&self) -> &OsStr {
+        OsStr::new(&*self.0)
+    }
+}
+
+impl Borrow<str> for InternedStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for InternedStr {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for InternedStr {}
+
+impl PartialEq<str> for InternedStr {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for InternedStr {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for InternedStr {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialEq<InternedStr> for str {
+    fn eq(&self, other: &InternedStr) -> bool {
+        self == &*other.0
+    }
+}
+
+impl PartialEq<InternedStr> for &str {
+    fn eq(&self, other: &InternedStr) -> bool {
+        *self == &*other.0
+    }
+}
+
+impl PartialEq<InternedStr> for String {
+    fn eq(&self, other: &InternedStr) -> bool {
+        **self == *other.0
+    }
+}
+
+impl Hash for InternedStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl Ord for InternedStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl PartialOrd for InternedStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Debug for InternedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for InternedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for InternedStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl From<&str> for InternedStr {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for InternedStr {
+    fn from(value: String) -> Self {
+        Self::new(&value)
+    }
+}
+
+impl From<InternedStr> for String {
+    fn from(value: InternedStr) -> Self {
+        value.0.to_string()
+    }
+}
+
+impl From<InternedStr> for PathBuf {
+    fn from(value: InternedStr) -> Self {
+        PathBuf::from(&*value.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::*;
+
+    fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_interned_str() {
+        let interner = Interner::default();
+        let a = interner.intern(&InternedStr::new("/root/src/interned.ts"));
+        let b = interner.intern(&InternedStr::from("/root/src/interned.ts".to_string()));
+        assert!(Arc::ptr_eq(&a.0, &b.0));
+        assert_eq!(a, b);
+        assert_eq!(a, "/root/src/interned.ts");
+        // the same as the strings, e.g. for the hashes of the chunks
+        assert_eq!(hash(&a), hash("/root/src/interned.ts"));
+        assert!(InternedStr::new("/root/src/a.ts") < InternedStr::new("/root/src/b.ts"));
+        assert_eq!(format!("{:?}", a), r#""/root/src/interned.ts""#);
+    }
+
+    #[test]
+    fn test_interned_str_prune() {
+        let interner = Interner::default();
+        let kept = interner.intern(&InternedStr::new("/root/src/kept.ts"));
+        interner.intern(&InternedStr::new("/root/src/pruned.ts"));
+        interner.prune();
+        assert!(!interner.strings.contains_key("/root/src/pruned.ts"));
+        assert!(interner.strings.contains_key("/root/src/kept.ts"));
+        assert!(Arc::ptr_eq(
+            &kept.0,
+            &interner.intern(&InternedStr::new("/root/src/kept.ts")).0
+        ));
+        assert_eq!(interner.usage().entries, 1);
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::hash::Hash;
+
+use cached::{Cached, SizedCache};
+
+/// The entry count and the estimated bytes of a cache, the bytes count the
+/// contents kept by the entries, e.g. the code of the chunks, but not the
+/// overhead of the maps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheUsage {
+    pub name: &'static str,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+impl CacheUsage {
+    pub fn new(name: &'static str, entries: usize, bytes: usize) -> Self {
+        Self {
+            name,
+            entries,
+            bytes,
+        }
+    }
+}
+
+/// The usages of the caches kept between the builds of the watch mode, which
+/// is printed with `--memory-report`.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryReport {
+    pub caches: Vec<CacheUsage>,
+}
+
+impl MemoryReport {
+    pub fn get(&self, name: &str) -> Option<&CacheUsage> {
+        self.caches.iter().find(|cache| cache.name == name)
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.caches.iter().map(|cache| cache.bytes).sum()
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Memory report:")?;
+        for cache in &self.caches {
+            writeln!(
+                f,
+                "  {:<32} {:>8} entries {:>12}",
+                cache.name,
+                cache.entries,
+                format_bytes(cache.bytes)
+            )?;
+        }
+        write!(
+            f,
+            "  {:<32} {:>8}         {:>12}",
+            "total",
+            "",
+            format_bytes(self.total_bytes())
+        )
+    }
+}
+
+fn format_bytes(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / 1024.0 / 1024.0)
+    } else if bytes >= 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Drop the entries which are not looked up since the last sweep, e.g. the
+/// previous versions of the changed chunks in watch mode, which are never
+/// used again but would be kept until the cache is full.
+///
+/// The looked up entries are the most recently used ones, and there are no
+/// more of them than the lookups.
+pub(crate) fn sweep_stale_entries<K, V>(cache: &mut SizedCache<K, V>)
+where
+    K: Hash + Eq + Clone,
+{
+    let lookups = cache.cache_hits().unwrap_or(0) + cache.cache_misses().unwrap_or(0);
+    let stale = cache
+        .key_order()
+        .skip(lookups as usize)
+        .cloned()
+        .collect::<Vec<_>>();
+    for key in &stale {
+        cache.cache_remove(key);
+    }
+    cache.cache_reset_metrics();
+}
+
+pub(crate) fn sized_cache_usage<K, V>(
+    name: &'static str,
+    cache: &SizedCache<K, V>,
+    bytes: impl Fn(&V) -> usize,
+) -> CacheUsage
+where
+    K: Hash + Eq + Clone,
+{
+    CacheUsage::new(
+        name,
+        cache.cache_size(),
+        cache.value_order().map(bytes).sum(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use cached::{Cached, SizedCache};
+
+    use super::*;
+
+    #[test]
+    fn test_sweep_stale_entries() {
+        let mut cache = SizedCache::with_size(10);
+        for i in 0..5 {
+            cache.cache_set(i, i.to_string());
+        }
+        cache.cache_reset_metrics();
+        // the render of the next build
+        assert!(cache.cache_get(&1).is_some());
+        assert!(cache.cache_get(&5).is_none());
+        cache.cache_set(5, "5".to_string());
+        sweep_stale_entries(&mut cache);
+        let mut keys = cache.key_order().cloned().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, vec![1, 5]);
+
+        // nothing is looked up
+        sweep_stale_entries(&mut cache);
+        assert_eq!(cache.cache_size(), 0);
+    }
+
+    #[test]
+    fn test_memory_report() {
+        let report = MemoryReport {
+            caches: vec![
+                CacheUsage::new("module graph", 2, 2048),
+                CacheUsage::new("resolve cache", 3, 100),
+            ],
+        };
+        assert_eq!(report.total_bytes(), 2148);
+        assert_eq!(report.get("resolve cache").unwrap().entries, 3);
+        let text = report.to_string();
+        assert!(text.contains("module graph"), "{}", text);
+        assert!(text.contains("2.0 KiB"), "{}", text);
+        assert!(text.contains("total"), "{}", text);
+    }
+}
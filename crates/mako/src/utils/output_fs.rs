@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use anyhow::{anyhow, Result};
+
+/// The file system that generated chunks and assets are emitted to.
+///
+/// Emitting through this trait instead of `std::fs` lets the dev server serve
+/// outputs from memory and lets tests assert on outputs without touching disk.
+pub trait OutputFileSystem: Send + Sync {
+    fn write(&self, path: &Path, content: &[u8]) -> Result<()>;
+
+    /// Write the parts one after another, e.g. a big chunk and its source
+    /// map url, without joining them first.
+    fn write_parts(&self, path: &Path, parts: &[&[u8]]) -> Result<()> {
+        self.write(path, &parts.concat())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    /// Remove a file, or a directory and everything under it.
+    fn remove(&self, path: &Path) -> Result<()>;
+
+    /// List all files under `dir` recursively, sorted by path.
+    fn list(&self, dir: &Path) -> Result<Vec<PathBuf>>;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.read(path).is_ok()
+    }
+
+    /// Mark an emitted file as executable, e.g. a cli with a shebang.
+    fn set_executable(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct DiskFileSystem {}
+
+impl OutputFileSystem for DiskFileSystem {
+    fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn write_parts(&self, path: &Path, parts: &[&[u8]]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        for part in parts {
+            writer.write_all(part)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        Ok(fs::read(path)?)
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    walk(&path, files)?;
+                } else {
+                    files.push(path);
+                }
+            }
+            Ok(())
+        }
+
+        let mut files = vec![];
+        if dir.is_dir() {
+            walk(dir, &mut files)?;
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    #[cfg(unix)]
+    fn set_executable(&self, path: &Path) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut permissions = fs::metadata(path)?.permissions();
+        // executable for whoever can read it
+        permissions.set_mode(permissions.mode() | ((permissions.mode() & 0o444) >> 2));
+        fs::set_permissions(path, permissions)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryFileSystem {
+    files: RwLock<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OutputFileSystem for MemoryFileSystem {
+    fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+        self.files
+            .write()
+            .unwrap()
+            .insert(path.to_path_buf(), content.to_vec());
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.files
+            .read()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow!("File not found: {}", path.display()))
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        self.files
+            .write()
+            .unwrap()
+            .retain(|file, _| !file.starts_with(path));
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        // BTreeMap keys are already sorted
+        Ok(self
+            .files
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|file| file.starts_with(dir))
+            .cloned()
+            .collect())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.read().unwrap().contains_key(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::utils::test_helper::setup_compiler_with_output_fs;
+
+    #[test]
+    fn test_memory_file_system() {
+        let fs = MemoryFileSystem::new();
+        fs.write(Path::new("/dist/a.js"), b"a").unwrap();
+        fs.write(Path::new("/dist/sub/b.js"), b"b").unwrap();
+        fs.write(Path::new("/other/c.js"), b"c").unwrap();
+
+        assert_eq!(fs.read(Path::new("/dist/a.js")).unwrap(), b"a");
+        assert!(fs.read(Path::new("/dist/none.js")).is_err());
+        assert_eq!(
+            fs.list(Path::new("/dist")).unwrap(),
+            vec![PathBuf::from("/dist/a.js"), PathBuf::from("/dist/sub/b.js")]
+        );
+
+        fs.remove(Path::new("/dist")).unwrap();
+        assert!(fs.list(Path::new("/dist")).unwrap().is_empty());
+        assert!(fs.exists(Path::new("/other/c.js")));
+    }
+
+    #[test]
+    fn test_memory_output_is_identical_to_disk_output() {
+        let memory_fs = Arc::new(MemoryFileSystem::new());
+        let compiler = setup_compiler_with_output_fs("test/build/output-fs", memory_fs.clone());
+        compiler.compile().unwrap();
+        let output_path = compiler.context.config.output.path.clone();
+        let memory_files = memory_fs.list(&output_path).unwrap();
+        assert!(!output_path.exists());
+
+        let disk_fs = Arc::new(DiskFileSystem::default());
+        let compiler = setup_compiler_with_output_fs("test/build/output-fs", disk_fs.clone());
+        compiler.compile().unwrap();
+        let disk_files = disk_fs.list(&output_path).unwrap();
+
+        assert!(!memory_files.is_empty());
+        assert_eq!(memory_files, disk_files);
+        for file in &memory_files {
+            assert_eq!(
+                memory_fs.read(file).unwrap(),
+                disk_fs.read(file).unwrap(),
+                "{} differs",
+                file.display()
+            );
+        }
+
+        disk_fs.remove(&output_path).unwrap();
+    }
+}
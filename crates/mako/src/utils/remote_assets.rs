@@ -0,0 +1,359 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use base64::engine::general_purpose;
+use base64::Engine;
+use hyper::header::{ACCEPT, HOST, LOCATION, USER_AGENT};
+use hyper::{Body, Request, Response};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::crypto::ring::default_provider;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use twox_hash::XxHash64;
+use url::{Position, Url};
+
+pub const CACHE_DIR: &str = "node_modules/.cache_mako/remote";
+pub const LOCK_FILE: &str = "mako-remote.lock.json";
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REDIRECTS: usize = 5;
+// the font services serve the css by the user agent, e.g. the woff2 fonts
+// are only in the css for the modern browsers
+const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+// the bundles of the root certificates of the systems, after `SSL_CERT_FILE`
+const CERT_BUNDLES: [&str; 5] = [
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/ca-bundle.pem",
+    "/etc/ssl/cert.pem",
+    "/usr/local/etc/openssl/cert.pem",
+];
+
+// the cached file or the error of a download
+type Download = Arc<OnceLock<Result<PathBuf, String>>>;
+
+/// The entry of a url in the lockfile.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LockedAsset {
+    /// the sha256 of the cached content, in the format of the subresource
+    /// integrity, e.g. `sha256-<base64>`
+    pub integrity: String,
+    pub size: u64,
+}
+
+/// The remote files fetched at build time, e.g. the css and the fonts of
+/// `remoteCSS: "download"`. They are cached by the url and the hash of the
+/// content in node_modules/.cache_mako/remote, so the later builds don't
+/// fetch them again, and recorded in `mako-remote.lock.json` of the root,
+/// which pins the content of each url. A locked url whose cached file is
+/// missing is fetched again and must have the same content.
+#[derive(Default)]
+pub struct RemoteAssets {
+    root: PathBuf,
+    // url -> entry of the lockfile, loaded at the first download
+    locked: OnceLock<BTreeMap<String, LockedAsset>>,
+    // url -> entry of the urls downloaded in the build
+    used: Mutex<BTreeMap<String, LockedAsset>>,
+    // url -> the cached file, the downloads of the same url in the other
+    // threads wait for the first one
+    downloads: Mutex<HashMap<String, Download>>,
+}
+
+impl RemoteAssets {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    /// The cached file of the url with the extension, which is fetched if
+    /// it's not cached. `rewrite` changes the fetched content before it's
+    /// cached, with the url it's fetched from after the redirects, e.g. the
+    /// relative urls in the css.
+    pub fn download(
+        &self,
+        url: &str,
+        extension: &str,
+        rewrite: impl Fn(&Url, Vec<u8>) -> Vec<u8>,
+    ) -> Result<PathBuf> {
+        let download = self
+            .downloads
+            .lock()
+            .unwrap()
+            .entry(url.to_string())
+            .or_default()
+            .clone();
+        let result = download
+            .get_or_init(|| {
+                self.download_once(url, extension, rewrite)
+                    .map_err(|err| err.to_string())
+            })
+            .clone();
+        // fetched again by the next build, e.g. when it's online
+        if result.is_err() {
+            self.downloads.lock().unwrap().remove(url);
+        }
+        result.map_err(|err| anyhow!(err))
+    }
+
+    fn download_once(
+        &self,
+        url: &str,
+        extension: &str,
+        rewrite: impl Fn(&Url, Vec<u8>) -> Vec<u8>,
+    ) -> Result<PathBuf> {
+        let locked = self.locked().get(url);
+        if let Some(locked) = locked {
+            let path = self.cache_path(url, &locked.integrity, extension);
+            if let Ok(content) = fs::read(&path)
+                && integrity(&content) == locked.integrity
+            {
+                self.used
+                    .lock()
+                    .unwrap()
+                    .insert(url.to_string(), locked.clone());
+                return Ok(path);
+            }
+        }
+
+        let parsed = Url::parse(url)?;
+        let (fetched_url, content) = fetch(&parsed).map_err(|err| {
+            anyhow!(
+                "Failed to download {}: {}. It's not in the cache at {} yet, which needs the network once, or set `remoteCSS` to \"preserve\" to keep the remote urls",
+                url,
+                err,
+                CACHE_DIR
+            )
+        })?;
+        let content = rewrite(&fetched_url, content);
+        let entry = LockedAsset {
+            integrity: integrity(&content),
+            size: content.len() as u64,
+        };
+        if let Some(locked) = locked
+            && locked.integrity != entry.integrity
+        {
+            bail!(
+                "The content of {} is changed, it's {} in {} but {} now, remove its entry from the lockfile to update it",
+                url,
+                locked.integrity,
+                LOCK_FILE,
+                entry.integrity
+            );
+        }
+        let path = self.cache_path(url, &entry.integrity, extension);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, &content)?;
+        self.used.lock().unwrap().insert(url.to_string(), entry);
+        Ok(path)
+    }
+
+    /// Write the lockfile if it's changed, with the urls downloaded in the
+    /// build, and the locked ones if not `prune`, e.g. the rebuilds only
+    /// download the urls of the changed files.
+    pub fn write_lock(&self, prune: bool) -> Result<()> {
+        let locked = self.locked();
+        let mut entries = if prune {
+            BTreeMap::new()
+        } else {
+            locked.clone()
+        };
+        entries.extend(self.used.lock().unwrap().clone());
+        let path = self.root.join(LOCK_FILE);
+        if entries == *locked && (path.exists() || entries.is_empty()) {
+            return Ok(());
+        }
+        fs::write(
+            &path,
+            format!("{}\n", serde_json::to_string_pretty(&entries)?),
+        )?;
+        Ok(())
+    }
+
+    /// The urls downloaded in the build.
+    pub fn used(&self) -> BTreeMap<String, LockedAsset> {
+        self.used.lock().unwrap().clone()
+    }
+
+    fn locked(&self) -> &BTreeMap<String, LockedAsset> {
+        self.locked.get_or_init(|| {
+            fs::read(self.root.join(LOCK_FILE))
+                .ok()
+                .and_then(|content| serde_json::from_slice(&content).ok())
+                .unwrap_or_default()
+        })
+    }
+
+    fn cache_path(&self, url: &str, integrity: &str, extension: &str) -> PathBuf {
+        self.root.join(CACHE_DIR).join(format!(
+            "{}-{}.{}",
+            xxhash(url),
+            xxhash(integrity),
+            extension
+        ))
+    }
+}
+
+fn integrity(content: &[u8]) -> String {
+    format!(
+        "sha256-{}",
+        general_purpose::STANDARD.encode(Sha256::digest(content))
+    )
+}
+
+fn xxhash(value: &str) -> String {
+    let mut hasher: XxHash64 = Default::default();
+    hasher.write(value.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+/// Fetch the url with GET, following the redirects, returns the url after the
+/// redirects and the body.
+pub fn fetch(url: &Url) -> Result<(Url, Vec<u8>)> {
+    let url = url.clone();
+    // a runtime of its own, the builds may run in the runtime of the cli
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        runtime.block_on(async {
+            tokio::time::timeout(FETCH_TIMEOUT, fetch_with_redirects(url))
+                .await
+                .map_err(|_| anyhow!("timed out after {}s", FETCH_TIMEOUT.as_secs()))?
+        })
+    })
+    .join()
+    .map_err(|_| anyhow!("the fetch panicked"))?
+}
+
+async fn fetch_with_redirects(mut url: Url) -> Result<(Url, Vec<u8>)> {
+    for _ in 0..=MAX_REDIRECTS {
+        let response = get(&url).await?;
+        let status = response.status();
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or_else(|| anyhow!("the server responds {} without a location", status))?;
+            url = url.join(location)?;
+            continue;
+        }
+        if !status.is_success() {
+            bail!("the server responds {}", status);
+        }
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        return Ok((url, body.to_vec()));
+    }
+    bail!("more than {} redirects", MAX_REDIRECTS)
+}
+
+async fn get(url: &Url) -> Result<Response<Body>> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("the url has no host"))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("the url has no port"))?;
+    let request = Request::get(&url[Position::BeforePath..Position::AfterQuery])
+        .header(HOST, &url[Position::BeforeHost..Position::AfterPort])
+        .header(USER_AGENT, BROWSER_USER_AGENT)
+        .header(ACCEPT, "*/*")
+        .body(Body::empty())?;
+    let stream = TcpStream::connect((host, port)).await?;
+    match url.scheme() {
+        "https" => {
+            let server_name = ServerName::try_from(host.to_string())?;
+            let stream = tls_connector()?.connect(server_name, stream).await?;
+            send(stream, request).await
+        }
+        "http" => send(stream, request).await,
+        scheme => bail!("the scheme {} is not supported", scheme),
+    }
+}
+
+async fn send<T>(io: T, request: Request<Body>) -> Result<Response<Body>>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, connection) = hyper::client::conn::handshake(io).await?;
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+    Ok(sender.send_request(request).await?)
+}
+
+fn tls_connector() -> Result<TlsConnector> {
+    let mut roots = RootCertStore::empty();
+    let (added, _) = roots.add_parsable_certificates(system_certificates());
+    if added == 0 {
+        bail!("no root certificates are found, set SSL_CERT_FILE to the bundle of them");
+    }
+    let config = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+// the certificates of the first bundle found
+fn system_certificates() -> Vec<CertificateDer<'static>> {
+    let bundle = std::env::var("SSL_CERT_FILE")
+        .ok()
+        .into_iter()
+        .chain(CERT_BUNDLES.iter().map(|path| path.to_string()))
+        .find_map(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    parse_pem_certificates(&bundle)
+}
+
+fn parse_pem_certificates(pem: &str) -> Vec<CertificateDer<'static>> {
+    let mut certificates = vec![];
+    let mut block: Option<String> = None;
+    for line in pem.lines().map(str::trim) {
+        match line {
+            "-----BEGIN CERTIFICATE-----" => block = Some(String::new()),
+            "-----END CERTIFICATE-----" => {
+                if let Some(der) = block
+                    .take()
+                    .and_then(|base64| general_purpose::STANDARD.decode(base64).ok())
+                {
+                    certificates.push(CertificateDer::from(der));
+                }
+            }
+            line => {
+                if let Some(block) = block.as_mut() {
+                    block.push_str(line);
+                }
+            }
+        }
+    }
+    certificates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{integrity, parse_pem_certificates};
+
+    #[test]
+    fn test_parse_pem_certificates() {
+        let pem = "# comment\n-----BEGIN CERTIFICATE-----\nAAEC\nAw==\n-----END CERTIFICATE-----\n-----BEGIN CERTIFICATE-----\n!!\n-----END CERTIFICATE-----\n";
+        let certificates = parse_pem_certificates(pem);
+        assert_eq!(certificates.len(), 1);
+        assert_eq!(certificates[0].as_ref(), &[0, 1, 2, 3]);
+        assert_eq!(
+            integrity(b"a"),
+            "sha256-ypeBEsobvcr6wjGzmiPcTaeG7/gUfE5yuYB3ha/uSLs="
+        );
+    }
+}
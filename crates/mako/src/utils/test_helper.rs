@@ -1,10 +1,14 @@
 use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use tracing_subscriber::{fmt, EnvFilter};
 
 use crate::compiler::{self, Compiler};
-use crate::config::{Config, Mode};
+use crate::config::{Config, Mode, ModuleIdStrategy};
 use crate::module::{Module, ModuleId};
+use crate::plugin::Plugin;
+use crate::utils::output_fs::OutputFileSystem;
 
 #[macro_export]
 macro_rules! assert_display_snapshot {
@@ -48,6 +52,24 @@ pub fn get_module(compiler: &Compiler, path: &str) -> Module {
 
 #[allow(dead_code)]
 pub fn setup_compiler(base: &str, cleanup: bool) -> Compiler {
+    let (config, root) = setup_config(base, cleanup);
+    compiler::Compiler::new(config, root, Default::default(), None).unwrap()
+}
+
+#[allow(dead_code)]
+pub fn setup_compiler_with_plugins(base: &str, plugins: Vec<Arc<dyn Plugin>>) -> Compiler {
+    let (config, root) = setup_config(base, false);
+    compiler::Compiler::new(config, root, Default::default(), Some(plugins)).unwrap()
+}
+
+#[allow(dead_code)]
+pub fn setup_compiler_with_output_fs(base: &str, output_fs: Arc<dyn OutputFileSystem>) -> Compiler {
+    let (config, root) = setup_config(base, false);
+    compiler::Compiler::new_with_output_fs(config, root, Default::default(), None, Some(output_fs))
+        .unwrap()
+}
+
+fn setup_config(base: &str, cleanup: bool) -> (Config, PathBuf) {
     setup_logger();
     let current_dir = std::env::current_dir().unwrap();
     let root = current_dir.join(base);
@@ -62,11 +84,14 @@ pub fn setup_compiler(base: &str, cleanup: bool) -> Compiler {
     }
     let mut config = Config::new(&root, None, None).unwrap();
     config.hmr = None;
-    config.minify = false;
+    config.minify = None;
     config.mode = Mode::Production;
+    // keep the output names and module ids stable regardless of the mode preset
+    config.hash = false;
+    config.module_id_strategy = ModuleIdStrategy::Named;
     config.optimization = None;
 
-    compiler::Compiler::new(config, root, Default::default(), None).unwrap()
+    (config, root)
 }
 
 pub fn setup_logger() {
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use anyhow::Result;
+
+use crate::ast::file::Content;
+use crate::compiler::Context;
+
+const VIRTUAL: &str = "virtual:";
+
+pub type VirtualModuleLoader = Box<dyn Fn(&Arc<Context>) -> Result<Content> + Send + Sync>;
+pub type VirtualModuleInvalidator = Box<dyn Fn(&str) + Send + Sync>;
+
+/// Modules generated by plugins at build time, e.g. `virtual:mako-build-info`.
+///
+/// Register them in `build_start`, the registered ids are resolved as virtual
+/// resources and loaded with the loaders.
+#[derive(Default)]
+pub struct VirtualModules {
+    loaders: RwLock<HashMap<String, VirtualModuleLoader>>,
+    // set by the dev server to trigger rebuilds
+    invalidator: Mutex<Option<VirtualModuleInvalidator>>,
+}
+
+impl VirtualModules {
+    pub fn register<F>(&self, id: &str, loader: F)
+    where
+        F: Fn(&Arc<Context>) -> Result<Content> + Send + Sync + 'static,
+    {
+        assert!(
+            id.starts_with(VIRTUAL),
+            "virtual module id must start with \"{}\", got \"{}\"",
+            VIRTUAL,
+            id
+        );
+        self.loaders
+            .write()
+            .unwrap()
+            .insert(id.to_string(), Box::new(loader));
+    }
+
+    pub fn has(&self, id: &str) -> bool {
+        self.loaders.read().unwrap().contains_key(id)
+    }
+
+    pub fn load(&self, id: &str, context: &Arc<Context>) -> Result<Option<Content>> {
+        match self.loaders.read().unwrap().get(id) {
+            Some(loader) => Ok(Some(loader(context)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_invalidator<F>(&self, invalidator: F)
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        *self.invalidator.lock().unwrap() = Some(Box::new(invalidator));
+    }
+
+    /// Reload the virtual module and rebuild in watch mode, no-op otherwise.
+    pub fn invalidate(&self, id: &str) {
+        if !self.has(id) {
+            return;
+        }
+        if let Some(invalidator) = self.invalidator.lock().unwrap().as_ref() {
+            invalidator(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::ast::file::JsContent;
+
+    #[test]
+    fn test_virtual_modules() {
+        let context: Arc<Context> = Arc::new(Default::default());
+        let virtual_modules = VirtualModules::default();
+        virtual_modules.register("virtual:foo", |_| {
+            Ok(Content::Js(JsContent {
+                content: "export default 1;".to_string(),
+                ..Default::default()
+            }))
+        });
+        assert!(virtual_modules.has("virtual:foo"));
+        assert!(!virtual_modules.has("virtual:bar"));
+        assert!(matches!(
+            virtual_modules.load("virtual:foo", &context).unwrap(),
+            Some(Content::Js(JsContent { content, .. })) if content == "export default 1;"
+        ));
+        assert!(virtual_modules
+            .load("virtual:bar", &context)
+            .unwrap()
+            .is_none());
+
+        let invalidated = Arc::new(AtomicUsize::new(0));
+        let counter = invalidated.clone();
+        virtual_modules.set_invalidator(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        virtual_modules.invalidate("virtual:foo");
+        virtual_modules.invalidate("virtual:bar");
+        assert_eq!(invalidated.load(Ordering::SeqCst), 1);
+    }
+}
@@ -2,27 +2,39 @@ pub(crate) mod amd_define_overrides;
 pub(crate) mod async_module;
 pub(crate) mod clean_ctxt;
 pub(crate) mod common_js;
+pub(crate) mod commonjs_globals;
 pub(crate) mod css_assets;
 pub(crate) mod css_dep_analyzer;
 pub(crate) mod css_flexbugs;
+pub(crate) mod css_import_attributes;
 pub(crate) mod css_imports;
+pub(crate) mod css_is_where;
+pub(crate) mod css_logical_properties;
 pub(crate) mod css_px2rem;
+pub(crate) mod css_remote_urls;
 pub(crate) mod default_export_namer;
 pub(crate) mod dep_analyzer;
 pub(crate) mod dep_replacer;
 pub(crate) mod dynamic_import;
 pub(crate) mod dynamic_import_to_require;
 pub(crate) mod env_replacer;
+pub(crate) mod eval_detector;
 pub(crate) mod fix_symbol_conflict;
+pub(crate) mod import_meta_check;
 pub(crate) mod import_meta_env_replacer;
 pub(crate) mod import_template_to_string_literal;
+pub(crate) mod keep_names;
 pub(crate) mod mako_require;
 pub(crate) mod meta_url_replacer;
 pub(crate) mod new_url_assets;
+pub(crate) mod node_interop;
 pub(crate) mod optimize_define_utils;
+pub(crate) mod process_env_object;
 pub(crate) mod provide;
 pub(crate) mod public_path_assignment;
 pub(crate) mod react;
+pub(crate) mod remove_prop_types;
+pub(crate) mod side_effect_only_imports;
 pub(crate) mod try_resolve;
 pub(crate) mod ts_strip;
 pub(crate) mod tsx_strip;
@@ -0,0 +1,93 @@
+use swc_core::common::errors::HANDLER;
+use swc_core::common::Mark;
+use swc_core::ecma::ast::{Expr, Ident, MemberExpr, MemberProp, UnaryExpr, UnaryOp};
+use swc_core::ecma::visit::{VisitMut, VisitMutWith};
+
+const COMMONJS_GLOBALS: [&str; 5] = ["require", "module", "exports", "__filename", "__dirname"];
+
+/// Report the globals of commonjs used by an es module, which are not defined
+/// for es modules in node. `typeof` checks and `module.hot` are allowed.
+pub struct CommonJsGlobals {
+    pub unresolved_mark: Mark,
+}
+
+impl VisitMut for CommonJsGlobals {
+    fn visit_mut_unary_expr(&mut self, n: &mut UnaryExpr) {
+        if n.op == UnaryOp::TypeOf && n.arg.is_ident() {
+            return;
+        }
+        n.visit_mut_children_with(self);
+    }
+
+    fn visit_mut_member_expr(&mut self, n: &mut MemberExpr) {
+        if let (Expr::Ident(ident), MemberProp::Ident(prop)) = (&*n.obj, &n.prop)
+            && ident.sym == "module"
+            && prop.sym == "hot"
+        {
+            return;
+        }
+        n.visit_mut_children_with(self);
+    }
+
+    fn visit_mut_ident(&mut self, n: &mut Ident) {
+        if n.ctxt.outer() == self.unresolved_mark && COMMONJS_GLOBALS.contains(&n.sym.as_ref()) {
+            let hint = match n.sym.as_ref() {
+                "__filename" | "__dirname" => "use import.meta.url instead",
+                _ => "use import and export instead",
+            };
+            HANDLER.with(|handler| {
+                handler
+                    .struct_span_err(
+                        n.span,
+                        &format!("{} is not defined in es module, {}", n.sym, hint),
+                    )
+                    .emit();
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use swc_core::base::try_with_handler;
+    use swc_core::common::errors::HANDLER;
+    use swc_core::common::GLOBALS;
+    use swc_core::ecma::visit::VisitMutWith;
+
+    use super::CommonJsGlobals;
+    use crate::ast::tests::TestUtils;
+
+    #[test]
+    fn test_commonjs_globals() {
+        assert!(run(r#"const foo = require("foo");"#).is_err());
+        assert!(run(r#"module.exports = 1;"#).is_err());
+        assert!(run(r#"exports.foo = 1;"#).is_err());
+        assert!(run(r#"console.log(__dirname);"#).is_err());
+    }
+
+    #[test]
+    fn test_commonjs_globals_allowed() {
+        assert!(run(r#"if (typeof require === "function") {}"#).is_ok());
+        assert!(run(r#"if (module.hot) { module.hot.accept(); }"#).is_ok());
+        assert!(run(r#"import { createRequire } from "module"; const require = createRequire(import.meta.url); require("foo");"#).is_ok());
+        assert!(run(r#"const obj = { require: 1 }; obj.exports = obj.require;"#).is_ok());
+    }
+
+    fn run(js_code: &str) -> Result<()> {
+        let mut test_utils = TestUtils::gen_js_ast(js_code);
+        let ast = test_utils.ast.js_mut();
+        let unresolved_mark = ast.unresolved_mark;
+        let cm = test_utils.context.meta.script.cm.clone();
+
+        GLOBALS.set(&test_utils.context.meta.script.globals, || {
+            try_with_handler(cm, Default::default(), |handler| {
+                HANDLER.set(handler, || {
+                    ast.ast
+                        .visit_mut_with(&mut CommonJsGlobals { unresolved_mark });
+                });
+                Ok(())
+            })
+        })
+    }
+}
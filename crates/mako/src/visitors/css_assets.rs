@@ -1,13 +1,17 @@
 use std::sync::Arc;
 
-use swc_core::css::ast::{Url, UrlValue};
+use colored::Colorize;
+use swc_core::css::ast::{ImportHref, ImportPrelude, Url, UrlValue};
 use swc_core::css::visit::VisitMut;
 
+use crate::ast::error::{code_frame, ErrorSpan};
 use crate::ast::file::File;
 use crate::ast::utils::{is_remote_or_data_or_hash, remove_first_tilde};
 use crate::build::load::Load;
 use crate::compiler::Context;
+use crate::config::static_public_path;
 use crate::module::{Dependency, ResolveType};
+use crate::plugins::bundless_compiler::{relative_source, to_dist_path};
 use crate::resolve;
 
 pub struct CSSAssets {
@@ -16,6 +20,36 @@ pub struct CSSAssets {
 }
 
 impl VisitMut for CSSAssets {
+    // the local css of @import is a module of the chunk instead of a file
+    // loaded by the browser, so its media, supports() and layer conditions
+    // can't be applied, e.g. @import "./print.css" print;
+    fn visit_mut_import_prelude(&mut self, n: &mut ImportPrelude) {
+        let href = match &*n.href {
+            ImportHref::Str(str) => str.value.to_string(),
+            ImportHref::Url(url) => match &url.value {
+                Some(box UrlValue::Str(s)) => s.value.to_string(),
+                Some(box UrlValue::Raw(s)) => s.value.to_string(),
+                None => return,
+            },
+        };
+        // the @import is kept with preserveModules
+        if self.context.config.output.preserve_modules
+            || is_remote_or_data_or_hash(&href)
+            || (n.layer_name.is_none() && n.import_conditions.is_none())
+        {
+            return;
+        }
+        eprintln!(
+            "{}: {}",
+            "Warning".yellow(),
+            code_frame(
+                ErrorSpan::Css(n.span),
+                "The media, supports() and layer conditions of @import are ignored, the imported css is bundled unconditionally, wrap its rules in the conditions instead",
+                self.context.clone()
+            )
+        );
+    }
+
     // e.g.
     // .foo { background: url(foo.png) }
     fn visit_mut_url(&mut self, n: &mut Url) {
@@ -33,6 +67,10 @@ impl VisitMut for CSSAssets {
         }
 
         let url = remove_first_tilde(url);
+        // the query and the fragment are resolved with the file, so ?inline
+        // and ?url of the asset take effect, and kept on the emitted url,
+        // e.g. url(font.eot?#iefix) and url(sprite.svg#icon)
+        let suffix = url_suffix(&url);
 
         let dep = Dependency {
             source: url,
@@ -50,7 +88,33 @@ impl VisitMut for CSSAssets {
                 true,
                 self.context.clone(),
             );
-            let asset_content = asset_content.unwrap_or(resolved_path);
+            let mut asset_content = asset_content.unwrap_or(resolved_path);
+            // relative to the emitted css with preserveModules
+            if self.context.config.output.preserve_modules && !asset_content.starts_with("data:") {
+                let output_path = &self.context.config.output.path;
+                let pathname = self.path.split('?').next().unwrap();
+                let css_dir = to_dist_path(pathname, &self.context);
+                if let Ok(relative) =
+                    relative_source(&output_path.join(&asset_content), css_dir.parent().unwrap())
+                {
+                    asset_content = relative;
+                }
+            }
+            // the inlined css is in the style elements, so the urls are
+            // relative to the html instead of the css file
+            if self.context.config.inline_css.is_some()
+                && !self.context.config.output.preserve_modules
+                && !asset_content.starts_with("data:")
+            {
+                asset_content = format!(
+                    "{}{}",
+                    static_public_path(&self.context.config.public_path),
+                    asset_content
+                );
+            }
+            if !asset_content.starts_with("data:") {
+                asset_content.push_str(&suffix);
+            }
             match n.value {
                 Some(box UrlValue::Str(ref mut s)) => {
                     s.value = asset_content.into();
@@ -66,11 +130,41 @@ impl VisitMut for CSSAssets {
     }
 }
 
+// the query without the params of the asset handling, and the fragment
+fn url_suffix(url: &str) -> String {
+    let (url, fragment) = match url.find('#') {
+        Some(index) => url.split_at(index),
+        None => (url, ""),
+    };
+    let query = match url.split_once('?') {
+        Some((_, query)) => {
+            let params = query
+                .split('&')
+                .filter(|param| {
+                    let key = param.split('=').next().unwrap_or_default();
+                    key != "inline" && key != "url"
+                })
+                .collect::<Vec<_>>();
+            if params.is_empty() && !query.is_empty() {
+                String::new()
+            } else {
+                format!("?{}", params.join("&"))
+            }
+        }
+        None => String::new(),
+    };
+    format!("{}{}", query, fragment)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use swc_core::css::visit::VisitMutWith;
 
-    use crate::ast::tests::TestUtils;
+    use crate::ast::tests::{TestUtils, TestUtilsOpts};
+    use crate::compiler::Context;
+    use crate::config::{InlineCssConfig, Mode};
 
     #[test]
     fn test_base64() {
@@ -125,8 +219,80 @@ mod tests {
         assert!(run(r#".foo { background: url(big.jpg) }"#).contains(".foo{background:url(big."));
     }
 
+    #[test]
+    fn test_query_and_fragment() {
+        assert!(run(r#".foo { background: url(big.jpg?v=1#top) }"#)
+            .contains(".foo{background:url(big."));
+        assert!(run(r#".foo { background: url(big.jpg?v=1#top) }"#).contains(".jpg?v=1#top)}"));
+        // inlined assets don't need them
+        assert!(run(r#".foo { background: url(umi.png#top) }"#)
+            .contains(".foo{background:url(data:image/png;base64,"));
+        assert!(!run(r#".foo { background: url(umi.png#top) }"#).contains("#top"));
+        assert!(run(r#".foo { background: url(big.jpg?#iefix) }"#).contains(".jpg?#iefix)}"));
+        assert!(run(r#".foo { background: url(big.jpg?raw) }"#).contains(".jpg?raw)}"));
+    }
+
+    #[test]
+    fn test_inline_and_url_query() {
+        assert!(run(r#".foo { background: url(big.jpg?inline) }"#)
+            .contains(".foo{background:url(data:image/jpeg;base64,"));
+        let emitted = run(r#".foo { background: url(umi.png?url&v=1) }"#);
+        assert!(emitted.contains(".foo{background:url(umi."));
+        assert!(emitted.contains(".png?v=1)}"));
+    }
+
+    #[test]
+    fn test_absolute_url() {
+        assert_eq!(
+            run(r#".foo { background: url(blob:https://a.com/uuid) }"#),
+            ".foo{background:url(blob:https://a.com/uuid)}"
+        );
+        assert_eq!(
+            run(r#".foo { background: url(about:blank) }"#),
+            ".foo{background:url(about:blank)}"
+        );
+    }
+
+    #[test]
+    fn test_inline_css_public_path() {
+        assert!(run_inline_css(
+            r#".foo { background: url(big.jpg) }"#,
+            "https://cdn.com/app/"
+        )
+        .contains(".foo{background:url(https://cdn.com/app/big."));
+        assert!(run_inline_css(r#".foo { background: url(big.jpg) }"#, "./")
+            .contains(".foo{background:url(./big."));
+        assert!(
+            run_inline_css(r#".foo { background: url(big.jpg) }"#, "runtime")
+                .contains(".foo{background:url(big.")
+        );
+        assert!(
+            run_inline_css(r#".foo { background: url(umi.png) }"#, "/app/")
+                .contains(".foo{background:url(data:image/png;base64,")
+        );
+    }
+
+    fn run_inline_css(css_code: &str, public_path: &str) -> String {
+        let mut context = Context::default();
+        context.config.devtool = None;
+        context.config.mode = Mode::Production;
+        context.config.inline_css = Some(InlineCssConfig {});
+        context.config.public_path = public_path.to_string();
+        let test_utils = TestUtils::with_context(
+            TestUtilsOpts {
+                file: Some("test.css".to_string()),
+                content: Some(css_code.to_string()),
+            },
+            Arc::new(context),
+        );
+        visit(test_utils)
+    }
+
     fn run(css_code: &str) -> String {
-        let mut test_utils = TestUtils::gen_css_ast(css_code.to_string(), true);
+        visit(TestUtils::gen_css_ast(css_code.to_string(), true))
+    }
+
+    fn visit(mut test_utils: TestUtils) -> String {
         let ast = test_utils.ast.css_mut();
         let current_dir = std::env::current_dir().unwrap();
         let css_path = current_dir.join("src/visitors/fixtures/css_assets/test.css");
@@ -93,6 +93,7 @@ mod tests {
         assert!(run(r#"@import url(data://a.com/a.css);"#).is_empty());
         assert!(run(r#"@import url(//a.com/a.css);"#).is_empty());
         assert!(run(r#"@import url(#a);"#).is_empty());
+        assert!(run(r#"@import url(blob:https://a.com/uuid);"#).is_empty());
     }
 
     #[test]
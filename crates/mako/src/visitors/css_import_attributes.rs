@@ -0,0 +1,156 @@
+use swc_core::ecma::ast::{
+    CallExpr, Expr, ImportDecl, Lit, ObjectLit, Prop, PropName, PropOrSpread, Str,
+};
+use swc_core::ecma::visit::{VisitMut, VisitMutWith};
+
+use crate::ast::utils::is_dynamic_import;
+
+/// The query of the css imported with `{ type: "css" }`, which is loaded as a
+/// `CSSStyleSheet` instead of being injected into the document.
+pub const STYLESHEET_QUERY: &str = "stylesheet";
+
+/// Marks the css imported with the `type: "css"` import attribute with
+/// `?stylesheet`, e.g.
+/// `import('./theme.css', { with: { type: 'css' } })` => `import('./theme.css?stylesheet')`
+/// `import sheet from './theme.css' with { type: 'css' }` => `import sheet from './theme.css?stylesheet'`
+pub struct CSSImportAttributes {}
+
+impl VisitMut for CSSImportAttributes {
+    fn visit_mut_import_decl(&mut self, import_decl: &mut ImportDecl) {
+        if import_decl.with.as_deref().is_some_and(is_css_type) {
+            add_stylesheet_query(&mut import_decl.src);
+            import_decl.with = None;
+        }
+    }
+
+    fn visit_mut_call_expr(&mut self, expr: &mut CallExpr) {
+        if is_dynamic_import(expr)
+            && expr.args.len() == 2
+            && expr.args.iter().all(|arg| arg.spread.is_none())
+            && let box Expr::Lit(Lit::Str(_)) = &expr.args[0].expr
+            && let box Expr::Object(options) = &expr.args[1].expr
+            && let Some(with) = object_prop(options, "with")
+            && let Expr::Object(with) = with
+            && is_css_type(with)
+        {
+            expr.args.truncate(1);
+            if let box Expr::Lit(Lit::Str(src)) = &mut expr.args[0].expr {
+                add_stylesheet_query(src);
+            }
+        }
+        expr.visit_mut_children_with(self);
+    }
+}
+
+fn object_prop<'a>(object: &'a ObjectLit, key: &str) -> Option<&'a Expr> {
+    object.props.iter().find_map(|prop| match prop {
+        PropOrSpread::Prop(box Prop::KeyValue(kv)) => {
+            let matched = match &kv.key {
+                PropName::Ident(ident) => ident.sym == *key,
+                PropName::Str(str) => str.value == *key,
+                _ => false,
+            };
+            matched.then_some(kv.value.as_ref())
+        }
+        _ => None,
+    })
+}
+
+fn is_css_type(with: &ObjectLit) -> bool {
+    matches!(object_prop(with, "type"), Some(Expr::Lit(Lit::Str(str))) if str.value == *"css")
+}
+
+fn add_stylesheet_query(src: &mut Str) {
+    let separator = if src.value.contains('?') { '&' } else { '?' };
+    *src = Str::from(format!("{}{}{}", src.value, separator, STYLESHEET_QUERY));
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_core::common::GLOBALS;
+    use swc_core::ecma::visit::VisitMutWith;
+
+    use crate::ast::tests::TestUtils;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_dynamic_import() {
+        assert_eq!(
+            run(r#"import('./a.css', { with: { type: 'css' } })"#),
+            r#"import("./a.css?stylesheet");"#
+        );
+        assert_eq!(
+            run(r#"import('./a.css?v=1', { with: { "type": "css" } })"#),
+            r#"import("./a.css?v=1&stylesheet");"#
+        );
+        assert_eq!(
+            run(r#"import('./a.json', { with: { type: 'json' } })"#),
+            r#"import('./a.json', {
+    with: {
+        type: 'json'
+    }
+});"#
+        );
+    }
+
+    #[test]
+    fn test_import_decl() {
+        assert_eq!(
+            run(r#"import sheet from './a.css' with { type: 'css' };"#),
+            r#"import sheet from "./a.css?stylesheet";"#
+        );
+        assert_eq!(run(r#"import './a.css';"#), r#"import './a.css';"#);
+    }
+
+    #[test]
+    fn test_css_import_attributes() {
+        let compiler = setup_compiler("test/build/css-import-attributes", false);
+        compiler.compile().unwrap();
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let module_ids = module_graph
+            .modules()
+            .iter()
+            .map(|module| module.id.id.clone())
+            .collect::<Vec<_>>();
+        assert!(module_ids.iter().any(|id| id.ends_with("/plain.css")));
+        assert!(
+            !module_ids
+                .iter()
+                .any(|id| id.ends_with("/sheet.css") || id.ends_with("/theme.css")),
+            "{:?}",
+            module_ids
+        );
+        drop(module_graph);
+
+        // emitted as assets and fetched by the wrappers
+        let output_path = &compiler.context.config.output.path;
+        let assets = std::fs::read_dir(output_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        for name in ["sheet", "theme"] {
+            let asset = assets
+                .iter()
+                .find(|asset| asset.starts_with(&format!("{}.", name)) && asset.ends_with(".css"))
+                .unwrap_or_else(|| panic!("{:?}", assets));
+            let content = std::fs::read_to_string(output_path.join(asset)).unwrap();
+            assert!(content.contains(":host"));
+        }
+        let js = std::fs::read_to_string(output_path.join("shadow.js")).unwrap();
+        assert!(js.contains("sheet.replaceSync(text)"));
+        // plain imports are unchanged
+        let css = std::fs::read_to_string(output_path.join("shadow.css")).unwrap();
+        assert!(css.contains(".plain"));
+        assert!(!css.contains(":host"));
+    }
+
+    fn run(js_code: &str) -> String {
+        let mut test_utils = TestUtils::gen_js_ast(js_code);
+        let ast = test_utils.ast.js_mut();
+        GLOBALS.set(&test_utils.context.meta.script.globals, || {
+            let mut visitor = super::CSSImportAttributes {};
+            ast.ast.visit_mut_with(&mut visitor);
+        });
+        test_utils.js_ast_to_code()
+    }
+}
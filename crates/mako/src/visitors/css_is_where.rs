@@ -0,0 +1,316 @@
+use std::sync::Arc;
+
+use colored::Colorize;
+use swc_core::common::util::take::Take;
+use swc_core::common::{EqIgnoreSpan, Span};
+use swc_core::css::ast::{
+    ComplexSelector, ComplexSelectorChildren, CompoundSelector, ForgivingComplexSelector,
+    PseudoClassSelector, PseudoClassSelectorChildren, QualifiedRule, QualifiedRulePrelude,
+    SubclassSelector, TypeSelector,
+};
+use swc_core::css::visit::{VisitMut, VisitMutWith};
+
+use crate::ast::error::{code_frame, ErrorSpan};
+use crate::compiler::Context;
+
+/// The first versions which support `:is()` and `:where()`.
+pub const IS_WHERE_SUPPORT: [(&str, f32); 8] = [
+    ("chrome", 88.0),
+    ("edge", 88.0),
+    ("firefox", 78.0),
+    ("safari", 14.0),
+    ("ios", 14.0),
+    ("opera", 74.0),
+    ("samsung", 15.0),
+    ("android", 88.0),
+];
+
+/// Expand `:is()` and `:where()` into the selectors of their arguments, e.g.
+/// `:is(.a, .b) .c` to `.a .c, .b .c`. The selectors whose matching would
+/// change are kept, and since the expanded selectors have their own
+/// specificity, a warning is printed if it differs from the original one.
+pub struct CSSIsWhere {
+    pub context: Arc<Context>,
+}
+
+enum Lowering {
+    Unchanged,
+    // the expanded selectors, and whether the specificity is changed
+    Expanded(Vec<ComplexSelector>, bool),
+    // the arguments can't be expanded in place
+    Unsupported,
+}
+
+type Specificity = (u32, u32, u32);
+
+impl VisitMut for CSSIsWhere {
+    fn visit_mut_qualified_rule(&mut self, rule: &mut QualifiedRule) {
+        rule.visit_mut_children_with(self);
+        let QualifiedRulePrelude::SelectorList(list) = &mut rule.prelude else {
+            return;
+        };
+        let mut selectors = vec![];
+        for selector in list.children.take() {
+            match lower(&selector) {
+                Lowering::Unchanged => selectors.push(selector),
+                Lowering::Expanded(expanded, specificity_changed) => {
+                    if specificity_changed {
+                        self.warn(
+                            selector.span,
+                            "The specificity of the selector with `:is()` or `:where()` changes when lowered for the targets, which may change the cascade",
+                        );
+                    }
+                    selectors.extend(expanded);
+                }
+                Lowering::Unsupported => {
+                    self.warn(
+                        selector.span,
+                        "The selector with `:is()` or `:where()` can't be lowered for the targets, the arguments with combinators are only supported at the start of the selector",
+                    );
+                    selectors.push(selector);
+                }
+            }
+        }
+        // the selectors never match, e.g. `div:is(span)`
+        if !selectors.is_empty() {
+            list.children = selectors;
+        }
+    }
+}
+
+impl CSSIsWhere {
+    fn warn(&self, span: Span, message: &str) {
+        eprintln!(
+            "{}: {}",
+            "Warning".yellow(),
+            code_frame(ErrorSpan::Css(span), message, self.context.clone())
+        );
+    }
+}
+
+fn lower(selector: &ComplexSelector) -> Lowering {
+    for (i, child) in selector.children.iter().enumerate() {
+        let ComplexSelectorChildren::CompoundSelector(compound) = child else {
+            continue;
+        };
+        for (j, subclass) in compound.subclass_selectors.iter().enumerate() {
+            let SubclassSelector::PseudoClass(pseudo) = subclass else {
+                continue;
+            };
+            let is_where = pseudo.name.value.eq_ignore_ascii_case("where");
+            if !is_where && !pseudo.name.value.eq_ignore_ascii_case("is") {
+                continue;
+            }
+            let Some(args) = forgiving_args(pseudo) else {
+                return Lowering::Unsupported;
+            };
+            let specificities = args.iter().map(|arg| specificity(arg)).collect::<Vec<_>>();
+            let mut specificity_changed = if is_where {
+                specificities.iter().any(|s| *s != (0, 0, 0))
+            } else {
+                specificities.windows(2).any(|pair| pair[0] != pair[1])
+            };
+            let mut expanded = vec![];
+            for arg in args {
+                let has_combinator = arg
+                    .children
+                    .iter()
+                    .any(|child| matches!(child, ComplexSelectorChildren::Combinator(_)));
+                // `a > :is(.b .c)` does not equal to `a > .b .c`
+                if has_combinator && i > 0 {
+                    return Lowering::Unsupported;
+                }
+                let Some((ComplexSelectorChildren::CompoundSelector(last), init)) =
+                    arg.children.split_last()
+                else {
+                    return Lowering::Unsupported;
+                };
+                let Some(merged) = merge(compound, j, last) else {
+                    continue;
+                };
+                let children = selector.children[..i]
+                    .iter()
+                    .chain(init)
+                    .cloned()
+                    .chain(std::iter::once(ComplexSelectorChildren::CompoundSelector(
+                        merged,
+                    )))
+                    .chain(selector.children[i + 1..].iter().cloned())
+                    .collect();
+                let expanded_selector = ComplexSelector {
+                    span: selector.span,
+                    children,
+                };
+                // the other :is() of the selector
+                match lower(&expanded_selector) {
+                    Lowering::Unchanged => expanded.push(expanded_selector),
+                    Lowering::Expanded(selectors, changed) => {
+                        specificity_changed |= changed;
+                        expanded.extend(selectors);
+                    }
+                    Lowering::Unsupported => return Lowering::Unsupported,
+                }
+            }
+            return Lowering::Expanded(expanded, specificity_changed);
+        }
+    }
+    Lowering::Unchanged
+}
+
+fn forgiving_args(pseudo: &PseudoClassSelector) -> Option<Vec<&ComplexSelector>> {
+    let children = pseudo.children.as_ref()?;
+    let mut args = vec![];
+    for child in children {
+        match child {
+            PseudoClassSelectorChildren::ForgivingSelectorList(list) => {
+                for selector in &list.children {
+                    match selector {
+                        ForgivingComplexSelector::ComplexSelector(selector) => args.push(selector),
+                        // invalid selectors are ignored by browsers
+                        ForgivingComplexSelector::ListOfComponentValues(_) => return None,
+                    }
+                }
+            }
+            PseudoClassSelectorChildren::SelectorList(list) => args.extend(&list.children),
+            _ => return None,
+        }
+    }
+    Some(args)
+}
+
+// the compound with the pseudo class at `index` replaced by the argument
+fn merge(
+    compound: &CompoundSelector,
+    index: usize,
+    arg: &CompoundSelector,
+) -> Option<CompoundSelector> {
+    let type_selector = match (&compound.type_selector, &arg.type_selector) {
+        (None, type_selector) | (type_selector, None) => type_selector.clone(),
+        (Some(outer), Some(inner)) => {
+            if matches!(**outer, TypeSelector::Universal(_)) {
+                Some(inner.clone())
+            } else if matches!(**inner, TypeSelector::Universal(_)) || outer.eq_ignore_span(inner) {
+                Some(outer.clone())
+            } else {
+                return None;
+            }
+        }
+    };
+    let subclass_selectors = compound.subclass_selectors[..index]
+        .iter()
+        .chain(&arg.subclass_selectors)
+        .chain(&compound.subclass_selectors[index + 1..])
+        .cloned()
+        .collect();
+    Some(CompoundSelector {
+        span: compound.span,
+        nesting_selector: compound
+            .nesting_selector
+            .clone()
+            .or_else(|| arg.nesting_selector.clone()),
+        type_selector,
+        subclass_selectors,
+    })
+}
+
+fn specificity(selector: &ComplexSelector) -> Specificity {
+    selector
+        .children
+        .iter()
+        .fold((0, 0, 0), |(a, b, c), child| match child {
+            ComplexSelectorChildren::CompoundSelector(compound) => {
+                let (x, y, z) = compound_specificity(compound);
+                (a + x, b + y, c + z)
+            }
+            ComplexSelectorChildren::Combinator(_) => (a, b, c),
+        })
+}
+
+fn compound_specificity(compound: &CompoundSelector) -> Specificity {
+    let mut total = match compound.type_selector.as_deref() {
+        Some(TypeSelector::TagName(_)) => (0, 0, 1),
+        _ => (0, 0, 0),
+    };
+    for subclass in &compound.subclass_selectors {
+        let (a, b, c) = match subclass {
+            SubclassSelector::Id(_) => (1, 0, 0),
+            SubclassSelector::Class(_) | SubclassSelector::Attribute(_) => (0, 1, 0),
+            SubclassSelector::PseudoElement(_) => (0, 0, 1),
+            SubclassSelector::PseudoClass(pseudo) => {
+                let name = pseudo.name.value.to_ascii_lowercase();
+                match name.as_str() {
+                    "where" => (0, 0, 0),
+                    // the most specific argument
+                    "is" | "not" | "has" => forgiving_args(pseudo)
+                        .and_then(|args| args.into_iter().map(specificity).max())
+                        .unwrap_or((0, 0, 0)),
+                    _ => (0, 1, 0),
+                }
+            }
+        };
+        total = (total.0 + a, total.1 + b, total.2 + c);
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_core::css::visit::VisitMutWith;
+
+    use super::CSSIsWhere;
+    use crate::ast::tests::TestUtils;
+
+    #[test]
+    fn test_is_where() {
+        assert_eq!(run(":is(.a, .b) .c{color:red}"), ".a .c,.b .c{color:red}");
+        assert_eq!(
+            run("div:is(.a, span, *).c{color:red}"),
+            "div.a.c,div.c{color:red}"
+        );
+        assert_eq!(
+            run(".x :is(.a, .b):hover, .y{color:red}"),
+            ".x .a:hover,.x .b:hover,.y{color:red}"
+        );
+        assert_eq!(
+            run(":is(.a, .b) :where(.c, .d){color:red}"),
+            ".a .c,.a .d,.b .c,.b .d{color:red}"
+        );
+        assert_eq!(
+            run(":is(.a .b, .c) > .d{color:red}"),
+            ".a .b>.d,.c>.d{color:red}"
+        );
+        // the matching would change
+        assert_eq!(
+            run(".x > :is(.a .b){color:red}"),
+            ".x>:is(.a .b){color:red}"
+        );
+        assert_eq!(
+            run("@media (min-width: 100px){:is(.a, .b){color:red}}"),
+            "@media(min-width:100px){.a,.b{color:red}}"
+        );
+    }
+
+    #[test]
+    fn test_specificity() {
+        use super::specificity;
+        let specificity_of = |selector: &str| {
+            let mut test_utils = TestUtils::gen_css_ast(format!("{}{{}}", selector), true);
+            let ast = test_utils.ast.css_mut();
+            let rule = ast.ast.rules[0].as_qualified_rule().unwrap();
+            let list = rule.prelude.as_selector_list().unwrap();
+            specificity(&list.children[0])
+        };
+        assert_eq!(specificity_of("#a .b div"), (1, 1, 1));
+        assert_eq!(specificity_of(":is(#a, .b) :where(#c)"), (1, 0, 0));
+        assert_eq!(specificity_of("a:hover::before"), (0, 1, 2));
+    }
+
+    fn run(css_code: &str) -> String {
+        let mut test_utils = TestUtils::gen_css_ast(css_code.to_string(), true);
+        let context = test_utils.context.clone();
+        let ast = test_utils.ast.css_mut();
+        let mut visitor = CSSIsWhere { context };
+        ast.ast.visit_mut_with(&mut visitor);
+        test_utils.css_ast_to_code()
+    }
+}
@@ -0,0 +1,287 @@
+use swc_core::css::ast::{
+    ComponentValue, Declaration, DeclarationName, Function, FunctionName, Ident, SimpleBlock,
+};
+use swc_core::css::visit::{VisitMut, VisitMutWith};
+
+use crate::config::CssDirection;
+
+/// The first versions which support the logical properties.
+pub const LOGICAL_PROPERTIES_SUPPORT: [(&str, f32); 8] = [
+    ("chrome", 89.0),
+    ("edge", 89.0),
+    ("firefox", 66.0),
+    ("safari", 15.0),
+    ("ios", 15.0),
+    ("opera", 75.0),
+    ("samsung", 15.0),
+    ("android", 89.0),
+];
+
+/// Convert the logical properties and values to the physical ones by the
+/// direction, with the horizontal writing mode, e.g. `margin-inline: 1px 2px`
+/// to `margin-left: 1px; margin-right: 2px` for ltr.
+pub struct CSSLogicalProperties {
+    pub direction: CssDirection,
+}
+
+enum Physical {
+    // one property
+    One(String),
+    // the start and the end side, the value is one or two values for them
+    Pair(String, String),
+    // the sides share the whole value, e.g. `border-inline: 1px solid`
+    Both(String, String),
+}
+
+impl VisitMut for CSSLogicalProperties {
+    fn visit_mut_simple_block(&mut self, block: &mut SimpleBlock) {
+        block.visit_mut_children_with(self);
+        if !block.value.iter().any(|value| self.is_logical(value)) {
+            return;
+        }
+        let mut values = Vec::with_capacity(block.value.len());
+        for value in std::mem::take(&mut block.value) {
+            match value {
+                ComponentValue::Declaration(declaration) => values.extend(
+                    self.lower(&declaration)
+                        .unwrap_or_else(|| vec![*declaration])
+                        .into_iter()
+                        .map(|declaration| ComponentValue::Declaration(Box::new(declaration))),
+                ),
+                value => values.push(value),
+            }
+        }
+        block.value = values;
+    }
+
+    fn visit_mut_declaration(&mut self, declaration: &mut Declaration) {
+        let Some(name) = declaration_name(declaration) else {
+            return;
+        };
+        if name != "float" && name != "clear" {
+            return;
+        }
+        let (start, end) = self.inline_sides();
+        for value in &mut declaration.value {
+            if let ComponentValue::Ident(ident) = value {
+                let side = match &*ident.value.to_ascii_lowercase() {
+                    "inline-start" => start,
+                    "inline-end" => end,
+                    _ => continue,
+                };
+                **ident = Ident {
+                    span: ident.span,
+                    value: side.into(),
+                    raw: None,
+                };
+            }
+        }
+    }
+}
+
+impl CSSLogicalProperties {
+    fn inline_sides(&self) -> (&'static str, &'static str) {
+        match self.direction {
+            CssDirection::Ltr => ("left", "right"),
+            CssDirection::Rtl => ("right", "left"),
+        }
+    }
+
+    fn is_logical(&self, value: &ComponentValue) -> bool {
+        match value {
+            ComponentValue::Declaration(declaration) => {
+                declaration_name(declaration).is_some_and(|name| self.physical(&name).is_some())
+            }
+            _ => false,
+        }
+    }
+
+    fn lower(&self, declaration: &Declaration) -> Option<Vec<Declaration>> {
+        let physical = self.physical(&declaration_name(declaration)?)?;
+        let with = |name: String, value: Vec<ComponentValue>| Declaration {
+            span: declaration.span,
+            name: DeclarationName::Ident(Ident {
+                span: declaration.span,
+                value: name.into(),
+                raw: None,
+            }),
+            value,
+            important: declaration.important.clone(),
+        };
+        let value = &declaration.value;
+        Some(match physical {
+            Physical::One(name) => vec![with(name, value.clone())],
+            Physical::Both(start, end) => {
+                vec![with(start, value.clone()), with(end, value.clone())]
+            }
+            Physical::Pair(start, end) => match &value[..] {
+                // a variable may be the two values
+                [value] if !is_var(value) => {
+                    vec![
+                        with(start, vec![value.clone()]),
+                        with(end, vec![value.clone()]),
+                    ]
+                }
+                [start_value, end_value] => vec![
+                    with(start, vec![start_value.clone()]),
+                    with(end, vec![end_value.clone()]),
+                ],
+                _ => return None,
+            },
+        })
+    }
+
+    fn physical(&self, name: &str) -> Option<Physical> {
+        let (start, end) = self.inline_sides();
+        let one = |name: &str| Some(Physical::One(name.to_string()));
+        match name {
+            "inline-size" => return one("width"),
+            "block-size" => return one("height"),
+            "min-inline-size" => return one("min-width"),
+            "max-inline-size" => return one("max-width"),
+            "min-block-size" => return one("min-height"),
+            "max-block-size" => return one("max-height"),
+            _ => {}
+        }
+        if let Some(corner) = name
+            .strip_prefix("border-")
+            .and_then(|name| name.strip_suffix("-radius"))
+        {
+            let (block, inline) = corner.split_once('-')?;
+            let block = match block {
+                "start" => "top",
+                "end" => "bottom",
+                _ => return None,
+            };
+            let inline = match inline {
+                "start" => start,
+                "end" => end,
+                _ => return None,
+            };
+            return Some(Physical::One(format!("border-{}-{}-radius", block, inline)));
+        }
+
+        let (property, rest) =
+            ["inset", "margin", "padding", "border"]
+                .iter()
+                .find_map(|property| {
+                    Some((*property, name.strip_prefix(property)?.strip_prefix('-')?))
+                })?;
+        let (sides, rest) = match rest.strip_prefix("inline") {
+            Some(rest) => ((start, end), rest),
+            None => (("top", "bottom"), rest.strip_prefix("block")?),
+        };
+        let (side, suffix) = match (rest.strip_prefix("-start"), rest.strip_prefix("-end")) {
+            (Some(suffix), _) => (Some(sides.0), suffix),
+            (_, Some(suffix)) => (Some(sides.1), suffix),
+            _ => (None, rest),
+        };
+        let valid_suffix = match property {
+            "border" => ["", "-width", "-style", "-color"].contains(&suffix),
+            _ => suffix.is_empty(),
+        };
+        if !valid_suffix {
+            return None;
+        }
+        let name = |side: &str| match property {
+            "inset" => side.to_string(),
+            _ => format!("{}-{}{}", property, side, suffix),
+        };
+        Some(match side {
+            Some(side) => Physical::One(name(side)),
+            None if property == "border" && suffix.is_empty() => {
+                Physical::Both(name(sides.0), name(sides.1))
+            }
+            None => Physical::Pair(name(sides.0), name(sides.1)),
+        })
+    }
+}
+
+fn declaration_name(declaration: &Declaration) -> Option<String> {
+    match &declaration.name {
+        DeclarationName::Ident(ident) => Some(ident.value.to_string().to_ascii_lowercase()),
+        DeclarationName::DashedIdent(_) => None,
+    }
+}
+
+fn is_var(value: &ComponentValue) -> bool {
+    matches!(
+        value,
+        ComponentValue::Function(box Function {
+            name: FunctionName::Ident(name),
+            ..
+        }) if name.value.eq_ignore_ascii_case("var") || name.value.eq_ignore_ascii_case("env")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_core::css::visit::VisitMutWith;
+
+    use super::CSSLogicalProperties;
+    use crate::ast::tests::TestUtils;
+    use crate::config::CssDirection;
+
+    #[test]
+    fn test_logical_properties() {
+        assert_eq!(
+            run(".a{inset-inline:0 10px;color:red}", CssDirection::Ltr),
+            ".a{left:0;right:10px;color:red}"
+        );
+        assert_eq!(
+            run(".a{inset-block:0}", CssDirection::Ltr),
+            ".a{top:0;bottom:0}"
+        );
+        assert_eq!(
+            run(".a{margin-inline-start:4px!important}", CssDirection::Ltr),
+            ".a{margin-left:4px!important}"
+        );
+        assert_eq!(
+            run(".a{padding-inline:1px 2px}", CssDirection::Rtl),
+            ".a{padding-right:1px;padding-left:2px}"
+        );
+        assert_eq!(
+            run(".a{border-inline:1px solid red}", CssDirection::Ltr),
+            ".a{border-left:1px solid red;border-right:1px solid red}"
+        );
+        assert_eq!(
+            run(".a{border-block-end-color:red}", CssDirection::Ltr),
+            ".a{border-bottom-color:red}"
+        );
+        assert_eq!(
+            run(".a{border-start-end-radius:4px}", CssDirection::Ltr),
+            ".a{border-top-right-radius:4px}"
+        );
+        assert_eq!(
+            run(
+                ".a{inline-size:10px;max-block-size:20px}",
+                CssDirection::Ltr
+            ),
+            ".a{width:10px;max-height:20px}"
+        );
+        assert_eq!(
+            run(".a{float:inline-start}", CssDirection::Rtl),
+            ".a{float:right}"
+        );
+        // a variable may be the two values
+        assert_eq!(
+            run(".a{margin-inline:var(--x)}", CssDirection::Ltr),
+            ".a{margin-inline:var(--x)}"
+        );
+        assert_eq!(
+            run(
+                "@media (min-width:1px){.a{inset-inline-end:0}}",
+                CssDirection::Ltr
+            ),
+            "@media(min-width:1px){.a{right:0}}"
+        );
+    }
+
+    fn run(css_code: &str, direction: CssDirection) -> String {
+        let mut test_utils = TestUtils::gen_css_ast(css_code.to_string(), true);
+        let ast = test_utils.ast.css_mut();
+        let mut visitor = CSSLogicalProperties { direction };
+        ast.ast.visit_mut_with(&mut visitor);
+        test_utils.css_ast_to_code()
+    }
+}
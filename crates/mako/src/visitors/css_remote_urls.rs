@@ -0,0 +1,394 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use regex::{Captures, Regex};
+use swc_core::common::Span;
+use swc_core::css::ast::{AtRule, AtRuleName, ImportHref, ImportPrelude, Str, Url, UrlValue};
+use swc_core::css::visit::{VisitMut, VisitMutWith};
+
+use crate::compiler::Context;
+use crate::config::RemoteCssConfig;
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+
+/// Handles the remote css of `@import` and the remote fonts of `@font-face`
+/// by `remoteCSS`, which are requested from the other origins before the
+/// page is rendered. `warn` reports them as `MAKO_REMOTE_CSS`, and `download`
+/// fetches them at build time and replaces the urls with the cached files,
+/// so the css is bundled as a module and the fonts are emitted as assets by
+/// `CSSAssets`, which runs after it. It runs after `@import url()` is
+/// replaced with `@import ""`.
+pub struct CSSRemoteUrls {
+    context: Arc<Context>,
+    path: PathBuf,
+    in_font_face: bool,
+    /// the first failed download, which fails the transform
+    pub error: Option<anyhow::Error>,
+}
+
+impl CSSRemoteUrls {
+    pub fn new(context: Arc<Context>, path: PathBuf) -> Self {
+        Self {
+            context,
+            path,
+            in_font_face: false,
+            error: None,
+        }
+    }
+
+    // the local path of the remote url, or none if it's kept
+    fn handle(&mut self, url: &str, span: Span, is_import: bool) -> Option<String> {
+        let remote = remote_url(url)?;
+        match self.context.config.remote_css {
+            RemoteCssConfig::Preserve => None,
+            RemoteCssConfig::Warn => {
+                self.report(url, span, is_import);
+                None
+            }
+            RemoteCssConfig::Download => {
+                if self.error.is_some() {
+                    return None;
+                }
+                // the fragment is not fetched, e.g. url(font.svg#icon)
+                let (remote, fragment) = match remote.split_once('#') {
+                    Some((remote, fragment)) => (remote.to_string(), format!("#{}", fragment)),
+                    None => (remote, String::new()),
+                };
+                let downloaded = if is_import {
+                    self.context
+                        .remote_assets
+                        .download(&remote, "css", |fetched_url, content| {
+                            absolute_css_urls(fetched_url, &String::from_utf8_lossy(&content))
+                                .into_bytes()
+                        })
+                } else {
+                    self.context.remote_assets.download(
+                        &remote,
+                        &font_extension(&remote),
+                        |_, content| content,
+                    )
+                };
+                match downloaded {
+                    Ok(path) => Some(format!("{}{}", path.to_string_lossy(), fragment)),
+                    Err(err) => {
+                        self.error = Some(err);
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    fn report(&self, url: &str, span: Span, is_import: bool) {
+        let message = if is_import {
+            format!(
+                "The css {} is imported from another origin by {}, which blocks the rendering until it's loaded, set `remoteCSS` to \"download\" to bundle it",
+                url,
+                self.path.display()
+            )
+        } else {
+            format!(
+                "The font {} of @font-face is loaded from another origin by {}, set `remoteCSS` to \"download\" to emit it as an asset",
+                url,
+                self.path.display()
+            )
+        };
+        let line = self.context.meta.css.cm.lookup_char_pos(span.lo).line;
+        self.context.diagnostics.report(
+            Diagnostic::new(DiagnosticCode::RemoteCss, message)
+                .paths(vec![self.path.clone()])
+                .locations(vec![(self.path.clone(), line)]),
+            &self.context,
+        );
+    }
+}
+
+impl VisitMut for CSSRemoteUrls {
+    fn visit_mut_import_prelude(&mut self, n: &mut ImportPrelude) {
+        if let box ImportHref::Str(str) = &mut n.href
+            && let Some(path) = self.handle(&str.value, str.span, true)
+        {
+            str.value = path.into();
+            str.raw = None;
+        }
+    }
+
+    fn visit_mut_at_rule(&mut self, n: &mut AtRule) {
+        let is_font_face = matches!(
+            &n.name,
+            AtRuleName::Ident(ident) if ident.value.eq_ignore_ascii_case("font-face")
+        );
+        let in_font_face = self.in_font_face;
+        self.in_font_face |= is_font_face;
+        n.visit_mut_children_with(self);
+        self.in_font_face = in_font_face;
+    }
+
+    fn visit_mut_url(&mut self, n: &mut Url) {
+        if !self.in_font_face {
+            return;
+        }
+        let value = match n.value.as_deref() {
+            Some(UrlValue::Str(str)) => str.value.to_string(),
+            Some(UrlValue::Raw(raw)) => raw.value.to_string(),
+            None => return,
+        };
+        if let Some(path) = self.handle(&value, n.span, false) {
+            n.value = Some(Box::new(UrlValue::Str(Str {
+                span: n.span,
+                value: path.into(),
+                raw: None,
+            })));
+        }
+    }
+}
+
+// the http and https urls, the protocol-relative ones are fetched with https
+fn remote_url(url: &str) -> Option<String> {
+    let lower = url.to_lowercase();
+    if lower.starts_with("http://") || lower.starts_with("https://") {
+        Some(url.to_string())
+    } else if url.starts_with("//") {
+        Some(format!("https:{}", url))
+    } else {
+        None
+    }
+}
+
+fn font_extension(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .filter(|extension| extension.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or_else(|| "font".to_string())
+}
+
+/// The relative urls of the fetched css are relative to the url it's fetched
+/// from, which are made absolute before it's cached, so the fonts and the
+/// nested imports are remote urls too.
+pub fn absolute_css_urls(base: &url::Url, css: &str) -> String {
+    static URL_REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex = URL_REGEX
+        .get_or_init(|| Regex::new(r#"(url\(\s*['"]?|@import\s+['"])([^'")\s]+)"#).unwrap());
+    regex
+        .replace_all(css, |captures: &Captures| {
+            let url = &captures[2];
+            let lower = url.to_lowercase();
+            let is_relative = !(url.starts_with('#')
+                || url.starts_with("//")
+                || lower.starts_with("data:")
+                || lower.contains("://"));
+            match base.join(url) {
+                Ok(absolute) if is_relative => format!("{}{}", &captures[1], absolute),
+                _ => captures[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::{absolute_css_urls, font_extension, remote_url};
+    use crate::compiler::{Args, Compiler};
+    use crate::config::Config;
+    use crate::diagnostics::DiagnosticCode;
+    use crate::testing::TempDir;
+    use crate::utils::remote_assets::{CACHE_DIR, LOCK_FILE};
+
+    const FONT_SIZE: usize = 20_000;
+
+    // serves the css which redirects to the one with a relative font url,
+    // returns the port and the count of the requests
+    fn serve() -> (u16, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let count = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut buf = vec![0; 4096];
+                let len = stream.read(&mut buf).unwrap();
+                let request = String::from_utf8_lossy(&buf[..len]).to_string();
+                let path = request.split(' ').nth(1).unwrap_or_default().to_string();
+                count.fetch_add(1, Ordering::SeqCst);
+                let (status, headers, body) = match path.as_str() {
+                    "/fonts.css" => ("302 Found", "Location: /css/inter.css\r\n", vec![]),
+                    "/css/inter.css" => (
+                        "200 OK",
+                        "",
+                        br#"@font-face { font-family: Inter; src: url(../files/inter.woff2) format("woff2"); }
+body { font-family: Inter; }"#
+                            .to_vec(),
+                    ),
+                    "/files/inter.woff2" => ("200 OK", "", vec![b'w'; FONT_SIZE]),
+                    _ => ("404 Not Found", "", vec![]),
+                };
+                let head = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    headers,
+                    body.len()
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        (port, requests)
+    }
+
+    fn setup(name: &str, css: &str) -> TempDir {
+        let root = TempDir::new(name);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/index.js"), "import './index.css';").unwrap();
+        std::fs::write(root.join("src/index.css"), css).unwrap();
+        root
+    }
+
+    fn compile(root: &Path, remote_css: &str) -> anyhow::Result<Compiler> {
+        let edit = format!(
+            r#"{{"entry":{{"index":"./src/index.js"}},"remoteCSS":"{}","minify":false,"hash":false}}"#,
+            remote_css
+        );
+        let config = Config::new(root, None, Some(&edit)).unwrap();
+        let compiler = Compiler::new(config, root.to_path_buf(), Args { watch: false }, None)?;
+        compiler.compile()?;
+        Ok(compiler)
+    }
+
+    #[test]
+    fn test_download() {
+        let (port, requests) = serve();
+        let remote = format!("http://127.0.0.1:{}/fonts.css", port);
+        let root = setup(
+            "remote-css-download",
+            &format!("@import url(\"{}\");\n.a {{ color: red; }}", remote),
+        );
+
+        let compiler = compile(&root, "download").unwrap();
+        let output = &compiler.context.config.output.path;
+        let css = std::fs::read_to_string(output.join("index.css")).unwrap();
+        assert!(!css.contains("127.0.0.1"), "{}", css);
+        assert!(css.contains("body {"), "{}", css);
+        let fonts = std::fs::read_dir(output)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".woff2"))
+            .collect::<Vec<_>>();
+        assert_eq!(fonts.len(), 1, "{:?}", fonts);
+        assert!(css.contains(&fonts[0]), "{}", css);
+        assert_eq!(
+            std::fs::read(output.join(&fonts[0])).unwrap().len(),
+            FONT_SIZE
+        );
+        // the redirect, the css and the font
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        let lock: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(root.join(LOCK_FILE)).unwrap()).unwrap();
+        let urls = lock
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                format!("http://127.0.0.1:{}/files/inter.woff2", port),
+                remote.clone()
+            ]
+        );
+        assert!(lock[&remote]["integrity"]
+            .as_str()
+            .unwrap()
+            .starts_with("sha256-"));
+
+        // from the cache
+        let compiler = compile(&root, "download").unwrap();
+        let output = &compiler.context.config.output.path;
+        assert_eq!(
+            std::fs::read_to_string(output.join("index.css")).unwrap(),
+            css
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(compiler.context.remote_assets.used().len(), 2);
+
+        // the locked content is verified when it's fetched again
+        let lock = std::fs::read_to_string(root.join(LOCK_FILE))
+            .unwrap()
+            .replacen("sha256-", "sha256-changed", 1);
+        std::fs::write(root.join(LOCK_FILE), lock).unwrap();
+        std::fs::remove_dir_all(root.join(CACHE_DIR)).unwrap();
+        let err = compile(&root, "download").err().unwrap().to_string();
+        assert!(err.contains("is changed"), "{}", err);
+    }
+
+    #[test]
+    fn test_offline() {
+        // the port of no server
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let root = setup(
+            "remote-css-offline",
+            &format!("@import \"http://127.0.0.1:{}/fonts.css\";", port),
+        );
+        let err = compile(&root, "download").err().unwrap().to_string();
+        assert!(err.contains("Failed to download"), "{}", err);
+        assert!(err.contains("needs the network once"), "{}", err);
+
+        // kept as is
+        let compiler = compile(&root, "warn").unwrap();
+        let warnings = compiler
+            .context
+            .diagnostics
+            .get_reported()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.code == DiagnosticCode::RemoteCss)
+            .count();
+        assert_eq!(warnings, 1);
+        let css =
+            std::fs::read_to_string(compiler.context.config.output.path.join("index.css")).unwrap();
+        assert!(css.contains(&format!("127.0.0.1:{}", port)), "{}", css);
+    }
+
+    #[test]
+    fn test_absolute_css_urls() {
+        let base = url::Url::parse("https://fonts.example.com/css/inter.css?v=1").unwrap();
+        let css = r#"@import "nested.css";
+@import url('https://other.com/a.css');
+@font-face { src: url(../files/inter.woff2) format("woff2"), url("inter.woff#iefix"), url(data:font/woff2;base64,AA); }
+.icon { background: url(#mask) }"#;
+        assert_eq!(
+            absolute_css_urls(&base, css),
+            r#"@import "https://fonts.example.com/css/nested.css";
+@import url('https://other.com/a.css');
+@font-face { src: url(https://fonts.example.com/files/inter.woff2) format("woff2"), url("https://fonts.example.com/css/inter.woff#iefix"), url(data:font/woff2;base64,AA); }
+.icon { background: url(#mask) }"#
+        );
+    }
+
+    #[test]
+    fn test_remote_url() {
+        assert_eq!(
+            remote_url("//fonts.example.com/a.css").as_deref(),
+            Some("https://fonts.example.com/a.css")
+        );
+        assert_eq!(
+            remote_url("HTTP://a.com/a.css").as_deref(),
+            Some("HTTP://a.com/a.css")
+        );
+        assert_eq!(remote_url("./a.css"), None);
+        assert_eq!(remote_url("data:text/css,a"), None);
+        assert_eq!(font_extension("https://a.com/inter.WOFF2?v=1#x"), "woff2");
+        assert_eq!(font_extension("https://a.com/s/AbC"), "font");
+    }
+}
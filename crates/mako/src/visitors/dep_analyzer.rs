@@ -8,7 +8,7 @@ use swc_core::ecma::visit::{Visit, VisitWith};
 
 use crate::ast::utils;
 use crate::compiler::Context;
-use crate::module::{Dependency, ImportOptions, ResolveType};
+use crate::module::{Dependency, ImportMode, ImportOptions, ResolveType};
 use crate::utils::create_cached_regex;
 
 pub struct DepAnalyzer {
@@ -39,18 +39,7 @@ impl DepAnalyzer {
     }
 
     fn analyze_import_options(&self, magic_comments_pos: BytePos) -> ImportOptions {
-        let comments_texts = self
-            .context
-            .meta
-            .script
-            .origin_comments
-            .read()
-            .unwrap()
-            .get_swc_comments()
-            .get_leading(magic_comments_pos)
-            .map_or(Vec::new(), |cms| {
-                cms.iter().map(|c| c.text.to_string()).collect()
-            });
+        let comments_texts = leading_comment_texts(&self.context, magic_comments_pos);
 
         let chunk_name = comments_texts.iter().find_map(|t| {
             get_magic_comment_chunk_name_regex()
@@ -66,10 +55,46 @@ impl DepAnalyzer {
                 })
         });
 
-        ImportOptions { chunk_name, ignore }
+        ImportOptions {
+            chunk_name,
+            ignore,
+            mode: magic_comment_import_mode(&comments_texts),
+        }
     }
 }
 
+pub(crate) fn leading_comment_texts(context: &Arc<Context>, pos: BytePos) -> Vec<String> {
+    context
+        .meta
+        .script
+        .origin_comments
+        .read()
+        .unwrap()
+        .get_swc_comments()
+        .get_leading(pos)
+        .map_or(Vec::new(), |cms| {
+            cms.iter().map(|c| c.text.to_string()).collect()
+        })
+}
+
+/// The mode of `import(/* webpackMode: "eager" */ "./a")`, lazy by default.
+pub(crate) fn magic_comment_import_mode(comments_texts: &[String]) -> ImportMode {
+    comments_texts
+        .iter()
+        .find_map(|t| {
+            get_magic_comment_mode_regex()
+                .captures(t.trim())
+                .and_then(|matched| match matched.get(2)?.as_str() {
+                    "lazy" => Some(ImportMode::Lazy),
+                    "lazy-once" => Some(ImportMode::LazyOnce),
+                    "eager" => Some(ImportMode::Eager),
+                    "weak" => Some(ImportMode::Weak),
+                    _ => None,
+                })
+        })
+        .unwrap_or_default()
+}
+
 impl Visit for DepAnalyzer {
     fn visit_module_decl(&mut self, decl: &ModuleDecl) {
         match decl {
@@ -114,7 +139,10 @@ impl Visit for DepAnalyzer {
     fn visit_call_expr(&mut self, expr: &CallExpr) {
         // e.g.
         // require('a')
-        if utils::is_commonjs_require(expr, &self.unresolved_mark) {
+        // require.resolve('a'), the module is bundled for its id
+        if utils::is_commonjs_require(expr, &self.unresolved_mark)
+            || utils::is_commonjs_require_resolve(expr, &self.unresolved_mark)
+        {
             if let Some(src) = utils::get_first_str_arg(expr) {
                 self.add_dependency(src, ResolveType::Require, Some(expr.span));
                 return;
@@ -241,12 +269,17 @@ fn get_magic_comment_ignore_regex() -> Regex {
     create_cached_regex(r#"(makoIgnore|webpackIgnore):\s*(true|false)"#)
 }
 
+fn get_magic_comment_mode_regex() -> Regex {
+    create_cached_regex(r#"(makoMode|webpackMode):\s*['"`]([\w-]+)['"`]"#)
+}
+
 #[cfg(test)]
 mod tests {
     use swc_core::common::GLOBALS;
     use swc_core::ecma::visit::VisitWith;
 
     use crate::ast::tests::TestUtils;
+    use crate::module::{Dependency, ImportMode, ResolveType};
 
     #[test]
     fn test_normal() {
@@ -275,6 +308,8 @@ mod tests {
         assert_eq!(run(r#"require('a');"#), vec!["a"]);
         assert!(run(r#"const require = 'a'; require('a');"#).is_empty());
         assert!(run(r#"require(a);"#).is_empty());
+        assert_eq!(run(r#"require.resolve('a');"#), vec!["a"]);
+        assert!(run(r#"const require = {}; require.resolve('a');"#).is_empty());
     }
 
     #[test]
@@ -302,18 +337,44 @@ mod tests {
         assert_eq!(run(r#"require(require("b"))"#), vec!["b"]);
     }
 
+    #[test]
+    fn test_magic_comment_mode() {
+        let modes = analyze(
+            r#"
+import(/* webpackMode: "eager" */ 'a');
+import(/* makoMode: 'weak' */ 'b');
+import(/* webpackMode: "lazy-once" */ 'c');
+import(/* webpackChunkName: "d" */ 'd');
+            "#,
+        )
+        .into_iter()
+        .map(|dep| match dep.resolve_type {
+            ResolveType::DynamicImport(import_options) => import_options.mode,
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(
+            modes,
+            vec![
+                ImportMode::Eager,
+                ImportMode::Weak,
+                ImportMode::LazyOnce,
+                ImportMode::Lazy
+            ]
+        );
+    }
+
     fn run(js_code: &str) -> Vec<String> {
+        analyze(js_code).into_iter().map(|dep| dep.source).collect()
+    }
+
+    fn analyze(js_code: &str) -> Vec<Dependency> {
         let mut test_utils = TestUtils::gen_js_ast(js_code);
         let ast = test_utils.ast.js_mut();
         let mut analyzer = super::DepAnalyzer::new(ast.unresolved_mark, test_utils.context.clone());
         GLOBALS.set(&test_utils.context.meta.script.globals, || {
             ast.ast.visit_with(&mut analyzer);
         });
-        let sources = analyzer
-            .dependencies
-            .iter()
-            .map(|dep| dep.source.clone())
-            .collect();
-        sources
+        analyzer.dependencies
     }
 }
@@ -3,17 +3,21 @@ use std::sync::Arc;
 
 use swc_core::common::{Mark, DUMMY_SP};
 use swc_core::ecma::ast::{
-    AssignOp, BlockStmt, Expr, ExprOrSpread, FnExpr, Function, Ident, ImportDecl, Lit, NamedExport,
-    NewExpr, Stmt, Str, ThrowStmt, VarDeclKind,
+    AssignOp, BinaryOp, BlockStmt, Expr, ExprOrSpread, FnExpr, Function, Ident, ImportDecl, Lit,
+    NamedExport, NewExpr, Stmt, Str, ThrowStmt, VarDeclKind,
 };
 use swc_core::ecma::utils::{member_expr, quote_ident, quote_str, ExprFactory};
 use swc_core::ecma::visit::{VisitMut, VisitMutWith};
 
-use crate::ast::file::parse_path;
-use crate::ast::utils::{is_commonjs_require, is_dynamic_import, is_remote_or_data};
+use crate::ast::utils::{
+    get_first_str_arg, is_commonjs_require, is_commonjs_require_resolve, is_dynamic_import,
+    is_remote_or_data,
+};
 use crate::ast::DUMMY_CTXT;
 use crate::compiler::Context;
-use crate::module::{Dependency, ModuleId};
+use crate::generate::webworker::is_webworker_chunk;
+use crate::module::{Dependency, ImportMode, ModuleId};
+use crate::utils::file_request::FileRequest;
 use crate::visitors::virtual_css_modules::is_css_path;
 
 pub struct DepReplacer<'a> {
@@ -28,6 +32,8 @@ pub struct ResolvedReplaceInfo {
     pub chunk_id: Option<String>,
     pub to_replace_source: String,
     pub resolved_module_id: ModuleId,
+    // how the module is loaded by import()
+    pub import_mode: ImportMode,
 }
 
 #[derive(Debug, Clone)]
@@ -93,9 +99,47 @@ pub fn miss_throw_stmt<T: AsRef<str>>(source: T) -> Expr {
     quote_ident!("Object").as_call(DUMMY_SP, vec![iife.as_arg()])
 }
 
+impl DepReplacer<'_> {
+    // the css of the workers is not injected, the import is the url of the
+    // css file of the chunk instead, e.g.
+    // `__mako_require__.publicPath + __mako_require__.getChunkFiles("sw.ts")[0]`
+    fn webworker_css_url(&self, source: &str) -> Option<Expr> {
+        let module_graph = self.context.module_graph.read().unwrap();
+        let chunk_graph = self.context.chunk_graph.read().unwrap();
+        let chunk = chunk_graph.get_chunk_for_module(self.module_id)?;
+        if !is_webworker_chunk(chunk, &chunk_graph, &self.context.config) {
+            return None;
+        }
+        let dep_module_id =
+            module_graph.get_dependency_module_by_source(self.module_id, &source.to_string())?;
+        let css_chunk = chunk_graph.get_chunk_for_module(dep_module_id)?;
+        let files = member_expr!(DUMMY_CTXT, DUMMY_SP, __mako_require__.getChunkFiles).as_call(
+            DUMMY_SP,
+            vec![quote_str!(css_chunk.id.id.to_string()).as_arg()],
+        );
+        Some(
+            member_expr!(DUMMY_CTXT, DUMMY_SP, __mako_require__.publicPath).make_bin(
+                BinaryOp::Add,
+                files.computed_member::<Expr>(Lit::from(0.0).into()),
+            ),
+        )
+    }
+}
+
 impl VisitMut for DepReplacer<'_> {
     fn visit_mut_expr(&mut self, expr: &mut Expr) {
         if let Expr::Call(call_expr) = expr {
+            // require.resolve('./a') -> "<module id>"
+            if is_commonjs_require_resolve(call_expr, &self.unresolved_mark) {
+                if let Some(source) = get_first_str_arg(call_expr) {
+                    if self.to_replace.missing.contains_key(&source) {
+                        *expr = miss_throw_stmt(&source);
+                    } else if let Some(replacement) = self.to_replace.resolved.get(&source) {
+                        *expr = quote_str!(replacement.to_replace_source.clone()).into();
+                    }
+                    return;
+                }
+            }
             let is_commonjs_require_flag = is_commonjs_require(call_expr, &self.unresolved_mark);
             let is_dynamic_import_flag = is_dynamic_import(call_expr);
             if is_commonjs_require_flag || is_dynamic_import_flag {
@@ -129,8 +173,11 @@ impl VisitMut for DepReplacer<'_> {
                     // TODO: add testcases for this
                     let is_replaceable_css =
                         if let Some(replace_info) = self.to_replace.resolved.get(&source_string) {
-                            let (path, _search, query, _) =
-                                parse_path(&replace_info.resolved_module_id.id).unwrap();
+                            let FileRequest {
+                                path,
+                                params: query,
+                                ..
+                            } = FileRequest::parse(&replace_info.resolved_module_id.id).unwrap();
                             // when inline_css is enabled
                             // css is parsed as js modules
                             self.context.config.inline_css.is_none()
@@ -142,7 +189,10 @@ impl VisitMut for DepReplacer<'_> {
                     if is_replaceable_css {
                         // remove `require('./xxx.css');`
                         if is_commonjs_require_flag {
-                            *expr = Expr::Lit(quote_str!("").into());
+                            *expr = match self.webworker_css_url(&source_string) {
+                                Some(url) => url,
+                                None => Expr::Lit(quote_str!("").into()),
+                            };
                             return;
                         } else {
                             // `import('./xxx.css')` 中的 css 模块会被拆分到单独的 chunk, 这里需要改为加载 css chunk
@@ -156,7 +206,7 @@ impl VisitMut for DepReplacer<'_> {
                                     chunk_graph.get_chunk_for_module(&dep_module_id.clone());
 
                                 if let Some(chunk) = chunk {
-                                    let chunk_id = chunk.id.id.clone();
+                                    let chunk_id = chunk.id.id.to_string();
                                     // `import('./xxx.css')` => `__mako_require__.ensure('./xxx.css')`
                                     *expr =
                                         member_expr!(DUMMY_CTXT, DUMMY_SP, __mako_require__.ensure)
@@ -254,7 +304,7 @@ mod tests {
 
     use super::{DepReplacer, DependenciesToReplace, ResolvedReplaceInfo};
     use crate::ast::tests::TestUtils;
-    use crate::module::{Dependency, ImportType, ModuleId, ResolveType};
+    use crate::module::{Dependency, ImportMode, ImportType, ModuleId, ResolveType};
 
     #[test]
     fn test_require() {
@@ -268,6 +318,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_require_resolve() {
+        assert_eq!(
+            run(
+                r#"require.resolve("react");"#,
+                build_resolved("react", "/root/node_modules/react/index.js"),
+                Default::default()
+            ),
+            r#""/root/node_modules/react/index.js";"#,
+        );
+        assert_eq!(
+            run(
+                r#"const require = {}; require.resolve("react");"#,
+                build_resolved("react", "/root/node_modules/react/index.js"),
+                Default::default()
+            ),
+            r#"const require = {};
+require.resolve("react");"#,
+        );
+    }
+
     #[test]
     fn test_import() {
         assert_eq!(
@@ -351,6 +422,7 @@ try {
                 chunk_id: None,
                 to_replace_source: module_id.into(),
                 resolved_module_id: "".into(),
+                import_mode: ImportMode::default(),
             }
         }
     }
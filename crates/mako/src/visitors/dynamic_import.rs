@@ -14,6 +14,7 @@ use crate::ast::utils::{is_dynamic_import, promise_all, require_ensure};
 use crate::ast::DUMMY_CTXT;
 use crate::compiler::Context;
 use crate::generate::chunk::ChunkId;
+use crate::module::ImportMode;
 use crate::visitors::dep_replacer::DependenciesToReplace;
 
 pub struct DynamicImport<'a> {
@@ -113,12 +114,27 @@ impl<'a> VisitMut for DynamicImport<'a> {
                     *expr = {
                         // let load_promise = self.make_load_promise(&chunk_ids);
 
-                        let load_promise = if self.context.args.watch
-                            && self.context.config.experimental.central_ensure
-                        {
-                            self.central_ensure(&generated_module_id)
-                        } else {
-                            self.inline_ensure(resolved_info, &self.context)
+                        let load_promise = match resolved_info.import_mode {
+                            // the module is in the chunk of the importer
+                            ImportMode::Eager => {
+                                member_expr!(DUMMY_CTXT, DUMMY_SP, Promise.resolve)
+                                    .as_call(DUMMY_SP, vec![])
+                            }
+                            ImportMode::Weak => {
+                                member_expr!(DUMMY_CTXT, DUMMY_SP, __mako_require__.weak).as_call(
+                                    DUMMY_SP,
+                                    vec![quote_str!(generated_module_id.clone()).as_arg()],
+                                )
+                            }
+                            ImportMode::Lazy | ImportMode::LazyOnce => {
+                                if self.context.args.watch
+                                    && self.context.config.experimental.central_ensure
+                                {
+                                    self.central_ensure(&generated_module_id)
+                                } else {
+                                    self.inline_ensure(resolved_info, &self.context)
+                                }
+                            }
                         };
 
                         let lazy_require_call =
@@ -135,8 +151,21 @@ impl<'a> VisitMut for DynamicImport<'a> {
                                 vec![self.interop.clone().as_arg(), lazy_require_call.as_arg()],
                             );
 
-                        member_expr!(@EXT, DUMMY_SP, load_promise.into(), then)
-                            .as_call(call_expr.span, vec![dr_call.as_arg()])
+                        let import_promise =
+                            member_expr!(@EXT, DUMMY_SP, load_promise.into(), then)
+                                .as_call(call_expr.span, vec![dr_call.as_arg()]);
+                        // report the imported module for the ssr, see loadableManifest
+                        if self.context.config.loadable_manifest.is_some() {
+                            member_expr!(DUMMY_CTXT, DUMMY_SP, __mako_require__.loadable).as_call(
+                                DUMMY_SP,
+                                vec![
+                                    quote_str!(resolved_info.to_replace_source.clone()).as_arg(),
+                                    import_promise.as_arg(),
+                                ],
+                            )
+                        } else {
+                            import_promise
+                        }
                     };
                 }
             }
@@ -175,7 +204,7 @@ impl DynamicImport<'_> {
                             .chunk(chunk_id)
                             .is_some_and(|c| !c.modules.is_empty())
                         {
-                            Some(chunk_id.id.clone())
+                            Some(chunk_id.id.to_string())
                         } else {
                             None
                         }
@@ -218,6 +247,7 @@ mod tests {
     use super::DynamicImport;
     use crate::ast::tests::TestUtils;
     use crate::generate::chunk::{Chunk, ChunkType};
+    use crate::module::ImportMode;
     use crate::visitors::dep_replacer::{DependenciesToReplace, ResolvedReplaceInfo};
 
     // TODO: add nested chunk test
@@ -235,6 +265,19 @@ Promise.all([
         );
     }
 
+    #[test]
+    fn test_dynamic_import_modes() {
+        assert_eq!(
+            run(r#"import("eager");import("weak");"#),
+            r#"
+var interop = __mako_require__("hashed_helper")._;
+Promise.resolve().then(__mako_require__.dr(interop, __mako_require__.bind(__mako_require__, "eager")));
+__mako_require__.weak("weak").then(__mako_require__.dr(interop, __mako_require__.bind(__mako_require__, "weak")));
+            "#
+            .trim()
+        );
+    }
+
     fn run(js_code: &str) -> String {
         let mut test_utils = TestUtils::gen_js_ast(js_code);
         {
@@ -250,12 +293,26 @@ Promise.all([
                 "@swc/helpers/_/_interop_require_wildcard".to_string() => ResolvedReplaceInfo {
                     chunk_id: None,
                     to_replace_source: "hashed_helper".to_string(),
-                    resolved_module_id:"dummy".into()
+                    resolved_module_id:"dummy".into(),
+                    import_mode: ImportMode::default(),
                 },
                 "foo".to_string() => ResolvedReplaceInfo {
                     chunk_id: Some("foo".into()),
                     to_replace_source: "foo".into(),
-                    resolved_module_id: "foo".into()
+                    resolved_module_id: "foo".into(),
+                    import_mode: ImportMode::default(),
+                },
+                "eager".to_string() => ResolvedReplaceInfo {
+                    chunk_id: None,
+                    to_replace_source: "eager".into(),
+                    resolved_module_id: "eager".into(),
+                    import_mode: ImportMode::Eager,
+                },
+                "weak".to_string() => ResolvedReplaceInfo {
+                    chunk_id: None,
+                    to_replace_source: "weak".into(),
+                    resolved_module_id: "weak".into(),
+                    import_mode: ImportMode::Weak,
                 }
             },
             missing: HashMap::new(),
@@ -664,6 +664,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_eval_code() {
+        // the code evaluated by eval is kept, the interpolations are evaluated
+        // before it
+        assert_eq!(
+            run(
+                r#"eval("log(A)"); new Function("x", `return ${A} + A`);"#,
+                hashmap! {
+                    "A".to_string() => json!(1)
+                }
+            ),
+            r#"eval("log(A)");new Function("x", `return ${1} + A`);"#
+        );
+    }
+
     fn run(js_code: &str, envs: HashMap<String, Value>) -> String {
         let mut test_utils = TestUtils::gen_js_ast(js_code);
         let envs = build_env_map(envs, &test_utils.context).unwrap();
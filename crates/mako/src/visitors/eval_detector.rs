@@ -0,0 +1,87 @@
+use swc_core::ecma::ast::{CallExpr, Callee, Expr, Module, NewExpr};
+use swc_core::ecma::visit::{Visit, VisitWith};
+
+use crate::module::BailoutReason;
+
+/// Finds the direct eval and new Function of a module, whose evaluated code
+/// refers to the names of the module, e.g. the compiled templates of lodash.
+#[derive(Default)]
+pub struct EvalDetector {
+    bailouts: Vec<BailoutReason>,
+}
+
+impl EvalDetector {
+    pub fn detect(module: &Module) -> Vec<BailoutReason> {
+        let mut detector = EvalDetector::default();
+        module.visit_with(&mut detector);
+        detector.bailouts
+    }
+
+    fn add(&mut self, reason: BailoutReason) {
+        if !self.bailouts.contains(&reason) {
+            self.bailouts.push(reason);
+        }
+    }
+}
+
+impl Visit for EvalDetector {
+    fn visit_call_expr(&mut self, call_expr: &CallExpr) {
+        if let Some(reason) = eval_call(call_expr) {
+            self.add(reason);
+        }
+        call_expr.visit_children_with(self);
+    }
+
+    fn visit_new_expr(&mut self, new_expr: &NewExpr) {
+        if is_new_function(new_expr) {
+            self.add(BailoutReason::NewFunction);
+        }
+        new_expr.visit_children_with(self);
+    }
+}
+
+// `eval(...)` and `Function(...)`, `(0, eval)(...)` evaluates in the global
+// scope and is not a direct eval
+fn eval_call(call_expr: &CallExpr) -> Option<BailoutReason> {
+    match &call_expr.callee {
+        Callee::Expr(box Expr::Ident(ident)) if ident.sym == *"eval" => {
+            Some(BailoutReason::DirectEval)
+        }
+        Callee::Expr(box Expr::Ident(ident)) if ident.sym == *"Function" => {
+            Some(BailoutReason::NewFunction)
+        }
+        _ => None,
+    }
+}
+
+fn is_new_function(new_expr: &NewExpr) -> bool {
+    matches!(&new_expr.callee, box Expr::Ident(ident) if ident.sym == *"Function")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EvalDetector;
+    use crate::ast::tests::TestUtils;
+    use crate::module::BailoutReason;
+
+    #[test]
+    fn test_eval_detector() {
+        assert_eq!(
+            detect(r#"function render(data) { return eval("data.name"); }"#),
+            vec![BailoutReason::DirectEval]
+        );
+        assert_eq!(
+            detect(r#"const f = new Function("a", "return a"); Function("return this")();"#),
+            vec![BailoutReason::NewFunction]
+        );
+        assert_eq!(
+            detect(r#"(0, eval)("1"); window.eval("2"); obj.Function();"#),
+            vec![]
+        );
+    }
+
+    fn detect(code: &str) -> Vec<BailoutReason> {
+        let test_utils = TestUtils::gen_js_ast(code);
+        EvalDetector::detect(&test_utils.ast.js().ast)
+    }
+}
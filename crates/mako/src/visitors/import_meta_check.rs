@@ -0,0 +1,156 @@
+use swc_core::common::errors::HANDLER;
+use swc_core::ecma::ast::{Expr, MetaPropExpr, MetaPropKind};
+use swc_core::ecma::visit::{VisitMut, VisitMutWith};
+
+use crate::ast::utils::is_import_meta_url;
+use crate::build::targets::targets_support;
+use crate::config::{Config, OutputMode};
+
+// https://caniuse.com/mdn-javascript_operators_import_meta
+pub const IMPORT_META_SUPPORT: [(&str, f32); 8] = [
+    ("chrome", 64.0),
+    ("edge", 79.0),
+    ("firefox", 62.0),
+    ("safari", 11.1),
+    ("ios", 12.0),
+    ("opera", 51.0),
+    ("samsung", 9.2),
+    ("android", 64.0),
+];
+
+/// Whether `import.meta` is kept in the output, only the es modules of the
+/// bundless mode whose targets support it, the chunks of the bundle are
+/// scripts.
+pub fn preserve_import_meta(config: &Config) -> bool {
+    config.output.mode == OutputMode::Bundless
+        && targets_support(&config.targets, &IMPORT_META_SUPPORT)
+}
+
+/// Report the `import.meta` left in an output which can't run it, which is a
+/// SyntaxError of the whole file at runtime otherwise. `import.meta.env` is
+/// replaced by `ImportMetaEnvReplacer` before, and `import.meta.url` by
+/// `MetaUrlReplacer` when generating.
+pub struct ImportMetaCheck {
+    pub bundless: bool,
+}
+
+impl VisitMut for ImportMetaCheck {
+    fn visit_mut_expr(&mut self, expr: &mut Expr) {
+        if is_import_meta_url(expr) {
+            return;
+        }
+        expr.visit_mut_children_with(self);
+    }
+
+    fn visit_mut_meta_prop_expr(&mut self, n: &mut MetaPropExpr) {
+        if n.kind != MetaPropKind::ImportMeta {
+            return;
+        }
+        let reason = if self.bundless {
+            "is not supported by the targets"
+        } else {
+            "is not supported by the bundle, whose chunks are scripts"
+        };
+        HANDLER.with(|handler| {
+            handler
+                .struct_span_err(
+                    n.span,
+                    &format!(
+                        "import.meta {}, only import.meta.url and import.meta.env are replaced",
+                        reason
+                    ),
+                )
+                .emit();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use swc_core::base::try_with_handler;
+    use swc_core::common::errors::HANDLER;
+    use swc_core::common::GLOBALS;
+    use swc_core::ecma::visit::VisitMutWith;
+
+    use super::*;
+    use crate::ast::tests::TestUtils;
+    use crate::testing::build_fixture;
+
+    #[test]
+    fn test_import_meta_check() {
+        assert!(run("console.log(import.meta.url);").is_ok());
+        assert!(run("new URL('./a', import.meta.url);").is_ok());
+        assert!(run("console.log(import.meta);").is_err());
+        assert!(run("if (import.meta.hot) {}").is_err());
+        assert!(run("import.meta.resolve('./a');").is_err());
+        assert!(run("const { url } = import.meta;").is_err());
+    }
+
+    fn run(js_code: &str) -> Result<()> {
+        let mut test_utils = TestUtils::gen_js_ast(js_code);
+        let ast = test_utils.ast.js_mut();
+        let cm = test_utils.context.meta.script.cm.clone();
+        GLOBALS.set(&test_utils.context.meta.script.globals, || {
+            try_with_handler(cm, Default::default(), |handler| {
+                HANDLER.set(handler, || {
+                    ast.ast
+                        .visit_mut_with(&mut ImportMetaCheck { bundless: false });
+                });
+                Ok(())
+            })
+        })
+    }
+
+    fn build(bundless: bool, targets: &str, code: &str) -> Result<String> {
+        // the modules of the bundless mode are emitted one by one
+        let output = if bundless {
+            r#"{"preserveModules":true,"preserveModulesRoot":"src"}"#
+        } else {
+            "{}"
+        };
+        let artifacts = build_fixture(
+            Some(&format!(
+                r#"{{"mode":"production","minify":false,"hash":false,"define":{{"import.meta.env.FOO":"\"foo\""}},"output":{},"targets":{}}}"#,
+                output, targets
+            )),
+            &[("src/index.js", code)],
+        )?;
+        Ok(artifacts.asset("index.js").unwrap())
+    }
+
+    const URL_AND_ENV: &str = "console.log(import.meta.url, import.meta.env.FOO);";
+    const HOT: &str = "if (import.meta.hot) {}";
+    const MODERN: &str = r#"{"chrome":80}"#;
+    const LEGACY: &str = r#"{"chrome":60,"ie":11}"#;
+
+    #[test]
+    fn test_import_meta_bundle() {
+        for targets in [MODERN, LEGACY] {
+            let code = build(false, targets, URL_AND_ENV).unwrap();
+            assert!(!code.contains("import.meta"), "{}", code);
+            assert!(code.contains("self.document.baseURI"), "{}", code);
+            assert!(code.contains(r#""foo""#), "{}", code);
+
+            let err = build(false, targets, HOT).unwrap_err().to_string();
+            assert!(err.contains("not supported by the bundle"), "{}", err);
+            assert!(err.contains("if (import.meta.hot)"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_import_meta_bundless() {
+        let code = build(true, MODERN, URL_AND_ENV).unwrap();
+        assert!(code.contains("import.meta.url"), "{}", code);
+        assert!(code.contains(r#""foo""#), "{}", code);
+        let code = build(true, MODERN, HOT).unwrap();
+        assert!(code.contains("import.meta.hot"), "{}", code);
+
+        let code = build(true, LEGACY, URL_AND_ENV).unwrap();
+        assert!(!code.contains("import.meta"), "{}", code);
+        assert!(code.contains("self.document.baseURI"), "{}", code);
+        assert!(code.contains(r#""foo""#), "{}", code);
+        let err = build(true, LEGACY, HOT).unwrap_err().to_string();
+        assert!(err.contains("not supported by the targets"), "{}", err);
+    }
+}
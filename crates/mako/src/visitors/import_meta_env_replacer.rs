@@ -1,19 +1,32 @@
+use std::collections::{BTreeMap, HashMap};
+
 use swc_core::common::DUMMY_SP;
 use swc_core::ecma::ast::{
     Expr, IdentName, KeyValueProp, MemberExpr, MemberProp, MetaPropExpr, MetaPropKind, ObjectLit,
-    Prop, PropOrSpread,
+    Prop, PropName, PropOrSpread,
 };
 use swc_core::ecma::utils::{quote_ident, quote_str, ExprFactory};
 use swc_core::ecma::visit::{VisitMut, VisitMutWith};
 
+pub(crate) const IMPORT_META_ENV_PREFIX: &str = "import.meta.env.";
+
 #[derive(Debug)]
 pub(crate) struct ImportMetaEnvReplacer {
     pub(crate) mode: String,
+    // the `import.meta.env.*` keys of define
+    pub(crate) env: BTreeMap<String, Expr>,
 }
 
 impl ImportMetaEnvReplacer {
-    pub(crate) fn new(mode: String) -> Self {
-        Self { mode }
+    pub(crate) fn new(mode: String, define: &HashMap<String, Expr>) -> Self {
+        let env = define
+            .iter()
+            .filter_map(|(key, value)| {
+                let key = key.strip_prefix(IMPORT_META_ENV_PREFIX)?;
+                (key != "MODE").then(|| (key.to_string(), value.clone()))
+            })
+            .collect();
+        Self { mode, env }
     }
 }
 
@@ -29,15 +42,28 @@ impl VisitMut for ImportMetaEnvReplacer {
                 prop: MemberProp::Ident(IdentName { sym, .. }),
                 ..
             }) if sym == "env" => {
-                // replace import.meta.env with "({ MODE: 'production' })"
+                // replace import.meta.env with "({ MODE: 'production', ... })"
+                let mode = (
+                    PropName::Ident(quote_ident!("MODE")),
+                    Expr::from(quote_str!(self.mode.clone())),
+                );
+                let env = self
+                    .env
+                    .iter()
+                    .map(|(key, value)| (PropName::Str(quote_str!(key.clone())), value.clone()));
                 *expr = ObjectLit {
-                    props: vec![PropOrSpread::Prop(
-                        Prop::KeyValue(KeyValueProp {
-                            key: quote_ident!("MODE").into(),
-                            value: quote_str!(self.mode.clone()).into(),
+                    props: std::iter::once(mode)
+                        .chain(env)
+                        .map(|(key, value)| {
+                            PropOrSpread::Prop(
+                                Prop::KeyValue(KeyValueProp {
+                                    key,
+                                    value: value.into(),
+                                })
+                                .into(),
+                            )
                         })
-                        .into(),
-                    )],
+                        .collect(),
                     span: DUMMY_SP,
                 }
                 .wrap_with_paren();
@@ -49,7 +75,11 @@ impl VisitMut for ImportMetaEnvReplacer {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use swc_core::common::GLOBALS;
+    use swc_core::ecma::ast::Expr;
+    use swc_core::ecma::utils::quote_str;
     use swc_core::ecma::visit::VisitMutWith;
 
     use super::ImportMetaEnvReplacer;
@@ -69,11 +99,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_import_meta_env_define() {
+        let define = HashMap::from([
+            ("import.meta.env.API".to_string(), quote_str!("/api").into()),
+            ("process.env.API".to_string(), quote_str!("/api").into()),
+        ]);
+        assert_eq!(
+            run_with_define("import.meta.env.API", &define),
+            r#"({
+    MODE: "development",
+    "API": "/api"
+}).API;"#
+        );
+    }
+
     fn run(js_code: &str) -> String {
+        run_with_define(js_code, &HashMap::new())
+    }
+
+    fn run_with_define(js_code: &str, define: &HashMap<String, Expr>) -> String {
         let mut test_utils = TestUtils::gen_js_ast(js_code);
         let ast = test_utils.ast.js_mut();
         GLOBALS.set(&test_utils.context.meta.script.globals, || {
-            let mut visitor = ImportMetaEnvReplacer::new("development".to_string());
+            let mut visitor = ImportMetaEnvReplacer::new("development".to_string(), define);
             ast.ast.visit_mut_with(&mut visitor);
         });
         test_utils.js_ast_to_code()
@@ -0,0 +1,203 @@
+use swc_core::common::DUMMY_SP;
+use swc_core::ecma::ast::{
+    ClassDecl, ClassExpr, Decl, ExportDecl, Expr, FnDecl, Ident, KeyValueProp, ModuleDecl,
+    ModuleItem, ObjectLit, Pat, Prop, PropOrSpread, Stmt, VarDecl,
+};
+use swc_core::ecma::utils::{member_expr, quote_ident, quote_str, ExprFactory};
+use swc_core::ecma::visit::{VisitMut, VisitMutWith};
+
+use crate::ast::DUMMY_CTXT;
+use crate::config::KeepNamesConfig;
+
+/// Keeps `name` of classes and functions through mangling for the files with
+/// the `/* @keepNames */` comment, e.g.
+/// `class A {}` -> `class A {} Object.defineProperty(A, "name", { value: "A", configurable: true });`
+pub struct KeepNames {
+    pub config: KeepNamesConfig,
+}
+
+impl KeepNames {
+    // (binding, name) of the declaration
+    fn names(&self, decl: &Decl) -> Vec<(Ident, String)> {
+        match decl {
+            Decl::Class(ClassDecl { ident, .. }) if self.config.class_names => {
+                vec![(ident.clone(), ident.sym.to_string())]
+            }
+            Decl::Fn(FnDecl { ident, .. }) if self.config.function_names => {
+                vec![(ident.clone(), ident.sym.to_string())]
+            }
+            // e.g. const A = class {} or const A = class B {}
+            Decl::Var(box VarDecl { decls, .. }) if self.config.class_names => decls
+                .iter()
+                .filter_map(|decl| match (&decl.name, decl.init.as_deref()) {
+                    (Pat::Ident(binding), Some(Expr::Class(ClassExpr { ident, .. }))) => Some((
+                        binding.id.clone(),
+                        ident.as_ref().unwrap_or(&binding.id).sym.to_string(),
+                    )),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn define_names(&self, decl: &Decl) -> Vec<Stmt> {
+        self.names(decl)
+            .into_iter()
+            .map(|(binding, name)| define_name(binding, &name))
+            .collect()
+    }
+}
+
+impl VisitMut for KeepNames {
+    fn visit_mut_module_items(&mut self, items: &mut Vec<ModuleItem>) {
+        items.visit_mut_children_with(self);
+        let mut new_items = Vec::with_capacity(items.len());
+        for item in items.drain(..) {
+            let stmts = match &item {
+                ModuleItem::Stmt(Stmt::Decl(decl))
+                | ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+                    self.define_names(decl)
+                }
+                _ => vec![],
+            };
+            new_items.push(item);
+            new_items.extend(stmts.into_iter().map(ModuleItem::Stmt));
+        }
+        *items = new_items;
+    }
+
+    fn visit_mut_stmts(&mut self, stmts: &mut Vec<Stmt>) {
+        stmts.visit_mut_children_with(self);
+        let mut new_stmts = Vec::with_capacity(stmts.len());
+        for stmt in stmts.drain(..) {
+            let define_stmts = match &stmt {
+                Stmt::Decl(decl) => self.define_names(decl),
+                _ => vec![],
+            };
+            new_stmts.push(stmt);
+            new_stmts.extend(define_stmts);
+        }
+        *stmts = new_stmts;
+    }
+}
+
+// Object.defineProperty(binding, "name", { value: "name", configurable: true });
+fn define_name(binding: Ident, name: &str) -> Stmt {
+    let descriptor: Expr = ObjectLit {
+        span: DUMMY_SP,
+        props: vec![
+            prop("value", quote_str!(name).into()),
+            prop("configurable", true.into()),
+        ],
+    }
+    .into();
+    member_expr!(DUMMY_CTXT, DUMMY_SP, Object.defineProperty)
+        .as_call(
+            DUMMY_SP,
+            vec![
+                binding.as_arg(),
+                quote_str!("name").as_arg(),
+                descriptor.as_arg(),
+            ],
+        )
+        .into_stmt()
+}
+
+fn prop(key: &str, value: Expr) -> PropOrSpread {
+    PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+        key: quote_ident!(key).into(),
+        value: Box::new(value),
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_core::common::GLOBALS;
+    use swc_core::ecma::visit::VisitMutWith;
+
+    use super::KeepNames;
+    use crate::ast::tests::TestUtils;
+    use crate::config::KeepNamesConfig;
+
+    #[test]
+    fn test_keep_names() {
+        assert_eq!(
+            run(
+                r#"
+class A {}
+export function b() {}
+const C = class {}, D = class E {};
+"#,
+                true,
+                true
+            ),
+            r#"
+class A {
+}
+Object.defineProperty(A, "name", {
+    value: "A",
+    configurable: true
+});
+export function b() {}
+Object.defineProperty(b, "name", {
+    value: "b",
+    configurable: true
+});
+const C = class {
+}, D = class E {
+};
+Object.defineProperty(C, "name", {
+    value: "C",
+    configurable: true
+});
+Object.defineProperty(D, "name", {
+    value: "E",
+    configurable: true
+});
+"#
+            .trim()
+        );
+    }
+
+    #[test]
+    fn test_nested_and_partial() {
+        assert_eq!(
+            run(
+                r#"
+function a() {
+    class B {}
+}
+"#,
+                true,
+                false
+            ),
+            r#"
+function a() {
+    class B {
+    }
+    Object.defineProperty(B, "name", {
+        value: "B",
+        configurable: true
+    });
+}
+"#
+            .trim()
+        );
+    }
+
+    fn run(js_code: &str, class_names: bool, function_names: bool) -> String {
+        let mut test_utils = TestUtils::gen_js_ast(js_code);
+        let ast = test_utils.ast.js_mut();
+        GLOBALS.set(&test_utils.context.meta.script.globals, || {
+            let mut visitor = KeepNames {
+                config: KeepNamesConfig {
+                    class_names,
+                    function_names,
+                },
+            };
+            ast.ast.visit_mut_with(&mut visitor);
+        });
+        test_utils.js_ast_to_code()
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use swc_core::common::DUMMY_SP;
+use swc_core::ecma::ast::{
+    CallExpr, Callee, Expr, KeyValueProp, Lit, MemberExpr, MemberProp, ObjectLit, Prop, PropName,
+    PropOrSpread,
+};
+use swc_core::ecma::utils::{quote_ident, ExprFactory};
+use swc_core::ecma::visit::{VisitMut, VisitMutWith};
+
+/// Interop of the commonjs deps of an es module by the rules of node, where
+/// `module.exports` is the default export, even with `__esModule`. Runs after
+/// the imports are transformed to requires with the interop helpers.
+pub struct NodeInterop<'a> {
+    // the sources of the deps which are commonjs
+    pub commonjs_sources: &'a HashSet<String>,
+}
+
+impl NodeInterop<'_> {
+    fn is_commonjs_require(&self, expr: &Expr) -> bool {
+        if let Expr::Call(CallExpr {
+            callee: Callee::Expr(box Expr::Ident(callee)),
+            args,
+            ..
+        }) = expr
+            && callee.sym == "require"
+            && let Some(Expr::Lit(Lit::Str(source))) = args.first().map(|arg| &*arg.expr)
+        {
+            self.commonjs_sources.contains(source.value.as_ref())
+        } else {
+            false
+        }
+    }
+}
+
+impl VisitMut for NodeInterop<'_> {
+    fn visit_mut_expr(&mut self, expr: &mut Expr) {
+        expr.visit_mut_children_with(self);
+        let Expr::Call(call) = expr else {
+            return;
+        };
+        let Callee::Expr(box Expr::Member(MemberExpr {
+            obj: box Expr::Ident(helper),
+            prop: MemberProp::Ident(prop),
+            ..
+        })) = &call.callee
+        else {
+            return;
+        };
+        if prop.sym != "_" || call.args.len() != 1 || !self.is_commonjs_require(&call.args[0].expr)
+        {
+            return;
+        }
+        match helper.sym.as_ref() {
+            // { default: module.exports }
+            "_interop_require_default" => {
+                *expr = ObjectLit {
+                    span: DUMMY_SP,
+                    props: vec![PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                        key: PropName::Ident(quote_ident!("default")),
+                        value: call.args.remove(0).expr,
+                    })))],
+                }
+                .into();
+            }
+            // the nodeInterop flag of the helper
+            "_interop_require_wildcard" => {
+                call.args.push(true.as_arg());
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use swc_core::common::GLOBALS;
+    use swc_core::ecma::visit::VisitMutWith;
+
+    use super::NodeInterop;
+    use crate::ast::tests::TestUtils;
+
+    #[test]
+    fn test_node_interop() {
+        assert_eq!(
+            run(r#"var _a = _interop_require_default._(require("./a.cjs"));"#),
+            r#"var _a = {
+    default: require("./a.cjs")
+};"#
+        );
+        assert_eq!(
+            run(r#"var _a = _interop_require_wildcard._(require("./a.cjs"));"#),
+            r#"var _a = _interop_require_wildcard._(require("./a.cjs"), true);"#
+        );
+        assert_eq!(
+            run(r#"var _b = _interop_require_default._(require("./b.js"));"#),
+            r#"var _b = _interop_require_default._(require("./b.js"));"#
+        );
+    }
+
+    fn run(js_code: &str) -> String {
+        let mut test_utils = TestUtils::gen_js_ast(js_code);
+        let ast = test_utils.ast.js_mut();
+        let commonjs_sources = HashSet::from(["./a.cjs".to_string()]);
+        GLOBALS.set(&test_utils.context.meta.script.globals, || {
+            ast.ast.visit_mut_with(&mut NodeInterop {
+                commonjs_sources: &commonjs_sources,
+            });
+        });
+        test_utils.js_ast_to_code()
+    }
+}
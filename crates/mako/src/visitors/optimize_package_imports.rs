@@ -104,7 +104,7 @@ impl Fold for OptimizePackageImports {
                             debug!("    specifier_keys: {:?}", specifiers.keys());
                             export_map.retain(|k, _| specifiers.contains_key(k));
                             debug!("    retain export_map: {:?}", export_map.keys());
-                            let path = resolved_resource.get_resolved_path();
+                            let path = resolved_resource.get_resolved_request();
                             let stmts = build_import_stmts(&export_map, &specifiers, &path);
                             new_module_items.extend(stmts);
                         } else {
@@ -284,7 +284,7 @@ fn parse_barrel_file(
                 debug!("    export all: {:?}", source);
                 if let Some(resolver_resource) = resolver_resource_infos.get(source) {
                     let more_export_infos = parse_barrel_file(resolver_resource, context, true);
-                    let path = resolver_resource.get_resolved_path();
+                    let path = resolver_resource.get_resolved_request();
                     if let Ok(Some((is_barrel_file, more_export_infos))) = more_export_infos {
                         // (source, orig, exported)
                         for (k, v) in more_export_infos {
@@ -342,7 +342,7 @@ fn parse_barrel_file(
                         export_infos.insert(
                             exported.clone(),
                             (
-                                resolver_resource.get_resolved_path(),
+                                resolver_resource.get_resolved_request(),
                                 orig.clone(),
                                 exported.clone(),
                             ),
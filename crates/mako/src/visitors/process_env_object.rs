@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use colored::Colorize;
+use swc_core::common::{Mark, Span, Spanned, DUMMY_SP};
+use swc_core::ecma::ast::{
+    BinExpr, BinaryOp, ComputedPropName, Expr, Ident, IdentName, KeyValueProp, Lit, MemberExpr,
+    MemberProp, ObjectLit, OptChainBase, OptChainExpr, ParenExpr, Prop, PropName, PropOrSpread,
+    Str, UnaryExpr, UnaryOp,
+};
+use swc_core::ecma::visit::{VisitMut, VisitMutWith};
+
+use crate::ast::error::{code_frame, ErrorSpan};
+use crate::ast::DUMMY_CTXT;
+use crate::compiler::Context;
+use crate::config::EnvObjectConfig;
+
+/// Report the uses of `process.env` which can't be replaced by define in the
+/// browser, i.e. as an object like `{ ...process.env }` or with a dynamic
+/// key, and replace the object with config.envObject.
+pub struct ProcessEnvObject {
+    context: Arc<Context>,
+    unresolved_mark: Mark,
+    env_object: Option<EnvObjectConfig>,
+    // the value of process.env.NODE_ENV
+    node_env: Option<Expr>,
+    warn: bool,
+    warnings: usize,
+}
+
+impl ProcessEnvObject {
+    pub fn new(
+        context: Arc<Context>,
+        unresolved_mark: Mark,
+        env_object: Option<EnvObjectConfig>,
+        node_env: Option<Expr>,
+        warn: bool,
+    ) -> Self {
+        Self {
+            context,
+            unresolved_mark,
+            env_object,
+            node_env,
+            // the object is not replaced without envObject only
+            warn: warn && env_object.is_none(),
+            warnings: 0,
+        }
+    }
+
+    fn is_process_env(&self, expr: &Expr) -> bool {
+        let member = match expr {
+            Expr::Member(member) => member,
+            Expr::OptChain(OptChainExpr {
+                base: box OptChainBase::Member(member),
+                ..
+            }) => member,
+            _ => return false,
+        };
+        let is_env = match &member.prop {
+            MemberProp::Ident(ident) => ident.sym == "env",
+            MemberProp::Computed(ComputedPropName {
+                expr: box Expr::Lit(Lit::Str(str)),
+                ..
+            }) => str.value == "env",
+            _ => false,
+        };
+        is_env
+            && matches!(&*member.obj, Expr::Ident(ident) if ident.sym == "process" && ident.ctxt.outer() == self.unresolved_mark)
+    }
+
+    fn report(&mut self, span: Span, message: &str) {
+        if !self.warn {
+            return;
+        }
+        self.warnings += 1;
+        eprintln!(
+            "{}: {}",
+            "Warning".yellow(),
+            code_frame(ErrorSpan::Js(span), message, self.context.clone())
+        );
+    }
+
+    fn replace(&self, expr: &mut Expr) {
+        let Some(env_object) = self.env_object else {
+            return;
+        };
+        let span = expr.span();
+        let object = Expr::Object(ObjectLit {
+            span,
+            props: self
+                .node_env
+                .iter()
+                .map(|node_env| {
+                    PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                        key: PropName::Ident(IdentName::new("NODE_ENV".into(), DUMMY_SP)),
+                        value: Box::new(node_env.clone()),
+                    })))
+                })
+                .collect(),
+        });
+        *expr = match env_object {
+            EnvObjectConfig::Empty => object,
+            // (typeof process !== "undefined" && process.env) || { NODE_ENV }
+            EnvObjectConfig::Runtime => {
+                let process = || {
+                    Box::new(Expr::Ident(Ident::new(
+                        "process".into(),
+                        DUMMY_SP,
+                        DUMMY_CTXT.apply_mark(self.unresolved_mark),
+                    )))
+                };
+                let is_defined = Expr::Bin(BinExpr {
+                    span: DUMMY_SP,
+                    op: BinaryOp::NotEqEq,
+                    left: Box::new(Expr::Unary(UnaryExpr {
+                        span: DUMMY_SP,
+                        op: UnaryOp::TypeOf,
+                        arg: process(),
+                    })),
+                    right: Box::new(Expr::Lit(Lit::Str(Str::from("undefined")))),
+                });
+                let runtime_env = Expr::Member(MemberExpr {
+                    span: DUMMY_SP,
+                    obj: process(),
+                    prop: MemberProp::Ident(IdentName::new("env".into(), DUMMY_SP)),
+                });
+                Expr::Bin(BinExpr {
+                    span,
+                    op: BinaryOp::LogicalOr,
+                    left: Box::new(Expr::Paren(ParenExpr {
+                        span: DUMMY_SP,
+                        expr: Box::new(Expr::Bin(BinExpr {
+                            span: DUMMY_SP,
+                            op: BinaryOp::LogicalAnd,
+                            left: Box::new(is_defined),
+                            right: Box::new(runtime_env),
+                        })),
+                    })),
+                    right: Box::new(object),
+                })
+            }
+        };
+    }
+}
+
+impl VisitMut for ProcessEnvObject {
+    fn visit_mut_member_expr(&mut self, member: &mut MemberExpr) {
+        if !self.is_process_env(&member.obj) {
+            member.visit_mut_children_with(self);
+            return;
+        }
+        match &mut member.prop {
+            // process.env.NAME, which is replaced by define or left as is
+            MemberProp::Ident(_)
+            | MemberProp::Computed(ComputedPropName {
+                expr: box Expr::Lit(Lit::Str(_) | Lit::Num(_)),
+                ..
+            }) => {}
+            prop => {
+                self.report(
+                    member.span,
+                    "`process.env` is accessed with a dynamic key, which can't be replaced by define at build time and fails in the browser, use `process.env.NAME` with a static name instead, or set envObject to replace the object",
+                );
+                self.replace(&mut member.obj);
+                prop.visit_mut_with(self);
+            }
+        }
+    }
+
+    fn visit_mut_expr(&mut self, expr: &mut Expr) {
+        if self.is_process_env(expr) {
+            self.report(
+                expr.span(),
+                "`process.env` is used as an object, e.g. spread or passed around, which can't be replaced by define at build time and fails in the browser, use `process.env.NAME` for each variable instead, or set envObject to replace the object",
+            );
+            self.replace(expr);
+            return;
+        }
+        expr.visit_mut_children_with(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_core::common::GLOBALS;
+    use swc_core::ecma::ast::{Expr, Lit, Str};
+    use swc_core::ecma::visit::VisitMutWith;
+
+    use super::ProcessEnvObject;
+    use crate::ast::tests::TestUtils;
+    use crate::config::EnvObjectConfig;
+
+    #[test]
+    fn test_warn() {
+        assert_eq!(run("log({ ...process.env });", None), 1);
+        assert_eq!(run("const { A } = process.env; log(process.env);", None), 2);
+        assert_eq!(run("log(process.env[key], process?.env);", None), 2);
+        assert_eq!(
+            run(
+                "log(process.env.A, process.env['B'], process.env?.C, process.env[0]);",
+                None
+            ),
+            0
+        );
+        assert_eq!(
+            run("function f(process) { return { ...process.env }; }", None),
+            0
+        );
+        assert_eq!(
+            run("const process = { env: {} }; log(process.env);", None),
+            0
+        );
+        assert_eq!(run("log(process.env);", Some(EnvObjectConfig::Empty)), 0);
+    }
+
+    #[test]
+    fn test_env_object() {
+        assert_eq!(
+            transform("log({ ...process.env });", EnvObjectConfig::Empty),
+            r#"log({...{NODE_ENV: "production"}});"#
+        );
+        assert_eq!(
+            transform("log(process.env[key]);", EnvObjectConfig::Empty),
+            r#"log({NODE_ENV: "production"}[key]);"#
+        );
+        assert_eq!(
+            transform("log(process.env);", EnvObjectConfig::Runtime),
+            r#"log((typeof process !== "undefined" && process.env) || {NODE_ENV: "production"});"#
+        );
+        assert_eq!(
+            transform("log(process.env.A);", EnvObjectConfig::Empty),
+            "log(process.env.A);"
+        );
+        assert_eq!(
+            transform(
+                "function f(process) { log(process.env); }",
+                EnvObjectConfig::Empty
+            ),
+            "function f(process) {log(process.env);}"
+        );
+    }
+
+    fn run(js_code: &str, env_object: Option<EnvObjectConfig>) -> usize {
+        visit(js_code, env_object).1
+    }
+
+    fn transform(js_code: &str, env_object: EnvObjectConfig) -> String {
+        visit(js_code, Some(env_object)).0
+    }
+
+    fn visit(js_code: &str, env_object: Option<EnvObjectConfig>) -> (String, usize) {
+        let mut test_utils = TestUtils::gen_js_ast(js_code);
+        let context = test_utils.context.clone();
+        let ast = test_utils.ast.js_mut();
+        let node_env = Expr::Lit(Lit::Str(Str::from("production")));
+        let warnings = GLOBALS.set(&context.meta.script.globals, || {
+            let mut visitor = ProcessEnvObject::new(
+                context.clone(),
+                ast.unresolved_mark,
+                env_object,
+                Some(node_env),
+                true,
+            );
+            ast.ast.visit_mut_with(&mut visitor);
+            visitor.warnings
+        });
+        let code = test_utils.js_ast_to_code();
+        (code.replace('\n', "").replace("    ", ""), warnings)
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::HashSet;
+
+use swc_core::common::Mark;
+use swc_core::ecma::ast::{
+    AssignOp, AssignTarget, BinaryOp, ClassMember, Decl, Expr, Id, Ident, Lit, MemberProp, Module,
+    ModuleDecl, ModuleItem, Pat, PropName, SimpleAssignTarget, Stmt, UnaryOp, VarDecl, VarDeclKind,
+};
+use swc_core::ecma::visit::{Visit, VisitMut, VisitMutWith, VisitWith};
+
+use crate::ast::utils::{get_first_str_arg, is_commonjs_require};
+
+const PROP_TYPES: &str = "propTypes";
+const DEFAULT_PROPS: &str = "defaultProps";
+const PROP_TYPES_PACKAGE: &str = "prop-types";
+
+/// Remove the `propTypes` of the components which are only checked in the
+/// development, e.g. `Button.propTypes = {}` and `static propTypes = {}`,
+/// and the imports or requires of `prop-types` which are unused then.
+///
+/// It runs after the dead branches are removed by the simplifier, and
+/// removes the dev-only statements the simplifier keeps, i.e. the ones
+/// guarded by a top level const which is `false` after the define, e.g.
+/// `const isDev = process.env.NODE_ENV !== 'production'; isDev && (x = 1);`.
+pub struct RemovePropTypes {
+    unresolved_mark: Mark,
+    default_props: bool,
+    false_consts: HashSet<Id>,
+    /// count of the removed statements and class properties
+    pub removed: usize,
+}
+
+impl RemovePropTypes {
+    /// Remove `defaultProps` too if `default_props` is true.
+    pub fn new(unresolved_mark: Mark, default_props: bool) -> Self {
+        Self {
+            unresolved_mark,
+            default_props,
+            false_consts: HashSet::new(),
+            removed: 0,
+        }
+    }
+
+    fn is_removed_prop(&self, name: &str) -> bool {
+        name == PROP_TYPES || (self.default_props && name == DEFAULT_PROPS)
+    }
+
+    fn is_false(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Lit(Lit::Bool(bool)) => !bool.value,
+            Expr::Ident(ident) => self.false_consts.contains(&ident.to_id()),
+            Expr::Paren(paren) => self.is_false(&paren.expr),
+            Expr::Bin(bin) if bin.op == BinaryOp::LogicalAnd => self.is_false(&bin.left),
+            _ => false,
+        }
+    }
+
+    // true if the statement is removed, the `if` with an `else` is replaced
+    // with the `else`
+    fn strip(&mut self, stmt: &mut Stmt) -> bool {
+        let removed = match stmt {
+            Stmt::Expr(expr_stmt) => match &*expr_stmt.expr {
+                Expr::Assign(assign) => {
+                    assign.op == AssignOp::Assign
+                        && matches!(
+                            &assign.left,
+                            AssignTarget::Simple(SimpleAssignTarget::Member(member))
+                                if member_prop_name(&member.prop)
+                                    .is_some_and(|name| self.is_removed_prop(&name))
+                        )
+                }
+                // isDev && (Button.displayName = 'Button')
+                Expr::Bin(bin) if bin.op == BinaryOp::LogicalAnd => self.is_false(&bin.left),
+                // isDev ? (Button.displayName = 'Button') : void 0
+                Expr::Cond(cond) if self.is_false(&cond.test) => {
+                    if is_void(&cond.alt) {
+                        true
+                    } else {
+                        expr_stmt.expr = Box::new(cond.alt.unwrap_parens().clone());
+                        self.removed += 1;
+                        false
+                    }
+                }
+                _ => false,
+            },
+            Stmt::If(if_stmt) if self.is_false(&if_stmt.test) => match if_stmt.alt.take() {
+                Some(alt) => {
+                    *stmt = *alt;
+                    self.removed += 1;
+                    false
+                }
+                None => true,
+            },
+            _ => false,
+        };
+        if removed {
+            self.removed += 1;
+        }
+        removed
+    }
+
+    fn collect_false_consts(&mut self, module: &Module) {
+        for item in &module.body {
+            if let ModuleItem::Stmt(Stmt::Decl(Decl::Var(box VarDecl {
+                kind: VarDeclKind::Const,
+                decls,
+                ..
+            }))) = item
+            {
+                for decl in decls {
+                    if let Pat::Ident(binding) = &decl.name
+                        && let Some(box Expr::Lit(Lit::Bool(bool))) = &decl.init
+                        && !bool.value
+                    {
+                        self.false_consts.insert(binding.to_id());
+                    }
+                }
+            }
+        }
+    }
+
+    // the imports and the top level requires of `prop-types` whose bindings
+    // are unused after the prop types are removed
+    fn remove_unused_prop_types(&mut self, module: &mut Module) {
+        let mut used = UsedIdents::default();
+        module
+            .body
+            .iter()
+            .filter(|item| self.prop_types_bindings(item).is_none())
+            .for_each(|item| item.visit_with(&mut used));
+        module.body.retain(|item| {
+            let unused = self
+                .prop_types_bindings(item)
+                .is_some_and(|bindings| bindings.iter().all(|id| !used.idents.contains(id)));
+            !unused
+        });
+    }
+
+    fn prop_types_bindings(&self, item: &ModuleItem) -> Option<Vec<Id>> {
+        match item {
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import))
+                if import.src.value == PROP_TYPES_PACKAGE && !import.specifiers.is_empty() =>
+            {
+                Some(
+                    import
+                        .specifiers
+                        .iter()
+                        .map(|specifier| specifier.local().to_id())
+                        .collect(),
+                )
+            }
+            // const PropTypes = require('prop-types');
+            ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl))) if var_decl.decls.len() == 1 => {
+                let decl = &var_decl.decls[0];
+                match (&decl.name, &decl.init) {
+                    (Pat::Ident(binding), Some(box Expr::Call(call)))
+                        if is_commonjs_require(call, &self.unresolved_mark)
+                            && get_first_str_arg(call).as_deref() == Some(PROP_TYPES_PACKAGE) =>
+                    {
+                        Some(vec![binding.to_id()])
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl VisitMut for RemovePropTypes {
+    fn visit_mut_module(&mut self, module: &mut Module) {
+        self.collect_false_consts(module);
+        module.visit_mut_children_with(self);
+        if self.removed > 0 {
+            self.remove_unused_prop_types(module);
+        }
+    }
+
+    fn visit_mut_module_items(&mut self, items: &mut Vec<ModuleItem>) {
+        items.visit_mut_children_with(self);
+        items.retain_mut(|item| match item {
+            ModuleItem::Stmt(stmt) => !self.strip(stmt),
+            _ => true,
+        });
+    }
+
+    fn visit_mut_stmts(&mut self, stmts: &mut Vec<Stmt>) {
+        stmts.visit_mut_children_with(self);
+        stmts.retain_mut(|stmt| !self.strip(stmt));
+    }
+
+    fn visit_mut_class_members(&mut self, members: &mut Vec<ClassMember>) {
+        members.visit_mut_children_with(self);
+        members.retain(|member| {
+            let removed = matches!(
+                member,
+                ClassMember::ClassProp(prop)
+                    if prop.is_static
+                        && prop_name(&prop.key).is_some_and(|name| self.is_removed_prop(&name))
+            );
+            if removed {
+                self.removed += 1;
+            }
+            !removed
+        });
+    }
+}
+
+fn member_prop_name(prop: &MemberProp) -> Option<String> {
+    match prop {
+        MemberProp::Ident(ident) => Some(ident.sym.to_string()),
+        MemberProp::Computed(computed) => match &*computed.expr {
+            Expr::Lit(Lit::Str(str)) => Some(str.value.to_string()),
+            _ => None,
+        },
+        MemberProp::PrivateName(_) => None,
+    }
+}
+
+fn prop_name(key: &PropName) -> Option<String> {
+    match key {
+        PropName::Ident(ident) => Some(ident.sym.to_string()),
+        PropName::Str(str) => Some(str.value.to_string()),
+        _ => None,
+    }
+}
+
+// void 0
+fn is_void(expr: &Expr) -> bool {
+    matches!(expr, Expr::Unary(unary) if unary.op == UnaryOp::Void && unary.arg.is_lit())
+}
+
+#[derive(Default)]
+struct UsedIdents {
+    idents: HashSet<Id>,
+}
+
+impl Visit for UsedIdents {
+    fn visit_ident(&mut self, ident: &Ident) {
+        self.idents.insert(ident.to_id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use swc_core::common::GLOBALS;
+    use swc_core::ecma::visit::VisitMutWith;
+
+    use super::RemovePropTypes;
+    use crate::ast::tests::TestUtils;
+
+    #[test]
+    fn test_prop_types() {
+        let (code, removed) = run(
+            r#"
+import PropTypes from 'prop-types';
+function Button() {}
+Button.propTypes = { label: PropTypes.string };
+Button["propTypes"] = {};
+Button.defaultProps = { label: 'ok' };
+class Panel {
+    static propTypes = { title: PropTypes.string };
+    static defaultProps = { title: 'panel' };
+    propTypes = 1;
+}
+            "#,
+            false,
+        );
+        assert_eq!(
+            code,
+            r#"function Button() {}
+Button.defaultProps = {
+    label: 'ok'
+};
+class Panel {
+    static defaultProps = {
+        title: 'panel'
+    };
+    propTypes = 1;
+}"#
+        );
+        assert_eq!(removed, 3);
+    }
+
+    #[test]
+    fn test_default_props() {
+        let (code, removed) = run(
+            r#"
+const PropTypes = require('prop-types');
+function Button() {}
+Button.defaultProps = { label: 'ok' };
+Button.propTypes = { label: PropTypes.string };
+            "#,
+            true,
+        );
+        assert_eq!(code, "function Button() {}");
+        assert_eq!(removed, 2);
+    }
+
+    #[test]
+    fn test_used_prop_types() {
+        let (code, _) = run(
+            r#"
+import PropTypes from 'prop-types';
+Button.propTypes = {};
+export const shape = PropTypes.shape({});
+            "#,
+            false,
+        );
+        assert_eq!(
+            code,
+            r#"import PropTypes from 'prop-types';
+export const shape = PropTypes.shape({});"#
+        );
+    }
+
+    #[test]
+    fn test_dev_only() {
+        let (code, removed) = run(
+            r#"
+const isDev = false;
+let notConst = false;
+isDev && (Button.displayName = 'Button');
+isDev ? (Button.x = 1) : void 0;
+isDev ? (Button.x = 1) : (Button.y = 2);
+if (isDev) Button.z = 3;
+if (isDev) Button.z = 3; else Button.z = 4;
+notConst && (Button.w = 5);
+function f() {
+    if (isDev) console.log('dev');
+}
+            "#,
+            false,
+        );
+        assert_eq!(
+            code,
+            r#"const isDev = false;
+let notConst = false;
+Button.y = 2;
+Button.z = 4;
+notConst && (Button.w = 5);
+function f() {}"#
+        );
+        assert_eq!(removed, 6);
+    }
+
+    fn run(js_code: &str, default_props: bool) -> (String, usize) {
+        let mut test_utils = TestUtils::gen_js_ast(js_code);
+        let unresolved_mark = test_utils.ast.js().unresolved_mark;
+        let ast = test_utils.ast.js_mut();
+        let removed = GLOBALS.set(&test_utils.context.meta.script.globals, || {
+            let mut visitor = RemovePropTypes::new(unresolved_mark, default_props);
+            ast.ast.visit_mut_with(&mut visitor);
+            visitor.removed
+        });
+        (test_utils.js_ast_to_code(), removed)
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+use swc_core::ecma::ast::{
+    Expr, Id, Ident, ImportPhase, Module, ModuleDecl, ModuleItem, UnaryExpr, UnaryOp,
+};
+use swc_core::ecma::utils::quote_str;
+use swc_core::ecma::visit::{Visit, VisitMut, VisitMutWith, VisitWith};
+
+/// Rewrite the imports whose bindings are all unused in the module to the
+/// bare imports, e.g. `import x from './init'` -> `import './init'`, which
+/// keeps the order of the side effects and drops the interop of the bindings.
+///
+/// The bindings which are only read by `typeof` are unused, and `typeof x` is
+/// replaced with `"undefined"`. It runs after the dead branches are removed,
+/// so the bindings only referenced in them are unused too.
+pub struct SideEffectOnlyImports {
+    include: Vec<Regex>,
+    stripped: HashSet<Id>,
+    /// count of the rewritten imports
+    pub rewritten: usize,
+}
+
+impl SideEffectOnlyImports {
+    /// Rewrite the imports of the sources matched by `include`, or all the
+    /// imports if it's empty.
+    pub fn new(include: Vec<Regex>) -> Self {
+        Self {
+            include,
+            stripped: HashSet::new(),
+            rewritten: 0,
+        }
+    }
+
+    fn is_included(&self, src: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|regex| regex.is_match(src))
+    }
+}
+
+impl VisitMut for SideEffectOnlyImports {
+    fn visit_mut_module(&mut self, module: &mut Module) {
+        let mut used = UsedIdents::default();
+        module
+            .body
+            .iter()
+            .filter(|item| !matches!(item, ModuleItem::ModuleDecl(ModuleDecl::Import(_))))
+            .for_each(|item| item.visit_with(&mut used));
+
+        for item in module.body.iter_mut() {
+            if let ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = item
+                && !import.type_only
+                && import.phase == ImportPhase::Evaluation
+                && !import.specifiers.is_empty()
+                && self.is_included(&import.src.value)
+                && import
+                    .specifiers
+                    .iter()
+                    .all(|specifier| !used.idents.contains(&specifier.local().to_id()))
+            {
+                self.stripped.extend(
+                    import
+                        .specifiers
+                        .drain(..)
+                        .map(|specifier| specifier.local().to_id()),
+                );
+                self.rewritten += 1;
+            }
+        }
+
+        if !self.stripped.is_empty() {
+            module.visit_mut_children_with(self);
+        }
+    }
+
+    fn visit_mut_expr(&mut self, expr: &mut Expr) {
+        if let Expr::Unary(UnaryExpr {
+            op: UnaryOp::TypeOf,
+            arg: box Expr::Ident(ident),
+            ..
+        }) = expr
+            && self.stripped.contains(&ident.to_id())
+        {
+            *expr = quote_str!("undefined").into();
+            return;
+        }
+        expr.visit_mut_children_with(self);
+    }
+}
+
+// the referenced idents, except the ones only read by `typeof`
+#[derive(Default)]
+struct UsedIdents {
+    idents: HashSet<Id>,
+}
+
+impl Visit for UsedIdents {
+    fn visit_ident(&mut self, ident: &Ident) {
+        self.idents.insert(ident.to_id());
+    }
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) {
+        if expr.op == UnaryOp::TypeOf && expr.arg.is_ident() {
+            return;
+        }
+        expr.visit_children_with(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+    use swc_core::common::GLOBALS;
+    use swc_core::ecma::visit::VisitMutWith;
+
+    use super::SideEffectOnlyImports;
+    use crate::ast::tests::TestUtils;
+
+    #[test]
+    fn test_unused_imports() {
+        let (code, rewritten) = run(
+            r#"
+import a from './a';
+import { b, c as d } from './b';
+import * as e from './e';
+import f from './f';
+import g, { h } from './g';
+import './i';
+console.log(f, g, typeof h);
+"#,
+            &[],
+        );
+        assert_eq!(
+            code,
+            r#"
+import './a';
+import './b';
+import './e';
+import f from './f';
+import g, { h } from './g';
+import './i';
+console.log(f, g, typeof h);
+"#
+            .trim()
+        );
+        assert_eq!(rewritten, 3);
+    }
+
+    #[test]
+    fn test_exported_and_shadowed() {
+        let (code, rewritten) = run(
+            r#"
+import a from './a';
+import b from './b';
+import c from './c';
+export { a };
+export default b;
+function foo(c) {
+    return c;
+}
+"#,
+            &[],
+        );
+        assert_eq!(
+            code,
+            r#"
+import a from './a';
+import b from './b';
+import './c';
+export { a };
+export default b;
+function foo(c) {
+    return c;
+}
+"#
+            .trim()
+        );
+        assert_eq!(rewritten, 1);
+    }
+
+    #[test]
+    fn test_typeof_only() {
+        let (code, rewritten) = run(
+            r#"
+import a from './a';
+import b from './b';
+if (typeof a === 'function') {
+    b.init();
+}
+"#,
+            &[],
+        );
+        assert_eq!(
+            code,
+            r#"
+import './a';
+import b from './b';
+if ("undefined" === 'function') {
+    b.init();
+}
+"#
+            .trim()
+        );
+        assert_eq!(rewritten, 1);
+    }
+
+    #[test]
+    fn test_include() {
+        let (code, rewritten) = run(
+            r#"
+import a from './init';
+import b from 'foo';
+"#,
+            &["^\\./init$"],
+        );
+        assert_eq!(
+            code,
+            r#"
+import './init';
+import b from 'foo';
+"#
+            .trim()
+        );
+        assert_eq!(rewritten, 1);
+    }
+
+    fn run(js_code: &str, include: &[&str]) -> (String, usize) {
+        let mut test_utils = TestUtils::gen_js_ast(js_code);
+        let ast = test_utils.ast.js_mut();
+        let rewritten = GLOBALS.set(&test_utils.context.meta.script.globals, || {
+            let mut visitor = SideEffectOnlyImports::new(
+                include
+                    .iter()
+                    .map(|include| Regex::new(include).unwrap())
+                    .collect(),
+            );
+            ast.ast.visit_mut_with(&mut visitor);
+            visitor.rewritten
+        });
+        (test_utils.js_ast_to_code(), rewritten)
+    }
+}
@@ -24,3 +24,25 @@ pub fn ts_strip(unresolved_mark: Mark, top_level_mark: Mark) -> impl VisitMut {
         unresolved_mark,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::module::ModuleId;
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_type_only_imports_and_exports_are_erased() {
+        let compiler = setup_compiler("test/build/type-only-imports", false);
+        compiler.compile().unwrap();
+        let root = compiler.context.root.clone();
+        let module_graph = compiler.context.module_graph.read().unwrap();
+        let has_module = |path: &str| module_graph.has_module(&ModuleId::from(root.join(path)));
+
+        assert!(has_module("index.ts"));
+        // import { type Bar, bar } keeps bar
+        assert!(has_module("bar.ts"));
+        assert!(!has_module("baz.ts"));
+        assert!(!has_module("types.ts"));
+        assert!(!has_module("side-effects.ts"));
+    }
+}
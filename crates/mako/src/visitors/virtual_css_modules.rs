@@ -1,8 +1,10 @@
-use swc_core::common::Mark;
+use swc_core::common::errors::HANDLER;
+use swc_core::common::{Mark, Span};
 use swc_core::ecma::ast::{CallExpr, ImportDecl, Lit, Str};
 use swc_core::ecma::visit::{VisitMut, VisitMutWith};
 
 use crate::ast::utils::{is_commonjs_require, is_dynamic_import};
+use crate::visitors::css_import_attributes::STYLESHEET_QUERY;
 
 pub struct VirtualCSSModules {
     pub auto_css_modules: bool,
@@ -17,15 +19,32 @@ pub fn is_css_path(path: &str) -> bool {
     path.ends_with(".css") || path.ends_with(".less") || path.ends_with(".scss")
 }
 
+// e.g. "./a.css?modules&foo=bar" => ("./a.css", ["modules", "foo=bar"])
+fn split_query(source: &str) -> (&str, Vec<&str>) {
+    match source.split_once('?') {
+        Some((path, query)) => (path, query.split('&').filter(|p| !p.is_empty()).collect()),
+        None => (source, vec![]),
+    }
+}
+
+fn has_query_key(query: &[&str], key: &str) -> bool {
+    query
+        .iter()
+        .any(|p| p.split_once('=').map_or(*p, |(k, _)| k) == key)
+}
+
+enum CssModulesQuery {
+    // ?modules
+    Force,
+    // ?global
+    Global,
+    None,
+}
+
 impl VisitMut for VirtualCSSModules {
     fn visit_mut_import_decl(&mut self, import_decl: &mut ImportDecl) {
-        let is_css_modules = is_css_modules_path(&import_decl.src.value)
-            || (self.auto_css_modules
-                && is_css_path(&import_decl.src.value)
-                && !&import_decl.specifiers.is_empty());
-        if is_css_modules {
-            self.replace_source(&mut import_decl.src);
-        }
+        let auto = self.auto_css_modules && !import_decl.specifiers.is_empty();
+        self.handle_source(&mut import_decl.src, auto);
         import_decl.visit_mut_children_with(self);
     }
 
@@ -41,12 +60,8 @@ impl VisitMut for VirtualCSSModules {
                 && let Some(lit) = arg.expr.as_mut_lit()
                 && let Lit::Str(ref mut str) = lit
             {
-                let ref_ = str.value.as_ref();
                 // `require()` and `import()` do not support auto_css_modules
-                let is_css_modules = is_css_modules_path(ref_);
-                if is_css_modules {
-                    self.replace_source(str);
-                }
+                self.handle_source(str, false);
             }
         }
 
@@ -55,8 +70,57 @@ impl VisitMut for VirtualCSSModules {
 }
 
 impl VirtualCSSModules {
-    fn replace_source(&mut self, source: &mut Str) {
-        let to_replace = format!("{}?asmodule", &source.value.to_string());
+    fn handle_source(&mut self, source: &mut Str, auto: bool) {
+        let value = source.value.to_string();
+        let (path, query) = split_query(&value);
+        if !is_css_path(path) || has_query_key(&query, STYLESHEET_QUERY) {
+            return;
+        }
+        let is_css_modules = match Self::css_modules_query(&value, &query, source.span) {
+            CssModulesQuery::Force => true,
+            CssModulesQuery::Global => false,
+            CssModulesQuery::None => is_css_modules_path(path) || auto,
+        };
+        if is_css_modules {
+            self.replace_source(source, path, &query);
+        }
+    }
+
+    fn css_modules_query(source: &str, query: &[&str], span: Span) -> CssModulesQuery {
+        match (
+            has_query_key(query, "modules"),
+            has_query_key(query, "global"),
+        ) {
+            (true, true) => {
+                HANDLER.with(|handler| {
+                    handler
+                        .struct_span_err(
+                            span,
+                            &format!(
+                                "Conflicting CSS Modules query in \"{}\", `?modules` and `?global` can not be used together",
+                                source
+                            ),
+                        )
+                        .emit();
+                });
+                CssModulesQuery::None
+            }
+            (true, false) => CssModulesQuery::Force,
+            (false, true) => CssModulesQuery::Global,
+            (false, false) => CssModulesQuery::None,
+        }
+    }
+
+    fn replace_source(&mut self, source: &mut Str, path: &str, query: &[&str]) {
+        // ?modules is only an opt-in flag here, the asmodule virtual module will
+        // import the css with ?modules itself
+        let mut query = query
+            .iter()
+            .filter(|p| **p != "modules" && !p.starts_with("modules="))
+            .copied()
+            .collect::<Vec<_>>();
+        query.push("asmodule");
+        let to_replace = format!("{}?{}", path, query.join("&"));
         let span = source.span;
         *source = Str::from(to_replace);
         source.span = span;
@@ -65,6 +129,9 @@ impl VirtualCSSModules {
 
 #[cfg(test)]
 mod tests {
+    use anyhow::Result;
+    use swc_core::base::try_with_handler;
+    use swc_core::common::errors::HANDLER;
     use swc_core::common::GLOBALS;
     use swc_core::ecma::visit::VisitMutWith;
 
@@ -119,18 +186,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_css_modules_virtual_with_query() {
+        assert_eq!(
+            run(r#"import x from "./legacy.css?modules";"#, false),
+            r#"import x from "./legacy.css?asmodule";"#
+        );
+        assert_eq!(
+            run(r#"require("./legacy.less?modules&foo=bar");"#, false),
+            r#"require("./legacy.less?foo=bar&asmodule");"#
+        );
+        assert_eq!(
+            run(r#"import x from "./foo.module.css?global";"#, false),
+            r#"import x from "./foo.module.css?global";"#
+        );
+        assert_eq!(
+            run(r#"import x from "./foo.css?global";"#, true),
+            r#"import x from "./foo.css?global";"#
+        );
+        assert_eq!(
+            run(r#"import x from "./foo.js?modules";"#, false),
+            r#"import x from "./foo.js?modules";"#
+        );
+    }
+
+    #[test]
+    fn test_css_modules_virtual_with_conflicting_query() {
+        assert!(try_run(r#"import x from "./foo.css?modules&global";"#, false).is_err());
+    }
+
     fn run(js_code: &str, auto_css_modules: bool) -> String {
+        try_run(js_code, auto_css_modules).unwrap()
+    }
+
+    fn try_run(js_code: &str, auto_css_modules: bool) -> Result<String> {
         let mut test_utils = TestUtils::gen_js_ast(js_code);
         let ast = test_utils.ast.js_mut();
         let unresolved_mark = ast.unresolved_mark;
+        let cm = test_utils.context.meta.script.cm.clone();
 
         GLOBALS.set(&test_utils.context.meta.script.globals, || {
-            let mut visitor = VirtualCSSModules {
-                auto_css_modules,
-                unresolved_mark,
-            };
-            ast.ast.visit_mut_with(&mut visitor);
-        });
-        test_utils.js_ast_to_code()
+            try_with_handler(cm, Default::default(), |handler| {
+                HANDLER.set(handler, || {
+                    let mut visitor = VirtualCSSModules {
+                        auto_css_modules,
+                        unresolved_mark,
+                    };
+                    ast.ast.visit_mut_with(&mut visitor);
+                });
+                Ok(())
+            })
+        })?;
+        Ok(test_utils.js_ast_to_code())
     }
 }
@@ -0,0 +1,131 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::compiler::Compiler;
+use crate::module::{relative_to_root, ModuleId, ResolveType};
+use crate::module_graph::ImportChain;
+
+impl Compiler {
+    /// Import chains from the entries to the modules of the file, shortest
+    /// first, it should be called after compile.
+    pub fn why(&self, path: &Path, max_chains: usize) -> Result<Vec<ImportChain>> {
+        let path = ModuleId::from_resolved(&path.to_string_lossy(), None, &self.context.root);
+        let module_graph = self.context.module_graph.read().unwrap();
+        // modules of the file with different queries, e.g. ?raw
+        let targets = module_graph
+            .modules()
+            .into_iter()
+            .filter(|module| module.id.path() == path.path())
+            .map(|module| module.id.clone())
+            .collect::<Vec<_>>();
+        if targets.is_empty() {
+            return Err(anyhow!(
+                "Module \"{}\" is not in the bundle",
+                path.relative_display(&self.context.root)
+            ));
+        }
+
+        let mut chains = targets
+            .iter()
+            .flat_map(|target| module_graph.import_chains(target, max_chains))
+            .collect::<Vec<_>>();
+        chains.sort_by_key(|chain| chain.steps.len());
+        chains.truncate(max_chains);
+        Ok(chains)
+    }
+
+    pub fn format_import_chains(&self, chains: &[ImportChain]) -> String {
+        let root = &self.context.root;
+        let module_graph = self.context.module_graph.read().unwrap();
+        chains
+            .iter()
+            .map(|chain| {
+                let mut lines = vec![chain.entry.relative_display(root)];
+                for (i, step) in chain.steps.iter().enumerate() {
+                    let dependency = &step.dependency;
+                    let boundary = match dependency.resolve_type {
+                        ResolveType::DynamicImport(_) => " (dynamic import)",
+                        ResolveType::Worker(_) => " (worker)",
+                        _ => "",
+                    };
+                    let module = module_graph.get_module(&step.to);
+                    let condition = module
+                        .and_then(|module| module.selected_condition())
+                        .map(|condition| format!(" (condition: {})", condition))
+                        .unwrap_or_default();
+                    let resolved_root = module
+                        .and_then(|module| module.resolved_root())
+                        .map(|dir| {
+                            format!(
+                                " (root: {})",
+                                relative_to_root(&dir.to_string_lossy().to_string(), root)
+                            )
+                        })
+                        .unwrap_or_default();
+                    lines.push(format!(
+                        "{}└─ {} \"{}\" {}{}{}{}",
+                        "   ".repeat(i),
+                        import_kind(&dependency.resolve_type),
+                        dependency.source,
+                        step.to.relative_display(root),
+                        condition,
+                        resolved_root,
+                        boundary
+                    ));
+                }
+                lines.join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+fn import_kind(resolve_type: &ResolveType) -> &'static str {
+    match resolve_type {
+        ResolveType::Import(_) => "import",
+        ResolveType::ExportNamed(_) | ResolveType::ExportAll => "export from",
+        ResolveType::Require => "require",
+        ResolveType::DynamicImport(_) => "import()",
+        ResolveType::Css => "@import",
+        ResolveType::Worker(_) => "new Worker",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::utils::test_helper::setup_compiler;
+
+    #[test]
+    fn test_why() {
+        let compiler = setup_compiler("test/build/why", false);
+        compiler.compile().unwrap();
+
+        let chains = compiler.why(Path::new("shared.ts"), 10).unwrap();
+        assert_eq!(
+            compiler.format_import_chains(&chains),
+            r#"
+./index.ts
+└─ import "./shared" ./shared.ts
+
+./index.ts
+└─ export from "./barrel" ./barrel.ts
+   └─ import "./shared" ./shared.ts
+
+./index.ts
+└─ import() "./lazy" ./lazy.ts (dynamic import)
+   └─ import "./shared" ./shared.ts"#
+                .trim()
+        );
+
+        let chains = compiler.why(Path::new("shared.ts"), 1).unwrap();
+        assert_eq!(chains.len(), 1);
+        assert!(compiler
+            .why(Path::new("missing.ts"), 10)
+            .unwrap_err()
+            .to_string()
+            .contains("Module \"./missing.ts\" is not in the bundle"));
+    }
+}
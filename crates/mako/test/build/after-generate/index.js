@@ -0,0 +1,2 @@
+import "./index.css";
+console.log("after generate");
@@ -0,0 +1,9 @@
+import { formatGreeting } from 'foo';
+import('./src/lazy').then(({ default: lazy }) => lazy());
+
+// The comments and the long names are dropped by the minifier, so the size of
+// the module in the report is much smaller than the size of this file, which
+// is what the report is for: the bytes that are actually shipped to the users,
+// instead of the bytes that are written by the developers.
+const greetingMessageForTheAnalyzeReport = formatGreeting('analyze');
+console.log(greetingMessageForTheAnalyzeReport);
@@ -0,0 +1,5 @@
+import { button } from 'ui';
+import { title } from './shared';
+
+console.log(button(title));
+import('./route').then(({ render }) => render());
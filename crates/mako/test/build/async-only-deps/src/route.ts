@@ -0,0 +1,6 @@
+import { chart } from 'heavy';
+import { title } from './shared';
+
+export function render() {
+  console.log(chart(title));
+}
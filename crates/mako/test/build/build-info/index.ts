@@ -0,0 +1,3 @@
+import buildInfo, { hash } from 'virtual:mako-build-info';
+
+console.log(buildInfo, hash);
@@ -0,0 +1,5 @@
+import './index.css';
+import { a } from './a';
+
+console.log(a);
+import('./lazy');
@@ -0,0 +1,4 @@
+import('./lazy').then(
+  (m) => console.log(m.default),
+  (e) => console.log(e.name, e.type),
+);
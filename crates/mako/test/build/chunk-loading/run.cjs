@@ -0,0 +1,19 @@
+// runs the bundle with a fake document, whose chunks can't be loaded by
+// the script elements, and fetch() which reads the files in dist
+const fs = require('fs');
+const path = require('path');
+const { pathToFileURL, fileURLToPath } = require('url');
+
+const dist = pathToFileURL(path.join(__dirname, 'dist') + '/').href;
+globalThis.self = globalThis;
+globalThis.publicPath = dist;
+globalThis.document = { baseURI: dist };
+globalThis.fetch = (url) => {
+  const file = fileURLToPath(url);
+  return Promise.resolve({
+    ok: fs.existsSync(file),
+    statusText: 'Not Found',
+    text: () => Promise.resolve(fs.readFileSync(file, 'utf-8')),
+  });
+};
+require('./dist/index.js');
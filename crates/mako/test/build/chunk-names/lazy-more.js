@@ -0,0 +1 @@
+export const lazyMore = 'lazy more';
@@ -0,0 +1,8 @@
+import { shared } from './shared';
+
+import(/* webpackChunkName: "main" */ './page');
+import(/* webpackChunkName: "lazy" */ './lazy');
+import(/* webpackChunkName: "lazy" */ './lazy-more');
+import(/* webpackChunkName: "vendor" */ './vendor-page');
+
+console.log(shared);
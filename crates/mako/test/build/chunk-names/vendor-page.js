@@ -0,0 +1,3 @@
+import { dep } from './vendor-dep';
+
+export const vendorPage = dep;
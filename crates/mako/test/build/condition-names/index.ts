@@ -0,0 +1,4 @@
+import Button from './Button';
+import { platform } from 'ui';
+
+console.log(Button, platform);
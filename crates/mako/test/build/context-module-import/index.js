@@ -0,0 +1,15 @@
+function load(lang) {
+  return import(`./locales/${lang}.json`);
+}
+
+function loadMessages(lang) {
+  return import(`./locales/messages_${lang}.json`);
+}
+
+function loadRules(lang) {
+  return import(`./locales/c++/c++_${lang}.json`);
+}
+
+load('en').then(console.log);
+loadMessages('en').then(console.log);
+loadRules('en').then(console.log);
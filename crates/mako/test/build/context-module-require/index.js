@@ -0,0 +1,5 @@
+const name = window.handler;
+console.log(require('./handlers/' + name + '.js'));
+console.log(require(`./handlers/${name}`));
+console.log(require.resolve('./handlers/a.js'));
+import('./lazy').then(console.log);
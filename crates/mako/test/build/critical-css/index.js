@@ -0,0 +1,3 @@
+import "./above.css";
+import "./index.css";
+console.log("critical css");
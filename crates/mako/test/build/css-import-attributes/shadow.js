@@ -0,0 +1,5 @@
+import './plain.css';
+import sheet from './sheet.css' with { type: 'css' };
+
+const theme = import('./theme.css', { with: { type: 'css' } });
+console.log(sheet, theme);
@@ -0,0 +1,9 @@
+import { shared } from "@/shared";
+import "./style.css";
+import type { Props } from "./types";
+import { missing } from "./missing";
+const lazy = () => import("./lazy");
+const worker = new Worker(new URL("./worker", import.meta.url));
+const cjs = require("./shared.cjs");
+
+export default { shared, missing, lazy, worker, cjs } as Props;
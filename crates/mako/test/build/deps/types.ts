@@ -0,0 +1 @@
+export type Props = Record<string, unknown>;
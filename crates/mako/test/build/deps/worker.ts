@@ -0,0 +1 @@
+self.postMessage(1);
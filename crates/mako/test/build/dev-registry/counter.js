@@ -0,0 +1,2 @@
+globalThis.executions = (globalThis.executions || 0) + 1;
+exports.executions = globalThis.executions;
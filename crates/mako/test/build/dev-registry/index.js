@@ -0,0 +1,15 @@
+const counterId = Object.keys(__mako_require__.m).find((id) =>
+  id.endsWith('counter.js'),
+);
+const first = require('./counter');
+const cached = __mako_require__.c[counterId].exports === first;
+const invalidated = __mako_require__.hot.invalidate(counterId);
+const second = require('./counter');
+
+console.log(
+  JSON.stringify({
+    cached,
+    invalidated,
+    executions: [first.executions, second.executions],
+  }),
+);
@@ -0,0 +1,28 @@
+const slow = require('./slow');
+
+let error;
+try {
+  require('./throws');
+} catch (e) {
+  error = e.message;
+}
+
+// the entry is timed after it's executed
+setTimeout(() => {
+  const timings = globalThis.__MAKO_TIMINGS__;
+  const id = (name) =>
+    Object.keys(timings.modules).find((id) => id.endsWith(name));
+  const index = timings.modules[id('index.js')];
+  const slowTiming = timings.modules[id('slow.js')];
+
+  console.log(
+    JSON.stringify({
+      self: slow.self,
+      error,
+      // the self time of the entry excludes the slow module it requires
+      slowest: slowTiming.self > 20 && index.self < slowTiming.self,
+      total: index.total >= slowTiming.total,
+      chunks: Object.keys(timings.chunks),
+    }),
+  );
+});
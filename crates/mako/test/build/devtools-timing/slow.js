@@ -0,0 +1,4 @@
+const start = Date.now();
+while (Date.now() - start < 30) {}
+
+exports.self = this === module.exports;
@@ -0,0 +1,5 @@
+// mako-ignore-next-line MAKO_CIRCULAR
+import { a } from './a';
+
+export const b = 'b';
+console.log(a);
@@ -0,0 +1,5 @@
+/* mako-disable MAKO_DYNAMIC_REQUIRE, MAKO_CIRCULR */
+
+export function handle(name) {
+  return require(name);
+}
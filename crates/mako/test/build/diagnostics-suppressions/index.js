@@ -0,0 +1,4 @@
+import { a } from './a';
+import { handle } from './handlers';
+
+console.log(a, handle);
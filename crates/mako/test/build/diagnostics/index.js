@@ -0,0 +1,4 @@
+import { a } from './a';
+import { lib } from 'old-lib';
+
+console.log(a, lib);
@@ -0,0 +1,3 @@
+import format from './format';
+
+export default () => console.log(format('a'));
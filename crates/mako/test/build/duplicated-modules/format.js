@@ -0,0 +1,3 @@
+export default function format(name) {
+  return `[${name}] ${new Date().toISOString()}`;
+}
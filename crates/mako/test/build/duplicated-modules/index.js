@@ -0,0 +1,2 @@
+import('./a').then((m) => m.default());
+import('./b').then((m) => m.default());
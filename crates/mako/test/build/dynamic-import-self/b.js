@@ -0,0 +1,4 @@
+export const b = 'b';
+
+// a dynamic cycle back to the root of the async chunk
+export const loadA = () => import('./a');
@@ -0,0 +1,5 @@
+export const name = 'index';
+
+// a direct self import
+import('./index').then((m) => console.log(m.name));
+import('./a').then((m) => m.loadA());
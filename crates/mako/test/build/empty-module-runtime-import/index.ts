@@ -0,0 +1,3 @@
+import { Foo } from './types';
+
+console.log(Foo);
@@ -0,0 +1,8 @@
+import './global.d.ts';
+import './types';
+import type { Foo } from './types';
+export * from './barrel';
+
+const required = require('./required');
+const foo: Foo = { a: 1 };
+console.log(foo, required);
@@ -0,0 +1,3 @@
+export interface Required {
+  b: string;
+}
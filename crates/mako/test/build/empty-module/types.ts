@@ -0,0 +1,5 @@
+export interface Foo {
+  a: number;
+}
+
+export type Bar = string;
@@ -0,0 +1,3 @@
+import { order } from './shared';
+
+order.push('bootstrap');
@@ -0,0 +1,4 @@
+import { order } from './shared';
+
+order.push('main');
+import('./lazy');
@@ -0,0 +1 @@
+globalThis.order.push(`entry ${globalThis.__BUILD__}`);
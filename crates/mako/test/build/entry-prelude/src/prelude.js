@@ -0,0 +1 @@
+globalThis.order = ['prelude'];
@@ -0,0 +1,2 @@
+import './utils';
+console.log('a');
@@ -0,0 +1,2 @@
+import './utils.css';
+console.log('utils');
@@ -0,0 +1,4 @@
+import { render, compile } from './template';
+import { add } from './math';
+
+console.log(render({ name: 'mako' }), compile(add(1, 2)));
@@ -0,0 +1,5 @@
+export function render(data) {
+  return eval('data.name');
+}
+
+export const compile = new Function('x', 'return x + process.env.NODE_ENV');
@@ -0,0 +1,5 @@
+export const Button = 'a';
+export const Local = 'a';
+export { Shared } from './shared';
+
+export default 'a';
@@ -0,0 +1,2 @@
+export const Button = 'b';
+export * from './shared';
@@ -0,0 +1,5 @@
+export * from './a';
+export * from './b';
+export * from './nested';
+
+export const Local = 'local';
@@ -0,0 +1,4 @@
+export function Icon() {
+  return 'd';
+}
+export const Only = 'd';
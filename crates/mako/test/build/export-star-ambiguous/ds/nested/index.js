@@ -0,0 +1,2 @@
+export * from './c';
+export * from './d';
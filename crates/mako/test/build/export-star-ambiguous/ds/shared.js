@@ -0,0 +1 @@
+export const Shared = 'shared';
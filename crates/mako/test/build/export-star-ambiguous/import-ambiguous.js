@@ -0,0 +1,3 @@
+import { Button, Icon } from './ds';
+
+console.log(Button, Icon);
@@ -0,0 +1,12 @@
+import * as ds from './ds';
+import { Local, Only, Shared } from './ds';
+
+console.log(
+  JSON.stringify({
+    // Button and Icon are ambiguous, default is not re-exported
+    keys: Object.keys(ds).sort(),
+    Local,
+    Only,
+    Shared,
+  }),
+);
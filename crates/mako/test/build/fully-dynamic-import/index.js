@@ -0,0 +1,5 @@
+function load(path) {
+  return import(path);
+}
+
+load('./a').then(console.log);
@@ -0,0 +1,5 @@
+function load(path) {
+  return require(path);
+}
+
+console.log(load('./a'));
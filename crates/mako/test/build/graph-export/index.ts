@@ -0,0 +1,6 @@
+import { foo } from './foo';
+import pkg from 'pkg';
+
+console.log(foo, pkg);
+import('./lazy').then((m) => console.log(m));
+new Worker(new URL('./worker.ts', import.meta.url));
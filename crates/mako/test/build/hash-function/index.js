@@ -0,0 +1,4 @@
+import logo from './logo.png';
+
+console.log(logo);
+import('./lazy');
@@ -0,0 +1,2 @@
+import data from './__fixtures__/data';
+console.log(data);
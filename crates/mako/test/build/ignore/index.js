@@ -0,0 +1,8 @@
+const components = require.context('./src', true, /\.js$/);
+console.log(components.keys());
+
+function load(name) {
+  return import(`./src/${name}`);
+}
+
+load('a.js').then(console.log);
@@ -0,0 +1,6 @@
+import webp from './hero.png?format=webp&w=16';
+import srcset from './hero.png?srcset=8,16';
+import jpeg from './hero.png?format=jpeg&quality=60';
+import broken from './broken.png?format=webp';
+
+console.log(webp, srcset, jpeg, broken);
@@ -0,0 +1,3 @@
+import baz from 'baz';
+
+console.log(baz);
@@ -0,0 +1,5 @@
+import foo from 'foo';
+import { render } from '@scope/bar/client';
+import React from 'react';
+
+render(foo, React);
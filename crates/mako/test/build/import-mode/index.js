@@ -0,0 +1,11 @@
+import(/* webpackMode: "eager" */ './eager').then(({ default: eager }) =>
+  console.log(eager()),
+);
+import(/* webpackMode: "weak" */ './weak').catch((e) => console.log(e.code));
+import('./lazy').then(({ default: lazy }) => console.log(lazy()));
+
+function load(lang) {
+  return import(/* webpackMode: "lazy-once" */ `./locales/${lang}.js`);
+}
+
+load('en').then(({ default: en }) => console.log(en));
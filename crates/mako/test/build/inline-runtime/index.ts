@@ -0,0 +1,4 @@
+import './style.css';
+
+console.log('</script>');
+import('./lazy').then(({ lazy }) => console.log(lazy));
@@ -0,0 +1 @@
+export const circular = () => 'CIRCULAR_MODULE';
@@ -0,0 +1 @@
+export class Base { name = 'CLASS_MODULE'; }
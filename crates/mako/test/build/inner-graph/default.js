@@ -0,0 +1 @@
+export default function unused() { return 'DEFAULT_MODULE'; }
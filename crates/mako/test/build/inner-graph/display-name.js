@@ -0,0 +1 @@
+export const displayName = 'DISPLAY_NAME_MODULE';
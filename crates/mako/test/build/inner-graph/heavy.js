@@ -0,0 +1 @@
+export function makeHeavy(x) { return [x, 'HEAVY_MODULE']; }
@@ -0,0 +1,4 @@
+import { used } from './lib';
+import unusedDefault from './default';
+
+console.log(used);
@@ -0,0 +1,47 @@
+import { makeHeavy } from './heavy';
+import { dep } from './dep';
+import { Base } from './class';
+import { helper } from './helper';
+import { circular } from './circular';
+import * as ns from './namespace';
+import { sideEffect } from './side-effect';
+import { displayName } from './display-name';
+
+// a chain of the pure declarations
+const heavy = /*#__PURE__*/ makeHeavy(dep);
+const chain = /*#__PURE__*/ makeHeavy(heavy);
+export const unusedChain = () => chain;
+
+// classes
+class Widget extends Base {}
+export class UnusedClass extends Widget {
+  static instance = new Widget();
+}
+
+// functions
+const run = function () {
+  return helper();
+};
+export function unusedFunction() {
+  return run();
+}
+
+// circular references
+function ping() {
+  return pong() + circular();
+}
+function pong() {
+  return ping();
+}
+export const unusedCircular = () => ping();
+
+// reads of the import namespace
+const value = ns.value;
+export const unusedNamespace = () => value;
+
+// the impure ones are kept with what they reference
+export const unusedImpure = sideEffect();
+function named() {}
+named.displayName = displayName;
+
+export const used = 'USED';
@@ -0,0 +1 @@
+export const sideEffect = () => 'SIDE_EFFECT_MODULE';
@@ -0,0 +1,2 @@
+import { foo } from './foo';
+console.log(foo);
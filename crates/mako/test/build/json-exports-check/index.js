@@ -0,0 +1,6 @@
+import pkg, { version, verison } from './package.json';
+import * as data from './data.yaml';
+import { title, missing } from './data.yaml';
+import logo, { height } from './logo.png';
+
+console.log(pkg, version, verison, data, title, missing, logo, height);
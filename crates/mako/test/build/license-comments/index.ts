@@ -0,0 +1,6 @@
+// normal comment
+import foo from 'foo';
+import other from 'foo/other';
+import bar from 'bar';
+
+console.log(foo, other, bar);
@@ -0,0 +1,4 @@
+﻿// the same module with different line endings
+export const a = `1
+2`;
+console.log(a);
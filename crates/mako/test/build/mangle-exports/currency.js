@@ -0,0 +1,3 @@
+export function formatCurrency(value) {
+  return '$' + value.toFixed(2);
+}
@@ -0,0 +1,15 @@
+import { formatCurrency, parseCurrency } from './utils';
+import * as math from './math';
+import * as colors from './colors';
+const { legacyValue } = require('./legacy');
+
+// the key of the namespace is only known at runtime
+const key = ['primary', 'Color'].join('');
+console.log(
+  formatCurrency(parseCurrency('1.5')),
+  math.multiplyNumbers(2, 3),
+  colors[key],
+  legacyValue,
+);
+
+export const entryExportName = 'entry';
@@ -0,0 +1,3 @@
+export function multiplyNumbers(a, b) {
+  return a * b;
+}
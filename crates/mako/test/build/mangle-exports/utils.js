@@ -0,0 +1,5 @@
+export { formatCurrency } from './currency';
+
+export function parseCurrency(value) {
+  return Number(value);
+}
@@ -0,0 +1,7 @@
+'use server';
+
+import { db } from './db';
+
+export async function save() {
+  return db.save();
+}
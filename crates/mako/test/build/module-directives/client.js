@@ -0,0 +1,4 @@
+'use strict';
+'use client';
+
+export const Button = 'button';
@@ -0,0 +1,5 @@
+export const db = {
+  save() {
+    return 'saved';
+  },
+};
@@ -0,0 +1,4 @@
+import { Button } from './client';
+import { save } from './action';
+
+console.log(Button, save);
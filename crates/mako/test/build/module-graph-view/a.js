@@ -0,0 +1,2 @@
+import "./c";
+console.log("a");
@@ -0,0 +1,3 @@
+import "./a";
+import "./b";
+console.log("index");
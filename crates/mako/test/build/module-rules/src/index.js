@@ -0,0 +1,5 @@
+import Legacy from './legacy/Legacy';
+import shader from './shader.frag';
+import { debug } from '../vendor/lib';
+
+console.log(Legacy, shader, debug, typeof VENDOR_DEBUG);
@@ -0,0 +1,5 @@
+const React = { createElement: () => null };
+
+export default function Legacy() {
+  return <div className="legacy" />;
+}
@@ -0,0 +1,6 @@
+// the whole module.exports is the default export of it in node
+module.exports = {
+  __esModule: true,
+  default: 'default',
+  named: 'named',
+};
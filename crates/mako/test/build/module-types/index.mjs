@@ -0,0 +1,6 @@
+import helper, { named } from './helper.cjs';
+import * as ns from './helper.cjs';
+import './lib/polyfill.js';
+import legacy from './vendor/legacy.js';
+
+console.log(helper.default, named, ns.default, legacy);
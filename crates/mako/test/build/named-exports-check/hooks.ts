@@ -0,0 +1,5 @@
+export function useHistory() {}
+
+export function useParams() {}
+
+export default 'hooks';
@@ -0,0 +1,5 @@
+import router, { useHistroy, useParams, Link } from './router';
+import cjs, { notExported } from './cjs';
+import { alsoNotExported } from './reexport-cjs';
+
+console.log(router, cjs, useHistroy, useParams, Link, notExported, alsoNotExported);
@@ -0,0 +1,3 @@
+export * from './cjs';
+
+export const bar = 'bar';
@@ -0,0 +1,3 @@
+export * from './hooks';
+
+export function Link() {}
@@ -0,0 +1,5 @@
+const direct = require('./addon/direct.node');
+const gyp = require('gyp-addon');
+const bindings = require('bindings-addon');
+
+console.log(JSON.stringify([direct, gyp, bindings]));
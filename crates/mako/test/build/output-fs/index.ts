@@ -0,0 +1,5 @@
+import './style.css';
+import { foo } from './foo';
+
+console.log(foo);
+import('./lazy').then(({ lazy }) => console.log(lazy));
@@ -0,0 +1,5 @@
+import { element } from 'jsx-pkg';
+import { greet } from 'flow-pkg';
+import { value } from 'proposal-pkg';
+
+console.log(element, greet('flow'), value);
@@ -0,0 +1,8 @@
+import { a } from './a';
+import type { T } from './types';
+export * from './b';
+import './missing';
+
+export function sum(t: T) {
+  return (a + t.b
+}
@@ -0,0 +1 @@
+export interface T { b: number }
@@ -0,0 +1,4 @@
+import { Button } from 'ui';
+import { useToggle } from 'hooks';
+
+console.log(Button, useToggle);
@@ -0,0 +1 @@
+exports.flat = (list) => list.flat();
@@ -0,0 +1,4 @@
+import { flat } from "./flat";
+Promise.allSettled([Promise.resolve(flat([1, [2]]))]).then(() => {
+  console.log(globalThis);
+});
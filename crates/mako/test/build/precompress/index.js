@@ -0,0 +1,3 @@
+import './index.css';
+
+console.log('precompress');
@@ -0,0 +1,9 @@
+import { add } from './utils/math';
+import './style.css';
+import logo from './logo.png';
+import styles from './button.module.css';
+import dep from 'dep';
+
+export const lazy = () => import('./lazy');
+
+export { add, logo, styles, dep };
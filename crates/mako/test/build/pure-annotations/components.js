@@ -0,0 +1,9 @@
+import { styled } from './styled';
+
+export const Title = /*#__PURE__*/ styled.div`color: red;`;
+export const Box = /*#__PURE__*/ styled.div;
+export const Seq = /*#__PURE__*/ (0, styled.create)();
+export const Paren = (/*#__PURE__*/ styled.create());
+export const Both = (/*#__PURE__*/ styled.create(), /*#__PURE__*/ styled.create());
+export const Kept = styled.create();
+export const used = 'used';
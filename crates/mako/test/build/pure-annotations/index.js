@@ -0,0 +1,3 @@
+import { used } from './components';
+
+console.log(used);
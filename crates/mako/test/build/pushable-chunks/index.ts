@@ -0,0 +1,3 @@
+import('./lazy').then(({ render }) => {
+  document.body.innerHTML = render();
+});
@@ -0,0 +1,2 @@
+import('./locale.js?locale=zh-CN');
+import('./locale.js?locale=zh-TW');
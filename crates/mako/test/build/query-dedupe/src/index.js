@@ -0,0 +1,11 @@
+import a from './icon.svg?raw&width=24&height=24';
+import b from './icon.svg?height=24&width=24&raw=';
+import c from './icon.svg?width=%32%34&raw&height=24';
+import d from './icon.svg?raw&width=32';
+import logo from './logo.png?w=1&h=2';
+import logo2 from './logo.png?h=2&w=1';
+import styles from './a.css?modules&theme=dark';
+import styles2 from './a.css?theme=dark&modules=';
+import('./lazy.js?b=1&a=2');
+import('./lazy.js?a=2&b=1');
+console.log(a, b, c, d, logo, logo2, styles, styles2);
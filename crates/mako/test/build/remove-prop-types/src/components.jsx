@@ -0,0 +1,42 @@
+import React from 'react';
+import PropTypes from 'prop-types';
+
+const isDev = process.env.NODE_ENV !== 'production';
+
+export function Button({ label, onClick }) {
+  return <button onClick={onClick}>{label}</button>;
+}
+
+Button.propTypes = {
+  label: PropTypes.string.isRequired,
+  onClick: PropTypes.func,
+};
+
+Button.defaultProps = {
+  label: 'button',
+};
+
+process.env.NODE_ENV !== 'production' && (Button.displayName = 'Button');
+process.env.NODE_ENV !== 'production' ? (Button.__docgen = 'button docs') : void 0;
+if (process.env.NODE_ENV !== 'production') Button.whyDidYouRender = true;
+isDev && (Button.displayName = 'DevButton');
+
+export class Panel extends React.Component {
+  static propTypes = {
+    title: PropTypes.string,
+    children: PropTypes.node,
+  };
+
+  static defaultProps = {
+    title: 'panel',
+  };
+
+  render() {
+    return (
+      <section>
+        <h1>{this.props.title}</h1>
+        {this.props.children}
+      </section>
+    );
+  }
+}
@@ -0,0 +1,11 @@
+import { Button, Panel } from './components';
+import { Tag } from 'ui-kit';
+
+export default function App() {
+  return (
+    <Panel title="app">
+      <Button label="ok" onClick={() => {}} />
+      <Tag>kit</Tag>
+    </Panel>
+  );
+}
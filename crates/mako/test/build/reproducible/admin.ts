@@ -0,0 +1,5 @@
+import { format } from './shared';
+import { b, a, c } from './exports';
+
+console.log(format('admin'), a, b, c);
+import('./lazy');
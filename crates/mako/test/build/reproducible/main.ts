@@ -0,0 +1,9 @@
+import { format } from './shared';
+import './style.css';
+import logo from './logo.png';
+
+const locales = require.context('./locales', false, /\.json$/);
+const page = (name: string) => import(`./pages/${name}`);
+
+console.log(format(locales.keys().join(',')), logo, page);
+import('./lazy').then(({ lazy }) => lazy());
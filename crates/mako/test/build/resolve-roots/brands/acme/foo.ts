@@ -0,0 +1 @@
+export default "not a package";
@@ -0,0 +1,4 @@
+import theme from "./theme";
+import { format } from "./utils";
+
+export default format(theme);
@@ -0,0 +1,5 @@
+import button from "./Button";
+import header from "@/Header";
+import foo from "foo";
+
+console.log(button, header, foo);
@@ -0,0 +1 @@
+export const format = (theme: string) => `header of ${theme}`;
@@ -0,0 +1,4 @@
+#!/usr/bin/env node
+import { run } from "./run";
+
+run(process.argv.slice(2));
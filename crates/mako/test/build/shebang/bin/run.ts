@@ -0,0 +1,3 @@
+export function run(args: string[]) {
+  console.log("hello", args);
+}
@@ -0,0 +1,4 @@
+console.log('debug loaded');
+export function debug(msg) {
+  console.log(msg);
+}
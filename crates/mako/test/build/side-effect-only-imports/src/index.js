@@ -0,0 +1,8 @@
+import init from './init';
+import { debug } from './debug';
+import Used from './used';
+
+if (DEBUG) {
+  debug('start');
+}
+console.log(new Used());
@@ -0,0 +1,2 @@
+window.__inited = true;
+export default 'init';
@@ -0,0 +1,3 @@
+import { greet } from 'vendor-pkg';
+
+console.log(greet('b'));
@@ -0,0 +1,3 @@
+import { DatePicker } from 'ui-lib';
+
+console.log(DatePicker);
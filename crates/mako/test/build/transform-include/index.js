@@ -0,0 +1,7 @@
+import { modern } from 'modern';
+import { lowered } from 'lowered';
+import { Tag } from 'jsx-lib';
+
+export const app = () => 'app';
+
+console.log(modern, lowered, Tag, app);
@@ -0,0 +1,3 @@
+import styles from './index.module.css';
+
+console.log(import.meta.env.API_URL, import.meta.env.DEBUG, styles.container);
@@ -0,0 +1,2 @@
+export type Bar = number;
+export const bar = 'bar';
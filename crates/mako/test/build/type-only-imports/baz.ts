@@ -0,0 +1 @@
+export type Baz = number;
@@ -0,0 +1,10 @@
+import type { Foo } from './types';
+import { type Bar, bar } from './bar';
+import { type Baz } from './baz';
+export type { Foo } from './types';
+export { type Bar } from './bar';
+export type * from './types';
+
+const foo: Foo = { foo: bar };
+const b: Bar & Baz = 1;
+console.log(foo, b);
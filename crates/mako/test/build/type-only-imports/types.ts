@@ -0,0 +1,5 @@
+import './side-effects';
+
+export interface Foo {
+  foo: string;
+}
@@ -0,0 +1,2 @@
+import './shared.js';
+import './b.css';
@@ -0,0 +1,26 @@
+const runtime = __mako_require_module__;
+
+async function main() {
+  await import('./a.js');
+  await import('./a.js');
+  await import('./b.js');
+  const loaded = links();
+  runtime.releaseChunk('a.js');
+  const afterOne = links();
+  runtime.releaseChunk('a.js');
+  const afterA = links();
+  runtime.releaseChunk('b.js');
+  const afterB = links();
+  await import('./a.js');
+  console.log(
+    JSON.stringify({ loaded, afterOne, afterA, afterB, reloaded: links() }),
+  );
+}
+
+function links() {
+  return document.head.children
+    .filter((node) => node.tagName === 'link')
+    .map((link) => link.href).sort();
+}
+
+main();
@@ -0,0 +1,33 @@
+// runs the bundle with a fake document whose links load at once and the
+// scripts are required from dist
+const path = require('path');
+
+const children = [];
+globalThis.self = globalThis;
+globalThis.document = {
+  baseURI: 'http://localhost/',
+  head: {
+    children,
+    appendChild(node) {
+      node.parentNode = this;
+      children.push(node);
+      setTimeout(() => {
+        if (node.tagName === 'script') {
+          require(path.join(__dirname, 'dist', node.src.slice(1)));
+        }
+        node.onload && node.onload({ type: 'load', target: node });
+      });
+    },
+    removeChild(node) {
+      children.splice(children.indexOf(node), 1);
+      node.parentNode = null;
+    },
+  },
+  createElement(tagName) {
+    return { tagName, setAttribute() {}, getAttribute(name) { return this[name]; } };
+  },
+  querySelectorAll: () => children.filter((node) => node.tagName === 'link'),
+  querySelector: () => null,
+  getElementsByTagName: () => [],
+};
+require('./dist/index.js');
@@ -0,0 +1,3 @@
+import './index.css';
+
+navigator.serviceWorker.register('/sw.js');
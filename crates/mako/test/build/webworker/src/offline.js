@@ -0,0 +1 @@
+export const offline = 'offline page';
@@ -0,0 +1,9 @@
+const offlineCss = require('./sw.css');
+
+self.addEventListener('install', () => {
+  self.__MAKO_MANIFEST__.map((entry) => entry.url);
+});
+
+import('./offline').then(({ offline }) => {
+  console.log(JSON.stringify({ offline, offlineCss }));
+});
@@ -0,0 +1,5 @@
+import { a } from './shared';
+export { c } from './barrel';
+
+console.log(a);
+import('./lazy');
@@ -0,0 +1,9 @@
+import { defineConfig } from '@umijs/mako';
+import { VERSION } from './version';
+
+export default defineConfig(({ mode }: { mode: string }) => ({
+  publicPath: `/${mode}/`,
+  define: {
+    VERSION: JSON.stringify(VERSION),
+  },
+}));
@@ -0,0 +1 @@
+export const VERSION: string = '1.0.0';
@@ -0,0 +1,11 @@
+const assert = require("assert");
+const { parseBuildResult } = require("../../../scripts/test-utils");
+const { files } = parseBuildResult(__dirname);
+
+const content = files["index.js"];
+assert(content.includes(`global["makoChunk_app_a"]`), "chunk loading global should be namespaced by uniqueName");
+assert(content.includes(`(root.__mako_runtimes__ = root.__mako_runtimes__ || {})["app_a"] = runtime`), "runtime should be registered by uniqueName");
+assert(!content.includes("root.jsonpCallback"), "runtime should not overwrite the shared globals");
+
+const lazy = Object.keys(files).find((file) => file.endsWith(".js") && file !== "index.js");
+assert(files[lazy].includes("makoChunk_app_a"), "async chunk should reference the namespaced global");
@@ -0,0 +1,3 @@
+import('./lazy').then(({ lazy }) => {
+  console.log(lazy);
+});
@@ -0,0 +1,24 @@
+const assert = require("assert");
+const { parseBuildResult } = require("../../../scripts/test-utils");
+const { files } = parseBuildResult(__dirname);
+
+const content = files["index.css"];
+
+assert.strictEqual(
+  content.match(/\.base \{/g).length,
+  1,
+  "identical rules should be deduplicated"
+);
+assert(
+  content.includes(`
+@media (max-width: 600px) {
+  .button {
+    padding: 0;
+  }
+  .input {
+    margin: 0;
+  }
+}
+  `.trim()),
+  "adjacent identical media queries should be merged"
+);
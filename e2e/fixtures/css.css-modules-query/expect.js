@@ -0,0 +1,19 @@
+const assert = require("assert");
+const { parseBuildResult } = require("../../../scripts/test-utils");
+const { files } = parseBuildResult(__dirname);
+
+const content = files["index.css"];
+
+assert.match(
+  content,
+  /\.legacy-.{8} \{/,
+  "./legacy.css?modules should be css modules"
+);
+assert(
+  content.includes(`.legacy {`),
+  "./legacy.css should not be css modules"
+);
+assert(
+  content.includes(`.foo {`),
+  "./foo.module.css?global should not be css modules"
+);
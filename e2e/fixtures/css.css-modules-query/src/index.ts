@@ -0,0 +1,8 @@
+// force css modules on a file that can't be renamed
+import legacy from "./legacy.css?modules";
+// the same file without query is still global
+import "./legacy.css";
+// opt out of css modules for a .module.css file
+import "./foo.module.css?global";
+
+console.log(legacy.legacy);
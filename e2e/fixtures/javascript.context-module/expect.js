@@ -9,12 +9,23 @@ const asyncContent = names.filter((name) => name.startsWith("src_i18n_")).reduce
 assert.match(
   content,
   moduleReg(
-    "src\\?context&glob=\\*\\*/\\*",
+    "src\\?context&glob=\\*\\*/\\*&prefix=i18n",
+    [
+      `'./i18n/en-US': ()=>__mako_require__("src/i18n/en-US.json")`,
+      `'./i18n/en-US.json': ()=>__mako_require__("src/i18n/en-US.json")`,
+      `'./i18n/zh-CN': ()=>__mako_require__("src/i18n/zh-CN.json")`,
+      `'./i18n/zh-CN.json': ()=>__mako_require__("src/i18n/zh-CN.json")`
+    ].join(',\n\\s+'),
+    true,
+  ),
+  "should generate context module with correct map",
+);
+
+assert.match(
+  content,
+  moduleReg(
+    "src\\?context&glob=\\*\\*/\\*&prefix=fake.js",
     [
-      `'.': ()=>__mako_require__("src/index.ts")`,
-      `'./': ()=>__mako_require__("src/index.ts")`,
-      `'./ext/json': ()=>__mako_require__("src/ext/json.ts")`,
-      `'./ext/json.ts': ()=>__mako_require__("src/ext/json.ts")`,
       `'./fake.js': ()=>__mako_require__("src/fake.js/index.js")`,
       `'./fake.js/': ()=>__mako_require__("src/fake.js/index.js")`,
       `'./fake.js/a': ()=>__mako_require__("src/fake.js/a.js")`,
@@ -22,77 +33,61 @@ assert.match(
       `'./fake.js/aa': ()=>__mako_require__("src/fake.js/aa.js")`,
       `'./fake.js/aa.js': ()=>__mako_require__("src/fake.js/aa.js")`,
       `'./fake.js/index': ()=>__mako_require__("src/fake.js/index.js")`,
-      `'./fake.js/index.js': ()=>__mako_require__("src/fake.js/index.js")`,
-      `'./first_empty_quasis': ()=>__mako_require__("src/first_empty_quasis/index.js")`,
-      `'./first_empty_quasis/': ()=>__mako_require__("src/first_empty_quasis/index.js")`,
-      `'./first_empty_quasis/index': ()=>__mako_require__("src/first_empty_quasis/index.js")`,
-      `'./first_empty_quasis/index.js': ()=>__mako_require__("src/first_empty_quasis/index.js")`,
-      `'./first_empty_quasis/other': ()=>__mako_require__("src/first_empty_quasis/other.js")`,
-      `'./first_empty_quasis/other.js': ()=>__mako_require__("src/first_empty_quasis/other.js")`,
-      `'./i18n/en-US': ()=>__mako_require__("src/i18n/en-US.json")`,
-      `'./i18n/en-US.json': ()=>__mako_require__("src/i18n/en-US.json")`,
-      `'./i18n/zh-CN': ()=>__mako_require__("src/i18n/zh-CN.json")`,
-      `'./i18n/zh-CN.json': ()=>__mako_require__("src/i18n/zh-CN.json")`,
-      `'./index': ()=>__mako_require__("src/index.ts")`,
-      `'./index.ts': ()=>__mako_require__("src/index.ts")`
+      `'./fake.js/index.js': ()=>__mako_require__("src/fake.js/index.js")`
     ].join(',\n\\s+'),
     true,
   ),
-  "should generate context module with correct map",
+  "should generate context module for fake ext directory with correct map",
 );
 
 assert.match(
   content,
   moduleReg(
-    "src/fake.js\\?context&glob=\\*\\*/\\*",
+    "src/fake.js\\?context&glob=\\*\\*/\\*&prefix=a",
     [
-      "'.': ()=>__mako_require__(\"src/fake.js/index.js\")",
-      "'./': ()=>__mako_require__(\"src/fake.js/index.js\")",
       "'./a': ()=>__mako_require__(\"src/fake.js/a.js\")",
       "'./a.js': ()=>__mako_require__(\"src/fake.js/a.js\")",
       "'./aa': ()=>__mako_require__(\"src/fake.js/aa.js\")",
       "'./aa.js': ()=>__mako_require__(\"src/fake.js/aa.js\")",
-      "'./index': ()=>__mako_require__(\"src/fake.js/index.js\")",
-      "'./index.js': ()=>__mako_require__(\"src/fake.js/index.js\")",
     ].join(',\n\\s+'),
     true,
   ),
-  "should generate context module for fake ext directory with correct map",
+  "should only map the candidates of the static part of the name",
 );
 
 assert.match(
   asyncContent,
-  moduleReg("src/i18n\\?context&glob=\\*\\*/\\*.json&async", "'./zh-CN.json': ()=>Promise.all([\n.*__mako_require__.ensure(\"src/i18n/zh-CN.json\")\n.*]).then(__mako_require__.dr(interop, __mako_require__.bind(__mako_require__, \"src/i18n/zh-CN.json\")))", true),
+  moduleReg("src/i18n\\?async&context&glob=\\*\\*/\\*.json", "'./zh-CN.json': ()=>Promise.resolve().then(__mako_require__.dr(interop, __mako_require__.bind(__mako_require__, \"src/i18n/zh-CN.json\")))", true),
   "should generate context module with correct map in async chunk",
 );
 
 assert.match(
-  asyncContent,
+  content,
   moduleReg("src/i18n/zh-CN.json", "中文", true),
-  "should generate context module with correct map in async chunk",
+  "should keep the candidates loaded by the entry in the entry chunk",
 );
 
 assert.match(
   asyncContent,
-  moduleReg("src/i18n\\?context&glob=\\*\\*/\\*.json&async", "'./en-US.json': ()=>Promise.all([\n.*__mako_require__.ensure(\"src/i18n/en-US.json\")\n.*]).then(__mako_require__.dr(interop, __mako_require__.bind(__mako_require__, \"src/i18n/en-US.json\")))", true),
+  moduleReg("src/i18n\\?async&context&glob=\\*\\*/\\*.json", "'./en-US.json': ()=>Promise.resolve().then(__mako_require__.dr(interop, __mako_require__.bind(__mako_require__, \"src/i18n/en-US.json\")))", true),
   "should generate context module with correct map in async chunk",
 );
 
 assert.match(
-  asyncContent,
+  content,
   moduleReg("src/i18n/en-US.json", "English", true),
-  "should generate context module with correct map in async chunk",
+  "should keep the candidates loaded by the entry in the entry chunk",
 );
 
 assert.match(
   content,
-  moduleReg("src/index.ts", '__mako_require__.ensure("src/i18n\\?context&glob=\\*\\*/\\*.json&async")', true),
+  moduleReg("src/index.ts", '__mako_require__.ensure("src/i18n\\?async&context&glob=\\*\\*/\\*.json")', true),
   "should generate async require for import dynamic module",
 );
 
 assert.match(
   content,
-  moduleReg("src/index.ts", 'ensure("src/i18n\\?context&glob=\\*\\*/\\*&async")', true),
+  moduleReg("src/index.ts", 'ensure("src/i18n\\?async&context&glob=\\*\\*/\\*")', true),
   "should generate async require for import dynamic module with then callback",
 );
 
@@ -108,10 +103,16 @@ assert.match(
   "prefix should match the last one '/' ",
 );
 
+assert.match(
+  content,
+  moduleReg("src/index.ts", '__mako_require__.ensure("src/i18n\\?async&context&glob=\\*\\*/\\*.json&prefix=zh-")', true),
+  "should narrow the context module to the static part of the name after the last '/'",
+);
+
 
 assert.match(
   content,
-  moduleReg("src/index.ts", '__mako_require__("src\\?context&glob=\\*\\*/\\*")', true),
+  moduleReg("src/index.ts", '__mako_require__("src\\?context&glob=\\*\\*/\\*&prefix=i18n")', true),
   "should generate sync require for require dynamic module",
 );
 
@@ -130,4 +131,4 @@ assert.doesNotMatch(
   "should escape glob pattern in module id debug comment"
 );
 
-assert.match(content, /src\/first_empty_quasis\?context&glob=\*\*\/\*&async/g, "should handle template string with first empty quasis");
+assert.match(content, /src\/first_empty_quasis\?async&context&glob=\*\*\/\*/g, "should handle template string with first empty quasis");
@@ -0,0 +1,12 @@
+const assert = require("assert");
+const { parseBuildResult } = require("../../../scripts/test-utils");
+const { files } = parseBuildResult(__dirname);
+
+const content = files["index.js"];
+
+assert(content.includes("class ValidationError"), "class names should be kept");
+assert(!content.includes("function createError"), "function names should be mangled");
+assert(
+  content.includes(`"name",{value:"createValidator"`),
+  "function names should be defined for files with @keepNames"
+);
@@ -0,0 +1,9 @@
+import { createValidator } from './validator';
+
+(function () {
+  class ValidationError extends Error {}
+  function createError() {
+    return new ValidationError();
+  }
+  console.log(createError().constructor.name, createValidator.name);
+})();
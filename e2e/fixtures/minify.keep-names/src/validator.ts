@@ -0,0 +1,4 @@
+/* @keepNames */
+export function createValidator() {
+  return function validate() {};
+}
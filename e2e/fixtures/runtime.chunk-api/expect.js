@@ -0,0 +1,22 @@
+const assert = require("assert");
+const fs = require("fs");
+const path = require("path");
+
+module.exports = async () => {
+  require("./dist/index.js");
+  const runtime = globalThis.__mako_require_module__;
+  const chunkId = "src/lazy.ts";
+
+  const files = runtime.getChunkFiles(chunkId);
+  assert.equal(files.length, 2, "chunk files should contain css and js");
+  assert(files[0].endsWith(".css"), "css file should come first");
+  assert(files[1].endsWith(".js"), "js file should come last");
+  files.forEach((file) => {
+    assert(fs.existsSync(path.join(__dirname, "dist", file)), `${file} should exist`);
+  });
+
+  assert(!runtime.loadedChunks[chunkId], "chunk should not be loaded yet");
+  assert.equal(await runtime.ensureChunk(chunkId), undefined);
+  assert(runtime.loadedChunks[chunkId], "chunk should be loaded");
+  assert(runtime.loadedChunks[Object.keys(runtime.loadedChunks)[0]], "entry chunk should be loaded");
+};
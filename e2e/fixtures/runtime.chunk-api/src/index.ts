@@ -0,0 +1,4 @@
+import './index.css';
+
+// the chunk is loaded with the runtime api instead
+(globalThis as any).loadLazy = () => import('./lazy');
@@ -0,0 +1,4 @@
+import './lazy.css';
+
+console.log('lazy');
+export default 'lazy';
@@ -0,0 +1,27 @@
+const assert = require("assert");
+const fs = require("fs");
+const path = require("path");
+
+module.exports = async () => {
+  // remove the async chunk like a new deployment does
+  const dist = path.join(__dirname, "dist");
+  const lazyChunk = fs
+    .readdirSync(dist)
+    .find((file) => file.endsWith(".js") && !file.startsWith("index"));
+  fs.unlinkSync(path.join(dist, lazyChunk));
+
+  require("./dist/index.js");
+  const error = await globalThis.lazyResult;
+  assert.equal(error.name, "ChunkLoadError", "import() should be rejected");
+  await globalThis.retryResult;
+
+  const errors = globalThis.chunkLoadErrors;
+  assert.equal(errors.length, 2, "onChunkLoadError should be called for each attempt");
+  assert.equal(errors[0].chunkId, error.chunkId);
+  assert.equal(errors[0].request, lazyChunk);
+  assert.deepEqual(
+    errors.map((e) => e.attempts),
+    [1, 2],
+    "attempts should be counted"
+  );
+};
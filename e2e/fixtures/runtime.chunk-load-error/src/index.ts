@@ -0,0 +1,15 @@
+const errors: any[] = [];
+(globalThis as any).chunkLoadErrors = errors;
+// called for each failed attempt, the first one is retried
+(globalThis as any).__mako_require_module__.onChunkLoadError = (
+  error: any,
+  chunkId: string,
+  retry: () => Promise<any>,
+) => {
+  errors.push({ chunkId, request: error.request, attempts: error.attempts });
+  if (error.attempts === 1) {
+    (globalThis as any).retryResult = retry().catch((e) => e);
+  }
+};
+
+(globalThis as any).lazyResult = import('./lazy').catch((e) => e);
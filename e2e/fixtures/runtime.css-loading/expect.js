@@ -0,0 +1,106 @@
+const assert = require("assert");
+const path = require("path");
+
+// a headless document which loads the scripts with require, and the links
+// when the test fires their load events
+function createDocument() {
+  const head = {
+    children: [],
+    appendChild(el) {
+      el.parentNode = head;
+      head.children.push(el);
+      if (el.tagName === "script") {
+        setTimeout(() => {
+          require(path.join(__dirname, "dist", el.src));
+          el.onload({ type: "load", target: el });
+        });
+      }
+    },
+    insertBefore(el) {
+      head.appendChild(el);
+    },
+    removeChild(el) {
+      el.parentNode = null;
+      head.children.splice(head.children.indexOf(el), 1);
+    },
+  };
+  return {
+    head,
+    createElement(tagName) {
+      return {
+        tagName,
+        setAttribute(name, value) {
+          this[name] = value;
+        },
+        getAttribute(name) {
+          return this[name];
+        },
+      };
+    },
+    querySelector() {
+      return null;
+    },
+    querySelectorAll() {
+      return head.children.filter((el) => el.tagName === "link");
+    },
+  };
+}
+
+const tick = () => new Promise((resolve) => setTimeout(resolve, 10));
+
+module.exports = async () => {
+  const document = createDocument();
+  globalThis.document = document;
+  globalThis.self = globalThis;
+  globalThis.window = { location: { origin: "http://localhost" } };
+  try {
+    await run(document);
+  } finally {
+    // the fixtures run in the same process
+    delete globalThis.document;
+    delete globalThis.self;
+    delete globalThis.window;
+  }
+};
+
+async function run(document) {
+  require("./dist/index.js");
+  const runtime = globalThis.__mako_require_module__;
+  const chunkId = "src/lazy.ts";
+  const url = runtime.getChunkFiles(chunkId)[0];
+  const links = () => document.head.children.filter((el) => el.tagName === "link");
+
+  // the concurrent loads share the link and wait for it
+  let loaded = 0;
+  const loads = [globalThis.loadLazy(), globalThis.loadLazy()].map((p) =>
+    p.then(() => loaded++)
+  );
+  await tick();
+  assert.equal(links().length, 1, "the link should be inserted once");
+  assert.equal(loaded, 0, "the loads should wait for the link");
+  links()[0].onload({ type: "load", target: links()[0] });
+  await Promise.all(loads);
+  assert.equal(loaded, 2);
+
+  // the references of the link
+  const stylesheet = runtime.stylesheets[url];
+  assert.equal(stylesheet.refs, 1);
+  await runtime.loadStylesheet(chunkId, url);
+  assert.equal(stylesheet.refs, 2);
+  assert.equal(links().length, 1, "the loaded link should be reused");
+  runtime.releaseStylesheet(url);
+  assert.equal(links().length, 1, "the link is still referenced");
+  runtime.releaseStylesheet(url);
+  assert.equal(links().length, 0, "the unused link should be removed");
+  assert.equal(runtime.stylesheets[url], undefined);
+
+  // a failed load is loaded again by the next reference
+  const failed = runtime.ensureChunk(chunkId).catch((e) => e);
+  await tick();
+  assert.equal(links().length, 1, "the link should be inserted again");
+  links()[0].onerror({ type: "error", target: links()[0] });
+  const error = await failed;
+  assert.equal(error.code, "CSS_CHUNK_LOAD_FAILED");
+  assert.equal(links().length, 0, "the failed link should be removed");
+  assert.equal(runtime.stylesheets[url], undefined);
+}
@@ -0,0 +1,2 @@
+// the chunk is loaded by the test with a fake document
+(globalThis as any).loadLazy = () => import('./lazy');
@@ -0,0 +1,3 @@
+import './lazy.css';
+
+export default 'lazy';
@@ -0,0 +1,11 @@
+const assert = require("assert");
+
+module.exports = async () => {
+  assert(typeof document === "undefined", "should run without document");
+  require("./dist/index.js");
+  assert.equal(
+    await globalThis.lazyResult,
+    "lazy",
+    "async chunk should be loaded with require when document is unavailable"
+  );
+};
@@ -0,0 +1,4 @@
+import './index.css';
+
+// the same browser bundle is executed under node in ssr
+(globalThis as any).lazyResult = import('./lazy').then((res) => res.default);
@@ -0,0 +1,2 @@
+import './lazy.css';
+export default 'lazy';
@@ -0,0 +1,7 @@
+const assert = require("assert");
+const { parseBuildResult } = require("../../../scripts/test-utils");
+const { files } = parseBuildResult(__dirname);
+const content = files["index.js"];
+
+assert(content.includes("script.setAttribute('nonce', __mako_nonce__)"), 'should set nonce of __mako_nonce__ for loadScript function');
+assert(content.includes("link.setAttribute('nonce', __mako_nonce__)"), 'should set nonce of __mako_nonce__ for createStylesheet function');
@@ -0,0 +1,5 @@
+import './async.css';
+
+console.log('async');
+
+export default 'async';
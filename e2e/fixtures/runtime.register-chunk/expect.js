@@ -0,0 +1,41 @@
+const assert = require("assert");
+const fs = require("fs");
+const path = require("path");
+
+module.exports = async () => {
+  const dist = path.join(__dirname, "dist");
+  const pushable = JSON.parse(
+    fs.readFileSync(path.join(dist, "src_lazy_ts-async.push.json"), "utf-8"),
+  );
+  assert.equal(pushable.chunkId, "src/lazy.ts");
+  assert(!/<\/script/i.test(pushable.script), "script should be safe to inline");
+
+  // inlined before the runtime, and the chunk file is not loaded
+  new Function(pushable.script)();
+  fs.renameSync(
+    path.join(dist, "src_lazy_ts-async.js"),
+    path.join(dist, "src_lazy_ts-async.js.bak"),
+  );
+  try {
+    require("./dist/index.js");
+    assert.equal(await globalThis.lazyResult, "<script>lazy</script>");
+  } finally {
+    fs.renameSync(
+      path.join(dist, "src_lazy_ts-async.js.bak"),
+      path.join(dist, "src_lazy_ts-async.js"),
+    );
+  }
+
+  const runtime = globalThis.__mako_require_module__;
+  assert.equal(globalThis.__MAKO_CHUNK_LOAD__, runtime.registerChunk);
+  assert(runtime.loadedChunks["src/lazy.ts"], "chunk should be loaded");
+
+  // registered once, the later registrations of the same chunk are ignored
+  runtime.registerChunk("src/lazy.ts", {
+    "src/lazy.ts": function () {
+      throw new Error("should not be registered again");
+    },
+  });
+  await runtime.ensureChunk("src/lazy.ts");
+  assert.equal(runtime("src/lazy.ts").default, "<script>lazy</script>");
+};
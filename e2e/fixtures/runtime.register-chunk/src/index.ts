@@ -0,0 +1,2 @@
+// the chunk is registered by the server before the app asks for it
+(globalThis as any).lazyResult = import('./lazy').then((res) => res.default);
@@ -0,0 +1,19 @@
+const assert = require("assert");
+const path = require("path");
+const { load } = require("../runtime.relative-url-base/page");
+
+module.exports = async () => {
+  const dist = path.join(__dirname, "dist");
+
+  // resolved against the page even if the script is known
+  const page = load(dist, {
+    tagName: "SCRIPT",
+    src: "https://example.com/static/index.js",
+  });
+  assert.equal(
+    page.__mako_require_module__.publicPath,
+    "https://example.com/t/acme/",
+  );
+  assert.deepEqual(page.loaded, ["https://example.com/t/acme/src_lazy_ts-async.js"]);
+  await assert.rejects(page.lazy, /Loading chunk/);
+};
@@ -0,0 +1 @@
+globalThis.lazy = import("./lazy");
@@ -0,0 +1,27 @@
+const assert = require("assert");
+const path = require("path");
+const { load } = require("./page");
+
+module.exports = async () => {
+  const dist = path.join(__dirname, "dist");
+
+  // resolved against the runtime script
+  const page = load(dist, {
+    tagName: "SCRIPT",
+    src: "https://example.com/static/index.js?v=1",
+  });
+  assert.equal(
+    page.__mako_require_module__.publicPath,
+    "https://example.com/static/",
+  );
+  assert.equal((await page.lazy).default, "lazy");
+  assert.deepEqual(page.loaded, ["https://example.com/static/src_lazy_ts-async.js"]);
+
+  // against the page if the script is unknown, e.g. in a module script
+  const page2 = load(dist, null);
+  assert.equal(
+    page2.__mako_require_module__.publicPath,
+    "https://example.com/t/acme/",
+  );
+  await assert.rejects(page2.lazy, /Loading chunk/);
+};
@@ -0,0 +1,37 @@
+const fs = require("fs");
+const path = require("path");
+const vm = require("vm");
+
+// runs dist/index.js in a page of a tenant, https://example.com/t/acme/,
+// which loads the scripts from https://example.com/static/
+exports.load = (dist, currentScript) => {
+  const context = vm.createContext({ URL, setTimeout, clearTimeout, console });
+  context.self = context;
+  context.window = context;
+  context.loaded = [];
+  context.document = {
+    baseURI: "https://example.com/t/acme/",
+    currentScript,
+    head: {
+      appendChild(script) {
+        context.loaded.push(script.src);
+        const url = new URL(script.src);
+        if (url.pathname.startsWith("/static/")) {
+          const file = path.join(dist, url.pathname.slice("/static/".length));
+          vm.runInContext(fs.readFileSync(file, "utf-8"), context);
+          script.onload({ type: "load", target: script });
+        } else {
+          script.onerror({ type: "error", target: script });
+        }
+      },
+    },
+    createElement() {
+      return { setAttribute() {} };
+    },
+    querySelector() {
+      return null;
+    },
+  };
+  vm.runInContext(fs.readFileSync(path.join(dist, "index.js"), "utf-8"), context);
+  return context;
+};
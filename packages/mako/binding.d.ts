@@ -10,6 +10,13 @@ export interface JsHooks {
     filePath: string,
   ) => Promise<{ content: string; type: 'css' | 'js' } | void> | void;
   loadInclude?: (filePath: string) => Promise<bool> | bool;
+  loadSubModules?: (filePath: string) =>
+    | Promise<{
+        content: string;
+        type: 'css' | 'js';
+        subModules: Record<string, { content: string; type: 'css' | 'js' }>;
+      } | void>
+    | void;
   generateEnd?: (data: {
     isFirstCompile: boolean;
     time: number;
@@ -27,14 +34,30 @@ export interface JsHooks {
       }[];
       modules: Record<
         string,
-        { id: string; dependents: string[]; dependencies: string[] }
+        {
+          id: string;
+          dependents: string[];
+          dependencies: string[];
+          directives: string[];
+          condition?: string;
+        }
       >;
       chunks: {
         type: string;
         id: string;
         files: string[];
         entry: boolean;
-        modules: { type: string; id: string; size: number; chunks: string[] }[];
+        modules: {
+          type: string;
+          id: string;
+          size: number;
+          chunks: string[];
+          placement?:
+            | { type: 'entry'; entry: string }
+            | { type: 'async' | 'worker'; root: string }
+            | { type: 'shared'; group: string; parents: string[] }
+            | { type: 'rule'; group: string; test: string; parents: string[] };
+        }[];
         siblings: string[];
         origin: {
           module: string;
@@ -44,14 +67,38 @@ export interface JsHooks {
           request: string;
         }[];
       }[];
-      entrypoints: Record<string, { name: string; chunks: string[] }>;
+      entrypoints: Record<
+        string,
+        {
+          name: string;
+          chunks: string[];
+          assets: { name: string; size: number }[];
+        }
+      >;
       rscClientComponents: { path; string; moduleId: string }[];
       rscCSSModules: { path; string; moduleId: string; modules: boolean }[];
+      packages: {
+        name: string;
+        version: string | null;
+        sourceSize: number;
+        transformedSize: number;
+        minifiedSize: number;
+        treeShakenSize: number;
+        keptModules: number;
+        droppedModules: number;
+      }[];
       startTime: number;
       endTime: number;
     };
   }) => void;
   writeBundle?: () => Promise<void>;
+  afterEmit?: (
+    sourceMaps: {
+      assetPath: string;
+      sourceMapPath: string;
+      debugId: string | null;
+    }[],
+  ) => Promise<void>;
   watchChanges?: (
     id: string,
     change: { event: 'create' | 'delete' | 'update' },
@@ -69,6 +116,10 @@ export interface JsHooks {
     path: string,
   ) => Promise<{ content: string; type: 'css' | 'js' } | void> | void;
   transformInclude?: (filePath: string) => Promise<bool> | bool;
+  replaceByDirective?: (
+    filePath: string,
+    directives: string[],
+  ) => Promise<{ content: string; type: 'css' | 'js' } | void> | void;
 }
 export interface WriteFile {
   path: string;
@@ -95,34 +146,102 @@ export interface TransformResult {
 export interface BuildParams {
   root: string;
   config: {
-    entry?: Record<string, string>;
+    entry?: Record<
+      string,
+      | string
+      | {
+          import: string;
+          dependOn?: string | string[];
+          target?: 'web' | 'webworker';
+          precacheManifest?: boolean;
+        }
+    >;
+    entryPrelude?: Array<string | { inline: string }>;
     output?: {
       path: string;
       mode: 'bundle' | 'bundless';
       esVersion?: string;
       meta?: boolean;
+      chunkLoadingGlobal?: string;
+      chunkLoadingTimeout?: number;
+      chunkLoading?: 'jsonp' | 'import' | 'fetch-eval';
+      uniqueName?: string;
       preserveModules?: boolean;
       preserveModulesRoot?: string;
       skipWrite?: boolean;
+      runtimeTarget?: false | 'es5' | 'es2015' | 'es2020';
+      precompress?:
+        | false
+        | Array<'gzip' | 'brotli'>
+        | {
+            algorithms: Array<'gzip' | 'brotli'>;
+            gzipLevel?: number;
+            brotliQuality?: number;
+            threshold?: number;
+          };
+      debugIds?: boolean;
+      pushableChunks?: boolean;
+      relativeUrlBase?: 'script' | 'document';
+      hashFunction?: 'md5' | 'xxhash' | 'sha256';
+      hashLength?: number;
+      assetFilename?: string;
+      unloadChunkCss?: boolean;
     };
     resolve?: {
       alias?: Array<[string, string]>;
       extensions?: string[];
+      tsconfig?: string;
+      conditionNames?: string[];
+      roots?: string[];
     };
     manifest?:
       | false
       | {
           fileName: string;
           basePath: string;
+          precompressed?: boolean;
+          publicPath?: boolean;
         };
-    mode?: 'development' | 'production';
+    buildManifest?:
+      | false
+      | {
+          fileName?: string;
+          previous?: string;
+        };
+    cacheRules?:
+      | false
+      | {
+          fileName?: string;
+          headers?: boolean;
+          overrides?: {
+            pattern: string;
+            rule: 'immutable' | 'revalidate';
+          }[];
+        };
+    loadableManifest?: false | { fileName?: string };
+    maxInFlightChunkBytes?: number;
+    maxOpenFiles?: number;
+    mode?: 'development' | 'production' | 'none';
+    env?: Record<string, Record<string, any>>;
+    envName?: string;
     define?: Record<string, string>;
+    envObject?: false | 'empty' | 'runtime';
+    graph?: false | { format: "dot" | "json"; collapseNodeModules?: boolean };
     devtool?: false | 'source-map' | 'inline-source-map';
+    devtoolsTiming?: boolean;
+    sourceMap?: {
+      excludeVendorSources?: boolean;
+      indexMap?: boolean;
+      vendor?: 'none' | 'cheap' | 'full';
+      vendorGlobs?: string[];
+    };
+    sourceMapIgnoreList?: boolean | string[];
     externals?: Record<
       string,
       | string
       | {
           root: string;
+          type?: 'commonjs' | 'module';
           script?: string;
           subpath?: {
             exclude?: string[];
@@ -165,9 +284,13 @@ export interface BuildParams {
     providers?: Record<string, string[]>;
     publicPath?: string;
     inlineLimit?: number;
+    inlineRuntime?: boolean;
+    inlineChunks?: string[];
     inlineExcludesExtensions?: string[];
+    imageOptimization?: false | { quality?: number };
     targets?: Record<string, number>;
     platform?: 'node' | 'browser';
+    nativeAddons?: 'emit' | 'external';
     hmr?: false | {};
     devServer?: false | { host?: string; port?: number };
     px2rem?:
@@ -185,24 +308,68 @@ export interface BuildParams {
       | false
       | {
           modules?: boolean;
+          packages?: boolean;
+          mangleExports?: boolean;
+          timestamps?: boolean;
         };
+    stub?: Record<
+      string,
+      {
+        exports?: string[];
+        value?: 'noop' | string | number | boolean | null | object;
+      }
+    >;
     hash?: boolean;
+    asyncGlobalSideEffects?:
+      | false
+      | { allowProperties?: string[]; allowModules?: string[] };
     autoCSSModules?: boolean;
     ignoreCSSParserErrors?: boolean;
+    remoteCSS?: 'preserve' | 'warn' | 'download';
     dynamicImportToRequire?: boolean;
     umd?: false | string | { name: string; export?: string[] };
     cjs?: boolean;
     writeToDisk?: boolean;
+    transform?: { include?: string[]; exclude?: string[] };
     transformImport?: {
       libraryName: string;
       libraryDirectory?: string;
       style?: boolean | string;
     }[];
+    transformImports?: Record<
+      string,
+      {
+        transform: string;
+        style?: string;
+        skip?: string[];
+      }
+    >;
     clean?: boolean;
     nodePolyfill?: boolean;
     ignores?: string[];
-    moduleIdStrategy?: 'hashed' | 'named';
-    minify?: boolean;
+    ignore?: string[];
+    moduleIdStrategy?: 'hashed' | 'named' | 'deterministic';
+    namedExportsCheck?: false | 'error' | 'warn';
+    defaultImportCheck?: false | 'error' | 'warn';
+    fullyDynamicImport?: false | 'error' | 'warn';
+    fullyDynamicRequire?: false | 'error' | 'warn';
+    moduleDirectives?: string[];
+    moduleRules?: {
+      test: string;
+      use: { jsx?: boolean; loader?: 'raw'; defines?: Record<string, string> };
+    }[];
+    moduleTypes?: { include: string; type: 'esm' | 'commonjs' }[];
+    jsonExportsCheck?: false | 'error' | 'warn';
+    peerDependencyCheck?: false | 'error' | 'warn';
+    duplicatedModules?: false | { maxBytes: number };
+    parseFallback?: false | { steps: Array<'jsx' | 'proposals' | 'flow'> };
+    typeDeclaration?: boolean | { fileName?: string };
+    minify?:
+      | boolean
+      | {
+          keepNames?: boolean | ('ClassNames' | 'FunctionNames')[];
+        };
+    licenseComments?: false | "extract" | "inline";
     _minifish?:
       | false
       | {
@@ -236,15 +403,31 @@ export interface BuildParams {
       | false
       | {
           skipModules?: boolean;
+          sideEffects?: boolean;
           concatenateModules?: boolean;
+          concatenateCss?: boolean;
+          pureFunctions?: string[];
+          mangleExports?: boolean;
         };
     react?: {
       runtime?: 'automatic' | 'classic';
       pragma?: string;
       importSource?: string;
       pragmaFrag?: string;
+      removePropTypes?:
+        | boolean
+        | {
+            include?: string[];
+            defaultProps?: boolean;
+          };
     };
     emitAssets?: boolean;
+    cssLowering?: {
+      isWhere?: boolean;
+      logicalProperties?: boolean;
+      direction?: 'ltr' | 'rtl';
+    };
+    cssModules?: { declarations?: boolean };
     cssModulesExportOnlyLocales?: boolean;
     inlineCSS?: false | {};
     rscServer?:
@@ -265,13 +448,140 @@ export interface BuildParams {
     watch?: {
       ignoredPaths?: string[];
       _nodeModulesRegexes?: string[];
+      debounce?: number;
+      settle?: number;
     };
     caseSensitiveCheck?: boolean;
+    dualBuild?:
+      | false
+      | {
+          targets?: Record<string, number>;
+          outputDir?: string;
+          template?: string;
+        };
+    criticalCss?:
+      | false
+      | {
+          entries: Record<string, { selectors?: string[]; modules?: string[] }>;
+          template?: string;
+        };
+    cspReport?:
+      | false
+      | {
+          fileName?: string;
+          nonce?: string;
+        };
+    extractCss?:
+      | false
+      | {
+          groups?: { name: string; test?: string[] }[];
+        };
+    importMap?:
+      | false
+      | {
+          url?: string;
+          inject?: boolean;
+        };
+    polyfill?:
+      | false
+      | {
+          mode: 'usage' | 'entry';
+          provider?: 'core-js';
+        };
+    sideEffectOnlyImports?:
+      | boolean
+      | {
+          include?: string[];
+        };
+    preBundle?:
+      | boolean
+      | {
+          exclude?: string[];
+        };
+    diagnostics?: {
+      errorOn?: Array<
+        | 'MAKO_DUPLICATE_PACKAGE'
+        | 'MAKO_CASE_MISMATCH'
+        | 'MAKO_CIRCULAR'
+        | 'MAKO_EVAL_BAILOUT'
+        | 'MAKO_DUPLICATED_MODULES'
+        | 'MAKO_PARSE_FALLBACK'
+        | 'MAKO_DYNAMIC_IMPORT'
+        | 'MAKO_DYNAMIC_REQUIRE'
+        | 'MAKO_CHUNK_NAME_COLLISION'
+        | 'MAKO_REMOTE_CSS'
+        | 'MAKO_ASYNC_GLOBAL_SIDE_EFFECT'
+      >;
+      ignore?: Array<{
+        code:
+          | 'MAKO_DUPLICATE_PACKAGE'
+          | 'MAKO_CASE_MISMATCH'
+          | 'MAKO_CIRCULAR'
+          | 'MAKO_EVAL_BAILOUT'
+          | 'MAKO_DUPLICATED_MODULES'
+          | 'MAKO_PARSE_FALLBACK'
+          | 'MAKO_DYNAMIC_IMPORT'
+          | 'MAKO_DYNAMIC_REQUIRE'
+          | 'MAKO_CHUNK_NAME_COLLISION'
+          | 'MAKO_REMOTE_CSS'
+          | 'MAKO_ASYNC_GLOBAL_SIDE_EFFECT';
+        pathPattern?: string;
+      }>;
+    };
   };
   plugins: Array<JsHooks>;
   watch: boolean;
 }
+export interface CompilerParams {
+  root: string;
+  config: BuildParams['config'];
+  plugins?: Array<JsHooks>;
+  /**
+   * Return the sources of the assets in the build results, the sources
+   * are kept in memory until the results are released, default true.
+   */
+  returnSources?: boolean;
+}
+export interface BuildAsset {
+  name: string;
+  size: number;
+  source?: Buffer;
+}
+export interface BuildDiagnostic {
+  code: string;
+  severity: 'warning' | 'error' | 'suppressed';
+  message: string;
+  paths: Array<string>;
+}
+export interface BuildResult {
+  /**
+   * false if the rebuild found no changes, the assets are of the last
+   * build then
+   */
+  updated: boolean;
+  assets: Array<BuildAsset>;
+  diagnostics: Array<BuildDiagnostic>;
+}
 export declare function build(buildParams: BuildParams): Promise<void>;
+export declare class Compiler {
+  constructor(params: CompilerParams);
+  /** Listen to the `diagnostic` and `progress` events. */
+  on(
+    event: 'diagnostic',
+    listener: (diagnostic: BuildDiagnostic) => void,
+  ): void;
+  on(
+    event: 'progress',
+    listener: (progress: {
+      stage: 'build' | 'generate' | 'done';
+      modules?: number;
+      time?: number;
+    }) => void,
+  ): void;
+  build(): Promise<BuildResult>;
+  /** Rebuild the changed, added or removed files. */
+  rebuild(changedFiles: Array<string>): Promise<BuildResult>;
+}
 export class PluginContext {
   warn(msg: string): void;
   error(msg: string): void;
@@ -304,7 +304,8 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`);
 }
 
-const { PluginContext, build } = nativeBinding;
+const { PluginContext, build, Compiler } = nativeBinding;
 
 module.exports.PluginContext = PluginContext;
 module.exports.build = build;
+module.exports.Compiler = Compiler;
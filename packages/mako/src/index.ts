@@ -26,6 +26,34 @@ type BuildParams = {
 
 export { BuildParams };
 
+type ConfigEnv = {
+  mode: 'development' | 'production' | 'none';
+};
+
+// js plugins are only supported by build(), not by the config file
+type ConfigObject = Omit<Config, 'plugins'>;
+
+type UserConfig =
+  | ConfigObject
+  | ((env: ConfigEnv) => ConfigObject | Promise<ConfigObject>);
+
+export { ConfigEnv, UserConfig };
+
+// for the types of mako.config.ts
+export function defineConfig(config: UserConfig) {
+  return config;
+}
+
+// the compiler kept alive between the builds, the less, sass and
+// forkTSChecker options of build() are not supported by it
+export { Compiler } from '../binding';
+export type {
+  BuildAsset,
+  BuildDiagnostic,
+  BuildResult,
+  CompilerParams,
+} from '../binding';
+
 // ref:
 // https://github.com/vercel/next.js/pull/51883
 function blockStdout() {
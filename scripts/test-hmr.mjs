@@ -335,6 +335,43 @@ ReactDOM.createRoot(document.getElementById("root")!).render(<App />);
   const thisColor = await getElementColor(page, '.foo');
   assert.equal(thisColor, 'rgb(0, 0, 255)', 'Second render');
   isReload = lastResult.random !== thisResult.random;
+  assert.equal(isReload, false, 'should not reload');
+  lastResult = thisResult;
+  await cleanup({ process, browser });
+});
+
+runTest('css: entry > css modules with class names changed', async () => {
+  write(
+    normalizeFiles({
+      '/src/index.module.css': `.foo {color:red;}`,
+      '/src/index.tsx': `
+import React from 'react';
+import ReactDOM from "react-dom/client";
+import styles from "./index.module.css";
+function App() {
+  return <div className={\`\${styles.foo} \${styles.bar} foo\`}>App<section>{Math.random()}</section></div>;
+}
+ReactDOM.createRoot(document.getElementById("root")!).render(<App />);
+    `,
+    }),
+  );
+  await startMakoDevServer();
+  await delay(DELAY_TIME);
+  const { browser, page } = await startBrowser();
+  let lastResult;
+  let thisResult;
+  let isReload;
+  lastResult = normalizeHtml(await getRootHtml(page));
+  const lastColor = await getElementColor(page, '.foo');
+  assert.equal(lastColor, 'rgb(255, 0, 0)', 'Initial render');
+  write({
+    '/src/index.module.css': `.foo {color:red;} .bar {color:blue;}`,
+  });
+  await delay(DELAY_TIME);
+  thisResult = normalizeHtml(await getRootHtml(page));
+  const thisColor = await getElementColor(page, '.foo');
+  assert.equal(thisColor, 'rgb(0, 0, 255)', 'Second render');
+  isReload = lastResult.random !== thisResult.random;
   assert.equal(isReload, true, 'should reload');
   lastResult = thisResult;
   await cleanup({ process, browser });
@@ -1810,6 +1847,61 @@ runTest('add async import', async () => {
   await cleanup({ process, browser });
 });
 
+runTest('remove import', async () => {
+  write(
+    normalizeFiles({
+      '/src/util.js': `
+      module.hot.dispose(() => {
+        window.__utilDisposed = (window.__utilDisposed || 0) + 1;
+      });
+      export default 'util';`,
+      '/src/App.tsx': `
+      import util from './util';
+      export default () => {
+        return <div>App {util}</div>;
+      };`,
+      '/src/index.tsx': `
+      import React from 'react';
+      import ReactDOM from "react-dom/client";
+      import App from './App';
+      ReactDOM.createRoot(document.getElementById("root")!).render(<><App /><section>{Math.random()}</section></>);
+          `,
+    }),
+  );
+  const { process } = await startMakoDevServer();
+  await delay(DELAY_TIME);
+  const { browser, page } = await startBrowser();
+  let lastResult;
+  let thisResult;
+  let isReload;
+  lastResult = normalizeHtml(await getRootHtml(page));
+  assert.equal(lastResult.html, '<div>App util</div>', 'Initial render');
+  write({
+    '/src/App.tsx': `
+      export default () => {
+        return <div>App</div>;
+      };`,
+  });
+  await delay(DELAY_TIME);
+  thisResult = normalizeHtml(await getRootHtml(page));
+  console.log(`new html`, thisResult.html);
+  assert.equal(thisResult.html, '<div>App</div>', 'Second render');
+  isReload = lastResult.random !== thisResult.random;
+  assert.equal(isReload, false, 'should not reload');
+  const disposed = await page.evaluate(() => window.__utilDisposed);
+  assert.equal(disposed, 1, 'the dispose handler of the removed module ran');
+  const requireError = await page.evaluate(() => {
+    try {
+      __mako_require_module__('src/util.js');
+      return null;
+    } catch (e) {
+      return e.code;
+    }
+  });
+  assert.equal(requireError, 'MODULE_NOT_FOUND', 'the removed module is gone');
+  await cleanup({ process, browser });
+});
+
 runTest('js: response correct content-type', async () => {
   write(
     normalizeFiles({